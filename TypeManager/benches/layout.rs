@@ -0,0 +1,111 @@
+/*
+    Benchmark suite for the layout engine.
+
+    Run with `cargo bench`. The 10-member optimizer case explores 10! layouts,
+    taking hundreds of milliseconds every time, so it only runs when `TM_BENCH_LARGE` is set.
+*/
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use type_manager::type_system::*;
+
+/// Minimum time spent measuring every benchmark
+const MEASURE_TIME: Duration = Duration::from_secs(2);
+
+/// Run `f` repeatedly for at least `MEASURE_TIME` and print the mean time per iteration
+/// ## Params
+/// * `name` - name to show in the report
+/// * `f` - function to measure
+fn bench<F, T>(name: &str, mut f: F)
+    where
+        F: FnMut() -> T
+{
+    // warm up once so lazy work doesn't count against the first sample
+    black_box(f());
+
+    let start = Instant::now();
+    let mut iters: u32 = 0;
+    while start.elapsed() < MEASURE_TIME || iters == 0 {
+        black_box(f());
+        iters += 1;
+    }
+
+    let mean = start.elapsed() / iters;
+    println!("{:<40} {:>12.3?} / iter ({} iters)", name, mean, iters);
+}
+
+/// Create a manager with some basic atomic types
+fn base_manager() -> TypeManager {
+    let mut manager = TypeManager::new();
+    let _ = manager.add("char".to_string(),  Type::Atomic(Atomic::new(1, 1)));
+    let _ = manager.add("short".to_string(), Type::Atomic(Atomic::new(2, 2)));
+    let _ = manager.add("int".to_string(),   Type::Atomic(Atomic::new(4, 4)));
+    let _ = manager.add("long".to_string(),  Type::Atomic(Atomic::new(8, 8)));
+
+    manager
+}
+
+/// Add 10k types: atomics interleaved with structs made of previous types
+fn bench_add_types() {
+    bench("add 10k types", || {
+        let mut manager = base_manager();
+        for i in 0..10_000 {
+            let new_type = if i % 2 == 0 {
+                Type::Atomic(Atomic::new(i % 16 + 1, 1 << (i % 4)))
+            }
+            else {
+                Type::Struct(Struct::new(vec![format!("t{}", i - 1), "int".to_string()]))
+            };
+
+            manager.add(format!("t{}", i), new_type).unwrap();
+        }
+
+        manager
+    });
+}
+
+/// Unpacked size of a struct nested 10 levels deep, every level holding
+/// the previous one twice
+fn bench_nested_unpacked_size() {
+    let mut manager = base_manager();
+    manager.add("n0".to_string(), Type::Struct(Struct::new(vec!["char".to_string(), "int".to_string()]))).unwrap();
+    for level in 1..=10 {
+        let prev = format!("n{}", level - 1);
+        let members = vec![prev.clone(), "char".to_string(), prev];
+        manager.add(format!("n{}", level), Type::Struct(Struct::new(members))).unwrap();
+    }
+
+    // the layout cache is emptied every time, or every level but the top one would be a cache hit
+    let nested = manager.get(&"n10".to_string()).unwrap();
+    bench("unpacked size, 10 nested levels", || {
+        manager.layout_cache().clear();
+        nested.size(&manager, Struct::unpacked_size)
+    });
+    bench("unpacked size, 10 nested levels, cached", || nested.size(&manager, Struct::unpacked_size));
+}
+
+/// Size of a struct with `n` members under every packing strategy
+fn bench_optimizer(n: usize) {
+    let mut manager = base_manager();
+    let pool = ["char", "short", "int", "long"];
+    let members = (0..n).map(|i| pool[i % pool.len()].to_string()).collect();
+    manager.add("s".to_string(), Type::Struct(Struct::new(members))).unwrap();
+
+    let s = manager.get(&"s".to_string()).unwrap();
+    bench(&format!("unpacked size, {} members", n),  || s.size(&manager, Struct::unpacked_size));
+    bench(&format!("packed size, {} members", n),    || s.size(&manager, Struct::packed_size));
+    bench(&format!("optimized size, {} members", n), || s.size(&manager, Struct::optimized_size));
+}
+
+fn main() {
+    // `cargo test --benches` runs this binary too, don't spend time measuring then
+    if std::env::args().any(|arg| arg == "--bench") {
+        bench_add_types();
+        bench_nested_unpacked_size();
+        bench_optimizer(6);
+        bench_optimizer(8);
+        if std::env::var_os("TM_BENCH_LARGE").is_some() {
+            bench_optimizer(10);
+        }
+    }
+}
@@ -0,0 +1,41 @@
+/*
+    Turn arbitrary bytes into a sequence of well formed commands, so the
+    fuzzer spends its time on the manager rather than on tokenizing
+*/
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use type_manager::driver::Program;
+
+mod common;
+
+/// Names commands may refer to, small so commands end up referencing each other
+const NAMES : [&str; 6] = ["a", "b", "c", "d", "e", "f"];
+
+/// Interesting numbers for sizes and alignments
+const NUMBERS : [&str; 8] = ["0", "1", "2", "3", "4", "8", "4096", "18446744073709551615"];
+
+fuzz_target!(|data: &[u8]| {
+    let mut program = Program::sandboxed();
+    let mut bytes = data.iter().copied();
+
+    while let Some(op) = bytes.next() {
+        let mut next = || bytes.next().unwrap_or(0) as usize;
+        let name = NAMES[next() % NAMES.len()];
+
+        let line = match op % 4 {
+            0 => format!("atomico {} {} {}", name, NUMBERS[next() % NUMBERS.len()], NUMBERS[next() % NUMBERS.len()]),
+            1 | 2 => {
+                let keyword = if op % 4 == 1 { "struct" } else { "union" };
+                let members : Vec<&str> = (0..next() % common::MAX_TOKENS)
+                                            .map(|_| NAMES[next() % NAMES.len()])
+                                            .collect();
+                format!("{} {} {}", keyword, name, members.join(" "))
+            },
+            _ => format!("describir {}", name)
+        };
+
+        program.run_line(&line);
+    }
+
+    common::check_invariants(program.manager());
+});
@@ -0,0 +1,29 @@
+/*
+    Shared helpers for fuzz targets
+*/
+use type_manager::type_system::*;
+
+/// Lines with more tokens than this are skipped, the optimizer is factorial
+/// on the amount of members and would turn every long line into a timeout
+pub const MAX_TOKENS : usize = 8;
+
+/// Check that every stored type has sane sizes and alignments
+pub fn check_invariants(manager : &TypeManager) {
+    for name in manager.names() {
+        let t = manager.get(name).unwrap();
+
+        let unpacked  = t.size(manager, Struct::unpacked_size);
+        let packed    = t.size(manager, Struct::packed_size);
+        let optimized = t.size(manager, Struct::optimized_size);
+
+        assert!(packed > 0, "'{}' has size 0", name);
+        assert!(packed <= unpacked,  "'{}' packed {} > unpacked {}", name, packed, unpacked);
+        assert!(packed <= optimized, "'{}' packed {} > optimized {}", name, packed, optimized);
+
+        assert!(t.align(manager, Struct::unpacked_align) > 0,  "'{}' has alignment 0", name);
+        assert!(t.align(manager, Struct::optimized_align) > 0, "'{}' has alignment 0", name);
+
+        // asking for a description should never fail for a stored type
+        assert!(manager.display(name).is_ok());
+    }
+}
@@ -0,0 +1,27 @@
+/*
+    Feed arbitrary text through the command parser and the manager, in a
+    program that never touches files whatever the text asks for
+*/
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use type_manager::driver::Program;
+
+mod common;
+
+fuzz_target!(|data: &[u8]| {
+    let text = match std::str::from_utf8(data) {
+        Ok(s)  => s,
+        Err(_) => return
+    };
+
+    let mut program = Program::sandboxed();
+    for line in text.lines() {
+        if line.split_whitespace().count() > common::MAX_TOKENS {
+            continue
+        }
+
+        program.run_line(line);
+    }
+
+    common::check_invariants(program.manager());
+});
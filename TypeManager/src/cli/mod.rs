@@ -0,0 +1,334 @@
+/*
+    Command line interface: subcommands accepted by the binary and
+    shell completion scripts for them
+*/
+use std::path::PathBuf;
+
+use crate::driver::ExportFormat;
+
+/// Port used by `serve` when none is given
+pub const DEFAULT_PORT : u16 = 7878;
+
+/// Every subcommand with a short description
+const SUBCOMMANDS : [(&str, &str); 9] = [
+    ("repl",        "Sesión interactiva (por defecto)"),
+    ("run",         "Ejecuta uno o más scripts"),
+    ("watch",       "Ejecuta un script cada vez que cambia y muestra qué tipos cambiaron"),
+    ("export",      "Exporta las definiciones en otro formato"),
+    ("serve",       "Atiende sesiones por TCP, un comando por línea"),
+    ("share",       "Comparte una sola sesión por TCP con todos los que se conecten"),
+    ("connect",     "Se conecta a una sesión compartida con 'share'"),
+    ("completions", "Genera el script de autocompletado para un shell"),
+    ("help",        "Muestra esta ayuda")
+];
+
+/// Formats accepted by `export --format`
+const FORMATS : [&str; 10] = ["json", "c", "rs", "tm", "capnp", "fbs", "sql", "arrow", "treemap", "treemap-html"];
+
+/// Shells we can generate completions for
+const SHELLS : [&str; 3] = ["bash", "zsh", "fish"];
+
+/// What the binary was asked to do
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Repl,
+    Run(Vec<PathBuf>),                          // scripts to run, in order
+    Watch(PathBuf),                             // script to run again on every change
+    Export(ExportFormat, Option<PathBuf>),      // format, definitions file (the configured one if none)
+    Serve(u16, Option<u16>),                    // port, port for metrics over HTTP if any
+    Share(u16, Option<u16>),                    // port, port for metrics over HTTP if any
+    Connect(String, u16),                       // host, port
+    StdioJson,                                  // a session driven by another program over stdin and stdout
+    Completions(Shell),
+    Help,
+    Version
+}
+
+/// Options accepted anywhere in the command line, for every subcommand
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GlobalOptions {
+    pub strict:    bool,    // warnings about new types are errors
+    pub read_only: bool,    // commands changing types are rejected
+    pub progress:  bool,    // long imports show how far they got
+    pub keep_first: bool,   // the optimizer keeps the first member of every struct first
+    pub shadowing: bool,    // defining a stored name again shadows the stored type
+    pub deferred: bool      // structs and unions may use types defined later
+}
+
+/// Shells we can generate completion scripts for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish
+}
+
+/// Parse command line arguments, not including the program name
+/// ## Params
+/// * `args` - arguments as given by the shell
+/// ---
+/// ## Return
+/// The command to run, or a description of what's wrong with the arguments
+pub fn parse<I>(args : I) -> Result<Command, String>
+    where
+        I: IntoIterator<Item = String>
+{
+    let mut args = args.into_iter();
+
+    let sub = match args.next() {
+        None => return Ok(Command::Repl),
+        Some(s) => s
+    };
+
+    match sub.as_str() {
+        "repl" => {
+            no_more_args(args)?;
+            Ok(Command::Repl)
+        },
+        "run" => {
+            let scripts : Vec<PathBuf> = args.map(PathBuf::from).collect();
+            if scripts.is_empty() {
+                return Err(String::from("'run' necesita al menos un script"))
+            }
+            Ok(Command::Run(scripts))
+        },
+        "watch" => {
+            let script = match args.next() {
+                Some(s) if !s.starts_with('-') => PathBuf::from(s),
+                Some(s) => return Err(format!("opción desconocida '{}'", s)),
+                None => return Err(String::from("'watch' necesita un script"))
+            };
+            no_more_args(args)?;
+            Ok(Command::Watch(script))
+        },
+        "export" => {
+            let mut format = ExportFormat::Json;
+            let mut input = None;
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--format" | "-f" => format = parse_format(&option_value(&arg, args.next())?)?,
+                    s if s.starts_with("--format=") => format = parse_format(&s["--format=".len()..])?,
+                    s if s.starts_with('-') => return Err(format!("opción desconocida '{}'", s)),
+                    _ if input.is_none() => input = Some(PathBuf::from(arg)),
+                    _ => return Err(String::from("'export' recibe un solo archivo"))
+                }
+            }
+            Ok(Command::Export(format, input))
+        },
+        "serve" | "share" | "connect" => {
+            let mut port = DEFAULT_PORT;
+            let mut metrics = None;
+            let mut host = String::from("127.0.0.1");
+            while let Some(arg) = args.next() {
+                match arg.as_str() {
+                    "--port" | "-p" => port = parse_port(&option_value(&arg, args.next())?)?,
+                    s if s.starts_with("--port=") => port = parse_port(&s["--port=".len()..])?,
+                    "--metrics-port" if sub != "connect" => metrics = Some(parse_port(&option_value(&arg, args.next())?)?),
+                    s if s.starts_with("--metrics-port=") && sub != "connect" => metrics = Some(parse_port(&s["--metrics-port=".len()..])?),
+                    "--host" if sub == "connect" => host = option_value(&arg, args.next())?,
+                    s if s.starts_with("--host=") && sub == "connect" => host = s["--host=".len()..].to_string(),
+                    s => return Err(format!("argumento inesperado '{}'", s))
+                }
+            }
+            Ok(match sub.as_str() {
+                "serve" => Command::Serve(port, metrics),
+                "share" => Command::Share(port, metrics),
+                _ => Command::Connect(host, port)
+            })
+        },
+        "completions" => {
+            let shell = match args.next().as_deref() {
+                Some("bash") => Shell::Bash,
+                Some("zsh")  => Shell::Zsh,
+                Some("fish") => Shell::Fish,
+                Some(s) => return Err(format!("shell desconocido '{}', se esperaba {}", s, SHELLS.join(", "))),
+                None    => return Err(format!("'completions' necesita un shell: {}", SHELLS.join(", ")))
+            };
+            no_more_args(args)?;
+            Ok(Command::Completions(shell))
+        },
+        "help" | "--help" | "-h" => Ok(Command::Help),
+        "--version" | "-V" => Ok(Command::Version),
+        "--stdio-json" => {
+            no_more_args(args)?;
+            Ok(Command::StdioJson)
+        },
+        s => Err(format!("subcomando desconocido '{}'", s))
+    }
+}
+
+/// Take global options out of the command line arguments
+/// ## Params
+/// * `args` - arguments as given by the shell, not including the program name
+/// ---
+/// ## Return
+/// Global options found and every other argument, in order
+pub fn split_options<I>(args : I) -> (GlobalOptions, Vec<String>)
+    where
+        I: IntoIterator<Item = String>
+{
+    let mut options = GlobalOptions::default();
+    let mut rest = vec![];
+
+    for arg in args {
+        match arg.as_str() {
+            "--strict" | "--estricto" => options.strict = true,
+            "--solo-lectura" | "--read-only" => options.read_only = true,
+            "--progreso" | "--progress" => options.progress = true,
+            "--conservar-primero" | "--keep-first" => options.keep_first = true,
+            "--sombrear" | "--shadow" => options.shadowing = true,
+            "--diferir" | "--defer" => options.deferred = true,
+            _ => rest.push(arg)
+        }
+    }
+
+    (options, rest)
+}
+
+/// Help text listing every subcommand
+pub fn usage(program : &str) -> String {
+    let mut text = format!("Uso: {} [subcomando] [opciones]\n\nSubcomandos:\n", program);
+    for (name, description) in SUBCOMMANDS.iter() {
+        text += &format!("  {:<12} {}\n", name, description);
+    }
+    text += &format!(
+        "\nOpciones:\n  export [--format {}] [archivo]\n  serve [--port N] [--metrics-port N]   (por defecto {}), con métricas de Prometheus en GET /metrics\n  share [--port N] [--metrics-port N]\n  connect [--host H] [--port N]\n  completions <{}>\n  --stdio-json              una solicitud JSON por línea en stdin, una respuesta JSON por línea en stdout\n  --strict                  los avisos son errores y los scripts paran en el primero\n  --solo-lectura            rechaza los comandos que cambian los tipos\n  --progreso                muestra el avance de las importaciones largas\n  --conservar-primero       el optimizador deja el primer miembro de cada struct primero\n  --sombrear                redefinir un tipo lo sombrea, el anterior sigue como 'nombre@anterior'\n  --diferir                 los structs y unions pueden usar tipos definidos después\n",
+        FORMATS.join("|"),
+        DEFAULT_PORT,
+        SHELLS.join("|")
+    );
+
+    text
+}
+
+/// Completion script for the given shell
+/// ## Params
+/// * `shell` - shell to generate the script for
+/// * `program` - name of the binary being completed
+pub fn completions(shell : Shell, program : &str) -> String {
+    let subcommands : Vec<&str> = SUBCOMMANDS.iter().map(|(name, _)| *name).collect();
+
+    match shell {
+        Shell::Bash => format!(
+r#"# bash completion for {prog}
+_{func}() {{
+    local cur prev
+    cur="${{COMP_WORDS[COMP_CWORD]}}"
+    prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+
+    if [ "$COMP_CWORD" -eq 1 ]; then
+        COMPREPLY=($(compgen -W "{subs}" -- "$cur"))
+        return
+    fi
+
+    case "$prev" in
+        --format|-f) COMPREPLY=($(compgen -W "{formats}" -- "$cur")); return ;;
+        completions) COMPREPLY=($(compgen -W "{shells}" -- "$cur")); return ;;
+        --port|-p|--host|--metrics-port) return ;;
+    esac
+
+    case "${{COMP_WORDS[1]}}" in
+        export) COMPREPLY=($(compgen -W "--format" -- "$cur") $(compgen -f -- "$cur")) ;;
+        serve|share) COMPREPLY=($(compgen -W "--port --metrics-port" -- "$cur")) ;;
+        connect) COMPREPLY=($(compgen -W "--host --port" -- "$cur")) ;;
+        run|watch) COMPREPLY=($(compgen -f -- "$cur")) ;;
+    esac
+}}
+complete -F _{func} {prog}
+"#,
+            prog = program,
+            func = program.replace('-', "_"),
+            subs = subcommands.join(" "),
+            formats = FORMATS.join(" "),
+            shells = SHELLS.join(" ")
+        ),
+        Shell::Zsh => {
+            let subs : Vec<String> = SUBCOMMANDS
+                                        .iter()
+                                        .map(|(name, description)| format!("'{}:{}'", name, description))
+                                        .collect();
+            format!(
+r#"#compdef {prog}
+_{func}() {{
+    local -a subcommands
+    subcommands=({subs})
+
+    if (( CURRENT == 2 )); then
+        _describe 'subcomando' subcommands
+        return
+    fi
+
+    case "$words[2]" in
+        export)      _arguments '--format[formato de salida]:formato:({formats})' '*:archivo:_files' ;;
+        serve|share) _arguments '--port[puerto TCP]:puerto:' '--metrics-port[puerto HTTP de las métricas]:puerto:' ;;
+        connect)     _arguments '--host[equipo]:equipo:_hosts' '--port[puerto TCP]:puerto:' ;;
+        run|watch)   _files ;;
+        completions) _values 'shell' {shells} ;;
+    esac
+}}
+compdef _{func} {prog}
+"#,
+                prog = program,
+                func = program.replace('-', "_"),
+                subs = subs.join(" "),
+                formats = FORMATS.join(" "),
+                shells = SHELLS.join(" ")
+            )
+        },
+        Shell::Fish => {
+            let mut script = format!("# fish completion for {}\ncomplete -c {} -f\n", program, program);
+            for (name, description) in SUBCOMMANDS.iter() {
+                script += &format!(
+                    "complete -c {} -n '__fish_use_subcommand' -a {} -d '{}'\n",
+                    program,
+                    name,
+                    description
+                );
+            }
+            script += &format!("complete -c {} -n '__fish_seen_subcommand_from run watch export' -F\n", program);
+            script += &format!(
+                "complete -c {} -n '__fish_seen_subcommand_from export' -l format -s f -x -a '{}'\n",
+                program,
+                FORMATS.join(" ")
+            );
+            script += &format!("complete -c {} -n '__fish_seen_subcommand_from serve share connect' -l port -s p -x\n", program);
+            script += &format!("complete -c {} -n '__fish_seen_subcommand_from serve share' -l metrics-port -x\n", program);
+            script += &format!("complete -c {} -n '__fish_seen_subcommand_from connect' -l host -x -a '(__fish_print_hostnames)'\n", program);
+            script += &format!(
+                "complete -c {} -n '__fish_seen_subcommand_from completions' -a '{}'\n",
+                program,
+                SHELLS.join(" ")
+            );
+            script
+        }
+    }
+}
+
+/// Value for an option given as a separate argument
+fn option_value(option : &str, value : Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("la opción '{}' necesita un valor", option))
+}
+
+/// Parse an export format name
+fn parse_format(value : &str) -> Result<ExportFormat, String> {
+    ExportFormat::parse(value).ok_or_else(|| format!("formato desconocido '{}', se esperaba {}", value, FORMATS.join(", ")))
+}
+
+/// Parse a TCP port number
+fn parse_port(value : &str) -> Result<u16, String> {
+    value.parse().map_err(|_| format!("'{}' no es un puerto válido", value))
+}
+
+/// Fail if there's any argument left
+fn no_more_args<I>(args : I) -> Result<(), String>
+    where
+        I: Iterator<Item = String>
+{
+    let rest : Vec<String> = args.collect();
+    if rest.is_empty() {
+        Ok(())
+    }
+    else {
+        Err(format!("argumentos inesperados: {}", rest.join(" ")))
+    }
+}
@@ -0,0 +1,154 @@
+/*
+    Apache Arrow schemas. Arrow stores a table by columns (structure of arrays),
+    each in buffers of its own padded to 64 bytes, plus a bitmap per array telling
+    which values are valid. So every struct comes with the memory its columns
+    take for some amount of rows, next to an array of the C layout (array of structures)
+*/
+use crate::type_system::*;
+
+/// Rows used when no amount is given
+pub const DEFAULT_ROWS : usize = 1024;
+
+/// Arrow buffers are padded to this many bytes
+const BUFFER_PADDING : usize = 64;
+
+/// An Arrow array and the memory it takes
+struct Column {
+    arrow:    String,   // type as Arrow writes it
+    values:   usize,    // bytes of offsets, values and children
+    validity: usize     // bytes of validity bitmaps, here and in children
+}
+
+/// Bytes taken by a buffer
+fn buffer(bytes : usize) -> usize {
+    bytes.saturating_add(BUFFER_PADDING - 1) / BUFFER_PADDING * BUFFER_PADDING
+}
+
+/// Bytes taken by a validity bitmap
+fn bitmap(rows : usize) -> usize {
+    buffer(rows.div_ceil(8))
+}
+
+/// Emit an Arrow schema for the structs in the given types, each a struct type. Atomics of
+/// 1, 2, 4 or 8 bytes become integers or floats, other atomics fixed size binaries, enums
+/// dictionaries of their variant names, strings `string`, vectors lists and unions sparse
+/// unions. Every struct comes with the bytes its columns take for a number of rows, with
+/// and without validity bitmaps, and the bytes an array of the C layout takes
+/// ## Params
+/// * `manager` - manager storing every type
+/// * `names` - types to emit
+/// * `rows` - rows in the table
+/// ---
+/// ## Return
+/// The schema and the memory report
+pub fn arrow_schema(manager : &TypeManager, names : &[Name], rows : usize) -> String {
+    let mut code = format!("# Generado por TypeManager, memoria para {} filas\n", rows);
+
+    for name in names {
+        let s = match manager.get(name) {
+            Some(Type::Struct(s)) => s,
+            _ => continue
+        };
+
+        let columns : Vec<(&Name, Column)> = s.fields
+                                                .iter()
+                                                .zip(&s.members)
+                                                .map(|(f, m)| (&f.name, arrow_column(manager, m, rows, &mut vec![name.clone()])))
+                                                .collect();
+
+        let fields : Vec<String> = columns.iter().map(|(f, c)| format!("{}: {}", f, c.arrow)).collect();
+        code += &format!("\n{}: struct<{}>\n", name, fields.join(", "));
+        for (field, column) in &columns {
+            code += &format!("#   {}: {} bytes de valores y {} de validez\n", field, column.values, column.validity);
+        }
+
+        let values : usize = columns.iter().map(|(_, c)| c.values).sum();
+        let validity : usize = columns.iter().map(|(_, c)| c.validity).sum::<usize>() + bitmap(rows);
+        let columnar = values + validity;
+        let size = s.c_size(manager);
+        let row_wise = rows.saturating_mul(size);
+        code += &format!(
+            "# en columnas: {} bytes, {} sin mapas de validez. Por filas en C: {} x {} = {} bytes\n",
+            columnar, values, rows, size, row_wise
+        );
+        code += &match columnar <= row_wise {
+            true  => format!("# las columnas ahorran {} bytes\n", row_wise - columnar),
+            false => format!("# las columnas ocupan {} bytes más\n", columnar - row_wise)
+        };
+    }
+
+    code
+}
+
+/// Arrow array holding every value of a member
+/// ## Params
+/// * `rows` - values in the array
+/// * `outer` - structs holding this member, a box or vector of one of them is kept as an address
+fn arrow_column(manager : &TypeManager, member : &Name, rows : usize, outer : &mut Vec<Name>) -> Column {
+    let fixed = |arrow : String, width : usize| Column { arrow, values: buffer(rows.saturating_mul(width)), validity: bitmap(rows) };
+    let offsets = buffer(rows.saturating_add(1).saturating_mul(4));
+
+    match manager.get(member) {
+        Some(Type::Atomic(a)) if a.representation == 1 && a.kind == Some(ValueKind::Bool) => {
+            // a bit per row
+            Column { arrow: String::from("bool"), values: bitmap(rows), validity: bitmap(rows) }
+        },
+        Some(Type::Atomic(a)) => {
+            let arrow = match (a.representation, a.kind) {
+                (2, Some(ValueKind::Float)) => String::from("halffloat"),
+                (4, Some(ValueKind::Float)) => String::from("float"),
+                (8, Some(ValueKind::Float)) => String::from("double"),
+                (n @ (1 | 2 | 4 | 8), Some(ValueKind::Signed)) => format!("int{}", n * 8),
+                (n @ (1 | 2 | 4 | 8), _) => format!("uint{}", n * 8),
+                (n, _) => format!("fixed_size_binary[{}]", n)
+            };
+            fixed(arrow, a.representation)
+        },
+        Some(t @ Type::Enum(e)) => {
+            // indices for every row, the variant names once
+            let width = Strategy::C.type_size(t, manager);
+            let names : usize = e.variants.iter().map(|v| v.len()).sum();
+            let mut column = fixed(format!("dictionary<values=string, indices=int{}>", width * 8), width);
+            column.values += buffer((e.variants.len() + 1) * 4) + buffer(names);
+            column
+        },
+        Some(Type::Struct(_)) if outer.contains(member) => fixed(String::from("uint64"), 8),
+        Some(Type::Struct(s)) => {
+            outer.push(member.clone());
+            let children : Vec<(&Name, Column)> = s.fields.iter().map(|f| &f.name).zip(s.members.iter().map(|m| arrow_column(manager, m, rows, outer))).collect();
+            outer.pop();
+            let fields : Vec<String> = children.iter().map(|(f, c)| format!("{}: {}", f, c.arrow)).collect();
+            Column {
+                arrow: format!("struct<{}>", fields.join(", ")),
+                values: children.iter().map(|(_, c)| c.values).sum(),
+                validity: children.iter().map(|(_, c)| c.validity).sum::<usize>() + bitmap(rows)
+            }
+        },
+        Some(Type::Union(u)) => {
+            // a type id per row, every child as long as the union
+            let children : Vec<Column> = u.variants.iter().map(|v| arrow_column(manager, v, rows, outer)).collect();
+            let fields : Vec<String> = children.iter().enumerate().map(|(i, c)| format!("{}: {}={}", super::member_name(i), c.arrow, i)).collect();
+            Column {
+                arrow: format!("sparse_union<{}>", fields.join(", ")),
+                values: buffer(rows) + children.iter().map(|c| c.values).sum::<usize>(),
+                validity: children.iter().map(|c| c.validity).sum()
+            }
+        },
+        Some(Type::Text(t)) => {
+            let length = match t.model {
+                TextModel::Raw(n) => n,
+                _ => 0
+            };
+            Column { arrow: String::from("string"), values: offsets + buffer(rows.saturating_mul(length)), validity: bitmap(rows) }
+        },
+        Some(Type::Container(c)) => match (c.kind, &c.element) {
+            (ContainerKind::Vec, Some(element)) => {
+                let child = arrow_column(manager, element, rows.saturating_mul(c.capacity), outer);
+                Column { arrow: format!("list<item: {}>", child.arrow), values: offsets + child.values, validity: bitmap(rows) + child.validity }
+            },
+            (ContainerKind::Box, Some(element)) => arrow_column(manager, element, rows, outer),
+            _ => Column { arrow: String::from("string"), values: offsets + buffer(rows.saturating_mul(c.capacity)), validity: bitmap(rows) }
+        },
+        Some(Type::Pointer(_)) | None => fixed(String::from("uint64"), 8)
+    }
+}
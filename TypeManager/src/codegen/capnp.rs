@@ -0,0 +1,266 @@
+/*
+    Cap'n Proto schemas. Its wire format is a fixed struct layout too: scalars go
+    into a data section of 64 bit words, each one in the first free hole of its size,
+    and everything else is a pointer in a section after it. So the schema is emitted
+    along with the places where that layout and the C strategy disagree
+*/
+use crate::type_system::*;
+
+/// Where a field is stored in a Cap'n Proto struct
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Slot {
+    Data(usize),    // log2 of its size in bits
+    Pointer
+}
+
+/// Data section of a Cap'n Proto struct, filled the way its compiler does it
+#[derive(Default)]
+struct DataSection {
+    words: usize,
+    holes: [usize; 6]   // free hole of each size smaller than a word, in units of that size. 0 if none
+}
+
+impl DataSection {
+
+    /// Place a new field
+    /// ## Params
+    /// * `lg` - log2 of the field's size in bits
+    /// ---
+    /// ## Return
+    /// Offset of the field in bits
+    fn allocate(&mut self, lg : usize) -> usize {
+        let offset = match self.take_hole(lg) {
+            Some(offset) => offset,
+            None => {
+                // a new word, what's left of it becomes holes
+                let offset = self.words << (6 - lg);
+                self.words += 1;
+                let mut hole = offset + 1;
+                for size in lg..6 {
+                    self.holes[size] = hole;
+                    hole = hole.div_ceil(2);
+                }
+                offset
+            }
+        };
+
+        offset << lg
+    }
+
+    /// Use a free hole for a field, splitting a bigger one if there's none of its size
+    fn take_hole(&mut self, lg : usize) -> Option<usize> {
+        if lg >= 6 {
+            return None
+        }
+        if self.holes[lg] != 0 {
+            return Some(std::mem::take(&mut self.holes[lg]))
+        }
+
+        let bigger = self.take_hole(lg + 1)?;
+        self.holes[lg] = bigger * 2 + 1;
+        Some(bigger * 2)
+    }
+}
+
+/// Emit a Cap'n Proto schema for the structs, unions and enums in the given types.
+/// Other types are used through the members referencing them: atomics of 1, 2, 4 or
+/// 8 bytes become integers or floats, strings `Text`, vectors `List` and anything else
+/// a pointer. Every struct comes with a comment comparing its layout under the C strategy
+/// with the one Cap'n Proto gives it, listing the members that land somewhere else, and
+/// structs and enums with a note come with it as a comment
+/// ## Params
+/// * `manager` - manager storing every type
+/// * `names` - types to emit
+/// ---
+/// ## Return
+/// A schema file, with an id derived from the types' names
+pub fn capnp_schema(manager : &TypeManager, names : &[Name]) -> String {
+    let mut code = format!("# Generado por TypeManager\n@0x{:016x};\n", file_id(names));
+
+    for name in names {
+        match manager.get(name) {
+            Some(Type::Struct(s)) => {
+                let fields : Vec<(&Name, &Name)> = s.fields.iter().map(|f| &f.name).zip(&s.members).collect();
+                code += "\n";
+                code += &capnp_report(manager, name, s);
+                code += &capnp_note(manager, name);
+                code += &format!("struct {} {{\n{}}}\n", type_name(name), capnp_fields(manager, &fields, "  "));
+            },
+            Some(t @ Type::Union(u)) => {
+                let fields : Vec<Name> = (0..u.variants.len()).map(super::member_name).collect();
+                let fields : Vec<(&Name, &Name)> = fields.iter().zip(&u.variants).collect();
+                code += &format!("\n# {}: {} bytes en C", name, Strategy::C.type_size(t, manager));
+                code += &match fields.len() > 1 {
+                    true  => format!(
+                        ", en Cap'n Proto lleva un discriminante de 16 bits y las variantes que son punteros van aparte\nstruct {} {{\n  union {{\n{}  }}\n}}\n",
+                        type_name(name),
+                        capnp_fields(manager, &fields, "    ")
+                    ),
+                    false => format!("\nstruct {} {{\n{}}}\n", type_name(name), capnp_fields(manager, &fields, "  "))
+                };
+            },
+            Some(t @ Type::Enum(e)) => {
+                let size = Strategy::C.type_size(t, manager);
+                code += "\n";
+                if size != 2 {
+                    code += &format!("# {}: {} bytes en C, 2 en Cap'n Proto\n", name, size);
+                }
+                code += &capnp_note(manager, name);
+                code += &format!("enum {} {{\n", type_name(name));
+                for (i, variant) in e.variants.iter().enumerate() {
+                    code += &format!("  {} @{};\n", camel_case(variant, false), i);
+                }
+                code += "}\n";
+            },
+            _ => ()
+        }
+    }
+
+    code
+}
+
+/// Comment with the note of a type, empty if it has none
+fn capnp_note(manager : &TypeManager, name : &Name) -> String {
+    manager.note(name).map(|note| format!("# {}\n", note)).unwrap_or_default()
+}
+
+/// Field declarations, numbered in order
+fn capnp_fields(manager : &TypeManager, fields : &[(&Name, &Name)], indent : &str) -> String {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, (field, member))| format!("{}{} @{} :{};\n", indent, camel_case(field, false), i, capnp_type(manager, member).0))
+        .collect()
+}
+
+/// Comment comparing the layout of a struct under the C strategy and in Cap'n Proto
+fn capnp_report(manager : &TypeManager, name : &Name, s : &Struct) -> String {
+    let mut data = DataSection::default();
+    let slots : Vec<Slot> = s.members.iter().map(|m| capnp_type(manager, m).1).collect();
+    let places : Vec<Option<usize>> = slots
+                                        .iter()
+                                        .map(|slot| match slot {
+                                            Slot::Data(lg) => Some(data.allocate(*lg)),
+                                            Slot::Pointer  => None
+                                        })
+                                        .collect();
+
+    let mut pointers = 0;
+    let mut lines = vec![];
+    for (i, offset) in s.c_offsets(manager).into_iter().enumerate() {
+        let size = manager.get(&s.members[i]).map_or(0, |t| Strategy::C.type_size(t, manager));
+        match (slots[i], places[i]) {
+            (Slot::Data(0), Some(place)) => lines.push(format!(
+                "#   {}: {} bytes en el byte {} en C, un bit en el bit {} del byte {} en Cap'n Proto\n",
+                s.fields[i].name, size, offset, place % 8, place / 8
+            )),
+            (Slot::Data(lg), Some(place)) if place / 8 != offset || 1 << lg != size * 8 => lines.push(format!(
+                "#   {}: {} bytes en el byte {} en C, {} bytes en el byte {} en Cap'n Proto\n",
+                s.fields[i].name, size, offset, (1 << lg) / 8, place / 8
+            )),
+            (Slot::Pointer, _) => {
+                lines.push(format!(
+                    "#   {}: {} bytes en el byte {} en C, un puntero en el byte {} en Cap'n Proto\n",
+                    s.fields[i].name, size, offset, (data.words + pointers) * 8
+                ));
+                pointers += 1;
+            },
+            _ => ()
+        }
+    }
+
+    let size  = s.c_size(manager);
+    let total = (data.words + pointers) * 8;
+    let summary = match lines.is_empty() && size == total {
+        true  => String::from("misma disposición en C y en Cap'n Proto"),
+        false => format!(
+            "{} bytes en C, {} palabras de datos y {} punteros en Cap'n Proto ({} bytes)",
+            size, data.words, pointers, total
+        )
+    };
+
+    format!("# {}: {}\n{}", name, summary, lines.concat())
+}
+
+/// Cap'n Proto type for a member and where it's stored
+fn capnp_type(manager : &TypeManager, member : &Name) -> (String, Slot) {
+    let (name, slot) = match manager.get(member) {
+        Some(Type::Atomic(a)) => match (a.representation, a.kind) {
+            (1, Some(ValueKind::Bool))   => ("Bool",    Slot::Data(0)),
+            (1, Some(ValueKind::Signed)) => ("Int8",    Slot::Data(3)),
+            (1, _)                       => ("UInt8",   Slot::Data(3)),
+            (2, Some(ValueKind::Signed)) => ("Int16",   Slot::Data(4)),
+            (2, _)                       => ("UInt16",  Slot::Data(4)),
+            (4, Some(ValueKind::Float))  => ("Float32", Slot::Data(5)),
+            (4, Some(ValueKind::Signed)) => ("Int32",   Slot::Data(5)),
+            (4, _)                       => ("UInt32",  Slot::Data(5)),
+            (8, Some(ValueKind::Float))  => ("Float64", Slot::Data(6)),
+            (8, Some(ValueKind::Signed)) => ("Int64",   Slot::Data(6)),
+            (8, _)                       => ("UInt64",  Slot::Data(6)),
+            _ => ("Data", Slot::Pointer)
+        },
+        Some(Type::Enum(_)) => return (type_name(member), Slot::Data(4)),
+        Some(Type::Struct(_)) | Some(Type::Union(_)) => return (type_name(member), Slot::Pointer),
+        Some(Type::Container(c)) => match (c.kind, &c.element) {
+            (ContainerKind::String, _) => ("Text", Slot::Pointer),
+            (ContainerKind::Vec, Some(element)) => {
+                // lists can't hold any pointer
+                let element = match capnp_type(manager, element).0 {
+                    t if t == "AnyPointer" => String::from("Data"),
+                    t => t
+                };
+                return (format!("List({})", element), Slot::Pointer)
+            },
+            (ContainerKind::Box, Some(element)) => return capnp_type(manager, element),
+            _ => ("AnyPointer", Slot::Pointer)
+        },
+        Some(Type::Text(_)) => ("Text", Slot::Pointer),
+        Some(Type::Pointer(_)) | None => ("AnyPointer", Slot::Pointer)
+    };
+
+    (name.to_string(), slot)
+}
+
+/// Cap'n Proto wants type names in UpperCamelCase
+fn type_name(name : &str) -> String {
+    let name = camel_case(name, true);
+    match name.starts_with(|c : char| c.is_ascii_digit()) {
+        true  => format!("T{}", name),
+        false => name
+    }
+}
+
+/// Join the words in a name, which can't have underscores in Cap'n Proto. Words
+/// written in capitals are lowercased first, so `NOT_SET` becomes `notSet`
+/// ## Params
+/// * `upper` - whether the first word is capitalized too
+fn camel_case(name : &str, upper : bool) -> String {
+    let mut result = String::new();
+    for word in name.split(|c : char| !c.is_ascii_alphanumeric()).filter(|w| !w.is_empty()) {
+        let word = match word.chars().any(|c| c.is_ascii_lowercase()) {
+            true  => word.to_string(),
+            false => word.to_ascii_lowercase()
+        };
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            match result.is_empty() && !upper {
+                true  => result.push(first.to_ascii_lowercase()),
+                false => result.push(first.to_ascii_uppercase())
+            }
+            result.extend(chars);
+        }
+    }
+
+    result
+}
+
+/// Unique id for a schema file: a hash of the names in it with the top bit set,
+/// so exporting the same types gives the same file
+fn file_id(names : &[Name]) -> u64 {
+    let mut hash : u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in names.join(" ").bytes() {
+        hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+    }
+
+    hash | 1 << 63
+}
@@ -0,0 +1,189 @@
+/*
+    FlatBuffers schemas. Structs holding only scalars and other such structs keep
+    a fixed layout there, everything else becomes a table, whose fields are optional
+    and found through a vtable. What the schema can't express is left as a warning
+*/
+use crate::type_system::*;
+
+/// Emit a FlatBuffers schema for the structs, unions and enums in the given types.
+/// A struct is a FlatBuffers `struct` when its members are atomics, enums or such structs,
+/// and a `table` otherwise. Strings and vectors become `string` and vectors, boxes the type
+/// they own. Unions, bit fields, pointers and alignment attributes need to be translated
+/// by hand, so they come with a warning. Notes of types and struct members become doc comments
+/// ## Params
+/// * `manager` - manager storing every type
+/// * `names` - types to emit
+/// ---
+/// ## Return
+/// A schema file
+pub fn fbs_schema(manager : &TypeManager, names : &[Name]) -> String {
+    fbs_schema_with_warnings(manager, names).0
+}
+
+/// What a FlatBuffers schema for the given types can't express, see `fbs_schema`
+/// ## Return
+/// Every warning left in the schema, in order
+pub fn fbs_warnings(manager : &TypeManager, names : &[Name]) -> Vec<String> {
+    fbs_schema_with_warnings(manager, names).1
+}
+
+/// Emit a FlatBuffers schema along with the warnings left in it
+fn fbs_schema_with_warnings(manager : &TypeManager, names : &[Name]) -> (String, Vec<String>) {
+    let mut code = String::from("// Generado por TypeManager\n");
+    let mut all = vec![];
+
+    for name in names {
+        let mut warnings = vec![];
+        let definition = match manager.get(name) {
+            Some(Type::Struct(s)) => fbs_struct(manager, name, s, &mut warnings),
+            Some(Type::Union(u))  => fbs_union(manager, name, u, &mut warnings),
+            Some(t @ Type::Enum(e)) => {
+                let base = match Strategy::C.type_size(t, manager) {
+                    1 => "ubyte",
+                    2 => "ushort",
+                    8 => "long",
+                    _ => "int"
+                };
+                format!("enum {} : {} {{ {} }}\n", name, base, e.variants.join(", "))
+            },
+            _ => continue
+        };
+
+        code += "\n";
+        for warning in &warnings {
+            code += &format!("// ADVERTENCIA: {}\n", warning);
+        }
+        if let Some(note) = manager.note(name) {
+            code += &format!("/// {}\n", note);
+        }
+        code += &definition;
+        all.extend(warnings);
+    }
+
+    (code, all)
+}
+
+/// Tells if a type has a fixed layout in FlatBuffers, so it can go inside a struct
+fn is_fixed(manager : &TypeManager, name : &Name) -> bool {
+    match manager.get(name) {
+        Some(Type::Atomic(_)) | Some(Type::Enum(_)) => true,
+        Some(Type::Struct(s)) => s.members.iter().all(|m| is_fixed(manager, m)),
+        _ => false
+    }
+}
+
+/// Union that FlatBuffers can express: a tagged union of structs and tables
+fn is_tagged(manager : &TypeManager, u : &Union) -> bool {
+    u.variants.iter().all(|v| matches!(manager.get(v), Some(Type::Struct(_))))
+}
+
+/// Struct or table for a struct
+fn fbs_struct(manager : &TypeManager, name : &Name, s : &Struct, warnings : &mut Vec<String>) -> String {
+    let fixed = is_fixed(manager, name);
+    if s.is_packed() {
+        warnings.push(format!("'{}' es @packed, FlatBuffers siempre alinea los campos", name));
+    }
+
+    let mut fields = String::new();
+    for (field, member) in s.fields.iter().zip(&s.members) {
+        if let Some(bits) = field.attribute(BITS) {
+            warnings.push(format!("'{}.{}' es un campo de bits de {} bits, se exporta entero", name, field.name, bits));
+        }
+        if attribute_align(&field.attributes).is_some() {
+            warnings.push(format!("'{}.{}' es @aligned, FlatBuffers solo puede alinear structs enteros", name, field.name));
+        }
+
+        if let Some(note) = note(&field.attributes) {
+            fields += &format!("  /// {}\n", note);
+        }
+        let label = format!("{}.{}", name, field.name);
+        match fbs_type(manager, member, fixed, &label, warnings) {
+            Some(t) => fields += &format!("  {}: {};\n", field.identifier(), t),
+            None    => {
+                warnings.push(format!("'{}' usa la union '{}', hay que traducirlo a mano", label, member));
+                fields += &format!("  // {}: {};\n", field.identifier(), member);
+            }
+        }
+    }
+
+    let keyword = match (fixed, attribute_align(&s.attributes)) {
+        (true, Some(align)) => format!("struct {} (force_align: {})", name, align),
+        (true, None)        => format!("struct {}", name),
+        (false, align)      => {
+            if align.is_some() {
+                warnings.push(format!("'{}' es @aligned pero es una table, que no tiene disposición fija", name));
+            }
+            format!("table {}", name)
+        }
+    };
+
+    format!("{} {{\n{}}}\n", keyword, fields)
+}
+
+/// Tagged union for a union, when its variants allow it
+fn fbs_union(manager : &TypeManager, name : &Name, u : &Union, warnings : &mut Vec<String>) -> String {
+    match is_tagged(manager, u) {
+        true  => {
+            warnings.push(format!("'{}' se exporta como union etiquetada, sus variantes no comparten memoria", name));
+            format!("union {} {{ {} }}\n", name, u.variants.join(", "))
+        },
+        false => {
+            warnings.push(format!("'{}' tiene variantes que no son structs, FlatBuffers no puede expresarla", name));
+            format!("// union {} {{ {} }}\n", name, u.variants.join(", "))
+        }
+    }
+}
+
+/// FlatBuffers type for a member
+/// ## Params
+/// * `fixed` - whether the member is inside a struct, where vectors can't go
+/// * `label` - the member's struct and field, for warnings
+/// ---
+/// ## Return
+/// The type, or none if it's a union FlatBuffers can't express
+fn fbs_type(manager : &TypeManager, member : &Name, fixed : bool, label : &str, warnings : &mut Vec<String>) -> Option<String> {
+    let name = match manager.get(member) {
+        Some(Type::Atomic(a)) => match (a.representation, a.kind) {
+            (1, Some(ValueKind::Bool))   => "bool",
+            (1, Some(ValueKind::Signed)) => "byte",
+            (1, _)                       => "ubyte",
+            (2, Some(ValueKind::Signed)) => "short",
+            (2, _)                       => "ushort",
+            (4, Some(ValueKind::Float))  => "float",
+            (4, Some(ValueKind::Signed)) => "int",
+            (4, _)                       => "uint",
+            (8, Some(ValueKind::Float))  => "double",
+            (8, Some(ValueKind::Signed)) => "long",
+            (8, _)                       => "ulong",
+            (n, _) => {
+                if a.alignment > 1 {
+                    warnings.push(format!("'{}' es de {} bytes, como arreglo de ubyte pierde su alineación de {}", label, n, a.alignment));
+                }
+                return Some(match fixed {
+                    true  => format!("[ubyte:{}]", n),
+                    false => String::from("[ubyte]")
+                })
+            }
+        },
+        Some(Type::Union(u)) if !is_tagged(manager, u) => return None,
+        Some(Type::Pointer(_)) => {
+            warnings.push(format!("'{}' es un puntero, se exporta como ulong", label));
+            "ulong"
+        },
+        Some(Type::Container(c)) => match (c.kind, &c.element) {
+            (ContainerKind::Box, Some(element)) => return fbs_type(manager, element, fixed, label, warnings),
+            (ContainerKind::Vec, Some(element)) => return fbs_type(manager, element, fixed, label, warnings).map(|t| match t.starts_with('[') {
+                true  => {
+                    warnings.push(format!("'{}' es un vector de vectores, se exporta como [ubyte]", label));
+                    String::from("[ubyte]")
+                },
+                false => format!("[{}]", t)
+            }),
+            _ => "string"
+        },
+        Some(Type::Text(_)) => "string",
+        _ => member
+    };
+
+    Some(name.to_string())
+}
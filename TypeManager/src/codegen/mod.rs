@@ -0,0 +1,302 @@
+/*
+    Code generation: turn stored types into source code for other tools
+*/
+use crate::type_system::*;
+
+mod arrow;
+mod capnp;
+mod fbs;
+mod sql;
+mod site;
+mod treemap;
+pub use arrow::*;
+pub use capnp::*;
+pub use fbs::*;
+pub use sql::*;
+pub use site::*;
+pub use treemap::*;
+
+/// Name given to the i-th member of a compound type in generated code, when it has no name
+pub fn member_name(i : usize) -> String {
+    Field::default_name(i)
+}
+
+/// Emit C11 definitions for the given types plus `_Static_assert`s checking that a C
+/// compiler agrees with the C strategy on sizes, alignments and member offsets.
+/// Atomics reading values of a kind C has a standard type for, with its natural alignment,
+/// become that type, such as `int32_t` or `_Bool`, and other atomics opaque byte arrays
+/// with the right size and alignment, enums using
+/// their minimal representation become GCC packed enums, pointers become `void *`
+/// containers a pointer plus their length and capacity, and strings follow their model,
+/// so the assertions expect a 64 bits target. Structs marked `@packed` or `@aligned`
+/// use GCC attributes, and notes of types and struct members go in comments before them
+/// ## Params
+/// * `manager` - manager storing every type
+/// * `names` - types to emit, every type should come after the types it uses
+/// ---
+/// ## Return
+/// A C translation unit that compiles only if every assertion holds
+pub fn c_layout_check(manager : &TypeManager, names : &[Name]) -> String {
+    let mut code = String::from("#include <stddef.h>\n#include <stdint.h>\n\n");
+
+    for name in names {
+        let my_type = match manager.get(name) {
+            Some(t) => t,
+            None    => continue
+        };
+
+        let size  = Strategy::C.type_size(my_type, manager);
+        let align = Strategy::C.type_align(my_type, manager);
+
+        if let Some(note) = manager.note(name) {
+            code += &format!("/* {} */\n", note);
+        }
+        if let Type::Struct(s) = my_type {
+            for (field, note) in s.fields.iter().filter_map(|f| note(&f.attributes).map(|n| (&f.name, n))) {
+                code += &format!("/* {}.{}: {} */\n", name, field, note);
+            }
+        }
+
+        // type definition
+        match my_type {
+            Type::Atomic(a) => match a.kind.and_then(|k| k.c_type(a.representation)).filter(|_| a.alignment == a.representation) {
+                Some(c_type) => code += &format!("typedef {} {};\n", c_type, name),
+                None => code += &format!("typedef struct {{ _Alignas({}) unsigned char bytes[{}]; }} {};\n", a.alignment, a.representation, name)
+            },
+            Type::Struct(s) => {
+                let fields : Vec<Name> = s.fields
+                                            .iter()
+                                            .map(|f| match attribute_align(&f.attributes) {
+                                                Some(align) => format!("{} __attribute__((aligned({})))", f.identifier(), align),
+                                                None => f.identifier()
+                                            })
+                                            .collect();
+                let mut attributes = vec![];
+                if s.is_packed() {
+                    attributes.push(String::from("packed"));
+                }
+                if let Some(align) = attribute_align(&s.attributes) {
+                    attributes.push(format!("aligned({})", align));
+                }
+                let keyword = match attributes.is_empty() {
+                    true  => String::from("struct"),
+                    false => format!("struct __attribute__(({}))", attributes.join(", "))
+                };
+                code += &c_compound(&keyword, name, &s.members, &fields)
+            },
+            Type::Union(u)  => {
+                let fields : Vec<Name> = (0..u.variants.len()).map(member_name).collect();
+                code += &c_compound("union", name, &u.variants, &fields)
+            },
+            Type::Enum(e)   => {
+                let packed = match e.repr {
+                    EnumRepr::Int     => "",
+                    EnumRepr::Minimal => "__attribute__((packed)) "
+                };
+                let variants : Vec<String> = e.variants.iter().map(|v| format!("{}_{}", name, v)).collect();
+                code += &format!("typedef enum {}{{ {} }} {};\n", packed, variants.join(", "), name)
+            },
+            Type::Pointer(p) => match p.kind {
+                PointerKind::Thin  => code += &format!("typedef void *{};\n", name),
+                PointerKind::Slice => code += &format!("typedef struct {{ void *ptr; size_t len; }} {};\n", name),
+                PointerKind::Trait => code += &format!("typedef struct {{ void *data; const void *vtable; }} {};\n", name)
+            },
+            Type::Container(c) => match c.kind.growable() {
+                true  => code += &format!("typedef struct {{ void *ptr; uint{0}_t len; uint{0}_t cap; }} {1};\n", c.width * 8, name),
+                false => code += &format!("typedef void *{};\n", name)
+            },
+            Type::Text(t) => match t.model {
+                TextModel::Raw(n) => code += &format!("typedef struct {{ char bytes[{}]; }} {};\n", n, name),
+                TextModel::PtrLen => code += &format!("typedef struct {{ const char *ptr; size_t len; }} {};\n", name),
+                TextModel::Sso(n) => code += &format!(
+                    "typedef union {{ struct {{ char *ptr; size_t len; size_t cap; }} largo; struct {{ char buf[{}]; unsigned char etiqueta; }} corto; }} {};\n",
+                    n - 1,
+                    name
+                )
+            }
+        }
+
+        // layout assertions
+        code += &format!("_Static_assert(sizeof({0}) == {1}, \"sizeof({0}) == {1}\");\n", name, size);
+        code += &format!("_Static_assert(_Alignof({0}) == {1}, \"alignof({0}) == {1}\");\n", name, align);
+
+        if let Type::Struct(s) = my_type {
+            for (i, offset) in s.c_offsets(manager).iter().enumerate() {
+                code += &format!(
+                    "_Static_assert(offsetof({0}, {1}) == {2}, \"offsetof({0}, {1}) == {2}\");\n", 
+                    name, 
+                    s.fields[i].identifier(), 
+                    offset
+                );
+            }
+        }
+
+        code += "\n";
+    }
+
+    code
+}
+
+/// C definition for a struct or union with the given member types and names, tagged
+/// and named by a typedef with the same name
+fn c_compound(keyword : &str, name : &Name, members : &[Name], field_names : &[Name]) -> String {
+    let fields : String = members
+                            .iter()
+                            .zip(field_names)
+                            .map(|(t, f)| format!(" {} {};", t, f))
+                            .collect();
+
+    format!("typedef {} {} {{{} }} {};\n", keyword, name, fields, name)
+}
+
+/// How C code names a stored type: structs and unions by their tag, which they have
+/// whether or not a typedef names them too
+fn c_name(my_type : &Type, name : &Name) -> String {
+    match my_type {
+        Type::Struct(_) => format!("struct {}", name),
+        Type::Union(_)  => format!("union {}", name),
+        _ => name.clone()
+    }
+}
+
+/// Emit a C snippet for types defined elsewhere: for each, a designated initializer
+/// setting every member to zero, and `static_assert`s checking its size, its alignment
+/// and the offset of every member under the C strategy, members of nested structs included.
+/// Union members start at 0 and are initialized through their first variant. Structs and
+/// unions are named by their tag, so they may be declared with or without a typedef
+/// ## Params
+/// * `manager` - manager storing every type
+/// * `names` - types to emit code for
+/// ---
+/// ## Return
+/// Code to paste after the types' definitions, or an error if some type does not exist
+pub fn c_snippet(manager : &TypeManager, names : &[Name]) -> Result<String, TypeError> {
+    let mut code = String::from("#include <assert.h>\n#include <stddef.h>\n");
+
+    for name in names {
+        let my_type = manager.get(name).ok_or_else(|| TypeError::TypeDoesNotExist(name.clone()))?;
+        let c_type = c_name(my_type, name);
+        code += &format!("\nstatic const {} {}_inicial = {};\n\n", c_type, name, c_initializer(manager, my_type, 0));
+
+        let size  = Strategy::C.type_size(my_type, manager);
+        let align = Strategy::C.type_align(my_type, manager);
+        code += &format!("static_assert(sizeof({0}) == {1}, \"sizeof({0}) == {1}\");\n", c_type, size);
+        code += &format!("static_assert(_Alignof({0}) == {1}, \"alignof({0}) == {1}\");\n", c_type, align);
+        for (path, offset) in c_member_offsets(manager, my_type, "", 0) {
+            code += &format!("static_assert(offsetof({0}, {1}) == {2}, \"offsetof({0}, {1}) == {2}\");\n", c_type, path, offset);
+        }
+    }
+
+    Ok(code)
+}
+
+/// Zero initializer for a value of some type, designated for structs and unions
+/// ## Params
+/// * `depth` - nesting level, for indentation
+fn c_initializer(manager : &TypeManager, my_type : &Type, depth : usize) -> String {
+    let fields : Vec<(Name, &Name)> = match my_type {
+        Type::Struct(s) => s.fields.iter().map(Field::identifier).zip(&s.members).collect(),
+        Type::Union(u)  => u.variants.first().map(|v| (member_name(0), v)).into_iter().collect(),
+        Type::Atomic(a) => return String::from(match a.kind {
+            Some(ValueKind::Float) => "0.0",
+            Some(ValueKind::Char)  => "'\\0'",
+            _ => "0"
+        }),
+        Type::Pointer(p) if p.kind == PointerKind::Thin => return String::from("NULL"),
+        Type::Container(c) if !c.kind.growable()        => return String::from("NULL"),
+        Type::Enum(_) => return String::from("0"),
+        _ => return String::from("{0}")
+    };
+
+    let indent = "    ".repeat(depth + 1);
+    let members : Vec<String> = fields
+                                    .iter()
+                                    .map(|(field, member)| match manager.get(member) {
+                                        Some(t) => format!("{}.{} = {}", indent, field, c_initializer(manager, t, depth + 1)),
+                                        None    => format!("{}.{} = {{0}}", indent, field)
+                                    })
+                                    .collect();
+
+    format!("{{\n{}\n{}}}", members.join(",\n"), "    ".repeat(depth))
+}
+
+/// Offset of every struct member under the C strategy, going into nested structs
+/// ## Return
+/// Member designator, such as `cabecera.largo`, and offset from the outermost type
+fn c_member_offsets(manager : &TypeManager, my_type : &Type, prefix : &str, base : usize) -> Vec<(String, usize)> {
+    let s = match my_type {
+        Type::Struct(s) => s,
+        _ => return vec![]
+    };
+
+    let mut offsets = vec![];
+    for ((member, field), offset) in s.members.iter().zip(&s.fields).zip(s.c_offsets(manager)) {
+        let path = format!("{}{}", prefix, field.identifier());
+        offsets.push((path.clone(), base + offset));
+        if let Some(t) = manager.get(member) {
+            offsets.extend(c_member_offsets(manager, t, &format!("{}.", path), base + offset));
+        }
+    }
+
+    offsets
+}
+
+/// Emit a Rust test module checking that `#[repr(C)]` types defined elsewhere agree
+/// with the C strategy, using `core::mem::{size_of, align_of}` and `memoffset::offset_of!`
+/// for the members of structs. The types must be in scope where the module is included.
+/// Tests come with the note of their type and the `repr` a `@packed` or `@aligned` struct needs
+/// ## Params
+/// * `manager` - manager storing every type
+/// * `names` - types to check
+/// ---
+/// ## Return
+/// Rust source with a test per type
+pub fn rust_layout_test(manager : &TypeManager, names : &[Name]) -> String {
+    let mut code = String::from("// Generado por TypeManager, los tipos deben estar en alcance\n");
+    code += "\nuse core::mem::{align_of, size_of};\nuse memoffset::offset_of;\n";
+
+    for name in names {
+        let my_type = match manager.get(name) {
+            Some(t) => t,
+            None    => continue
+        };
+
+        code += "\n";
+        if let Some(note) = manager.note(name) {
+            code += &format!("/// {}\n", note);
+        }
+        if let Some(repr) = rust_repr(my_type) {
+            code += &format!("/// `{}` se declara con {}\n", name, repr);
+        }
+        // an attribute per test, an inner one would break the module once included
+        code += &format!("#[test]\n#[allow(non_snake_case)]\nfn disposicion_{}() {{\n", name);
+        code += &format!("    assert_eq!(size_of::<{}>(), {});\n", name, Strategy::C.type_size(my_type, manager));
+        code += &format!("    assert_eq!(align_of::<{}>(), {});\n", name, Strategy::C.type_align(my_type, manager));
+        if let Type::Struct(s) = my_type {
+            for (field, offset) in s.fields.iter().zip(s.c_offsets(manager)) {
+                code += &format!("    assert_eq!(offset_of!({}, {}), {});\n", name, field.identifier(), offset);
+            }
+        }
+        code += "}\n";
+    }
+
+    code
+}
+
+/// How Rust declares a struct with the layout its `@packed` and `@aligned` attributes ask for.
+/// Rust can't have both on a type, so a packed struct asking for an alignment goes inside an aligned one
+/// ## Return
+/// The `repr` attributes, or none if the type is not a struct or `#[repr(C)]` is enough
+fn rust_repr(my_type : &Type) -> Option<String> {
+    let s = match my_type {
+        Type::Struct(s) => s,
+        _ => return None
+    };
+
+    match (s.is_packed(), attribute_align(&s.attributes)) {
+        (false, None) => None,
+        (true, None)  => Some(String::from("`#[repr(C, packed)]`")),
+        (false, Some(align)) => Some(format!("`#[repr(C, align({}))]`", align)),
+        (true, Some(align))  => Some(format!("`#[repr(C, packed)]` dentro de un `#[repr(C, align({}))]`", align))
+    }
+}
@@ -0,0 +1,275 @@
+/*
+    Documentation sites: static HTML pages to browse the types of a session,
+    like rustdoc for memory layouts. An index with every type and its sizes,
+    a page per type with its report, the members of structs with their offsets
+    and diagrams of their layouts, a page with the dependency graph, and links
+    from every type to the types it uses and the types using it
+*/
+use std::collections::{HashMap, HashSet};
+
+use crate::type_system::*;
+use crate::image;
+
+/// Page every site starts from
+pub const INDEX : &str = "index.html";
+
+/// Page with the dependency graph
+const GRAPH : &str = "grafo.html";
+
+/// Dependency graph for Graphviz
+const DOT : &str = "grafo.dot";
+
+/// Style shared by every page
+const STYLE : &str = "estilo.css";
+
+const CSS : &str = "body { font-family: sans-serif; max-width: 60em; margin: 2em auto; padding: 0 1em; color: #222; }
+nav a { margin-right: 1em; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; }
+td.n { text-align: right; font-family: monospace; }
+pre { background: #f6f6f6; padding: 1em; }
+figure { margin: 1em 0; }
+.nivel { margin: 1em 0; padding: 0.5em 1em; border-left: 4px solid #8dd3c7; }
+";
+
+/// A file of a site: its path inside the site's directory and its contents
+pub type SiteFile = (String, Vec<u8>);
+
+/// Generate a documentation site for some types
+/// ## Params
+/// * `manager` - manager storing every type
+/// * `names` - types to document, every type should come after the types it uses
+/// ---
+/// ## Return
+/// Every file of the site, the index first
+pub fn site(manager : &TypeManager, names : &[Name]) -> Vec<SiteFile> {
+    let mut users : HashMap<&Name, Vec<&Name>> = HashMap::new();
+    for name in names {
+        if let Some(t) = manager.get(name) {
+            for reference in t.references().into_iter().filter_map(|r| manager.resolve(r)) {
+                let list = users.entry(reference).or_default();
+                if !list.contains(&name) {
+                    list.push(name);
+                }
+            }
+        }
+    }
+
+    let mut files = vec![(INDEX.to_string(), index(manager, names).into_bytes())];
+    for name in names {
+        let t = match manager.get(name) {
+            Some(t) => t,
+            None => continue
+        };
+        let used_by = users.get(name).map(Vec::as_slice).unwrap_or_default();
+        let mut diagrams = vec![];
+        for strategy in [Strategy::C, Strategy::Optimized] {
+            if let Ok(diagram) = manager.diagram(name, strategy) {
+                let file = format!("{}-{}.png", slug(name), strategy.id());
+                files.push((file.clone(), image::draw(&diagram).png()));
+                diagrams.push((strategy, file));
+            }
+        }
+        files.push((page(name), type_page(manager, name, t, used_by, &diagrams).into_bytes()));
+    }
+    files.push((GRAPH.to_string(), graph(manager, names).into_bytes()));
+    files.push((DOT.to_string(), dot(manager, names).into_bytes()));
+    files.push((STYLE.to_string(), CSS.as_bytes().to_vec()));
+
+    files
+}
+
+/// Types a type refers to, each once
+fn uses(t : &Type) -> Vec<&Name> {
+    let mut uses = t.references();
+    let mut seen = HashSet::new();
+    uses.retain(|n| seen.insert(*n));
+    uses
+}
+
+/// Page of a type
+fn page(name : &str) -> String {
+    format!("{}.html", slug(name))
+}
+
+/// Name of a type made safe for file names: letters, digits and `_` are kept, and
+/// anything else becomes `-` and its code in hex
+fn slug(name : &str) -> String {
+    let mut slug = String::from("tipo-");
+    for c in name.chars() {
+        match c.is_ascii_alphanumeric() || c == '_' {
+            true  => slug.push(c),
+            false => slug += &format!("-{:x}", c as u32)
+        }
+    }
+    slug
+}
+
+/// Text escaped for HTML
+pub(super) fn escape(text : &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&#39;")
+}
+
+/// Link to the page of a type, just its name if it's not stored
+fn link(manager : &TypeManager, name : &str) -> String {
+    match manager.resolve(&name.to_string()) {
+        Some(stored) => format!("<a href=\"{}\">{}</a>", page(stored), escape(name)),
+        None => escape(name)
+    }
+}
+
+/// Links to several types separated by commas, or a dash if there are none
+fn links<'a, I>(manager : &TypeManager, names : I) -> String
+    where
+        I: IntoIterator<Item = &'a Name>
+{
+    let links : Vec<String> = names.into_iter().map(|n| link(manager, n)).collect();
+    match links.is_empty() {
+        true  => String::from("—"),
+        false => links.join(", ")
+    }
+}
+
+/// Start of every page, up to its heading
+fn header(title : &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"es\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<link rel=\"stylesheet\" href=\"{}\">\n</head>\n<body>\n<nav><a href=\"{}\">Índice</a><a href=\"{}\">Grafo de dependencias</a></nav>\n<h1>{}</h1>\n",
+        escape(title), STYLE, INDEX, GRAPH, escape(title)
+    )
+}
+
+/// End of every page
+const FOOTER : &str = "<footer><p>Generado por TypeManager</p></footer>\n</body>\n</html>\n";
+
+/// Index: every type with its kind and its size under every strategy
+fn index(manager : &TypeManager, names : &[Name]) -> String {
+    let mut html = header("Tipos");
+    if names.is_empty() {
+        html += "<p>No hay tipos definidos</p>\n";
+        return html + FOOTER
+    }
+
+    let mut sorted : Vec<&Name> = names.iter().collect();
+    sorted.sort();
+    html += &format!("<p>{} tipos</p>\n<table>\n<tr><th>Nombre</th><th>Tipo</th>", names.len());
+    for strategy in Strategy::ALL.iter() {
+        html += &format!("<th>{}</th>", strategy.name());
+    }
+    html += "</tr>\n";
+    for name in sorted {
+        let t = match manager.get(name) {
+            Some(t) => t,
+            None => continue
+        };
+        html += &format!("<tr><td>{}</td><td>{}</td>", link(manager, name), t.kind_name());
+        for strategy in Strategy::ALL.iter() {
+            html += &format!("<td class=\"n\">{}</td>", strategy.type_size(t, manager));
+        }
+        html += "</tr>\n";
+    }
+
+    html + "</table>\n" + FOOTER
+}
+
+/// Page of a type: its note, its report, members and diagrams for structs, and the types it uses and using it
+fn type_page(manager : &TypeManager, name : &Name, t : &Type, used_by : &[&Name], diagrams : &[(Strategy, String)]) -> String {
+    let mut html = header(name);
+    html += &format!("<p>{}", t.kind_name());
+    if let Some(origin) = manager.origin(name) {
+        html += &format!(", definido en <code>{}</code>", escape(origin));
+    }
+    html += "</p>\n";
+    if let Some(note) = manager.note(name) {
+        html += &format!("<p>{}</p>\n", escape(note));
+    }
+    html += &format!("<pre>{}</pre>\n", escape(&t.report(manager).display()));
+
+    if let Type::Struct(s) = t {
+        html += "<h2>Miembros</h2>\n<table>\n<tr><th>#</th><th>Campo</th><th>Tipo</th><th>Desplazamiento (C)</th><th>Tamaño</th><th>Nota</th></tr>\n";
+        for m in s.layout(manager, Strategy::C) {
+            html += &format!(
+                "<tr><td class=\"n\">{}</td><td>{}</td><td>{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td><td>{}</td></tr>\n",
+                m.index, escape(&s.fields[m.index].name), link(manager, &s.members[m.index]), m.offset, m.size,
+                escape(note(&s.fields[m.index].attributes).unwrap_or_default())
+            );
+        }
+        html += "</table>\n";
+    }
+    if let Type::Union(u) = t {
+        html += &format!("<h2>Variantes</h2>\n<p>{}</p>\n", links(manager, &u.variants));
+    }
+
+    for (strategy, file) in diagrams {
+        html += &format!(
+            "<figure><img src=\"{}\" alt=\"Disposición {}\"><figcaption>Disposición {}</figcaption></figure>\n",
+            file, strategy.name(), strategy.name()
+        );
+    }
+
+    html += &format!("<h2>Usa</h2>\n<p>{}</p>\n", links(manager, uses(t)));
+    html += &format!("<h2>Usado por</h2>\n<p>{}</p>\n", links(manager, used_by.iter().copied()));
+    html + FOOTER
+}
+
+/// Depth of every type in the dependency graph: 0 for types built on no other, one more
+/// than their deepest dependency for the rest
+fn levels<'a>(manager : &TypeManager, names : &'a [Name]) -> Vec<Vec<&'a Name>> {
+    let mut depth : HashMap<&Name, usize> = HashMap::new();
+    let mut levels : Vec<Vec<&Name>> = vec![];
+    for name in names {
+        let level = manager
+                        .get(name)
+                        .map(|t| t.dependencies().iter().filter_map(|d| manager.resolve(d)).filter_map(|d| depth.get(d)).map(|l| l + 1).max().unwrap_or(0))
+                        .unwrap_or(0);
+        depth.insert(name, level);
+        if levels.len() <= level {
+            levels.resize(level + 1, vec![]);
+        }
+        levels[level].push(name);
+    }
+    levels
+}
+
+/// Page with the dependency graph, by levels: every type with the types it uses, all in earlier levels
+fn graph(manager : &TypeManager, names : &[Name]) -> String {
+    let mut html = header("Grafo de dependencias");
+    html += &format!(
+        "<p>Cada tipo está un nivel por encima del tipo más profundo que usa como miembro o variante. \
+         Los punteros y contenedores pueden apuntar a cualquier nivel. También en <a href=\"{}\">Graphviz</a>.</p>\n",
+        DOT
+    );
+    for (level, types) in levels(manager, names).iter().enumerate() {
+        html += &format!("<div class=\"nivel\">\n<h2>Nivel {}</h2>\n<ul>\n", level);
+        for name in types {
+            let references = manager.get(name).map(uses).unwrap_or_default();
+            match references.is_empty() {
+                true  => html += &format!("<li>{}</li>\n", link(manager, name)),
+                false => html += &format!("<li>{} → {}</li>\n", link(manager, name), links(manager, references))
+            }
+        }
+        html += "</ul>\n</div>\n";
+    }
+
+    html + FOOTER
+}
+
+/// Dependency graph for Graphviz, pointers and containers as dashed edges
+fn dot(manager : &TypeManager, names : &[Name]) -> String {
+    let mut dot = String::from("digraph tipos {\n    rankdir=BT;\n    node [shape=box];\n");
+    for name in names {
+        let t = match manager.get(name) {
+            Some(t) => t,
+            None => continue
+        };
+        dot += &format!("    \"{}\" [URL=\"{}\"];\n", name, page(name));
+        let style = match t {
+            Type::Pointer(_) | Type::Container(_) => " [style=dashed]",
+            _ => ""
+        };
+        for reference in uses(t) {
+            dot += &format!("    \"{}\" -> \"{}\"{};\n", name, reference, style);
+        }
+    }
+
+    dot + "}\n"
+}
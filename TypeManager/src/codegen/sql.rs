@@ -0,0 +1,248 @@
+/*
+    SQL tables. A database row is laid out with the same kind of math as a struct:
+    a header, a bitmap telling which columns are null and then the values, padded
+    to their alignment in some engines. So every table comes with an estimate of
+    what a row takes on disk
+*/
+use crate::type_system::*;
+
+/// Database engine whose types and row format are used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    Postgres,   // heap tuples: 23 bytes of header, values aligned to their type
+    Mysql,      // InnoDB with the dynamic row format, no padding
+    Sqlite      // records: a header with a varint per column, no padding
+}
+
+/// A column of a table and what it takes in a row
+struct Column {
+    name:     String,
+    sql:      String,
+    size:     usize,    // bytes of a value, the maximum length for variable ones
+    align:    usize,    // alignment of a value in a PostgreSQL row
+    variable: bool      // stored along with its length
+}
+
+impl Engine {
+
+    /// Find an engine by name
+    pub fn parse(name : &str) -> Option<Engine> {
+        match name.to_lowercase().as_str() {
+            "postgres" | "postgresql" => Some(Engine::Postgres),
+            "mysql" | "innodb"        => Some(Engine::Mysql),
+            "sqlite"                  => Some(Engine::Sqlite),
+            _ => None
+        }
+    }
+
+    /// Name used in commands
+    pub fn name(self) -> &'static str {
+        match self {
+            Engine::Postgres => "postgres",
+            Engine::Mysql    => "mysql",
+            Engine::Sqlite   => "sqlite"
+        }
+    }
+
+    /// Column holding a number of 1, 2, 4 or 8 bytes, or a boolean of 1
+    fn number(self, name : String, size : usize, kind : Option<ValueKind>) -> Column {
+        let float = kind == Some(ValueKind::Float);
+        let (sql, size, align) = match (self, kind) {
+            (Engine::Postgres, Some(ValueKind::Bool)) | (Engine::Mysql, Some(ValueKind::Bool)) if size == 1 => ("BOOLEAN", 1, 1),
+            _ => self.number_type(size, float)
+        };
+
+        let sql = match self == Engine::Mysql && kind == Some(ValueKind::Unsigned) {
+            true  => format!("{} UNSIGNED", sql),
+            false => sql.to_string()
+        };
+        Column { name, sql, size, align, variable: false }
+    }
+
+    /// Type of a column holding a number of 1, 2, 4 or 8 bytes, with its size and alignment
+    fn number_type(self, size : usize, float : bool) -> (&'static str, usize, usize) {
+        match self {
+            Engine::Postgres => match (size, float) {
+                (4, true) => ("REAL", 4, 4),
+                (8, true) => ("DOUBLE PRECISION", 8, 8),
+                (1, _) | (2, _) => ("SMALLINT", 2, 2),
+                (4, _) => ("INTEGER", 4, 4),
+                _ => ("BIGINT", 8, 8)
+            },
+            Engine::Mysql => match (size, float) {
+                (4, true) => ("FLOAT", 4, 1),
+                (8, true) => ("DOUBLE", 8, 1),
+                (1, _) => ("TINYINT", 1, 1),
+                (2, _) => ("SMALLINT", 2, 1),
+                (4, _) => ("INT", 4, 1),
+                _ => ("BIGINT", 8, 1)
+            },
+            Engine::Sqlite => match float {
+                true  => ("REAL", 8, 1),
+                false => ("INTEGER", size, 1)
+            }
+        }
+    }
+
+    /// Column holding bytes or text
+    /// ## Params
+    /// * `size` - length of every value, or the maximum length of variable ones. 0 if unknown
+    /// * `fixed` - whether every value has that length
+    fn bytes(self, name : String, size : usize, text : bool, fixed : bool) -> Column {
+        let (sql, variable) = match (self, text) {
+            (Engine::Postgres, true) | (Engine::Mysql, true) if size > 0 => (format!("VARCHAR({})", size), true),
+            (Engine::Postgres, false) => (String::from("BYTEA"), true),
+            (Engine::Mysql, false) if fixed && size <= 255 => (format!("BINARY({})", size), false),
+            (Engine::Mysql, false) if size > 0 && size <= 65535 => (format!("VARBINARY({})", size), true),
+            (Engine::Mysql, false) | (Engine::Sqlite, false) => (String::from("BLOB"), true),
+            _ => (String::from("TEXT"), true)
+        };
+
+        Column { name, sql, size, align: 1, variable }
+    }
+
+    /// Quote an identifier
+    fn quote(self, name : &str) -> String {
+        match self {
+            Engine::Mysql => format!("`{}`", name),
+            _ => format!("\"{}\"", name)
+        }
+    }
+}
+
+/// Emit `CREATE TABLE` statements for the structs in the given types, a column per
+/// member. Members that are structs are flattened into columns named `campo_subcampo`,
+/// atomics of 1, 2, 4 or 8 bytes become numbers, strings text columns, and pointers
+/// and boxes a `BIGINT` key. Every table comes with an estimate of the bytes a row
+/// takes in the engine, assuming every column is nullable and variable length
+/// columns hold their maximum length
+/// ## Params
+/// * `manager` - manager storing every type
+/// * `names` - types to emit
+/// * `engine` - database engine for types and the row estimate
+/// ---
+/// ## Return
+/// A SQL script
+pub fn sql_schema(manager : &TypeManager, names : &[Name], engine : Engine) -> String {
+    let mut code = format!("-- Generado por TypeManager para {}\n", engine.name());
+
+    for name in names {
+        let s = match manager.get(name) {
+            Some(Type::Struct(s)) => s,
+            _ => continue
+        };
+
+        let mut columns = vec![];
+        for (field, member) in s.fields.iter().zip(&s.members) {
+            sql_columns(manager, engine, field.name.clone(), member, &mut columns);
+        }
+
+        code += &format!("\n-- {}: {} bytes en C\n", name, s.c_size(manager));
+        code += &row_estimate(engine, &columns);
+        let definitions : Vec<String> = columns.iter().map(|c| format!("    {} {}", engine.quote(&c.name), c.sql)).collect();
+        code += &format!("CREATE TABLE {} (\n{}\n);\n", engine.quote(name), definitions.join(",\n"));
+    }
+
+    code
+}
+
+/// Add the columns for a member, several if it's a struct
+fn sql_columns(manager : &TypeManager, engine : Engine, name : String, member : &Name, columns : &mut Vec<Column>) {
+    let column = match manager.get(member) {
+        Some(Type::Struct(s)) => {
+            for (field, inner) in s.fields.iter().zip(&s.members) {
+                sql_columns(manager, engine, format!("{}_{}", name, field.name), inner, columns);
+            }
+            return
+        },
+        Some(Type::Atomic(a)) => match a.representation {
+            1 | 2 | 4 | 8 => engine.number(name, a.representation, a.kind),
+            n => engine.bytes(name, n, false, true)
+        },
+        Some(t @ Type::Enum(_)) => engine.number(name, Strategy::C.type_size(t, manager), Some(ValueKind::Unsigned)),
+        Some(t @ Type::Union(_)) => engine.bytes(name, Strategy::C.type_size(t, manager), false, true),
+        Some(Type::Text(t)) => match t.model {
+            TextModel::Raw(n) => engine.bytes(name, n, true, false),
+            _ => engine.bytes(name, 0, true, false)
+        },
+        Some(Type::Container(c)) => match c.kind {
+            ContainerKind::String => engine.bytes(name, c.capacity, true, false),
+            ContainerKind::Vec => {
+                let element = c.element.as_ref().and_then(|e| manager.get(e)).map_or(0, |t| Strategy::C.type_size(t, manager));
+                engine.bytes(name, element * c.capacity, false, false)
+            },
+            ContainerKind::Box => engine.number(name, 8, Some(ValueKind::Signed))
+        },
+        Some(Type::Pointer(_)) | None => engine.number(name, 8, Some(ValueKind::Signed))
+    };
+
+    columns.push(column)
+}
+
+/// Comment with the bytes a row takes in an engine
+fn row_estimate(engine : Engine, columns : &[Column]) -> String {
+    let nulls = columns.len().div_ceil(8);
+    let values : usize = columns.iter().map(|c| c.size).sum();
+    let mut code = match engine {
+        Engine::Postgres => {
+            // values start after a header padded to 8 bytes, short variable values have a byte of length
+            let mut data : usize = 0;
+            let mut padding = 0;
+            for c in columns {
+                let (align, size) = match (c.variable, c.size <= 126) {
+                    (true, true)  => (1, c.size + 1),
+                    (true, false) => (4, c.size + 4),
+                    (false, _)    => (c.align, c.size)
+                };
+                let start = data.next_multiple_of(align);
+                padding += start - data;
+                data = start + size;
+            }
+
+            let row = |header : usize| header.next_multiple_of(8) + data.next_multiple_of(8) + 4;
+            format!(
+                "-- fila en postgres: {} bytes si alguna columna es nula, {} si no. Cabecera de 23 bytes, {} del mapa de nulos, {} de datos con {} de relleno y 4 del puntero de línea\n",
+                row(23 + nulls), row(23), nulls, data, padding
+            )
+        },
+        Engine::Mysql => {
+            let lengths : usize = columns.iter().filter(|c| c.variable).map(|c| if c.size > 255 { 2 } else { 1 }).sum();
+            format!(
+                "-- fila en mysql: {} bytes. Cabecera de 5 bytes, {} del mapa de nulos, {} de longitudes, 19 de columnas ocultas y {} de datos, sin relleno\n",
+                5 + nulls + lengths + 19 + values, nulls, lengths, values
+            )
+        },
+        Engine::Sqlite => {
+            // a varint per column with its type and length, after a varint with the size of the header
+            let types : usize = columns.iter().map(|c| match c.variable {
+                true  => varint_size(2 * c.size + 13),
+                false => 1
+            }).sum();
+            let header = types + varint_size(types + 1);
+            let record = header + values;
+            let cell = record + varint_size(record) + 2;
+            format!(
+                "-- fila en sqlite: entre {} y {} bytes, según el rowid. Cabecera del registro de {} bytes, {} de datos como mucho, sin relleno, y 2 del puntero de celda\n",
+                cell + 1, cell + 9, header, values
+            )
+        }
+    };
+
+    if columns.iter().any(|c| c.variable) {
+        code += "-- las columnas de longitud variable cuentan con su longitud máxima\n";
+    }
+
+    code
+}
+
+/// Bytes taken by a number written as a SQLite varint
+fn varint_size(n : usize) -> usize {
+    let mut size = 1;
+    let mut rest = n >> 7;
+    while rest > 0 && size < 9 {
+        size += 1;
+        rest >>= 7;
+    }
+
+    size
+}
@@ -0,0 +1,222 @@
+/*
+    Treemaps: every type as a rectangle whose area is its C size, with the
+    bytes it wastes in padding, its own and that of its members, hatched at
+    its bottom. Rectangles are laid out as squarified treemaps are, biggest
+    first, rows along the shorter free side while that keeps them close to
+    squares, so a whole imported codebase fits in a picture telling where
+    memory goes to padding
+*/
+use super::site::escape;
+use crate::type_system::*;
+
+/// Size of the picture, in pixels
+const WIDTH : f64 = 960.0;
+const HEIGHT : f64 = 600.0;
+
+/// Smallest rectangle a name is written in, in pixels
+const LABEL_WIDTH : f64 = 48.0;
+const LABEL_HEIGHT : f64 = 16.0;
+
+/// Types with most padding listed in pages
+const WORST : usize = 20;
+
+/// A type in the treemap
+struct Tile<'a> {
+    name:    &'a Name,
+    kind:    &'static str,
+    size:    usize,
+    padding: usize
+}
+
+/// A rectangle, in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rect {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64
+}
+
+/// Color of the rectangles of a kind of type
+fn color(kind : &str) -> &'static str {
+    match kind {
+        "struct"     => "#8dd3c7",
+        "variante"   => "#bebada",
+        "enum"       => "#fb8072",
+        "puntero"    => "#80b1d3",
+        "contenedor" => "#fdb462",
+        "cadena"     => "#b3de69",
+        _            => "#d9d9d9"
+    }
+}
+
+/// Every type that takes some room, biggest first and by name when as big
+fn tiles<'a>(manager : &TypeManager, names : &'a [Name]) -> Vec<Tile<'a>> {
+    let mut tiles : Vec<Tile> = names
+                                    .iter()
+                                    .filter_map(|name| {
+                                        let t = manager.get(name)?;
+                                        let size = manager.size(name, Strategy::C).ok().filter(|s| *s > 0)?;
+                                        let padding = manager.loss(name, Strategy::C).unwrap_or(0);
+                                        Some(Tile { name, kind: t.kind_name(), size, padding })
+                                    })
+                                    .collect();
+    tiles.sort_by(|a, b| b.size.cmp(&a.size).then(a.name.cmp(b.name)));
+    tiles
+}
+
+/// Worst aspect ratio of the rectangles of a row
+/// ## Params
+/// * `row` - areas in the row
+/// * `side` - length of the side the row lies along
+fn worst(row : &[f64], side : f64) -> f64 {
+    let sum : f64 = row.iter().sum();
+    let max = row.iter().cloned().fold(0.0, f64::max);
+    let min = row.iter().cloned().fold(f64::INFINITY, f64::min);
+    let (side, sum) = (side * side, sum * sum);
+    (side * max / sum).max(sum / (side * min))
+}
+
+/// Lay out areas in a rectangle as a squarified treemap
+/// ## Params
+/// * `areas` - area of every rectangle, biggest first, adding up to the area of `free`
+/// * `free` - rectangle to fill
+/// ---
+/// ## Return
+/// A rectangle per area, in the same order
+fn squarify(areas : &[f64], mut free : Rect) -> Vec<Rect> {
+    let mut rects = Vec::with_capacity(areas.len());
+    let mut start = 0;
+    while start < areas.len() {
+        let side = free.w.min(free.h);
+        let mut end = start + 1;
+        while end < areas.len() && worst(&areas[start..=end], side) <= worst(&areas[start..end], side) {
+            end += 1;
+        }
+
+        // the row lies along the shorter side, as thick as its area needs
+        let thickness = areas[start..end].iter().sum::<f64>() / side;
+        let mut offset = 0.0;
+        for area in &areas[start..end] {
+            let length = area / thickness;
+            rects.push(match free.w >= free.h {
+                true  => Rect { x: free.x, y: free.y + offset, w: thickness, h: length },
+                false => Rect { x: free.x + offset, y: free.y, w: length, h: thickness }
+            });
+            offset += length;
+        }
+        free = match free.w >= free.h {
+            true  => Rect { x: free.x + thickness, w: (free.w - thickness).max(0.0), ..free },
+            false => Rect { y: free.y + thickness, h: (free.h - thickness).max(0.0), ..free }
+        };
+        start = end;
+    }
+
+    rects
+}
+
+/// Part of some bytes, as a percentage
+fn percent(part : usize, total : usize) -> f64 {
+    match total {
+        0 => 0.0,
+        total => part as f64 * 100.0 / total as f64
+    }
+}
+
+/// Draw the treemap of some types as an SVG image
+/// ## Params
+/// * `manager` - manager storing every type
+/// * `names` - types to draw, types not taking any room are left out
+/// ---
+/// ## Return
+/// An SVG document, with the name, size and padding of every type in its tooltip
+pub fn treemap(manager : &TypeManager, names : &[Name]) -> String {
+    let tiles = tiles(manager, names);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"sans-serif\" font-size=\"11\">\n",
+        WIDTH, HEIGHT, WIDTH, HEIGHT
+    );
+    svg += "<defs><pattern id=\"relleno\" width=\"6\" height=\"6\" patternUnits=\"userSpaceOnUse\" patternTransform=\"rotate(45)\">\
+            <rect width=\"6\" height=\"6\" fill=\"#eeeeee\"/><line x1=\"0\" y1=\"0\" x2=\"0\" y2=\"6\" stroke=\"#888888\" stroke-width=\"2\"/></pattern></defs>\n";
+    if tiles.is_empty() {
+        svg += &format!("<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">No hay tipos definidos</text>\n", WIDTH / 2.0, HEIGHT / 2.0);
+        return svg + "</svg>\n"
+    }
+
+    let total : usize = tiles.iter().map(|t| t.size).sum();
+    let padding : usize = tiles.iter().map(|t| t.padding).sum();
+    svg += &format!(
+        "<title>{} tipos, {} bytes, {} de relleno ({:.1}%)</title>\n",
+        tiles.len(), total, padding, percent(padding, total)
+    );
+
+    let scale = WIDTH * HEIGHT / total as f64;
+    let areas : Vec<f64> = tiles.iter().map(|t| t.size as f64 * scale).collect();
+    for (tile, r) in tiles.iter().zip(squarify(&areas, Rect { x: 0.0, y: 0.0, w: WIDTH, h: HEIGHT })) {
+        svg += &format!(
+            "<g><title>{}: {} bytes, {} de relleno ({:.1}%)</title>\n<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" fill=\"{}\" stroke=\"#ffffff\"/>\n",
+            escape(tile.name), tile.size, tile.padding, percent(tile.padding, tile.size), r.x, r.y, r.w, r.h, color(tile.kind)
+        );
+        if tile.padding > 0 {
+            let h = r.h * tile.padding as f64 / tile.size as f64;
+            svg += &format!(
+                "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" fill=\"url(#relleno)\" stroke=\"#ffffff\"/>\n",
+                r.x, r.y + r.h - h, r.w, h
+            );
+        }
+        if r.w >= LABEL_WIDTH && r.h >= LABEL_HEIGHT {
+            svg += &format!("<text x=\"{:.2}\" y=\"{:.2}\">{}</text>\n", r.x + 4.0, r.y + 13.0, escape(tile.name));
+        }
+        svg += "</g>\n";
+    }
+
+    svg + "</svg>\n"
+}
+
+/// Page with the treemap of some types, how much of their memory is padding and
+/// the types wasting the most
+/// ## Params
+/// * `manager` - manager storing every type
+/// * `names` - types to draw
+/// ---
+/// ## Return
+/// An HTML page with the treemap inline
+pub fn treemap_page(manager : &TypeManager, names : &[Name]) -> String {
+    let tiles = tiles(manager, names);
+    let total : usize = tiles.iter().map(|t| t.size).sum();
+    let padding : usize = tiles.iter().map(|t| t.padding).sum();
+
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html lang=\"es\">\n<head>\n<meta charset=\"utf-8\">\n<title>Relleno por tipo</title>\n<style>\n\
+         body { font-family: sans-serif; margin: 2em; color: #222; }\n\
+         table { border-collapse: collapse; margin: 1em 0; }\n\
+         th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; }\n\
+         td.n { text-align: right; font-family: monospace; }\n\
+         .muestra { display: inline-block; width: 1em; height: 1em; margin: 0 0.3em 0 1em; vertical-align: middle; }\n\
+         </style>\n</head>\n<body>\n<h1>Relleno por tipo</h1>\n"
+    );
+    html += &format!(
+        "<p>{} tipos, {} bytes en C, {} de relleno ({:.1}%). El área de cada tipo es su tamaño y su relleno está rayado abajo.</p>\n<p>",
+        tiles.len(), total, padding, percent(padding, total)
+    );
+    for kind in ["struct", "variante", "enum", "puntero", "contenedor", "cadena", "atómico"] {
+        html += &format!("<span class=\"muestra\" style=\"background: {}\"></span>{}", color(kind), kind);
+    }
+    html += "</p>\n";
+    html += &treemap(manager, names);
+
+    let mut wasteful : Vec<&Tile> = tiles.iter().filter(|t| t.padding > 0).collect();
+    wasteful.sort_by(|a, b| b.padding.cmp(&a.padding).then(a.name.cmp(b.name)));
+    if !wasteful.is_empty() {
+        html += "<h2>Tipos con más relleno</h2>\n<table>\n<tr><th>Tipo</th><th>Tamaño</th><th>Relleno</th><th>%</th></tr>\n";
+        for tile in wasteful.into_iter().take(WORST) {
+            html += &format!(
+                "<tr><td>{}</td><td class=\"n\">{}</td><td class=\"n\">{}</td><td class=\"n\">{:.1}</td></tr>\n",
+                escape(tile.name), tile.size, tile.padding, percent(tile.padding, tile.size)
+            );
+        }
+        html += "</table>\n";
+    }
+
+    html + "<footer><p>Generado por TypeManager</p></footer>\n</body>\n</html>\n"
+}
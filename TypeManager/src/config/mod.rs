@@ -0,0 +1,306 @@
+/*
+    User configuration, read from a simple `clave = valor` file
+*/
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::utils::table::TableStyle;
+use crate::utils::number::NumberFormat;
+use crate::driver::{Keywords, Verb};
+use crate::type_system::{BitfieldPolicy, EnumRepr, Limits, Objective};
+
+/// Name for the config file, looked up in the working directory first
+pub const CONFIG_FILE : &str = "typemanager.conf";
+
+/// Default definitions file loaded at startup
+pub const DEFAULT_DEFINITIONS : &str = "types.tm";
+
+/// Default file where the session is saved after every change
+pub const DEFAULT_RECOVERY : &str = ".typemanager_recuperacion.tm";
+
+/// Default prompt for the REPL
+pub const DEFAULT_PROMPT : &str = ">> ";
+
+/// Fields that can be shown in the prompt, written between braces: `{n}`
+pub const PROMPT_FIELDS : [&str; 5] = [
+    "n",        // amount of defined types
+    "macros",   // amount of defined macros
+    "cambios",  // `*` if there are changes not saved to the recovery file
+    "sesion",   // name of the session in use
+    "abi"       // target machine of the session in use
+];
+
+/// Every configurable option
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub definitions: PathBuf,   // definitions file to load at startup
+    pub autoload:    bool,      // whether to load the definitions file at all
+    pub recovery:    PathBuf,   // file to save the session to, for crash recovery
+    pub autosave:    bool,      // whether to save the session after every change
+    pub pager:       bool,      // whether to page outputs longer than the terminal
+    pub tables:      TableStyle,// characters used to draw tables
+    pub prompt:      String,    // REPL prompt, with fields from PROMPT_FIELDS
+    pub numbers:     NumberFormat,// how to group digits in reports
+    pub keywords:    Keywords,  // words accepted for every command
+    pub ignore_case: bool,      // whether `Int` and `int` name the same type
+    pub strict:      bool,      // whether warnings about new types are errors
+    pub read_only:   bool,      // whether commands changing types are rejected, in every session
+    pub journal:     Option<PathBuf>,   // file recording every command that changed the session
+    pub progress:    bool,      // whether long imports show how far they got
+    pub enums:       EnumRepr,  // representation for enums that don't pick one
+    pub bitfields:   BitfieldPolicy,// rules for placing bitfields when a command doesn't pick them
+    pub limits:      Limits,    // bounds on types and the work done with them, for servers and batches
+    pub keep_first:  bool,      // whether the optimizer keeps the first member of every struct first
+    pub objective:   Objective, // what the optimizer minimizes
+    pub shadowing:   bool,      // whether defining a stored name again shadows the stored type
+    pub deferred:    bool,      // whether structs and unions may use types defined later
+    pub sandbox:     bool       // whether commands reading or writing files are rejected, for untrusted input
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            definitions: PathBuf::from(DEFAULT_DEFINITIONS),
+            autoload: true,
+            recovery: PathBuf::from(DEFAULT_RECOVERY),
+            autosave: true,
+            pager: true,
+            tables: TableStyle::Unicode,
+            prompt: String::from(DEFAULT_PROMPT),
+            numbers: NumberFormat::Plain,
+            keywords: Keywords::default(),
+            ignore_case: false,
+            strict: false,
+            read_only: false,
+            journal: None,
+            progress: false,
+            enums: EnumRepr::Int,
+            bitfields: BitfieldPolicy::Gcc,
+            limits: Limits::default(),
+            keep_first: false,
+            objective: Objective::Size,
+            shadowing: false,
+            deferred: false,
+            sandbox: false
+        }
+    }
+}
+
+impl Config {
+
+    /// Load configuration from the working directory or, if there's no config file 
+    /// there, from the user's config directory
+    /// ## Return
+    /// The loaded configuration (default if no file was found) and warnings
+    /// about lines that couldn't be understood
+    pub fn load() -> (Config, Vec<String>) {
+        let candidates = vec![Some(PathBuf::from(CONFIG_FILE)), Config::user_dir().map(|d| d.join("config"))];
+
+        for path in candidates.into_iter().flatten() {
+            if let Ok(text) = std::fs::read_to_string(&path) {
+                let (mut config, warnings) = Config::parse(&text);
+
+                if let Some(dir) = path.parent() {
+                    config.relative_to(dir);
+                }
+
+                return (config, warnings)
+            }
+        }
+
+        // no config file, try the default definitions file in both places as well
+        let mut config = Config::default();
+        if !config.definitions.exists() {
+            if let Some(dir) = Config::user_dir() {
+                let candidate = dir.join(DEFAULT_DEFINITIONS);
+                if candidate.exists() {
+                    config.definitions = candidate;
+                }
+            }
+        }
+
+        (config, vec![])
+    }
+
+    /// Parse configuration from text
+    /// ## Params
+    /// * `text` - config file contents, a `clave = valor` pair per line, `#` starts a comment
+    /// ---
+    /// ## Return
+    /// Configuration with every recognized option set and a warning for every bad line
+    pub fn parse(text : &str) -> (Config, Vec<String>) {
+        let mut config = Config::default();
+        let mut warnings = vec![];
+
+        for (i, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((k, v)) => (k.trim(), v.trim().trim_matches('"')),
+                None => {
+                    warnings.push(format!("línea {}: se esperaba 'clave = valor'", i + 1));
+                    continue
+                }
+            };
+
+            if let Err(e) = config.set(key, value) {
+                warnings.push(format!("línea {}: {}", i + 1, e));
+            }
+        }
+
+        (config, warnings)
+    }
+
+    /// Make the paths in this configuration relative to a directory, the one of the
+    /// config file they were read from. Absolute paths are left as they are
+    pub fn relative_to(&mut self, dir : &Path) {
+        self.definitions = dir.join(&self.definitions);
+        self.recovery = dir.join(&self.recovery);
+        self.journal = self.journal.as_ref().map(|journal| dir.join(journal));
+    }
+
+    /// Set a single option by its name
+    /// ## Return
+    /// Description of the problem if the key or value is not valid
+    pub fn set(&mut self, key : &str, value : &str) -> Result<(), String> {
+        match key {
+            "definiciones" => self.definitions = PathBuf::from(value),
+            "autocargar"   => self.autoload = parse_bool(value)?,
+            "recuperacion" => self.recovery = PathBuf::from(value),
+            "autoguardar"  => self.autosave = parse_bool(value)?,
+            "paginar"      => self.pager = parse_bool(value)?,
+            "tablas"       => self.tables = parse_table_style(value)?,
+            "prompt"       => self.prompt = parse_prompt(value)?,
+            "numeros"      => self.numbers = parse_number_format(value)?,
+            "ignorar_mayusculas" => self.ignore_case = parse_bool(value)?,
+            "estricto"     => self.strict = parse_bool(value)?,
+            "solo_lectura" => self.read_only = parse_bool(value)?,
+            "progreso"     => self.progress = parse_bool(value)?,
+            "diario"       => self.journal = Some(PathBuf::from(value)).filter(|p| !p.as_os_str().is_empty()),
+            "enums"        => self.enums = parse_enum_repr(value)?,
+            "campos_bits"  => self.bitfields = parse_bitfield_policy(value)?,
+            "limite_miembros"   => self.limits.members = parse_limit(value)?,
+            "limite_anidamiento" => self.limits.depth = parse_limit(value)?,
+            "limite_tipos"      => self.limits.types = parse_limit(value)?,
+            "limite_optimizador_ms" => self.limits.optimizer = parse_limit(value)?.map(|ms| Duration::from_millis(ms as u64)),
+            "conservar_primero" => self.keep_first = parse_bool(value)?,
+            "objetivo"     => self.objective = parse_objective(value)?,
+            "sombrear"     => self.shadowing = parse_bool(value)?,
+            "diferir"      => self.deferred = parse_bool(value)?,
+            "comandos"     => {
+                if !self.keywords.set_language(value) {
+                    return Err(format!("'{}' no es un idioma de comandos, se esperaba 'es', 'en' o 'ambos'", value))
+                }
+            },
+            _ => match key.strip_prefix("alias.") {
+                Some(verb) => self.add_aliases(verb, value)?,
+                None => return Err(format!("clave desconocida '{}'", key))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Accept other words for a command: `alias.describir = describe, mostrar`
+    fn add_aliases(&mut self, verb : &str, words : &str) -> Result<(), String> {
+        let verb = Verb::parse(verb).ok_or_else(|| format!("'{}' no es un comando", verb))?;
+        for word in words.split(',') {
+            self.keywords.alias(word.trim(), verb)?;
+        }
+
+        Ok(())
+    }
+
+    /// Directory for user configuration: `$XDG_CONFIG_HOME/typemanager` or `~/.config/typemanager`
+    pub fn user_dir() -> Option<PathBuf> {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+            .map(|dir| dir.join("typemanager"))
+    }
+}
+
+/// A config line without its comment, which starts at a `#` outside quotes
+fn strip_comment(line : &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => ()
+        }
+    }
+
+    line
+}
+
+/// Parse a yes/no value
+fn parse_bool(value : &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "si" | "sí" | "true" | "1"  => Ok(true),
+        "no" | "false" | "0"        => Ok(false),
+        _ => Err(format!("'{}' no es un valor de sí/no", value))
+    }
+}
+
+/// Parse a limit, `no` for none
+fn parse_limit(value : &str) -> Result<Option<usize>, String> {
+    match value.to_lowercase().as_str() {
+        "no" | "ninguno" => Ok(None),
+        _ => match value.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(Some(n)),
+            _ => Err(format!("'{}' no es un límite, se esperaba un número mayor que 0 o 'no'", value))
+        }
+    }
+}
+
+/// Parse a table style name
+fn parse_table_style(value : &str) -> Result<TableStyle, String> {
+    match value.to_lowercase().as_str() {
+        "unicode" => Ok(TableStyle::Unicode),
+        "ascii"   => Ok(TableStyle::Ascii),
+        _ => Err(format!("'{}' no es un estilo de tabla, se esperaba 'unicode' o 'ascii'", value))
+    }
+}
+
+/// Parse a number format name
+fn parse_number_format(value : &str) -> Result<NumberFormat, String> {
+    NumberFormat::parse(value).ok_or_else(|| format!(
+        "'{}' no es un formato de números, se esperaba 'simple', 'guion_bajo', 'puntos', 'comas' o 'local'",
+        value
+    ))
+}
+
+/// Parse an enum representation name
+fn parse_enum_repr(value : &str) -> Result<EnumRepr, String> {
+    EnumRepr::parse(value).ok_or_else(|| format!("'{}' no es una representación de enums, se esperaba 'int' o 'minimo'", value))
+}
+
+/// Parse what the optimizer minimizes: `tamaño` or `paso`
+fn parse_objective(value : &str) -> Result<Objective, String> {
+    Objective::parse(value).ok_or_else(|| format!("'{}' no es un objetivo del optimizador, se esperaba 'tamaño' o 'paso'", value))
+}
+
+/// Parse a bitfield policy name
+fn parse_bitfield_policy(value : &str) -> Result<BitfieldPolicy, String> {
+    BitfieldPolicy::parse(value).ok_or_else(|| format!("'{}' no es una política de campos de bits, se esperaba 'gcc' o 'msvc'", value))
+}
+
+/// Check every field used in a prompt exists
+fn parse_prompt(value : &str) -> Result<String, String> {
+    let mut rest = value;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').ok_or_else(|| format!("falta '}}' en el prompt '{}'", value))?;
+        let field = &rest[start + 1..start + end];
+        if !PROMPT_FIELDS.contains(&field) {
+            let known : Vec<String> = PROMPT_FIELDS.iter().map(|f| format!("{{{}}}", f)).collect();
+            return Err(format!("'{{{}}}' no es un campo del prompt, se esperaba uno de: {}", field, known.join(", ")))
+        }
+        rest = &rest[start + end + 1..];
+    }
+
+    Ok(value.to_string())
+}
@@ -0,0 +1,73 @@
+/*
+    Commands written in the REPL, so they can be listed and run again:
+    `!!` runs the last one, `!n` the one numbered n and `^viejo^nuevo`
+    runs the last one replacing some text first
+*/
+use super::ProgramError;
+
+/// Every command written so far, numbered from 1
+#[derive(Debug, Default, Clone)]
+pub struct History {
+    entries: Vec<String>
+}
+
+impl History {
+
+    /// Store a command, empty lines are ignored
+    pub fn push(&mut self, line: &str) {
+        let line = line.trim();
+        if !line.is_empty() {
+            self.entries.push(line.to_string())
+        }
+    }
+
+    /// Every command with its number
+    pub fn entries(&self) -> impl Iterator<Item = (usize, &String)> {
+        self.entries.iter().enumerate().map(|(i, e)| (i + 1, e))
+    }
+
+    /// Amount of stored commands
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Tells if no command was stored yet
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Replace a history reference by the command it refers to
+    /// ## Params
+    /// * `line` - line written by the user
+    /// ---
+    /// ## Return
+    /// The command to run if the line refers to the history, None if it's a regular
+    /// command, or an error if there's no such command
+    pub fn expand(&self, line: &str) -> Result<Option<String>, ProgramError> {
+        let line = line.trim();
+        let missing = || ProgramError::HistoryEvent(line.to_string());
+
+        if line == "!!" {
+            return self.entries.last().cloned().map(Some).ok_or_else(missing)
+        }
+
+        if let Some(number) = line.strip_prefix('!') {
+            let n : usize = number.parse().map_err(|_| missing())?;
+            return match n {
+                0 => Err(missing()),
+                n => self.entries.get(n - 1).cloned().map(Some).ok_or_else(missing)
+            }
+        }
+
+        if let Some(edit) = line.strip_prefix('^') {
+            let (old, new) = edit.split_once('^').ok_or_else(missing)?;
+            let new = new.strip_suffix('^').unwrap_or(new);
+            return match self.entries.last() {
+                Some(last) if !old.is_empty() && last.contains(old) => Ok(Some(last.replacen(old, new, 1))),
+                _ => Err(missing())
+            }
+        }
+
+        Ok(None)
+    }
+}
@@ -0,0 +1,72 @@
+/*
+    Command journal: every command that changed the session, with the time it
+    ran, appended to a text file. Running those commands again in order rebuilds
+    the session, so the journal is both an audit trail and a way to save it.
+    Every entry is a line `<segundos>.<milisegundos>\t<comando>`, seconds since
+    the Unix epoch. Lines starting with `#` are comments
+*/
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// First line of every journal
+const HEADER : &str = "# Diario de TypeManager: <segundos>.<milisegundos>\t<comando> por línea";
+
+/// What a `diario` command asks for
+#[derive(Debug, Clone, PartialEq)]
+pub enum JournalCommand {
+    Show,           // tell which file is being written
+    Start(String),  // start writing to a file
+    Stop
+}
+
+/// A file commands are appended to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Journal {
+    path: PathBuf
+}
+
+impl Journal {
+
+    /// Journal writing to a file, it's created with a header on the first command recorded
+    pub fn new(path : &Path) -> Journal {
+        Journal { path: path.to_path_buf() }
+    }
+
+    /// File this journal writes to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a command, stamped with the current time
+    pub fn record(&self, command : &str) -> io::Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let header = !self.path.exists();
+        let mut file = std::fs::OpenOptions::new().append(true).create(true).open(&self.path)?;
+        if header {
+            writeln!(file, "{}", HEADER)?;
+        }
+        writeln!(file, "{}.{:03}\t{}", now.as_secs(), now.subsec_millis(), command.trim())
+    }
+}
+
+/// Command in a journal line
+/// ## Return
+/// None for comments and empty lines, the command for entries, or an error if the line
+/// has no valid time stamp
+pub fn parse_entry(line : &str) -> Option<Result<&str, String>> {
+    let line = line.trim_end_matches(['\n', '\r']);
+    if line.trim().is_empty() || line.starts_with('#') {
+        return None
+    }
+
+    let valid = |stamp : &str| match stamp.split_once('.') {
+        Some((secs, millis)) => !secs.is_empty() && secs.chars().all(|c| c.is_ascii_digit()) && millis.len() == 3 && millis.chars().all(|c| c.is_ascii_digit()),
+        None => false
+    };
+    match line.split_once('\t') {
+        Some((stamp, command)) if valid(stamp) => Some(Ok(command)),
+        _ => Some(Err(format!("'{}' no es una entrada del diario, se esperaba '<segundos>.<milisegundos>\\t<comando>'", line)))
+    }
+}
@@ -0,0 +1,267 @@
+/*
+    Words for every built in command. The parser looks verbs up here instead
+    of matching fixed words, so the same scripts can be written in Spanish
+    or English and users can add their own aliases
+*/
+use std::collections::HashMap;
+
+/// Every built in command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Verb {
+    Exit,
+    Union,
+    Struct,
+    Wizard,
+    Macro,
+    Atomic,
+    Let,
+    Include,
+    History,
+    Variables,
+    Calculate,
+    Assert,
+    Describe,
+    List,
+    Offsets,
+    Lint,
+    Split,
+    Flags,
+    Enum,
+    Pointer,
+    Optional,
+    Container,
+    Text,
+    Bits,
+    Variant,
+    Reuse,
+    Density,
+    Plan,
+    Addresses,
+    Annotate,
+    Decode,
+    Generate,
+    Code,
+    Import,
+    Export,
+    Save,
+    Load,
+    Session,
+    Merge,
+    Diff,
+    Journal,
+    Replay,
+    VerifyReplay,
+    Profile,
+    Redefine,
+    Cancel,
+    Anneal,
+    Solve,
+    Pareto,
+    Stride,
+    Trace,
+    Clean,
+    Document,
+    Attribute,
+    Try,
+    Budget,
+    Validate,
+    Growth,
+    Flatten
+}
+
+/// Spanish words, these name the verbs in configuration files
+pub const SPANISH : [(&str, Verb); 59] = [
+    ("salir",           Verb::Exit),
+    ("union",           Verb::Union),
+    ("struct",          Verb::Struct),
+    ("asistente",       Verb::Wizard),
+    ("macro",           Verb::Macro),
+    ("atomico",         Verb::Atomic),
+    ("let",             Verb::Let),
+    ("incluir",         Verb::Include),
+    ("historial",       Verb::History),
+    ("variables",       Verb::Variables),
+    ("calcular",        Verb::Calculate),
+    ("afirmar",         Verb::Assert),
+    ("describir",       Verb::Describe),
+    ("listar",          Verb::List),
+    ("desplazamientos", Verb::Offsets),
+    ("lint",            Verb::Lint),
+    ("dividir",         Verb::Split),
+    ("banderas",        Verb::Flags),
+    ("enum",            Verb::Enum),
+    ("puntero",         Verb::Pointer),
+    ("opcional",        Verb::Optional),
+    ("contenedor",      Verb::Container),
+    ("cadena",          Verb::Text),
+    ("bits",            Verb::Bits),
+    ("variante",        Verb::Variant),
+    ("reutilizar",      Verb::Reuse),
+    ("densidad",        Verb::Density),
+    ("planificar",      Verb::Plan),
+    ("direcciones",     Verb::Addresses),
+    ("anotar",          Verb::Annotate),
+    ("decodificar",     Verb::Decode),
+    ("generar",         Verb::Generate),
+    ("codigo",          Verb::Code),
+    ("importar",        Verb::Import),
+    ("exportar",        Verb::Export),
+    ("guardar",         Verb::Save),
+    ("cargar",          Verb::Load),
+    ("sesion",          Verb::Session),
+    ("fusionar",        Verb::Merge),
+    ("diff",            Verb::Diff),
+    ("diario",          Verb::Journal),
+    ("reproducir",      Verb::Replay),
+    ("verificar-reproduccion", Verb::VerifyReplay),
+    ("perfil",          Verb::Profile),
+    ("redefinir",       Verb::Redefine),
+    ("cancelar",        Verb::Cancel),
+    ("recocer",         Verb::Anneal),
+    ("resolver",        Verb::Solve),
+    ("pareto",          Verb::Pareto),
+    ("paso",            Verb::Stride),
+    ("traza",           Verb::Trace),
+    ("limpiar",         Verb::Clean),
+    ("documentar",      Verb::Document),
+    ("atributo",        Verb::Attribute),
+    ("probar",          Verb::Try),
+    ("presupuesto",     Verb::Budget),
+    ("validar",         Verb::Validate),
+    ("historia",        Verb::Growth),
+    ("aplanar",         Verb::Flatten)
+];
+
+/// English words
+pub const ENGLISH : [(&str, Verb); 59] = [
+    ("exit",            Verb::Exit),
+    ("union",           Verb::Union),
+    ("struct",          Verb::Struct),
+    ("wizard",          Verb::Wizard),
+    ("macro",           Verb::Macro),
+    ("atomic",          Verb::Atomic),
+    ("let",             Verb::Let),
+    ("include",         Verb::Include),
+    ("history",         Verb::History),
+    ("variables",       Verb::Variables),
+    ("calculate",       Verb::Calculate),
+    ("assert",          Verb::Assert),
+    ("describe",        Verb::Describe),
+    ("list",            Verb::List),
+    ("offsets",         Verb::Offsets),
+    ("lint",            Verb::Lint),
+    ("split",           Verb::Split),
+    ("flags",           Verb::Flags),
+    ("enum",            Verb::Enum),
+    ("pointer",         Verb::Pointer),
+    ("optional",        Verb::Optional),
+    ("container",       Verb::Container),
+    ("string",          Verb::Text),
+    ("bits",            Verb::Bits),
+    ("variant",         Verb::Variant),
+    ("reuse",           Verb::Reuse),
+    ("density",         Verb::Density),
+    ("plan",            Verb::Plan),
+    ("addresses",       Verb::Addresses),
+    ("annotate",        Verb::Annotate),
+    ("decode",          Verb::Decode),
+    ("generate",        Verb::Generate),
+    ("code",            Verb::Code),
+    ("import",          Verb::Import),
+    ("export",          Verb::Export),
+    ("save",            Verb::Save),
+    ("load",            Verb::Load),
+    ("session",         Verb::Session),
+    ("merge",           Verb::Merge),
+    ("diff",            Verb::Diff),
+    ("journal",         Verb::Journal),
+    ("replay",          Verb::Replay),
+    ("verify-replay",   Verb::VerifyReplay),
+    ("profile",         Verb::Profile),
+    ("redefine",        Verb::Redefine),
+    ("cancel",          Verb::Cancel),
+    ("anneal",          Verb::Anneal),
+    ("solve",           Verb::Solve),
+    ("pareto",          Verb::Pareto),
+    ("stride",          Verb::Stride),
+    ("trace",           Verb::Trace),
+    ("clean",           Verb::Clean),
+    ("document",        Verb::Document),
+    ("attribute",       Verb::Attribute),
+    ("try",             Verb::Try),
+    ("budget",          Verb::Budget),
+    ("validate",        Verb::Validate),
+    ("growth",          Verb::Growth),
+    ("flatten",         Verb::Flatten)
+];
+
+impl Verb {
+
+    /// Find a verb by its Spanish word
+    pub fn parse(word : &str) -> Option<Verb> {
+        SPANISH.iter().find(|(w, _)| *w == word).map(|(_, v)| *v)
+    }
+
+    /// Spanish word for this verb
+    pub fn name(self) -> &'static str {
+        SPANISH.iter().find(|(_, v)| *v == self).map(|(w, _)| *w).unwrap_or("")
+    }
+}
+
+/// Words accepted for every command
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keywords {
+    words:   HashMap<String, Verb>,     // words from the chosen language
+    aliases: HashMap<String, Verb>      // words added by the user
+}
+
+impl Default for Keywords {
+    fn default() -> Self {
+        let mut keywords = Keywords { words: HashMap::new(), aliases: HashMap::new() };
+        keywords.set_language("es");
+        keywords
+    }
+}
+
+impl Keywords {
+
+    /// Use the words for a language: `es`, `en` or `ambos`, keeping aliases
+    /// ## Return
+    /// False if the language is unknown, words are left as they were then
+    pub fn set_language(&mut self, name : &str) -> bool {
+        let tables : Vec<&[(&str, Verb)]> = match name.to_lowercase().as_str() {
+            "es" | "español" | "espanol" => vec![&SPANISH],
+            "en" | "ingles" | "inglés"   => vec![&ENGLISH],
+            "ambos"                      => vec![&SPANISH, &ENGLISH],
+            _ => return false
+        };
+
+        self.words = tables
+                        .into_iter()
+                        .flat_map(|t| t.iter())
+                        .map(|(w, v)| (w.to_string(), *v))
+                        .collect();
+        true
+    }
+
+    /// Verb for a word, if it's a command word
+    pub fn get(&self, word : &str) -> Option<Verb> {
+        let word = word.to_lowercase();
+        self.aliases.get(&word).or_else(|| self.words.get(&word)).copied()
+    }
+
+    /// Accept another word for a verb
+    /// ## Return
+    /// An error if the word is already used by another verb
+    pub fn alias(&mut self, word : &str, verb : Verb) -> Result<(), String> {
+        let word = word.to_lowercase();
+        if word.is_empty() || word.contains(char::is_whitespace) {
+            return Err(format!("'{}' no es una palabra válida para un comando", word))
+        }
+
+        match self.get(&word) {
+            Some(other) if other != verb => Err(format!("'{}' ya es el comando '{}'", word, other.name())),
+            _ => { self.aliases.insert(word, verb); Ok(()) }
+        }
+    }
+}
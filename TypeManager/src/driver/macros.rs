@@ -0,0 +1,120 @@
+/*
+    User defined macros: templates for commands, so families of
+    similar types can be defined with a single line
+*/
+use crate::type_system::{Name, Field};
+use super::ProgramError;
+
+/// Where a macro was defined when it wasn't defined in a script
+pub const INTERACTIVE : &str = "<interactivo>";
+
+/// A command template with parameters
+#[derive(Debug, Clone, PartialEq)]
+pub struct Macro {
+    pub params: Vec<Name>,
+    pub body:   String,     // commands separated by `;`
+    pub origin: String      // where it was defined, to point errors at it
+}
+
+impl Macro {
+
+    /// Parse a macro definition: `nombre(P1, P2) => comando; comando...`
+    /// ## Params
+    /// * `text` - definition, without the `macro` keyword
+    /// ---
+    /// ## Return
+    /// Macro name, parameters and body
+    pub fn parse_definition(text : &str) -> Result<(Name, Vec<Name>, String), ProgramError> {
+        let (header, body) = match text.split_once("=>") {
+            Some((h, b)) => (h.trim(), b.trim()),
+            None => return Err(ProgramError::NotEnoughArgs)
+        };
+
+        let (name, params) = parse_call(header).ok_or_else(|| ProgramError::InvalidArgument(header.to_string()))?;
+        if body.is_empty() {
+            return Err(ProgramError::NotEnoughArgs)
+        }
+
+        // every parameter is a single word, and only once
+        for (i, p) in params.iter().enumerate() {
+            if !p.chars().all(char::is_alphanumeric) || !Field::valid_name(p) || params[..i].contains(p) {
+                return Err(ProgramError::InvalidArgument(p.clone()))
+            }
+        }
+
+        Ok((name, params, body.to_string()))
+    }
+
+    /// Commands this macro expands to for the given arguments. Parameters are replaced
+    /// wherever they appear as a whole word, `_` separates words too: with `T = int`,
+    /// `par_T` becomes `par_int`
+    /// ## Params
+    /// * `name` - name of this macro, for errors
+    /// * `args` - a value for every parameter
+    pub fn expand(&self, name : &str, args : &[String]) -> Result<Vec<String>, ProgramError> {
+        if args.len() != self.params.len() {
+            return Err(ProgramError::MacroArguments(name.to_string(), self.params.len(), args.len()))
+        }
+
+        let commands = self.body
+                        .split(';')
+                        .map(|command| substitute(command.trim(), &self.params, args))
+                        .filter(|command| !command.is_empty())
+                        .collect();
+
+        Ok(commands)
+    }
+}
+
+/// Parse a call such as `nombre(a, b)`
+/// ## Return
+/// Name and arguments, or None if the text is not a call
+pub fn parse_call(text : &str) -> Option<(Name, Vec<String>)> {
+    let text = text.trim();
+    let open = text.find('(')?;
+    let inner = text.strip_suffix(')')?.get(open + 1..)?;
+
+    let name = text[..open].trim();
+    if !Field::valid_name(name) {
+        return None
+    }
+
+    let args : Vec<String> = match inner.trim() {
+        ""    => vec![],
+        inner => inner.split(',').map(|a| a.trim().to_string()).collect()
+    };
+
+    if args.iter().any(|a| a.is_empty() || a.contains(char::is_whitespace)) {
+        return None
+    }
+
+    Some((name.to_string(), args))
+}
+
+/// Replace every parameter by its argument in a command
+fn substitute(command : &str, params : &[Name], args : &[String]) -> String {
+    let mut result = String::with_capacity(command.len());
+    let mut word = String::new();
+
+    // words are runs of letters and digits, anything else is copied as is
+    let flush = |word : &mut String, result : &mut String| {
+        match params.iter().position(|p| p == word) {
+            Some(i) => result.push_str(&args[i]),
+            None    => result.push_str(word)
+        }
+        word.clear();
+    };
+
+    for c in command.chars() {
+        if c.is_alphanumeric() {
+            word.push(c);
+        }
+        else {
+            flush(&mut word, &mut result);
+            result.push(c);
+        }
+    }
+    flush(&mut word, &mut result);
+
+    result
+}
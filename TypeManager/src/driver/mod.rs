@@ -4,207 +4,4341 @@
 
 // Rust imports
 use std::io;
-use std::io::Write;
+use std::io::{BufRead, Write};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Instant;
+
+mod wizard;
+mod plugin;
+mod macros;
+mod history;
+mod keywords;
+mod session;
+mod workspace;
+mod journal;
+mod protocol;
+pub use wizard::*;
+pub use plugin::*;
+pub use macros::*;
+pub use history::*;
+pub use keywords::*;
+pub use workspace::{MergeStrategy, SessionCommand, MAIN_SESSION};
+use workspace::Workspace;
+pub use journal::JournalCommand;
+use journal::Journal;
 
 // Internal imports
 use crate::type_system::*;
+use crate::type_system::cancel::Canceller;
+use crate::json;
+use crate::expr::{self, Cmp, Expr, ExprError, Scope, Variables};
+use crate::codegen;
+use crate::import;
+use crate::config::Config;
+use crate::metrics;
+use crate::image;
+use crate::output::{Output, Progress};
+use crate::utils::{self, table::{Table, Align}};
+pub use session::SessionFormat;
+
+
+/// Our program object
+pub struct Program {
+    running: bool,
+    manager: TypeManager,
+    config:  Config,
+    out:     Output,
+    wizard:  Option<Wizard>,    // struct being defined step by step, if any
+    plugins: PluginRegistry,    // commands added from outside the driver
+    macros:  HashMap<Name, Macro>,
+    origin:  Option<String>,    // file and line being run, if running a script
+    depth:   usize,             // macro expansions being run right now
+    variables: Variables,       // values stored with `let`, and the last result
+    files:   Vec<PathBuf>,      // files being run right now, the innermost one last
+    history: History,           // commands written in the REPL
+    unsaved: bool,              // whether some change is not in the recovery file yet
+    running_depth: usize,       // commands and scripts running, the recovery file is written when none is
+    revision: usize,            // changes made so far
+    allowed: HashMap<Name, Vec<String>>,    // lint rules disabled for every type
+    pending_allow: Vec<String>, // lint rules disabled for the next type defined
+    pending_origin: Option<String>, // where the next type defined came from, if a comment says so
+    session: Name,              // name of the session in use
+    locked:  bool,              // whether the types of the session in use can't change
+    journal: Option<Journal>,   // where commands changing the session are recorded, if anywhere
+    recording: bool,            // whether a command being recorded is running, the ones it runs are not recorded
+    canceller: Canceller,       // stops the command running, from another thread
+    sessions: HashMap<Name, Workspace>  // sessions not in use
+}
+
+/// Maximum amount of nested macro expansions, so recursive macros end
+pub const MAX_MACRO_DEPTH : usize = 32;
+
+/// Outcome of running a script, a file with a command per line
+#[derive(Debug, Default)]
+pub struct ScriptSummary {
+    pub defined: usize,         // amount of types successfully defined
+    pub errors:  Vec<String>    // an error message for every failed line
+}
+
+/// possible errors
+#[derive(Debug, PartialEq)]
+pub enum ProgramError {
+    NotEnoughArgs,
+    TooManyArgs,
+    InvalidAction(String),
+    InvalidArgument(String),
+    DuplicateCommand(String),
+    MacroArguments(String, usize, usize),   // macro, expected arguments, given arguments
+    MacroRecursion(String),
+    IncludeCycle(Vec<String>),              // every file in the cycle, the repeated one last
+    IncludeRead(String, String),            // file, reason
+    Import(String, String),                 // file, reason
+    SessionWrite(String, String),           // file, reason
+    OutputWrite(String, String),            // file a command's output was redirected to, reason
+    SessionRead(String, String),            // file, reason
+    UnknownAbi(String),
+    SessionExists(Name),
+    UnknownSession(Name),
+    ReadOnly(Name),                         // session that can't change
+    Sandboxed(String),                      // command using files in a program that can't
+    ReplayMismatch(String, Vec<String>),    // files compared, the first divergence
+    AssertionFailed(String, Option<(i64, Cmp, i64)>),   // expression, sides of the comparison if it is one
+    Strict(Vec<String>),                    // warnings, errors in strict mode
+    HistoryEvent(String),                   // history reference with no command
+    UnknownLintRule(String),
+    OverBudget(Vec<String>),                // every budget that doesn't hold, described
+    Expression(ExprError)
+}
+
+/// Anything that can go wrong running a single command
+#[derive(Debug, PartialEq)]
+pub enum CommandError {
+    Program(ProgramError),  // couldn't understand the command
+    Type(TypeError),        // the command failed
+    Macro { name: Name, origin: String, command: String, error: Box<CommandError> },   // a command from a macro failed
+    Defined { name: Name, origin: String, error: Box<CommandError> },  // a command failed on a type defined in a file
+    Include { file: String, errors: Vec<String> }   // some lines of an included file failed
+}
+
+/// Extra options for describing a type
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DisplayOptions {
+    pub stats: bool,    // show optimizer statistics
+    pub json:  bool,    // machine readable output
+    pub detail: bool,   // show fields inside string representations
+    pub progressive: bool,  // show every better member order as the optimizer finds it
+    pub pahole: bool,       // show structs and unions as the pahole tool does
+    pub strategy: Option<Strategy>  // show only the layout under this strategy
+}
+
+/// Formats every stored type can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,   // an array with a report per type
+    C,      // C definitions checking our layouts with static asserts
+    Rust,   // Rust tests checking our layouts for structs and unions
+    Script, // commands that define every type again
+    Capnp,  // Cap'n Proto schema, comparing its layout with ours
+    Fbs,    // FlatBuffers schema, warning about what needs manual translation
+    Sql(codegen::Engine),   // tables for a database engine, with the size of their rows
+    Arrow(usize),           // Arrow schema, with the memory its columns take for some rows
+    Treemap,                // SVG treemap of the sizes of every type, padding hatched
+    TreemapPage             // HTML page with the treemap and the types with most padding
+}
+
+impl ExportFormat {
+
+    /// Find a format by the name used in commands and in the command line
+    pub fn parse(name: &str) -> Option<ExportFormat> {
+        match name {
+            "json"  => Some(ExportFormat::Json),
+            "c"     => Some(ExportFormat::C),
+            "rs"    => Some(ExportFormat::Rust),
+            "tm"    => Some(ExportFormat::Script),
+            "capnp" => Some(ExportFormat::Capnp),
+            "fbs"   => Some(ExportFormat::Fbs),
+            "sql"   => Some(ExportFormat::Sql(codegen::Engine::Postgres)),
+            "arrow" => Some(ExportFormat::Arrow(codegen::DEFAULT_ROWS)),
+            "treemap" => Some(ExportFormat::Treemap),
+            "treemap-html" => Some(ExportFormat::TreemapPage),
+            _ => None
+        }
+    }
+}
+
+/// Possible actions
+pub enum Action {
+    Display(Name, DisplayOptions),
+    DisplayAll(DisplayOptions),
+    DisplayMatching(Vec<String>, DisplayOptions),   // names and patterns like `net::*`, in order
+    List,
+    Offsets(Name),
+    AddStruct(Name, Struct),
+    AddUnion(Name, TypeList),       // name, variants
+    AddAtomic(Name, Expr, Expr, Option<ValueKind>),    // name, representation, alignment, how to read its value
+    AddEnum(Name, Vec<Name>, Option<EnumRepr>), // name, variants, representation if not the configured one
+    AddPointer(Name, Name, PointerKind),    // name, pointed type, thin or fat pointer
+    Optional(Name),                 // type to wrap
+    Profile(Name),
+    Anneal(Name, AnnealOptions),    // struct, seed and length of the search
+    Solve(Name),                    // struct to find the smallest order of
+    Pareto(Name, usize),            // struct, cache line size
+    Stride(Name),                   // struct to compare the best orders for size and stride of
+    Trace(Name, String, usize),     // struct, access trace file, cache line size
+    Image(Name, String, Strategy),  // struct, PNG file to draw its layout in, strategy
+    Document(String),               // directory to write a documentation site in
+    Redefine(Box<Action>),          // definition taking the place of a stored type with its name
+    Try(Box<Action>),               // definition to check and report on without storing it
+    Budget(Name, Option<Budget>),   // type, most bytes it may take, none to remove its budget
+    Budgets,                        // show every budget and whether it holds
+    Validate,                       // check every budget holds
+    Growth(Name),                   // type to show the layouts it took of
+    Flatten(Name, Name, Strategy),  // struct, name of its flat copy, strategy giving the offsets
+    AddContainer(Name, Container),
+    AddText(Name, TextModel),
+    Bitfields(Name, Option<BitfieldPolicy>),   // struct, policy if not the configured one
+    Variant(Name, Name),            // union, active variant
+    TailReuse(Name),
+    Density(Name, Strategy, usize, usize),  // type, strategy, cache line size, page size
+    Plan(usize, Vec<(Name, usize)>),        // budget in bytes, type and amount of every array
+    Addresses(Name, Expr, Strategy),        // type, base address, strategy
+    Annotate(Name, Vec<u8>, Strategy),      // type, bytes to overlay it on, strategy
+    Decode(Name, Vec<u8>, Strategy, Endian),    // type, bytes to read, strategy, byte order
+    Generate(Name, Option<u64>, Strategy),  // type, seed for random bytes, strategy
+    Code(Vec<Name>, bool),          // types to emit layout checks for, in Rust instead of C
+    Import(String, import::Imported),   // file, types read from it
+    ImportLines(String),                // JSON lines file with a command per line
+    Export(ExportFormat),
+    Save(String, SessionFormat),    // file, format
+    Load(String, Option<SessionFormat>),    // file, format if not the one its contents tell
+    Session(SessionCommand),
+    Merge(String, MergeStrategy),   // saved session, what to do with conflicts
+    Diff(Name, Name),               // session before, session after
+    Journal(JournalCommand),
+    Replay(String),                 // journal to run again
+    VerifyReplay(String, String),   // journal, saved session it should rebuild
+    Wizard,                         // define a struct step by step
+    Plugin(String, Vec<String>),    // command name, arguments as parsed by the plugin
+    DefineMacro(Name, Vec<Name>, String),   // name, parameters, body
+    Calculate(Expr),
+    Assert(String, Expr),           // expression as written, parsed
+    Let(Name, Expr),                // variable name, value
+    ListVariables,
+    Include(String),                // file to run, relative to the current one
+    History,
+    Lint(Option<Name>),             // type to check, every type if none
+    LintRules,
+    Split(Name, usize),             // struct, cache line size
+    Flags(Name, Option<Name>),      // struct, name for its packed version if it should be defined
+    Attribute(Name, Option<Name>, Vec<Attribute>),  // type, member if any, attributes to add
+    Clean(Vec<Name>),               // types to keep with everything they refer to, every struct and union if none
+    ResolvePending,                 // add the types waiting for others, if those are defined now
+    Cancel,                         // stop the computation running, only servers read it while one runs
+    Exit
+}
+
+impl Action {
+
+    /// Tells if this action only reads stored types, so it can run on a snapshot of them.
+    /// A snapshot has no variables, so addresses only run there when their base reads none
+    pub fn reads_types(&self) -> bool {
+        match self {
+            Action::Addresses(_, base, _) => !base.reads_variables(),
+            action => matches!(action, Action::Display(..) | Action::DisplayAll(_) | Action::DisplayMatching(..) | Action::List | Action::Offsets(_) | Action::Optional(_) | Action::Profile(_) | Action::Anneal(..) | Action::Solve(_) | Action::Pareto(..) | Action::Stride(_) | Action::Trace(..) | Action::Variant(..) | Action::TailReuse(_) | Action::Density(..) | Action::Split(..) | Action::Code(..) | Action::Export(_) | Action::Image(..) | Action::Document(_) | Action::Bitfields(..) | Action::Try(_) | Action::Budgets | Action::Validate | Action::Growth(_) |
+                                     Action::Plan(..) | Action::Annotate(..) | Action::Decode(..) | Action::Generate(..) | Action::Lint(_) | Action::LintRules)
+        }
+    }
+
+    /// Tells if this action defines a new type
+    pub fn is_definition(&self) -> bool {
+        matches!(self, Action::AddAtomic(..) | Action::AddStruct(..) | Action::AddUnion(..) | Action::AddEnum(..) | Action::AddPointer(..) | Action::AddContainer(..) | Action::AddText(..) | Action::Flags(_, Some(_)) | Action::Redefine(_))
+    }
+
+    /// Name of the type this action defines, if it defines one
+    pub fn defined(&self) -> Option<&Name> {
+        match self {
+            Action::AddAtomic(name, ..) | Action::AddStruct(name, _) | Action::AddUnion(name, _) | Action::AddEnum(name, ..) |
+            Action::AddPointer(name, ..) | Action::AddContainer(name, ..) | Action::AddText(name, ..) | Action::Flags(name, Some(_)) => Some(name),
+            Action::Redefine(action) => action.defined(),
+            _ => None
+        }
+    }
+}
+
+impl Default for Program {
+    fn default() -> Self {
+        Program::new()
+    }
+}
+
+impl Program {
+
+    // Crea un programa nuevo listo para correr, sin autoguardado
+    pub fn new() -> Program {
+        Program::with_config(Config { autosave: false, ..Config::default() })
+    }
+
+    /// Create a program that never reads or writes files, commands doing so fail.
+    /// Meant for running untrusted input, such as the fuzzer's
+    pub fn sandboxed() -> Program {
+        Program::with_config(Config { autosave: false, sandbox: true, ..Config::default() })
+    }
+
+    /// Create a new program using the given configuration
+    pub fn with_config(config: Config) -> Program {
+        Program {
+            locked: config.read_only,
+            journal: config.journal.as_deref().filter(|_| !config.sandbox).map(Journal::new),
+            recording: false,
+            canceller: Canceller::default(),
+            manager: Program::new_manager(&config),
+            running: true,
+            config,
+            out: Output::new(),
+            wizard: None,
+            plugins: PluginRegistry::with_builtins(),
+            macros: HashMap::new(),
+            origin: None,
+            depth: 0,
+            variables: Variables::default(),
+            files: vec![],
+            history: History::default(),
+            unsaved: false,
+            running_depth: 0,
+            revision: 0,
+            allowed: HashMap::new(),
+            pending_allow: vec![],
+            pending_origin: None,
+            session: String::from(MAIN_SESSION),
+            sessions: HashMap::new()
+        }
+    }
+
+    /// Create a program reading some types, such as a snapshot of another program's
+    pub fn with_manager(config: Config, manager: TypeManager) -> Program {
+        Program { manager, ..Program::with_config(config) }
+    }
+
+    /// Configuration this program runs with
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Create an empty manager following the given configuration
+    fn new_manager(config: &Config) -> TypeManager {
+        let mut manager = match config.ignore_case {
+            true  => TypeManager::ignoring_case(),
+            false => TypeManager::new()
+        };
+        manager.set_strict(config.strict);
+        manager.set_limits(config.limits);
+        manager.set_keep_first(config.keep_first);
+        manager.set_objective(config.objective);
+        manager.set_shadowing(config.shadowing);
+        manager.set_deferred(config.deferred);
+        manager
+    }
+
+    /// Keep output instead of printing it, so it can be retrieved with `take_output`
+    pub fn capture_output(&mut self) {
+        self.out = Output::capturing();
+    }
+
+    /// Get every output captured so far, see `capture_output`
+    pub fn take_output(&mut self) -> String {
+        self.out.take_captured()
+    }
+
+    /// Load the definitions file set in our config, if it exists, reporting
+    /// how many types were loaded and every error found
+    pub fn autoload(&mut self) {
+        let path = self.config.definitions.clone();
+        if !self.config.autoload || self.config.sandbox || !path.is_file() {
+            return
+        }
+
+        match self.run_file(&path) {
+            Err(e) => self.out.line(&format!("[ERROR]: No se pudo leer '{}': {}", path.display(), e)),
+            Ok(summary) => {
+                self.out.line(&format!("Cargados {} tipos desde '{}'", summary.defined, path.display()));
+                for e in summary.errors {
+                    self.out.line(&e);
+                }
+            }
+        }
+        self.out.flush(false);
+    }
+
+    /// Run every command in a file
+    /// ## Params
+    /// * `path` - file to run
+    /// ---
+    /// ## Return
+    /// Summary of the execution, or an error if the file couldn't be read
+    pub fn run_file(&mut self, path: &Path) -> io::Result<ScriptSummary> {
+        let file = std::fs::File::open(path)?;
+
+        // remember the file so it can include others relative to it
+        self.files.push(path.canonicalize()?);
+        let summary = self.run_lines(io::BufReader::new(file).lines(), &path.display().to_string());
+        self.files.pop();
+
+        Ok(summary)
+    }
+
+    /// Run a file from another one, or from the REPL
+    /// ## Params
+    /// * `file` - path to the file, relative to the file being run or the working directory
+    /// ---
+    /// ## Return
+    /// An error if the file can't be read, it's already being run or any of its lines failed
+    fn include(&mut self, file: &str) -> Result<(), CommandError> {
+        let path = self.relative_path(file);
+        let summary = self.run_nested(&path)?;
+        match summary.errors.is_empty() {
+            true  => Ok(()),
+            false => Err(CommandError::Include { file: path.display().to_string(), errors: summary.errors })
+        }
+    }
+
+    /// Path for a file named in a command, relative to the file being run or the working directory
+    fn relative_path(&self, file: &str) -> PathBuf {
+        match self.files.last().and_then(|current| current.parent()) {
+            Some(dir) => dir.join(file),
+            None      => PathBuf::from(file)
+        }
+    }
+
+    /// Run a file from the one being run, if it's not being run already
+    /// ## Return
+    /// How many types were defined and the lines that failed, or an error if the file
+    /// can't be read or it would make a cycle
+    fn run_nested(&mut self, path: &Path) -> Result<ScriptSummary, CommandError> {
+        let read_error = |e: io::Error| CommandError::Program(ProgramError::IncludeRead(path.display().to_string(), e.to_string()));
+
+        let canonical = path.canonicalize().map_err(read_error)?;
+        if let Some(start) = self.files.iter().position(|f| *f == canonical) {
+            let mut cycle : Vec<String> = self.files[start..].iter().map(|f| f.display().to_string()).collect();
+            cycle.push(canonical.display().to_string());
+            return Err(CommandError::Program(ProgramError::IncludeCycle(cycle)))
+        }
+
+        self.run_file(path).map_err(read_error)
+    }
+
+    /// Save every type in the session to a file
+    /// ## Params
+    /// * `file` - path to the file, relative like `incluir` paths
+    /// * `format` - a script, or the binary format for big sessions
+    fn save(&mut self, file: &str, format: SessionFormat) -> Result<(), CommandError> {
+        let path = self.relative_path(file);
+        let order = self.manager.definition_order();
+        let bytes = match format {
+            SessionFormat::Script => self.session_script().into_bytes(),
+            SessionFormat::Binary => {
+                let types : Vec<session::Saving> = order
+                                                                .iter()
+                                                                .filter_map(|name| {
+                                                                    let allowed = self.allowed.get(name).map_or(&[][..], |rules| rules.as_slice());
+                                                                    self.manager.get(name).map(|t| (name, t, allowed, self.manager.origin(name), self.manager.extra_attributes(name), self.manager.budget(name)))
+                                                                })
+                                                                .collect();
+                session::encode(&types)
+            }
+        };
+
+        std::fs::write(&path, bytes).map_err(|e| CommandError::Program(ProgramError::SessionWrite(path.display().to_string(), e.to_string())))?;
+        self.out.line(&format!("Guardados {} tipos en '{}'", order.len(), path.display()));
+        Ok(())
+    }
+
+    /// Load the types saved in a file with `guardar`. Types already defined are kept
+    /// ## Params
+    /// * `file` - path to the file, relative like `incluir` paths
+    /// * `format` - format of the file, if none it's told by its contents
+    fn load(&mut self, file: &str, format: Option<SessionFormat>) -> Result<(), CommandError> {
+        let path = self.relative_path(file);
+        let origin = path.display().to_string();
+        let bytes = std::fs::read(&path).map_err(|e| CommandError::Program(ProgramError::SessionRead(origin.clone(), e.to_string())))?;
+
+        let summary = match format.unwrap_or_else(|| SessionFormat::detect(&bytes)) {
+            SessionFormat::Script => self.run_nested(&path)?,
+            SessionFormat::Binary => {
+                let types = session::decode(&bytes).map_err(|e| CommandError::Program(ProgramError::SessionRead(origin.clone(), e)))?;
+                let mut summary = ScriptSummary::default();
+                let mut kept = vec![];
+                let mut progress = Progress::new(&origin, self.config.progress);
+                let total = types.len();
+                for (i, saved) in types.into_iter().enumerate() {
+                    progress.update(i, summary.defined);
+                    if self.manager.get(&saved.name).is_some() {
+                        kept.push(saved.name);
+                        continue
+                    }
+                    let result = self.manager.add(saved.name.clone(), saved.value);
+                    match self.added(saved.name.clone(), result) {
+                        Err(e) => summary.errors.push(format!("{}: '{}': {}", origin, saved.name, e.display())),
+                        Ok(_)  => {
+                            self.manager.set_origin(&saved.name, saved.origin.or_else(|| Some(origin.clone())));
+                            if saved.budget.is_some() {
+                                self.manager.set_budget(&saved.name, saved.budget);
+                            }
+                            if !saved.attributes.is_empty() {
+                                if let Err(e) = self.manager.add_attributes(&saved.name, None, saved.attributes) {
+                                    summary.errors.push(format!("{}: '{}': {}", origin, saved.name, e.display()));
+                                }
+                            }
+                            if !saved.allowed.is_empty() {
+                                self.allowed.insert(saved.name, saved.allowed);
+                            }
+                            summary.defined += 1;
+                        }
+                    }
+                }
+                progress.finish(total, summary.defined);
+                if !kept.is_empty() {
+                    self.out.line(&format!("Ya estaban definidos: {}", kept.join(", ")));
+                }
+                if summary.defined > 0 {
+                    self.changed();
+                }
+                summary
+            }
+        };
+
+        self.out.line(&format!("Cargados {} tipos de '{}'", summary.defined, origin));
+        match summary.errors.is_empty() {
+            true  => Ok(()),
+            false => Err(CommandError::Include { file: origin, errors: summary.errors })
+        }
+    }
+
+    /// Manager of a session, the one in use or another
+    fn session_manager(&self, name: &Name) -> Option<&TypeManager> {
+        match *name == self.session {
+            true  => Some(&self.manager),
+            false => self.sessions.get(name).map(|w| &w.manager)
+        }
+    }
+
+    /// Show the types added, removed and modified from one session to another, with
+    /// how the layout of modified types changed under the C strategy
+    fn diff(&mut self, before: &Name, after: &Name) -> Result<(), ProgramError> {
+        let old = self.session_manager(before).ok_or_else(|| ProgramError::UnknownSession(before.clone()))?;
+        let new = self.session_manager(after).ok_or_else(|| ProgramError::UnknownSession(after.clone()))?;
+        let changes = crate::type_system::diff(old, new);
+
+        if changes.is_empty() {
+            self.out.line(&format!("Las sesiones '{}' y '{}' tienen los mismos tipos", before, after));
+            return Ok(())
+        }
+
+        let count = |f : fn(&TypeChange) -> bool| changes.iter().filter(|c| f(c)).count();
+        self.out.line(&format!(
+            "De '{}' a '{}': {} añadidos, {} quitados, {} modificados",
+            before, after,
+            count(|c| matches!(c, TypeChange::Added(_))),
+            count(|c| matches!(c, TypeChange::Removed(_))),
+            count(|c| matches!(c, TypeChange::Modified(_)))
+        ));
+
+        for line in Program::change_lines(&changes) {
+            self.out.line(&line);
+        }
+
+        Ok(())
+    }
+
+    /// Tell how the types changed from another program to this one, as `diff` does with
+    /// sessions. `watch` compares every run of a script with the previous one this way
+    /// ## Params
+    /// * `before` - program with the old types
+    /// ---
+    /// ## Return
+    /// A line for every type added, removed or modified, followed by the fields that moved
+    pub fn changes_since(&self, before: &Program) -> Vec<String> {
+        Program::change_lines(&crate::type_system::diff(&before.manager, &self.manager))
+    }
+
+    /// Lines for every change in types, the fields of a modified struct indented under it
+    fn change_lines(changes: &[TypeChange]) -> Vec<String> {
+        let mut lines = vec![];
+        for change in changes {
+            let d = match change {
+                TypeChange::Added(name)   => { lines.push(format!("+ {}", name)); continue },
+                TypeChange::Removed(name) => { lines.push(format!("- {}", name)); continue },
+                TypeChange::Modified(d)   => d
+            };
+
+            lines.push(format!("~ {}: {}", d.name, Program::change_summary(d)));
+            lines.extend(d.fields.iter().map(Program::field_change));
+        }
+
+        lines
+    }
+
+    /// How the size and alignment of a modified type changed, in a line
+    fn change_summary(d: &TypeDiff) -> String {
+        let mut layout = vec![];
+        if d.size.0 != d.size.1 {
+            layout.push(format!("tamaño {} -> {}", d.size.0, d.size.1));
+        }
+        if d.align.0 != d.align.1 {
+            layout.push(format!("alineación {} -> {}", d.align.0, d.align.1));
+        }
+        if d.padding.0 != d.padding.1 {
+            layout.push(format!("relleno {} -> {}", d.padding.0, d.padding.1));
+        }
+        match (layout.is_empty(), d.layout_changed()) {
+            (false, _)    => layout.join(", "),
+            (true, true)  => String::from("mismo tamaño, otros desplazamientos"),
+            (true, false) => String::from("definido de otra forma, misma disposición")
+        }
+    }
+
+    /// How a struct field moved, appeared or disappeared, indented under its type
+    fn field_change(field: &FieldChange) -> String {
+        match field {
+            FieldChange::Moved(name, from, to) => format!("    {}: desplazamiento {} -> {}", name, from, to),
+            FieldChange::Added(name, offset)   => format!("    + {}: desplazamiento {}", name, offset),
+            FieldChange::Removed(name, offset) => format!("    - {}: desplazamiento {}", name, offset)
+        }
+    }
+
+    /// Check that an expression holds, such as `tamaño(s) == 12`, so scripts can guard
+    /// layouts against regressions. Any value but 0 holds
+    /// ## Params
+    /// * `text` - expression as written, to tell which one failed
+    /// * `e` - parsed expression
+    /// ---
+    /// ## Return
+    /// An error if it doesn't hold or can't be evaluated, which fails the script running it
+    fn assert(&mut self, text: &str, e: &Expr) -> Result<(), CommandError> {
+        let scope = Scope { manager: &self.manager, variables: &self.variables };
+        let eval = |e: &Expr| e.eval(&scope).map_err(|e| CommandError::Type(TypeError::Expression(e)));
+        if eval(e)? != 0 {
+            self.out.line(&format!("Se cumple '{}'", text));
+            return Ok(())
+        }
+
+        let sides = match e {
+            Expr::Compare(cmp, l, r) => Some((eval(l)?, *cmp, eval(r)?)),
+            _ => None
+        };
+        Err(CommandError::Program(ProgramError::AssertionFailed(text.to_string(), sides)))
+    }
+
+    /// Replay a journal in a session of its own and compare the types it rebuilds with the
+    /// ones saved in a file, to catch changes in how commands or layouts behave between
+    /// versions. The session in use doesn't change
+    /// ## Params
+    /// * `journal` - journal to replay, relative like `incluir` paths
+    /// * `snapshot` - session saved with `guardar` the journal should rebuild
+    /// ---
+    /// ## Return
+    /// An error if either file can't be read, any journal line failed, or the types differ
+    fn verify_replay(&mut self, journal: &str, snapshot: &str) -> Result<(), CommandError> {
+        let (journal, snapshot) = (self.relative_path(journal), self.relative_path(snapshot));
+        let isolated = || {
+            let mut program = Program::with_config(Config { autosave: false, journal: None, read_only: false, ..self.config.clone() });
+            program.capture_output();
+            program
+        };
+
+        let mut replayed = isolated();
+        replayed.replay(&journal.display().to_string())?;
+        let mut saved = isolated();
+        saved.load(&snapshot.display().to_string(), None)?;
+
+        let changes = crate::type_system::diff(&saved.manager, &replayed.manager);
+        let files = format!("'{}' con '{}'", journal.display(), snapshot.display());
+        if changes.is_empty() {
+            self.out.line(&format!("Reproducción verificada: '{}' da los mismos {} tipos que '{}'", journal.display(), saved.manager.len(), snapshot.display()));
+            return Ok(())
+        }
+
+        // dependencies come first, so the first divergence is usually the cause of the rest
+        let order : Vec<Name> = saved.manager.definition_order().into_iter().chain(replayed.manager.definition_order()).collect();
+        let position = |change: &TypeChange| {
+            let name = match change {
+                TypeChange::Added(n) | TypeChange::Removed(n) => n,
+                TypeChange::Modified(d) => &d.name
+            };
+            order.iter().position(|n| n == name).unwrap_or(order.len())
+        };
+        let first = changes.iter().min_by_key(|c| position(c)).unwrap_or(&changes[0]);
+
+        let mut lines = vec![match first {
+            TypeChange::Added(name)   => format!("'{}' no está en la sesión guardada", name),
+            TypeChange::Removed(name) => format!("falta '{}', que está en la sesión guardada", name),
+            TypeChange::Modified(d)   => format!("'{}' difiere: {}", d.name, Program::change_summary(d))
+        }];
+        if let TypeChange::Modified(d) = first {
+            lines.extend(d.fields.iter().map(Program::field_change));
+        }
+        if changes.len() > 1 {
+            lines.push(format!("y {} tipos más difieren", changes.len() - 1));
+        }
+        Err(CommandError::Program(ProgramError::ReplayMismatch(files, lines)))
+    }
+
+    /// Merge the types saved in a file with `guardar` into ours. Types defined the same way
+    /// in both are left as they are, conflicts are solved as the strategy says
+    /// ## Params
+    /// * `file` - path to the file, relative like `incluir` paths
+    /// * `strategy` - what to do with types named like ours but defined otherwise
+    fn merge(&mut self, file: &str, strategy: &MergeStrategy) -> Result<(), CommandError> {
+        let path = self.relative_path(file);
+        let origin = path.display().to_string();
+
+        // read the file on its own first, so a broken file changes nothing
+        let mut other = Program::with_config(Config { autosave: false, ..self.config.clone() });
+        other.capture_output();
+        other.manager.set_abi(self.manager.abi());
+        other.load(&origin, None)?;
+
+        let mut renamed : HashMap<Name, Name> = HashMap::new();
+        let (mut merged, mut identical, mut kept, mut replaced, mut errors) = (0, vec![], vec![], vec![], vec![]);
+        for name in other.manager.definition_order() {
+            let original = match other.manager.get(&name) {
+                Some(t) => t,
+                None => continue
+            };
+            let theirs = original.clone().rename_references(|n| renamed.get(n).unwrap_or(n).clone());
+
+            let target = match self.manager.resolve(&name).cloned() {
+                None => name.clone(),
+                Some(stored) => {
+                    let ours = self.manager.get(&stored).map_or(String::new(), |t| Program::definition_command(&stored, t));
+                    if ours == Program::definition_command(&stored, &theirs) {
+                        identical.push(name);
+                        continue
+                    }
+                    match strategy {
+                        MergeStrategy::Skip => { kept.push(name); continue },
+                        MergeStrategy::Prefix(prefix) => format!("{}{}", prefix, name),
+                        MergeStrategy::Replace => {
+                            match self.same_layout(&stored, &other.manager, &name) {
+                                true  => {
+                                    let result = self.manager.replace(&stored, theirs);
+                                    match self.added(stored.clone(), result) {
+                                        Ok(_)  => {
+                                            if let Some(origin) = other.manager.origin(&name) {
+                                                self.manager.set_origin(&stored, Some(origin.to_string()));
+                                            }
+                                            replaced.push(stored)
+                                        },
+                                        Err(e) => errors.push(format!("'{}': {}", name, e.display()))
+                                    }
+                                },
+                                false => kept.push(name)
+                            }
+                            continue
+                        }
+                    }
+                }
+            };
+
+            let result = self.manager.add(target.clone(), theirs);
+            match self.added(target.clone(), result) {
+                Err(e) => errors.push(format!("'{}': {}", target, e.display())),
+                Ok(_)  => {
+                    if let Some(origin) = other.manager.origin(&name) {
+                        self.manager.set_origin(&target, Some(origin.to_string()));
+                    }
+                    if let Some(rules) = other.allowed.get(&name) {
+                        self.allowed.insert(target.clone(), rules.clone());
+                    }
+                    if target != name {
+                        renamed.insert(name, target);
+                    }
+                    merged += 1;
+                }
+            }
+        }
+
+        self.out.line(&format!("Fusionados {} tipos de '{}'", merged, origin));
+        if !identical.is_empty() {
+            self.out.line(&format!("Definidos igual, sin cambios: {}", identical.join(", ")));
+        }
+        let mut renames : Vec<String> = renamed.iter().map(|(from, to)| format!("{} -> {}", from, to)).collect();
+        renames.sort();
+        if !renames.is_empty() {
+            self.out.line(&format!("Renombrados: {}", renames.join(", ")));
+        }
+        if !replaced.is_empty() {
+            self.out.line(&format!("Reemplazados, con la misma disposición: {}", replaced.join(", ")));
+        }
+        if !kept.is_empty() {
+            let reason = match strategy {
+                MergeStrategy::Replace => "con otra disposición",
+                _ => "definidos de otra forma"
+            };
+            self.out.line(&format!("Conflictos {}, se mantienen los actuales: {}", reason, kept.join(", ")));
+        }
+
+        if merged + replaced.len() > 0 {
+            self.changed();
+        }
+        match errors.is_empty() {
+            true  => Ok(()),
+            false => Err(CommandError::Include { file: origin, errors })
+        }
+    }
+
+    /// Tells if one of our types takes the same memory as a type in another manager: same
+    /// size and alignment under the C strategy and, for structs, the same member offsets
+    fn same_layout(&self, ours: &Name, other: &TypeManager, theirs: &Name) -> bool {
+        let (a, b) = match (self.manager.get(ours), other.get(theirs)) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return false
+        };
+
+        let offsets = match (a, b) {
+            (Type::Struct(a), Type::Struct(b)) => a.c_offsets(&self.manager) == b.c_offsets(other),
+            (Type::Struct(_), _) | (_, Type::Struct(_)) => false,
+            _ => true
+        };
+        offsets
+            && Strategy::C.type_size(a, &self.manager) == Strategy::C.type_size(b, other)
+            && Strategy::C.type_align(a, &self.manager) == Strategy::C.type_align(b, other)
+    }
+
+    /// Run a `sesion` command: list sessions, create one, switch to another or lock the one
+    /// in use. Once there's more than one session, every output line starts with the name
+    /// of the one in use
+    fn session(&mut self, command: SessionCommand) -> Result<(), ProgramError> {
+        let name = match command {
+            SessionCommand::List => {
+                let mut names : Vec<&Name> = self.sessions.keys().chain(std::iter::once(&self.session)).collect();
+                names.sort();
+                let mut table = Table::new(&["", "Sesión", "ABI", "Puntero", "Tipos", "Solo lectura"]).align(3, Align::Right).align(4, Align::Right);
+                for name in names {
+                    let (current, manager, locked) = match self.sessions.get(name) {
+                        Some(w) => ("", &w.manager, w.locked),
+                        None    => ("*", &self.manager, self.locked)
+                    };
+                    let abi = manager.abi();
+                    let locked = match locked {
+                        true  => "sí",
+                        false => ""
+                    };
+                    table.row(vec![current.to_string(), name.clone(), abi.name.to_string(), abi.pointer_size.to_string(), manager.len().to_string(), locked.to_string()]);
+                }
+                self.out.line(table.render(self.config.tables).trim_end());
+                return Ok(())
+            },
+            SessionCommand::New(name, abi) => {
+                if name == self.session || self.sessions.contains_key(&name) {
+                    return Err(ProgramError::SessionExists(name))
+                }
+                let mut manager = Program::new_manager(&self.config);
+                manager.set_abi(abi);
+                self.sessions.insert(name.clone(), Workspace { manager, allowed: HashMap::new(), locked: self.config.read_only });
+                name
+            },
+            SessionCommand::Lock | SessionCommand::Unlock => {
+                // the whole program may be read only, then no session can be unlocked
+                self.locked = command == SessionCommand::Lock || self.config.read_only;
+                return match self.locked {
+                    true if command == SessionCommand::Unlock => Err(ProgramError::ReadOnly(self.session.clone())),
+                    true  => { self.out.line(&format!("La sesión '{}' es de solo lectura", self.session)); Ok(()) },
+                    false => { self.out.line(&format!("La sesión '{}' se puede cambiar", self.session)); Ok(()) }
+                }
+            },
+            SessionCommand::Use(name) => {
+                if name != self.session && !self.sessions.contains_key(&name) {
+                    return Err(ProgramError::UnknownSession(name))
+                }
+                name
+            }
+        };
+
+        // changes to the main session are saved before it stops being the one in use
+        self.autosave();
+        if let Some(next) = self.sessions.remove(&name) {
+            let previous = Workspace {
+                manager: std::mem::replace(&mut self.manager, next.manager),
+                allowed: std::mem::replace(&mut self.allowed, next.allowed),
+                locked:  std::mem::replace(&mut self.locked, next.locked)
+            };
+            self.sessions.insert(std::mem::replace(&mut self.session, name), previous);
+        }
+
+        self.revision += 1;
+        self.out.set_prefix(&format!("[{}] ", self.session));
+        self.out.line(&format!("Usando la sesión '{}' ({}, punteros de {} bytes)", self.session, self.manager.abi().name, self.manager.pointer_size()));
+        Ok(())
+    }
+
+    /// Run a script: a command per line, empty lines and lines starting with `#` are ignored.
+    /// While the wizard runs every line but comments is an answer for it, empty ones too.
+    /// A `salir` command ends the script but not the program
+    /// ## Params
+    /// * `source` - script contents
+    /// * `origin` - where the script comes from, to point errors at it
+    /// ---
+    /// ## Return
+    /// How many types were defined and an error message for every failed line
+    pub fn run_script(&mut self, source: &str, origin: &str) -> ScriptSummary {
+        self.run_lines(source.lines().map(Ok), origin)
+    }
+
+    /// Run a script a line at a time as it's read, so big files are never held in memory
+    /// whole. With `progreso` on, runs outside of other scripts show how far they got
+    /// ## Params
+    /// * `lines` - lines of the script, a line that can't be read ends it
+    /// * `origin` - where the lines come from, for error messages
+    fn run_lines<I, S>(&mut self, lines: I, origin: &str) -> ScriptSummary
+        where
+            I: Iterator<Item = io::Result<S>>,
+            S: AsRef<str>
+    {
+        let mut summary = ScriptSummary::default();
+        let mut progress = Progress::new(origin, self.config.progress && self.files.len() <= 1);
+        let mut read = 0;
+        self.running_depth += 1;
+
+        for (i, line) in lines.enumerate() {
+            // in strict mode the first error stops the script
+            if self.config.strict && !summary.errors.is_empty() {
+                break
+            }
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    let error = CommandError::Program(ProgramError::IncludeRead(origin.to_string(), e.to_string()));
+                    summary.errors.push(format!("{}:{}: {}", origin, i + 1, error.display()));
+                    break
+                }
+            };
+            read = i + 1;
+            progress.update(read, summary.defined);
+            let line = line.as_ref().trim();
+            if line.starts_with('#') {
+                if let Some(origin) = parse_origin(line) {
+                    self.pending_origin = Some(origin);
+                    continue
+                }
+                match lint::parse_suppression(line) {
+                    Some(Ok(rules)) => self.pending_allow = rules,
+                    Some(Err(rule)) => {
+                        let error = CommandError::Program(ProgramError::UnknownLintRule(rule));
+                        summary.errors.push(format!("{}:{}: {}", origin, i + 1, error.display()))
+                    },
+                    None => {}
+                }
+                continue
+            }
+
+            // a running wizard takes every line as an answer, empty ones too
+            if self.wizard.is_some() {
+                match self.wizard_answer(line) {
+                    Ok(defined) => if defined { summary.defined += 1 },
+                    Err(e) => summary.errors.push(format!("{}:{}: {}", origin, i + 1, CommandError::Type(e).display()))
+                }
+                self.out.flush(false);
+                continue
+            }
+
+            if line.is_empty() {
+                continue
+            }
+
+            // salir ends the script, not the program
+            if let Ok(Action::Exit) = Program::parse_with(line, &self.config.keywords) {
+                break
+            }
+
+            let types = self.manager.len();
+            let outer = self.origin.replace(format!("{}:{}", origin, i + 1));
+            let result = self.run_command(line);
+            self.origin = outer;
+            self.pending_allow.clear();
+            self.pending_origin = None;
+            summary.defined += self.manager.len().saturating_sub(types);
+
+            // script output is never paged, it could be waiting for input forever
+            self.out.flush(false);
+
+            if let Err(e) = result {
+                summary.errors.push(format!("{}:{}: {}", origin, i + 1, e.display()))
+            }
+        }
+
+        progress.finish(read, summary.defined);
+        self.finished();
+        summary
+    }
+
+    /// Run the commands in a JSON lines file, an object per line with the command to
+    /// run as `"command"` and optionally the lint rules it's allowed to break as `"allow"`.
+    /// Lines are read and run one at a time, so files with any amount of types can be imported
+    /// ## Params
+    /// * `file` - path to the file, relative like `incluir` paths
+    fn import_lines(&mut self, file: &str) -> Result<(), CommandError> {
+        let path = self.relative_path(file);
+        let origin = path.display().to_string();
+        let input = std::fs::File::open(&path).map_err(|e| CommandError::Program(ProgramError::Import(origin.clone(), e.to_string())))?;
+
+        let mut progress = Progress::new(&origin, self.config.progress);
+        let (mut read, mut defined, mut errors) = (0, 0, vec![]);
+        for (i, line) in io::BufReader::new(input).lines().enumerate() {
+            let line = match line {
+                Ok(l) => l,
+                Err(e) => { errors.push(format!("{}:{}: {}", origin, i + 1, e)); break }
+            };
+            read = i + 1;
+            progress.update(read, defined);
+            if line.trim().is_empty() {
+                continue
+            }
+
+            let (command, allow) = match Program::json_line(&line) {
+                Ok(entry) => entry,
+                Err(e) => { errors.push(format!("{}:{}: {}", origin, i + 1, CommandError::Program(e).display())); continue }
+            };
+            let types = self.manager.len();
+            self.pending_allow = allow;
+            let result = self.run_command(&command);
+            self.pending_allow.clear();
+            defined += self.manager.len().saturating_sub(types);
+            self.out.flush(false);
+            if let Err(e) = result {
+                errors.push(format!("{}:{}: {}", origin, i + 1, e.display()))
+            }
+        }
+
+        progress.finish(read, defined);
+        self.out.line(&format!("Importados {} tipos de '{}'", defined, origin));
+        match errors.is_empty() {
+            true  => Ok(()),
+            false => Err(CommandError::Include { file: origin, errors })
+        }
+    }
+
+    /// Read a line of a JSON lines import
+    /// ## Return
+    /// The command and the lint rules allowed for it, or what's wrong with the line
+    fn json_line(line: &str) -> Result<(String, Vec<String>), ProgramError> {
+        let value = json::parse(line).map_err(ProgramError::InvalidArgument)?;
+        Program::json_command(&value)
+    }
+
+    /// Read the command of an object of a JSON lines import or request
+    /// ## Return
+    /// The command and the lint rules allowed for it, or what's wrong with the object
+    fn json_command(value: &json::Value) -> Result<(String, Vec<String>), ProgramError> {
+        let command = value
+                        .get("command")
+                        .and_then(|c| c.as_str())
+                        .ok_or_else(|| ProgramError::InvalidArgument(String::from("falta el texto \"command\"")))?;
+
+        let mut allow = vec![];
+        for rule in value.get("allow").and_then(|a| a.as_array()).unwrap_or_default() {
+            match rule.as_str() {
+                Some(r) if lint::RULES.iter().any(|known| known.name == r) => allow.push(r.to_string()),
+                Some(r) => return Err(ProgramError::UnknownLintRule(r.to_string())),
+                None => return Err(ProgramError::InvalidArgument(String::from("\"allow\" tiene que ser una lista de textos")))
+            }
+        }
+
+        Ok((command.to_string(), allow))
+    }
+
+    /// Tells if this program should run
+    pub fn should_run(&self) -> bool {
+        self.running
+    }
+
+    /// Run an iteration for the program
+    pub fn run(&mut self) {
+
+        // Command buffer: store user input in this line
+        let mut line = String::new();
+
+        print!("{}", self.prompt()); // print prompt
+        // flush so the print! doesn't mess up the execution order with read_line
+        io::stdout().flush().expect("Couldn't flush stdout"); 
+
+        // Read a single line, stop running when there's no more input
+        match io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => { self.stop(); return },
+            Ok(_) => {}
+        }
+
+        self.run_line(&line)
+    }
+
+    /// Parse and execute a single line of input, printing any error
+    /// ## Params
+    /// * `line` - line to run, as written by the user
+    pub fn run_line(&mut self, line: &str) {
+        let (start, types) = (Instant::now(), self.manager.len());
+        let result = match self.wizard {
+            Some(_) => self.wizard_answer(line).map(|_| ()).map_err(CommandError::Type),
+            None    => self.run_interactive(line)
+        };
+        metrics::command(result.is_ok(), start.elapsed(), self.manager.len().saturating_sub(types));
+
+        if let Err(e) = result {
+            self.out.line(&e.display())
+        }
+
+        // wizard answers don't go through run_command
+        self.autosave();
+        self.out.flush(self.config.pager)
+    }
+
+    /// Run a command written by the user, expanding history references and storing
+    /// it in the history, even if it fails so it can be fixed and run again
+    fn run_interactive(&mut self, line: &str) -> Result<(), CommandError> {
+        let line = match self.history.expand(line).map_err(CommandError::Program)? {
+            Some(command) => {
+                self.out.line(&command);
+                command
+            },
+            None => line.to_string()
+        };
+
+        self.history.push(&line);
+        self.run_command(&line)
+    }
+
+    /// Parse and execute a single line of input
+    /// ## Params
+    /// * `line` - line to run, as written by the user
+    /// ---
+    /// ## Return
+    /// Error describing the issue if the command failed, or nothing on success
+    pub fn run_command(&mut self, line: &str) -> Result<(), CommandError> {
+        // output redirected to a file is written there as the command goes, errors and warnings are not
+        let (line, redirect) = self.split_redirect(line);
+        if let (Some((file, _)), true) = (&redirect, self.config.sandbox) {
+            return Err(CommandError::Program(ProgramError::Sandboxed(file.clone())))
+        }
+        if let Some((file, append)) = &redirect {
+            let path = self.relative_path(file);
+            let opened = std::fs::OpenOptions::new().write(true).create(true).append(*append).truncate(!*append).open(&path);
+            match opened {
+                Ok(f) => self.out.redirect(Box::new(io::BufWriter::new(f))),
+                Err(e) => return Err(CommandError::Program(ProgramError::OutputWrite(path.display().to_string(), e.to_string())))
+            }
+        }
+
+        // commands run by this one, such as the lines of a script, are part of its computation
+        let computation = self.canceller.start();
+        let timed_out = limits::timed_out();
+        self.running_depth += 1;
+        let mut result = self.dispatch(line);
+        self.finished();
+        if let Some((file, _)) = redirect {
+            if let (Err(e), true) = (self.out.end_redirect(), result.is_ok()) {
+                result = Err(CommandError::Program(ProgramError::OutputWrite(self.relative_path(&file).display().to_string(), e.to_string())));
+            }
+        }
+        if computation.cancelled() {
+            self.out.line("[AVISO]: Se canceló el cálculo, los resultados son los mejores encontrados hasta entonces");
+        }
+        if let Some(limit) = self.manager.limits().optimizer.filter(|_| computation.outermost() && limits::timed_out() != timed_out) {
+            let warning = format!("El optimizador llegó a su límite de {} ms, los resultados son los mejores encontrados hasta entonces", limit.as_millis());
+            match self.config.strict {
+                true  => result = result.and(Err(CommandError::Program(ProgramError::Strict(vec![warning])))),
+                false => self.out.line(&format!("[AVISO]: {}", warning))
+            }
+        }
+        result
+    }
+
+    /// Split a redirection off the end of a command: `> archivo` writes its output to a
+    /// file, `>> archivo` appends to it. Commands taking expressions, where `>` compares,
+    /// have no redirections
+    /// ## Return
+    /// The command without the redirection, and the file with whether to append to it
+    fn split_redirect<'a>(&self, line: &'a str) -> (&'a str, Option<(String, bool)>) {
+        let words : Vec<&str> = line.split_whitespace().collect();
+        let compares = words.first().and_then(|w| self.config.keywords.get(w)).is_some_and(|v| matches!(v, Verb::Calculate | Verb::Assert | Verb::Let | Verb::Atomic));
+        let (operator, file) = match words.as_slice() {
+            [_, .., operator, file] if !compares && (*operator == ">" || *operator == ">>") => (*operator, *file),
+            _ => return (line, None)
+        };
+
+        // the operator is the last one in the line, followed by the file
+        let end = line.trim_end().len() - file.len();
+        let start = line[..end].trim_end().len() - operator.len();
+        (line[..start].trim_end(), Some((file.to_string(), operator == ">>")))
+    }
+
+    /// Stops the command running in this program, from any thread
+    pub fn canceller(&self) -> Canceller {
+        self.canceller.clone()
+    }
+
+    /// Make commands stop with the given canceller, such as one shared by every program a client uses
+    pub fn use_canceller(&mut self, canceller: Canceller) {
+        self.canceller = canceller;
+    }
+
+    /// Run a line as a command, macro calls included
+    fn dispatch(&mut self, line: &str) -> Result<(), CommandError> {
+        if let Some((name, args)) = macros::parse_call(line) {
+            return self.expand_macro(&name, &args)
+        }
+
+        let action = self.parse_command(line).map_err(CommandError::Program)?;
+        if self.locked && self.modifies(&action) {
+            return Err(CommandError::Program(ProgramError::ReadOnly(self.session.clone())))
+        }
+
+        // commands run by a recorded one, such as the lines of a loaded session, are not recorded again
+        let journaled = self.journal.is_some() && !self.recording && match &action {
+            Action::Let(..) | Action::Session(SessionCommand::New(..)) | Action::Session(SessionCommand::Use(_)) => true,
+            Action::Wizard => false,
+            action => self.modifies(action)
+        };
+        self.recording |= journaled;
+
+        // types waiting for others are added before anything looks at types
+        if self.manager.has_pending() && !matches!(action, Action::ResolvePending) && !self.modifies(&action) {
+            self.settle();
+        }
+        let unresolved = |e: CommandError, manager: &TypeManager| match e {
+            CommandError::Type(TypeError::TypeDoesNotExist(name)) if manager.pending_type(&name).is_some() => {
+                let missing = manager.pending().into_iter().filter(|(n, _)| *n == name).collect();
+                CommandError::Type(TypeError::Unresolved(missing))
+            },
+            e => e
+        };
+
+        let defined = action.defined().cloned();
+        let edited = match &action {
+            Action::Attribute(name, ..) | Action::Flatten(_, name, _) => Some(name.clone()),
+            _ => defined.clone()
+        };
+        let result = match action {
+            Action::Include(file) => self.include(&file),
+            Action::Save(file, format) => self.save(&file, format),
+            Action::Load(file, format) => self.load(&file, format),
+            Action::Session(command) => self.session(command).map_err(CommandError::Program),
+            Action::Merge(file, strategy) => self.merge(&file, &strategy),
+            Action::Diff(before, after) => self.diff(&before, &after).map_err(CommandError::Program),
+            Action::Replay(file) => self.replay(&file),
+            Action::ImportLines(file) => self.import_lines(&file),
+            Action::VerifyReplay(journal, snapshot) => self.verify_replay(&journal, &snapshot),
+            Action::Trace(name, file, line) => self.trace(&name, &file, line),
+            Action::Image(name, file, strategy) => self.image(&name, &file, strategy),
+            Action::Document(dir) => self.document(&dir).map_err(CommandError::Program),
+            Action::Assert(text, e) => self.assert(&text, &e),
+            Action::Export(format) => self.export_command(format).map_err(CommandError::Program),
+            Action::Validate => self.validate().map_err(CommandError::Program),
+            action => self.execute(action).map_err(CommandError::Type)
+        };
+        let result = result.map_err(|e| self.located(unresolved(e, &self.manager), defined.as_ref()));
+        if let (Ok(_), Some(name)) = (&result, &edited) {
+            self.manager.record_growth(name, line.trim(), self.origin.as_deref());
+        }
+
+        if journaled {
+            self.recording = false;
+            if result.is_ok() {
+                self.record(line);
+            }
+        }
+        result
+    }
+
+    /// Tell where the type an error is about was defined, if it was defined in a file
+    /// ## Params
+    /// * `e` - error of a command
+    /// * `defined` - type the command was defining, if any, for redefinitions
+    fn located(&self, e: CommandError, defined: Option<&Name>) -> CommandError {
+        let subject = match &e {
+            CommandError::Type(TypeError::TypeRedefinition) => defined,
+            CommandError::Type(e) => e.subject(),
+            _ => None
+        };
+        match subject.and_then(|name| self.manager.origin(name).map(|origin| (name.clone(), origin.to_string()))) {
+            Some((name, origin)) => CommandError::Defined { name, origin, error: Box::new(e) },
+            None => e
+        }
+    }
+
+    /// Append a command to the journal, if there's one
+    fn record(&mut self, command: &str) {
+        if let Some(journal) = &self.journal {
+            if let Err(e) = journal.record(command) {
+                self.out.line(&format!("[AVISO]: No se pudo escribir en el diario '{}': {}", journal.path().display(), e));
+            }
+        }
+    }
+
+    /// Run again every command in a journal, in order. Entries are not recorded again
+    /// when the journal being run is the one being written
+    /// ## Params
+    /// * `file` - path to the journal, relative like `incluir` paths
+    fn replay(&mut self, file: &str) -> Result<(), CommandError> {
+        let path = self.relative_path(file);
+        let origin = path.display().to_string();
+        let read_error = |e: io::Error| CommandError::Program(ProgramError::IncludeRead(origin.clone(), e.to_string()));
+        let text = std::fs::read_to_string(&path).map_err(read_error)?;
+        let canonical = path.canonicalize().map_err(read_error)?;
+
+        let same = |j: &Journal| j.path().canonicalize().is_ok_and(|p| p == canonical);
+        let paused = match self.journal.as_ref().is_some_and(same) {
+            true  => self.journal.take(),
+            false => None
+        };
+        self.files.push(canonical);
+
+        let (mut commands, mut errors) = (0, vec![]);
+        for (i, line) in text.lines().enumerate() {
+            let command = match journal::parse_entry(line) {
+                None => continue,
+                Some(Err(e)) => { errors.push(format!("{}:{}: {}", origin, i + 1, e)); continue },
+                Some(Ok(command)) => command
+            };
+            match self.run_command(command) {
+                Ok(()) => commands += 1,
+                Err(e) => errors.push(format!("{}:{}: {}", origin, i + 1, e.display()))
+            }
+        }
+
+        self.files.pop();
+        if paused.is_some() {
+            self.journal = paused;
+        }
+
+        self.out.line(&format!("Reproducidos {} comandos de '{}'", commands, origin));
+        match errors.is_empty() {
+            true  => Ok(()),
+            false => Err(CommandError::Include { file: origin, errors })
+        }
+    }
+
+    /// Run a `diario` command: tell which journal is being written, start one or stop it
+    fn journal(&mut self, command: JournalCommand) {
+        match command {
+            JournalCommand::Show => match &self.journal {
+                Some(j) => self.out.line(&format!("Escribiendo el diario en '{}'", j.path().display())),
+                None    => self.out.line("No se está escribiendo ningún diario")
+            },
+            JournalCommand::Start(file) => {
+                let path = self.relative_path(&file);
+                self.out.line(&format!("Escribiendo el diario en '{}'", path.display()));
+                self.journal = Some(Journal::new(&path));
+            },
+            JournalCommand::Stop => {
+                if let Some(j) = self.journal.take() {
+                    self.out.line(&format!("Se dejó de escribir el diario en '{}'", j.path().display()));
+                }
+            }
+        }
+    }
+
+    /// Run every command a macro expands to, stopping at the first error
+    /// ## Params
+    /// * `name` - macro to expand
+    /// * `args` - a value for every macro parameter
+    fn expand_macro(&mut self, name: &str, args: &[String]) -> Result<(), CommandError> {
+        let definition = match self.macros.get(name) {
+            Some(m) => m.clone(),
+            None    => return Err(CommandError::Program(ProgramError::InvalidAction(name.to_string())))
+        };
+
+        let commands = definition.expand(name, args).map_err(CommandError::Program)?;
+        let wrap = |command: String, error: CommandError| CommandError::Macro {
+            name: name.to_string(),
+            origin: definition.origin.clone(),
+            command,
+            error: Box::new(error)
+        };
+
+        if self.depth >= MAX_MACRO_DEPTH {
+            let call = format!("{}({})", name, args.join(", "));
+            return Err(wrap(call, CommandError::Program(ProgramError::MacroRecursion(name.to_string()))))
+        }
+
+        self.depth += 1;
+        let mut result = Ok(());
+        for command in commands {
+            match self.run_command(&command) {
+                Ok(()) => continue,
+                // point at the macro only once when it calls itself forever
+                Err(e) if e.is_macro_recursion() => result = Err(e),
+                Err(e) => result = Err(wrap(command, e))
+            }
+            break
+        }
+        self.depth -= 1;
+
+        result
+    }
+
+    /// Text asking for the next line: `?> ` while the wizard runs, the configured prompt otherwise
+    pub fn prompt(&self) -> String {
+        if self.wizard.is_some() {
+            return String::from("?> ")
+        }
+
+        self.config.prompt
+            .replace("{n}", &self.manager.len().to_string())
+            .replace("{macros}", &self.macros.len().to_string())
+            .replace("{cambios}", if self.unsaved { "*" } else { "" })
+            .replace("{sesion}", &self.session)
+            .replace("{abi}", self.manager.abi().name)
+    }
+
+    /// Feed a line to the running wizard, adding the struct once it's complete
+    /// ## Return
+    /// Whether a struct was defined, or the error found defining it
+    fn wizard_answer(&mut self, line: &str) -> Result<bool, TypeError> {
+        let wizard = match &mut self.wizard {
+            Some(w) => w,
+            None    => return Ok(false)
+        };
+
+        match wizard.feed(line, &self.manager) {
+            WizardResult::Continue(messages) => {
+                for m in messages {
+                    self.out.line(&m);
+                }
+                self.out.line(&wizard.question());
+                Ok(false)
+            },
+            WizardResult::Cancelled => {
+                self.wizard = None;
+                self.out.line("Asistente cancelado");
+                Ok(false)
+            },
+            WizardResult::Done(name, members, fields) => {
+                self.wizard = None;
+                let s = Struct::with_fields(members, fields);
+                let command = Program::definition_command(&name, &Type::Struct(s.clone()));
+                self.execute(Action::AddStruct(name.clone(), s))?;
+                self.record(&command);
+                self.out.line(&format!("Struct '{}' definido", name));
+                Ok(true)
+            }
+        }
+    }
+
+    /// Get the manager storing every type defined so far
+    pub fn manager(&self) -> &TypeManager {
+        &self.manager
+    }
+
+    /// Tells if an action can change stored types, directly or through a plugin
+    fn modifies(&self, action: &Action) -> bool {
+        match action {
+            Action::Plugin(name, _) => matches!(self.plugins.get(name), Some(p) if p.modifies()),
+            Action::Import(..) | Action::ImportLines(_) | Action::Load(..) | Action::Merge(..) | Action::Attribute(..) | Action::Budget(..) | Action::Flatten(..) | Action::Clean(_) | Action::ResolvePending | Action::Wizard => true,
+            action => action.is_definition()
+        }
+    }
+
+    /// Execute an already parsed action
+    pub fn execute(&mut self, next_action: Action) -> Result<(), TypeError> {
+        let defines = match &next_action {
+            Action::Plugin(name, _) => matches!(self.plugins.get(name), Some(p) if p.modifies()),
+            action => action.is_definition()
+        };
+
+        // Create a new type as described
+        let result = match next_action {
+            Action::Exit => { self.stop(); Ok(()) },
+            Action::Cancel => { self.out.line("No hay ningún cálculo en curso"); Ok(()) },
+            Action::Display(s, options) => self.display(&s, &options),
+            Action::DisplayAll(options) => {
+                for name in self.manager.definition_order() {
+                    // pahole only knows structs and unions
+                    if options.pahole && !matches!(self.manager.get(&name), Some(Type::Struct(_)) | Some(Type::Union(_))) {
+                        continue
+                    }
+                    self.display(&name, &options)?;
+                }
+                Ok(())
+            },
+            Action::DisplayMatching(patterns, options) => self.display_matching(&patterns, &options),
+            Action::List => { self.list(); Ok(()) },
+            Action::Offsets(s) => self.offsets(&s),
+            action @ (Action::AddAtomic(..) | Action::AddStruct(..) | Action::AddUnion(..) | Action::AddEnum(..) | Action::AddPointer(..) | Action::AddContainer(..) | Action::AddText(..)) => {
+                let (name, t) = self.new_type(action)?;
+                let result = self.manager.add(name.clone(), t);
+                self.added(name, result)
+            },
+            Action::Redefine(action) => self.redefine(*action),
+            Action::Try(action) => self.try_definition(*action),
+            Action::Optional(name) => self.optional(&name),
+            Action::Profile(name) => self.profile(&name),
+            Action::Anneal(name, options) => self.anneal(&name, options),
+            Action::Solve(name) => self.solve(&name),
+            Action::Stride(name) => self.stride(&name),
+            Action::Clean(roots) => self.clean(&roots),
+            Action::Attribute(name, member, attributes) => self.attribute(&name, member.as_deref(), attributes),
+            Action::Flatten(name, flat, strategy) => self.flatten(&name, flat, strategy),
+            Action::ResolvePending => self.resolve_pending(),
+            Action::Variant(union, variant) => self.variant(&union, &variant),
+            Action::TailReuse(name) => self.tail_reuse(&name),
+            Action::Density(name, strategy, line, page) => self.density(&name, strategy, line, page),
+            Action::Plan(budget, arrays) => self.plan(budget, &arrays),
+            Action::Annotate(name, bytes, strategy) => self.annotate(&name, &bytes, strategy),
+            Action::Decode(name, bytes, strategy, endian) => self.decode(&name, &bytes, strategy, endian),
+            Action::Generate(name, seed, strategy) => self.generate(&name, seed, strategy),
+            Action::Code(names, rust) => {
+                if let Some(missing) = names.iter().find(|n| self.manager.get(n).is_none()) {
+                    return Err(TypeError::TypeDoesNotExist(missing.clone()))
+                }
+                let code = match rust {
+                    true  => codegen::rust_layout_test(&self.manager, &names),
+                    false => codegen::c_snippet(&self.manager, &names)?
+                };
+                self.out.line(code.trim_end());
+                Ok(())
+            },
+            Action::Import(file, imported) => self.import(&file, imported),
+            Action::Export(format) => {
+                if let Err(e) = self.export_command(format) {
+                    self.out.line(&CommandError::Program(e).display())
+                }
+                Ok(())
+            },
+            Action::Addresses(name, base, strategy) => {
+                let scope = Scope { manager: &self.manager, variables: &self.variables };
+                let base = base.eval_natural(&scope).map_err(TypeError::Expression)?;
+                self.addresses(&name, base, strategy)
+            },
+            Action::Bitfields(name, policy) => self.bitfields(&name, policy.unwrap_or(self.config.bitfields)),
+            Action::Lint(name) => self.lint(name),
+            Action::Split(name, line) => self.split(&name, line),
+            Action::Pareto(name, line) => self.pareto(&name, line),
+            Action::Flags(name, packed) => self.flags(&name, packed),
+            Action::LintRules => {
+                for rule in lint::RULES.iter() {
+                    self.out.line(&format!("{:<16} {}", rule.name, rule.description));
+                }
+                Ok(())
+            },
+            Action::Plugin(name, args) => {
+                let output = match self.plugins.get_mut(&name) {
+                    Some(plugin) => plugin.execute(&args, &mut self.manager)?,
+                    None => return Ok(())
+                };
+                if !output.is_empty() {
+                    self.out.line(&output);
+                }
+                Ok(())
+            },
+            Action::Calculate(e) => {
+                let scope = Scope { manager: &self.manager, variables: &self.variables };
+                let value = e.eval(&scope).map_err(TypeError::Expression)?;
+                self.variables.set_last(value);
+                let text = match (e.is_comparison(), value) {
+                    (true, 0)  => String::from("falso"),
+                    (true, _)  => String::from("verdadero"),
+                    (false, n) => self.config.numbers.format(n)
+                };
+                self.out.line(&text);
+                Ok(())
+            },
+            Action::Include(file) => {
+                if let Err(e) = self.include(&file) {
+                    self.out.line(&e.display())
+                }
+                Ok(())
+            },
+            Action::Save(file, format) => {
+                if let Err(e) = self.save(&file, format) {
+                    self.out.line(&e.display())
+                }
+                Ok(())
+            },
+            Action::Load(file, format) => {
+                if let Err(e) = self.load(&file, format) {
+                    self.out.line(&e.display())
+                }
+                Ok(())
+            },
+            Action::Session(command) => {
+                if let Err(e) = self.session(command) {
+                    self.out.line(&e.display())
+                }
+                Ok(())
+            },
+            Action::Merge(file, strategy) => {
+                if let Err(e) = self.merge(&file, &strategy) {
+                    self.out.line(&e.display())
+                }
+                Ok(())
+            },
+            Action::Diff(before, after) => {
+                if let Err(e) = self.diff(&before, &after) {
+                    self.out.line(&e.display())
+                }
+                Ok(())
+            },
+            Action::Journal(command) => { self.journal(command); Ok(()) },
+            Action::Replay(file) => {
+                if let Err(e) = self.replay(&file) {
+                    self.out.line(&e.display())
+                }
+                Ok(())
+            },
+            Action::Trace(name, file, line) => {
+                if let Err(e) = self.trace(&name, &file, line) {
+                    self.out.line(&e.display())
+                }
+                Ok(())
+            },
+            Action::Image(name, file, strategy) => {
+                if let Err(e) = self.image(&name, &file, strategy) {
+                    self.out.line(&e.display())
+                }
+                Ok(())
+            },
+            Action::Document(dir) => {
+                if let Err(e) = self.document(&dir) {
+                    self.out.line(&CommandError::Program(e).display())
+                }
+                Ok(())
+            },
+            Action::Validate => {
+                if let Err(e) = self.validate() {
+                    self.out.line(&CommandError::Program(e).display())
+                }
+                Ok(())
+            },
+            Action::Budget(name, budget) => {
+                self.manager.set_budget(&name, budget);
+                match budget {
+                    Some(b) => self.out.line(&format!("Presupuesto de '{}': {} bytes ({})", name, self.config.numbers.format(b.limit), b.strategy.name())),
+                    None => self.out.line(&format!("'{}' ya no tiene presupuesto", name))
+                }
+                self.changed();
+                Ok(())
+            },
+            Action::Budgets => { self.budgets(); Ok(()) },
+            Action::Growth(name) => self.growth(&name),
+            Action::ImportLines(file) => {
+                if let Err(e) = self.import_lines(&file) {
+                    self.out.line(&e.display())
+                }
+                Ok(())
+            },
+            Action::VerifyReplay(journal, snapshot) => {
+                if let Err(e) = self.verify_replay(&journal, &snapshot) {
+                    self.out.line(&e.display())
+                }
+                Ok(())
+            },
+            Action::Assert(text, e) => {
+                if let Err(e) = self.assert(&text, &e) {
+                    self.out.line(&e.display())
+                }
+                Ok(())
+            },
+            Action::History => {
+                for (i, command) in self.history.entries() {
+                    self.out.line(&format!("{:>4}  {}", i, command));
+                }
+                Ok(())
+            },
+            Action::Let(name, e) => {
+                let scope = Scope { manager: &self.manager, variables: &self.variables };
+                let value = e.eval(&scope).map_err(TypeError::Expression)?;
+                self.variables.set(&name, value);
+                self.out.line(&format!("{} = {}", name, self.config.numbers.format(value)));
+                Ok(())
+            },
+            Action::ListVariables => {
+                for (name, value) in self.variables.sorted() {
+                    self.out.line(&format!("{} = {}", name, self.config.numbers.format(value)));
+                }
+                if let Some(last) = self.variables.get(expr::LAST) {
+                    self.out.line(&format!("{} = {}", expr::LAST, self.config.numbers.format(last)));
+                }
+                Ok(())
+            },
+            Action::DefineMacro(name, params, body) => {
+                let origin = self.origin.clone().unwrap_or_else(|| INTERACTIVE.to_string());
+                self.macros.insert(name, Macro { params, body, origin });
+                Ok(())
+            },
+            Action::Wizard => {
+                let wizard = Wizard::new();
+                self.out.line("Asistente para definir structs, escribe 'cancelar' para salir");
+                self.out.line(&wizard.question());
+                self.wizard = Some(wizard);
+                Ok(())
+            }
+        };
+
+        // save every change so nothing is lost if we crash
+        if result.is_ok() && defines {
+            self.changed();
+        }
+
+        result
+    }
+
+    /// Show every diagnostic about a type just added, and keep the lint rules disabled for it
+    fn added(&mut self, name: Name, added: Result<Vec<Diagnostic>, TypeError>) -> Result<(), TypeError> {
+        for d in added? {
+            self.out.line(&d.display_with_level());
+        }
+
+        let origin = self.pending_origin.take().or_else(|| self.origin.clone());
+        self.manager.set_origin(&name, origin);
+        if !self.pending_allow.is_empty() {
+            self.allowed.insert(name, std::mem::take(&mut self.pending_allow));
+        }
+        Ok(())
+    }
+
+    /// Show how a struct would look split in a hot part and a cold part
+    fn split(&mut self, name: &Name, line: usize) -> Result<(), TypeError> {
+        let report = self.manager.hot_cold_split(name, line)?;
+        if report.cold.is_empty() {
+            self.out.line(&format!("'{}' no tiene campos marcados con @frio, no hay nada que dividir", name));
+            return Ok(())
+        }
+
+        let s = match self.manager.get(name) {
+            Some(Type::Struct(s)) => s,
+            _ => return Ok(())
+        };
+        let fields = |indices: &[usize]| -> String {
+            indices.iter().map(|i| s.fields[*i].name.as_str()).collect::<Vec<&str>>().join(", ")
+        };
+        let lines = |n: usize| if n == 1 { String::from("1 línea") } else { format!("{} líneas", n) };
+        let n = self.config.numbers;
+
+        let text = format!(
+            "División de '{}' (líneas de caché de {} bytes):\n  \
+            Ahora: {} bytes en {}, los campos calientes están en {}\n  \
+            Caliente '{}_caliente': {}{}puntero -> {} bytes en {}\n  \
+            Frío '{}_frio': {} -> {} bytes en {}",
+            name, report.line,
+            n.format(report.size), lines(report.lines), lines(report.hot_lines_now),
+            name, fields(&report.hot), if report.hot.is_empty() { "" } else { ", " }, n.format(report.hot_size), lines(report.hot_lines()),
+            name, fields(&report.cold), n.format(report.cold_size), lines(report.cold_lines())
+        );
+        self.out.line(&text);
+
+        Ok(())
+    }
+
+    /// Show every trade-off between the size of a struct and keeping its `@caliente`
+    /// members in the first cache line
+    fn pareto(&mut self, name: &Name, line: usize) -> Result<(), TypeError> {
+        let pareto = self.manager.pareto(name, line)?;
+        let s = match self.manager.get(name) {
+            Some(Type::Struct(s)) => s,
+            _ => return Ok(())
+        };
+        let n = self.config.numbers;
+
+        if pareto.hot.is_empty() {
+            self.out.line(&format!("'{}' no tiene campos marcados con @caliente, el orden más pequeño es el único compromiso", name));
+        }
+        let mut table = Table::new(&["Tamaño", "Bytes calientes fuera", "Orden"])
+                            .align(0, Align::Right)
+                            .align(1, Align::Right);
+        for t in &pareto.tradeoffs {
+            let fields : Vec<&str> = t.order.iter().map(|i| s.fields[*i].name.as_str()).collect();
+            table.row(vec![n.format(t.size), n.format(t.outside), fields.join(" ")]);
+        }
+
+        self.out.line(&format!("Compromisos de '{}' entre tamaño y campos calientes en la primera línea de caché ({} bytes):", name, pareto.line));
+        self.out.line(table.render(self.config.tables).trim_end());
+        if let [smallest, .., local] = pareto.tradeoffs.as_slice() {
+            self.out.line(&format!(
+                "Dejar {} bytes calientes menos fuera de la primera línea cuesta {} bytes más",
+                n.format(smallest.outside - local.outside),
+                n.format(local.size - smallest.size)
+            ));
+        }
+        if pareto.stopped {
+            self.out.line("La búsqueda se detuvo antes de probar todos los órdenes, los compromisos son los encontrados hasta entonces");
+        }
+
+        Ok(())
+    }
+
+    /// Show how much a struct would save packing its flags in a bitset, and define the packed struct if asked
+    fn flags(&mut self, name: &Name, packed: Option<Name>) -> Result<(), TypeError> {
+        let report = self.manager.flag_packing(name)?;
+        if report.flags.len() < 2 {
+            self.out.line(&format!("'{}' tiene menos de dos banderas, no hay nada que empaquetar", name));
+            return Ok(())
+        }
+
+        let fields : Vec<&str> = match self.manager.get(name) {
+            Some(Type::Struct(s)) => report.flags.iter().map(|f| s.fields[f.index].name.as_str()).collect(),
+            _ => return Ok(())
+        };
+        let n = self.config.numbers;
+        let text = format!(
+            "Banderas de '{}': {} ({} bits en {} bytes)\n  \
+            Ahora: {} bytes\n  \
+            Empaquetadas: {} bytes, ahorra {} bytes",
+            name, fields.join(", "), report.bits(), report.bytes(),
+            n.format(report.size),
+            n.format(report.packed_size), n.format(report.savings())
+        );
+        self.out.line(&text);
+
+        let packed = match packed {
+            Some(packed) => packed,
+            None => return Ok(())
+        };
+
+        let (bitset, atomic, rewritten) = self.manager.pack_flags(name, &report)?;
+        match self.manager.get(&bitset) {
+            Some(Type::Atomic(a)) if a.representation == atomic.representation && a.alignment == atomic.alignment => {},
+            Some(_) => return Err(TypeError::TypeRedefinition),
+            None => {
+                let result = self.manager.add(bitset.clone(), Type::Atomic(atomic));
+                self.added(bitset, result)?;
+            }
+        }
+        let result = self.manager.add(packed.clone(), Type::Struct(rewritten));
+        self.added(packed.clone(), result)?;
+        self.out.line(&format!("Struct '{}' definido", packed));
+
+        Ok(())
+    }
+
+    /// Show where every member of a struct goes, bit by bit, with the given bitfield rules
+    fn bitfields(&mut self, name: &Name, policy: BitfieldPolicy) -> Result<(), TypeError> {
+        let s = match self.manager.get(name) {
+            None => return Err(TypeError::TypeDoesNotExist(name.clone())),
+            Some(Type::Struct(s)) => s,
+            Some(_) => return Err(TypeError::NotAStruct(name.clone()))
+        };
+        let layout = s.bitfield_layout(&self.manager, policy)?;
+
+        let mut table = Table::new(&["#", "Campo", "Tipo", "Byte", "Bit", "Bits"]).align(0, Align::Right);
+        for i in 3..6 {
+            table = table.align(i, Align::Right);
+        }
+        for m in &layout.members {
+            table.row(vec![
+                m.index.to_string(),
+                s.fields[m.index].name.clone(),
+                s.members[m.index].clone(),
+                self.config.numbers.format(m.offset / 8),
+                (m.offset % 8).to_string(),
+                self.config.numbers.format(m.bits)
+            ]);
+        }
+
+        let n = self.config.numbers;
+        self.out.line(&format!("Campos de bits de '{}' ({}):", name, policy.name()));
+        self.out.line(table.render(self.config.tables).trim_end());
+        self.out.line(&format!("Tamaño: {} bytes, alineación {}", n.format(layout.size), n.format(layout.align)));
+
+        Ok(())
+    }
+
+    /// Show how many instances of a type fit in a cache line and in a page
+    fn density(&mut self, name: &Name, strategy: Strategy, line: usize, page: usize) -> Result<(), TypeError> {
+        let density = self.manager.density(name, strategy, line, page)?;
+        let n = self.config.numbers;
+        let fit = |label: &str, f: Fit| match f.count {
+            0 => format!("  {} ({} bytes): ningún elemento entero", label, n.format(f.block)),
+            count => format!(
+                "  {} ({} bytes): {} {}, {} bytes sin usar",
+                label, n.format(f.block), n.format(count), if count == 1 { "elemento" } else { "elementos" }, n.format(f.waste)
+            )
+        };
+
+        self.out.line(&format!("Densidad de '{}' ({}, {} bytes por elemento):", name, strategy.name(), n.format(density.stride)));
+        self.out.line(&fit("Línea de caché", density.line));
+        self.out.line(&fit("Página", density.page));
+
+        Ok(())
+    }
+
+    /// Show a buffer with every byte labelled by the member of a type it belongs to
+    fn annotate(&mut self, name: &Name, bytes: &[u8], strategy: Strategy) -> Result<(), TypeError> {
+        const SHOWN : usize = 16;     // bytes shown per member, the rest are elided
+        let annotations = self.manager.annotate(name, strategy, bytes, Endian::Little)?;
+        let size = self.manager.size(name, strategy)?;
+        let n = self.config.numbers;
+
+        let hex = |offset: usize, len: usize| {
+            let mut text : Vec<String> = (offset..offset.saturating_add(len.min(SHOWN)))
+                                            .map(|i| bytes.get(i).map(|b| format!("{:02x}", b)).unwrap_or_else(|| String::from("??")))
+                                            .collect();
+            if len > SHOWN {
+                text.push(String::from("…"));
+            }
+            text.join(" ")
+        };
+        let width = 3 * SHOWN + 1;
+        let digits = format!("{:x}", size.max(bytes.len())).len().max(4);
+
+        self.out.line(&format!(
+            "Anotación de '{}' ({}, {} bytes, {} leídos):", name, strategy.name(), n.format(size), n.format(bytes.len())
+        ));
+        for a in annotations.iter() {
+            let line = match a {
+                Annotation::Member { path, typename, offset, size, value } => {
+                    let label = match path.is_empty() {
+                        true  => typename.clone(),
+                        false => format!("{}: {}", path, typename)
+                    };
+                    let value = value.as_ref().map(|v| format!(" = {}", v)).unwrap_or_default();
+                    format!("  {:0digits$x}  {:<width$} {}{}", offset, hex(*offset, *size), label, value, digits = digits, width = width)
+                },
+                Annotation::Padding { offset, size } => self.out.dim(&format!(
+                    "  {:0digits$x}  {:<width$} relleno ({} bytes)", offset, hex(*offset, *size), n.format(size), digits = digits, width = width
+                ))
+            };
+            self.out.line(line.trim_end());
+        }
+
+        match bytes.len().cmp(&size) {
+            std::cmp::Ordering::Less    => self.out.line(&format!("Faltan {} bytes para cubrir el tipo", n.format(size - bytes.len()))),
+            std::cmp::Ordering::Greater => self.out.line(&format!("Sobran {} bytes sin anotar", n.format(bytes.len() - size))),
+            std::cmp::Ordering::Equal   => {}
+        }
+
+        Ok(())
+    }
+
+    /// Show a test vector for a type and where every member is in it
+    fn generate(&mut self, name: &Name, seed: Option<u64>, strategy: Strategy) -> Result<(), TypeError> {
+        let vector = self.manager.test_vector(name, strategy, seed)?;
+        let n = self.config.numbers;
+        let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+
+        let mut table = Table::new(&["#", "Miembro", "Tipo", "Desplazamiento", "Tamaño", "Bytes"])
+                            .align(0, Align::Right)
+                            .align(3, Align::Right)
+                            .align(4, Align::Right);
+        for (i, m) in vector.members.iter().enumerate() {
+            let path = match m.path.is_empty() {
+                true  => name.clone(),
+                false => m.path.clone()
+            };
+            table.row(vec![(i + 1).to_string(), path, m.typename.clone(), n.format(m.offset), n.format(m.size), hex(vector.member_bytes(m))]);
+        }
+
+        let seed = seed.map(|s| format!(", semilla {}", s)).unwrap_or_default();
+        self.out.line(&format!("Vector de prueba de '{}' ({}, {} bytes{}):", name, strategy.name(), n.format(vector.bytes.len()), seed));
+        self.out.line(&format!("0x{}", hex(&vector.bytes)));
+        self.out.line(table.render(self.config.tables).trim_end());
+
+        Ok(())
+    }
+
+    /// Define types read from another tool, types already defined are kept as they are. Layouts
+    /// chosen by a compiler are compared with the C strategy
+    fn import(&mut self, file: &str, imported: import::Imported) -> Result<(), TypeError> {
+        let mut defined = 0;
+        let mut kept = vec![];
+        let mut progress = Progress::new(file, self.config.progress);
+        let import::Imported { types, compiled, skipped, declared } = imported;
+        let total = types.len();
+        let origin = |name: &Name| match declared.get(name) {
+            Some((Some(source), line)) => format!("{}:{}", source, line),
+            Some((None, line)) => format!("{}:{}", file, line),
+            None => file.to_string()
+        };
+        for (i, (name, t)) in types.into_iter().enumerate() {
+            progress.update(i, defined);
+            if self.manager.get(&name).is_some() && !self.manager.shadows() {
+                kept.push(name);
+                continue
+            }
+            let result = self.manager.add(name.clone(), t);
+            self.added(name.clone(), result)?;
+            self.manager.set_origin(&name, Some(origin(&name)));
+            defined += 1;
+        }
+        progress.finish(total, defined);
+
+        self.out.line(&format!("Importados {} tipos de '{}'", defined, file));
+        if !kept.is_empty() {
+            self.out.line(&format!("Ya estaban definidos: {}", kept.join(", ")));
+        }
+        for (name, e) in &skipped {
+            self.out.line(&format!("No se importó '{}': {}", name, e.display()));
+        }
+        if compiled.is_empty() {
+            return Ok(())
+        }
+
+        let n = self.config.numbers;
+        let mut table = Table::new(&["Tipo", "Binario", "C", "Diferencias"])
+                            .align(1, Align::Right)
+                            .align(2, Align::Right);
+        for compiled in &compiled {
+            let s = match self.manager.get(&compiled.name) {
+                Some(Type::Struct(s)) => s,
+                _ => continue
+            };
+            let differences : Vec<String> = s.c_offsets(&self.manager)
+                                                .into_iter()
+                                                .zip(&compiled.offsets)
+                                                .zip(&s.fields)
+                                                .filter_map(|((ours, theirs), field)| match theirs {
+                                                    Some(theirs) if *theirs != ours => Some(format!("{}: {} en vez de {}", field.name, n.format(*theirs), n.format(ours))),
+                                                    _ => None
+                                                })
+                                                .collect();
+            let differences = match differences.is_empty() {
+                true  => String::from("ninguna"),
+                false => differences.join(", ")
+            };
+            table.row(vec![compiled.name.clone(), n.format(compiled.size), n.format(s.c_size(&self.manager)), differences]);
+        }
+        self.out.line("Disposición en el binario comparada con la estrategia C:");
+        self.out.line(table.render(self.config.tables).trim_end());
+        Ok(())
+    }
+
+    /// Show the value of every member of a type read from a buffer
+    fn decode(&mut self, name: &Name, bytes: &[u8], strategy: Strategy, endian: Endian) -> Result<(), TypeError> {
+        let annotations = self.manager.annotate(name, strategy, bytes, endian)?;
+        let size = self.manager.size(name, strategy)?;
+        let n = self.config.numbers;
+
+        let mut table = Table::new(&["Miembro", "Tipo", "Desplazamiento", "Valor"]).align(2, Align::Right);
+        for a in annotations.iter() {
+            if let Annotation::Member { path, typename, offset, size, value } = a {
+                let value = match (value, bytes.get(*offset..offset.saturating_add(*size))) {
+                    (Some(value), _) => value.clone(),
+                    (None, Some(raw)) => format!("0x{}", raw.iter().map(|b| format!("{:02x}", b)).collect::<String>()),
+                    (None, None) => String::from("(incompleto)")
+                };
+                let path = match path.is_empty() {
+                    true  => name.clone(),
+                    false => path.clone()
+                };
+                table.row(vec![path, typename.clone(), n.format(offset), value]);
+            }
+        }
+
+        self.out.line(&format!("Valores de '{}' ({}, {} endian):", name, strategy.name(), endian.name()));
+        self.out.line(table.render(self.config.tables).trim_end());
+        if bytes.len() < size {
+            self.out.line(&format!("Faltan {} bytes para cubrir el tipo", n.format(size - bytes.len())));
+        }
+
+        Ok(())
+    }
+
+    /// Show the absolute address of every member inside a type placed at some address
+    fn addresses(&mut self, name: &Name, base: usize, strategy: Strategy) -> Result<(), TypeError> {
+        let members = self.manager.nested_members(name, strategy)?;
+        let end = base.checked_add(self.manager.size(name, strategy)?).ok_or(TypeError::SizeOverflow)?;
+
+        let digits = format!("{:x}", end).len().max(8);
+        let address = |offset: usize| format!("0x{:0width$x}", base + offset, width = digits);
+        let n = self.config.numbers;
+        let mut table = Table::new(&["Dirección", "Desplazamiento", "Tamaño", "Miembro", "Tipo"])
+                            .align(1, Align::Right)
+                            .align(2, Align::Right);
+        for m in members.iter() {
+            table.row(vec![address(m.offset), n.format(m.offset), n.format(m.size), m.path.clone(), m.typename.clone()]);
+        }
+
+        self.out.line(&format!("Direcciones de '{}' desde {} ({}):", name, address(0), strategy.name()));
+        match members.is_empty() {
+            true  => self.out.line(&format!("  '{}' no tiene miembros, ocupa {} bytes", name, n.format(end - base))),
+            false => self.out.line(table.render(self.config.tables).trim_end())
+        }
+
+        Ok(())
+    }
+
+    /// Draw the layout of a struct as a grid of bytes and save it as a PNG image
+    /// ## Params
+    /// * `name` - struct to draw
+    /// * `file` - image to write, relative like `incluir` paths
+    /// * `strategy` - how the struct is laid out
+    fn image(&mut self, name: &Name, file: &str, strategy: Strategy) -> Result<(), CommandError> {
+        let diagram = self.manager.diagram(name, strategy).map_err(CommandError::Type)?;
+        let canvas = image::draw(&diagram);
+        let path = self.relative_path(file);
+        std::fs::write(&path, canvas.png()).map_err(|e| CommandError::Program(ProgramError::OutputWrite(path.display().to_string(), e.to_string())))?;
+
+        self.out.line(&format!(
+            "Disposición de '{}' ({}) guardada en '{}', {}x{} píxeles",
+            name, strategy.name(), path.display(), canvas.width(), canvas.height()
+        ));
+        Ok(())
+    }
+
+    /// Write a documentation site for every stored type, see `codegen::site`
+    /// ## Params
+    /// * `dir` - directory to write it in, created if missing, relative like `incluir` paths
+    fn document(&mut self, dir: &str) -> Result<(), ProgramError> {
+        let dir = self.relative_path(dir);
+        let names = self.manager.definition_order();
+        let files = codegen::site(&self.manager, &names);
+        let failed = |path: &Path, e: io::Error| ProgramError::OutputWrite(path.display().to_string(), e.to_string());
+
+        std::fs::create_dir_all(&dir).map_err(|e| failed(&dir, e))?;
+        for (file, contents) in &files {
+            let path = dir.join(file);
+            std::fs::write(&path, contents).map_err(|e| failed(&path, e))?;
+        }
+
+        self.out.line(&format!(
+            "Documentados {} tipos en {} archivos, empezando por '{}'",
+            names.len(), files.len(), dir.join(codegen::INDEX).display()
+        ));
+        Ok(())
+    }
+
+    /// Show the bytes taken by several arrays under every strategy and whether they fit in a budget
+    fn plan(&mut self, budget: usize, arrays: &[(Name, usize)]) -> Result<(), TypeError> {
+        let plans = self.manager.plan(arrays)?;
+        let n = self.config.numbers;
+
+        let mut header = vec!["Tipo", "Cantidad"];
+        header.extend(plans.iter().map(|p| p.strategy.name()));
+        let mut table = Table::new(&header);
+        for i in 1..header.len() {
+            table = table.align(i, Align::Right);
+        }
+        for (i, (name, count)) in arrays.iter().enumerate() {
+            let mut row = vec![name.clone(), n.format(*count)];
+            row.extend(plans.iter().map(|p| n.format(p.subtotals[i])));
+            table.row(row);
+        }
+        let mut total = vec![String::from("Total"), String::new()];
+        total.extend(plans.iter().map(|p| n.format(p.total)));
+        table.row(total);
+
+        self.out.line(&format!("Plan de memoria ({} bytes disponibles):", n.format(budget)));
+        self.out.line(table.render(self.config.tables).trim_end());
+        for p in plans.iter() {
+            self.out.line(&match p.fits(budget) {
+                true  => format!("  {}: cabe, sobran {} bytes", p.strategy.name(), n.format(budget - p.total)),
+                false => format!("  {}: no cabe, faltan {} bytes", p.strategy.name(), n.format(p.total - budget))
+            });
+        }
+
+        let total = |strategy: Strategy| plans.iter().find(|p| p.strategy == strategy).map(|p| p.total).unwrap_or(0);
+        let savings = total(Strategy::C).saturating_sub(total(Strategy::Optimized));
+        if savings > 0 {
+            self.out.line(&format!("Reordenar los campos ahorra {} bytes respecto a C", n.format(savings)));
+        }
+
+        Ok(())
+    }
+
+    /// Show a struct laid out with and without reusing the tail padding of marked members
+    fn tail_reuse(&mut self, name: &Name) -> Result<(), TypeError> {
+        let reuse = self.manager.tail_reuse(name)?;
+        let s = match self.manager.get(name) {
+            Some(Type::Struct(s)) => s,
+            _ => return Ok(())
+        };
+
+        let n = self.config.numbers;
+        let mut table = Table::new(&["#", "Campo", "Tipo", "Sin reutilizar", "Reutilizando"])
+                            .align(0, Align::Right)
+                            .align(3, Align::Right)
+                            .align(4, Align::Right);
+        for (i, (plain, offset)) in reuse.plain_offsets.iter().zip(&reuse.offsets).enumerate() {
+            table.row(vec![i.to_string(), s.fields[i].name.clone(), s.members[i].clone(), n.format(plain), n.format(offset)]);
+        }
+
+        self.out.line(&format!("Relleno de cola de '{}':", name));
+        self.out.line(table.render(self.config.tables).trim_end());
+        self.out.line(&format!(
+            "Tamaño: {} bytes sin reutilizar, {} reutilizando (ahorra {})",
+            n.format(reuse.plain_size),
+            n.format(reuse.size),
+            n.format(reuse.savings())
+        ));
+
+        Ok(())
+    }
+
+    /// Show the size of a union variant against the whole union, under every strategy
+    fn variant(&mut self, union: &Name, variant: &Name) -> Result<(), TypeError> {
+        let reports = self.manager.variant_report(union, variant)?;
+
+        let mut table = Table::new(&["Estrategia", "Variante", "Unión", "Holgura"]);
+        for i in 1..4 {
+            table = table.align(i, Align::Right);
+        }
+        let n = self.config.numbers;
+        for r in reports {
+            table.row(vec![r.strategy.name().to_string(), n.format(r.variant), n.format(r.size), n.format(r.slack)]);
+        }
+
+        self.out.line(&format!("Variante '{}' de '{}':", variant, union));
+        self.out.line(table.render(self.config.tables).trim_end());
+
+        Ok(())
+    }
+
+    /// Type described by a definition
+    /// ## Params
+    /// * `action` - one of the actions adding a type, such as `AddStruct`
+    /// ---
+    /// ## Return
+    /// Name and type defined, or an error if an expression in it can't be computed
+    fn new_type(&self, action: Action) -> Result<(Name, Type), TypeError> {
+        let defined = match action {
+            Action::AddAtomic(name, repr, align, kind) => {
+                let scope = Scope { manager: &self.manager, variables: &self.variables };
+                let repr = repr.eval_natural(&scope).map_err(TypeError::Expression)?;
+                let align = align.eval_natural(&scope).map_err(TypeError::Expression)?;
+                (name, Type::Atomic(Atomic::with_kind(repr, align, kind)))
+            },
+            Action::AddStruct(name, s) => (name, Type::Struct(s)),
+            Action::AddUnion(name, variants) => (name, Type::Union(Union::new(variants))),
+            Action::AddEnum(name, variants, repr) => (name, Type::Enum(Enum::new(variants, repr.unwrap_or(self.config.enums)))),
+            Action::AddPointer(name, target, kind) => (name, Type::Pointer(Pointer::new(target, kind))),
+            Action::AddContainer(name, container) => (name, Type::Container(container)),
+            Action::AddText(name, model) => (name, Type::Text(Text::new(model))),
+            _ => unreachable!("only definitions describe a new type")
+        };
+
+        Ok(defined)
+    }
+
+    /// Put a new definition in place of a stored type. Types built on it keep using it,
+    /// only their layouts are computed again
+    /// ## Params
+    /// * `action` - definition of the type, parsed by `redefinir` so it's one adding a type
+    fn redefine(&mut self, action: Action) -> Result<(), TypeError> {
+        let (name, t) = self.new_type(action)?;
+        let stored = self.manager.resolve(&name).cloned().ok_or_else(|| TypeError::TypeDoesNotExist(name.clone()))?;
+        let affected = self.manager.dependents(&stored);
+
+        let result = self.manager.replace(&stored, t);
+        self.added(stored.clone(), result)?;
+
+        self.out.line(&match affected.is_empty() {
+            true  => format!("'{}' redefinido, ningún tipo lo usa", stored),
+            false => format!("'{}' redefinido, {} tipos afectados: {}", stored, affected.len(), affected.join(", "))
+        });
+        Ok(())
+    }
+
+    /// Check a definition and show the report it would have, without storing it. A name
+    /// already stored is checked as a redefinition, so there's no redefinition error,
+    /// and the C size of every type built on it is shown before and after
+    /// ## Params
+    /// * `action` - definition of the type, parsed by `probar` so it's one adding a type
+    fn try_definition(&mut self, action: Action) -> Result<(), TypeError> {
+        let (name, t) = self.new_type(action)?;
+
+        // a copy takes the definition, pending types would have nothing to report
+        let mut trial = self.manager.snapshot();
+        trial.set_deferred(false);
+        let (stored, diagnostics) = match trial.resolve(&name).cloned() {
+            Some(stored) => {
+                let diagnostics = trial.replace(&stored, t)?;
+                self.out.line(&format!("Prueba de '{}' en lugar de la definición guardada, no se guardó:", stored));
+                (stored, diagnostics)
+            },
+            None => {
+                let diagnostics = trial.add(name.clone(), t)?;
+                self.out.line(&format!("Prueba de '{}', no se guardó:", name));
+                (name, diagnostics)
+            }
+        };
+
+        for d in diagnostics {
+            self.out.line(&d.display_with_level());
+        }
+        let report = trial.report(&stored)?;
+        let n = self.config.numbers;
+        self.out.line(&format!("Símbolo: {}\n{}", stored, report.display_with(n)));
+        let affected = self.manager.dependents(&stored);
+        if !affected.is_empty() {
+            self.out.line("Tipos afectados (tamaño en C):");
+        }
+        for user in &affected {
+            let (before, after) = (self.manager.size(user, Strategy::C)?, trial.size(user, Strategy::C)?);
+            self.out.line(&format!("   * {}: {} -> {} bytes", user, n.format(before), n.format(after)));
+        }
+        let allowed = &self.allowed;
+        for f in trial.lint(std::slice::from_ref(&stored), |t, rule| matches!(allowed.get(t), Some(rules) if rules.iter().any(|r| r == rule))) {
+            self.out.line(&f.display());
+        }
+        Ok(())
+    }
+
+    /// Add every pending type whose members are defined now, telling what went wrong
+    /// with the ones that turned out invalid
+    fn settle(&mut self) -> Resolution {
+        let resolution = self.manager.resolve_pending();
+        for (name, diagnostics) in &resolution.resolved {
+            for d in diagnostics {
+                self.out.line(&format!("{} ('{}')", d.display_with_level(), name));
+            }
+        }
+        for (name, e) in &resolution.failed {
+            self.out.line(&format!("[AVISO]: '{}' estaba pendiente y no se pudo definir: {}", name, e.display()));
+        }
+        if !resolution.resolved.is_empty() {
+            self.changed();
+        }
+
+        resolution
+    }
+
+    /// Add the types waiting for others, failing if some of them still miss a type
+    fn resolve_pending(&mut self) -> Result<(), TypeError> {
+        let resolution = self.settle();
+        let names : Vec<&str> = resolution.resolved.iter().map(|(n, _)| n.as_str()).collect();
+        match names.is_empty() {
+            true  => self.out.line("No se resolvió ningún tipo pendiente"),
+            false => self.out.line(&format!("Resueltos {} tipos pendientes: {}", names.len(), names.join(", ")))
+        }
+
+        match resolution.pending.is_empty() {
+            true  => Ok(()),
+            false => Err(TypeError::Unresolved(resolution.pending))
+        }
+    }
+
+    /// Show every budget with the size of its type and whether it holds
+    fn budgets(&mut self) {
+        let budgets = self.manager.budgets();
+        if budgets.is_empty() {
+            self.out.line("No hay presupuestos");
+            return
+        }
+
+        let mut table = Table::new(&["Tipo", "Estrategia", "Tamaño", "Presupuesto", ""]);
+        for i in 2..4 {
+            table = table.align(i, Align::Right);
+        }
+        let n = self.config.numbers;
+        for (name, budget) in budgets {
+            let size = self.manager.size(name, budget.strategy).ok();
+            let holds = matches!(size, Some(size) if size <= budget.limit);
+            table.row(vec![
+                name.clone(),
+                budget.strategy.name().to_string(),
+                size.map_or(String::from("—"), |s| n.format(s)),
+                n.format(budget.limit),
+                String::from(match holds { true => "✅", false => "❌" })
+            ]);
+        }
+        self.out.line(table.render(self.config.tables).trim_end());
+    }
+
+    /// Show the C layouts a type took over the session, each with the edit giving it
+    fn growth(&mut self, name: &Name) -> Result<(), TypeError> {
+        let history = self.manager.growth(name);
+        if history.is_empty() {
+            return match self.manager.get(name) {
+                Some(_) => { self.out.line(&format!("No hay historia de '{}', no se definió con un comando", name)); Ok(()) },
+                None => Err(TypeError::TypeDoesNotExist(name.clone()))
+            }
+        }
+
+        let mut table = Table::new(&["#", "Tamaño", "Cambio", "Alineación", "Edición"]);
+        for i in 0..4 {
+            table = table.align(i, Align::Right);
+        }
+        let n = self.config.numbers;
+        let mut previous : Option<usize> = None;
+        for (i, g) in history.iter().enumerate() {
+            let change = match previous {
+                Some(p) if g.size >= p => format!("+{}", n.format(g.size - p)),
+                Some(p) => format!("-{}", n.format(p - g.size)),
+                None => String::from("—")
+            };
+            let mut edit = g.edit.clone();
+            if let Some(cause) = &g.cause {
+                edit += &format!(" (por '{}')", cause);
+            }
+            if let Some(origin) = &g.origin {
+                edit += &format!(" en {}", origin);
+            }
+            table.row(vec![(i + 1).to_string(), n.format(g.size), change, n.format(g.align), edit]);
+            previous = Some(g.size);
+        }
+        let stored = self.manager.resolve(name).unwrap_or(name);
+        self.out.line(&format!("Historia de '{}' (C):\n{}", stored, table.render(self.config.tables).trim_end()));
+        Ok(())
+    }
+
+    /// Every budget that doesn't hold, described, for batch runs and watch mode to fail on
+    pub fn budget_errors(&self) -> Vec<String> {
+        self.manager.overruns().iter().map(Overrun::display).collect()
+    }
+
+    /// Check every budget holds
+    /// ## Return
+    /// An error listing the budgets that don't hold, if any
+    fn validate(&mut self) -> Result<(), ProgramError> {
+        let errors = self.budget_errors();
+        if !errors.is_empty() {
+            return Err(ProgramError::OverBudget(errors))
+        }
+
+        self.out.line(&match self.manager.budgets().len() {
+            0 => String::from("No hay presupuestos que validar"),
+            1 => String::from("Se cumple el único presupuesto"),
+            n => format!("Se cumplen los {} presupuestos", n)
+        });
+        Ok(())
+    }
+
+    /// Add attributes to a stored type or to a member of a stored struct
+    fn attribute(&mut self, name: &Name, member: Option<&str>, attributes: Vec<Attribute>) -> Result<(), TypeError> {
+        self.manager.add_attributes(name, member, attributes)?;
+        match member {
+            Some(field) => self.out.line(&format!("Atributos de '{}.{}' actualizados", name, field)),
+            None => self.out.line(&format!("Atributos de '{}' actualizados", name))
+        }
+        self.changed();
+        Ok(())
+    }
+
+    /// Define a struct with the leaf fields of a struct, at the offsets they have under a
+    /// strategy, and show where each one went
+    fn flatten(&mut self, name: &Name, flat: Name, strategy: Strategy) -> Result<(), TypeError> {
+        let stored = self.manager.resolve(name).cloned().ok_or_else(|| TypeError::TypeDoesNotExist(name.clone()))?;
+        let result = self.manager.flatten(&stored, strategy).and_then(|s| self.manager.add(flat.clone(), Type::Struct(s)));
+        self.added(flat.clone(), result)?;
+
+        let s = match self.manager.get(&flat) {
+            Some(Type::Struct(s)) => s,
+            _ => return Ok(())
+        };
+        let mut table = Table::new(&["Campo", "Tipo", "Desplazamiento", "Tamaño"]);
+        for i in 2..4 {
+            table = table.align(i, Align::Right);
+        }
+        let n = self.config.numbers;
+        for m in s.layout(&self.manager, Strategy::C) {
+            table.row(vec![s.fields[m.index].name.clone(), s.members[m.index].clone(), n.format(m.offset), n.format(m.size)]);
+        }
+        self.out.line(&format!(
+            "'{}' es '{}' aplanado, {} campos en los desplazamientos de {}:\n{}",
+            flat, stored, s.members.len(), strategy.name(), table.render(self.config.tables).trim_end()
+        ));
+        let (size, original) = (self.manager.size(&flat, Strategy::C)?, self.manager.size(&stored, strategy)?);
+        if size != original {
+            self.out.line(&format!("'{}' ocupa {} bytes en C y '{}' {} con {}", flat, n.format(size), stored, n.format(original), strategy.name()));
+        }
+        self.changed();
+        Ok(())
+    }
+
+    /// Remove every type not reachable from some roots, telling which ones went
+    fn clean(&mut self, roots: &[Name]) -> Result<(), TypeError> {
+        let removed = self.manager.clean(roots)?;
+        if removed.is_empty() {
+            self.out.line("No hay tipos que eliminar");
+            return Ok(())
+        }
+
+        for name in &removed {
+            self.allowed.remove(name);
+        }
+        self.out.line(&format!("Eliminados {} tipos: {}", removed.len(), removed.join(", ")));
+        self.changed();
+        Ok(())
+    }
+
+    /// Search a good member order for a struct with simulated annealing, comparing it
+    /// with the greedy heuristic and declaration order
+    fn anneal(&mut self, name: &Name, options: AnnealOptions) -> Result<(), TypeError> {
+        let annealing = self.manager.anneal(name, options)?;
+        let s = match self.manager.get(name) {
+            Some(Type::Struct(s)) => s,
+            _ => return Err(TypeError::NotAStruct(name.clone()))
+        };
+        let n = self.config.numbers;
+        let fields = |order: &[usize]| order.iter().map(|i| s.fields[*i].name.as_str()).collect::<Vec<_>>().join(" ");
+
+        let mut text = format!(
+            "Recocido simulado de '{}' (semilla {}, {} reinicios de {} pasos, {:.3} ms):\n",
+            name,
+            options.seed,
+            options.restarts.max(1),
+            n.format(annealing.steps),
+            annealing.elapsed.as_secs_f64() * 1000.0
+        );
+        text += &format!("   * Recocido: {} bytes con {}\n", n.format(annealing.size), fields(&annealing.order));
+        text += &format!("   * Voraz (mayor alineación primero): {} bytes con {}\n", n.format(annealing.greedy_size), fields(&annealing.greedy));
+        text += &format!("   * Declaración: {} bytes\n", n.format(annealing.declared_size));
+        text += &match annealing.greedy_size - annealing.size {
+            0 => String::from("El recocido no mejora el orden voraz"),
+            saved => format!("El recocido ahorra {} bytes más que el orden voraz", n.format(saved))
+        };
+        if annealing.stopped {
+            text += "\nLa búsqueda se detuvo antes de dar todos los pasos, el orden es el mejor encontrado";
+        }
+        if let Some(constraints) = Program::constraints_text(s, &s.constraints(&self.manager, &annealing.order)) {
+            text += &format!("\n{}", constraints);
+        }
+
+        self.out.line(&text);
+        Ok(())
+    }
+
+    /// Find the smallest member order of a struct, proving there's no smaller one
+    #[cfg(feature = "solver")]
+    fn solve(&mut self, name: &Name) -> Result<(), TypeError> {
+        let solution = match self.manager.solve(name)? {
+            Some(solution) => solution,
+            None => {
+                self.out.line("La búsqueda se detuvo antes de probar que el orden es el mejor");
+                return Ok(())
+            }
+        };
+        let s = match self.manager.get(name) {
+            Some(Type::Struct(s)) => s,
+            _ => return Err(TypeError::NotAStruct(name.clone()))
+        };
+        let fields : Vec<&str> = solution.order.iter().map(|i| s.fields[*i].name.as_str()).collect();
+        let n = self.config.numbers;
+
+        self.out.line(&format!(
+            "Orden óptimo de '{}': {} bytes con {}\nProbado sobre {} subconjuntos de miembros en {:.3} ms, ningún orden ocupa menos",
+            name,
+            n.format(solution.size),
+            fields.join(" "),
+            n.format(solution.states),
+            solution.elapsed.as_secs_f64() * 1000.0
+        ));
+        if let Some(text) = Program::constraints_text(s, &s.constraints(&self.manager, &solution.order)) {
+            self.out.line(&text);
+        }
+        Ok(())
+    }
+
+    /// Find the smallest member order of a struct, this build has no solver
+    #[cfg(not(feature = "solver"))]
+    fn solve(&mut self, _name: &Name) -> Result<(), TypeError> {
+        self.out.line("[ERROR]: Esta versión se compiló sin el solucionador exacto, está en la característica 'solver'");
+        Ok(())
+    }
+
+    /// Find the member order of a struct touching the fewest cache lines for an access
+    /// trace, comparing it with the smallest order and declaration order
+    /// ## Params
+    /// * `file` - access trace, relative like `incluir` paths
+    /// * `line` - cache line size, in bytes
+    fn trace(&mut self, name: &Name, file: &str, line: usize) -> Result<(), CommandError> {
+        let s = match self.manager.get(name) {
+            None => return Err(CommandError::Type(TypeError::TypeDoesNotExist(name.clone()))),
+            Some(Type::Struct(s)) => s,
+            Some(_) => return Err(CommandError::Type(TypeError::NotAStruct(name.clone())))
+        };
+        let path = self.relative_path(file);
+        let origin = path.display().to_string();
+        let text = std::fs::read_to_string(&path).map_err(|e| CommandError::Program(ProgramError::IncludeRead(origin.clone(), e.to_string())))?;
+        let patterns = parse_trace(&text, s).map_err(|errors| CommandError::Include {
+            file: origin.clone(),
+            errors: errors.into_iter().map(|(i, e)| match i {
+                0 => format!("{}: {}", origin, e),
+                i => format!("{}:{}: {}", origin, i, e)
+            }).collect()
+        })?;
+
+        let layout = self.manager.trace_layout(name, &patterns, line).map_err(CommandError::Type)?;
+        let n = self.config.numbers;
+        let fields = |order: &[usize]| order.iter().map(|i| s.fields[*i].name.as_str()).collect::<Vec<_>>().join(" ");
+        let orders = [("traza", &layout.best), ("menor tamaño", &layout.smallest), ("declaración", &layout.declared)];
+
+        let mut table = Table::new(&["Orden", "Tamaño", "Líneas por acceso", "Campos"]).align(1, Align::Right).align(2, Align::Right);
+        for (label, t) in orders {
+            table.row(vec![label.to_string(), n.format(t.size), format!("{:.2}", t.expected(&patterns)), fields(&t.order)]);
+        }
+        let mut lines = Table::new(&["Patrón", "Peso", "traza", "menor tamaño", "declaración"]).align(1, Align::Right).align(2, Align::Right).align(3, Align::Right).align(4, Align::Right);
+        for (i, p) in patterns.iter().enumerate() {
+            let mut row = vec![fields(&p.members), n.format(p.weight)];
+            row.extend(orders.iter().map(|(_, t)| t.lines[i].to_string()));
+            lines.row(row);
+        }
+
+        self.out.line(&format!("Accesos a '{}' según '{}' (líneas de caché de {} bytes, {} patrones):", name, file, layout.line, patterns.len()));
+        self.out.line(table.render(self.config.tables).trim_end());
+        self.out.line("Líneas tocadas por cada patrón:");
+        self.out.line(lines.render(self.config.tables).trim_end());
+        let saved = layout.smallest.expected(&patterns) - layout.best.expected(&patterns);
+        self.out.line(&match (saved > 0.0, layout.best.size.saturating_sub(layout.smallest.size)) {
+            (false, _) => String::from("El orden más pequeño ya toca las menos líneas posibles"),
+            (true, 0) => format!("El orden de la traza toca {:.2} líneas menos por acceso sin ocupar más", saved),
+            (true, extra) => format!("El orden de la traza toca {:.2} líneas menos por acceso a cambio de {} bytes más", saved, n.format(extra))
+        });
+        if layout.stopped {
+            self.out.line("La búsqueda se detuvo antes de probar todos los órdenes, el orden de la traza es el mejor encontrado");
+        }
+
+        Ok(())
+    }
+
+    /// Compare the best member order of a struct for size and for array stride with
+    /// declaration order, telling whether reordering makes arrays of it any smaller
+    fn stride(&mut self, name: &Name) -> Result<(), TypeError> {
+        let reports = self.manager.objectives(name)?;
+        let s = match self.manager.get(name) {
+            Some(Type::Struct(s)) => s,
+            _ => return Err(TypeError::NotAStruct(name.clone()))
+        };
+        let n = self.config.numbers;
+
+        let mut table = Table::new(&["Objetivo", "Tamaño", "Paso", "Orden"]).align(1, Align::Right).align(2, Align::Right);
+        for r in &reports {
+            table.row(vec![
+                r.objective.map(|o| o.name()).unwrap_or("declaración").to_string(),
+                n.format(r.size),
+                n.format(r.stride),
+                r.order.iter().map(|i| s.fields[*i].name.as_str()).collect::<Vec<_>>().join(" ")
+            ]);
+        }
+
+        let (smallest, declared) = (&reports[0], &reports[reports.len() - 1]);
+        let summary = match (declared.size - smallest.size, declared.stride - smallest.stride) {
+            (0, _) => String::from("Reordenar no ahorra nada"),
+            (saved, 0) => format!("Reordenar ahorra {} bytes de tamaño pero ninguno en arrays, el orden declarado ya tiene el menor paso", n.format(saved)),
+            (_, saved) => format!("Reordenar ahorra {} bytes por elemento en arrays", n.format(saved))
+        };
+
+        self.out.line(&format!("Objetivos de '{}' (los arrays avanzan de a múltiplos de {} bytes, la mayor alineación):", name, s.array_align(&self.manager)));
+        self.out.line(table.render(self.config.tables).trim_end());
+        self.out.line(&format!("{}\nEl optimizador usa el objetivo '{}'", summary, self.manager.objective().name()));
+        Ok(())
+    }
+
+    /// Show where the time computing a type's layout goes, under every strategy
+    fn profile(&mut self, name: &Name) -> Result<(), TypeError> {
+        let profiles = self.manager.profile(name)?;
+
+        let mut table = Table::new(&["Estrategia", "Tiempo (ms)", "Profundidad", "Búsquedas", "Aciertos", "Fallos", "En caché", "Calculados", "Optimizaciones", "Permutaciones", "Podadas"]);
+        for i in 1..11 {
+            table = table.align(i, Align::Right);
+        }
+        let n = self.config.numbers;
+        for p in &profiles {
+            table.row(vec![
+                p.strategy.map(|s| s.name()).unwrap_or_default().to_string(),
+                format!("{:.3}", p.elapsed.as_secs_f64() * 1000.0),
+                n.format(p.depth),
+                n.format(p.lookups),
+                n.format(p.hits),
+                n.format(p.misses),
+                n.format(p.cache_hits),
+                n.format(p.cache_misses),
+                n.format(p.optimizations),
+                n.format(p.optimizer.permutations),
+                n.format(p.optimizer.pruned)
+            ]);
+        }
+
+        self.out.line(&format!("Perfil de '{}':", name));
+        self.out.line(table.render(self.config.tables).trim_end());
+
+        // the optimizer running again and again for the same structs is the usual cause of slow reports
+        if let Some(slowest) = profiles.iter().max_by_key(|p| p.elapsed) {
+            if slowest.optimizations > slowest.misses {
+                self.out.line(&format!(
+                    "El optimizador buscó un orden de miembros {} veces entre {} tipos distintos, {} es la estrategia más lenta",
+                    n.format(slowest.optimizations),
+                    n.format(slowest.misses),
+                    slowest.strategy.map(|s| s.name()).unwrap_or_default()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Show the size of an optional value of some type, with and without a niche
+    fn optional(&mut self, name: &Name) -> Result<(), TypeError> {
+        let report = self.manager.optional(name)?;
+        let n = self.config.numbers;
+        let niche = match report.niche {
+            Some(size) => format!("{} bytes, el puntero nulo representa el valor vacío", n.format(size)),
+            None => format!("no es posible, '{}' puede tomar cualquier valor", name)
+        };
+
+        let text = format!(
+            "Opcional de '{}' ({} bytes):\n  \
+            Sin nicho: {} bytes (1 byte de bandera y {} de relleno)\n  \
+            Con nicho: {}",
+            name, n.format(report.size),
+            n.format(report.flagged), n.format(report.flag_padding()),
+            niche
+        );
+        self.out.line(&text);
+
+        Ok(())
+    }
+
+    /// Run every lint rule over a type, or over every type
+    fn lint(&mut self, name: Option<Name>) -> Result<(), TypeError> {
+        let names = match name {
+            Some(name) => match self.manager.resolve(&name) {
+                Some(stored) => vec![stored.clone()],
+                None => return Err(TypeError::TypeDoesNotExist(name))
+            },
+            None => self.manager.definition_order()
+        };
+
+        let allowed = &self.allowed;
+        let findings = self.manager.lint(&names, |t, rule| matches!(allowed.get(t), Some(rules) if rules.iter().any(|r| r == rule)));
+
+        if findings.is_empty() {
+            self.out.line("Sin problemas");
+        }
+        for f in findings {
+            self.out.line(&f.display());
+        }
+        Ok(())
+    }
+
+    /// Stop running after a clean exit, there's nothing to recover then
+    fn stop(&mut self) {
+        self.running = false;
+        if self.config.autosave && !self.config.sandbox {
+            let _ = std::fs::remove_file(&self.config.recovery);
+        }
+    }
+
+    /// Remember the session changed, it's saved to the recovery file once the command
+    /// or script making the change ends, not after every line a script runs
+    fn changed(&mut self) {
+        self.unsaved = true;
+        self.revision += 1;
+    }
+
+    /// End a command or script, saving the session to the recovery file if it was the
+    /// outermost one and something changed
+    fn finished(&mut self) {
+        self.running_depth -= 1;
+        if self.running_depth == 0 {
+            self.autosave();
+        }
+    }
+
+    /// Amount of changes made to the session so far, to tell if a command changed it
+    pub fn revision(&self) -> usize {
+        self.revision
+    }
+
+    /// Tells if a line is the command to exit
+    pub fn is_exit(&self, line: &str) -> bool {
+        matches!(Program::parse_with(line, &self.config.keywords), Ok(Action::Exit))
+    }
+
+    /// Write the whole session to the recovery file, if it changed since it was last written
+    fn autosave(&mut self) {
+        if !self.unsaved || !self.config.autosave || self.config.sandbox || self.session != MAIN_SESSION {
+            return
+        }
+
+        // write to a temporary file first, so a crash while saving doesn't leave a broken file
+        let path = &self.config.recovery;
+        let temp = path.with_extension("tmp");
+        let result = std::fs::write(&temp, self.session_script())
+                        .and_then(|_| std::fs::rename(&temp, path));
+
+        match result {
+            Ok(_)  => self.unsaved = false,
+            Err(e) => self.out.line(&format!("[AVISO]: No se pudo guardar la sesión en '{}': {}", path.display(), e))
+        }
+    }
+
+    /// If the previous session didn't end cleanly, offer to restore it from the recovery file
+    /// ## Return
+    /// If the session was restored
+    pub fn offer_recovery(&mut self) -> bool {
+        let path = self.config.recovery.clone();
+        if !self.config.autosave || !path.is_file() {
+            return false
+        }
+
+        print!("La sesión anterior no terminó con 'salir'. ¿Restaurarla? (s/n) ");
+        io::stdout().flush().expect("Couldn't flush stdout");
+
+        let mut answer = String::new();
+        let restore = io::stdin().read_line(&mut answer).is_ok() 
+                        && matches!(answer.trim().to_lowercase().as_str(), "s" | "si" | "sí");
+
+        if !restore {
+            let _ = std::fs::remove_file(&path);
+            return false
+        }
+
+        let restored = match self.run_file(&path) {
+            Err(e) => { self.out.line(&format!("[ERROR]: No se pudo leer '{}': {}", path.display(), e)); false },
+            Ok(summary) => {
+                self.out.line(&format!("Restaurados {} tipos", summary.defined));
+                for e in summary.errors {
+                    self.out.line(&e);
+                }
+                true
+            }
+        };
+        self.out.flush(false);
+
+        restored
+    }
+
+    /// Create a script that defines every type in this session when run
+    pub fn session_script(&self) -> String {
+        let mut script = String::from("# Sesión guardada por TypeManager\n");
+        for name in self.manager.definition_order() {
+            if let Some(t) = self.manager.get(&name) {
+                if let Some(origin) = self.manager.origin(&name) {
+                    script += &origin_comment(origin);
+                    script += "\n";
+                }
+                if let Some(rules) = self.allowed.get(&name) {
+                    script += &format!("# lint: permitir {}\n", rules.join(", "));
+                }
+                script += &Program::definition_command(&name, t);
+                script += "\n";
+                if let Some(line) = Program::attribute_command(&name, self.manager.extra_attributes(&name)) {
+                    script += &line;
+                    script += "\n";
+                }
+                if let Some(budget) = self.manager.budget(&name) {
+                    script += &Program::budget_command(&name, budget);
+                    script += "\n";
+                }
+            }
+        }
+        for (name, _) in self.manager.pending() {
+            if let Some(t) = self.manager.pending_type(&name) {
+                if let Some(origin) = self.manager.origin(&name) {
+                    script += &origin_comment(origin);
+                    script += "\n";
+                }
+                script += &Program::definition_command(&name, t);
+                script += "\n";
+            }
+        }
+        // budgets of types not stored yet, they're checked once they are
+        for (name, budget) in self.manager.budgets() {
+            if self.manager.get(name).is_none() {
+                script += &Program::budget_command(name, budget);
+                script += "\n";
+            }
+        }
+
+        script
+    }
+
+    /// Print every type in the session in some format
+    fn export_command(&mut self, format: ExportFormat) -> Result<(), ProgramError> {
+        let exported = self.checked_export(format)?;
+        self.out.line(exported.trim_end());
+        Ok(())
+    }
+
+    /// Every type in the session in some format, see `export`. In strict mode what the
+    /// format can't express is an error instead of a warning in the output
+    /// ## Return
+    /// The exported types, or the warnings about them in strict mode
+    pub fn checked_export(&self, format: ExportFormat) -> Result<String, ProgramError> {
+        let warnings = match format {
+            ExportFormat::Fbs if self.config.strict => codegen::fbs_warnings(&self.manager, &self.manager.definition_order()),
+            _ => vec![]
+        };
+        match warnings.is_empty() {
+            true  => Ok(self.export(format)),
+            false => Err(ProgramError::Strict(warnings))
+        }
+    }
+
+    /// Render every stored type in the given format, dependencies first
+    pub fn export(&self, format: ExportFormat) -> String {
+        let names = self.manager.definition_order();
+
+        match format {
+            ExportFormat::Script => self.session_script(),
+            ExportFormat::C      => codegen::c_layout_check(&self.manager, &names),
+            ExportFormat::Capnp  => codegen::capnp_schema(&self.manager, &names),
+            ExportFormat::Fbs    => codegen::fbs_schema(&self.manager, &names),
+            ExportFormat::Sql(engine) => codegen::sql_schema(&self.manager, &names, engine),
+            ExportFormat::Arrow(rows) => codegen::arrow_schema(&self.manager, &names, rows),
+            ExportFormat::Treemap => codegen::treemap(&self.manager, &names),
+            ExportFormat::TreemapPage => codegen::treemap_page(&self.manager, &names),
+            ExportFormat::Rust   => {
+                let compounds : Vec<Name> = names
+                                                .into_iter()
+                                                .filter(|n| matches!(self.manager.get(n), Some(Type::Struct(_)) | Some(Type::Union(_))))
+                                                .collect();
+                codegen::rust_layout_test(&self.manager, &compounds)
+            },
+            ExportFormat::Json   => {
+                let reports = names
+                                .iter()
+                                .filter_map(|name| self.manager.report(name).ok().map(|r| {
+                                    let mut json = r.to_json(name);
+                                    if let (json::Value::Object(pairs), Some(origin)) = (&mut json, self.manager.origin(name)) {
+                                        pairs.push(("origin".to_string(), json::Value::str(origin)));
+                                    }
+                                    json
+                                }))
+                                .collect();
+                format!("{}\n", json::Value::Array(reports))
+            }
+        }
+    }
+
+    /// Serve a session over a pair of streams: every line read is run as a command
+    /// and its output, if any, is written back. Stops on `salir` or end of input
+    /// ## Params
+    /// * `input` - where commands come from
+    /// * `output` - where outputs and errors go
+    pub fn serve<R, W>(&mut self, input: R, output: &mut W) -> io::Result<()>
+        where
+            R: BufRead + Send + 'static,
+            W: Write
+    {
+        self.capture_output();
+
+        for line in Program::read_commands(input, self.config.keywords.clone(), vec![self.canceller()]) {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue
+            }
+
+            self.run_line(&line);
+            output.write_all(self.take_output().as_bytes())?;
+            output.flush()?;
+
+            if !self.should_run() {
+                break
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serve a session to another program over a pair of streams with the JSON lines
+    /// protocol: every line read is a request object with the command to run, and a
+    /// response object is written back for it in a line of its own, see `protocol`.
+    /// Stops on `salir` or end of input
+    /// ## Params
+    /// * `input` - where requests come from
+    /// * `output` - where responses go
+    pub fn serve_json<R, W>(&mut self, input: R, output: &mut W) -> io::Result<()>
+        where
+            R: BufRead,
+            W: Write
+    {
+        self.capture_output();
+
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue
+            }
+
+            let request = json::parse(&line);
+            let id = request.as_ref().ok().and_then(|r| r.get("id")).cloned().unwrap_or(json::Value::Null);
+            let response = match request.map_err(ProgramError::InvalidArgument).and_then(|r| Program::json_command(&r)) {
+                Err(e) => protocol::invalid(id, &e.display()),
+                Ok((command, allow)) => {
+                    let (start, types) = (Instant::now(), self.manager.len());
+                    self.pending_allow = allow;
+                    let result = match self.wizard {
+                        Some(_) => self.wizard_answer(&command).map(|_| ()).map_err(CommandError::Type),
+                        None    => self.run_command(&command)
+                    };
+                    self.pending_allow.clear();
+                    metrics::command(result.is_ok(), start.elapsed(), self.manager.len().saturating_sub(types));
+                    self.out.flush(false);
+                    protocol::response(id, &self.take_output(), result.as_ref().map(|_| ()))
+                }
+            };
+            writeln!(output, "{}", response)?;
+            output.flush()?;
+
+            if !self.should_run() {
+                break
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read commands on a thread of their own, so `cancelar` stops the command running
+    /// as soon as it's sent instead of after it ends. Every other line, and `cancelar`
+    /// when there's nothing to stop, comes out in order to be run
+    /// ## Params
+    /// * `input` - where commands come from
+    /// * `keywords` - words accepted for every command
+    /// * `cancellers` - what `cancelar` stops
+    /// ---
+    /// ## Return
+    /// Lines to run, until the input ends or fails to be read
+    pub fn read_commands<R>(input: R, keywords: Keywords, cancellers: Vec<Canceller>) -> mpsc::Receiver<io::Result<String>>
+        where
+            R: BufRead + Send + 'static
+    {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in input.lines() {
+                let failed = line.is_err();
+                if let Ok(l) = &line {
+                    let cancel = matches!(Program::parse_with(l, &keywords), Ok(Action::Cancel));
+                    // every canceller is asked, not just until one stops something
+                    if cancel && cancellers.iter().fold(false, |stopped, c| c.cancel() | stopped) {
+                        continue
+                    }
+                }
+                if sender.send(line).is_err() || failed {
+                    break
+                }
+            }
+        });
+        receiver
+    }
+
+    /// Command that defines the given type
+    fn definition_command(name: &Name, t: &Type) -> String {
+        match t {
+            Type::Atomic(a) => match a.kind {
+                None       => format!("atomico {} {} {}", name, a.representation, a.alignment),
+                Some(kind) => format!("atomico {} {} {} {}", name, a.representation, a.alignment, kind.name())
+            },
+            Type::Struct(s) => {
+                let members : Vec<String> = s.members
+                                                .iter()
+                                                .zip(&s.fields)
+                                                .enumerate()
+                                                .map(|(i, (t, f))| Program::member_definition(i, t, f))
+                                                .collect();
+                let attributes : String = s.attributes.iter().map(|a| format!(" {}", a)).collect();
+                format!("struct {}{} {}", name, attributes, members.join(" "))
+            },
+            Type::Union(u)  => format!("union {} {}", name, u.variants.join(" ")),
+            Type::Enum(e)   => format!("enum {} {} --repr {}", name, e.variants.join(" "), e.repr.name()),
+            Type::Pointer(p) => match p.kind {
+                PointerKind::Thin => format!("puntero {} {}", name, p.target),
+                kind => format!("puntero {} {} {}", name, p.target, kind.option())
+            },
+            Type::Container(c) => {
+                let mut text = format!("contenedor {} {}", name, c.kind.name());
+                if let Some(e) = &c.element {
+                    text += &format!(" {}", e);
+                }
+                if c.kind.growable() {
+                    text += &format!(" --capacidad {} --ancho {}", c.capacity, c.width);
+                }
+                text
+            },
+            Type::Text(t) => format!("cadena {} {}", name, t.model.definition())
+        }
+    }
+
+    /// Command setting the budget of a type
+    fn budget_command(name: &Name, budget: Budget) -> String {
+        match budget.strategy {
+            Strategy::C => format!("presupuesto {} {}", name, budget.limit),
+            strategy => format!("presupuesto {} {} --estrategia {}", name, budget.limit, strategy.id())
+        }
+    }
+
+    /// Command adding attributes to a type that is not a struct, structs have theirs in their definition
+    /// ## Return
+    /// The command, or none if there are no attributes to add
+    fn attribute_command(name: &Name, attributes: &[Attribute]) -> Option<String> {
+        match attributes.is_empty() {
+            true  => None,
+            false => Some(format!("atributo {} {}", name, attributes.iter().map(Attribute::to_string).collect::<Vec<_>>().join(" ")))
+        }
+    }
+
+    /// How a single struct member is written in a definition: `tipo` for unnamed members,
+    /// `nombre:tipo` otherwise, followed by its attributes
+    fn member_definition(i: usize, member_type: &Name, field: &Field) -> String {
+        let mut text = match field.name == Field::default_name(i) {
+            true  => member_type.clone(),
+            false => format!("{}:{}", field.name, member_type)
+        };
+
+        for a in &field.attributes {
+            text += &format!(" {}", a);
+        }
+
+        text
+    }
+
+    /// Fields inside a string representation, one block per layout
+    fn text_fields(t: &Text, pointer_size: usize) -> String {
+        let layouts = t.fields(pointer_size);
+        let titles : &[&str] = match layouts.len() {
+            1 => &["Campos"],
+            _ => &["Modo largo", "Modo corto"]
+        };
+
+        let mut text = String::new();
+        for (title, fields) in titles.iter().zip(&layouts) {
+            text += &format!("   * {}:\n", title);
+            for f in fields {
+                text += &format!("      + {}: desplazamiento {}, {} bytes\n", f.name, f.offset, f.size);
+            }
+        }
+
+        text.trim_end().to_string()
+    }
+
+    /// A constraint on the member order of a struct as a JSON object
+    fn constraint_json(s: &Struct, constraint: &Constraint) -> json::Value {
+        match constraint {
+            Constraint::First { member, kept } => json::Value::object(vec![
+                ("kind",     json::Value::str("first")),
+                ("member",   json::Value::str(&s.fields[*member].name)),
+                ("kept",     json::Value::Bool(*kept))
+            ]),
+            Constraint::Pinned { member, position, offset, kept } => json::Value::object(vec![
+                ("kind",     json::Value::str("pinned")),
+                ("member",   json::Value::str(&s.fields[*member].name)),
+                ("position", json::Value::uint(*position)),
+                ("offset",   json::Value::uint(*offset)),
+                ("kept",     json::Value::Bool(*kept))
+            ]),
+            Constraint::Group { label, members, kept } => json::Value::object(vec![
+                ("kind",     json::Value::str("group")),
+                ("label",    json::Value::str(label)),
+                ("members",  json::Value::Array(members.iter().map(|i| json::Value::str(&s.fields[*i].name)).collect())),
+                ("kept",     json::Value::Bool(*kept))
+            ])
+        }
+    }
+
+    /// Constraints on the member order of a struct and whether an order keeps them, if it has any
+    fn constraints_text(s: &Struct, constraints: &[Constraint]) -> Option<String> {
+        if constraints.is_empty() {
+            return None
+        }
+
+        let mut text = String::from("🔒 Restricciones:");
+        for c in constraints {
+            let kept = match c.kept() {
+                true  => "respetada",
+                false => "no respetada, ningún orden las respeta todas"
+            };
+            text += &match c {
+                Constraint::First { member, .. } => format!(
+                    "\n   * '{}' primero, en el desplazamiento 0: {}",
+                    s.fields[*member].name, kept
+                ),
+                Constraint::Pinned { member, position, offset, .. } => format!(
+                    "\n   * '{}' fijo en la posición {}, desplazamiento {}: {}",
+                    s.fields[*member].name, position, offset, kept
+                ),
+                Constraint::Group { label, members, .. } => format!(
+                    "\n   * grupo '{}' junto ({}): {}",
+                    label,
+                    members.iter().map(|i| s.fields[*i].name.as_str()).collect::<Vec<_>>().join(", "),
+                    kept
+                )
+            };
+        }
+
+        Some(text)
+    }
+
+    /// Describe a type, as text or JSON, with optimizer stats if requested
+    fn display(&mut self, name: &Name, options: &DisplayOptions) -> Result<(), TypeError> {
+        // a search stopped early already showed the best order it found, the report would search again
+        if options.progressive && !self.optimize_progressively(name, options.json)? {
+            return Ok(())
+        }
+
+        if options.pahole {
+            let text = self.manager.pahole(name, self.config.bitfields, DEFAULT_CACHE_LINE)?;
+            self.out.line(&format!("{}\n", text));
+            return Ok(())
+        }
+
+        let report = match options.strategy {
+            Some(strategy) => self.manager.report(name)?.only(strategy),
+            None => self.manager.report(name)?
+        };
+
+        // stats are only computed when they'll be shown, the optimizer runs again for them
+        let stats = if options.stats || options.json { 
+            self.manager.optimizer_stats(name)? 
+        } 
+        else { 
+            None 
+        };
+
+        // containers already show what they own
+        let heap = match report {
+            Report::Container { .. } => 0,
+            _ => self.manager.heap_size(name)
+        };
+
+        // constraints kept by the optimized order, for structs having any
+        let constraints = match self.manager.get(name) {
+            Some(Type::Struct(_)) => self.manager.constraints(name)?,
+            _ => vec![]
+        };
+
+        if options.json {
+            let mut json = report.to_json(name);
+            if let (json::Value::Object(pairs), Some(stats)) = (&mut json, &stats) {
+                pairs.push(("optimizer".to_string(), stats.to_json()));
+            }
+            if let (json::Value::Object(pairs), Some(Type::Struct(s)), false) = (&mut json, self.manager.get(name), constraints.is_empty()) {
+                pairs.push(("constraints".to_string(), json::Value::Array(constraints.iter().map(|c| Program::constraint_json(s, c)).collect())));
+            }
+            if let (json::Value::Object(pairs), true) = (&mut json, heap > 0) {
+                pairs.push(("heap".to_string(), json::Value::uint(heap)));
+            }
+            if let (json::Value::Object(pairs), Some(origin)) = (&mut json, self.manager.origin(name)) {
+                pairs.push(("origin".to_string(), json::Value::str(origin)));
+            }
+            if let (json::Value::Object(pairs), Some(note)) = (&mut json, self.manager.note(name)) {
+                pairs.push(("note".to_string(), json::Value::str(note)));
+            }
+
+            self.out.line(&json.to_string());
+            return Ok(())
+        }
+
+        self.out.line(&format!("Símbolo: {}\n{}", name, report.display_with(self.config.numbers)));
+
+        if let (true, Some(Type::Text(t))) = (options.detail, self.manager.get(name)) {
+            self.out.line(&Program::text_fields(t, self.manager.pointer_size()));
+        }
+
+        if heap > 0 {
+            self.out.line(&format!("💾 Memoria dinámica: {} bytes, fuera del tamaño en línea", self.config.numbers.format(heap)));
+        }
+        if let Some(origin) = self.manager.origin(name) {
+            self.out.line(&format!("📍 Definido en {}", origin));
+        }
+        if let Some(note) = self.manager.note(name) {
+            self.out.line(&format!("📝 {}", note));
+        }
+        if let Some(Type::Struct(s)) = self.manager.get(name) {
+            for (field, note) in s.fields.iter().filter_map(|f| note(&f.attributes).map(|n| (&f.name, n))) {
+                self.out.line(&format!("📝 {}: {}", field, note));
+            }
+            if let Some(text) = Program::constraints_text(s, &constraints) {
+                self.out.line(&text);
+            }
+        }
+        if options.stats {
+            match stats {
+                Some(stats) => self.out.line(&stats.display_with(self.config.numbers)),
+                None => self.out.line("No hay nada que optimizar en un tipo que no es struct")
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Describe several types, named or matching patterns where `*` stands for any text
+    /// and `?` for a character. Patterns take the types in definition order, and types
+    /// named or matched more than once are described once
+    fn display_matching(&mut self, patterns: &[String], options: &DisplayOptions) -> Result<(), TypeError> {
+        let order = self.manager.definition_order();
+        let ignore_case = self.manager.ignores_case();
+        let fold = |text: &str| match ignore_case {
+            true  => text.to_lowercase(),
+            false => text.to_string()
+        };
+
+        let mut names : Vec<Name> = vec![];
+        for pattern in patterns {
+            if !Program::is_pattern(pattern) {
+                names.push(pattern.clone());
+                continue
+            }
+
+            // pahole only knows structs and unions
+            let matching : Vec<&Name> = order
+                                            .iter()
+                                            .filter(|n| utils::wildcard(&fold(pattern), &fold(n)))
+                                            .filter(|n| !options.pahole || matches!(self.manager.get(n), Some(Type::Struct(_)) | Some(Type::Union(_))))
+                                            .collect();
+            if matching.is_empty() {
+                self.out.line(&format!("Ningún tipo coincide con '{}'", pattern));
+            }
+            names.extend(matching.into_iter().cloned());
+        }
+
+        let mut seen = HashSet::new();
+        names.retain(|n| seen.insert(self.manager.resolve(n).unwrap_or(n).clone()));
+        for name in names {
+            self.display(&name, options)?;
+        }
+        Ok(())
+    }
+
+    /// Tells if a name given to `describir` is a pattern matching several types
+    fn is_pattern(name: &str) -> bool {
+        name.contains(['*', '?'])
+    }
+
+    /// Search the best member order of a struct, showing every better one as soon as
+    /// it's found, so it's worth something even if the search is stopped
+    /// ## Params
+    /// * `name` - type to optimize, nothing is shown if it's not a struct
+    /// * `json` - whether every order is shown as a JSON line
+    /// ---
+    /// ## Return
+    /// Whether the search tried every order, or an error if the type does not exist
+    fn optimize_progressively(&mut self, name: &Name, json: bool) -> Result<bool, TypeError> {
+        let s = match self.manager.get(name) {
+            None => return Err(TypeError::TypeDoesNotExist(name.clone())),
+            Some(Type::Struct(s)) => s,
+            Some(_) => return Ok(true)
+        };
+
+        let (out, numbers, start) = (&mut self.out, self.config.numbers, std::time::Instant::now());
+        let (_, _, stats) = s.optimize_with(&self.manager, &mut |order, size| {
+            let fields : Vec<&str> = order.iter().map(|i| s.fields[*i].name.as_str()).collect();
+            match json {
+                true  => out.line(&json::Value::object(vec![
+                    ("size",  json::Value::uint(size)),
+                    ("order", json::Value::Array(fields.iter().map(|f| json::Value::str(f)).collect()))
+                ]).to_string()),
+                false => out.line(&format!(
+                    "⏳ {:.3} ms: {} bytes con {}",
+                    start.elapsed().as_secs_f64() * 1000.0,
+                    numbers.format(size),
+                    fields.join(" ")
+                ))
+            }
+            // shown right away, the search may go on for long
+            out.flush(false);
+        });
+
+        let complete = !stats.cancelled && !stats.timed_out;
+        if !complete && !json {
+            self.out.line("La búsqueda se detuvo antes de probar todos los órdenes, el último mostrado es el mejor encontrado");
+        }
+        Ok(complete)
+    }
+
+    /// List every stored type with its kind and size under every strategy
+    fn list(&mut self) {
+        let names : TypeList = self.manager.names().cloned().collect();
+
+        let mut headers = vec!["Nombre", "Tipo"];
+        headers.extend(Strategy::ALL.iter().map(|s| s.name()));
+        let mut table = Table::new(&headers);
+        for i in 0..Strategy::ALL.len() {
+            table = table.align(i + 2, Align::Right);
+        }
+
+        for name in names {
+            let t = match self.manager.get(&name) {
+                Some(t) => t,
+                None => continue
+            };
+            let mut row = vec![name.clone(), t.kind_name().to_string()];
+            row.extend(Strategy::ALL.iter().map(|s| self.config.numbers.format(s.type_size(t, &self.manager))));
+            table.row(row);
+        }
+
+        if table.is_empty() {
+            self.out.line("No hay tipos definidos");
+            return
+        }
+
+        self.out.line(table.render(self.config.tables).trim_end());
+    }
+
+    /// Show the offset of every member in a struct under every strategy
+    fn offsets(&mut self, name : &Name) -> Result<(), TypeError> {
+        let s = match self.manager.get(name) {
+            None => return Err(TypeError::TypeDoesNotExist(name.clone())),
+            Some(Type::Struct(s)) => s,
+            Some(_) => return Err(TypeError::NotAStruct(name.clone()))
+        };
+
+        // Members in declaration order
+        let strategies = [Strategy::Unpacked, Strategy::Packed, Strategy::C];
+        let mut headers = vec!["#", "Campo", "Tipo"];
+        headers.extend(strategies.iter().map(|s| s.name()));
+        let mut table = Table::new(&headers).align(0, Align::Right);
+        for i in 0..strategies.len() {
+            table = table.align(i + 3, Align::Right);
+        }
+
+        let layouts : Vec<Vec<MemberLayout>> = strategies.iter().map(|st| s.layout(&self.manager, *st)).collect();
+        for (i, member) in s.members.iter().enumerate() {
+            let mut row = vec![i.to_string(), s.fields[i].name.clone(), member.clone()];
+            row.extend(layouts.iter().map(|l| self.config.numbers.format(l[i].offset)));
+            table.row(row);
+        }
+
+        // Members in the order chosen by the optimizer
+        let mut optimized = Table::new(&["#", "Campo", "Tipo", "Desplazamiento", "Tamaño"])
+                                .align(0, Align::Right)
+                                .align(3, Align::Right)
+                                .align(4, Align::Right);
+        for m in s.layout(&self.manager, Strategy::Optimized) {
+            optimized.row(vec![
+                m.index.to_string(), 
+                s.fields[m.index].name.clone(), 
+                s.members[m.index].clone(), 
+                self.config.numbers.format(m.offset),
+                self.config.numbers.format(m.size)
+            ]);
+        }
+
+        self.out.line(&format!("Desplazamientos de {}:", name));
+        self.out.line(table.render(self.config.tables).trim_end());
+        self.out.line(&format!("{}:", Strategy::Optimized.name()));
+        self.out.line(optimized.render(self.config.tables).trim_end());
+        if let Some(text) = Program::constraints_text(s, &self.manager.constraints(name)?) {
+            self.out.line(&text);
+        }
+
+        Ok(())
+    }
+
+    /// Add a command defined outside the driver
+    /// ## Return
+    /// An error if there's a command with the same name already
+    pub fn register_plugin(&mut self, plugin: Box<dyn CommandPlugin>) -> Result<(), ProgramError> {
+        if self.config.keywords.get(plugin.name()).is_some() {
+            return Err(ProgramError::DuplicateCommand(plugin.name().to_string()))
+        }
+        self.plugins.register(plugin)
+    }
+
+    /// Get next action from user input, built in commands first and then plugins
+    pub fn parse_command(&self, input: &str) -> Result<Action, ProgramError> {
+        // some commands read their files as they are parsed
+        if self.config.sandbox && Program::uses_files(input, &self.config.keywords) {
+            return Err(ProgramError::Sandboxed(input.trim().to_string()))
+        }
+
+        match Program::parse_with(input, &self.config.keywords) {
+            Err(ProgramError::InvalidAction(verb)) => {
+                let plugin = match self.plugins.get(&verb) {
+                    Some(p) => p,
+                    None    => return Err(ProgramError::InvalidAction(verb))
+                };
+
+                let args : Vec<&str> = input.split_whitespace().skip(1).collect();
+                Ok(Action::Plugin(verb.clone(), plugin.parse(&args)?))
+            },
+            result => result
+        }
+    }
+
+    /// Tells if a line is a command reading or writing files: the ones loading, saving,
+    /// importing or exporting to files, journals and access traces
+    fn uses_files(line: &str, keywords: &Keywords) -> bool {
+        let words = Program::words(line);
+        let verb = match words.first().and_then(|w| keywords.get(w)) {
+            Some(v) => v,
+            None    => return false
+        };
+
+        match verb {
+            Verb::Include | Verb::Save | Verb::Load | Verb::Merge | Verb::Import | Verb::Replay | Verb::VerifyReplay | Verb::Document | Verb::Trace => true,
+            Verb::Journal => !matches!(words.get(1), None | Some(&"--parar") | Some(&"--stop")),
+            Verb::Export  => words.get(1) == Some(&"imagen"),
+            Verb::Decode  => matches!(words.get(2), Some(source) if parse_hex(source).is_none()),
+            Verb::Redefine | Verb::Try => Program::uses_files(line.trim_start()[words[0].len()..].trim(), keywords),
+            _ => false
+        }
+    }
+
+    /// Get next action from user input, only built in commands with their Spanish words
+    pub fn parse(line: &str) -> Result<Action, ProgramError>{
+        Program::parse_with(line, &Keywords::default())
+    }
+
+    /// Split a line in words separated by whitespace, where text between quotes is part
+    /// of a single word, so attribute values can have spaces: `@nota="cabecera de red"`
+    fn words(line: &str) -> Vec<&str> {
+        let mut words = vec![];
+        let mut start = None;
+        let mut quoted = false;
+        for (i, c) in line.char_indices() {
+            match (start, c) {
+                (_, '"') => {
+                    quoted = !quoted;
+                    start = start.or(Some(i));
+                },
+                (Some(s), c) if c.is_whitespace() && !quoted => {
+                    words.push(&line[s..i]);
+                    start = None;
+                },
+                (None, c) if !c.is_whitespace() => start = Some(i),
+                _ => ()
+            }
+        }
+        if let Some(s) = start {
+            words.push(&line[s..]);
+        }
+        words
+    }
+
+    /// Get next action from user input, only built in commands
+    /// ## Params
+    /// * `line` - line to parse
+    /// * `keywords` - words accepted for every command
+    pub fn parse_with(line: &str, keywords: &Keywords) -> Result<Action, ProgramError>{
+        let mut input = Program::words(line).into_iter();
+
+        // Try to Parse verb from input
+        let word = match input.next() {
+            None  => return Err( ProgramError::NotEnoughArgs ),
+            Some(s) => s
+        };
+        let verb = match keywords.get(word) {
+            Some(v) => v,
+            None    => return Err( ProgramError::InvalidAction(word.to_lowercase()) )
+        };
+
+        // everything after the command word
+        let rest = line.trim_start()[word.len()..].trim();
+
+        match verb {
+            Verb::Exit      => Ok(Action::Exit),
+            Verb::Cancel    => match input.next() {
+                None    => Ok(Action::Cancel),
+                Some(_) => Err(ProgramError::TooManyArgs)
+            },
+            Verb::Union     => Program::parse_action(compound_tokens(input), Action::AddUnion),
+            Verb::Struct    => Program::parse_struct(compound_tokens(input)),
+            Verb::Wizard    => Program::parse_wizard(input),
+            Verb::Macro     => {
+                Macro::parse_definition(rest).map(|(name, params, body)| Action::DefineMacro(name, params, body))
+            },
+            Verb::Atomic    => Program::parse_atomic(rest),
+            Verb::Let       => Program::parse_let(rest),
+            Verb::Include   => match rest.trim_matches('"') {
+                ""   => Err(ProgramError::NotEnoughArgs),
+                file => Ok(Action::Include(file.to_string()))
+            },
+            Verb::Save      => match Program::parse_session_args(input)? {
+                (file, Some(format)) => Ok(Action::Save(file, format)),
+                (file, None)         => Ok(Action::Save(file, SessionFormat::Script))
+            },
+            Verb::Load      => Program::parse_session_args(input).map(|(file, format)| Action::Load(file, format)),
+            Verb::Merge     => {
+                let file = input.next().ok_or(ProgramError::NotEnoughArgs)?;
+                let options : Vec<&str> = input.collect();
+                match MergeStrategy::parse(&options) {
+                    Some(strategy) => Ok(Action::Merge(file.to_string(), strategy)),
+                    None => Err(ProgramError::InvalidArgument(options.join(" ")))
+                }
+            },
+            Verb::Journal   => match (input.next(), input.next()) {
+                (None, _) => Ok(Action::Journal(JournalCommand::Show)),
+                (Some("--parar") | Some("--stop"), None) => Ok(Action::Journal(JournalCommand::Stop)),
+                (Some(file), None) => Ok(Action::Journal(JournalCommand::Start(file.to_string()))),
+                _ => Err(ProgramError::TooManyArgs)
+            },
+            Verb::Replay    => match (input.next(), input.next()) {
+                (Some(file), None) => Ok(Action::Replay(file.to_string())),
+                (None, _) => Err(ProgramError::NotEnoughArgs),
+                _ => Err(ProgramError::TooManyArgs)
+            },
+            Verb::VerifyReplay => match (input.next(), input.next(), input.next()) {
+                (Some(journal), Some(snapshot), None) => Ok(Action::VerifyReplay(journal.to_string(), snapshot.to_string())),
+                (_, None, _) => Err(ProgramError::NotEnoughArgs),
+                _ => Err(ProgramError::TooManyArgs)
+            },
+            Verb::Diff      => match (input.next(), input.next(), input.next()) {
+                (Some(before), Some(after), None) => Ok(Action::Diff(before.to_string(), after.to_string())),
+                (_, None, _) => Err(ProgramError::NotEnoughArgs),
+                _ => Err(ProgramError::TooManyArgs)
+            },
+            Verb::Session   => {
+                let args : Vec<&str> = input.collect();
+                match SessionCommand::parse(&args) {
+                    Ok(command)    => Ok(Action::Session(command)),
+                    Err(Some(abi)) => Err(ProgramError::UnknownAbi(abi)),
+                    Err(None) if args.len() < 2 => Err(ProgramError::NotEnoughArgs),
+                    Err(None) => Err(ProgramError::InvalidArgument(args.join(" ")))
+                }
+            },
+            Verb::History   => match input.next() {
+                None    => Ok(Action::History),
+                Some(_) => Err(ProgramError::TooManyArgs)
+            },
+            Verb::Variables => match input.next() {
+                None    => Ok(Action::ListVariables),
+                Some(_) => Err(ProgramError::TooManyArgs)
+            },
+            Verb::Calculate => {
+                if rest.is_empty() {
+                    return Err(ProgramError::NotEnoughArgs)
+                }
+                Expr::parse(rest).map(Action::Calculate).map_err(ProgramError::Expression)
+            },
+            Verb::Assert    => {
+                if rest.is_empty() {
+                    return Err(ProgramError::NotEnoughArgs)
+                }
+                Expr::parse(rest).map(|e| Action::Assert(rest.trim().to_string(), e)).map_err(ProgramError::Expression)
+            },
+            Verb::Describe  => Program::parse_display(input),
+            Verb::List      => Program::parse_list(input),
+            Verb::Offsets   => Program::parse_offsets(input),
+            Verb::Split     => Program::parse_split(input, Action::Split),
+            Verb::Pareto    => Program::parse_split(input, Action::Pareto),
+            Verb::Flags     => Program::parse_flags(input),
+            Verb::Enum      => Program::parse_enum(compound_tokens(input)),
+            Verb::Pointer   => match (input.next(), input.next(), input.next(), input.next()) {
+                (Some(name), Some(target), None, _) => Ok(Action::AddPointer(name.to_string(), target.to_string(), PointerKind::Thin)),
+                (Some(name), Some(target), Some(option), None) => match PointerKind::parse(option) {
+                    Some(kind) => Ok(Action::AddPointer(name.to_string(), target.to_string(), kind)),
+                    None => Err(ProgramError::InvalidArgument(option.to_string()))
+                },
+                (_, _, _, Some(_)) => Err(ProgramError::TooManyArgs),
+                _ => Err(ProgramError::NotEnoughArgs)
+            },
+            Verb::Container => Program::parse_container(input),
+            Verb::Density   => Program::parse_density(input),
+            Verb::Plan      => Program::parse_plan(input),
+            Verb::Annotate  => Program::parse_annotate(input),
+            Verb::Decode    => Program::parse_decode(input),
+            Verb::Generate  => Program::parse_generate(input),
+            Verb::Import    => Program::parse_import(input),
+            Verb::Export    => Program::parse_export(input),
+            Verb::Code      => {
+                let mut rust = false;
+                let mut names = vec![];
+                for arg in input {
+                    match arg {
+                        "--rust" => rust = true,
+                        "--c"    => rust = false,
+                        s if s.starts_with("--") => return Err(ProgramError::InvalidArgument(s.to_string())),
+                        s => names.push(s.to_string())
+                    }
+                }
+                match names.is_empty() {
+                    true  => Err(ProgramError::NotEnoughArgs),
+                    false => Ok(Action::Code(names, rust))
+                }
+            },
+            Verb::Addresses => match (input.next(), input.next(), input.next(), input.next(), input.next()) {
+                (Some(name), Some(base), None, _, _) => Ok(Action::Addresses(name.to_string(), Expr::parse(base).map_err(ProgramError::Expression)?, Strategy::C)),
+                (Some(name), Some(base), Some("--estrategia"), Some(strategy), None) => match Strategy::parse(strategy) {
+                    Some(strategy) => Ok(Action::Addresses(name.to_string(), Expr::parse(base).map_err(ProgramError::Expression)?, strategy)),
+                    None => Err(ProgramError::InvalidArgument(strategy.to_string()))
+                },
+                (_, _, _, _, Some(_)) => Err(ProgramError::TooManyArgs),
+                (Some(_), Some(_), Some(option), None, _) if option != "--estrategia" => Err(ProgramError::InvalidArgument(option.to_string())),
+                _ => Err(ProgramError::NotEnoughArgs)
+            },
+            Verb::Reuse     => match (input.next(), input.next()) {
+                (Some(name), None) => Ok(Action::TailReuse(name.to_string())),
+                (Some(_), Some(_)) => Err(ProgramError::TooManyArgs),
+                (None, _) => Err(ProgramError::NotEnoughArgs)
+            },
+            Verb::Variant   => match (input.next(), input.next(), input.next()) {
+                (Some(union), Some(variant), None) => Ok(Action::Variant(union.to_string(), variant.to_string())),
+                (_, _, Some(_)) => Err(ProgramError::TooManyArgs),
+                _ => Err(ProgramError::NotEnoughArgs)
+            },
+            Verb::Bits      => match (input.next(), input.next(), input.next(), input.next()) {
+                (Some(name), None, _, _) => Ok(Action::Bitfields(name.to_string(), None)),
+                (Some(name), Some("--politica") | Some("--política"), Some(policy), None) => match BitfieldPolicy::parse(policy) {
+                    Some(policy) => Ok(Action::Bitfields(name.to_string(), Some(policy))),
+                    None => Err(ProgramError::InvalidArgument(policy.to_string()))
+                },
+                (Some(_), Some(s), None, _) if s.starts_with("--") => Err(ProgramError::NotEnoughArgs),
+                (Some(_), Some(s), _, _) if s.starts_with("--") => Err(ProgramError::InvalidArgument(s.to_string())),
+                (None, _, _, _) => Err(ProgramError::NotEnoughArgs),
+                _ => Err(ProgramError::TooManyArgs)
+            },
+            Verb::Text      => {
+                let args : Vec<&str> = input.collect();
+                let (name, model, size) = match args.as_slice() {
+                    [name, model]       => (name, model, None),
+                    [name, model, size] => (name, model, Some(size.parse().map_err(|_| ProgramError::InvalidArgument(size.to_string()))?)),
+                    [] | [_]            => return Err(ProgramError::NotEnoughArgs),
+                    _                   => return Err(ProgramError::TooManyArgs)
+                };
+                let model = TextModel::parse(model, size).map_err(ProgramError::InvalidArgument)?;
+                Ok(Action::AddText(name.to_string(), model))
+            },
+            Verb::Optional  => match (input.next(), input.next()) {
+                (Some(name), None) => Ok(Action::Optional(name.to_string())),
+                (Some(_), Some(_)) => Err(ProgramError::TooManyArgs),
+                (None, _) => Err(ProgramError::NotEnoughArgs)
+            },
+            Verb::Redefine  => match Program::parse_with(rest, keywords)? {
+                Action::Flags(..) | Action::Redefine(_) => Err(ProgramError::InvalidArgument(rest.to_string())),
+                action if action.is_definition() => Ok(Action::Redefine(Box::new(action))),
+                _ => Err(ProgramError::InvalidArgument(rest.to_string()))
+            },
+            Verb::Try       => match Program::parse_with(rest, keywords)? {
+                Action::Flags(..) | Action::Redefine(_) | Action::Try(_) => Err(ProgramError::InvalidArgument(rest.to_string())),
+                action if action.is_definition() => Ok(Action::Try(Box::new(action))),
+                _ => Err(ProgramError::InvalidArgument(rest.to_string()))
+            },
+            Verb::Budget    => Program::parse_budget(input),
+            Verb::Validate  => match input.next() {
+                None => Ok(Action::Validate),
+                Some(_) => Err(ProgramError::TooManyArgs)
+            },
+            Verb::Flatten   => Program::parse_flatten(input),
+            Verb::Growth    => match (input.next(), input.next()) {
+                (Some(name), None) => Ok(Action::Growth(name.to_string())),
+                (Some(_), Some(_)) => Err(ProgramError::TooManyArgs),
+                (None, _) => Err(ProgramError::NotEnoughArgs)
+            },
+            Verb::Anneal    => Program::parse_anneal(input),
+            Verb::Solve     => match (input.next(), input.next()) {
+                (Some(name), None) => Ok(Action::Solve(name.to_string())),
+                (Some(_), Some(_)) => Err(ProgramError::TooManyArgs),
+                (None, _) => Ok(Action::ResolvePending)
+            },
+            Verb::Trace     => Program::parse_trace(input),
+            Verb::Clean     => Ok(Action::Clean(input.map(String::from).collect())),
+            Verb::Attribute => Program::parse_attribute(input),
+            Verb::Document  => match (input.next(), input.next()) {
+                (Some(dir), None) => Ok(Action::Document(dir.to_string())),
+                (Some(_), Some(_)) => Err(ProgramError::TooManyArgs),
+                (None, _) => Err(ProgramError::NotEnoughArgs)
+            },
+            Verb::Stride    => match (input.next(), input.next()) {
+                (Some(name), None) => Ok(Action::Stride(name.to_string())),
+                (Some(_), Some(_)) => Err(ProgramError::TooManyArgs),
+                (None, _) => Err(ProgramError::NotEnoughArgs)
+            },
+            Verb::Profile   => match (input.next(), input.next()) {
+                (Some(name), None) => Ok(Action::Profile(name.to_string())),
+                (Some(_), Some(_)) => Err(ProgramError::TooManyArgs),
+                (None, _) => Err(ProgramError::NotEnoughArgs)
+            },
+            Verb::Lint      => match (input.next(), input.next()) {
+                (None, _)                 => Ok(Action::Lint(None)),
+                (Some("--reglas"), None)  => Ok(Action::LintRules),
+                (Some(s), _) if s.starts_with("--") => Err(ProgramError::InvalidArgument(s.to_string())),
+                (Some(name), None)        => Ok(Action::Lint(Some(name.to_string()))),
+                (Some(_), Some(_))        => Err(ProgramError::TooManyArgs)
+            }
+        }
+    }
+
+    /// Parse properties from token iterator and create a new action with the given constructor
+    fn parse_action<'a, I>(input:  I, act : fn (Name, TypeList) -> Action) -> Result<Action, ProgramError> 
+        where 
+            I: Iterator<Item=&'a str>
+    {
+        let mut input = input; // honestly i don't get why this is necessary for this function to type check but ok
+
+        // parse name, if not found, then not enough arguments error is raised
+        let name = match &mut input.next() {
+            None    => return Err( ProgramError::NotEnoughArgs ),
+            Some(e) => e.to_string()
+        };
+
+        // Create a typelist from the rest of our tokens
+        let types : TypeList= input.map(|s| s.to_string()).collect();
+
+        Ok(act(name, types))
+    }
+
+    /// Parse a struct: a name and its members, written as `tipo` or `nombre:tipo`.
+    /// `@clave` and `@clave=valor` attributes apply to the member right before them
+    fn parse_struct<'a, I>(input: I) -> Result<Action, ProgramError> 
+        where 
+            I: Iterator<Item=&'a str>
+    {
+        let mut input = input;
+        let name = match input.next() {
+            None    => return Err( ProgramError::NotEnoughArgs ),
+            Some(e) => e.to_string()
+        };
+
+        let mut members = TypeList::new();
+        let mut fields : Vec<Field> = vec![];
+        let mut attributes = vec![];    // before the first member, for the whole struct
+        for token in input {
+            if token.starts_with('@') {
+                let attribute = Attribute::parse(token).ok_or_else(|| ProgramError::InvalidArgument(token.to_string()))?;
+                match fields.last_mut() {
+                    Some(f) => f.attributes.push(attribute),
+                    None    => attributes.push(attribute)
+                }
+                continue
+            }
+
+            let (field, member) = match token.split_once(':') {
+                Some((f, t)) if Field::valid_path(f) && !t.is_empty() => (f.to_string(), t.to_string()),
+                Some(_) => return Err(ProgramError::InvalidArgument(token.to_string())),
+                None    => (Field::default_name(members.len()), token.to_string())
+            };
+            members.push(member);
+            fields.push(Field::new(field));
+        }
+
+        Ok(Action::AddStruct(name, Struct::with_fields(members, fields).with_attributes(attributes)))
+    }
+
+    /// Parse a wizard action, it takes no arguments
+    fn parse_wizard<'a, I>(input: I) -> Result<Action, ProgramError> 
+        where 
+            I: Iterator<Item = &'a str>
+    {
+        let mut input = input;
+        if input.next().is_some() {
+            return Err(ProgramError::TooManyArgs)
+        }
+
+        Ok(Action::Wizard)
+    }
+
+    /// Parse atomic type: a name, its size and its alignment. Size and alignment
+    /// can be expressions using other atomics, such as `(int.size / 2)`
+    fn parse_atomic(input: &str) -> Result<Action, ProgramError> {
+        let args = expr::split_arguments(input);
+
+        match args.len() {
+            n if n < 3 => return Err(ProgramError::NotEnoughArgs),
+            n if n > 4 => return Err(ProgramError::TooManyArgs),
+            _ => {}
+        }
+
+        let repr = Expr::parse(args[1]).map_err(ProgramError::Expression)?;
+        let align = Expr::parse(args[2]).map_err(ProgramError::Expression)?;
+        let kind = match args.get(3) {
+            None => None,
+            Some(kind) => Some(ValueKind::parse(kind).ok_or_else(|| ProgramError::InvalidArgument(kind.to_string()))?)
+        };
+
+        // return our new atomic type
+        Ok(Action::AddAtomic(args[0].to_string(), repr, align, kind))
+    }
+
+    /// Parse an action on a struct and a cache line size, such as `dividir <struct> [--linea N]`
+    fn parse_split<'a, I>(input: I, action: fn(Name, usize) -> Action) -> Result<Action, ProgramError>
+        where
+            I: Iterator<Item=&'a str>
+    {
+        let mut name = None;
+        let mut line = DEFAULT_CACHE_LINE;
+        let mut input = input;
+
+        while let Some(arg) = input.next() {
+            match arg {
+                "--linea" | "--línea" => {
+                    let value = input.next().ok_or(ProgramError::NotEnoughArgs)?;
+                    line = match value.parse() {
+                        Ok(n) if n > 0 => n,
+                        _ => return Err(ProgramError::InvalidArgument(value.to_string()))
+                    };
+                },
+                s if s.starts_with("--") => return Err(ProgramError::InvalidArgument(s.to_string())),
+                s if name.is_none() => name = Some(s.to_string()),
+                _ => return Err(ProgramError::TooManyArgs)
+            }
+        }
+
+        match name {
+            Some(name) => Ok(action(name, line)),
+            None => Err(ProgramError::NotEnoughArgs)
+        }
+    }
 
+    /// Parse an access trace command: a struct, the trace file and optionally `--linea N`
+    fn parse_trace<'a, I>(input: I) -> Result<Action, ProgramError>
+        where
+            I: Iterator<Item=&'a str>
+    {
+        let mut args = vec![];
+        let mut line = DEFAULT_CACHE_LINE;
+        let mut input = input;
 
-/// Our program object
-pub struct Program {
-    running: bool,
-    manager: TypeManager
-}
+        while let Some(arg) = input.next() {
+            match arg {
+                "--linea" | "--línea" => {
+                    let value = input.next().ok_or(ProgramError::NotEnoughArgs)?;
+                    line = match value.parse() {
+                        Ok(n) if n > 0 => n,
+                        _ => return Err(ProgramError::InvalidArgument(value.to_string()))
+                    };
+                },
+                s if s.starts_with("--") => return Err(ProgramError::InvalidArgument(s.to_string())),
+                s if args.len() < 2 => args.push(s.to_string()),
+                _ => return Err(ProgramError::TooManyArgs)
+            }
+        }
 
-/// possible errors
-pub enum ProgramError {
-    NotEnoughArgs,
-    TooManyArgs,
-    InvalidAction(String),
-    InvalidArgument(String)
-}
+        match args.as_slice() {
+            [name, file] => Ok(Action::Trace(name.clone(), file.clone(), line)),
+            _ => Err(ProgramError::NotEnoughArgs)
+        }
+    }
 
-/// Possible actions
-pub enum Action {
-    Display(Name),
-    AddStruct(Name, TypeList),      // name, members
-    AddUnion(Name, TypeList),       // name, variants
-    AddAtomic(Name, usize, usize),  // name, representation, alignment
-    Exit
-}
+    /// Parse an enum: a name, its variants and optionally `--repr int|minimo`
+    fn parse_enum<'a, I>(input: I) -> Result<Action, ProgramError>
+        where
+            I: Iterator<Item=&'a str>
+    {
+        let mut input = input;
+        let name = match input.next() {
+            None    => return Err(ProgramError::NotEnoughArgs),
+            Some(e) => e.to_string()
+        };
 
-impl Program {
+        let mut variants = vec![];
+        let mut repr = None;
+        while let Some(arg) = input.next() {
+            match arg {
+                "--repr" => {
+                    let value = input.next().ok_or(ProgramError::NotEnoughArgs)?;
+                    repr = Some(EnumRepr::parse(value).ok_or_else(|| ProgramError::InvalidArgument(value.to_string()))?);
+                },
+                s if Field::valid_name(s) => variants.push(s.to_string()),
+                s => return Err(ProgramError::InvalidArgument(s.to_string()))
+            }
+        }
 
-    // Crea un programa nuevo listo para correr
-    pub fn new() -> Program {
-        Program {
-            manager: TypeManager::new(),
-            running: true
+        Ok(Action::AddEnum(name, variants, repr))
+    }
+
+    /// Parse a container: `contenedor <nombre> <vec|string|box> [tipo] [--capacidad N] [--ancho N]`.
+    /// Vectors and boxes need the type they own, strings own bytes
+    fn parse_container<'a, I>(input: I) -> Result<Action, ProgramError>
+        where
+            I: Iterator<Item=&'a str>
+    {
+        let mut input = input;
+        let name = input.next().ok_or(ProgramError::NotEnoughArgs)?.to_string();
+        let kind = input.next().ok_or(ProgramError::NotEnoughArgs)?;
+        let kind = ContainerKind::parse(kind).ok_or_else(|| ProgramError::InvalidArgument(kind.to_string()))?;
+
+        let mut element = None;
+        let mut capacity = 0;
+        let mut width = DEFAULT_WIDTH;
+        while let Some(arg) = input.next() {
+            match arg {
+                "--capacidad" | "--ancho" if kind.growable() => {
+                    let value = input.next().ok_or(ProgramError::NotEnoughArgs)?;
+                    let n = value.parse().map_err(|_| ProgramError::InvalidArgument(value.to_string()))?;
+                    match arg {
+                        "--capacidad" => capacity = n,
+                        _ if [1, 2, 4, 8].contains(&n) => width = n,
+                        _ => return Err(ProgramError::InvalidArgument(value.to_string()))
+                    }
+                },
+                s if s.starts_with("--") => return Err(ProgramError::InvalidArgument(s.to_string())),
+                s if element.is_none() && kind != ContainerKind::String => element = Some(s.to_string()),
+                _ => return Err(ProgramError::TooManyArgs)
+            }
         }
+
+        if element.is_none() && kind != ContainerKind::String {
+            return Err(ProgramError::NotEnoughArgs)
+        }
+        Ok(Action::AddContainer(name, Container::new(kind, element, capacity, width)))
     }
 
-    /// Tells if this program should run
-    pub fn should_run(&self) -> bool {
-        self.running
+    /// Parse a density action: `densidad <tipo> [--estrategia E] [--linea N] [--pagina N]`
+    fn parse_density<'a, I>(input: I) -> Result<Action, ProgramError>
+        where
+            I: Iterator<Item=&'a str>
+    {
+        let mut name = None;
+        let mut strategy = Strategy::C;
+        let mut line = DEFAULT_CACHE_LINE;
+        let mut page = DEFAULT_PAGE;
+        let mut input = input;
+
+        while let Some(arg) = input.next() {
+            match arg {
+                "--estrategia" | "--linea" | "--línea" | "--pagina" | "--página" => {
+                    let value = input.next().ok_or(ProgramError::NotEnoughArgs)?;
+                    let invalid = || ProgramError::InvalidArgument(value.to_string());
+                    match arg {
+                        "--estrategia" => strategy = Strategy::parse(value).ok_or_else(invalid)?,
+                        _ => {
+                            let n = match value.parse() {
+                                Ok(n) if n > 0 => n,
+                                _ => return Err(invalid())
+                            };
+                            match arg.starts_with("--l") {
+                                true  => line = n,
+                                false => page = n
+                            }
+                        }
+                    }
+                },
+                s if s.starts_with("--") => return Err(ProgramError::InvalidArgument(s.to_string())),
+                s if name.is_none() => name = Some(s.to_string()),
+                _ => return Err(ProgramError::TooManyArgs)
+            }
+        }
+
+        match name {
+            Some(name) => Ok(Action::Density(name, strategy, line, page)),
+            None => Err(ProgramError::NotEnoughArgs)
+        }
     }
 
-    /// Run an iteration for the program
-    pub fn run(&mut self) {
+    /// Parse an annotation action: `anotar <tipo> <archivo|hex> [--estrategia E]`. Text that
+    /// isn't hexadecimal names a file, which is read here
+    fn parse_annotate<'a, I>(input: I) -> Result<Action, ProgramError>
+        where
+            I: Iterator<Item=&'a str>
+    {
+        let mut input = input;
+        let (name, source) = match (input.next(), input.next()) {
+            (Some(name), Some(source)) => (name.to_string(), source),
+            _ => return Err(ProgramError::NotEnoughArgs)
+        };
+        let strategy = match (input.next(), input.next(), input.next()) {
+            (None, _, _) => Strategy::C,
+            (Some("--estrategia"), Some(strategy), None) => Strategy::parse(strategy).ok_or_else(|| ProgramError::InvalidArgument(strategy.to_string()))?,
+            (Some("--estrategia"), None, _) => return Err(ProgramError::NotEnoughArgs),
+            (Some("--estrategia"), Some(_), Some(_)) => return Err(ProgramError::TooManyArgs),
+            (Some(option), _, _) => return Err(ProgramError::InvalidArgument(option.to_string()))
+        };
 
-        // Command buffer: store user input in this line
-        let mut line = String::new();
+        let bytes = match parse_hex(source) {
+            Some(bytes) => bytes,
+            None => std::fs::read(source).map_err(|e| ProgramError::IncludeRead(source.to_string(), e.to_string()))?
+        };
 
-        print!(">> "); // print prompt
-        // flush so the print! doesn't mess up the execution order with read_line
-        io::stdout().flush().expect("Couldn't flush stdout"); 
+        Ok(Action::Annotate(name, bytes, strategy))
+    }
 
-        // Read a single line
-        if let Err(_) = io::stdin().read_line(&mut line) { panic!("Error leyendo input D:") }
-        
-        // Parse next action
-        let next_action = match Program::parse(line) {
-            Err(e) => { println!("[ERROR]: {}", e.display()); return },
-            Ok(a)  => a
-        };
-
-        // Function to handle type errors 
-        let handle_error = |e : TypeError| {
-            println!("[TYPE ERROR]: {}", e.display());
-            let res : Option<TypeError> = None;
-
-            res
-        };
-
-        // Create a new type as described and handle error if necessary
-        match next_action {
-            Action::Exit => self.running = false,
-            Action::Display(s) => { 
-                    self.manager
-                    .display(&s)
-                    .and_then(|msg|
-                        { println!("Símbolo: {}\n{}", s, msg); Ok(()) }
-                    )
-                    .err()
-                    .and_then(handle_error); 
-                }
-            Action::AddAtomic(name, repr, align) => {
-                    self.manager
-                    .add(
-                        name,
-                        Type::Atomic( 
-                            Atomic::new(repr, align)
-                        )
-                    )
-                    .err()
-                    .and_then(handle_error);
-                },
-            Action::AddStruct(name, members) => {
-                self.manager
-                .add(
-                    name, 
-                    Type::Struct(
-                        Struct::new(members)
-                    )
-                )
-                .err()
-                .and_then(handle_error);
-            },
-            Action::AddUnion(name, variants) => {
-                self.manager
-                .add(
-                    name, 
-                    Type::Union(
-                        Union::new(variants)
-                    )
-                )
-                .err()
-                .and_then(handle_error);
-            }
+    /// Parse `presupuesto [<tipo> <bytes> [--estrategia E] | <tipo> --quitar]`, with no
+    /// arguments it shows every budget
+    fn parse_budget<'a, I>(input: I) -> Result<Action, ProgramError>
+        where
+            I: Iterator<Item=&'a str>
+    {
+        let args : Vec<&str> = input.collect();
+        let (name, limit, strategy) = match args.as_slice() {
+            [] => return Ok(Action::Budgets),
+            [name, "--quitar"] => return Ok(Action::Budget(name.to_string(), None)),
+            [name, limit] => (name, limit, Strategy::C),
+            [name, limit, "--estrategia", strategy] => (name, limit, Strategy::parse(strategy).ok_or_else(|| ProgramError::InvalidArgument(strategy.to_string()))?),
+            [_] | [_, _, "--estrategia"] => return Err(ProgramError::NotEnoughArgs),
+            [_, _, option, ..] if *option != "--estrategia" => return Err(ProgramError::InvalidArgument(option.to_string())),
+            _ => return Err(ProgramError::TooManyArgs)
         };
+
+        let limit = limit.parse::<usize>().map_err(|_| ProgramError::InvalidArgument(limit.to_string()))?;
+        Ok(Action::Budget(name.to_string(), Some(Budget { limit, strategy })))
     }
 
-    /// Get next action from user input
-    fn parse(input: String) -> Result<Action, ProgramError>{
-        let mut input = input.split_whitespace();
+    /// Parse `aplanar <struct> [--nombre <nombre>] [--estrategia <estrategia>]`, the flat
+    /// struct is named `<struct>_plano` by default and keeps the C offsets
+    fn parse_flatten<'a, I>(input: I) -> Result<Action, ProgramError>
+        where
+            I: Iterator<Item=&'a str>
+    {
+        let mut input = input;
+        let name = input.next().ok_or(ProgramError::NotEnoughArgs)?.to_string();
+        let (mut flat, mut strategy) = (format!("{}_plano", name), Strategy::C);
+        while let Some(option) = input.next() {
+            let value = input.next().ok_or(ProgramError::NotEnoughArgs)?;
+            match option {
+                "--nombre" => flat = value.to_string(),
+                "--estrategia" => strategy = Strategy::parse(value).ok_or_else(|| ProgramError::InvalidArgument(value.to_string()))?,
+                _ => return Err(ProgramError::InvalidArgument(option.to_string()))
+            }
+        }
+
+        Ok(Action::Flatten(name, flat, strategy))
+    }
 
-        // Try to Parse verb from input
-        let action = match input.next() {
-            None  => return Err( ProgramError::NotEnoughArgs ),
-            Some(s) => s.to_lowercase()
+    /// Parse `atributo <tipo>[.<campo>] @clave[=valor]...`
+    fn parse_attribute<'a, I>(input: I) -> Result<Action, ProgramError>
+        where
+            I: Iterator<Item=&'a str>
+    {
+        let mut input = input;
+        let target = input.next().ok_or(ProgramError::NotEnoughArgs)?;
+        let (name, member) = match target.split_once('.') {
+            Some((t, f)) if !t.is_empty() && Field::valid_path(f) => (t.to_string(), Some(f.to_string())),
+            Some(_) => return Err(ProgramError::InvalidArgument(target.to_string())),
+            None => (target.to_string(), None)
         };
 
-        match action.as_str() {
-            "salir"     => Ok(Action::Exit),
-            "union"     => Program::parse_action(input, Action::AddUnion),
-            "struct"    => Program::parse_action(input, Action::AddStruct),
-            "atomico"   => Program::parse_atomic(input),
-            "describir" => Program::parse_display(input),
-            _        => Err( ProgramError::InvalidAction(action) )
+        let attributes = input
+                            .map(|token| Attribute::parse(token).ok_or_else(|| ProgramError::InvalidArgument(token.to_string())))
+                            .collect::<Result<Vec<Attribute>, ProgramError>>()?;
+        match attributes.is_empty() {
+            true  => Err(ProgramError::NotEnoughArgs),
+            false => Ok(Action::Attribute(name, member, attributes))
         }
     }
 
-    /// Parse properties from token iterator and create a new action with the given constructor
-    fn parse_action<'a, I>(input:  I, act : fn (Name, TypeList) -> Action) -> Result<Action, ProgramError> 
-        where 
+    /// Parse the arguments of `guardar` and `cargar`: `<archivo> [--formato tm|bin]`
+    /// ## Return
+    /// The file and the format, if given
+    fn parse_session_args<'a, I>(mut input: I) -> Result<(String, Option<SessionFormat>), ProgramError>
+        where
             I: Iterator<Item=&'a str>
     {
-        let mut input = input; // honestly i don't get why this is necessary for this function to type check but ok
+        match (input.next(), input.next(), input.next(), input.next()) {
+            (None, _, _, _) | (Some(_), Some("--formato"), None, _) => Err(ProgramError::NotEnoughArgs),
+            (Some(file), None, _, _) => Ok((file.to_string(), None)),
+            (Some(file), Some("--formato"), Some(format), None) => match SessionFormat::parse(format) {
+                Some(format) => Ok((file.to_string(), Some(format))),
+                None => Err(ProgramError::InvalidArgument(format.to_string()))
+            },
+            (Some(_), Some(s), _, _) if s.starts_with("--") => Err(ProgramError::InvalidArgument(s.to_string())),
+            _ => Err(ProgramError::TooManyArgs)
+        }
+    }
 
-        // parse name, if not found, then not enough arguments error is raised
-        let name = match &mut input.next() {
-            None    => return Err( ProgramError::NotEnoughArgs ),
-            Some(e) => e.to_string()
+    /// Parse an export action: `exportar <formato>`, `exportar sql [--motor postgres|mysql|sqlite]`,
+    /// `exportar arrow [--filas N]` or `exportar imagen <struct> <archivo.png> [--estrategia e]`
+    fn parse_export<'a, I>(mut input: I) -> Result<Action, ProgramError>
+        where
+            I: Iterator<Item=&'a str>
+    {
+        let format = input.next().ok_or(ProgramError::NotEnoughArgs)?;
+        if format == "imagen" {
+            return match (input.next(), input.next(), input.next(), input.next(), input.next()) {
+                (Some(name), Some(file), None, _, _) => Ok(Action::Image(name.to_string(), file.to_string(), Strategy::C)),
+                (Some(name), Some(file), Some("--estrategia"), Some(strategy), None) => match Strategy::parse(strategy) {
+                    Some(strategy) => Ok(Action::Image(name.to_string(), file.to_string(), strategy)),
+                    None => Err(ProgramError::InvalidArgument(strategy.to_string()))
+                },
+                (_, _, _, _, Some(_)) => Err(ProgramError::TooManyArgs),
+                (Some(_), Some(_), Some(option), None, _) if option != "--estrategia" => Err(ProgramError::InvalidArgument(option.to_string())),
+                _ => Err(ProgramError::NotEnoughArgs)
+            }
+        }
+        let format = ExportFormat::parse(format).ok_or_else(|| ProgramError::InvalidArgument(format.to_string()))?;
+
+        match (format, input.next(), input.next(), input.next()) {
+            (_, None, _, _) => Ok(Action::Export(format)),
+            (ExportFormat::Sql(_), Some("--motor"), Some(engine), None) => match codegen::Engine::parse(engine) {
+                Some(engine) => Ok(Action::Export(ExportFormat::Sql(engine))),
+                None => Err(ProgramError::InvalidArgument(engine.to_string()))
+            },
+            (ExportFormat::Arrow(_), Some("--filas"), Some(rows), None) => match rows.parse::<usize>() {
+                Ok(rows) if rows > 0 => Ok(Action::Export(ExportFormat::Arrow(rows))),
+                _ => Err(ProgramError::InvalidArgument(rows.to_string()))
+            },
+            (ExportFormat::Sql(_), Some("--motor"), None, _) | (ExportFormat::Arrow(_), Some("--filas"), None, _) => Err(ProgramError::NotEnoughArgs),
+            (_, Some(s), _, _) if s.starts_with("--") => Err(ProgramError::InvalidArgument(s.to_string())),
+            _ => Err(ProgramError::TooManyArgs)
+        }
+    }
+
+    /// Parse an import action: `importar clang-ast <archivo.json>`, `importar dwarf <binario>` or
+    /// `importar proto <archivo.proto> [--escalar proto=tipo]...`. The file is read and its types
+    /// are parsed here
+    fn parse_import<'a, I>(input: I) -> Result<Action, ProgramError>
+        where
+            I: Iterator<Item=&'a str>
+    {
+        let mut input = input;
+        let (format, file) = match (input.next(), input.next()) {
+            (Some("jsonl"), Some(file)) => return match input.next() {
+                None => Ok(Action::ImportLines(file.to_string())),
+                Some(_) => Err(ProgramError::TooManyArgs)
+            },
+            (Some(format), Some(file)) if ["clang-ast", "dwarf", "proto"].contains(&format) => (format, file),
+            (Some(format), Some(_)) => return Err(ProgramError::InvalidArgument(format.to_string())),
+            _ => return Err(ProgramError::NotEnoughArgs)
         };
 
-        // Create a typelist from the rest of our tokens
-        let types : TypeList= input.map(|s| s.to_string()).collect();
+        let mut mapping = vec![];
+        while let Some(arg) = input.next() {
+            match (format, arg) {
+                ("proto", "--escalar") => {
+                    let pair = input.next().ok_or(ProgramError::NotEnoughArgs)?;
+                    match pair.split_once('=') {
+                        Some((scalar, typename)) if !scalar.is_empty() && !typename.is_empty() => mapping.push((scalar.to_string(), typename.to_string())),
+                        _ => return Err(ProgramError::InvalidArgument(pair.to_string()))
+                    }
+                },
+                (_, s) if s.starts_with("--") => return Err(ProgramError::InvalidArgument(s.to_string())),
+                _ => return Err(ProgramError::TooManyArgs)
+            }
+        }
 
-        Ok(act(name, types))
+        let bytes = std::fs::read(file).map_err(|e| ProgramError::IncludeRead(file.to_string(), e.to_string()))?;
+        let imported = match format {
+            "dwarf" => import::dwarf(&bytes),
+            "proto" => import::proto(&String::from_utf8_lossy(&bytes), &mapping),
+            _ => import::clang_ast_declared(&String::from_utf8_lossy(&bytes))
+        };
+
+        imported
+            .map(|imported| Action::Import(file.to_string(), imported))
+            .map_err(|e| ProgramError::Import(file.to_string(), e.display()))
     }
 
-    /// Parse atomic type
-    fn parse_atomic<'a, I>(input: I) -> Result<Action, ProgramError> 
+    /// Parse a decode action: `decodificar <tipo> <hex> [--estrategia E] [--orden little|big]`
+    fn parse_decode<'a, I>(input: I) -> Result<Action, ProgramError>
         where
-            I: Iterator<Item = &'a str>
+            I: Iterator<Item=&'a str>
     {
+        let mut name = None;
+        let mut bytes = None;
+        let mut strategy = Strategy::C;
+        let mut endian = Endian::Little;
         let mut input = input;
 
-        // Try to parse name
-        let name = match input.next() {
-            Some(s) => s,
-            None    => return Err( ProgramError::NotEnoughArgs )
-        };
+        while let Some(arg) = input.next() {
+            match arg {
+                "--estrategia" | "--orden" => {
+                    let value = input.next().ok_or(ProgramError::NotEnoughArgs)?;
+                    let invalid = || ProgramError::InvalidArgument(value.to_string());
+                    match arg {
+                        "--estrategia" => strategy = Strategy::parse(value).ok_or_else(invalid)?,
+                        _ => endian = Endian::parse(value).ok_or_else(invalid)?
+                    }
+                },
+                s if s.starts_with("--") => return Err(ProgramError::InvalidArgument(s.to_string())),
+                s if name.is_none() => name = Some(s.to_string()),
+                s if bytes.is_none() => bytes = Some(parse_hex(s).ok_or_else(|| ProgramError::InvalidArgument(s.to_string()))?),
+                _ => return Err(ProgramError::TooManyArgs)
+            }
+        }
 
-        // Try to get first argument
-        let repr = match input.next() {
-            Some(s) => s,
-            None    => return Err(ProgramError::NotEnoughArgs)
-        };
+        match (name, bytes) {
+            (Some(name), Some(bytes)) => Ok(Action::Decode(name, bytes, strategy, endian)),
+            _ => Err(ProgramError::NotEnoughArgs)
+        }
+    }
 
-        // try to get second argument
-        let align = match input.next() {
-            Some(s) => s,
-            None    => return Err(ProgramError::NotEnoughArgs)
-        };
+    /// Parse a test vector action: `generar <tipo> [semilla] [--estrategia E]`
+    fn parse_generate<'a, I>(input: I) -> Result<Action, ProgramError>
+        where
+            I: Iterator<Item=&'a str>
+    {
+        let mut name = None;
+        let mut seed = None;
+        let mut strategy = Strategy::C;
+        let mut input = input;
 
-        // check if too many arguments
-        if let Some(_) = input.next() {
-            return Err(ProgramError::TooManyArgs)
-        };
+        while let Some(arg) = input.next() {
+            match arg {
+                "--estrategia" => {
+                    let value = input.next().ok_or(ProgramError::NotEnoughArgs)?;
+                    strategy = Strategy::parse(value).ok_or_else(|| ProgramError::InvalidArgument(value.to_string()))?;
+                },
+                s if s.starts_with("--") => return Err(ProgramError::InvalidArgument(s.to_string())),
+                s if name.is_none() => name = Some(s.to_string()),
+                s if seed.is_none() => seed = Some(s.parse().map_err(|_| ProgramError::InvalidArgument(s.to_string()))?),
+                _ => return Err(ProgramError::TooManyArgs)
+            }
+        }
 
-        // Try to parse arguments
-        let repr = match repr.parse::<usize>() {
-            Ok(n)  => n,
-            Err(_) => return Err(ProgramError::InvalidArgument(repr.to_string()))
-        };
+        match name {
+            Some(name) => Ok(Action::Generate(name, seed, strategy)),
+            None => Err(ProgramError::NotEnoughArgs)
+        }
+    }
+
+    /// Parse a simulated annealing action: `recocer <struct> [--semilla N] [--pasos N] [--reinicios N]`
+    fn parse_anneal<'a, I>(input: I) -> Result<Action, ProgramError>
+        where
+            I: Iterator<Item=&'a str>
+    {
+        let mut name = None;
+        let mut options = AnnealOptions::default();
+        let mut input = input;
+
+        while let Some(arg) = input.next() {
+            let mut number = || -> Result<u64, ProgramError> {
+                let value = input.next().ok_or(ProgramError::NotEnoughArgs)?;
+                value.parse().map_err(|_| ProgramError::InvalidArgument(value.to_string()))
+            };
+            match arg {
+                "--semilla"   => options.seed = number()?,
+                "--pasos"     => options.steps = number()? as usize,
+                "--reinicios" => options.restarts = number()? as usize,
+                s if s.starts_with("--") => return Err(ProgramError::InvalidArgument(s.to_string())),
+                s if name.is_none() => name = Some(s.to_string()),
+                _ => return Err(ProgramError::TooManyArgs)
+            }
+        }
+
+        match name {
+            Some(name) => Ok(Action::Anneal(name, options)),
+            None => Err(ProgramError::NotEnoughArgs)
+        }
+    }
+
+    /// Parse a memory plan action: `planificar <bytes> <tipo:cantidad>...`
+    fn parse_plan<'a, I>(input: I) -> Result<Action, ProgramError>
+        where
+            I: Iterator<Item=&'a str>
+    {
+        let mut input = input;
+        let budget = input.next().ok_or(ProgramError::NotEnoughArgs)?;
+        let budget = budget.parse().map_err(|_| ProgramError::InvalidArgument(budget.to_string()))?;
+
+        let arrays = input
+                        .map(|arg| match arg.rsplit_once(':') {
+                            Some((name, count)) if !name.is_empty() => match count.parse() {
+                                Ok(count) => Ok((name.to_string(), count)),
+                                Err(_) => Err(ProgramError::InvalidArgument(arg.to_string()))
+                            },
+                            _ => Err(ProgramError::InvalidArgument(arg.to_string()))
+                        })
+                        .collect::<Result<Vec<_>, ProgramError>>()?;
+
+        match arrays.is_empty() {
+            true  => Err(ProgramError::NotEnoughArgs),
+            false => Ok(Action::Plan(budget, arrays))
+        }
+    }
+
+    /// Parse a flag packing action: `banderas <struct> [--aplicar <nuevo>]`
+    fn parse_flags<'a, I>(input: I) -> Result<Action, ProgramError>
+        where
+            I: Iterator<Item=&'a str>
+    {
+        let mut name = None;
+        let mut packed = None;
+        let mut input = input;
+
+        while let Some(arg) = input.next() {
+            match arg {
+                "--aplicar" => {
+                    let value = input.next().ok_or(ProgramError::NotEnoughArgs)?;
+                    packed = Some(value.to_string());
+                },
+                s if s.starts_with("--") => return Err(ProgramError::InvalidArgument(s.to_string())),
+                s if name.is_none() => name = Some(s.to_string()),
+                _ => return Err(ProgramError::TooManyArgs)
+            }
+        }
+
+        match name {
+            Some(name) => Ok(Action::Flags(name, packed)),
+            None => Err(ProgramError::NotEnoughArgs)
+        }
+    }
 
-        let align = match align.parse::<usize>() {
-            Ok(n)  => n,
-            Err(_) => return Err(ProgramError::InvalidArgument(repr.to_string()))
+    /// Parse a variable definition: `nombre = expresión`
+    fn parse_let(input: &str) -> Result<Action, ProgramError> {
+        let (name, value) = match input.split_once('=') {
+            Some((n, v)) => (n.trim(), v.trim()),
+            None => return Err(ProgramError::NotEnoughArgs)
         };
 
-        // return our new atomic type
-        Ok(Action::AddAtomic(name.to_string(), repr, align))
+        if !Field::valid_name(name) {
+            return Err(ProgramError::InvalidArgument(name.to_string()))
+        }
+        if value.is_empty() {
+            return Err(ProgramError::NotEnoughArgs)
+        }
+
+        let value = Expr::parse(value).map_err(ProgramError::Expression)?;
+        Ok(Action::Let(name.to_string(), value))
     }
 
     /// Parse a display action
@@ -214,24 +4348,88 @@ impl Program {
     {
         let mut input = input;
 
-        // Parse name
+        // Parse names and options
+        let mut names = vec![];
+        let mut options = DisplayOptions::default();
+        while let Some(arg) = input.next() {
+            match arg {
+                "--stats" => options.stats = true,
+                "--json"  => options.json = true,
+                "--detalle" => options.detail = true,
+                "--progresivo" => options.progressive = true,
+                "--formato" => match input.next() {
+                    Some("pahole") => options.pahole = true,
+                    Some("json")   => options.json = true,
+                    Some("texto")  => {},
+                    Some(format)   => return Err(ProgramError::InvalidArgument(format.to_string())),
+                    None => return Err(ProgramError::NotEnoughArgs)
+                },
+                "--estrategia" => match input.next() {
+                    Some(strategy) => options.strategy = Some(Strategy::parse(strategy).ok_or_else(|| ProgramError::InvalidArgument(strategy.to_string()))?),
+                    None => return Err(ProgramError::NotEnoughArgs)
+                },
+                s if s.starts_with("--") => return Err(ProgramError::InvalidArgument(s.to_string())),
+                name => names.push(name.to_string())
+            }
+        }
+
+        // describir todo describes every type
+        match names.as_slice() {
+            [] => Err(ProgramError::NotEnoughArgs),
+            [name] if name == "todo" => Ok(Action::DisplayAll(options)),
+            [name] if !Program::is_pattern(name) => Ok(Action::Display(name.clone(), options)),
+            _ => Ok(Action::DisplayMatching(names, options))
+        }
+    }
+
+    /// Parse a list action, it takes no arguments
+    fn parse_list<'a, I>(input: I) -> Result<Action, ProgramError> 
+        where 
+            I: Iterator<Item = &'a str>
+    {
+        let mut input = input;
+        if input.next().is_some() {
+            return Err(ProgramError::TooManyArgs)
+        }
+
+        Ok(Action::List)
+    }
+
+    /// Parse an offsets action, it takes just a type name
+    fn parse_offsets<'a, I>(input: I) -> Result<Action, ProgramError> 
+        where 
+            I: Iterator<Item = &'a str>
+    {
+        let mut input = input;
         let name = match input.next() {
             None    => return Err(ProgramError::NotEnoughArgs),
-            Some(s) => s
+            Some(s) => s.to_string()
         };
 
-        // Check if too many arguments
-        if let Some(_) = input.next() {
+        if input.next().is_some() {
             return Err(ProgramError::TooManyArgs)
-        };
+        }
 
-        Ok(Action::Display(name.to_string()))
+        Ok(Action::Offsets(name))
     }
 }
 
+/// Tokens for a struct or union definition, which can also be written with braces
+/// and commas: `struct par { a:int, b:int }`
+fn compound_tokens<'a, I>(input: I) -> impl Iterator<Item = &'a str>
+    where
+        I: Iterator<Item = &'a str>
+{
+    input
+        .flat_map(|token| token.split(','))
+        .flat_map(|token| token.split(['{', '}']))
+        .filter(|token| !token.is_empty())
+}
+
 impl ProgramError {
 
     /// Get human readable description for this error
+    #[allow(clippy::useless_format)]
     pub fn display(&self) -> String {
         match self {
             ProgramError::InvalidAction(s) => {
@@ -245,7 +4443,101 @@ impl ProgramError {
             },
             ProgramError::TooManyArgs => {
                 format!("Demasiados argumentos")
+            },
+            ProgramError::DuplicateCommand(s) => {
+                format!("Ya existe un comando llamado '{}'", s)
+            },
+            ProgramError::MacroArguments(s, expected, given) => {
+                format!("La macro '{}' recibe {} argumentos, pero se dieron {}", s, expected, given)
+            },
+            ProgramError::MacroRecursion(s) => {
+                format!("Demasiadas expansiones anidadas al expandir la macro '{}'", s)
+            },
+            ProgramError::IncludeCycle(files) => {
+                format!("Inclusión cíclica: {}", files.join(" -> "))
+            },
+            ProgramError::IncludeRead(file, reason) => {
+                format!("No se pudo leer '{}': {}", file, reason)
+            },
+            ProgramError::Import(file, reason) => {
+                format!("No se pudo importar '{}': {}", file, reason)
+            },
+            ProgramError::SessionWrite(file, reason) => {
+                format!("No se pudo guardar la sesión en '{}': {}", file, reason)
+            },
+            ProgramError::OutputWrite(file, reason) => {
+                format!("No se pudo escribir la salida en '{}': {}", file, reason)
+            },
+            ProgramError::SessionRead(file, reason) => {
+                format!("No se pudo cargar la sesión de '{}': {}", file, reason)
+            },
+            ProgramError::UnknownAbi(s) => {
+                let abis : Vec<&str> = ABIS.iter().map(|a| a.name).collect();
+                format!("'{}' no es una ABI conocida, las ABIs son: {}", s, abis.join(", "))
+            },
+            ProgramError::SessionExists(s) => {
+                format!("Ya existe una sesión llamada '{}'", s)
+            },
+            ProgramError::UnknownSession(s) => {
+                format!("No existe una sesión llamada '{}'", s)
+            },
+            ProgramError::ReadOnly(s) => {
+                format!("La sesión '{}' es de solo lectura, sus tipos no pueden cambiar", s)
+            },
+            ProgramError::Sandboxed(s) => {
+                format!("Este programa no puede usar archivos: '{}'", s)
+            },
+            ProgramError::ReplayMismatch(files, divergence) => {
+                format!("La reproducción de {} no coincide, {}", files, divergence.join("\n"))
+            },
+            ProgramError::AssertionFailed(e, sides) => match sides {
+                Some((l, cmp, r)) => format!("No se cumple '{}': {} {} {} es falso", e, l, cmp.symbol(), r),
+                None => format!("No se cumple '{}': vale 0", e)
+            },
+            ProgramError::Strict(warnings) => {
+                format!("Hay avisos, que son errores en modo estricto:\n  * {}", warnings.join("\n  * "))
+            },
+            ProgramError::OverBudget(overruns) => {
+                format!("Hay presupuestos que no se cumplen:\n  * {}", overruns.join("\n  * "))
+            },
+            ProgramError::UnknownLintRule(s) => {
+                let rules : Vec<&str> = lint::RULES.iter().map(|r| r.name).collect();
+                format!("'{}' no es una regla de lint, las reglas son: {}", s, rules.join(", "))
+            },
+            ProgramError::HistoryEvent(s) => {
+                format!("'{}' no corresponde a ningún comando del historial", s)
+            },
+            ProgramError::Expression(e) => e.display()
+        }
+    }
+}
+
+impl CommandError {
+
+    /// Tells if this error comes from too many nested macro expansions
+    pub fn is_macro_recursion(&self) -> bool {
+        match self {
+            CommandError::Program(ProgramError::MacroRecursion(_)) => true,
+            CommandError::Macro { error, .. } => error.is_macro_recursion(),
+            _ => false
+        }
+    }
+
+    /// Get human readable description for this error
+    pub fn display(&self) -> String {
+        match self {
+            CommandError::Program(e) => format!("[ERROR]: {}", e.display()),
+            CommandError::Type(e)    => format!("[TYPE ERROR]: {}", e.display()),
+            CommandError::Macro { name, origin, command, error } => {
+                format!("{} (en '{}', de la macro '{}' definida en {})", error.display(), command, name, origin)
+            },
+            CommandError::Defined { name, origin, error } => {
+                format!("{} ('{}' se definió en {})", error.display(), name, origin)
+            },
+            CommandError::Include { file, errors } => {
+                let lines = errors.join("\n").replace('\n', "\n  ");
+                format!("[ERROR]: {} líneas fallaron al incluir '{}':\n  {}", errors.len(), file, lines)
             }
         }
     }
-}
\ No newline at end of file
+}
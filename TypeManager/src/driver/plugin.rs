@@ -0,0 +1,134 @@
+/*
+    Plugins: commands defined outside the driver, registered
+    when the program is created
+*/
+use std::collections::HashSet;
+
+use crate::type_system::*;
+use super::ProgramError;
+
+/// A command that can be added to the program without changing the driver. Plugins
+/// are `Send` so a program can be shared between threads
+pub trait CommandPlugin : Send {
+
+    /// Word that invokes this command, it can't be one of the built in commands
+    fn name(&self) -> &str;
+
+    /// Check the arguments given to this command
+    /// ## Params
+    /// * `args` - every word after the command name
+    /// ---
+    /// ## Return
+    /// Arguments to pass to `execute`, or the problem with them
+    fn parse(&self, args: &[&str]) -> Result<Vec<String>, ProgramError>;
+
+    /// Run this command
+    /// ## Params
+    /// * `args` - arguments as returned by `parse`
+    /// * `manager` - every type defined so far
+    /// ---
+    /// ## Return
+    /// Text to show, if any
+    fn execute(&mut self, args: &[String], manager: &mut TypeManager) -> Result<String, TypeError>;
+
+    /// Tells if this command can change stored types, so the session is saved after it runs
+    fn modifies(&self) -> bool {
+        false
+    }
+}
+
+/// Every plugin known to a program
+#[derive(Default)]
+pub struct PluginRegistry {
+    plugins: Vec<Box<dyn CommandPlugin>>
+}
+
+impl PluginRegistry {
+
+    /// Create a registry with the plugins shipped with the simulator
+    pub fn with_builtins() -> PluginRegistry {
+        let mut registry = PluginRegistry::default();
+        registry.plugins.push(Box::new(Dependencies));
+
+        registry
+    }
+
+    /// Add a plugin
+    /// ## Return
+    /// An error if there's a command with the same name already
+    pub fn register(&mut self, plugin: Box<dyn CommandPlugin>) -> Result<(), ProgramError> {
+        let name = plugin.name().to_string();
+        let builtin = !matches!(super::Program::parse(&name), Err(ProgramError::InvalidAction(_)));
+
+        if builtin || self.get(&name).is_some() || name.contains(char::is_whitespace) {
+            return Err(ProgramError::DuplicateCommand(name))
+        }
+
+        self.plugins.push(plugin);
+        Ok(())
+    }
+
+    /// Find a plugin by its command name
+    pub fn get(&self, name: &str) -> Option<&dyn CommandPlugin> {
+        self.plugins.iter().find(|p| p.name() == name).map(|p| p.as_ref())
+    }
+
+    /// Find a plugin by its command name, to run it
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Box<dyn CommandPlugin>> {
+        self.plugins.iter_mut().find(|p| p.name() == name)
+    }
+
+    /// Name of every registered command
+    pub fn names(&self) -> Vec<&str> {
+        self.plugins.iter().map(|p| p.name()).collect()
+    }
+}
+
+/// `dependencias <tipo>`: types used by a type, directly or not, and types using it
+struct Dependencies;
+
+impl CommandPlugin for Dependencies {
+    fn name(&self) -> &str {
+        "dependencias"
+    }
+
+    fn parse(&self, args: &[&str]) -> Result<Vec<String>, ProgramError> {
+        match args {
+            []     => Err(ProgramError::NotEnoughArgs),
+            [name] => Ok(vec![name.to_string()]),
+            _      => Err(ProgramError::TooManyArgs)
+        }
+    }
+
+    fn execute(&mut self, args: &[String], manager: &mut TypeManager) -> Result<String, TypeError> {
+        let name = match manager.resolve(&args[0]) {
+            Some(name) => name,
+            None => return Err(TypeError::TypeDoesNotExist(args[0].clone()))
+        };
+
+        // every type reachable from this one, in definition order
+        let mut used = HashSet::new();
+        let mut pending = vec![name];
+        while let Some(next) = pending.pop() {
+            if let Some(t) = manager.get(next) {
+                for d in t.dependencies() {
+                    if used.insert(d.clone()) {
+                        pending.push(d);
+                    }
+                }
+            }
+        }
+
+        let order = manager.definition_order();
+        let uses : TypeList = order.iter().filter(|n| used.contains(*n)).cloned().collect();
+        let used_by : TypeList = order
+                                    .iter()
+                                    .filter(|n| matches!(manager.get(n), Some(t) if t.dependencies().contains(name)))
+                                    .cloned()
+                                    .collect();
+
+        let show = |list : &TypeList| if list.is_empty() { String::from("(ninguno)") } else { list.join(", ") };
+
+        Ok(format!("Usa: {}\nUsado por: {}", show(&uses), show(&used_by)))
+    }
+}
@@ -0,0 +1,76 @@
+/*
+    JSON lines protocol: editors and build tools drive a session as a subprocess,
+    writing a request per line to its stdin and reading a response per line from
+    its stdout, as with `--stdio-json`. A request is an object such as
+    `{"id": 7, "command": "describir punto", "allow": ["padding"]}`, only
+    `"command"` is required and `"id"` is given back as is. A response is
+    `{"id": 7, "ok": true, "output": ["..."], "error": null}`, with the lines
+    the command wrote and, when it failed, an object telling the kind of error
+    and its message. Fields may be added in later versions, never removed
+*/
+use crate::json::Value;
+
+use super::CommandError;
+
+/// Kind of error for requests that aren't a valid JSON object with a command
+pub const REQUEST_ERROR : &str = "solicitud";
+
+/// Response to a request
+/// ## Params
+/// * `id` - id of the request, null if it had none or couldn't be read
+/// * `output` - everything the command wrote
+/// * `result` - how the command ended
+pub fn response(id : Value, output : &str, result : Result<(), &CommandError>) -> Value {
+    let lines = output.lines().map(Value::str).collect();
+    let error = match result {
+        Ok(()) => Value::Null,
+        Err(e) => error(e)
+    };
+
+    Value::object(vec![
+        ("id", id),
+        ("ok", Value::Bool(result.is_ok())),
+        ("output", Value::Array(lines)),
+        ("error", error)
+    ])
+}
+
+/// Response to a line that couldn't be read as a request
+/// ## Params
+/// * `id` - id of the request if it could be found, null otherwise
+/// * `message` - what's wrong with the line
+pub fn invalid(id : Value, message : &str) -> Value {
+    let error = Value::object(vec![("kind", Value::str(REQUEST_ERROR)), ("message", Value::str(message))]);
+    Value::object(vec![
+        ("id", id),
+        ("ok", Value::Bool(false)),
+        ("output", Value::Array(vec![])),
+        ("error", error)
+    ])
+}
+
+/// Error of a failed command: its kind, its message, and for errors on a type
+/// defined in a file, the type and where it was defined
+fn error(e : &CommandError) -> Value {
+    let mut pairs = vec![("kind", Value::str(kind(e))), ("message", Value::str(&e.display()))];
+    if let CommandError::Defined { name, origin, .. } = e {
+        pairs.push(("type", Value::str(name)));
+        pairs.push(("origin", Value::str(origin)));
+    }
+    if let CommandError::Include { errors, .. } = e {
+        pairs.push(("errors", Value::Array(errors.iter().map(|l| Value::str(l)).collect())));
+    }
+
+    Value::object(pairs)
+}
+
+/// Name of the kind of a command error: `comando` when the command couldn't be understood
+/// or run, `tipo` when the type system rejected it, `script` when lines of a file failed
+fn kind(e : &CommandError) -> &'static str {
+    match e {
+        CommandError::Program(_) => "comando",
+        CommandError::Type(_)    => "tipo",
+        CommandError::Macro { error, .. } | CommandError::Defined { error, .. } => kind(error),
+        CommandError::Include { .. } => "script"
+    }
+}
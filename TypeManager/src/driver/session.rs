@@ -0,0 +1,397 @@
+/*
+    Binary session files: every type with the lint rules allowed for it,
+    where it was defined, the attributes added to it and its budget, in a compact format that loads much faster than running a script when a session
+    has thousands of imported types. Files start with a magic word and the version
+    of the format, so newer versions can still read older files
+*/
+use crate::type_system::*;
+
+/// First bytes of every binary session file
+pub const MAGIC : &[u8; 4] = b"TMSB";
+
+/// Format version written. When the format changes this goes up and `decode`
+/// keeps reading every older version, migrating what changed. Version 2 added origins,
+/// version 3 attributes of types other than structs, version 4 budgets
+pub const VERSION : u16 = 4;
+
+/// Ways to save a session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionFormat {
+    Script,     // commands defining every type, as `incluir` runs them
+    Binary      // compact binary encoding
+}
+
+impl SessionFormat {
+
+    /// Find a format by the name used in commands: `tm` or `bin`
+    pub fn parse(name : &str) -> Option<SessionFormat> {
+        match name {
+            "tm"  => Some(SessionFormat::Script),
+            "bin" => Some(SessionFormat::Binary),
+            _ => None
+        }
+    }
+
+    /// Format of a saved session, by its first bytes
+    pub fn detect(bytes : &[u8]) -> SessionFormat {
+        match bytes.starts_with(MAGIC) {
+            true  => SessionFormat::Binary,
+            false => SessionFormat::Script
+        }
+    }
+}
+
+/// A type read from a session file
+#[derive(Debug, Clone)]
+pub struct SavedType {
+    pub name:    Name,
+    pub value:   Type,
+    pub allowed: Vec<String>,   // lint rules disabled for it
+    pub origin:  Option<String>, // where it was defined, none if at the prompt or in a version 1 file
+    pub attributes: Vec<Attribute>, // added to a type other than a struct, structs keep theirs
+    pub budget:  Option<Budget>     // most bytes it may take, if it has a budget
+}
+
+/// A type to save: its name, the type, the lint rules allowed for it, its origin, the
+/// attributes added to it if it's not a struct and its budget
+pub type Saving<'a> = (&'a Name, &'a Type, &'a [String], Option<&'a str>, &'a [Attribute], Option<Budget>);
+
+/// Encode a session
+/// ## Params
+/// * `types` - every type to save, each after the types it uses
+/// ---
+/// ## Return
+/// The contents of a binary session file
+pub fn encode(types : &[Saving]) -> Vec<u8> {
+    let mut w = Writer { bytes: MAGIC.to_vec() };
+    w.bytes.extend(VERSION.to_le_bytes());
+    w.number(types.len());
+
+    for (name, t, allowed, origin, attributes, budget) in types {
+        w.text(name);
+        w.names(allowed);
+        w.optional(*origin);
+        w.attributes(attributes);
+        w.budget(*budget);
+        match t {
+            Type::Atomic(a) => {
+                w.byte(0);
+                w.number(a.representation);
+                w.number(a.alignment);
+                w.byte(match a.kind {
+                    None => 0,
+                    Some(ValueKind::Signed)   => 1,
+                    Some(ValueKind::Unsigned) => 2,
+                    Some(ValueKind::Float)    => 3,
+                    Some(ValueKind::Char)     => 4,
+                    Some(ValueKind::Bool)     => 5
+                });
+            },
+            Type::Struct(s) => {
+                w.byte(1);
+                w.names(&s.members);
+                for f in &s.fields {
+                    w.text(&f.name);
+                    w.attributes(&f.attributes);
+                }
+                w.attributes(&s.attributes);
+            },
+            Type::Union(u) => {
+                w.byte(2);
+                w.names(&u.variants);
+            },
+            Type::Enum(e) => {
+                w.byte(3);
+                w.names(&e.variants);
+                w.byte(match e.repr {
+                    EnumRepr::Int     => 0,
+                    EnumRepr::Minimal => 1
+                });
+            },
+            Type::Pointer(p) => {
+                w.byte(4);
+                w.text(&p.target);
+                w.byte(match p.kind {
+                    PointerKind::Thin  => 0,
+                    PointerKind::Slice => 1,
+                    PointerKind::Trait => 2
+                });
+            },
+            Type::Container(c) => {
+                w.byte(5);
+                w.byte(match c.kind {
+                    ContainerKind::Vec    => 0,
+                    ContainerKind::String => 1,
+                    ContainerKind::Box    => 2
+                });
+                w.optional(c.element.as_deref());
+                w.number(c.capacity);
+                w.number(c.width);
+            },
+            Type::Text(t) => {
+                w.byte(6);
+                match t.model {
+                    TextModel::Raw(n) => { w.byte(0); w.number(n) },
+                    TextModel::PtrLen => w.byte(1),
+                    TextModel::Sso(n) => { w.byte(2); w.number(n) }
+                }
+            }
+        }
+    }
+
+    w.bytes
+}
+
+/// Decode a session
+/// ## Return
+/// Every type in the order they were saved, or what's wrong with the file
+pub fn decode(bytes : &[u8]) -> Result<Vec<SavedType>, String> {
+    if !bytes.starts_with(MAGIC) {
+        return Err(String::from("no es una sesión binaria"))
+    }
+    let version = match bytes.get(4..6) {
+        Some(v) => u16::from_le_bytes([v[0], v[1]]),
+        None    => return Err(String::from("el archivo está truncado"))
+    };
+    if version == 0 || version > VERSION {
+        return Err(format!("la sesión es de la versión {} del formato y solo se leen hasta la {}", version, VERSION))
+    }
+
+    let mut r = Reader { bytes, next: 6 };
+    let count = r.count()?;
+    let mut types = Vec::with_capacity(count);
+    for _ in 0..count {
+        let name = r.text()?;
+        let allowed = r.names()?;
+        let origin = match version {
+            1 => None,
+            _ => r.optional()?
+        };
+        let attributes = match version {
+            1 | 2 => vec![],
+            _ => r.attributes()?
+        };
+        let budget = match version {
+            1..=3 => None,
+            _ => r.budget()?
+        };
+        let value = match r.byte()? {
+            0 => {
+                let representation = r.number()?;
+                let alignment = r.number()?;
+                let kind = match r.byte()? {
+                    0 => None,
+                    1 => Some(ValueKind::Signed),
+                    2 => Some(ValueKind::Unsigned),
+                    3 => Some(ValueKind::Float),
+                    4 => Some(ValueKind::Char),
+                    5 => Some(ValueKind::Bool),
+                    k => return Err(r.invalid("tipo de valor", k))
+                };
+                Type::Atomic(Atomic::with_kind(representation, alignment, kind))
+            },
+            1 => {
+                let members = r.names()?;
+                let mut fields = Vec::with_capacity(members.len());
+                for _ in 0..members.len() {
+                    let mut field = Field::new(r.text()?);
+                    field.attributes = r.attributes()?;
+                    fields.push(field);
+                }
+                Type::Struct(Struct::with_fields(members, fields).with_attributes(r.attributes()?))
+            },
+            2 => Type::Union(Union::new(r.names()?)),
+            3 => {
+                let variants = r.names()?;
+                let repr = match r.byte()? {
+                    0 => EnumRepr::Int,
+                    1 => EnumRepr::Minimal,
+                    k => return Err(r.invalid("representación de enum", k))
+                };
+                Type::Enum(Enum::new(variants, repr))
+            },
+            4 => {
+                let target = r.text()?;
+                let kind = match r.byte()? {
+                    0 => PointerKind::Thin,
+                    1 => PointerKind::Slice,
+                    2 => PointerKind::Trait,
+                    k => return Err(r.invalid("tipo de puntero", k))
+                };
+                Type::Pointer(Pointer::new(target, kind))
+            },
+            5 => {
+                let kind = match r.byte()? {
+                    0 => ContainerKind::Vec,
+                    1 => ContainerKind::String,
+                    2 => ContainerKind::Box,
+                    k => return Err(r.invalid("tipo de contenedor", k))
+                };
+                let element = r.optional()?;
+                let capacity = r.number()?;
+                let width = r.number()?;
+                Type::Container(Container::new(kind, element, capacity, width))
+            },
+            6 => {
+                let model = match r.byte()? {
+                    0 => TextModel::Raw(r.number()?),
+                    1 => TextModel::PtrLen,
+                    2 => TextModel::Sso(r.number()?),
+                    k => return Err(r.invalid("representación de cadena", k))
+                };
+                Type::Text(Text::new(model))
+            },
+            k => return Err(r.invalid("clase de tipo", k))
+        };
+        types.push(SavedType { name, value, allowed, origin, attributes, budget });
+    }
+
+    match r.next == bytes.len() {
+        true  => Ok(types),
+        false => Err(format!("sobran {} bytes al final", bytes.len() - r.next))
+    }
+}
+
+/// Appends values to a binary session
+struct Writer {
+    bytes: Vec<u8>
+}
+
+impl Writer {
+
+    fn byte(&mut self, b : u8) {
+        self.bytes.push(b)
+    }
+
+    /// Unsigned LEB128, 7 bits per byte
+    fn number(&mut self, n : usize) {
+        let mut n = n;
+        while n >= 0x80 {
+            self.bytes.push((n & 0x7f) as u8 | 0x80);
+            n >>= 7;
+        }
+        self.bytes.push(n as u8)
+    }
+
+    fn text(&mut self, s : &str) {
+        self.number(s.len());
+        self.bytes.extend(s.as_bytes())
+    }
+
+    fn optional(&mut self, s : Option<&str>) {
+        match s {
+            None    => self.byte(0),
+            Some(s) => { self.byte(1); self.text(s) }
+        }
+    }
+
+    fn names(&mut self, names : &[String]) {
+        self.number(names.len());
+        for name in names {
+            self.text(name);
+        }
+    }
+
+    fn attributes(&mut self, attributes : &[Attribute]) {
+        self.number(attributes.len());
+        for a in attributes {
+            self.text(&a.key);
+            self.optional(a.value.as_deref());
+        }
+    }
+
+    fn budget(&mut self, budget : Option<Budget>) {
+        match budget {
+            None    => self.byte(0),
+            Some(b) => { self.byte(1); self.number(b.limit); self.text(b.strategy.id()) }
+        }
+    }
+}
+
+/// Reads values from a binary session, failing instead of reading past its end
+struct Reader<'a> {
+    bytes: &'a [u8],
+    next:  usize
+}
+
+impl Reader<'_> {
+
+    fn truncated(&self) -> String {
+        String::from("el archivo está truncado")
+    }
+
+    fn invalid(&self, what : &str, value : u8) -> String {
+        format!("{} inválido ({}) en el byte {}", what, value, self.next - 1)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        let b = *self.bytes.get(self.next).ok_or_else(|| self.truncated())?;
+        self.next += 1;
+        Ok(b)
+    }
+
+    fn number(&mut self) -> Result<usize, String> {
+        let mut n : usize = 0;
+        let mut shift = 0;
+        loop {
+            let b = self.byte()?;
+            if shift >= usize::BITS || ((b & 0x7f) as usize) << shift >> shift != (b & 0x7f) as usize {
+                return Err(format!("número demasiado grande en el byte {}", self.next - 1))
+            }
+            n |= ((b & 0x7f) as usize) << shift;
+            if b & 0x80 == 0 {
+                return Ok(n)
+            }
+            shift += 7;
+        }
+    }
+
+    /// Amount of items that follow, each takes at least a byte
+    fn count(&mut self) -> Result<usize, String> {
+        let n = self.number()?;
+        match n <= self.bytes.len() - self.next {
+            true  => Ok(n),
+            false => Err(self.truncated())
+        }
+    }
+
+    fn text(&mut self) -> Result<String, String> {
+        let len = self.count()?;
+        let bytes = &self.bytes[self.next..self.next + len];
+        self.next += len;
+        String::from_utf8(bytes.to_vec()).map_err(|_| format!("texto con UTF-8 inválido antes del byte {}", self.next))
+    }
+
+    fn optional(&mut self) -> Result<Option<String>, String> {
+        match self.byte()? {
+            0 => Ok(None),
+            1 => self.text().map(Some),
+            k => Err(self.invalid("marca de opcional", k))
+        }
+    }
+
+    fn names(&mut self) -> Result<Vec<String>, String> {
+        let count = self.count()?;
+        (0..count).map(|_| self.text()).collect()
+    }
+
+    fn attributes(&mut self) -> Result<Vec<Attribute>, String> {
+        let count = self.count()?;
+        (0..count)
+            .map(|_| Ok(Attribute { key: self.text()?, value: self.optional()? }))
+            .collect()
+    }
+
+    fn budget(&mut self) -> Result<Option<Budget>, String> {
+        match self.byte()? {
+            0 => Ok(None),
+            1 => {
+                let limit = self.number()?;
+                let id = self.text()?;
+                let strategy = Strategy::parse(&id).ok_or_else(|| format!("estrategia desconocida '{}' antes del byte {}", id, self.next))?;
+                Ok(Some(Budget { limit, strategy }))
+            },
+            k => Err(self.invalid("marca de presupuesto", k))
+        }
+    }
+}
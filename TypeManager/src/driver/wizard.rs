@@ -0,0 +1,184 @@
+/*
+    Step by step struct definition: asks for the struct name and then
+    for every field its name, type and attributes, validating each answer
+*/
+use crate::type_system::*;
+
+/// Answer that leaves the wizard at any step
+const CANCEL : &str = "cancelar";
+
+/// Answer that ends the field list, an empty answer does too
+const FINISH : &str = "fin";
+
+/// Answer listing every type when asked for a field type
+const LIST_TYPES : &str = "?";
+
+/// What the wizard is waiting for
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    StructName,
+    FieldName,
+    FieldType(Name),                // name of the field being defined
+    FieldAttributes(Name, Name)     // field name, field type
+}
+
+/// Outcome of feeding an answer to the wizard
+#[derive(Debug, PartialEq)]
+pub enum WizardResult {
+    Continue(Vec<String>),                  // messages to show before the next question
+    Done(Name, TypeList, Vec<Field>),       // struct name, members and fields, ready to be added
+    Cancelled
+}
+
+/// State for a struct being defined step by step
+#[derive(Debug)]
+pub struct Wizard {
+    step:    Step,
+    name:    Name,
+    members: TypeList,
+    fields:  Vec<Field>
+}
+
+impl Default for Wizard {
+    fn default() -> Self {
+        Wizard::new()
+    }
+}
+
+impl Wizard {
+
+    /// Start a new wizard, asking for the struct name
+    pub fn new() -> Wizard {
+        Wizard {
+            step: Step::StructName,
+            name: Name::new(),
+            members: vec![],
+            fields: vec![]
+        }
+    }
+
+    /// Question for the current step
+    pub fn question(&self) -> String {
+        match &self.step {
+            Step::StructName => String::from("Nombre del struct:"),
+            Step::FieldName  => format!(
+                "Campo #{} de '{}', nombre ('{}' o vacío para terminar):",
+                self.fields.len(),
+                self.name,
+                FINISH
+            ),
+            Step::FieldType(f) => format!("Tipo de '{}' ('{}' lista los tipos):", f, LIST_TYPES),
+            Step::FieldAttributes(f, _) => format!("Atributos de '{}' (ej. @caliente @grupo=red), vacío para ninguno:", f)
+        }
+    }
+
+    /// Feed an answer to the current question
+    /// ## Params
+    /// * `answer` - line written by the user
+    /// * `manager` - manager with every type defined so far, to validate against
+    /// ---
+    /// ## Return
+    /// Whether the wizard needs more answers, finished or was cancelled
+    pub fn feed(&mut self, answer: &str, manager: &TypeManager) -> WizardResult {
+        let answer = answer.trim();
+        if answer == CANCEL {
+            return WizardResult::Cancelled
+        }
+
+        let step = self.step.clone();
+        let messages = match step {
+            Step::StructName => self.struct_name(answer, manager),
+            Step::FieldName  => {
+                if answer.is_empty() || answer == FINISH {
+                    if self.members.is_empty() {
+                        vec![String::from("El struct necesita al menos un campo")]
+                    }
+                    else {
+                        return WizardResult::Done(self.name.clone(), self.members.clone(), self.fields.clone())
+                    }
+                }
+                else {
+                    self.field_name(answer)
+                }
+            },
+            Step::FieldType(field) => self.field_type(field, answer, manager),
+            Step::FieldAttributes(field, field_type) => self.field_attributes(field, field_type, answer)
+        };
+
+        WizardResult::Continue(messages)
+    }
+
+    /// Check the struct name is a new one
+    fn struct_name(&mut self, answer: &str, manager: &TypeManager) -> Vec<String> {
+        if answer.is_empty() || answer.contains(char::is_whitespace) {
+            return vec![String::from("El nombre no puede estar vacío ni tener espacios")]
+        }
+
+        if manager.get(&answer.to_string()).is_some() {
+            return vec![TypeError::TypeRedefinition.display()]
+        }
+
+        self.name = answer.to_string();
+        self.step = Step::FieldName;
+        vec![]
+    }
+
+    /// Check a field name is valid and not used yet
+    fn field_name(&mut self, answer: &str) -> Vec<String> {
+        if !Field::valid_name(answer) {
+            return vec![format!("'{}' no es un nombre de campo válido", answer)]
+        }
+
+        if self.fields.iter().any(|f| f.name == answer) {
+            return vec![TypeError::DuplicateField(answer.to_string()).display()]
+        }
+
+        self.step = Step::FieldType(answer.to_string());
+        vec![]
+    }
+
+    /// Check the type exists, completing unambiguous prefixes
+    fn field_type(&mut self, field: Name, answer: &str, manager: &TypeManager) -> Vec<String> {
+        let mut names : TypeList = manager.names().cloned().collect();
+        names.sort();
+
+        if answer == LIST_TYPES || answer.is_empty() {
+            return match names.is_empty() {
+                true  => vec![String::from("No hay tipos definidos")],
+                false => vec![format!("Tipos: {}", names.join(", "))]
+            }
+        }
+
+        if let Some(name) = manager.resolve(&answer.to_string()) {
+            self.step = Step::FieldAttributes(field, name.clone());
+            return vec![]
+        }
+
+        let candidates : TypeList = names.into_iter().filter(|n| n.starts_with(answer)).collect();
+        match candidates.len() {
+            0 => vec![TypeError::TypeDoesNotExist(answer.to_string()).display()],
+            1 => {
+                let message = format!("Completado a '{}'", candidates[0]);
+                self.step = Step::FieldAttributes(field, candidates[0].clone());
+                vec![message]
+            },
+            _ => vec![format!("'{}' es ambiguo: {}", answer, candidates.join(", "))]
+        }
+    }
+
+    /// Parse every attribute for a field, then add the field
+    fn field_attributes(&mut self, field: Name, field_type: Name, answer: &str) -> Vec<String> {
+        let mut attributes = vec![];
+        for token in answer.split_whitespace() {
+            match Attribute::parse(token) {
+                Some(a) => attributes.push(a),
+                None => return vec![format!("'{}' no es un atributo válido, se esperaba @clave o @clave=valor", token)]
+            }
+        }
+
+        self.members.push(field_type);
+        self.fields.push(Field { name: field, attributes });
+        self.step = Step::FieldName;
+        vec![]
+    }
+}
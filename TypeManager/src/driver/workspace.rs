@@ -0,0 +1,77 @@
+/*
+    Several independent sessions in the same program, each with its own types
+    and target machine, so the same definitions can be loaded for several ABIs
+    and compared. Only one is in use at a time, the rest are kept here
+*/
+use std::collections::HashMap;
+
+use crate::type_system::{Abi, Name, TypeManager};
+
+/// Session every program starts with
+pub const MAIN_SESSION : &str = "principal";
+
+/// What a `sesion` command asks for
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionCommand {
+    List,               // every session, with its target and amount of types
+    New(Name, Abi),     // create a session and start using it
+    Use(Name),          // start using another session
+    Lock,               // reject commands changing the types of the session in use
+    Unlock
+}
+
+/// What `fusionar` does with a type named like one of ours but defined otherwise
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeStrategy {
+    Skip,           // keep ours
+    Prefix(String), // add theirs with a prefix, and types using it refer to the new name
+    Replace         // put theirs instead if it has the same layout, keep ours otherwise
+}
+
+/// A session not in use
+#[derive(Debug)]
+pub struct Workspace {
+    pub manager: TypeManager,
+    pub allowed: HashMap<Name, Vec<String>>,    // lint rules disabled for every type
+    pub locked:  bool                           // whether its types can't change
+}
+
+impl SessionCommand {
+
+    /// Parse the arguments of a `sesion` command: nothing or `lista`, `nueva <nombre> [abi]`,
+    /// `usar <nombre>`, `bloquear` or `desbloquear`. A new session named after a target
+    /// uses it if none is given
+    /// ## Return
+    /// The command, or the argument that's wrong. Wrong targets are `Err(Some(abi))`,
+    /// a wrong amount of arguments is `Err(None)`
+    pub fn parse(args : &[&str]) -> Result<SessionCommand, Option<String>> {
+        match args {
+            [] | ["lista"] | ["list"] => Ok(SessionCommand::List),
+            ["nueva", name] | ["new", name] => Ok(SessionCommand::New(name.to_string(), Abi::parse(name).unwrap_or_default())),
+            ["nueva", name, abi] | ["new", name, abi] => match Abi::parse(abi) {
+                Some(abi) => Ok(SessionCommand::New(name.to_string(), abi)),
+                None      => Err(Some(abi.to_string()))
+            },
+            ["usar", name] | ["use", name] => Ok(SessionCommand::Use(name.to_string())),
+            ["bloquear"] | ["lock"] => Ok(SessionCommand::Lock),
+            ["desbloquear"] | ["unlock"] => Ok(SessionCommand::Unlock),
+            _ => Err(None)
+        }
+    }
+}
+
+impl MergeStrategy {
+
+    /// Parse the options of a `fusionar` command: nothing or `--omitir`, `--prefijo <prefijo>`
+    /// or `--reemplazar`
+    /// ## Return
+    /// The strategy, or none if the options are wrong
+    pub fn parse(args : &[&str]) -> Option<MergeStrategy> {
+        match args {
+            [] | ["--omitir"] | ["--skip"] => Some(MergeStrategy::Skip),
+            ["--prefijo", prefix] | ["--prefix", prefix] => Some(MergeStrategy::Prefix(prefix.to_string())),
+            ["--reemplazar"] | ["--replace"] => Some(MergeStrategy::Replace),
+            _ => None
+        }
+    }
+}
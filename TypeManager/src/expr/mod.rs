@@ -0,0 +1,591 @@
+/*
+    Integer expressions for command arguments and calculations: numbers,
+    properties of stored types (`int.size`), layout functions (`tamaño(s)`),
+    arithmetic, comparisons and parenthesis
+*/
+use std::convert::TryFrom;
+use std::collections::HashMap;
+
+use crate::type_system::*;
+
+/// A parsed expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(i64),
+    Name(Name),                         // a type, strategy or field name, depending on where it's used
+    Property(Name, String),             // type name, property name
+    Call(String, Vec<Expr>),            // function name, arguments
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    Compare(Cmp, Box<Expr>, Box<Expr>)  // 1 if true, 0 if false
+}
+
+/// Arithmetic operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem
+}
+
+/// Comparison operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cmp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge
+}
+
+/// Anything that can go wrong parsing or evaluating an expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprError {
+    Syntax(String),                     // description of the problem
+    DivisionByZero,
+    Overflow,
+    Negative(i64),                      // a negative value where only natural numbers make sense
+    UnknownType(Name),
+    NotAtomic(Name),
+    NotAStruct(Name),
+    UnknownProperty(String),
+    UnknownName(Name),
+    UnknownFunction(String),
+    UnknownField(Name, String),         // struct, field
+    UnknownStrategy(String),
+    Arguments(String, &'static str)     // function, how to call it
+}
+
+/// Where expressions get the value of references from
+pub trait Environment {
+
+    /// Value for a property of a type, as in `int.size`
+    fn property(&self, name : &str, property : &str) -> Result<i64, ExprError>;
+
+    /// Value for a bare name, as in `x + 1`
+    fn name(&self, name : &str) -> Result<i64, ExprError> {
+        Err(ExprError::UnknownName(name.to_string()))
+    }
+
+    /// Result of a function call, as in `tamaño(s)`. Arguments are given unevaluated,
+    /// since some of them are names
+    fn call(&self, function : &str, _args : &[Expr]) -> Result<i64, ExprError> {
+        Err(ExprError::UnknownFunction(function.to_string()))
+    }
+}
+
+/// Name for the result of the last calculation
+pub const LAST : &str = "$_";
+
+/// Values stored by the user, plus the last result
+#[derive(Debug, Default, Clone)]
+pub struct Variables {
+    values: HashMap<Name, i64>,
+    last:   Option<i64>
+}
+
+/// Types and variables together, everything an expression can reference
+pub struct Scope<'a> {
+    pub manager:   &'a TypeManager,
+    pub variables: &'a Variables
+}
+
+impl Variables {
+
+    /// Store a value, it also becomes the last result
+    pub fn set(&mut self, name : &str, value : i64) {
+        self.values.insert(name.to_string(), value);
+        self.last = Some(value);
+    }
+
+    /// Remember the result of a calculation as `$_`
+    pub fn set_last(&mut self, value : i64) {
+        self.last = Some(value);
+    }
+
+    /// Value for a name: `$_` is the last result, `$x` is the same as `x`
+    pub fn get(&self, name : &str) -> Option<i64> {
+        match name {
+            LAST => self.last,
+            _    => self.values.get(name.trim_start_matches('$')).copied()
+        }
+    }
+
+    /// Every stored variable sorted by name, not including `$_`
+    pub fn sorted(&self) -> Vec<(&Name, i64)> {
+        let mut values : Vec<(&Name, i64)> = self.values.iter().map(|(n, v)| (n, *v)).collect();
+        values.sort();
+        values
+    }
+}
+
+impl Environment for Scope<'_> {
+    fn property(&self, name : &str, property : &str) -> Result<i64, ExprError> {
+        self.manager.property(name, property)
+    }
+
+    fn name(&self, name : &str) -> Result<i64, ExprError> {
+        self.variables.get(name).ok_or_else(|| ExprError::UnknownName(name.to_string()))
+    }
+
+    fn call(&self, function : &str, args : &[Expr]) -> Result<i64, ExprError> {
+        self.manager.call(function, args)
+    }
+}
+
+/// Properties of atomic types and enums, and layout functions for every type
+impl Environment for TypeManager {
+    fn property(&self, name : &str, property : &str) -> Result<i64, ExprError> {
+        let (size, align) = match self.get(&name.to_string()) {
+            Some(Type::Atomic(a)) => (a.representation, a.alignment),
+            Some(Type::Enum(e))   => (e.size(), e.align()),
+            Some(_) => return Err(ExprError::NotAtomic(name.to_string())),
+            None    => return Err(ExprError::UnknownType(name.to_string()))
+        };
+
+        let value = match property {
+            "size" | "tamaño" | "tamano"     => size,
+            "align" | "alineacion" | "alineación" => align,
+            _ => return Err(ExprError::UnknownProperty(property.to_string()))
+        };
+
+        natural(value)
+    }
+
+    fn call(&self, function : &str, args : &[Expr]) -> Result<i64, ExprError> {
+        match function {
+            "tamaño" | "tamano" | "size" | "sizeof" => {
+                let usage = "tamaño(tipo[, estrategia])";
+                let (t, strategy) = type_and_strategy(self, function, usage, args)?;
+                natural(strategy.type_size(t, self))
+            },
+            "alineacion" | "alineación" | "align" | "alignof" => {
+                let usage = "alineacion(tipo[, estrategia])";
+                let (t, strategy) = type_and_strategy(self, function, usage, args)?;
+                natural(strategy.type_align(t, self))
+            },
+            "offset" | "desplazamiento" | "offsetof" => {
+                let usage = "offset(tipo, campo[, estrategia])";
+                let (name, field) = match args {
+                    [Expr::Name(t), Expr::Name(f)] | [Expr::Name(t), Expr::Name(f), _] => (t, f),
+                    _ => return Err(ExprError::Arguments(function.to_string(), usage))
+                };
+                let (t, strategy) = match args.get(2) {
+                    None    => type_and_strategy(self, function, usage, &args[..1])?,
+                    Some(s) => type_and_strategy(self, function, usage, &[args[0].clone(), s.clone()])?
+                };
+
+                let s = match t {
+                    Type::Struct(s) => s,
+                    _ => return Err(ExprError::NotAStruct(name.clone()))
+                };
+                let index = s.field_index(field).ok_or_else(|| ExprError::UnknownField(name.clone(), field.clone()))?;
+                let layout = s.layout(self, strategy);
+                let offset = layout.iter().find(|m| m.index == index).map(|m| m.offset).unwrap_or(0);
+
+                natural(offset)
+            },
+            _ => Err(ExprError::UnknownFunction(function.to_string()))
+        }
+    }
+}
+
+/// Arguments for functions taking a type and an optional strategy, the C strategy if none is given
+fn type_and_strategy<'a>(manager : &'a TypeManager, function : &str, usage : &'static str, args : &[Expr]) -> Result<(&'a Type, Strategy), ExprError> {
+    let (name, strategy) = match args {
+        [Expr::Name(t)] => (t, Strategy::C),
+        [Expr::Name(t), Expr::Name(s)] => (t, Strategy::parse(s).ok_or_else(|| ExprError::UnknownStrategy(s.clone()))?),
+        _ => return Err(ExprError::Arguments(function.to_string(), usage))
+    };
+
+    match manager.get(name) {
+        Some(t) => Ok((t, strategy)),
+        None    => Err(ExprError::UnknownType(name.clone()))
+    }
+}
+
+/// Convert a size to an expression value
+fn natural(value : usize) -> Result<i64, ExprError> {
+    i64::try_from(value).map_err(|_| ExprError::Overflow)
+}
+
+/// Lexical units
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(i64),
+    Word(String),
+    Symbol(char),
+    Compare(Cmp)
+}
+
+impl Expr {
+
+    /// Parse an expression
+    /// ## Params
+    /// * `text` - expression, such as `int.size / 2 + 1`
+    /// ---
+    /// ## Return
+    /// Parsed expression, or a description of the problem
+    pub fn parse(text : &str) -> Result<Expr, ExprError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, next: 0 };
+
+        let expr = parser.comparison()?;
+        match parser.peek() {
+            None    => Ok(expr),
+            Some(t) => Err(ExprError::Syntax(format!("sobra '{}'", t)))
+        }
+    }
+
+    /// Compute the value of this expression
+    /// ## Params
+    /// * `env` - where to get referenced values from
+    pub fn eval(&self, env : &dyn Environment) -> Result<i64, ExprError> {
+        match self {
+            Expr::Number(n) => Ok(*n),
+            Expr::Name(name) => env.name(name),
+            Expr::Property(name, property) => env.property(name, property),
+            Expr::Call(function, args) => env.call(function, args),
+            Expr::Neg(e) => e.eval(env)?.checked_neg().ok_or(ExprError::Overflow),
+            Expr::Binary(op, l, r) => {
+                let (l, r) = (l.eval(env)?, r.eval(env)?);
+                let result = match op {
+                    Op::Add => l.checked_add(r),
+                    Op::Sub => l.checked_sub(r),
+                    Op::Mul => l.checked_mul(r),
+                    Op::Div | Op::Rem if r == 0 => return Err(ExprError::DivisionByZero),
+                    Op::Div => l.checked_div(r),
+                    Op::Rem => l.checked_rem(r)
+                };
+
+                result.ok_or(ExprError::Overflow)
+            },
+            Expr::Compare(cmp, l, r) => {
+                let (l, r) = (l.eval(env)?, r.eval(env)?);
+                let result = match cmp {
+                    Cmp::Eq => l == r,
+                    Cmp::Ne => l != r,
+                    Cmp::Lt => l < r,
+                    Cmp::Le => l <= r,
+                    Cmp::Gt => l > r,
+                    Cmp::Ge => l >= r
+                };
+
+                Ok(result as i64)
+            }
+        }
+    }
+
+    /// Compute the value of this expression as a size or alignment
+    /// ## Return
+    /// The value, or an error if it's negative
+    pub fn eval_natural(&self, env : &dyn Environment) -> Result<usize, ExprError> {
+        let value = self.eval(env)?;
+
+        usize::try_from(value).map_err(|_| ExprError::Negative(value))
+    }
+
+    /// Tells if the value of this expression is a truth value
+    pub fn is_comparison(&self) -> bool {
+        matches!(self, Expr::Compare(..))
+    }
+
+    /// Tells if this expression may read a variable. Any bare name counts, even
+    /// the ones a call takes as a type or strategy name
+    pub fn reads_variables(&self) -> bool {
+        match self {
+            Expr::Number(_) | Expr::Property(..) => false,
+            Expr::Name(_) => true,
+            Expr::Call(_, args) => args.iter().any(Expr::reads_variables),
+            Expr::Neg(e) => e.reads_variables(),
+            Expr::Binary(_, l, r) | Expr::Compare(_, l, r) => l.reads_variables() || r.reads_variables()
+        }
+    }
+}
+
+/// Split arguments separated by whitespace, except whitespace between parenthesis:
+/// `4 (int.size + 2)` are two arguments
+pub fn split_arguments(text : &str) -> Vec<&str> {
+    let mut args = vec![];
+    let mut depth = 0i32;
+    let mut start = None;
+
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c.is_whitespace() && depth <= 0 => {
+                if let Some(s) = start.take() {
+                    args.push(&text[s..i]);
+                }
+                continue
+            },
+            _ => {}
+        }
+
+        if start.is_none() {
+            start = Some(i);
+        }
+    }
+
+    if let Some(s) = start {
+        args.push(&text[s..]);
+    }
+
+    args
+}
+
+/// Split an expression into tokens
+fn tokenize(text : &str) -> Result<Vec<Token>, ExprError> {
+    let mut tokens = vec![];
+    let chars : Vec<char> = text.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        if c.is_whitespace() {
+            i += 1;
+        }
+        else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let text : String = chars[start..i].iter().filter(|c| **c != '_').collect();
+            tokens.push(Token::Number(parse_number(&text)?));
+        }
+        else if c.is_alphabetic() || c == '_' || c == '$' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            if i == start + 1 && c == '$' {
+                return Err(ExprError::Syntax(String::from("falta el nombre después de '$'")))
+            }
+            tokens.push(Token::Word(chars[start..i].iter().collect()));
+        }
+        else if "<>=!".contains(c) {
+            let (cmp, len) = match (c, next) {
+                ('=', Some('=')) => (Cmp::Eq, 2),
+                ('!', Some('=')) => (Cmp::Ne, 2),
+                ('<', Some('=')) => (Cmp::Le, 2),
+                ('>', Some('=')) => (Cmp::Ge, 2),
+                ('<', _) => (Cmp::Lt, 1),
+                ('>', _) => (Cmp::Gt, 1),
+                _ => return Err(ExprError::Syntax(format!("carácter inesperado '{}'", c)))
+            };
+            tokens.push(Token::Compare(cmp));
+            i += len;
+        }
+        else if "+-*/%().,".contains(c) {
+            tokens.push(Token::Symbol(c));
+            i += 1;
+        }
+        else {
+            return Err(ExprError::Syntax(format!("carácter inesperado '{}'", c)))
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parse a decimal, hexadecimal (`0x`) or binary (`0b`) number
+fn parse_number(text : &str) -> Result<i64, ExprError> {
+    let result = if let Some(hex) = text.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16)
+    }
+    else if let Some(bin) = text.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2)
+    }
+    else {
+        text.parse()
+    };
+
+    result.map_err(|_| ExprError::Syntax(format!("'{}' no es un número válido", text)))
+}
+
+/// Recursive descent parser, one function per precedence level
+struct Parser {
+    tokens: Vec<Token>,
+    next:   usize
+}
+
+impl Parser {
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token
+    }
+
+    /// Consume the given symbol if it's next
+    fn eat(&mut self, symbol : char) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.next += 1;
+            return true
+        }
+
+        false
+    }
+
+    /// comparison := sum (cmp sum)?
+    fn comparison(&mut self) -> Result<Expr, ExprError> {
+        let left = self.sum()?;
+        let cmp = match self.peek() {
+            Some(Token::Compare(cmp)) => *cmp,
+            _ => return Ok(left)
+        };
+        self.next += 1;
+
+        let right = self.sum()?;
+        if let Some(Token::Compare(_)) = self.peek() {
+            return Err(ExprError::Syntax(String::from("las comparaciones no se pueden encadenar")))
+        }
+
+        Ok(Expr::Compare(cmp, Box::new(left), Box::new(right)))
+    }
+
+    /// sum := product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<Expr, ExprError> {
+        let mut expr = self.product()?;
+        loop {
+            let op = if self.eat('+') { Op::Add } else if self.eat('-') { Op::Sub } else { return Ok(expr) };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+    }
+
+    /// product := unary (('*' | '/' | '%') unary)*
+    fn product(&mut self) -> Result<Expr, ExprError> {
+        let mut expr = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                Op::Mul
+            }
+            else if self.eat('/') {
+                Op::Div
+            }
+            else if self.eat('%') {
+                Op::Rem
+            }
+            else {
+                return Ok(expr)
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    /// unary := '-' unary | atom
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        if self.eat('-') {
+            return Ok(Expr::Neg(Box::new(self.unary()?)))
+        }
+
+        self.atom()
+    }
+
+    /// atom := number | name | name '.' property | name '(' arguments ')' | '(' comparison ')'
+    fn atom(&mut self) -> Result<Expr, ExprError> {
+        match self.advance() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Symbol('(')) => {
+                let expr = self.comparison()?;
+                if !self.eat(')') {
+                    return Err(ExprError::Syntax(String::from("falta ')'")))
+                }
+                Ok(expr)
+            },
+            Some(Token::Word(name)) => {
+                if self.eat('(') {
+                    return Ok(Expr::Call(name, self.arguments()?))
+                }
+
+                if !self.eat('.') {
+                    return Ok(Expr::Name(name))
+                }
+
+                match self.advance() {
+                    Some(Token::Word(property)) => Ok(Expr::Property(name, property)),
+                    _ => Err(ExprError::Syntax(format!("se esperaba una propiedad de '{}', como '{}.size'", name, name)))
+                }
+            },
+            Some(t) => Err(ExprError::Syntax(format!("no se esperaba '{}'", t))),
+            None    => Err(ExprError::Syntax(String::from("la expresión termina antes de tiempo")))
+        }
+    }
+
+    /// arguments := (comparison (',' comparison)*)? ')'
+    fn arguments(&mut self) -> Result<Vec<Expr>, ExprError> {
+        let mut args = vec![];
+        if self.eat(')') {
+            return Ok(args)
+        }
+
+        loop {
+            args.push(self.comparison()?);
+            if self.eat(')') {
+                return Ok(args)
+            }
+            if !self.eat(',') {
+                return Err(ExprError::Syntax(String::from("se esperaba ',' o ')'")))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::Number(n)  => write!(f, "{}", n),
+            Token::Word(w)    => write!(f, "{}", w),
+            Token::Symbol(c)  => write!(f, "{}", c),
+            Token::Compare(c) => write!(f, "{}", c.symbol())
+        }
+    }
+}
+
+impl Cmp {
+
+    /// Symbol this operator is written with
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Cmp::Eq => "==",
+            Cmp::Ne => "!=",
+            Cmp::Lt => "<",
+            Cmp::Le => "<=",
+            Cmp::Gt => ">",
+            Cmp::Ge => ">="
+        }
+    }
+}
+
+impl ExprError {
+
+    /// Get human readable description for this error
+    pub fn display(&self) -> String {
+        match self {
+            ExprError::Syntax(s)          => format!("Expresión inválida: {}", s),
+            ExprError::DivisionByZero     => String::from("División por cero"),
+            ExprError::Overflow           => String::from("El resultado es demasiado grande para representarlo"),
+            ExprError::Negative(n)        => format!("Se esperaba un número natural, pero el resultado es {}", n),
+            ExprError::UnknownType(s)     => format!("El símbolo '{}' no existe", s),
+            ExprError::NotAtomic(s)       => format!("El tipo '{}' no es atómico", s),
+            ExprError::NotAStruct(s)      => format!("El tipo '{}' no es un struct", s),
+            ExprError::UnknownProperty(s) => format!("'{}' no es una propiedad, se esperaba 'size' o 'align'", s),
+            ExprError::UnknownName(s)     => format!("'{}' no tiene un valor", s),
+            ExprError::UnknownFunction(s) => format!("'{}' no es una función, se esperaba tamaño, alineacion u offset", s),
+            ExprError::UnknownField(t, f) => format!("El struct '{}' no tiene un campo '{}'", t, f),
+            ExprError::UnknownStrategy(s) => format!("'{}' no es una estrategia, se esperaba optimizado, sin_empaquetar, empaquetado o c", s),
+            ExprError::Arguments(f, u)    => format!("Argumentos inválidos para '{}', se usa así: {}", f, u)
+        }
+    }
+}
@@ -0,0 +1,184 @@
+/*
+    Images: raster backend for layout diagrams, for slides and documentation.
+    Every byte of a struct is a cell in a grid of rows, colored by the member
+    it belongs to and hatched when it's padding, with member names written over
+    their first cell and the offset of every row on its left. Images are saved
+    as PNG with an encoder of our own
+*/
+mod font;
+mod png;
+
+use crate::type_system::{Diagram, Span};
+use font::{GLYPH_HEIGHT, GLYPH_WIDTH};
+
+pub use png::crc32;
+
+/// Width of the cell of a byte, in pixels
+const CELL : usize = 44;
+
+/// Height of a row of bytes, in pixels
+const ROW_HEIGHT : usize = 36;
+
+/// Space on the left for row offsets, in pixels
+const LEFT : usize = 48;
+
+/// Space on top for the title, in pixels
+const TOP : usize = 32;
+
+/// Space on the right and at the bottom, in pixels
+const MARGIN : usize = 12;
+
+/// Pixels per font pixel
+const SCALE : usize = 2;
+
+/// Horizontal space taken by a character, in pixels
+const ADVANCE : usize = (GLYPH_WIDTH + 1) * SCALE;
+
+const BACKGROUND : [u8; 3] = [0xff, 0xff, 0xff];
+const INK        : [u8; 3] = [0x22, 0x22, 0x22];
+const PADDING    : [u8; 3] = [0xee, 0xee, 0xee];
+const HATCH      : [u8; 3] = [0xaa, 0xaa, 0xaa];
+
+/// Colors of members, in memory order, starting over after the last one
+const PALETTE : [[u8; 3]; 8] = [
+    [0x8d, 0xd3, 0xc7],
+    [0xff, 0xed, 0x8f],
+    [0xbe, 0xba, 0xda],
+    [0xfb, 0x80, 0x72],
+    [0x80, 0xb1, 0xd3],
+    [0xfd, 0xb4, 0x62],
+    [0xb3, 0xde, 0x69],
+    [0xfc, 0xcd, 0xe5]
+];
+
+/// An RGB image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Canvas {
+    width:  usize,
+    height: usize,
+    pixels: Vec<[u8; 3]>    // row by row
+}
+
+impl Canvas {
+
+    /// An image of one color
+    pub fn new(width : usize, height : usize, color : [u8; 3]) -> Canvas {
+        Canvas { width, height, pixels: vec![color; width * height] }
+    }
+
+    /// Width in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Color of a pixel, none outside the image
+    pub fn pixel(&self, x : usize, y : usize) -> Option<[u8; 3]> {
+        match x < self.width && y < self.height {
+            true  => Some(self.pixels[y * self.width + x]),
+            false => None
+        }
+    }
+
+    /// This image encoded as PNG
+    pub fn png(&self) -> Vec<u8> {
+        png::encode(self.width, self.height, &self.pixels)
+    }
+
+    /// Paint a rectangle, clipped to the image
+    fn fill(&mut self, x : usize, y : usize, width : usize, height : usize, color : [u8; 3]) {
+        for row in y..(y + height).min(self.height) {
+            for column in x..(x + width).min(self.width) {
+                self.pixels[row * self.width + column] = color;
+            }
+        }
+    }
+
+    /// Paint a rectangle with diagonal stripes, aligned with the whole image so
+    /// neighbouring rectangles continue each other's stripes
+    fn hatch(&mut self, x : usize, y : usize, width : usize, height : usize) {
+        for row in y..(y + height).min(self.height) {
+            for column in x..(x + width).min(self.width) {
+                self.pixels[row * self.width + column] = match (row + column) % 8 < 2 {
+                    true  => HATCH,
+                    false => PADDING
+                };
+            }
+        }
+    }
+
+    /// Draw the outline of a rectangle, a pixel wide
+    fn outline(&mut self, x : usize, y : usize, width : usize, height : usize, color : [u8; 3]) {
+        self.fill(x, y, width, 1, color);
+        self.fill(x, y + height - 1, width, 1, color);
+        self.fill(x, y, 1, height, color);
+        self.fill(x + width - 1, y, 1, height, color);
+    }
+
+    /// Write text with its top left corner somewhere, as many characters as fit in a width
+    fn text(&mut self, x : usize, y : usize, text : &str, width : usize) {
+        for (i, c) in text.chars().take(width / ADVANCE).enumerate() {
+            for (row, bits) in font::glyph(c).iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                        self.fill(x + i * ADVANCE + column * SCALE, y + row * SCALE, SCALE, SCALE, INK);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Draw a layout diagram
+pub fn draw(diagram : &Diagram) -> Canvas {
+    let rows = diagram.rows();
+    let width = LEFT + diagram.row * CELL + MARGIN;
+    let mut canvas = Canvas::new(width, TOP + rows * ROW_HEIGHT + MARGIN, BACKGROUND);
+    let text_height = GLYPH_HEIGHT * SCALE;
+
+    let title = format!("{}: {} bytes", diagram.name, diagram.size);
+    canvas.text(LEFT, (TOP - text_height) / 2, &title, width - LEFT);
+    for row in 0..rows {
+        let offset = (row * diagram.row).to_string();
+        canvas.text(MARGIN / 2, TOP + row * ROW_HEIGHT + (ROW_HEIGHT - text_height) / 2, &offset, LEFT - MARGIN / 2);
+    }
+
+    for span in &diagram.spans {
+        let mut labeled = false;
+        for (start, bytes) in segments(span, diagram.row) {
+            let (x, y) = (LEFT + (start % diagram.row) * CELL, TOP + (start / diagram.row) * ROW_HEIGHT);
+            let (w, h) = (bytes * CELL, ROW_HEIGHT);
+            match span.member {
+                Some(m) => canvas.fill(x, y, w, h, PALETTE[m % PALETTE.len()]),
+                None => canvas.hatch(x, y, w, h)
+            }
+            // short ticks between the bytes of a segment
+            for byte in 1..bytes {
+                canvas.fill(x + byte * CELL, y + h - h / 4, 1, h / 4, INK);
+            }
+            canvas.outline(x, y, w + 1, h + 1, INK);
+            if !labeled {
+                canvas.text(x + 2 * SCALE, y + 2 * SCALE, &span.label, w.saturating_sub(2 * SCALE));
+                labeled = true;
+            }
+        }
+    }
+
+    canvas
+}
+
+/// Pieces of a span in every row it touches: first byte and bytes in the row
+fn segments(span : &Span, row : usize) -> Vec<(usize, usize)> {
+    let (mut start, end) = (span.offset, span.offset + span.size);
+    let mut segments = vec![];
+    while start < end {
+        let bytes = (end - start).min(row - start % row);
+        segments.push((start, bytes));
+        start += bytes;
+    }
+    segments
+}
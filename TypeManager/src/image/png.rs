@@ -0,0 +1,183 @@
+/*
+    PNG encoding, just what's needed to save diagrams without other crates:
+    8 bit RGB pixels, every row filtered against the one above and compressed
+    with the fixed Huffman codes of deflate. Diagrams are mostly flat colors,
+    so repeating the previous byte or pixel is the only match looked for
+*/
+
+/// Every PNG starts with these bytes
+const SIGNATURE : [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Bytes per pixel, RGB
+const PIXEL : usize = 3;
+
+/// Longest match deflate can express
+const MAX_MATCH : usize = 258;
+
+/// Smallest length of every deflate length code, from 257
+const LENGTH_BASE : [usize; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+
+/// Extra bits of every deflate length code, from 257
+const LENGTH_EXTRA : [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+
+/// Encode an image as PNG
+/// ## Params
+/// * `width` - pixels in every row
+/// * `height` - rows
+/// * `pixels` - RGB values, row by row
+pub fn encode(width : usize, height : usize, pixels : &[[u8; 3]]) -> Vec<u8> {
+    let stride = width * PIXEL;
+    let mut raw = Vec::with_capacity((stride + 1) * height);
+    let mut above = vec![0u8; stride];
+    for row in pixels.chunks(width.max(1)).take(height) {
+        let current : Vec<u8> = row.iter().flatten().copied().collect();
+        raw.push(2);    // Up filter: every byte minus the one above it
+        raw.extend(current.iter().zip(&above).map(|(b, a)| b.wrapping_sub(*a)));
+        above = current;
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend((width as u32).to_be_bytes());
+    header.extend((height as u32).to_be_bytes());
+    header.extend([8, 2, 0, 0, 0]);     // 8 bit depth, RGB, deflate, no interlacing
+
+    let mut png = SIGNATURE.to_vec();
+    chunk(&mut png, b"IHDR", &header);
+    chunk(&mut png, b"IDAT", &zlib(&raw));
+    chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+/// Append a chunk: its length, type, data and the CRC of type and data
+fn chunk(png : &mut Vec<u8>, kind : &[u8; 4], data : &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+/// CRC-32 as used by PNG
+pub fn crc32(data : &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xedb8_8320,
+                _ => crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Adler-32 checksum closing a zlib stream
+fn adler32(data : &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// Compress data as a zlib stream: a single deflate block with fixed codes
+fn zlib(data : &[u8]) -> Vec<u8> {
+    let mut bits = Bits { bytes: vec![0x78, 0x01], current: 0, used: 0 };
+    bits.write(1, 1);   // last block
+    bits.write(1, 2);   // fixed Huffman codes
+
+    let mut i = 0;
+    while i < data.len() {
+        let (length, distance) = [1, PIXEL]
+                                    .iter()
+                                    .map(|d| (matching(data, i, *d), *d))
+                                    .max()
+                                    .unwrap_or((0, 1));
+        match length >= 3 {
+            true  => {
+                bits.length(length);
+                bits.code(distance as u32 - 1, 5);  // codes 0 and 2 are distances 1 and 3, without extra bits
+                i += length;
+            },
+            false => {
+                bits.literal(data[i] as u32);
+                i += 1;
+            }
+        }
+    }
+    bits.literal(256);  // end of block
+
+    let mut bytes = bits.finish();
+    bytes.extend(adler32(data).to_be_bytes());
+    bytes
+}
+
+/// Bytes from `i` equal to those `distance` bytes before them
+fn matching(data : &[u8], i : usize, distance : usize) -> usize {
+    if i < distance {
+        return 0
+    }
+    let mut length = 0;
+    while length < MAX_MATCH && i + length < data.len() && data[i + length] == data[i + length - distance] {
+        length += 1;
+    }
+    length
+}
+
+/// Deflate output, packed from the lowest bit of every byte
+struct Bits {
+    bytes:   Vec<u8>,
+    current: u32,
+    used:    u32
+}
+
+impl Bits {
+
+    /// Write the lowest `count` bits of a value, lowest first
+    fn write(&mut self, value : u32, count : u32) {
+        for i in 0..count {
+            self.current |= ((value >> i) & 1) << self.used;
+            self.used += 1;
+            if self.used == 8 {
+                self.bytes.push(self.current as u8);
+                self.current = 0;
+                self.used = 0;
+            }
+        }
+    }
+
+    /// Write a Huffman code, highest bit first
+    fn code(&mut self, code : u32, length : u32) {
+        for i in (0..length).rev() {
+            self.write(code >> i, 1);
+        }
+    }
+
+    /// Write a literal byte or the end of block, 256, with the fixed codes
+    fn literal(&mut self, value : u32) {
+        match value {
+            0..=143   => self.code(0x30 + value, 8),
+            144..=255 => self.code(0x190 + value - 144, 9),
+            256..=279 => self.code(value - 256, 7),
+            _         => self.code(0xc0 + value - 280, 8)
+        }
+    }
+
+    /// Write the length of a match, from 3 to 258
+    fn length(&mut self, length : usize) {
+        let code = LENGTH_BASE.iter().rposition(|base| *base <= length).unwrap_or(0);
+        self.literal(257 + code as u32);
+        self.write((length - LENGTH_BASE[code]) as u32, LENGTH_EXTRA[code] as u32);
+    }
+
+    /// Every byte written, the last one completed with zeros
+    fn finish(mut self) -> Vec<u8> {
+        if self.used > 0 {
+            self.bytes.push(self.current as u8);
+        }
+        self.bytes
+    }
+}
@@ -0,0 +1,776 @@
+/*
+    DWARF: debug information compilers write into binaries. Structs and unions
+    found there are imported with every type their members use, keeping the
+    offsets the compiler chose so they can be compared with our strategies.
+    Only 64 bit little endian ELF files are read (x86-64, AArch64, RISC-V),
+    with DWARF 2 to 5. Relocations in object files are applied, compressed
+    debug sections are not supported
+*/
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
+
+use super::{type_name, CompiledLayout, ImportError, Imported};
+use crate::type_system::*;
+
+// tags
+const TAG_ARRAY       : u64 = 0x01;
+const TAG_CLASS       : u64 = 0x02;
+const TAG_ENUMERATION : u64 = 0x04;
+const TAG_MEMBER      : u64 = 0x0d;
+const TAG_POINTER     : u64 = 0x0f;
+const TAG_REFERENCE   : u64 = 0x10;
+const TAG_STRUCTURE   : u64 = 0x13;
+const TAG_SUBROUTINE  : u64 = 0x15;
+const TAG_TYPEDEF     : u64 = 0x16;
+const TAG_UNION       : u64 = 0x17;
+const TAG_INHERITANCE : u64 = 0x1c;
+const TAG_SUBRANGE    : u64 = 0x21;
+const TAG_BASE        : u64 = 0x24;
+const TAG_CONST       : u64 = 0x26;
+const TAG_ENUMERATOR  : u64 = 0x28;
+const TAG_VOLATILE    : u64 = 0x35;
+const TAG_RESTRICT    : u64 = 0x37;
+const TAG_RVALUE_REF  : u64 = 0x42;
+const TAG_ATOMIC      : u64 = 0x47;
+
+// attributes
+const AT_NAME            : u64 = 0x03;
+const AT_BYTE_SIZE       : u64 = 0x0b;
+const AT_BIT_SIZE        : u64 = 0x0d;
+const AT_UPPER_BOUND     : u64 = 0x2f;
+const AT_COUNT           : u64 = 0x37;
+const AT_MEMBER_LOCATION : u64 = 0x38;
+const AT_DECLARATION     : u64 = 0x3c;
+const AT_ENCODING        : u64 = 0x3e;
+const AT_TYPE            : u64 = 0x49;
+const AT_STR_OFFSETS     : u64 = 0x72;
+const AT_ALIGNMENT       : u64 = 0x88;
+
+// base type encodings
+const ATE_BOOLEAN       : u64 = 0x02;
+const ATE_FLOAT         : u64 = 0x04;
+const ATE_SIGNED        : u64 = 0x05;
+const ATE_SIGNED_CHAR   : u64 = 0x06;
+const ATE_UNSIGNED_CHAR : u64 = 0x08;
+const ATE_UTF           : u64 = 0x10;
+
+/// Biggest alignment of a base type, a `long double` on x86-64
+const MAX_BASE_ALIGN : usize = 16;
+
+/// Deepest chain of qualifiers and typedefs followed, in case a broken binary loops
+const MAX_CHAIN : usize = 64;
+
+/// Read the structs and unions described in the debug information of a binary
+/// ## Params
+/// * `bytes` - an ELF executable, library or object file compiled with `-g`
+/// ---
+/// ## Return
+/// Types imported, each one after the types it uses, their layout in the binary and the
+/// types that couldn't be imported, or an error if the binary can't be read
+pub fn dwarf(bytes : &[u8]) -> Result<Imported, ImportError> {
+    let elf = Elf::parse(bytes)?;
+    let info = elf.section(".debug_info")?.ok_or_else(|| invalid("no tiene información de depuración, compilalo con -g"))?;
+    let sections = Sections {
+        abbrev:      elf.section(".debug_abbrev")?.unwrap_or_default(),
+        strings:     elf.section(".debug_str")?.unwrap_or_default(),
+        line:        elf.section(".debug_line_str")?.unwrap_or_default(),
+        str_offsets: elf.section(".debug_str_offsets")?.unwrap_or_default()
+    };
+    let tree = Tree::parse(&info, &sections)?;
+
+    let mut importer = DwarfImporter {
+        tree:     &tree,
+        names:    HashMap::new(),
+        types:    TypeManager::new(),
+        order:    vec![],
+        visiting: HashSet::new(),
+        compiled: vec![]
+    };
+    importer.name_records();
+
+    let mut imported = Imported::default();
+    for (index, die) in tree.dies.iter().enumerate() {
+        if !matches!(die.tag, TAG_STRUCTURE | TAG_CLASS | TAG_UNION) || die.flag(AT_DECLARATION) {
+            continue
+        }
+        let name = match importer.record_name(index) {
+            Some(name) => name,
+            None => continue
+        };
+        if let Err(e) = importer.record(index) {
+            imported.skipped.push((name, e));
+        }
+    }
+
+    imported.types = importer.order.iter().filter_map(|n| importer.types.get(n).map(|t| (n.clone(), t.clone()))).collect();
+    imported.compiled = importer.compiled;
+    Ok(imported)
+}
+
+/// Error for a binary that can't be read
+fn invalid(reason : &str) -> ImportError {
+    ImportError::Object(reason.to_string())
+}
+
+/// Little endian reader over some bytes
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos:   usize
+}
+
+impl<'a> Reader<'a> {
+
+    fn new(bytes : &'a [u8], pos : usize) -> Reader<'a> {
+        Reader { bytes, pos }
+    }
+
+    fn take(&mut self, n : usize) -> Result<&'a [u8], ImportError> {
+        let end = self.pos.checked_add(n).filter(|end| *end <= self.bytes.len()).ok_or_else(|| invalid("datos truncados"))?;
+        let taken = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+
+    /// Unsigned number of up to 8 bytes
+    fn uint(&mut self, n : usize) -> Result<u64, ImportError> {
+        Ok(self.take(n)?.iter().rev().fold(0, |value, byte| (value << 8) | *byte as u64))
+    }
+
+    fn uleb(&mut self) -> Result<u64, ImportError> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.take(1)?[0];
+            if shift < 64 {
+                value |= ((byte & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(value)
+            }
+        }
+    }
+
+    fn sleb(&mut self) -> Result<i64, ImportError> {
+        let mut value = 0i64;
+        let mut shift = 0;
+        loop {
+            let byte = self.take(1)?[0];
+            if shift < 64 {
+                value |= ((byte & 0x7f) as i64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    value |= -1 << shift;
+                }
+                return Ok(value)
+            }
+        }
+    }
+
+    /// Text ending in a 0 byte
+    fn text(&mut self) -> Result<String, ImportError> {
+        let rest = &self.bytes[self.pos.min(self.bytes.len())..];
+        let len = rest.iter().position(|b| *b == 0).ok_or_else(|| invalid("texto sin terminar"))?;
+        self.pos += len + 1;
+        Ok(String::from_utf8_lossy(&rest[..len]).into_owned())
+    }
+}
+
+/// Text at some offset of a string section
+fn text_at(section : &[u8], offset : u64) -> Result<String, ImportError> {
+    Reader::new(section, offset as usize).text()
+}
+
+/// Sections of an ELF file
+struct Elf<'a> {
+    bytes:    &'a [u8],
+    machine:  u64,
+    sections: Vec<Section>
+}
+
+struct Section {
+    name:   String,
+    label:  u64,    // offset of the name in the section names
+    kind:   u64,
+    flags:  u64,
+    offset: usize,
+    size:   usize,
+    link:   usize,
+    info:   usize
+}
+
+const SHT_RELA        : u64 = 4;
+const SHT_NOBITS      : u64 = 8;
+const SHF_COMPRESSED  : u64 = 0x800;
+
+impl<'a> Elf<'a> {
+
+    fn parse(bytes : &'a [u8]) -> Result<Elf<'a>, ImportError> {
+        if bytes.len() < 64 || &bytes[..4] != b"\x7fELF" {
+            return Err(invalid("no es un archivo ELF"))
+        }
+        if bytes[4] != 2 || bytes[5] != 1 {
+            return Err(invalid("solo se leen archivos ELF de 64 bits little endian"))
+        }
+
+        let mut header = Reader::new(bytes, 0x12);
+        let machine = header.uint(2)?;
+        header.pos = 0x28;
+        let table = header.uint(8)? as usize;
+        header.pos = 0x3a;
+        let (entry, count, names) = (header.uint(2)? as usize, header.uint(2)? as usize, header.uint(2)? as usize);
+
+        let mut sections = Vec::with_capacity(count);
+        for i in 0..count {
+            let mut r = Reader::new(bytes, table.saturating_add(i.saturating_mul(entry)));
+            let name = r.uint(4)?;
+            let kind = r.uint(4)?;
+            let flags = r.uint(8)?;
+            r.take(8)?;
+            let (offset, size) = (r.uint(8)? as usize, r.uint(8)? as usize);
+            let (link, info) = (r.uint(4)? as usize, r.uint(4)? as usize);
+            sections.push(Section { name: String::new(), label: name, kind, flags, offset, size, link, info });
+        }
+
+        let mut elf = Elf { bytes, machine, sections };
+        let names = elf.sections.get(names).map(|s| elf.data(s)).transpose()?.unwrap_or_default();
+        for section in elf.sections.iter_mut() {
+            section.name = text_at(names, section.label).unwrap_or_default();
+        }
+        Ok(elf)
+    }
+
+    /// Bytes of a section as stored in the file
+    fn data(&self, section : &Section) -> Result<&'a [u8], ImportError> {
+        if section.kind == SHT_NOBITS {
+            return Ok(&[])
+        }
+        Reader::new(self.bytes, section.offset).take(section.size)
+    }
+
+    /// Bytes of a section with its relocations applied
+    /// ## Return
+    /// The bytes, none if there's no such section, or an error if the section can't be read
+    fn section(&self, name : &str) -> Result<Option<Vec<u8>>, ImportError> {
+        let index = match self.sections.iter().position(|s| s.name == name) {
+            Some(index) => index,
+            None => return Ok(None)
+        };
+        if self.sections[index].flags & SHF_COMPRESSED != 0 {
+            return Err(invalid("las secciones de depuración están comprimidas, recompila con -gz=none"))
+        }
+
+        let mut data = self.data(&self.sections[index])?.to_vec();
+        for rela in self.sections.iter().filter(|s| s.kind == SHT_RELA && s.info == index) {
+            let symbols = self.sections.get(rela.link).map(|s| self.data(s)).transpose()?.unwrap_or_default();
+            let mut r = Reader::new(self.data(rela)?, 0);
+            while r.pos < r.bytes.len() {
+                let (offset, info, addend) = (r.uint(8)? as usize, r.uint(8)?, r.uint(8)?);
+                let value = Reader::new(symbols, ((info >> 32) as usize).saturating_mul(24).saturating_add(8))
+                                .uint(8)
+                                .unwrap_or(0)
+                                .wrapping_add(addend);
+                let width = match (self.machine, info & 0xffff_ffff) {
+                    (62, 1) | (183, 257) | (243, 2)            => 8,   // x86-64, AArch64 and RISC-V 64 bit absolute
+                    (62, 10) | (62, 11) | (183, 258) | (243, 1) => 4,  // 32 bit absolute
+                    _ => continue
+                };
+                if let Some(target) = data.get_mut(offset..offset.saturating_add(width)) {
+                    target.copy_from_slice(&value.to_le_bytes()[..width]);
+                }
+            }
+        }
+        Ok(Some(data))
+    }
+}
+
+/// Debug sections other than `.debug_info`
+struct Sections {
+    abbrev:      Vec<u8>,
+    strings:     Vec<u8>,
+    line:        Vec<u8>,
+    str_offsets: Vec<u8>
+}
+
+/// Value of an attribute
+#[derive(Debug, Clone)]
+enum Attr {
+    Unsigned(u64),
+    Signed(i64),
+    Text(String),
+    StrIndex(u64),      // text in `.debug_str_offsets`, read once the unit is known
+    Ref(usize),         // offset of another entry in `.debug_info`
+    Block(Vec<u8>),
+    Other
+}
+
+/// A debugging information entry
+#[derive(Debug, Default)]
+struct Die {
+    tag:      u64,
+    attrs:    Vec<(u64, Attr)>,
+    children: Vec<usize>
+}
+
+impl Die {
+
+    fn attr(&self, at : u64) -> Option<&Attr> {
+        self.attrs.iter().find(|(a, _)| *a == at).map(|(_, v)| v)
+    }
+
+    fn unsigned(&self, at : u64) -> Option<u64> {
+        match self.attr(at)? {
+            Attr::Unsigned(u) => Some(*u),
+            Attr::Signed(s) if *s >= 0 => Some(*s as u64),
+            _ => None
+        }
+    }
+
+    fn name(&self) -> Option<&str> {
+        match self.attr(AT_NAME)? {
+            Attr::Text(t) if !t.is_empty() => Some(t),
+            _ => None
+        }
+    }
+
+    fn flag(&self, at : u64) -> bool {
+        self.unsigned(at).is_some_and(|f| f != 0)
+    }
+}
+
+/// Layout of an abbreviation: tag, if it has children, and attributes with their forms
+struct Abbrev {
+    tag:      u64,
+    children: bool,
+    specs:    Vec<(u64, u64, i64)>
+}
+
+/// What every form needs to know about its unit
+struct Unit {
+    start:        usize,
+    offset_size:  usize,
+    address_size: usize
+}
+
+/// Every entry of `.debug_info`
+struct Tree {
+    dies:      Vec<Die>,
+    by_offset: HashMap<usize, usize>
+}
+
+impl Tree {
+
+    fn parse(info : &[u8], sections : &Sections) -> Result<Tree, ImportError> {
+        let mut tree = Tree { dies: vec![], by_offset: HashMap::new() };
+        let mut abbrevs : HashMap<u64, HashMap<u64, Abbrev>> = HashMap::new();
+        let mut r = Reader::new(info, 0);
+
+        while r.pos < info.len() {
+            let start = r.pos;
+            let (length, offset_size) = match r.uint(4)? {
+                0xffff_ffff => (r.uint(8)?, 8),
+                length => (length, 4)
+            };
+            let end = r.pos.checked_add(length as usize).filter(|end| *end <= info.len()).ok_or_else(|| invalid("unidad truncada"))?;
+            let (abbrev_offset, address_size) = match r.uint(2)? {
+                2..=4 => (r.uint(offset_size)?, r.uint(1)? as usize),
+                5 => {
+                    let kind = r.uint(1)?;
+                    let address_size = r.uint(1)? as usize;
+                    let abbrev_offset = r.uint(offset_size)?;
+                    match kind {
+                        2 | 6 => { r.take(8 + offset_size)?; },     // type units: signature and type offset
+                        4 | 5 => { r.take(8)?; },                   // skeleton and split units: their id
+                        _ => {}
+                    }
+                    (abbrev_offset, address_size)
+                },
+                version => return Err(ImportError::Object(format!("la versión {} de DWARF no está soportada", version)))
+            };
+
+            let table = match abbrevs.entry(abbrev_offset) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => e.insert(parse_abbrevs(&sections.abbrev, abbrev_offset)?)
+            };
+            let unit = Unit { start, offset_size, address_size };
+            let first = tree.dies.len();
+
+            let mut parents : Vec<usize> = vec![];
+            while r.pos < end {
+                let offset = r.pos;
+                let code = r.uleb()?;
+                if code == 0 {
+                    parents.pop();
+                    continue
+                }
+                let abbrev = table.get(&code).ok_or_else(|| ImportError::Object(format!("la abreviatura {} no existe", code)))?;
+                let mut attrs = Vec::with_capacity(abbrev.specs.len());
+                for (at, form, implicit) in &abbrev.specs {
+                    attrs.push((*at, read_form(&mut r, *form, *implicit, &unit, sections)?));
+                }
+
+                let index = tree.dies.len();
+                if let Some(parent) = parents.last() {
+                    tree.dies[*parent].children.push(index);
+                }
+                tree.dies.push(Die { tag: abbrev.tag, attrs, children: vec![] });
+                tree.by_offset.insert(offset, index);
+                if abbrev.children {
+                    parents.push(index);
+                }
+            }
+            r.pos = end;
+
+            // texts by index start at the unit's base, right after the table header by default
+            let base = tree.dies.get(first).and_then(|d| d.unsigned(AT_STR_OFFSETS)).unwrap_or(2 * offset_size as u64);
+            for die in &mut tree.dies[first..] {
+                for (_, value) in die.attrs.iter_mut() {
+                    if let Attr::StrIndex(i) = value {
+                        let entry = Reader::new(&sections.str_offsets, (base + *i * offset_size as u64) as usize).uint(offset_size)?;
+                        *value = Attr::Text(text_at(&sections.strings, entry)?);
+                    }
+                }
+            }
+        }
+
+        Ok(tree)
+    }
+
+    /// Entry referenced by an attribute
+    fn reference(&self, die : &Die, at : u64) -> Option<usize> {
+        match die.attr(at)? {
+            Attr::Ref(offset) => self.by_offset.get(offset).copied(),
+            _ => None
+        }
+    }
+}
+
+/// Read the abbreviation table at some offset of `.debug_abbrev`
+fn parse_abbrevs(section : &[u8], offset : u64) -> Result<HashMap<u64, Abbrev>, ImportError> {
+    let mut table = HashMap::new();
+    let mut r = Reader::new(section, offset as usize);
+    loop {
+        let code = r.uleb()?;
+        if code == 0 {
+            return Ok(table)
+        }
+        let tag = r.uleb()?;
+        let children = r.uint(1)? != 0;
+        let mut specs = vec![];
+        loop {
+            let (at, form) = (r.uleb()?, r.uleb()?);
+            if at == 0 && form == 0 {
+                break
+            }
+            let implicit = match form {
+                0x21 => r.sleb()?,
+                _ => 0
+            };
+            specs.push((at, form, implicit));
+        }
+        table.insert(code, Abbrev { tag, children, specs });
+    }
+}
+
+/// Read an attribute value written in some form
+fn read_form(r : &mut Reader, form : u64, implicit : i64, unit : &Unit, sections : &Sections) -> Result<Attr, ImportError> {
+    let skip = |r : &mut Reader, n : usize| r.take(n).map(|_| Attr::Other);
+    let block = |r : &mut Reader, n : u64| r.take(n as usize).map(|b| Attr::Block(b.to_vec()));
+
+    match form {
+        0x01 => skip(r, unit.address_size),
+        0x03 => { let n = r.uint(2)?; block(r, n) },
+        0x04 => { let n = r.uint(4)?; block(r, n) },
+        0x09 | 0x18 => { let n = r.uleb()?; block(r, n) },
+        0x0a => { let n = r.uint(1)?; block(r, n) },
+        0x05 => Ok(Attr::Unsigned(r.uint(2)?)),
+        0x06 => Ok(Attr::Unsigned(r.uint(4)?)),
+        0x07 => Ok(Attr::Unsigned(r.uint(8)?)),
+        0x0b | 0x0c => Ok(Attr::Unsigned(r.uint(1)?)),
+        0x0d => Ok(Attr::Signed(r.sleb()?)),
+        0x0f => Ok(Attr::Unsigned(r.uleb()?)),
+        0x19 => Ok(Attr::Unsigned(1)),
+        0x21 => Ok(Attr::Signed(implicit)),
+        0x08 => Ok(Attr::Text(r.text()?)),
+        0x0e => { let offset = r.uint(unit.offset_size)?; Ok(Attr::Text(text_at(&sections.strings, offset)?)) },
+        0x1f => { let offset = r.uint(unit.offset_size)?; Ok(Attr::Text(text_at(&sections.line, offset)?)) },
+        0x1a => Ok(Attr::StrIndex(r.uleb()?)),
+        0x25..=0x28 => Ok(Attr::StrIndex(r.uint((form - 0x24) as usize)?)),
+        0x10 => Ok(Attr::Ref(r.uint(unit.offset_size)? as usize)),
+        0x11 => Ok(Attr::Ref(unit.start + r.uint(1)? as usize)),
+        0x12 => Ok(Attr::Ref(unit.start + r.uint(2)? as usize)),
+        0x13 => Ok(Attr::Ref(unit.start + r.uint(4)? as usize)),
+        0x14 => Ok(Attr::Ref(unit.start.saturating_add(r.uint(8)? as usize))),
+        0x15 => Ok(Attr::Ref(unit.start.saturating_add(r.uleb()? as usize))),
+        0x1e => skip(r, 16),
+        0x20 | 0x24 => skip(r, 8),
+        0x1c => skip(r, 4),
+        0x17 | 0x1d | 0x1f20 | 0x1f21 => skip(r, unit.offset_size),
+        0x1b | 0x22 | 0x23 | 0x1f01 | 0x1f02 => r.uleb().map(|_| Attr::Other),
+        0x29..=0x2c => skip(r, (form - 0x28) as usize),
+        0x16 => {
+            let form = r.uleb()?;
+            read_form(r, form, implicit, unit, sections)
+        },
+        _ => Err(ImportError::Object(format!("la forma DWARF 0x{:x} no está soportada", form)))
+    }
+}
+
+/// Type, field and offset in the binary of every member of a record
+type Members = (TypeList, Vec<Field>, Vec<Option<usize>>);
+
+/// State while importing types from a tree of entries
+struct DwarfImporter<'a> {
+    tree:     &'a Tree,
+    names:    HashMap<usize, Name>,     // names for unnamed records, from typedefs or the member holding them
+    types:    TypeManager,
+    order:    Vec<Name>,
+    visiting: HashSet<usize>,
+    compiled: Vec<CompiledLayout>
+}
+
+impl<'a> DwarfImporter<'a> {
+
+    fn die(&self, index : usize) -> &'a Die {
+        &self.tree.dies[index]
+    }
+
+    fn target(&self, index : usize) -> Option<usize> {
+        self.tree.reference(self.die(index), AT_TYPE)
+    }
+
+    /// Skip qualifiers such as `const`
+    fn unqualified(&self, index : usize) -> Option<usize> {
+        let mut index = index;
+        for _ in 0..MAX_CHAIN {
+            match self.die(index).tag {
+                TAG_CONST | TAG_VOLATILE | TAG_RESTRICT | TAG_ATOMIC => index = self.target(index)?,
+                _ => return Some(index)
+            }
+        }
+        None
+    }
+
+    /// Name unnamed records after the typedef naming them, as in `typedef struct { ... } punto;`
+    fn name_records(&mut self) {
+        for die in &self.tree.dies {
+            if die.tag != TAG_TYPEDEF {
+                continue
+            }
+            let target = self.tree.reference(die, AT_TYPE).and_then(|t| self.unqualified(t));
+            if let (Some(name), Some(target)) = (die.name(), target) {
+                if self.die(target).name().is_none() && !self.names.contains_key(&target) {
+                    self.names.insert(target, name.to_string());
+                }
+            }
+        }
+    }
+
+    /// Name of a struct, union or enum
+    fn record_name(&self, index : usize) -> Option<Name> {
+        self.names.get(&index).cloned().or_else(|| self.die(index).name().map(String::from))
+    }
+
+    /// Add a type to the imported ones
+    fn define(&mut self, name : &str, t : Type) -> Result<Name, ImportError> {
+        self.types.add(name.to_string(), t).map_err(|e| ImportError::Invalid(name.to_string(), e))?;
+        self.order.push(name.to_string());
+        Ok(name.to_string())
+    }
+
+    /// Name a type would be imported with, without importing it
+    fn name_of(&self, index : usize, depth : usize) -> Name {
+        let die = self.die(index);
+        let target = || match (self.target(index), depth < MAX_CHAIN) {
+            (Some(t), true) => self.name_of(t, depth + 1),
+            _ => String::from("void")
+        };
+        match die.tag {
+            TAG_POINTER | TAG_REFERENCE | TAG_RVALUE_REF => match self.target(index).map(|t| self.die(t).tag) {
+                Some(TAG_SUBROUTINE) | None => String::from("ptr_void"),
+                Some(_) => format!("ptr_{}", target())
+            },
+            TAG_CONST | TAG_VOLATILE | TAG_RESTRICT | TAG_ATOMIC => target(),
+            TAG_ARRAY => format!("{}_x{}", target(), self.array_count(index)),
+            TAG_BASE => type_name(die.name().unwrap_or("void")),
+            _ => self.record_name(index).unwrap_or_else(|| format!("anonimo_{}", index))
+        }
+    }
+
+    /// Elements in an array, counting every dimension
+    fn array_count(&self, index : usize) -> u64 {
+        self.die(index)
+            .children
+            .iter()
+            .map(|c| self.die(*c))
+            .filter(|d| d.tag == TAG_SUBRANGE)
+            .map(|d| d.unsigned(AT_COUNT).or_else(|| d.unsigned(AT_UPPER_BOUND).map(|u| u.saturating_add(1))).unwrap_or(0))
+            .fold(1, u64::saturating_mul)
+    }
+
+    /// Import the type of an entry
+    /// ## Return
+    /// Name of the imported type
+    fn resolve(&mut self, index : usize) -> Result<Name, ImportError> {
+        let index = self.unqualified(index).ok_or_else(|| ImportError::UnsupportedType(String::from("void")))?;
+        let die = self.die(index);
+        let name = self.name_of(index, 0);
+        if self.types.get(&name).is_some() {
+            return Ok(name)
+        }
+
+        match die.tag {
+            TAG_BASE => {
+                let size = die.unsigned(AT_BYTE_SIZE).unwrap_or(0) as usize;
+                if size == 0 {
+                    return Err(ImportError::UnsupportedType(name))
+                }
+                let kind = match die.unsigned(AT_ENCODING) {
+                    Some(ATE_FLOAT) => Some(ValueKind::Float),
+                    Some(ATE_SIGNED) => Some(ValueKind::Signed),
+                    Some(ATE_SIGNED_CHAR) | Some(ATE_UNSIGNED_CHAR) | Some(ATE_UTF) => Some(ValueKind::Char),
+                    Some(ATE_BOOLEAN) => Some(ValueKind::Bool),
+                    Some(_) => Some(ValueKind::Unsigned),
+                    None => None
+                };
+                let align = 1 << size.trailing_zeros().min(MAX_BASE_ALIGN.trailing_zeros());
+                self.define(&name, Type::Atomic(Atomic::with_kind(size, align, kind)))
+            },
+            TAG_POINTER | TAG_REFERENCE | TAG_RVALUE_REF => {
+                let target = name.strip_prefix("ptr_").unwrap_or("void").to_string();
+                self.define(&name, Type::Pointer(Pointer::new(target, PointerKind::Thin)))
+            },
+            TAG_TYPEDEF => {
+                let target = self.target(index).ok_or_else(|| ImportError::UnsupportedType(String::from("void")))?;
+                let name = die.name().map(String::from).unwrap_or(name);
+
+                // only the outermost typedef of a base type is kept, as in `uint32_t` over `__uint32_t`
+                let mut base = target;
+                for _ in 0..MAX_CHAIN {
+                    match self.unqualified(base).map(|b| (b, self.die(b).tag)) {
+                        Some((b, TAG_TYPEDEF)) => base = self.target(b).unwrap_or(b),
+                        _ => break
+                    }
+                }
+                let resolved = match self.unqualified(base).map(|b| self.die(b).tag) {
+                    Some(TAG_BASE) => self.resolve(base)?,
+                    _ => self.resolve(target)?
+                };
+                match self.types.get(&resolved) {
+                    Some(Type::Atomic(a)) if resolved != name => {
+                        let alias = Atomic::with_kind(a.representation, a.alignment, a.kind);
+                        self.define(&name, Type::Atomic(alias))
+                    },
+                    _ => Ok(resolved)
+                }
+            },
+            TAG_ENUMERATION => {
+                let variants = die.children.iter().map(|c| self.die(*c)).filter(|d| d.tag == TAG_ENUMERATOR).filter_map(|d| d.name().map(String::from)).collect();
+                let repr = match die.unsigned(AT_BYTE_SIZE) {
+                    Some(4) | None => EnumRepr::Int,
+                    Some(_) => EnumRepr::Minimal
+                };
+                self.define(&name, Type::Enum(Enum::new(variants, repr)))
+            },
+            TAG_STRUCTURE | TAG_CLASS | TAG_UNION => self.record(index),
+            TAG_ARRAY => {
+                let target = self.target(index).ok_or_else(|| ImportError::UnsupportedType(name.clone()))?;
+                let element = self.resolve(target)?;
+                let size = self.types.size_of(&element, Strategy::C).saturating_mul(self.array_count(index) as usize);
+                let align = self.types.align_of(&element, Strategy::C);
+                if size == 0 {
+                    return Err(ImportError::UnsupportedType(format!("{}[]", element)))
+                }
+                self.define(&name, Type::Atomic(Atomic::new(size, align)))
+            },
+            _ => Err(ImportError::UnsupportedType(name))
+        }
+    }
+
+    /// Import a struct or union, and every type its members use
+    fn record(&mut self, index : usize) -> Result<Name, ImportError> {
+        let die = self.die(index);
+        let name = self.record_name(index).ok_or_else(|| ImportError::UnsupportedType(String::from("anonimo")))?;
+        if self.types.get(&name).is_some() || self.visiting.contains(&index) {
+            return Ok(name)
+        }
+        if die.flag(AT_DECLARATION) {
+            return Err(ImportError::UnknownType(name))
+        }
+
+        self.visiting.insert(index);
+        let members = self.members(index, &name);
+        self.visiting.remove(&index);
+        let (members, fields, offsets) = members?;
+
+        let t = match die.tag {
+            TAG_UNION => Type::Union(Union::new(members)),
+            _ => {
+                let mut attributes = vec![];
+                let misaligned = members.iter().zip(&offsets).any(|(m, offset)| {
+                    offset.is_some_and(|o| o % self.types.align_of(m, Strategy::C) != 0)
+                });
+                if misaligned {
+                    attributes.push(Attribute { key: String::from("packed"), value: None });
+                }
+                if let Some(align) = die.unsigned(AT_ALIGNMENT) {
+                    attributes.push(Attribute { key: String::from("aligned"), value: Some(align.to_string()) });
+                }
+                self.compiled.push(CompiledLayout { name: name.clone(), size: die.unsigned(AT_BYTE_SIZE).unwrap_or(0) as usize, offsets });
+                Type::Struct(Struct::with_fields(members, fields).with_attributes(attributes))
+            }
+        };
+        self.define(&name, t)
+    }
+
+    /// Import the members of a record
+    /// ## Return
+    /// Type, field and offset in the binary of every member, none for bit-fields
+    fn members(&mut self, index : usize, name : &str) -> Result<Members, ImportError> {
+        let mut members = TypeList::new();
+        let mut fields = vec![];
+        let mut offsets = vec![];
+
+        for child in &self.die(index).children {
+            let die = self.die(*child);
+            if !matches!(die.tag, TAG_MEMBER | TAG_INHERITANCE) || die.flag(AT_DECLARATION) {
+                continue
+            }
+            let target = self.target(*child).ok_or_else(|| ImportError::UnsupportedType(String::from("void")))?;
+            let field = match (die.name(), die.tag) {
+                (Some(n), _) => n.to_string(),
+                (None, TAG_INHERITANCE) => format!("base_{}", self.name_of(target, 0)),
+                (None, _) => Field::default_name(members.len())
+            };
+
+            // unnamed records are named after the member holding them
+            if let Some(nested) = self.unqualified(target) {
+                if matches!(self.die(nested).tag, TAG_STRUCTURE | TAG_UNION | TAG_CLASS | TAG_ENUMERATION) && self.record_name(nested).is_none() {
+                    self.names.insert(nested, format!("{}_{}", name, field));
+                }
+            }
+            members.push(self.resolve(target)?);
+
+            let mut field = Field::new(field);
+            let offset = match die.attr(AT_MEMBER_LOCATION) {
+                Some(Attr::Block(expr)) if expr.first() == Some(&0x23) => Reader::new(expr, 1).uleb().ok(),    // DW_OP_plus_uconst
+                _ => die.unsigned(AT_MEMBER_LOCATION)
+            };
+            match die.unsigned(AT_BIT_SIZE) {
+                Some(bits) => {
+                    field.attributes.push(Attribute { key: String::from(BITS), value: Some(bits.to_string()) });
+                    offsets.push(None);
+                },
+                None => offsets.push(Some(offset.unwrap_or(0) as usize))
+            }
+            if let Some(align) = die.unsigned(AT_ALIGNMENT) {
+                field.attributes.push(Attribute { key: String::from("aligned"), value: Some(align.to_string()) });
+            }
+            fields.push(field);
+        }
+
+        Ok((members, fields, offsets))
+    }
+}
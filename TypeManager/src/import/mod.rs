@@ -0,0 +1,578 @@
+/*
+    Import: read type definitions written by other tools. Clang dumps what it
+    parses as JSON (`clang -Xclang -ast-dump=json -fsyntax-only archivo.c`), so
+    records and enums declared in the main file are imported from there along
+    with every type they use. C builtin types take their x86-64 sizes, arrays
+    become atomics with the size and alignment of the whole array, and pointers
+    become thin pointers. Simple C++ classes are imported too: methods and
+    access specifiers don't change the layout, a polymorphic class starts with
+    a `vptr` and a single base class goes first, inlined when it isn't POD so
+    members can use its tail padding as Itanium C++ compilers do
+*/
+use std::collections::{HashMap, HashSet};
+
+use crate::json::{self, Value};
+use crate::type_system::*;
+
+mod dwarf;
+mod proto;
+pub use dwarf::*;
+pub use proto::*;
+
+/// C builtin types as Clang writes them: name, size, alignment and value kind, for x86-64
+pub const C_BUILTINS : [(&str, usize, usize, ValueKind); 22] = [
+    ("char",               1,  1,  ValueKind::Char),
+    ("signed char",        1,  1,  ValueKind::Signed),
+    ("unsigned char",      1,  1,  ValueKind::Unsigned),
+    ("short",              2,  2,  ValueKind::Signed),
+    ("unsigned short",     2,  2,  ValueKind::Unsigned),
+    ("int",                4,  4,  ValueKind::Signed),
+    ("unsigned int",       4,  4,  ValueKind::Unsigned),
+    ("long",               8,  8,  ValueKind::Signed),
+    ("unsigned long",      8,  8,  ValueKind::Unsigned),
+    ("long long",          8,  8,  ValueKind::Signed),
+    ("unsigned long long", 8,  8,  ValueKind::Unsigned),
+    ("__int128",           16, 16, ValueKind::Signed),
+    ("unsigned __int128",  16, 16, ValueKind::Unsigned),
+    ("float",              4,  4,  ValueKind::Float),
+    ("double",             8,  8,  ValueKind::Float),
+    ("long double",        16, 16, ValueKind::Float),
+    ("_Bool",              1,  1,  ValueKind::Bool),
+    ("bool",               1,  1,  ValueKind::Bool),
+    ("wchar_t",            4,  4,  ValueKind::Char),
+    ("char8_t",            1,  1,  ValueKind::Char),
+    ("char16_t",           2,  2,  ValueKind::Char),
+    ("char32_t",           4,  4,  ValueKind::Char)
+];
+
+/// Record tags, a C++ type may be named without them
+const TAGS : [&str; 4] = ["struct", "class", "union", "enum"];
+
+/// Field holding the address of the virtual table in polymorphic classes
+pub const VPTR_FIELD : &str = "vptr";
+
+/// Alignment of `__attribute__((aligned))` without a value, the biggest one on x86-64
+const DEFAULT_ALIGNED : usize = 16;
+
+/// Words qualifying a type without changing its layout
+const QUALIFIERS : [&str; 5] = ["const", "volatile", "restrict", "__restrict", "_Atomic"];
+
+/// Anything that can go wrong importing
+#[derive(Debug, PartialEq)]
+pub enum ImportError {
+    Json(String),               // the document is not valid JSON
+    NotAnAst,                   // valid JSON, but not a Clang AST dump
+    UnsupportedType(String),    // C type without a layout here, such as `void` or a flexible array
+    UnknownType(String),        // type used but never defined
+    Invalid(Name, TypeError),   // an imported type was rejected
+    Unsupported(Name, &'static str),    // a C++ class uses something without a layout here
+    Object(String),             // the binary can't be read
+    Syntax(usize, String)       // line and problem in a schema
+}
+
+/// Types read from another tool
+#[derive(Debug, Default)]
+pub struct Imported {
+    pub types:    Vec<(Name, Type)>,        // each one after the types it uses
+    pub compiled: Vec<CompiledLayout>,      // layouts a compiler chose, when known
+    pub skipped:  Vec<(Name, ImportError)>, // types that couldn't be imported
+    pub declared: HashMap<Name, Declared>   // where types are declared, when the tool tells
+}
+
+/// File a type is declared in, if it's not the one imported, and the line of its declaration
+pub type Declared = (Option<String>, usize);
+
+/// Layout of a struct as found in a compiled binary
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledLayout {
+    pub name:    Name,
+    pub size:    usize,
+    pub offsets: Vec<Option<usize>>     // every member in declaration order, none for bit-fields
+}
+
+impl ImportError {
+
+    /// Human readable description of this error
+    pub fn display(&self) -> String {
+        match self {
+            ImportError::Json(e) => format!("JSON inválido: {}", e),
+            ImportError::NotAnAst => String::from("no es un volcado del AST de Clang, se esperaba un 'TranslationUnitDecl'"),
+            ImportError::UnsupportedType(t) => format!("el tipo '{}' no se puede representar", t),
+            ImportError::UnknownType(t) => format!("el tipo '{}' no está definido", t),
+            ImportError::Invalid(name, e) => format!("'{}': {}", name, e.display()),
+            ImportError::Unsupported(name, feature) => format!("'{}' usa {}, que no está soportada", name, feature),
+            ImportError::Object(e) => format!("binario inválido: {}", e),
+            ImportError::Syntax(line, e) => format!("línea {}: {}", line, e)
+        }
+    }
+}
+
+/// Read the types declared in a Clang AST dump
+/// ## Params
+/// * `text` - output of `clang -Xclang -ast-dump=json`
+/// ---
+/// ## Return
+/// Every type imported, each one after the types it uses, or the first problem found
+pub fn clang_ast(text : &str) -> Result<Vec<(Name, Type)>, ImportError> {
+    clang_ast_declared(text).map(|imported| imported.types)
+}
+
+/// Read the types declared in a Clang AST dump along with the file and line of the
+/// declaration of every struct, union, enum and typedef, see `clang_ast`
+pub fn clang_ast_declared(text : &str) -> Result<Imported, ImportError> {
+    let root = json::parse(text).map_err(ImportError::Json)?;
+    if root.get("kind").and_then(Value::as_str) != Some("TranslationUnitDecl") {
+        return Err(ImportError::NotAnAst)
+    }
+
+    let mut headers = HashSet::new();
+    included(&root, &mut false, &mut headers);
+    let mut decls = vec![];
+    flatten(inner(&root), &mut decls);
+
+    let mut importer = ClangImporter::default();
+    locations(&root, &mut (String::new(), 0), &mut importer.locations);
+    for (i, decl) in decls.iter().enumerate() {
+        importer.index(decl);
+
+        // a typedef names the unnamed record right before it
+        if kind(decl) == "TypedefDecl" && i > 0 && is_unnamed_record(decls[i - 1]) {
+            if let Some(name) = name(decl) {
+                importer.anonymous.insert(name.to_string(), decls[i - 1]);
+            }
+        }
+    }
+
+    let from_main_file = |decl : &Value| {
+        let id = decl.get("id").and_then(Value::as_str).unwrap_or("");
+        !headers.contains(id) && !decl.get("isImplicit").is_some_and(Value::is_true)
+    };
+    for decl in decls.into_iter().filter(|d| from_main_file(d)) {
+        match (kind(decl), name(decl)) {
+            ("RecordDecl", Some(n)) | ("CXXRecordDecl", Some(n)) if decl.get("completeDefinition").is_some_and(Value::is_true) => {
+                importer.record(n, decl)?;
+            },
+            ("EnumDecl", Some(n)) => {
+                importer.enumeration(n, decl)?;
+            },
+            ("TypedefDecl", Some(n)) => {
+                importer.resolve(n)?;
+            },
+            _ => {}
+        }
+    }
+
+    let types = importer.order.iter().filter_map(|n| importer.types.get(n).map(|t| (n.clone(), t.clone()))).collect();
+    Ok(Imported { types, declared: importer.declared, ..Default::default() })
+}
+
+/// State while importing a translation unit
+#[derive(Default)]
+struct ClangImporter<'a> {
+    records:   HashMap<String, &'a Value>,  // complete records and enums, by `struct x`, `class x`, `union x` or `enum x`
+    typedefs:  HashMap<String, &'a Value>,
+    anonymous: HashMap<String, &'a Value>,  // unnamed records, by the typedef naming them
+    types:     TypeManager,                 // types imported so far, to size arrays
+    order:     Vec<Name>,
+    visiting:  HashSet<Name>,               // records being imported, so cycles end
+    locations: HashMap<String, (String, usize)>,    // file and line of every declaration, by id
+    declared:  HashMap<Name, Declared>      // where imported types are declared
+}
+
+impl<'a> ClangImporter<'a> {
+
+    /// Remember a declaration and the records declared inside it
+    fn index(&mut self, decl : &'a Value) {
+        match (kind(decl), name(decl)) {
+            ("RecordDecl", Some(n)) | ("CXXRecordDecl", Some(n)) if decl.get("completeDefinition").is_some_and(Value::is_true) => {
+                let tag = decl.get("tagUsed").and_then(Value::as_str).unwrap_or("struct");
+                self.records.insert(format!("{} {}", tag, n), decl);
+            },
+            ("EnumDecl", Some(n)) => { self.records.insert(format!("enum {}", n), decl); },
+            ("TypedefDecl", Some(n)) => { self.typedefs.insert(n.to_string(), decl); },
+            _ => {}
+        }
+
+        if is_record(decl) {
+            for nested in inner(decl) {
+                self.index(nested);
+            }
+        }
+    }
+
+    /// Add a type to the imported ones
+    fn define(&mut self, name : &str, t : Type) -> Result<Name, ImportError> {
+        self.types.add(name.to_string(), t).map_err(|e| ImportError::Invalid(name.to_string(), e))?;
+        self.order.push(name.to_string());
+        Ok(name.to_string())
+    }
+
+    /// Remember where an imported type is declared
+    fn locate(&mut self, name : &Name, decl : &Value) {
+        let id = decl.get("id").and_then(Value::as_str).unwrap_or("");
+        if let Some((file, line)) = self.locations.get(id) {
+            self.declared.insert(name.clone(), (Some(file.clone()), *line));
+        }
+    }
+
+    /// Import a type written as Clang writes it, such as `const struct nodo *` or `uint8_t[4]`
+    /// ## Return
+    /// Name of the imported type
+    fn resolve(&mut self, written : &str) -> Result<Name, ImportError> {
+        let spelled = unqualified(written);
+
+        // pointers to functions and arrays, whatever they point to
+        if spelled.contains("(*") {
+            return self.pointer("ptr_void", "void")
+        }
+
+        if let Some(open) = spelled.strip_suffix(']').and_then(|s| s.rfind('[')) {
+            let element = spelled[..open].trim();
+            let count : usize = spelled[open + 1..spelled.len() - 1]
+                                    .trim()
+                                    .parse()
+                                    .map_err(|_| ImportError::UnsupportedType(spelled.clone()))?;
+            let element = self.resolve(element)?;
+            let name = format!("{}_x{}", element, count);
+            if self.types.get(&name).is_none() {
+                let size = self.types.size_of(&element, Strategy::C).saturating_mul(count);
+                let align = self.types.align_of(&element, Strategy::C);
+                if size == 0 {
+                    return Err(ImportError::UnsupportedType(spelled))
+                }
+                self.define(&name, Type::Atomic(Atomic::new(size, align)))?;
+            }
+            return Ok(name)
+        }
+
+        if let Some(target) = spelled.strip_suffix('*') {
+            let target = target.trim();
+            return self.pointer(&type_name(&spelled), &type_name(target))
+        }
+
+        if let Some(tag) = TAGS.iter().find(|tag| spelled.starts_with(&format!("{} ", tag))) {
+            let name = spelled[tag.len() + 1..].to_string();
+            return self.tagged(&name).unwrap_or(Err(ImportError::UnknownType(spelled)))
+        }
+
+        if self.types.get(&spelled).is_some() {
+            return Ok(spelled)
+        }
+
+        if let Some(record) = self.anonymous.get(&spelled).copied() {
+            return self.record(&spelled, record)
+        }
+
+        if self.typedefs.contains_key(&spelled) {
+            // follow typedefs to the type they name, only the outermost one is kept
+            let mut underlying = spelled.clone();
+            while let Some(decl) = self.typedefs.get(&underlying) {
+                underlying = unqualified(decl.get("type").and_then(|t| t.get("qualType")).and_then(Value::as_str).unwrap_or("void"));
+            }
+            let target = self.resolve(&underlying)?;
+            return match self.types.get(&target) {
+                Some(Type::Atomic(a)) if target != spelled => {
+                    let alias = Atomic::with_kind(a.representation, a.alignment, a.kind);
+                    let name = self.define(&spelled, Type::Atomic(alias))?;
+                    if let Some(decl) = self.typedefs.get(&spelled).copied() {
+                        self.locate(&name, decl);
+                    }
+                    Ok(name)
+                },
+                _ => Ok(target)
+            }
+        }
+
+        if let Some(imported) = self.tagged(&spelled) {
+            return imported
+        }
+
+        match C_BUILTINS.iter().find(|(n, ..)| *n == spelled) {
+            Some(_) if self.types.get(&type_name(&spelled)).is_some() => Ok(type_name(&spelled)),
+            Some((_, size, align, kind)) => self.define(&type_name(&spelled), Type::Atomic(Atomic::with_kind(*size, *align, Some(*kind)))),
+            None if spelled == "void" => Err(ImportError::UnsupportedType(spelled)),
+            None => Err(ImportError::UnknownType(spelled))
+        }
+    }
+
+    /// Import a record or enum named without its tag
+    /// ## Return
+    /// Name of the imported type, or none if there's no such record or enum
+    fn tagged(&mut self, name : &str) -> Option<Result<Name, ImportError>> {
+        if self.types.get(&name.to_string()).is_some() {
+            return Some(Ok(name.to_string()))
+        }
+        if let Some(decl) = self.records.get(&format!("enum {}", name)).copied() {
+            return Some(self.enumeration(name, decl))
+        }
+        self.declaration(name).map(|decl| self.record(name, decl))
+    }
+
+    /// Declaration of a struct, class or union, named without its tag
+    fn declaration(&self, name : &str) -> Option<&'a Value> {
+        TAGS[..3]
+            .iter()
+            .find_map(|tag| self.records.get(&format!("{} {}", tag, name)).copied())
+            .or_else(|| self.anonymous.get(name).copied())
+    }
+
+    /// Import a thin pointer, its target doesn't need to exist
+    fn pointer(&mut self, name : &str, target : &str) -> Result<Name, ImportError> {
+        match self.types.get(&name.to_string()) {
+            Some(_) => Ok(name.to_string()),
+            None => self.define(name, Type::Pointer(Pointer::new(target.to_string(), PointerKind::Thin)))
+        }
+    }
+
+    /// Import an enum, every enum is an `int`
+    fn enumeration(&mut self, typename : &str, decl : &Value) -> Result<Name, ImportError> {
+        if self.types.get(&typename.to_string()).is_some() {
+            return Ok(typename.to_string())
+        }
+
+        let variants = inner(decl)
+                        .iter()
+                        .filter(|d| kind(d) == "EnumConstantDecl")
+                        .filter_map(|d| name(d).map(String::from))
+                        .collect();
+        let name = self.define(typename, Type::Enum(Enum::new(variants, EnumRepr::Int)))?;
+        self.locate(&name, decl);
+        Ok(name)
+    }
+
+    /// Import a struct, class or union with the given name, and every type its members use
+    fn record(&mut self, typename : &str, decl : &'a Value) -> Result<Name, ImportError> {
+        if self.types.get(&typename.to_string()).is_some() || !self.visiting.insert(typename.to_string()) {
+            return Ok(typename.to_string())
+        }
+
+        let mut members = TypeList::new();
+        let mut fields = vec![];
+        let mut attributes = vec![];
+        let mut unnamed : Option<&'a Value> = None;     // nested record waiting for the member using it
+
+        let bases = decl.get("bases").and_then(Value::as_array).unwrap_or(&[]);
+        if bases.iter().any(|b| b.get("isVirtual").is_some_and(Value::is_true)) {
+            return Err(ImportError::Unsupported(typename.to_string(), "herencia virtual"))
+        }
+        if bases.len() > 1 {
+            return Err(ImportError::Unsupported(typename.to_string(), "herencia múltiple"))
+        }
+        let base = match bases.first() {
+            Some(b) => {
+                let written = b.get("type").and_then(|t| t.get("qualType")).and_then(Value::as_str).unwrap_or("void");
+                let name = self.resolve(written)?;
+                Some((self.declaration(&name), name))
+            },
+            None => None
+        };
+
+        // the virtual table pointer goes first, unless the base already has one
+        if flag(decl, "isPolymorphic") && !base.as_ref().is_some_and(|(d, _)| d.is_some_and(|d| flag(d, "isPolymorphic"))) {
+            members.push(self.pointer("ptr_vtable", "vtable")?);
+            fields.push(Field::new(VPTR_FIELD.to_string()));
+        }
+        match base {
+            Some((Some(d), _)) if flag(d, "isEmpty") => {},     // empty base optimization
+            Some((Some(d), name)) if !flag(d, "isPOD") => {
+                if let Some(Type::Struct(s)) = self.types.get(&name) {
+                    members.extend(s.members.iter().cloned());
+                    fields.extend(s.fields.iter().cloned());
+                }
+            },
+            Some((_, name)) => {
+                fields.push(Field::new(format!("base_{}", name)));
+                members.push(name);
+            },
+            None => {}
+        }
+        let inherited = fields.len();
+
+        for item in inner(decl) {
+            match kind(item) {
+                "PackedAttr"  => attributes.push(Attribute { key: String::from("packed"), value: None }),
+                "AlignedAttr" => attributes.push(aligned(item)),
+                "RecordDecl" | "CXXRecordDecl" if is_unnamed_record(item) => unnamed = Some(item),
+                "FieldDecl" => {
+                    let field = name(item).map(String::from).unwrap_or_else(|| Field::default_name(members.len()));
+                    if let Some(shadowed) = fields[..inherited].iter_mut().find(|f| f.name == field) {
+                        shadowed.name = format!("{}_{}", base_name(bases).unwrap_or_default(), field);
+                    }
+                    let written = item.get("type").and_then(|t| t.get("qualType")).and_then(Value::as_str).unwrap_or("void");
+                    let member = match unnamed.take() {
+                        Some(nested) if written.contains("(unnamed") || written.contains("(anonymous") => {
+                            self.record(&format!("{}_{}", typename, field), nested)?
+                        },
+                        _ => self.resolve(written)?
+                    };
+
+                    let mut field = Field::new(field);
+                    if item.get("isBitfield").is_some_and(Value::is_true) {
+                        let width = inner(item).first().and_then(|w| w.get("value")).and_then(Value::as_str).unwrap_or("0");
+                        field.attributes.push(Attribute { key: String::from(BITS), value: Some(width.to_string()) });
+                    }
+                    field.attributes.extend(inner(item).iter().filter(|a| kind(a) == "AlignedAttr").map(aligned));
+                    members.push(member);
+                    fields.push(field);
+                },
+                _ => {}
+            }
+        }
+
+        self.visiting.remove(typename);
+        let t = match decl.get("tagUsed").and_then(Value::as_str) {
+            Some("union") => Type::Union(Union::new(members)),
+            _ if members.is_empty() && kind(decl) == "CXXRecordDecl" => Type::Atomic(Atomic::new(1, 1)),    // empty classes take a byte
+            _ => Type::Struct(Struct::with_fields(members, fields).with_attributes(attributes))
+        };
+        let name = self.define(typename, t)?;
+        self.locate(&name, decl);
+        Ok(name)
+    }
+}
+
+/// Declarations inside a node
+fn inner(node : &Value) -> &[Value] {
+    node.get("inner").and_then(Value::as_array).unwrap_or(&[])
+}
+
+/// Kind of a node, such as `RecordDecl`
+fn kind(node : &Value) -> &str {
+    node.get("kind").and_then(Value::as_str).unwrap_or("")
+}
+
+/// Name of a declaration, if it has one
+fn name(node : &Value) -> Option<&str> {
+    node.get("name").and_then(Value::as_str).filter(|n| !n.is_empty())
+}
+
+/// Tells if a node is a complete record without a name
+fn is_unnamed_record(node : &Value) -> bool {
+    is_record(node) && name(node).is_none() && node.get("completeDefinition").is_some_and(Value::is_true)
+}
+
+/// Tells if a node is a C struct or union, or a C++ class
+fn is_record(node : &Value) -> bool {
+    matches!(kind(node), "RecordDecl" | "CXXRecordDecl")
+}
+
+/// Tells if a C++ class has some property, such as `isPOD`
+fn flag(decl : &Value, property : &str) -> bool {
+    decl.get("definitionData").and_then(|d| d.get(property)).is_some_and(Value::is_true)
+}
+
+/// Name of the base class, as imported
+fn base_name(bases : &[Value]) -> Option<Name> {
+    let written = bases.first()?.get("type")?.get("qualType")?.as_str()?;
+    Some(type_name(&unqualified(written)))
+}
+
+/// Declarations at the top level, and inside namespaces and `extern "C"` blocks
+fn flatten<'a>(decls : &'a [Value], flat : &mut Vec<&'a Value>) {
+    for decl in decls {
+        match kind(decl) {
+            "NamespaceDecl" | "LinkageSpecDecl" => flatten(inner(decl), flat),
+            _ => flat.push(decl)
+        }
+    }
+}
+
+/// Find declarations from headers included by the main file. Clang only writes the file
+/// of a location when it changes, so every location is read in order
+/// ## Params
+/// * `node` - node to read, with every node inside it
+/// * `header` - tells if the last location read was in a header
+/// * `headers` - ids of declarations in headers
+fn included(node : &Value, header : &mut bool, headers : &mut HashSet<String>) {
+    match node {
+        Value::Object(pairs) => {
+            if node.get("file").is_some() {
+                *header = node.get("includedFrom").is_some();
+            }
+            for (key, value) in pairs {
+                if key == "includedFrom" {
+                    continue
+                }
+                included(value, header, headers);
+                if key == "loc" && *header {
+                    if let Some(id) = node.get("id").and_then(Value::as_str) {
+                        headers.insert(id.to_string());
+                    }
+                }
+            }
+        },
+        Value::Array(values) => values.iter().for_each(|v| included(v, header, headers)),
+        _ => {}
+    }
+}
+
+/// Find the file and line of every declaration. Clang only writes the file and line of
+/// a location when they change, so every location is read in order, as in `included`
+/// ## Params
+/// * `node` - node to read, with every node inside it
+/// * `last` - file and line of the last location read
+/// * `found` - file and line of every declaration read, by id
+fn locations(node : &Value, last : &mut (String, usize), found : &mut HashMap<String, (String, usize)>) {
+    match node {
+        Value::Object(pairs) => {
+            if node.get("line").is_some() || node.get("file").is_some() {
+                if let Some(file) = node.get("file").and_then(Value::as_str) {
+                    last.0 = file.to_string();
+                }
+                if let Some(line) = node.get("line").and_then(Value::as_uint) {
+                    last.1 = line;
+                }
+            }
+            for (key, value) in pairs {
+                if key == "includedFrom" {
+                    continue
+                }
+                locations(value, last, found);
+                if let ("loc", Some(id)) = (key.as_str(), node.get("id").and_then(Value::as_str)) {
+                    found.insert(id.to_string(), last.clone());
+                }
+            }
+        },
+        Value::Array(values) => values.iter().for_each(|v| locations(v, last, found)),
+        _ => {}
+    }
+}
+
+/// `@aligned` attribute for an `AlignedAttr` node
+fn aligned(node : &Value) -> Attribute {
+    let align = inner(node)
+                    .first()
+                    .and_then(|e| e.get("value"))
+                    .and_then(Value::as_str)
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_ALIGNED);
+    Attribute { key: String::from("aligned"), value: Some(align.to_string()) }
+}
+
+/// Type as written without qualifiers and with single spaces, such as `char *`
+fn unqualified(written : &str) -> String {
+    written
+        .replace("&&", "*")     // C++ references are stored as pointers
+        .replace('&', "*")
+        .replace('*', " * ")
+        .split_whitespace()
+        .filter(|w| !QUALIFIERS.contains(w))
+        .map(|w| match w.contains('<') {
+            true  => w,
+            false => w.rsplit("::").next().unwrap_or(w)     // drop namespaces
+        })
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .replace(" *", "*")
+        .replace("* ", "*")
+        .replace(" [", "[")
+}
+
+/// Name for a type written in C: spaces become `_`, tags are dropped and every `*` adds a `ptr_` prefix
+fn type_name(spelled : &str) -> Name {
+    let base = spelled.trim_end_matches('*').trim();
+    let base = ["struct ", "union ", "enum "].iter().fold(base, |b, tag| b.strip_prefix(tag).unwrap_or(b));
+    let stars = spelled.len() - spelled.trim_end_matches('*').len();
+
+    format!("{}{}", "ptr_".repeat(stars), base.replace(' ', "_"))
+}
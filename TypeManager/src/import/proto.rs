@@ -0,0 +1,465 @@
+/*
+    Protocol Buffers: messages in a `.proto` schema become structs with a member
+    per field, in declaration order, without the headers each language's runtime
+    adds. Scalars become atomics (or any type the user maps them to), strings and
+    bytes become strings, repeated fields and maps become vectors, and message
+    fields become boxes, as generated code keeps them behind a pointer. A `oneof`
+    becomes a tagged union: an enum saying which field is set, then a union of
+    every field. Nested types are named after their parents: `Exterior_Interior`
+*/
+use std::collections::{HashMap, HashSet};
+
+use super::{ImportError, Imported};
+use crate::type_system::*;
+
+/// Scalar types: name, size, alignment and value kind, strings and bytes are not here
+pub const PROTO_SCALARS : [(&str, usize, usize, ValueKind); 13] = [
+    ("double",   8, 8, ValueKind::Float),
+    ("float",    4, 4, ValueKind::Float),
+    ("int32",    4, 4, ValueKind::Signed),
+    ("int64",    8, 8, ValueKind::Signed),
+    ("uint32",   4, 4, ValueKind::Unsigned),
+    ("uint64",   8, 8, ValueKind::Unsigned),
+    ("sint32",   4, 4, ValueKind::Signed),
+    ("sint64",   8, 8, ValueKind::Signed),
+    ("fixed32",  4, 4, ValueKind::Unsigned),
+    ("fixed64",  8, 8, ValueKind::Unsigned),
+    ("sfixed32", 4, 4, ValueKind::Signed),
+    ("sfixed64", 8, 8, ValueKind::Signed),
+    ("bool",     1, 1, ValueKind::Bool)
+];
+
+/// Scalars stored as strings
+const PROTO_STRINGS : [&str; 2] = ["string", "bytes"];
+
+/// Read the messages and enums of a Protocol Buffers schema
+/// ## Params
+/// * `text` - contents of a `.proto` file
+/// * `mapping` - types to use for some scalars instead of the default ones, such as `("string", "texto")`
+/// ---
+/// ## Return
+/// Every type imported, each one after the types it uses, or the first problem found
+pub fn proto(text : &str, mapping : &[(String, Name)]) -> Result<Imported, ImportError> {
+    if let Some((scalar, _)) = mapping.iter().find(|(s, _)| !is_scalar(s)) {
+        return Err(ImportError::UnknownType(scalar.clone()))
+    }
+
+    let mut parser = Parser { tokens: tokenize(text)?, pos: 0, package: vec![], messages: vec![], enums: vec![] };
+    parser.file()?;
+
+    let mut importer = ProtoImporter {
+        known:   HashMap::new(),
+        package: parser.package.clone(),
+        mapping: mapping.iter().cloned().collect(),
+        defined: HashSet::new(),
+        types:   vec![]
+    };
+    for e in &parser.enums {
+        importer.known.insert(e.path.join("."), false);
+    }
+    for m in &parser.messages {
+        importer.known.insert(m.path.join("."), true);
+    }
+
+    for e in &parser.enums {
+        importer.define(e.path.join("_"), Type::Enum(Enum::new(e.variants.clone(), EnumRepr::Int)));
+    }
+    for m in &parser.messages {
+        importer.message(m);
+    }
+
+    let declared = parser.enums
+                        .iter()
+                        .map(|e| (e.path.join("_"), e.line))
+                        .chain(parser.messages.iter().map(|m| (m.path.join("_"), m.line)))
+                        .map(|(name, line)| (name, (None, line)))
+                        .collect();
+    Ok(Imported { types: importer.types, declared, ..Default::default() })
+}
+
+/// Tells if a type name is a scalar
+fn is_scalar(name : &str) -> bool {
+    PROTO_SCALARS.iter().any(|(s, ..)| *s == name) || PROTO_STRINGS.contains(&name)
+}
+
+/// A word of the schema and the line it's in
+struct Token {
+    text: String,
+    line: usize
+}
+
+/// Split a schema in words, strings and symbols, skipping comments
+fn tokenize(text : &str) -> Result<Vec<Token>, ImportError> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => {},
+            '/' if chars.peek() == Some(&'/') => {
+                while chars.peek().is_some_and(|c| *c != '\n') {
+                    chars.next();
+                }
+            },
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if last == '*' => break,
+                        Some(c) => { line += (c == '\n') as usize; last = c },
+                        None => return Err(ImportError::Syntax(line, String::from("comentario sin terminar")))
+                    }
+                }
+            },
+            '"' | '\'' => {
+                let mut text = String::from(c);
+                loop {
+                    match chars.next() {
+                        Some('\\') => { text.push('\\'); text.extend(chars.next()) },
+                        Some(q) if q == c => { text.push(q); break },
+                        Some('\n') | None => return Err(ImportError::Syntax(line, String::from("texto sin terminar"))),
+                        Some(other) => text.push(other)
+                    }
+                }
+                tokens.push(Token { text, line });
+            },
+            c if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' || c == '+' => {
+                let mut text = String::from(c);
+                while let Some(next) = chars.peek().copied().filter(|n| n.is_alphanumeric() || *n == '_' || *n == '.') {
+                    text.push(next);
+                    chars.next();
+                }
+                tokens.push(Token { text, line });
+            },
+            symbol => tokens.push(Token { text: symbol.to_string(), line })
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// A field of a message
+enum Item {
+    Field { repeated: bool, typename: String, name: String },
+    Map { key: String, value: String, name: String },
+    Oneof { name: String, fields: Vec<(String, String)> }   // type and name of every field
+}
+
+/// A message, named by its path from the package: `["Exterior", "Interior"]`
+struct Message {
+    path:  Vec<String>,
+    items: Vec<Item>,
+    line:  usize        // where it's declared
+}
+
+struct ProtoEnum {
+    path:     Vec<String>,
+    variants: Vec<Name>,
+    line:     usize
+}
+
+/// Reads messages and enums, anything else is skipped
+struct Parser {
+    tokens:   Vec<Token>,
+    pos:      usize,
+    package:  Vec<String>,
+    messages: Vec<Message>,     // nested messages go before their parents
+    enums:    Vec<ProtoEnum>
+}
+
+impl Parser {
+
+    /// Line of the last token read
+    fn line(&self) -> usize {
+        self.tokens.get(self.pos.saturating_sub(1)).or_else(|| self.tokens.last()).map_or(1, |t| t.line)
+    }
+
+    fn error<T>(&self, reason : String) -> Result<T, ImportError> {
+        Err(ImportError::Syntax(self.line(), reason))
+    }
+
+    fn next(&mut self) -> Result<String, ImportError> {
+        match self.tokens.get(self.pos) {
+            Some(t) => {
+                self.pos += 1;
+                Ok(t.text.clone())
+            },
+            None => self.error(String::from("el archivo termina antes de tiempo"))
+        }
+    }
+
+    fn expect(&mut self, expected : &str) -> Result<(), ImportError> {
+        match self.next()? {
+            t if t == expected => Ok(()),
+            t => self.error(format!("se esperaba '{}' y se encontró '{}'", expected, t))
+        }
+    }
+
+    /// A name: a word without dots or symbols
+    fn name(&mut self) -> Result<String, ImportError> {
+        match self.next()? {
+            t if t.chars().all(|c| c.is_alphanumeric() || c == '_') && !t.starts_with(|c : char| c.is_ascii_digit()) => Ok(t),
+            t => self.error(format!("'{}' no es un nombre válido", t))
+        }
+    }
+
+    /// Skip up to the `;` ending a statement, or the `}` ending a block
+    fn skip_statement(&mut self) -> Result<(), ImportError> {
+        let mut depth = 0;
+        loop {
+            match self.next()?.as_str() {
+                "{" => depth += 1,
+                "}" if depth <= 1 => return Ok(()),
+                "}" => depth -= 1,
+                ";" if depth == 0 => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Skip `[opciones]` after a field, if there are any
+    fn skip_options(&mut self) -> Result<(), ImportError> {
+        if self.tokens.get(self.pos).is_some_and(|t| t.text == "[") {
+            while self.next()? != "]" {}
+        }
+        Ok(())
+    }
+
+    /// Read `= número [opciones];` ending a field
+    fn field_end(&mut self) -> Result<(), ImportError> {
+        self.expect("=")?;
+        self.next()?;
+        self.skip_options()?;
+        self.expect(";")
+    }
+
+    fn file(&mut self) -> Result<(), ImportError> {
+        while self.pos < self.tokens.len() {
+            match self.next()?.as_str() {
+                "message" => self.message(&[])?,
+                "enum" => self.enumeration(&[])?,
+                "package" => {
+                    self.package = self.next()?.split('.').map(String::from).collect();
+                    self.expect(";")?;
+                },
+                ";" => {},
+                _ => self.skip_statement()?
+            }
+        }
+        Ok(())
+    }
+
+    fn enumeration(&mut self, scope : &[String]) -> Result<(), ImportError> {
+        let mut path = scope.to_vec();
+        path.push(self.name()?);
+        let line = self.line();
+        self.expect("{")?;
+
+        let mut variants = vec![];
+        loop {
+            match self.next()?.as_str() {
+                "}" => break,
+                ";" => {},
+                "option" | "reserved" => self.skip_statement()?,
+                variant => {
+                    variants.push(variant.to_string());
+                    self.field_end()?;
+                }
+            }
+        }
+
+        self.enums.push(ProtoEnum { path, variants, line });
+        Ok(())
+    }
+
+    fn message(&mut self, scope : &[String]) -> Result<(), ImportError> {
+        let mut path = scope.to_vec();
+        path.push(self.name()?);
+        let line = self.line();
+        self.expect("{")?;
+
+        let mut items = vec![];
+        loop {
+            let word = self.next()?;
+            match word.as_str() {
+                "}" => break,
+                ";" => {},
+                "message" => self.message(&path)?,
+                "enum" => self.enumeration(&path)?,
+                "option" | "reserved" | "extensions" | "extend" => self.skip_statement()?,
+                "oneof" => {
+                    let name = self.name()?;
+                    self.expect("{")?;
+                    let mut fields = vec![];
+                    loop {
+                        match self.next()?.as_str() {
+                            "}" => break,
+                            ";" => {},
+                            "option" => self.skip_statement()?,
+                            typename => {
+                                let field = (typename.to_string(), self.name()?);
+                                self.field_end()?;
+                                fields.push(field);
+                            }
+                        }
+                    }
+                    items.push(Item::Oneof { name, fields });
+                },
+                "map" => {
+                    self.expect("<")?;
+                    let key = self.next()?;
+                    self.expect(",")?;
+                    let value = self.next()?;
+                    self.expect(">")?;
+                    let name = self.name()?;
+                    self.field_end()?;
+                    items.push(Item::Map { key, value, name });
+                },
+                _ => {
+                    let (repeated, typename) = match word.as_str() {
+                        "repeated" => (true, self.next()?),
+                        "optional" | "required" => (false, self.next()?),
+                        _ => (false, word)
+                    };
+                    if typename == "group" {
+                        return self.error(String::from("los grupos no están soportados"))
+                    }
+                    let name = self.name()?;
+                    self.field_end()?;
+                    items.push(Item::Field { repeated, typename, name });
+                }
+            }
+        }
+
+        self.messages.push(Message { path, items, line });
+        Ok(())
+    }
+}
+
+/// State while turning messages into types
+struct ProtoImporter {
+    known:   HashMap<String, bool>,     // every message and enum by its dotted path, true for messages
+    package: Vec<String>,
+    mapping: HashMap<String, Name>,
+    defined: HashSet<Name>,
+    types:   Vec<(Name, Type)>
+}
+
+impl ProtoImporter {
+
+    /// Add a type, unless there's one with that name already
+    fn define(&mut self, name : Name, t : Type) -> Name {
+        if self.defined.insert(name.clone()) {
+            self.types.push((name.clone(), t));
+        }
+        name
+    }
+
+    /// Find a message or enum used from a scope, as protoc does: innermost scope first
+    /// ## Return
+    /// Name of the type, and if it's a message. Types not in this file are taken as messages
+    fn lookup(&self, written : &str, scope : &[String]) -> (Name, bool) {
+        let written = written.strip_prefix('.').unwrap_or(written);
+        let relative = written.strip_prefix(&format!("{}.", self.package.join("."))).unwrap_or(written);
+        let found = (0..=scope.len())
+                        .rev()
+                        .map(|i| scope[..i].iter().map(String::as_str).chain(relative.split('.')).collect::<Vec<&str>>())
+                        .find_map(|path| self.known.get(&path.join(".")).map(|message| (path.join("_"), *message)));
+
+        found.unwrap_or_else(|| (written.replace('.', "_"), true))
+    }
+
+    /// Import the type of a field, messages go in boxes
+    /// ## Return
+    /// Name of the type a member holding the field has
+    fn field_type(&mut self, written : &str, scope : &[String]) -> Name {
+        match self.element(written, scope) {
+            (name, true) => self.define(format!("box_{}", name), Type::Container(Container::new(ContainerKind::Box, Some(name), 0, DEFAULT_WIDTH))),
+            (name, false) => name
+        }
+    }
+
+    /// Import the type of a field as stored in vectors, messages by value
+    /// ## Return
+    /// Name of the type, and if it's a message
+    fn element(&mut self, written : &str, scope : &[String]) -> (Name, bool) {
+        if let Some(mapped) = self.mapping.get(written) {
+            return (mapped.clone(), false)
+        }
+        if PROTO_STRINGS.contains(&written) {
+            return (self.define(written.to_string(), Type::Container(Container::new(ContainerKind::String, None, 0, DEFAULT_WIDTH))), false)
+        }
+        if let Some((name, size, align, kind)) = PROTO_SCALARS.iter().find(|(s, ..)| *s == written) {
+            return (self.define(name.to_string(), Type::Atomic(Atomic::with_kind(*size, *align, Some(*kind)))), false)
+        }
+        self.lookup(written, scope)
+    }
+
+    /// Vector of some element
+    fn vector(&mut self, element : Name) -> Name {
+        self.define(format!("vec_{}", element), Type::Container(Container::new(ContainerKind::Vec, Some(element), 0, DEFAULT_WIDTH)))
+    }
+
+    fn message(&mut self, m : &Message) {
+        let name = m.path.join("_");
+        let mut members = TypeList::new();
+        let mut fields = vec![];
+
+        for item in &m.items {
+            match item {
+                Item::Field { repeated, typename, name : field } => {
+                    let member = match repeated {
+                        true  => {
+                            let (element, _) = self.element(typename, &m.path);
+                            self.vector(element)
+                        },
+                        false => self.field_type(typename, &m.path)
+                    };
+                    members.push(member);
+                    fields.push(Field::new(field.clone()));
+                },
+                Item::Map { key, value, name : field } => {
+                    // maps are repeated entries with a key and a value, on the wire too
+                    let entry = format!("{}_{}Entry", name, camel(field));
+                    let entry_members = vec![self.field_type(key, &m.path), self.field_type(value, &m.path)];
+                    let entry_fields = vec![Field::new(String::from("key")), Field::new(String::from("value"))];
+                    self.define(entry.clone(), Type::Struct(Struct::with_fields(entry_members, entry_fields)));
+                    members.push(self.vector(entry));
+                    fields.push(Field::new(field.clone()));
+                },
+                Item::Oneof { name : oneof, fields : variants } => {
+                    let mut cases = vec![format!("{}_NOT_SET", oneof.to_uppercase())];
+                    cases.extend(variants.iter().map(|(_, f)| format!("k{}", camel(f))));
+                    let case = self.define(format!("{}_{}Case", name, camel(oneof)), Type::Enum(Enum::new(cases, EnumRepr::Int)));
+                    let union : TypeList = variants.iter().map(|(t, _)| self.field_type(t, &m.path)).collect();
+                    let union = self.define(format!("{}_{}", name, oneof), Type::Union(Union::new(union)));
+
+                    members.push(case);
+                    fields.push(Field::new(format!("{}_caso", oneof)));
+                    members.push(union);
+                    fields.push(Field::new(oneof.clone()));
+                }
+            }
+        }
+
+        let t = match members.is_empty() {
+            true  => Type::Atomic(Atomic::new(1, 1)),   // empty messages take a byte, as empty C++ classes
+            false => Type::Struct(Struct::with_fields(members, fields))
+        };
+        self.define(name, t);
+    }
+}
+
+/// Field name in CamelCase, as protoc writes it in generated names: `numero_total` is `NumeroTotal`
+fn camel(name : &str) -> String {
+    name.split('_')
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            chars.next().map(|c| c.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+        })
+        .collect()
+}
@@ -0,0 +1,310 @@
+/*
+    Minimal JSON support, just what we need to export our data
+    in a machine readable format and to read what other tools export
+*/
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Any JSON value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>)    // keeps insertion order so output is stable
+}
+
+impl Value {
+
+    /// Create an object from a list of key-value pairs
+    pub fn object(pairs : Vec<(&str, Value)>) -> Value {
+        Value::Object(pairs.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
+    /// Create a number from an unsigned value, saturating if it doesn't fit
+    pub fn uint(n : usize) -> Value {
+        Value::Int(i64::try_from(n).unwrap_or(i64::MAX))
+    }
+
+    /// Create a string value
+    pub fn str(s : &str) -> Value {
+        Value::Str(s.to_string())
+    }
+
+    /// Value for a key, if this is an object with that key
+    pub fn get(&self, key : &str) -> Option<&Value> {
+        match self {
+            Value::Object(pairs) => pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None
+        }
+    }
+
+    /// Text in this value, if it's a string
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None
+        }
+    }
+
+    /// Number in this value, if it's a non negative integer
+    pub fn as_uint(&self) -> Option<usize> {
+        match self {
+            Value::Int(n) => usize::try_from(*n).ok(),
+            _ => None
+        }
+    }
+
+    /// Elements in this value, if it's an array
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None
+        }
+    }
+
+    /// Tells if this value is `true`
+    pub fn is_true(&self) -> bool {
+        matches!(self, Value::Bool(true))
+    }
+}
+
+/// Parse a JSON document
+/// ## Return
+/// The value, or a description of the first problem found
+pub fn parse(text : &str) -> Result<Value, String> {
+    let mut parser = Parser { text: text.as_bytes(), next: 0, depth: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    match parser.next < parser.text.len() {
+        true  => Err(parser.error("se esperaba el final del documento")),
+        false => Ok(value)
+    }
+}
+
+/// Deepest nesting accepted, so hostile documents can't overflow the stack
+const MAX_DEPTH : usize = 512;
+
+/// Recursive descent parser over the bytes of a document
+struct Parser<'a> {
+    text:  &'a [u8],
+    next:  usize,
+    depth: usize
+}
+
+impl<'a> Parser<'a> {
+
+    fn error(&self, message : &str) -> String {
+        format!("{} en la posición {}", message, self.next)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.text.get(self.next), Some(b' ') | Some(b'\n') | Some(b'\r') | Some(b'\t')) {
+            self.next += 1;
+        }
+    }
+
+    /// Consume the given text if it comes next
+    fn eat(&mut self, expected : &str) -> bool {
+        match self.text[self.next..].starts_with(expected.as_bytes()) {
+            true  => { self.next += expected.len(); true },
+            false => false
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.text.get(self.next) {
+            None       => Err(self.error("se esperaba un valor")),
+            Some(b'{') => self.nested(Parser::object),
+            Some(b'[') => self.nested(Parser::array),
+            Some(b'"') => self.string().map(Value::Str),
+            Some(b'-') | Some(b'0'..=b'9') => self.number(),
+            _ if self.eat("true")  => Ok(Value::Bool(true)),
+            _ if self.eat("false") => Ok(Value::Bool(false)),
+            _ if self.eat("null")  => Ok(Value::Null),
+            _ => Err(self.error("valor inesperado"))
+        }
+    }
+
+    /// Parse an object or array, keeping track of nesting
+    fn nested(&mut self, parse : fn(&mut Parser<'a>) -> Result<Value, String>) -> Result<Value, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error("el documento está demasiado anidado"))
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.next += 1;     // {
+        let mut pairs = vec![];
+        self.skip_whitespace();
+        if self.eat("}") {
+            return Ok(Value::Object(pairs))
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.text.get(self.next) != Some(&b'"') {
+                return Err(self.error("se esperaba una clave"))
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if !self.eat(":") {
+                return Err(self.error("se esperaba ':'"))
+            }
+            pairs.push((key, self.value()?));
+            self.skip_whitespace();
+            if self.eat("}") {
+                return Ok(Value::Object(pairs))
+            }
+            if !self.eat(",") {
+                return Err(self.error("se esperaba ',' o '}'"))
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.next += 1;     // [
+        let mut values = vec![];
+        self.skip_whitespace();
+        if self.eat("]") {
+            return Ok(Value::Array(values))
+        }
+
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            if self.eat("]") {
+                return Ok(Value::Array(values))
+            }
+            if !self.eat(",") {
+                return Err(self.error("se esperaba ',' o ']'"))
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.next += 1;     // opening quote
+        let mut bytes = vec![];
+        loop {
+            match self.text.get(self.next) {
+                None => return Err(self.error("cadena sin cerrar")),
+                Some(b'"') => {
+                    self.next += 1;
+                    return String::from_utf8(bytes).map_err(|_| self.error("cadena con UTF-8 inválido"))
+                },
+                Some(b'\\') => {
+                    let escaped = self.text.get(self.next + 1).copied();
+                    self.next += 2;
+                    let c = match escaped {
+                        Some(b'"')  => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/')  => '/',
+                        Some(b'b')  => '\u{8}',
+                        Some(b'f')  => '\u{c}',
+                        Some(b'n')  => '\n',
+                        Some(b'r')  => '\r',
+                        Some(b't')  => '\t',
+                        Some(b'u')  => self.unicode_escape()?,
+                        _ => return Err(self.error("secuencia de escape inválida"))
+                    };
+                    bytes.extend(c.to_string().as_bytes());
+                },
+                Some(b) => {
+                    bytes.push(*b);
+                    self.next += 1;
+                }
+            }
+        }
+    }
+
+    /// Character after `\u`, joining surrogate pairs
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        let code = match high {
+            0xD800..=0xDBFF if self.eat("\\u") => {
+                let low = self.hex4()?;
+                0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+            },
+            _ => high
+        };
+
+        Ok(std::char::from_u32(code).unwrap_or('\u{FFFD}'))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits = self.text.get(self.next..self.next + 4).and_then(|d| std::str::from_utf8(d).ok());
+        let code = digits.and_then(|d| u32::from_str_radix(d, 16).ok()).ok_or_else(|| self.error("escape \\u inválido"))?;
+        self.next += 4;
+        Ok(code)
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.next;
+        while matches!(self.text.get(self.next), Some(b'-') | Some(b'+') | Some(b'.') | Some(b'e') | Some(b'E') | Some(b'0'..=b'9')) {
+            self.next += 1;
+        }
+
+        let text = std::str::from_utf8(&self.text[start..self.next]).unwrap_or("");
+        match text.parse::<i64>() {
+            Ok(n) => Ok(Value::Int(n)),
+            Err(_) => text.parse::<f64>().map(Value::Float).map_err(|_| self.error("número inválido"))
+        }
+    }
+}
+
+/// Write a string with the proper escape sequences
+fn write_escaped(f : &mut fmt::Formatter, s : &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"'  => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?
+        }
+    }
+    write!(f, "\"")
+}
+
+impl fmt::Display for Value {
+
+    /// Compact JSON representation
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null     => write!(f, "null"),
+            Value::Bool(b)  => write!(f, "{}", b),
+            Value::Int(n)   => write!(f, "{}", n),
+            Value::Float(x) if x.is_finite() => write!(f, "{}", x),
+            Value::Float(_) => write!(f, "null"),
+            Value::Str(s)   => write_escaped(f, s),
+            Value::Array(values) => {
+                write!(f, "[")?;
+                for (i, v) in values.iter().enumerate() {
+                    if i > 0 { write!(f, ",")? }
+                    write!(f, "{}", v)?;
+                }
+                write!(f, "]")
+            },
+            Value::Object(pairs) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in pairs.iter().enumerate() {
+                    if i > 0 { write!(f, ",")? }
+                    write_escaped(f, k)?;
+                    write!(f, ":{}", v)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
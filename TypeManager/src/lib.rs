@@ -0,0 +1,19 @@
+/*
+    Library entry point: exposes the simulator internals so the
+    binary, benchmarks and other tooling can share them
+*/
+pub mod type_system;
+pub mod driver;
+pub mod utils;
+pub mod codegen;
+pub mod json;
+pub mod config;
+pub mod output;
+pub mod cli;
+pub mod expr;
+pub mod import;
+pub mod shared;
+pub mod metrics;
+pub mod image;
+#[cfg(test)]
+mod test_suite;
@@ -1,15 +1,257 @@
-mod type_system;
-mod driver;
-mod utils;
-mod test_suite;
+use std::io::BufReader;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
+
+use type_manager::driver;
+use type_manager::shared;
+use type_manager::metrics;
+use type_manager::cli::{self, Command};
+use type_manager::config::Config;
+use type_manager::type_system::cancel;
+use type_manager::driver::ExportFormat;
+
+/// Name used for the binary in help and completions
+const PROGRAM : &str = "typemanager";
+
+/// How often `watch` checks whether the script changed
+const WATCH_INTERVAL : Duration = Duration::from_millis(500);
 
 fn main() {
-    let mut program = driver::Program::new();
+    let (options, args) = cli::split_options(std::env::args().skip(1));
+    let command = match cli::parse(args) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("[ERROR]: {}\n\n{}", e, cli::usage(PROGRAM));
+            process::exit(2)
+        }
+    };
+
+    let (mut config, warnings) = Config::load();
+    for w in &warnings {
+        eprintln!("[CONFIG]: {}", w);
+    }
+    config.strict |= options.strict;
+    config.read_only |= options.read_only;
+    config.progress |= options.progress;
+    config.keep_first |= options.keep_first;
+    config.shadowing |= options.shadowing;
+    config.deferred |= options.deferred;
+    cancel::on_interrupt();
+
+    match command {
+        Command::Repl => repl(config),
+        Command::Run(scripts) => run(config, &scripts),
+        Command::Watch(script) => watch(config, &script),
+        Command::Export(format, input) => export(config, format, input),
+        Command::Serve(port, metrics) => serve(config, port, metrics),
+        Command::Share(port, metrics) => share(config, port, metrics),
+        Command::Connect(host, port) => connect(&host, port),
+        Command::StdioJson => stdio_json(config),
+        Command::Completions(shell) => print!("{}", cli::completions(shell, PROGRAM)),
+        Command::Help => print!("{}", cli::usage(PROGRAM)),
+        Command::Version => println!("{} {}", PROGRAM, env!("CARGO_PKG_VERSION"))
+    }
+}
+
+/// Interactive session
+fn repl(config: Config) {
+    let mut program = driver::Program::with_config(config);
 
     println!("¡Bienvenido al simulador de tipos de Luis!\n");
     println!("  -powered by Rust ⚙️ 😎\n\n");
 
+    // a restored session already has everything the definitions file would add
+    if !program.offer_recovery() {
+        program.autoload();
+    }
+
     while program.should_run() {
         program.run()
     }
 }
+
+/// Run every script in order in the same session, exit with an error if any line failed
+/// or some type is bigger than its budget
+fn run(config: Config, scripts: &[PathBuf]) {
+    let mut program = driver::Program::with_config(Config { autosave: false, ..config });
+    let mut failed = false;
+
+    for script in scripts {
+        match program.run_file(script) {
+            Err(e) => {
+                eprintln!("[ERROR]: No se pudo leer '{}': {}", script.display(), e);
+                failed = true;
+            },
+            Ok(summary) => {
+                for e in &summary.errors {
+                    eprintln!("{}", e);
+                }
+                failed |= !summary.errors.is_empty();
+            }
+        }
+    }
+
+    let overruns = program.budget_errors();
+    for overrun in &overruns {
+        eprintln!("[PRESUPUESTO]: {}", overrun);
+    }
+    failed |= !overruns.is_empty();
+
+    if failed {
+        process::exit(1)
+    }
+}
+
+/// Run a script in a fresh session every time it changes, telling how sizes, padding
+/// and offsets changed since the previous run and which types went over their budgets.
+/// Ctrl-C ends it
+fn watch(config: Config, script: &Path) {
+    let mut previous : Option<driver::Program> = None;
+    let mut seen = None;
+    println!("Observando '{}', Ctrl-C para terminar", script.display());
+
+    loop {
+        let modified = std::fs::metadata(script).and_then(|m| m.modified());
+        let stamp = modified.as_ref().ok().copied();
+        if seen != Some(stamp) {
+            seen = Some(stamp);
+
+            let mut program = driver::Program::with_config(Config { autosave: false, ..config.clone() });
+            program.capture_output();
+            match modified.and_then(|_| program.run_file(script)) {
+                Err(e) => eprintln!("[ERROR]: No se pudo leer '{}': {}", script.display(), e),
+                Ok(summary) => {
+                    for e in &summary.errors {
+                        eprintln!("{}", e);
+                    }
+                    for overrun in program.budget_errors() {
+                        eprintln!("[PRESUPUESTO]: {}", overrun);
+                    }
+                    match &previous {
+                        None => println!("{} tipos definidos, {} errores", summary.defined, summary.errors.len()),
+                        Some(before) => {
+                            let changes = program.changes_since(before);
+                            match changes.is_empty() {
+                                true  => println!("Sin cambios en los tipos ({} errores)", summary.errors.len()),
+                                false => println!("Cambios desde la ejecución anterior ({} errores):\n{}", summary.errors.len(), changes.join("\n"))
+                            }
+                        }
+                    }
+                    previous = Some(program);
+                }
+            }
+        }
+
+        std::thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+/// Print every type defined in a file, the configured definitions file if none is given
+fn export(config: Config, format: ExportFormat, input: Option<PathBuf>) {
+    let path = input.unwrap_or_else(|| config.definitions.clone());
+    let strict = config.strict;
+    let mut program = driver::Program::with_config(Config { autosave: false, ..config });
+
+    match program.run_file(&path) {
+        Err(e) => {
+            eprintln!("[ERROR]: No se pudo leer '{}': {}", path.display(), e);
+            process::exit(1)
+        },
+        Ok(summary) => {
+            for e in &summary.errors {
+                eprintln!("{}", e);
+            }
+            // in strict mode types missing from a failed line mean no export at all
+            if strict && !summary.errors.is_empty() {
+                process::exit(1)
+            }
+        }
+    }
+
+    match program.checked_export(format) {
+        Ok(exported) => print!("{}", exported),
+        Err(e) => {
+            eprintln!("{}", driver::CommandError::Program(e).display());
+            process::exit(1)
+        }
+    }
+}
+
+/// Accept TCP connections on localhost, every connection gets its own session
+fn serve(config: Config, port: u16, metrics: Option<u16>) {
+    serve_metrics(metrics);
+    let listener = match TcpListener::bind(("127.0.0.1", port)) {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("[ERROR]: No se pudo escuchar en el puerto {}: {}", port, e);
+            process::exit(1)
+        }
+    };
+    println!("Escuchando en 127.0.0.1:{}", port);
+
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(s) => s,
+            Err(e) => { eprintln!("[ERROR]: {}", e); continue }
+        };
+
+        let config = Config { autosave: false, pager: false, ..config.clone() };
+        std::thread::spawn(move || {
+            let mut program = driver::Program::with_config(config);
+            if let Ok(input) = stream.try_clone() {
+                let _ = program.serve(BufReader::new(input), &mut stream);
+            }
+            // commands are read on another thread, it stops once the connection is closed
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        });
+    }
+}
+
+/// Serve a session to the program that started this one, a JSON request per line
+/// of stdin and a JSON response per line of stdout
+fn stdio_json(config: Config) {
+    let mut program = driver::Program::with_config(Config { autosave: false, pager: false, ..config });
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    if let Err(e) = program.serve_json(stdin.lock(), &mut stdout.lock()) {
+        eprintln!("[ERROR]: {}", e);
+        process::exit(1)
+    }
+}
+
+/// Host a single session on localhost, shared by every connection
+fn share(config: Config, port: u16, metrics: Option<u16>) {
+    serve_metrics(metrics);
+    let mut program = driver::Program::with_config(Config { autosave: false, pager: false, ..config });
+    program.autoload();
+
+    if let Err(e) = shared::host(program, port) {
+        eprintln!("[ERROR]: No se pudo compartir la sesión en el puerto {}: {}", port, e);
+        process::exit(1)
+    }
+}
+
+/// Serve metrics over HTTP on localhost, if a port is given
+fn serve_metrics(port: Option<u16>) {
+    let port = match port {
+        Some(p) => p,
+        None => return
+    };
+    match metrics::listen(port) {
+        Ok(()) => println!("Métricas en http://127.0.0.1:{}/metrics", port),
+        Err(e) => {
+            eprintln!("[ERROR]: No se pudo servir las métricas en el puerto {}: {}", port, e);
+            process::exit(1)
+        }
+    }
+}
+
+/// Join a session shared with `share`
+fn connect(host: &str, port: u16) {
+    if let Err(e) = shared::connect(host, port) {
+        eprintln!("[ERROR]: No se pudo usar la sesión compartida en {}:{}: {}", host, port, e);
+        process::exit(1)
+    }
+}
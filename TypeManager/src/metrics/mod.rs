@@ -0,0 +1,218 @@
+/*
+    Metrics: counters to operate the servers, in the Prometheus text format.
+    Commands run and how long they take, types defined, how the layout cache
+    does and how long the optimizer searches, added up over every session of
+    the process. Nothing is counted until `enable` is called, so the cost the
+    rest of the time is checking a flag. `listen` answers `GET /metrics` over
+    HTTP with them, for `serve` and `share` with `--metrics-port`
+*/
+use std::convert::TryFrom;
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Upper bounds of the histogram buckets, in seconds
+pub const BUCKETS : [f64; 9] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0];
+
+/// Longest request head read, the rest of bigger requests is ignored
+const MAX_REQUEST : u64 = 8 * 1024;
+
+/// How long a client may take to send its request
+const REQUEST_TIMEOUT : Duration = Duration::from_secs(5);
+
+static ENABLED : AtomicBool = AtomicBool::new(false);
+static COMMANDS_OK : AtomicU64 = AtomicU64::new(0);
+static COMMANDS_FAILED : AtomicU64 = AtomicU64::new(0);
+static TYPES : AtomicU64 = AtomicU64::new(0);
+static CACHE_HITS : AtomicU64 = AtomicU64::new(0);
+static CACHE_MISSES : AtomicU64 = AtomicU64::new(0);
+static OPTIMIZER : Histogram = Histogram::new();
+static REQUESTS : Histogram = Histogram::new();
+
+/// Observations counted by the bucket they fall in, as Prometheus histograms do
+pub struct Histogram {
+    buckets: [AtomicU64; BUCKETS.len()],    // observations up to every bound, each counted in every bucket it fits
+    count:   AtomicU64,
+    micros:  AtomicU64                      // sum of every observation
+}
+
+impl Histogram {
+
+    /// A histogram without observations
+    pub const fn new() -> Histogram {
+        Histogram {
+            buckets: [const { AtomicU64::new(0) }; BUCKETS.len()],
+            count:   AtomicU64::new(0),
+            micros:  AtomicU64::new(0)
+        }
+    }
+
+    /// Count an observation
+    pub fn observe(&self, elapsed : Duration) {
+        let seconds = elapsed.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS) {
+            if seconds <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.micros.fetch_add(u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX), Ordering::Relaxed);
+    }
+
+    /// Lines for this histogram in the Prometheus text format
+    fn render(&self, name : &str, help : &str) -> String {
+        let mut text = format!("# HELP {} {}\n# TYPE {} histogram\n", name, help, name);
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS) {
+            text += &format!("{}_bucket{{le=\"{}\"}} {}\n", name, bound, bucket.load(Ordering::Relaxed));
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        text += &format!("{}_bucket{{le=\"+Inf\"}} {}\n", name, count);
+        text += &format!("{}_sum {}\n", name, self.micros.load(Ordering::Relaxed) as f64 / 1e6);
+        text += &format!("{}_count {}\n", name, count);
+        text
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Histogram::new()
+    }
+}
+
+/// Start counting
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed)
+}
+
+/// Tells if metrics are being counted
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// A command was run
+/// ## Params
+/// * `ok` - whether it succeeded
+/// * `elapsed` - how long it took
+/// * `types` - types it defined
+pub fn command(ok : bool, elapsed : Duration, types : usize) {
+    if !enabled() {
+        return
+    }
+    match ok {
+        true  => COMMANDS_OK.fetch_add(1, Ordering::Relaxed),
+        false => COMMANDS_FAILED.fetch_add(1, Ordering::Relaxed)
+    };
+    TYPES.fetch_add(types as u64, Ordering::Relaxed);
+    REQUESTS.observe(elapsed);
+}
+
+/// The layout cache was asked for the size or alignment of a type
+/// ## Params
+/// * `hit` - whether it had it
+pub fn cached(hit : bool) {
+    if !enabled() {
+        return
+    }
+    match hit {
+        true  => CACHE_HITS.fetch_add(1, Ordering::Relaxed),
+        false => CACHE_MISSES.fetch_add(1, Ordering::Relaxed)
+    };
+}
+
+/// The optimizer finished searching a member order
+pub fn optimized(elapsed : Duration) {
+    if enabled() {
+        OPTIMIZER.observe(elapsed)
+    }
+}
+
+/// Every metric in the Prometheus text format
+pub fn render() -> String {
+    let counter = |name : &str, help : &str, samples : &[(&str, u64)]| {
+        let mut text = format!("# HELP {} {}\n# TYPE {} counter\n", name, help, name);
+        for (labels, value) in samples {
+            text += &format!("{}{} {}\n", name, labels, value);
+        }
+        text
+    };
+
+    let ok = COMMANDS_OK.load(Ordering::Relaxed);
+    let failed = COMMANDS_FAILED.load(Ordering::Relaxed);
+    let hits = CACHE_HITS.load(Ordering::Relaxed);
+    let misses = CACHE_MISSES.load(Ordering::Relaxed);
+    let ratio = match hits + misses {
+        0 => 0.0,
+        total => hits as f64 / total as f64
+    };
+
+    let mut text = counter("typemanager_commands_total", "Comandos ejecutados", &[("{result=\"ok\"}", ok), ("{result=\"error\"}", failed)]);
+    text += &counter("typemanager_types_registered_total", "Tipos definidos", &[("", TYPES.load(Ordering::Relaxed))]);
+    text += &counter("typemanager_layout_cache_hits_total", "Disposiciones encontradas en la caché", &[("", hits)]);
+    text += &counter("typemanager_layout_cache_misses_total", "Disposiciones calculadas y guardadas en la caché", &[("", misses)]);
+    text += &format!(
+        "# HELP typemanager_layout_cache_hit_ratio Fracción de consultas a la caché que la encontraron\n\
+         # TYPE typemanager_layout_cache_hit_ratio gauge\n\
+         typemanager_layout_cache_hit_ratio {}\n",
+        ratio
+    );
+    text += &OPTIMIZER.render("typemanager_optimizer_seconds", "Tiempo de cada búsqueda del optimizador");
+    text += &REQUESTS.render("typemanager_request_seconds", "Tiempo de cada comando");
+    text
+}
+
+/// Answer an HTTP request for the metrics: `GET /metrics`
+/// ## Params
+/// * `stream` - connection the request comes from, the answer goes back through it
+pub fn respond(stream : &mut TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?.take(MAX_REQUEST));
+    let mut first = String::new();
+    reader.read_line(&mut first)?;
+
+    // the headers don't change the answer, but the client expects them to be read
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && !line.trim().is_empty() {
+        line.clear();
+    }
+
+    let request : Vec<&str> = first.split_whitespace().collect();
+    match request.as_slice() {
+        ["GET", path, ..] if path.split('?').next() == Some("/metrics") => answer(stream, "200 OK", &render()),
+        ["GET", ..] => answer(stream, "404 Not Found", "Solo se sirve /metrics\n"),
+        _ => answer(stream, "405 Method Not Allowed", "")
+    }
+}
+
+/// Write an HTTP response and close the connection
+fn answer(stream : &mut TcpStream, status : &str, body : &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, body.len(), body
+    )?;
+    stream.flush()
+}
+
+/// Serve the metrics over HTTP on a port of localhost, on a thread of their own, and
+/// start counting
+/// ## Params
+/// * `port` - port to listen on
+/// ---
+/// ## Return
+/// An error if the port can't be used
+pub fn listen(port : u16) -> io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    enable();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(mut stream) => { let _ = respond(&mut stream); },
+                Err(e) => eprintln!("[ERROR]: {}", e)
+            }
+        }
+    });
+    Ok(())
+}
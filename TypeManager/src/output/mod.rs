@@ -0,0 +1,257 @@
+/*
+    Output layer: commands write here instead of printing directly,
+    so long outputs can be paged, captured or sent to any writer, such as
+    the file a command is redirected to with `describir s > s.txt`
+*/
+use std::io;
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Terminal height used when it can't be detected
+const DEFAULT_HEIGHT : usize = 24;
+
+/// Outputs shorter than this fit in any terminal, so its height isn't asked for
+const MIN_PAGED : usize = 10;
+
+/// Buffered output for a single command
+#[derive(Debug, Default)]
+pub struct Output {
+    buffer:   String,
+    captured: Option<String>,   // when capturing, flushed output is kept here instead of printed
+    prefix:   String,           // written before every line
+    redirects: Vec<Redirect>    // writers taking flushed output instead, the last one in use
+}
+
+/// A writer output is sent to, with the first error writing to it
+struct Redirect {
+    writer: Box<dyn Write + Send>,
+    error:  Option<io::Error>
+}
+
+impl std::fmt::Debug for Redirect {
+    fn fmt(&self, f : &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Redirect").field("error", &self.error).finish()
+    }
+}
+
+impl Output {
+
+    /// Create an output that prints to stdout when flushed
+    pub fn new() -> Output {
+        Output::default()
+    }
+
+    /// Create an output that keeps everything flushed instead of printing it
+    pub fn capturing() -> Output {
+        Output { captured: Some(String::new()), ..Output::default() }
+    }
+
+    /// Add a line of text, every line in it starts with our prefix
+    pub fn line(&mut self, text : &str) {
+        for line in text.split('\n') {
+            self.buffer += &self.prefix;
+            self.buffer += line;
+            self.buffer += "\n";
+        }
+    }
+
+    /// Start every line added from now on with some text, such as `[arm] `
+    pub fn set_prefix(&mut self, prefix : &str) {
+        self.prefix = prefix.to_string();
+    }
+
+    /// Wrap text so terminals show it dimmed, left as is when capturing or redirecting,
+    /// when stdout is not a terminal or when `$NO_COLOR` is set
+    pub fn dim(&self, text : &str) -> String {
+        match self.captured.is_none() && self.redirects.is_empty() && io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none() {
+            true  => format!("\x1b[2m{}\x1b[0m", text),
+            false => text.to_string()
+        }
+    }
+
+    /// Send output to a writer until `end_redirect`, instead of printing or capturing it.
+    /// Pending output goes where it was going before, redirections can be nested
+    pub fn redirect(&mut self, writer : Box<dyn Write + Send>) {
+        self.flush(false);
+        self.redirects.push(Redirect { writer, error: None });
+    }
+
+    /// Stop sending output to the last writer given to `redirect`, pending output goes to it first
+    /// ## Return
+    /// The first error writing to it, if any
+    pub fn end_redirect(&mut self) -> io::Result<()> {
+        self.flush(false);
+        match self.redirects.pop() {
+            Some(Redirect { error: Some(e), .. }) => Err(e),
+            Some(Redirect { mut writer, error: None }) => writer.flush(),
+            None => Ok(())
+        }
+    }
+
+    /// Take everything captured so far, empty if not capturing
+    pub fn take_captured(&mut self) -> String {
+        match &mut self.captured {
+            Some(text) => std::mem::take(text),
+            None => String::new()
+        }
+    }
+
+    /// Print pending output. If paging is allowed, stdout is a terminal and the 
+    /// output doesn't fit in it, show it through `$PAGER` or our own pager
+    /// ## Params
+    /// * `allow_paging` - whether this output may be paged
+    pub fn flush(&mut self, allow_paging : bool) {
+        let text = std::mem::take(&mut self.buffer);
+        if text.is_empty() {
+            return
+        }
+
+        if let Some(redirect) = self.redirects.last_mut() {
+            if let (None, Err(e)) = (&redirect.error, redirect.writer.write_all(text.as_bytes())) {
+                redirect.error = Some(e);
+            }
+            return
+        }
+
+        if let Some(captured) = &mut self.captured {
+            *captured += &text;
+            return
+        }
+
+        if allow_paging && io::stdout().is_terminal() {
+            let lines = text.lines().count();
+            if lines >= MIN_PAGED {
+                let height = terminal_height();
+                if lines >= height {
+                    return page(&text, height)
+                }
+            }
+        }
+
+        print!("{}", text);
+        io::stdout().flush().expect("Couldn't flush stdout");
+    }
+}
+
+/// Time between progress updates
+const PROGRESS_INTERVAL : Duration = Duration::from_millis(200);
+
+/// Progress of a long import, written to stderr over the same line so it never
+/// mixes with command output. Does nothing unless enabled
+#[derive(Debug)]
+pub struct Progress {
+    label:   String,
+    enabled: bool,
+    shown:   bool,      // whether a line was written and needs to be ended
+    last:    Instant
+}
+
+impl Progress {
+
+    /// Progress for a task, shown only if enabled
+    pub fn new(label : &str, enabled : bool) -> Progress {
+        Progress { label: label.to_string(), enabled, shown: false, last: Instant::now() }
+    }
+
+    /// Tell how far the task got, at most once every `PROGRESS_INTERVAL`
+    /// ## Params
+    /// * `read` - lines or items read so far
+    /// * `defined` - types defined so far
+    pub fn update(&mut self, read : usize, defined : usize) {
+        if !self.enabled || self.last.elapsed() < PROGRESS_INTERVAL {
+            return
+        }
+        self.last = Instant::now();
+        self.shown = true;
+        eprint!("\r{}: {} leídos, {} tipos definidos", self.label, read, defined);
+        let _ = io::stderr().flush();
+    }
+
+    /// End the task, with a last update if any was shown
+    pub fn finish(self, read : usize, defined : usize) {
+        if self.shown {
+            eprintln!("\r{}: {} leídos, {} tipos definidos", self.label, read, defined);
+        }
+    }
+}
+
+/// Terminal height: `$LINES`, or what `stty` says, or a sensible default
+fn terminal_height() -> usize {
+    if let Some(lines) = std::env::var("LINES").ok().and_then(|l| l.parse().ok()) {
+        return lines
+    }
+
+    std::fs::File::open("/dev/tty")
+        .ok()
+        .and_then(|tty| Command::new("stty").arg("size").stdin(tty).output().ok())
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .and_then(|size| size.split_whitespace().next().and_then(|rows| rows.parse().ok()))
+        .filter(|rows| *rows > 1)
+        .unwrap_or(DEFAULT_HEIGHT)
+}
+
+/// Show text through `$PAGER` if set, or through our internal pager otherwise
+fn page(text : &str, height : usize) {
+    if let Ok(pager) = std::env::var("PAGER") {
+        let child = Command::new("sh").arg("-c").arg(&pager).stdin(Stdio::piped()).spawn();
+        if let Ok(mut child) = child {
+            if let Some(stdin) = child.stdin.as_mut() {
+                let _ = stdin.write_all(text.as_bytes());
+            }
+            drop(child.stdin.take());
+            let _ = child.wait();
+            return
+        }
+    }
+
+    internal_pager(text, height)
+}
+
+/// Minimal pager: Enter shows the next page, `/texto` jumps to the next line
+/// containing `texto`, `n` repeats the last search and `q` stops
+fn internal_pager(text : &str, height : usize) {
+    let lines : Vec<&str> = text.lines().collect();
+    let page_size = height.saturating_sub(1).max(1);
+    let mut start = 0;
+    let mut last_search = String::new();
+
+    loop {
+        let end = std::cmp::min(start + page_size, lines.len());
+        for line in &lines[start..end] {
+            println!("{}", line);
+        }
+
+        if end >= lines.len() {
+            return
+        }
+
+        print!("-- Más ({}/{}) -- [Enter: seguir, /texto: buscar, n: repetir búsqueda, q: salir] ", end, lines.len());
+        io::stdout().flush().expect("Couldn't flush stdout");
+
+        let mut answer = String::new();
+        if let Ok(0) | Err(_) = io::stdin().read_line(&mut answer) {
+            return
+        }
+
+        let answer = answer.trim();
+        if answer == "q" {
+            return
+        }
+
+        // searches start right after the current page
+        let search = match answer.strip_prefix('/') {
+            Some(s) => { last_search = s.to_string(); Some(s.to_string()) },
+            None if answer == "n" && !last_search.is_empty() => Some(last_search.clone()),
+            None => None
+        };
+
+        start = match search {
+            None => end,
+            Some(s) => match lines[end..].iter().position(|l| l.contains(s.as_str())) {
+                Some(i) => end + i,
+                None => { println!("'{}' no encontrado", s); end }
+            }
+        };
+    }
+}
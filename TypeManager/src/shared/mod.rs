@@ -0,0 +1,364 @@
+/*
+    Shared sessions over TCP: one program hosts a session and every client
+    connected runs commands on it. Commands that change the session are sent to
+    every other client along with their output, so everyone sees the same types.
+
+    Every message is UTF-8 text, a line at a time. Clients send a command per
+    line, as written in the REPL. The host answers with a header line
+    `<TIPO> <cliente> <n>` followed by n lines:
+    * `HOLA <cliente> 0`: sent once on connecting, with the number of this client
+    * `RESULTADO <cliente> <n>`: output of a command sent by this client
+    * `CAMBIO <cliente> <n>`: another client changed the session, the first line
+      is its command and the rest are its output
+    * `ADIOS <cliente> 0`: another client left
+
+    Queries that only read types, such as `describir`, run on a snapshot of the
+    types taken after the last change, so they never wait for a command changing
+    the session and it never waits for them. `cancelar` stops the command the
+    session is running and the query of the client sending it
+*/
+use std::collections::BTreeMap;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+
+use crate::config::Config;
+use crate::driver::Program;
+use crate::type_system::TypeManager;
+use crate::type_system::cancel::Canceller;
+
+mod snapshot;
+pub use snapshot::Snapshot;
+
+/// A message from the host to a client
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Hello(usize),                       // number given to the client
+    Result(usize, Vec<String>),         // client, output lines
+    Change(usize, String, Vec<String>), // client, command, output lines
+    Bye(usize)                          // client
+}
+
+impl Message {
+
+    /// Text sent over the wire, header and lines
+    pub fn encode(&self) -> String {
+        let (tag, client, lines) = match self {
+            Message::Hello(c) => ("HOLA", c, vec![]),
+            Message::Result(c, output) => ("RESULTADO", c, output.iter().map(|l| l.as_str()).collect()),
+            Message::Change(c, command, output) => {
+                let mut lines = vec![command.as_str()];
+                lines.extend(output.iter().map(|l| l.as_str()));
+                ("CAMBIO", c, lines)
+            },
+            Message::Bye(c) => ("ADIOS", c, vec![])
+        };
+
+        let mut text = format!("{} {} {}\n", tag, client, lines.len());
+        for line in lines {
+            text += line;
+            text += "\n";
+        }
+        text
+    }
+
+    /// Read a message sent by the host
+    /// ## Return
+    /// The message, none if the host closed the connection, or an error if what
+    /// was sent is not a message
+    pub fn read<R : BufRead>(input : &mut R) -> io::Result<Option<Message>> {
+        let invalid = |text : String| io::Error::new(io::ErrorKind::InvalidData, text);
+
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None)
+        }
+        let fields : Vec<&str> = header.split_whitespace().collect();
+        let (tag, client, count) = match fields.as_slice() {
+            [tag, client, count] => match (client.parse::<usize>(), count.parse::<usize>()) {
+                (Ok(client), Ok(count)) => (*tag, client, count),
+                _ => return Err(invalid(format!("cabecera inválida: '{}'", header.trim_end())))
+            },
+            _ => return Err(invalid(format!("cabecera inválida: '{}'", header.trim_end())))
+        };
+
+        let mut lines = Vec::with_capacity(count.min(1024));
+        for _ in 0..count {
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                return Err(invalid(String::from("el mensaje terminó antes de tiempo")))
+            }
+            lines.push(line.trim_end_matches(['\n', '\r']).to_string());
+        }
+
+        match (tag, lines.is_empty()) {
+            ("HOLA", true)       => Ok(Some(Message::Hello(client))),
+            ("ADIOS", true)      => Ok(Some(Message::Bye(client))),
+            ("RESULTADO", _)     => Ok(Some(Message::Result(client, lines))),
+            ("CAMBIO", false)    => {
+                let command = lines.remove(0);
+                Ok(Some(Message::Change(client, command, lines)))
+            },
+            _ => Err(invalid(format!("mensaje inválido: '{}'", header.trim_end())))
+        }
+    }
+}
+
+/// A program shared by every connected client
+pub struct SharedSession {
+    program: Mutex<Program>,
+    clients: Mutex<Clients>,
+    config:  Config,                    // configuration queries run with
+    types:   Snapshot<TypeManager>,     // types as of the last change, for queries
+    canceller: Canceller                // stops the command the shared program runs
+}
+
+/// What a client runs queries on
+#[derive(Default)]
+pub struct Reader {
+    program:   Option<(usize, Program)>,    // revision of the types it has, program reading them
+    canceller: Canceller                    // stops the query running
+}
+
+impl Reader {
+
+    /// Revision of the types queries run on, none before the first query
+    pub fn revision(&self) -> Option<usize> {
+        self.program.as_ref().map(|(revision, _)| *revision)
+    }
+}
+
+/// Streams to every connected client, by number
+#[derive(Default)]
+struct Clients {
+    next:    usize,
+    streams: BTreeMap<usize, Box<dyn Write + Send>>
+}
+
+impl SharedSession {
+
+    /// Share a program, its output is captured to send it to clients
+    pub fn new(program : Program) -> SharedSession {
+        let mut program = program;
+        program.capture_output();
+        let config = Config { autosave: false, pager: false, journal: None, ..program.config().clone() };
+        let types = Snapshot::new(program.revision(), program.manager().snapshot());
+        let canceller = program.canceller();
+        SharedSession { program: Mutex::new(program), clients: Mutex::new(Clients::default()), config, types, canceller }
+    }
+
+    /// Types as of the last change
+    pub fn types(&self) -> &Snapshot<TypeManager> {
+        &self.types
+    }
+
+    /// Add a client, greeting it with its number
+    /// ## Params
+    /// * `output` - where messages to this client go
+    /// ---
+    /// ## Return
+    /// Number given to the client
+    pub fn join(&self, output : Box<dyn Write + Send>) -> usize {
+        let mut clients = lock(&self.clients);
+        clients.next += 1;
+        let client = clients.next;
+        clients.streams.insert(client, output);
+        send(&mut clients, client, &Message::Hello(client));
+        client
+    }
+
+    /// Remove a client, telling everyone else
+    pub fn leave(&self, client : usize) {
+        let mut clients = lock(&self.clients);
+        if clients.streams.remove(&client).is_some() {
+            broadcast(&mut clients, client, &Message::Bye(client));
+        }
+    }
+
+    /// Run a command for a client. Its output goes back to the client and, if the
+    /// session changed, the command and its output go to everyone else
+    pub fn run(&self, client : usize, line : &str) {
+        let mut program = lock(&self.program);
+        let revision = program.revision();
+        program.run_line(line);
+        let output : Vec<String> = program.take_output().lines().map(String::from).collect();
+        let changed = program.revision() != revision;
+        if changed {
+            self.types.store(program.revision(), program.manager().snapshot());
+        }
+
+        // clients are locked before the program is released, so every client sees changes in order
+        let mut clients = lock(&self.clients);
+        drop(program);
+        send(&mut clients, client, &Message::Result(client, output.clone()));
+        if changed {
+            broadcast(&mut clients, client, &Message::Change(client, line.trim().to_string(), output));
+        }
+    }
+
+    /// Answer a query that only reads types on the latest snapshot of them, without
+    /// waiting for the shared program. The reader is made again when the types changed
+    /// ## Params
+    /// * `client` - client asking, the answer only goes to it
+    /// * `reader` - what this client runs queries on
+    /// ---
+    /// ## Return
+    /// Whether the line was such a query, if it's not it must be run with `run`
+    pub fn query(&self, client : usize, line : &str, reader : &mut Reader) -> bool {
+        if !Program::parse_with(line, &self.config.keywords).is_ok_and(|action| action.reads_types()) {
+            return false
+        }
+
+        let (revision, types) = self.types.load();
+        let program = match &mut reader.program {
+            Some((r, program)) if *r == revision => program,
+            stale => {
+                let mut program = Program::with_manager(self.config.clone(), types.snapshot());
+                program.capture_output();
+                program.use_canceller(reader.canceller.clone());
+                &mut stale.insert((revision, program)).1
+            }
+        };
+        program.run_line(line);
+        let output : Vec<String> = program.take_output().lines().map(String::from).collect();
+
+        send(&mut lock(&self.clients), client, &Message::Result(client, output));
+        true
+    }
+
+    /// Run every command a client sends until it exits or disconnects. `salir`
+    /// disconnects the client, the session keeps running for everyone else
+    /// ## Params
+    /// * `client` - number given by `join`
+    /// * `input` - where commands from the client come from
+    pub fn serve_client<R : BufRead + Send + 'static>(&self, client : usize, input : R) {
+        let mut reader = Reader::default();
+        let cancellers = vec![self.canceller.clone(), reader.canceller.clone()];
+        for line in Program::read_commands(input, self.config.keywords.clone(), cancellers) {
+            let line = match line {
+                Ok(l) => l,
+                Err(_) => break
+            };
+            if line.trim().is_empty() {
+                continue
+            }
+            if matches!(Program::parse_with(&line, &self.config.keywords), Ok(crate::driver::Action::Exit)) {
+                break
+            }
+            if !self.query(client, &line, &mut reader) {
+                self.run(client, &line);
+            }
+        }
+
+        self.leave(client)
+    }
+}
+
+/// Lock a mutex, even if a thread panicked while holding it
+fn lock<T>(mutex : &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Send a message to a client, dropping it if its connection broke
+fn send(clients : &mut Clients, client : usize, message : &Message) {
+    let sent = match clients.streams.get_mut(&client) {
+        Some(stream) => stream.write_all(message.encode().as_bytes()).and_then(|_| stream.flush()).is_ok(),
+        None => return
+    };
+    if !sent {
+        clients.streams.remove(&client);
+    }
+}
+
+/// Send a message to every client but one
+fn broadcast(clients : &mut Clients, except : usize, message : &Message) {
+    let others : Vec<usize> = clients.streams.keys().copied().filter(|c| *c != except).collect();
+    for client in others {
+        send(clients, client, message);
+    }
+}
+
+/// Host a program on a TCP port of localhost, every client connected shares it
+/// ## Params
+/// * `program` - program to share
+/// * `port` - port to listen on
+/// ---
+/// ## Return
+/// An error if the port can't be used
+pub fn host(program : Program, port : u16) -> io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    println!("Compartiendo la sesión en 127.0.0.1:{}", port);
+    let session = Arc::new(SharedSession::new(program));
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(s) => s,
+            Err(e) => { eprintln!("[ERROR]: {}", e); continue }
+        };
+        let (input, closer) = match (stream.try_clone(), stream.try_clone()) {
+            (Ok(i), Ok(c)) => (i, c),
+            (Err(e), _) | (_, Err(e)) => { eprintln!("[ERROR]: {}", e); continue }
+        };
+
+        let session = Arc::clone(&session);
+        thread::spawn(move || {
+            let client = session.join(Box::new(stream));
+            session.serve_client(client, BufReader::new(input));
+            // commands are read on another thread, it stops once the connection is closed
+            let _ = closer.shutdown(std::net::Shutdown::Both);
+        });
+    }
+
+    Ok(())
+}
+
+/// Connect to a shared session, sending every line from stdin as a command and
+/// showing results and changes made by other clients
+/// ## Params
+/// * `host` - host name or address
+/// * `port` - port the session is shared on
+/// ---
+/// ## Return
+/// An error if the connection failed or the host sent something that's not a message
+pub fn connect(host : &str, port : u16) -> io::Result<()> {
+    let stream = TcpStream::connect((host, port))?;
+    let mut input = BufReader::new(stream.try_clone()?);
+    let address = format!("{}:{}", host, port);
+
+    // messages are shown as they come, while commands are read
+    let reader = thread::spawn(move || -> io::Result<()> {
+        while let Some(message) = Message::read(&mut input)? {
+            match message {
+                Message::Hello(client) => println!("Conectado a {} como cliente {}", address, client),
+                Message::Result(_, output) => {
+                    for line in output {
+                        println!("{}", line);
+                    }
+                },
+                Message::Change(client, command, output) => {
+                    println!("[cliente {}] {}", client, command);
+                    for line in output {
+                        println!("[cliente {}] {}", client, line);
+                    }
+                },
+                Message::Bye(client) => println!("[cliente {}] se fue", client)
+            }
+        }
+        println!("La sesión compartida terminó");
+        Ok(())
+    });
+
+    let mut output = stream;
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if output.write_all(format!("{}\n", line).as_bytes()).is_err() {
+            break
+        }
+    }
+    let _ = output.shutdown(std::net::Shutdown::Write);
+
+    reader.join().unwrap_or(Ok(()))
+}
+
@@ -0,0 +1,38 @@
+/*
+    Snapshots: the latest published version of some value, shared behind an `Arc`.
+    Readers take the `Arc` and keep using that version as long as they want, the
+    writer publishes a new one without waiting for them. The lock is only held
+    to swap or clone the `Arc`, never while a version is being read or built
+*/
+use std::sync::{Arc, RwLock};
+
+/// Latest version of a value, with the revision it was published at
+#[derive(Debug)]
+pub struct Snapshot<T> {
+    current: RwLock<(usize, Arc<T>)>
+}
+
+impl<T> Snapshot<T> {
+
+    /// Publish a first version
+    pub fn new(revision : usize, value : T) -> Snapshot<T> {
+        Snapshot { current: RwLock::new((revision, Arc::new(value))) }
+    }
+
+    /// Latest version and its revision
+    pub fn load(&self) -> (usize, Arc<T>) {
+        let current = self.current.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        (current.0, Arc::clone(&current.1))
+    }
+
+    /// Revision of the latest version
+    pub fn revision(&self) -> usize {
+        self.current.read().unwrap_or_else(|poisoned| poisoned.into_inner()).0
+    }
+
+    /// Publish a new version, readers still holding older ones keep them
+    pub fn store(&self, revision : usize, value : T) {
+        let value = Arc::new(value);
+        *self.current.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = (revision, value);
+    }
+}
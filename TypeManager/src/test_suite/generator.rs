@@ -0,0 +1,97 @@
+/*
+    Random type graph generator for property based tests
+*/
+use crate::type_system::*;
+use crate::utils::Rng;
+
+/// Max amount of members for generated compound types, keep it low
+/// so the optimizer doesn't take forever
+const MAX_MEMBERS : usize = 4;
+
+/// Max nesting depth for compound types. The optimizer recomputes nested
+/// layouts for every permutation, so cost grows exponentially with depth
+const MAX_DEPTH : usize = 3;
+
+/// Generate a manager filled with random types. Atomics go first and every
+/// compound type only refers to types defined before it, so they're always valid
+/// ## Params
+/// * `rng` - random number generator to use
+/// * `n_types` - how many types to generate
+/// ---
+/// ## Return
+/// The new manager and every type name in definition order
+pub fn random_manager(rng : &mut Rng, n_types : usize) -> (TypeManager, TypeList) {
+    generate(rng, n_types, random_atomic)
+}
+
+/// Same as `random_manager`, but atomics look like C scalars and arrays:
+/// their size is a multiple of their alignment
+pub fn random_c_manager(rng : &mut Rng, n_types : usize) -> (TypeManager, TypeList) {
+    generate(rng, n_types, random_c_atomic)
+}
+
+/// Generate `n_types` types creating atomics with the given function
+fn generate(rng : &mut Rng, n_types : usize, new_atomic : fn(&mut Rng) -> Type) -> (TypeManager, TypeList) {
+    let mut manager = TypeManager::new();
+    let mut names : TypeList = vec![];
+    let mut depths : Vec<usize> = vec![];
+
+    // we need at least one atomic to build anything else
+    let n_atomics = 1 + rng.below(std::cmp::min(n_types, 4));
+
+    for i in 0..n_types {
+        let name = format!("t{}", i);
+        let (new_type, depth) = if i < n_atomics { 
+            (new_atomic(rng), 0) 
+        } 
+        else { 
+            random_compound(rng, &names, &depths) 
+        };
+
+        manager.add(name.clone(), new_type).expect("generated type should be valid");
+        names.push(name);
+        depths.push(depth);
+    }
+
+    (manager, names)
+}
+
+/// Atomic with size in [1, 16] and power of two alignment in [1, 8]
+fn random_atomic(rng : &mut Rng) -> Type {
+    let size  = 1 + rng.below(16);
+    let align = 1 << rng.below(4);
+
+    Type::Atomic(Atomic::new(size, align))
+}
+
+/// Atomic with power of two alignment in [1, 8] and size up to 3 times its alignment
+fn random_c_atomic(rng : &mut Rng) -> Type {
+    let align = 1 << rng.below(4);
+    let size  = align * (1 + rng.below(3));
+
+    Type::Atomic(Atomic::new(size, align))
+}
+
+/// Struct or union made of previously defined types that are not nested too deep
+/// ## Return
+/// The new type and its nesting depth
+fn random_compound(rng : &mut Rng, defined : &[Name], depths : &[usize]) -> (Type, usize) {
+    // atomics have depth 0, so there's always some candidate
+    let candidates : Vec<usize> = (0..defined.len()).filter(|i| depths[*i] < MAX_DEPTH).collect();
+
+    let n_members = 1 + rng.below(MAX_MEMBERS);
+    let mut members : TypeList = vec![];
+    let mut depth = 0;
+    for _ in 0..n_members {
+        let i = candidates[rng.below(candidates.len())];
+        members.push(defined[i].clone());
+        depth = std::cmp::max(depth, depths[i] + 1);
+    }
+
+    if rng.below(3) == 0 {
+        (Type::Union(Union::new(members)), depth)
+    }
+    else {
+        (Type::Struct(Struct::new(members)), depth)
+    }
+}
@@ -0,0 +1,5139 @@
+#[allow(unused)]
+use crate::utils::*;
+#[allow(unused)]
+use crate::type_system::*;
+
+mod generator;
+
+#[test]
+fn test_gcd() {
+    // just test that gcd works ok
+    assert_eq!(gcd(4, 1), 1);
+    assert_eq!(gcd(4,4), 4);
+    assert_eq!(gcd(7,9), 1);
+    assert_eq!(gcd(3,9), 3);
+}
+
+#[test]
+fn test_lcm() {
+    // test that lcm works ok
+    assert_eq!(lcm(4, 4), 4);
+    assert_eq!(lcm(7,9), 7*9);
+    assert_eq!(lcm(3, lcm(5, 7)), lcm(lcm(3,5), 7));
+    assert_eq!(lcm(1,3), 3);
+}
+
+#[test]
+fn test_permutations_singleton() {
+    // singleton should return just one 
+    let mut v = vec![1];
+    assert_eq!(permutations(&mut v), vec![v]);
+}
+
+#[test]
+fn test_permutations_empty() {
+    // empty should return empty list
+    let mut v : Vec<usize>     = vec![];
+    let void : Vec<Vec<usize>> = vec![];
+    assert_eq!(permutations(&mut v ), void);
+}
+
+#[test]
+#[allow(clippy::needless_borrow)]
+fn test_permutations() {
+    // permutations for simple list
+    let mut v  = vec![1,2,3];
+    let ans = vec![
+        vec![1,2,3],
+        vec![1,3,2],
+        vec![2,1,3],
+        vec![2,3,1],
+        vec![3,2,1],
+        vec![3,1,2]
+    ];
+
+    let output = permutations(&mut v);
+
+    // check set equality
+    for v in &output{
+        assert!(ans.contains(&v));
+    }
+    for v in &ans{
+        assert!(output.contains(&v));
+    } 
+}
+
+// shortcut to create atomic types in tests
+#[allow(unused)]
+fn atom(repr : usize, align : usize) -> Type {
+    Type::Atomic(Atomic::new(repr, align))
+}
+
+#[allow(unused)]
+fn strc(members : TypeList) -> Type {
+    Type::Struct(Struct::new(members))
+}
+
+#[allow(unused)]
+fn uni(variants : TypeList) -> Type {
+    Type::Union(Union::new(variants))
+}
+
+
+#[test]
+#[allow(clippy::useless_format)]
+fn test_add_atomics() {
+    let mut manager = TypeManager::new();
+    let name = format!("int");
+    // check add ok
+    assert!(manager.add(name.clone(), atom(4,4)).is_ok()) ;
+    assert_eq!(manager.add(name, atom(4,4)), Err(TypeError::TypeRedefinition));
+    
+    // check add 0 sized should crash
+    assert_eq!(manager.add(format!("zero"), atom(0,4)), Err(TypeError::NoZeroSizedType));
+
+    // check add 0 aligned should crash
+    assert_eq!(manager.add(format!("zero"), atom(4,0)), Err(TypeError::NoZeroAlign));
+}
+
+#[test]
+#[allow(clippy::useless_format)]
+fn test_add_compound() {
+    let mut manager = TypeManager::new();
+    let _ = manager.add(format!("int"), atom(4,4));
+    let _ = manager.add(format!("char"), atom(2,4));
+
+    // check adding non existent type crashes
+    assert_eq!( 
+        manager.add(format!("s"), 
+        strc( 
+            vec!["int".to_string(), "foo".to_string()])
+        ), Err(TypeError::TypeDoesNotExist(format!("foo"))) );
+
+    // check cannot add empty compund
+    assert_eq!(
+        manager.add("s".to_string(), uni(vec![])),
+        Err(TypeError::EmptyCompoundType)
+    );
+
+    assert_eq!(
+        manager.add("s".to_string(), strc(vec![])),
+        Err(TypeError::EmptyCompoundType)
+    );
+
+    assert_eq!(
+        manager.add("s".to_string(), strc(vec!["int".to_string()])),
+        Ok(vec![])
+    );
+
+    assert_eq!(
+        manager.add("u".to_string(), uni(vec!["s".to_string(), "s".to_string(), "int".to_string() ])),
+        Ok(vec![])
+    );
+}
+
+
+#[test]
+fn test_size_struct() {
+    let mut manager = TypeManager::new();
+    let int = "int".to_string();
+    let my_char = "char".to_string();
+
+    let _ = manager.add(int.clone(), atom(4,4));
+    let _ = manager.add(my_char.clone(), atom(1,4));
+    let _ = manager.add("s1".to_string(), strc(vec![ int.clone(), my_char.clone() ]));
+    let _ = manager.add("s2".to_string(), strc(vec![my_char, int]));
+
+    // check sizes 
+    let s1 = manager.get(&"s1".to_string()).unwrap();
+    let s2 = manager.get(&"s2".to_string()).unwrap();
+    
+    // s1 it's well-sorted by default, so it has the same size for every packing
+    assert_eq!(s1.size(&manager, Struct::unpacked_size), 5);
+    assert_eq!(s1.size(&manager, Struct::optimized_size), 5);
+    assert_eq!(s1.size(&manager, Struct::packed_size), 5);
+
+    // s2 will lose some space in its unpacked version, but with optimal sorting 
+    // becomes s1
+    assert_eq!(s2.size(&manager, Struct::unpacked_size), 8);
+    assert_eq!(s2.size(&manager, Struct::optimized_size), 5);
+    assert_eq!(s2.size(&manager, Struct::packed_size), 5);
+
+}
+
+#[test]
+fn test_size_and_align_union() {
+    let mut manager = TypeManager::new();
+    let int         = "int".to_string();
+    let my_char     = "char".to_string();
+
+    let _ = manager.add(int.clone(), atom(4,4));
+    let _ = manager.add(my_char.clone(), atom(1,4));
+    let _ = manager.add("s1".to_string(), strc(vec![ int.clone(), my_char.clone() ]));
+    let _ = manager.add("s2".to_string(), strc(vec![my_char.clone(), int.clone()]));
+    let _ = manager.add("u1".to_string(), uni(vec![int.clone(), int.clone()]) );
+    let _ = manager.add("u2".to_string(), uni(vec!["s2".to_string(), "s1".to_string()]) );
+
+    // check sizes 
+    let u1 = manager.get(&"u1".to_string()).unwrap();
+    let u2 = manager.get(&"u2".to_string()).unwrap();
+    
+    // for every packing type
+    assert_eq!(u1.align(&manager, Struct::unpacked_align),  4);
+    assert_eq!(u1.align(&manager, Struct::packed_align),    4);
+    assert_eq!(u1.align(&manager, Struct::optimized_align), 4);
+    
+    // size for every packing type
+    assert_eq!(u1.size(&manager, Struct::unpacked_size),    4); 
+    assert_eq!(u1.size(&manager, Struct::packed_size),      4);
+    assert_eq!(u1.size(&manager, Struct::optimized_size),   4);
+
+    // now with a more complex union type
+    assert_eq!(u2.align(&manager, Struct::unpacked_align),  4);
+    assert_eq!(u2.align(&manager, Struct::packed_align),    4);
+    assert_eq!(u2.align(&manager, Struct::optimized_align), 4);
+
+    assert_eq!(u2.size(&manager, Struct::unpacked_size),    8); // 1 bytes for char + 3 bytes align + 4 bytes for int
+    assert_eq!(u2.size(&manager, Struct::packed_size),      5);
+    assert_eq!(u2.size(&manager, Struct::optimized_size),   5);
+}
+
+// ---- Property based tests over randomly generated type graphs ----
+
+/// Amount of random managers generated for every property
+const PROPERTY_CASES : u64 = 100;
+
+/// Run a property over many random managers, reporting the failing seed
+#[allow(unused)]
+fn for_random_managers<F>(property : F)
+    where
+        F: Fn(&TypeManager, &TypeList, u64)
+{
+    for seed in 0..PROPERTY_CASES {
+        let mut rng = Rng::new(seed);
+        let (manager, names) = generator::random_manager(&mut rng, 10);
+        property(&manager, &names, seed);
+    }
+}
+
+#[test]
+fn prop_optimized_not_bigger_than_unpacked() {
+    for_random_managers(|manager, names, seed| {
+        for name in names {
+            let t = manager.get(name).unwrap();
+            let optimized = t.size(manager, Struct::optimized_size);
+            let unpacked  = t.size(manager, Struct::unpacked_size);
+            assert!(optimized <= unpacked, "seed {}: '{}' optimized {} > unpacked {}", seed, name, optimized, unpacked);
+        }
+    });
+}
+
+#[test]
+fn prop_packed_is_the_smallest() {
+    for_random_managers(|manager, names, seed| {
+        for name in names {
+            let t = manager.get(name).unwrap();
+            let packed = t.size(manager, Struct::packed_size);
+            assert!(packed <= t.size(manager, Struct::unpacked_size),  "seed {}: '{}'", seed, name);
+            assert!(packed <= t.size(manager, Struct::optimized_size), "seed {}: '{}'", seed, name);
+        }
+    });
+}
+
+/// Check offsets for a member list: aligned, non overlapping and consistent with the total size,
+/// which may include tail padding up to `tail_align`
+#[allow(unused)]
+fn check_offsets(   manager : &TypeManager,
+                    members : &[Name],
+                    offsets : &[usize],
+                    total_size : usize,
+                    strategy : Strategy,
+                    check_align : bool,
+                    tail_align : usize
+                ) {
+    assert_eq!(members.len(), offsets.len());
+
+    let mut end = 0;
+    for (member, offset) in members.iter().zip(offsets) {
+        let t = manager.get(member).unwrap();
+        if check_align {
+            assert_eq!(offset % t.align(manager, strategy.align_fn()), 0, "member '{}' at {} is misaligned", member, offset);
+        }
+        assert!(*offset >= end, "member '{}' at {} overlaps previous member", member, offset);
+        end = offset + t.size(manager, strategy.size_fn());
+    }
+
+    // size ends right after the last member plus tail padding up to `tail_align`,
+    // models without tail padding pass 1
+    assert_eq!(total_size % tail_align, 0, "size {} is not a multiple of {}", total_size, tail_align);
+    assert_eq!(round_up(end, tail_align), total_size);
+}
+
+#[test]
+fn prop_offsets_are_consistent() {
+    for_random_managers(|manager, names, _| {
+        for name in names {
+            let s = match manager.get(name).unwrap() {
+                Type::Struct(s) => s,
+                _ => continue
+            };
+
+            let unpacked = s.unpacked_offsets(manager);
+            check_offsets(manager, &s.members, &unpacked, s.unpacked_size(manager), Strategy::Unpacked, true, 1);
+
+            let packed = s.packed_offsets(manager);
+            check_offsets(manager, &s.members, &packed, s.packed_size(manager), Strategy::Packed, false, 1);
+
+            let (layout, optimized) = s.optimized_offsets(manager);
+            check_offsets(manager, &layout, &optimized, s.optimized_size(manager), Strategy::Optimized, true, 1);
+
+            let c = s.c_offsets(manager);
+            check_offsets(manager, &s.members, &c, s.c_size(manager), Strategy::C, true, s.c_align(manager));
+        }
+    });
+}
+
+#[test]
+fn test_rng_is_deterministic() {
+    let mut a = Rng::new(42);
+    let mut b = Rng::new(42);
+    for _ in 0..100 {
+        assert_eq!(a.next_u64(), b.next_u64());
+    }
+
+    let mut rng = Rng::new(7);
+    for n in 1..50 {
+        assert!(rng.below(n) < n);
+    }
+}
+
+// ---- Fuzz-like smoke tests for the driver, see `fuzz/` for the real harness ----
+
+#[test]
+fn test_parse_random_input() {
+    // parsing random junk should only ever produce errors, never panic
+    let alphabet : Vec<char> = "atomico struct union describir salir 0123456789 -+ ñ\t".chars().collect();
+    let mut rng = Rng::new(1396);
+
+    for _ in 0..2000 {
+        let len = rng.below(40);
+        let line : String = (0..len).map(|_| alphabet[rng.below(alphabet.len())]).collect();
+        let _ = crate::driver::Program::parse(&line);
+    }
+}
+
+#[test]
+fn test_run_random_commands() {
+    let names   = ["a", "b", "c", "d"];
+    let numbers = ["0", "1", "2", "4", "8", "x", "-1", "18446744073709551615"];
+    let mut rng = Rng::new(1396);
+
+    for _ in 0..50 {
+        let mut program = crate::driver::Program::new();
+        for _ in 0..20 {
+            let name = names[rng.below(names.len())];
+            let line = match rng.below(4) {
+                0 => format!("atomico {} {} {}", name, numbers[rng.below(numbers.len())], numbers[rng.below(numbers.len())]),
+                1 => format!("struct {} {} {}", name, names[rng.below(names.len())], names[rng.below(names.len())]),
+                2 => format!("union {} {} {}", name, names[rng.below(names.len())], names[rng.below(names.len())]),
+                _ => format!("describir {}", name)
+            };
+            program.run_line(&line);
+        }
+
+        // every stored type should have consistent sizes
+        let manager = program.manager();
+        for name in manager.names() {
+            let t = manager.get(name).unwrap();
+            let packed = t.size(manager, Struct::packed_size);
+            assert!(packed <= t.size(manager, Struct::unpacked_size));
+            assert!(packed <= t.size(manager, Struct::optimized_size));
+            assert!(manager.display(name).is_ok());
+        }
+    }
+}
+
+#[test]
+fn test_size_overflow() {
+    let mut manager = TypeManager::new();
+    let _ = manager.add("huge".to_string(), atom(usize::MAX - 1, 1));
+
+    assert_eq!(
+        manager.add("s".to_string(), strc(vec!["huge".to_string(), "huge".to_string()])),
+        Err(TypeError::SizeOverflow)
+    );
+}
+
+// ---- Golden snapshot tests for reports ----
+
+/// Compare `content` against the stored snapshot `name`. 
+/// Run tests with `UPDATE_SNAPSHOTS=1` to create or update snapshots after an intended change
+#[allow(unused)]
+fn assert_snapshot(name : &str, content : &str) {
+    let path = format!("{}/src/test_suite/snapshots/{}.snap", env!("CARGO_MANIFEST_DIR"), name);
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(&path, content).expect("Couldn't write snapshot");
+        return
+    }
+
+    let expected = std::fs::read_to_string(&path)
+                    .unwrap_or_else(|_| panic!("Snapshot '{}' not found, run with UPDATE_SNAPSHOTS=1 to create it", name));
+
+    assert_eq!(expected, content, "Snapshot '{}' doesn't match, run with UPDATE_SNAPSHOTS=1 if the change is intended", name);
+}
+
+/// Manager with a representative set of atomics, structs and unions
+#[allow(unused)]
+fn snapshot_manager() -> TypeManager {
+    let mut manager = TypeManager::new();
+    let defs = vec![
+        ("char",   atom(1, 1)),
+        ("short",  atom(2, 2)),
+        ("int",    atom(4, 4)),
+        ("double", atom(8, 8)),
+        ("mixed",  strc(vec!["char".to_string(), "int".to_string(), "char".to_string(), "short".to_string()])),
+        ("nested", strc(vec!["char".to_string(), "mixed".to_string(), "double".to_string()])),
+        ("u",      uni(vec!["mixed".to_string(), "double".to_string(), "char".to_string()])),
+        ("holder", strc(vec!["char".to_string(), "u".to_string(), "short".to_string()]))
+    ];
+
+    for (name, t) in defs {
+        manager.add(name.to_string(), t).unwrap();
+    }
+
+    manager
+}
+
+#[test]
+fn snapshot_describe_atomic() {
+    let manager = snapshot_manager();
+    assert_snapshot("describe_atomic", &manager.display(&"double".to_string()).unwrap());
+}
+
+#[test]
+fn snapshot_describe_struct() {
+    let manager = snapshot_manager();
+    assert_snapshot("describe_struct", &manager.display(&"mixed".to_string()).unwrap());
+    assert_snapshot("describe_nested_struct", &manager.display(&"nested".to_string()).unwrap());
+}
+
+#[test]
+fn snapshot_describe_union() {
+    let manager = snapshot_manager();
+    assert_snapshot("describe_union", &manager.display(&"u".to_string()).unwrap());
+    assert_snapshot("describe_union_in_struct", &manager.display(&"holder".to_string()).unwrap());
+}
+
+#[test]
+fn test_report_is_structured() {
+    let manager = snapshot_manager();
+    let report = manager.get(&"mixed".to_string()).unwrap().report(&manager);
+
+    // one entry per strategy, in report order
+    match report {
+        Report::Struct(strategies) => {
+            let order : Vec<Strategy> = strategies.iter().map(|s| s.strategy).collect();
+            assert_eq!(order, Strategy::ALL.to_vec());
+            assert!(strategies.iter().all(|s| s.size >= 8));
+        },
+        _ => panic!("expected a struct report")
+    }
+}
+
+// ---- Differential tests against a real C compiler ----
+
+#[test]
+fn test_c_strategy() {
+    let mut manager = TypeManager::new();
+    let _ = manager.add("char".to_string(), atom(1, 1));
+    let _ = manager.add("int".to_string(), atom(4, 4));
+    let _ = manager.add("s".to_string(), strc(vec!["int".to_string(), "char".to_string()]));
+    let _ = manager.add("u".to_string(), uni(vec!["s".to_string(), "char".to_string()]));
+
+    // tail padding up to the biggest alignment
+    let s = manager.get(&"s".to_string()).unwrap();
+    assert_eq!(Strategy::C.type_size(s, &manager), 8);
+    assert_eq!(Strategy::C.type_align(s, &manager), 4);
+
+    let u = manager.get(&"u".to_string()).unwrap();
+    assert_eq!(Strategy::C.type_size(u, &manager), 8);
+    assert_eq!(Strategy::C.type_align(u, &manager), 4);
+}
+
+/// Compile random definitions checked with `_Static_assert` using the system C compiler
+/// (`$CC` or `cc`). Set `TM_DIFF_CASES` to run more cases
+#[test]
+fn test_c_strategy_matches_c_compiler() {
+    use std::process::Command;
+
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    if Command::new(&cc).arg("--version").output().is_err() {
+        eprintln!("No C compiler found, skipping differential test");
+        return
+    }
+
+    let cases = std::env::var("TM_DIFF_CASES").ok().and_then(|n| n.parse().ok()).unwrap_or(20);
+    let dir = std::env::temp_dir();
+
+    for seed in 0..cases {
+        let mut rng = Rng::new(seed);
+        let (manager, names) = generator::random_c_manager(&mut rng, 10);
+        let code = crate::codegen::c_layout_check(&manager, &names);
+
+        let path = dir.join(format!("typemanager_diff_{}_{}.c", std::process::id(), seed));
+        std::fs::write(&path, &code).unwrap();
+        let output = Command::new(&cc).args(["-std=c11", "-fsyntax-only"]).arg(&path).output().unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert!(
+            output.status.success(),
+            "seed {}: C compiler disagrees with the simulator:\n{}\n{}",
+            seed,
+            String::from_utf8_lossy(&output.stderr),
+            code
+        );
+    }
+}
+
+// ---- Optimizer statistics and JSON output ----
+
+#[test]
+fn test_optimizer_stats() {
+    let mut manager = TypeManager::new();
+    let _ = manager.add("char".to_string(), atom(1, 1));
+    let _ = manager.add("int".to_string(), atom(4, 4));
+    let _ = manager.add("s".to_string(), strc(vec!["char".to_string(), "int".to_string(), "char".to_string(), "int".to_string()]));
+
+    let stats = manager.optimizer_stats(&"s".to_string()).unwrap().unwrap();
+    assert_eq!(stats.permutations, 24);
+    assert!(stats.pruned < stats.permutations);
+
+    // pruning doesn't change the result
+    let s = manager.get(&"s".to_string()).unwrap();
+    assert_eq!(s.size(&manager, Struct::optimized_size), 10);
+
+    // nothing to optimize for other types
+    assert_eq!(manager.optimizer_stats(&"int".to_string()), Ok(None));
+    assert!(manager.optimizer_stats(&"foo".to_string()).is_err());
+}
+
+#[test]
+fn test_json_output() {
+    use crate::json::Value;
+
+    let value = Value::object(vec![
+        ("name", Value::str("a \"quoted\"\nname")),
+        ("list", Value::Array(vec![Value::uint(1), Value::Bool(false), Value::Null]))
+    ]);
+    assert_eq!(value.to_string(), r#"{"name":"a \"quoted\"\nname","list":[1,false,null]}"#);
+
+    let manager = snapshot_manager();
+    let json = manager.report(&"int".to_string()).unwrap().to_json(&"int".to_string());
+    assert_eq!(json.to_string(), r#"{"name":"int","kind":"atomic","size":4,"align":4}"#);
+}
+
+#[test]
+fn test_parse_display_options() {
+    use crate::driver::*;
+
+    match Program::parse("describir s --stats --json") {
+        Ok(Action::Display(name, options)) => {
+            assert_eq!(name, "s");
+            assert!(options.stats && options.json);
+        },
+        _ => panic!("expected a display action")
+    }
+
+    assert!(Program::parse("describir s --nope").is_err());
+    assert!(Program::parse("describir s --estrategia nada").is_err());
+    assert!(matches!(Program::parse("describir s t"), Ok(Action::DisplayMatching(names, _)) if names == ["s", "t"]));
+}
+
+// ---- Config and scripts ----
+
+#[test]
+fn test_config_parse() {
+    use crate::config::Config;
+
+    let (config, warnings) = Config::parse("# comentario\ndefiniciones = \"base.tm\"\nautocargar = no\n\nfoo = 1\nbar\n");
+    assert_eq!(config.definitions, std::path::PathBuf::from("base.tm"));
+    assert!(!config.autoload);
+    assert_eq!(warnings.len(), 2);
+    assert!(warnings[0].starts_with("línea 5"));
+
+    // a `#` between quotes is part of the value
+    let (config, warnings) = Config::parse("prompt = \"#{n}> \" # comentario\ndiario = \"registro#1.tmj\"\n");
+    assert!(warnings.is_empty(), "{:?}", warnings);
+    assert_eq!(config.prompt, "#{n}> ");
+    assert_eq!(config.journal, Some(std::path::PathBuf::from("registro#1.tmj")));
+
+    // paths are relative to the config file, unless absolute
+    let mut config = config;
+    config.recovery = std::path::PathBuf::from("/tmp/recuperacion.tm");
+    config.relative_to(std::path::Path::new("/etc/typemanager"));
+    assert_eq!(config.journal, Some(std::path::PathBuf::from("/etc/typemanager/registro#1.tmj")));
+    assert_eq!(config.definitions, std::path::Path::new("/etc/typemanager").join(crate::config::DEFAULT_DEFINITIONS));
+    assert_eq!(config.recovery, std::path::PathBuf::from("/tmp/recuperacion.tm"));
+}
+
+#[test]
+fn test_run_script() {
+    let mut program = crate::driver::Program::new();
+    let script = "# tipos\natomico int 4 4\n\nstruct s int foo\nstruct p int int\nsalir\natomico char 1 1\n";
+    let summary = program.run_script(script, "types.tm");
+
+    // salir ends the script, not the program
+    assert_eq!(summary.defined, 2);
+    assert_eq!(summary.errors, vec!["types.tm:4: [TYPE ERROR]: El símbolo 'foo' no existe".to_string()]);
+    assert!(program.should_run());
+    assert!(program.manager().get(&"char".to_string()).is_none());
+}
+
+// ---- Session autosave ----
+
+#[test]
+fn test_session_script_round_trip() {
+    let mut program = crate::driver::Program::new();
+    program.run_script("atomico int 4 4\natomico char 1 1\nstruct b char int\nunion a b int\n", "test");
+
+    // dependencies always come first
+    assert_eq!(program.manager().definition_order(), vec!["int", "char", "b", "a"]);
+
+    let script = program.session_script();
+    let mut restored = crate::driver::Program::new();
+    let summary = restored.run_script(&script, "recovery");
+    assert_eq!(summary.defined, 4);
+    assert!(summary.errors.is_empty());
+    assert_eq!(restored.session_script(), script);
+}
+
+#[test]
+fn test_autosave_and_clean_exit() {
+    use crate::config::Config;
+
+    let path = std::env::temp_dir().join(format!("typemanager_recovery_{}.tm", std::process::id()));
+    let config = Config { recovery: path.clone(), autosave: true, ..Config::default() };
+    let mut program = crate::driver::Program::with_config(config);
+
+    // failed commands don't touch the recovery file
+    program.run_line("struct s foo");
+    assert!(!path.exists());
+
+    program.run_line("atomico int 4 4");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), program.session_script());
+
+    // a script is saved once, when the command running it ends
+    let script = std::env::temp_dir().join(format!("typemanager_recovery_script_{}.tm", std::process::id()));
+    std::fs::write(&script, "atomico char 1 1\nstruct s char int\n").unwrap();
+    program.run_line(&format!("incluir {}", script.display()));
+    let _ = std::fs::remove_file(&script);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), program.session_script());
+
+    // a clean exit leaves nothing to recover
+    program.run_line("salir");
+    assert!(!path.exists());
+}
+
+// ---- Output layer, listing and describing everything ----
+
+#[test]
+fn test_list_and_describe_all() {
+    let mut program = crate::driver::Program::new();
+    program.capture_output();
+    program.run_script("atomico int 4 4\natomico char 1 1\nstruct s char int\nunion u s int\n", "test");
+    assert_eq!(program.take_output(), "");
+
+    program.run_line("listar");
+    let output = program.take_output();
+    let names : Vec<&str> = output.lines().skip(3).filter_map(|l| l.split('│').nth(1)).map(|c| c.trim()).collect();
+    assert_eq!(names, vec!["int", "char", "s", "u"]);
+
+    // every type is described, dependencies first
+    program.run_line("describir todo --json");
+    let output = program.take_output();
+    let names : Vec<&str> = output.lines().map(|l| l.split('"').nth(3).unwrap()).collect();
+    assert_eq!(names, vec!["int", "char", "s", "u"]);
+
+    program.run_line("listar algo");
+    assert_eq!(program.take_output(), "[ERROR]: Demasiados argumentos\n");
+}
+
+// ---- Tables ----
+
+#[test]
+fn test_table_render() {
+    use crate::utils::table::*;
+
+    let mut table = Table::new(&["Nombre", "Tamaño"]).align(1, Align::Right);
+    assert!(table.is_empty());
+    table.row(vec!["char".to_string(), "1".to_string()]);
+    table.row(vec!["estructura".to_string(), "128".to_string()]);
+    assert_eq!(table.len(), 2);
+
+    let expected = "\
++------------+--------+
+| Nombre     | Tamaño |
++------------+--------+
+| char       |      1 |
+| estructura |    128 |
++------------+--------+
+";
+    assert_eq!(table.render(TableStyle::Ascii), expected);
+
+    // same shape in unicode, every line as wide as the others
+    let unicode = table.render(TableStyle::Unicode);
+    assert!(unicode.starts_with('┌'));
+    let widths : Vec<usize> = unicode.lines().map(|l| l.chars().count()).collect();
+    assert!(widths.iter().all(|w| *w == widths[0]));
+}
+
+#[test]
+fn test_offsets_command() {
+    use crate::config::Config;
+    use crate::utils::table::TableStyle;
+
+    let config = Config { autosave: false, tables: TableStyle::Ascii, ..Config::default() };
+    let mut program = crate::driver::Program::with_config(config);
+    program.capture_output();
+    program.run_script("atomico int 4 4\natomico char 1 1\nstruct s char int char\n", "test");
+
+    program.run_line("desplazamientos s");
+    let expected = "\
+Desplazamientos de s:
++---+-------+------+----------------+-------------+---+
+| # | Campo | Tipo | Sin Empaquetar | Empaquetado | C |
++---+-------+------+----------------+-------------+---+
+| 0 | m0    | char |              0 |           0 | 0 |
+| 1 | m1    | int  |              4 |           1 | 4 |
+| 2 | m2    | char |              8 |           5 | 8 |
++---+-------+------+----------------+-------------+---+
+Optimizado:
++---+-------+------+----------------+--------+
+| # | Campo | Tipo | Desplazamiento | Tamaño |
++---+-------+------+----------------+--------+
+| 1 | m1    | int  |              0 |      4 |
+| 0 | m0    | char |              4 |      1 |
+| 2 | m2    | char |              5 |      1 |
++---+-------+------+----------------+--------+
+";
+    assert_eq!(program.take_output(), expected);
+
+    program.run_line("desplazamientos int");
+    assert_eq!(program.take_output(), "[TYPE ERROR]: El tipo 'int' no es un struct ('int' se definió en test:1)\n");
+
+    assert_eq!(Config::parse("tablas = ascii").0.tables, TableStyle::Ascii);
+    assert_eq!(Config::parse("tablas = lineas").1.len(), 1);
+}
+
+// ---- Command line ----
+
+#[test]
+fn test_cli_parse() {
+    use crate::cli::{self, Command, Shell};
+    use crate::driver::ExportFormat;
+    use std::path::PathBuf;
+
+    let parse = |args : &str| cli::parse(args.split_whitespace().map(|s| s.to_string()));
+
+    assert_eq!(parse(""), Ok(Command::Repl));
+    assert_eq!(parse("run a.tm b.tm"), Ok(Command::Run(vec![PathBuf::from("a.tm"), PathBuf::from("b.tm")])));
+    assert_eq!(parse("export"), Ok(Command::Export(ExportFormat::Json, None)));
+    assert_eq!(parse("export --format c tipos.tm"), Ok(Command::Export(ExportFormat::C, Some(PathBuf::from("tipos.tm")))));
+    assert_eq!(parse("export --format=tm"), Ok(Command::Export(ExportFormat::Script, None)));
+    assert_eq!(parse("serve -p 9000"), Ok(Command::Serve(9000, None)));
+    assert_eq!(parse("completions fish"), Ok(Command::Completions(Shell::Fish)));
+    assert_eq!(parse("--stdio-json"), Ok(Command::StdioJson));
+
+    assert!(parse("run").is_err());
+    assert!(parse("export --format xml").is_err());
+    assert!(parse("export --format").is_err());
+    assert!(parse("serve --port 70000").is_err());
+    assert!(parse("completions powershell").is_err());
+    assert!(parse("volar").is_err());
+    assert!(parse("--stdio-json s.tm").is_err());
+
+    // every subcommand can be completed in every shell
+    for shell in [Shell::Bash, Shell::Zsh, Shell::Fish].iter() {
+        let script = cli::completions(*shell, "typemanager");
+        for sub in ["repl", "run", "export", "serve", "completions"].iter() {
+            assert!(script.contains(sub), "{:?} completions miss '{}'", shell, sub);
+        }
+    }
+}
+
+#[test]
+fn test_serve_and_export() {
+    use crate::driver::{Program, ExportFormat};
+
+    let mut program = Program::new();
+    let input = "atomico int 4 4\n\nstruct s int int\nvolar\nsalir\natomico char 1 1\n";
+    let mut output = vec![];
+    program.serve(std::io::Cursor::new(input), &mut output).unwrap();
+
+    // commands after salir are never run
+    assert_eq!(String::from_utf8(output).unwrap(), "[ERROR]: 'volar' no es una acción válida\n");
+    assert!(program.manager().get(&"char".to_string()).is_none());
+
+    let exported = program.export(ExportFormat::Json);
+    assert!(exported.starts_with(r#"[{"name":"int","kind":"atomic","size":4,"align":4},{"name":"s","kind":"struct""#));
+    assert_eq!(program.export(ExportFormat::Script), program.session_script());
+    assert!(program.export(ExportFormat::C).contains("_Static_assert(sizeof(s) == 8"));
+}
+
+// ---- Named fields and the struct wizard ----
+
+#[test]
+fn test_named_fields() {
+    let mut program = crate::driver::Program::new();
+    program.capture_output();
+    program.run_script("atomico int 4 4\natomico char 1 1\nstruct s tag:char @fijo int valor:int @grupo=red @caliente\n", "test");
+    assert_eq!(program.take_output(), "");
+
+    let s = match program.manager().get(&"s".to_string()) {
+        Some(Type::Struct(s)) => s,
+        _ => panic!("s should be a struct")
+    };
+    let names : Vec<&str> = s.fields.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, vec!["tag", "m1", "valor"]);
+    assert_eq!(s.field_index("valor"), Some(2));
+    assert_eq!(s.fields[2].attributes, vec![
+        Attribute { key: "grupo".to_string(), value: Some("red".to_string()) },
+        Attribute { key: "caliente".to_string(), value: None }
+    ]);
+
+    // the definition is written back the same way
+    assert!(program.session_script().contains("struct s tag:char @fijo int valor:int @grupo=red @caliente\n"));
+
+    let errors = [
+        ("struct a x:int x:char", "[TYPE ERROR]: El campo 'x' está repetido\n"),
+        ("struct a @aligned=3 int", "[TYPE ERROR]: El atributo '@aligned=3' no es válido, la alineación debe ser una potencia de 2\n"),
+        ("struct a 1x:int",       "[ERROR]: Este no es un argumento válido: 1x:int\n"),
+        ("struct a int @=1",      "[ERROR]: Este no es un argumento válido: @=1\n")
+    ];
+    for (line, expected) in errors.iter() {
+        program.run_line(line);
+        assert_eq!(program.take_output(), *expected, "running '{}'", line);
+    }
+}
+
+#[test]
+fn test_wizard() {
+    let mut program = crate::driver::Program::new();
+    program.capture_output();
+    program.run_script("atomico int 4 4\natomico int8 1 1\natomico char 1 1\n", "test");
+
+    let answers = [
+        "asistente",
+        "int",          // already exists
+        "punto",
+        "",             // no fields yet
+        "x",
+        "flotante",     // doesn't exist
+        "in",           // ambiguous
+        "int",
+        "@caliente",
+        "x",            // repeated
+        "tag",
+        "ch",           // completed
+        "caliente",     // not an attribute
+        "",
+        "fin"
+    ];
+    for a in answers.iter() {
+        program.run_line(a);
+    }
+
+    let expected = "\
+Asistente para definir structs, escribe 'cancelar' para salir
+Nombre del struct:
+No puedes redefinir un tipo ya existente
+Nombre del struct:
+Campo #0 de 'punto', nombre ('fin' o vacío para terminar):
+El struct necesita al menos un campo
+Campo #0 de 'punto', nombre ('fin' o vacío para terminar):
+Tipo de 'x' ('?' lista los tipos):
+El símbolo 'flotante' no existe
+Tipo de 'x' ('?' lista los tipos):
+'in' es ambiguo: int, int8
+Tipo de 'x' ('?' lista los tipos):
+Atributos de 'x' (ej. @caliente @grupo=red), vacío para ninguno:
+Campo #1 de 'punto', nombre ('fin' o vacío para terminar):
+El campo 'x' está repetido
+Campo #1 de 'punto', nombre ('fin' o vacío para terminar):
+Tipo de 'tag' ('?' lista los tipos):
+Completado a 'char'
+Atributos de 'tag' (ej. @caliente @grupo=red), vacío para ninguno:
+'caliente' no es un atributo válido, se esperaba @clave o @clave=valor
+Atributos de 'tag' (ej. @caliente @grupo=red), vacío para ninguno:
+Campo #2 de 'punto', nombre ('fin' o vacío para terminar):
+Struct 'punto' definido
+";
+    assert_eq!(program.take_output(), expected);
+    assert_eq!(program.prompt(), ">> ");
+    assert!(program.session_script().contains("struct punto x:int @caliente tag:char\n"));
+
+    // cancelling leaves everything as it was, scripts can drive the wizard too
+    let summary = program.run_script("asistente\nvacio\ncancelar\nasistente\nlinea\na\nint\n\nfin\n", "test");
+    assert_eq!(summary.defined, 1);
+    assert!(program.manager().get(&"vacio".to_string()).is_none());
+    assert!(program.manager().get(&"linea".to_string()).is_some());
+}
+
+// ---- Plugins ----
+
+/// Plugin defining an alias: a struct with a single member of the given type
+struct Alias;
+
+impl crate::driver::CommandPlugin for Alias {
+    fn name(&self) -> &str {
+        "alias"
+    }
+
+    fn parse(&self, args: &[&str]) -> Result<Vec<String>, crate::driver::ProgramError> {
+        match args.len() {
+            2 => Ok(args.iter().map(|s| s.to_string()).collect()),
+            _ => Err(crate::driver::ProgramError::NotEnoughArgs)
+        }
+    }
+
+    fn execute(&mut self, args: &[String], manager: &mut TypeManager) -> Result<String, TypeError> {
+        manager.add(args[0].clone(), strc(vec![args[1].clone()]))?;
+        Ok(format!("{} = {}", args[0], args[1]))
+    }
+
+    fn modifies(&self) -> bool {
+        true
+    }
+}
+
+/// Plugin trying to take a built in command name
+struct Impostor;
+
+impl crate::driver::CommandPlugin for Impostor {
+    fn name(&self) -> &str {
+        "listar"
+    }
+
+    fn parse(&self, _: &[&str]) -> Result<Vec<String>, crate::driver::ProgramError> {
+        Ok(vec![])
+    }
+
+    fn execute(&mut self, _: &[String], _: &mut TypeManager) -> Result<String, TypeError> {
+        Ok(String::new())
+    }
+}
+
+#[test]
+fn test_plugins() {
+    use crate::driver::{Program, ProgramError};
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("atomico int 4 4\natomico char 1 1\nstruct s int char\nunion u s int\n", "test");
+
+    // shipped with the program
+    program.run_line("dependencias s");
+    assert_eq!(program.take_output(), "Usa: int, char\nUsado por: u\n");
+    program.run_line("dependencias u");
+    assert_eq!(program.take_output(), "Usa: int, char, s\nUsado por: (ninguno)\n");
+    program.run_line("dependencias nada");
+    assert_eq!(program.take_output(), "[TYPE ERROR]: El símbolo 'nada' no existe\n");
+
+    // unknown until registered
+    program.run_line("alias entero int");
+    assert_eq!(program.take_output(), "[ERROR]: 'alias' no es una acción válida\n");
+
+    assert_eq!(program.register_plugin(Box::new(Alias)), Ok(()));
+    assert_eq!(program.register_plugin(Box::new(Alias)), Err(ProgramError::DuplicateCommand("alias".to_string())));
+    assert_eq!(program.register_plugin(Box::new(Impostor)), Err(ProgramError::DuplicateCommand("listar".to_string())));
+
+    program.run_line("alias entero int");
+    assert_eq!(program.take_output(), "entero = int\n");
+    assert!(program.manager().get(&"entero".to_string()).is_some());
+
+    // plugins work in scripts too, argument errors are reported as usual
+    let summary = program.run_script("alias corto char\nalias x\n", "test");
+    assert_eq!(summary.errors, vec!["test:2: [ERROR]: No hay suficientes argumentos".to_string()]);
+    assert!(program.manager().get(&"corto".to_string()).is_some());
+}
+
+// ---- Macros ----
+
+#[test]
+fn test_macros() {
+    use crate::driver::{Program, Macro};
+
+    let mut program = Program::new();
+    program.capture_output();
+    let script = "\
+atomico int 4 4
+atomico char 1 1
+atomico short 2 2
+macro par(T) => struct par_T { a:T, b:T }
+par(int)
+par(char)
+macro envuelto(T, N) => par(T); struct N x:par_T @fijo y:T
+envuelto(short, caja)
+par(int)
+envuelto(long, x)
+par(int, char)
+";
+    let summary = program.run_script(script, "macros.tm");
+    assert_eq!(summary.defined, 7);
+    assert_eq!(summary.errors, vec![
+        "macros.tm:9: [TYPE ERROR]: No puedes redefinir un tipo ya existente ('par_int' se definió en macros.tm:5) \
+         (en 'struct par_int { a:int, b:int }', de la macro 'par' definida en macros.tm:4)".to_string(),
+        "macros.tm:10: [TYPE ERROR]: El símbolo 'long' no existe \
+         (en 'struct par_long { a:long, b:long }', de la macro 'par' definida en macros.tm:4) \
+         (en 'par(long)', de la macro 'envuelto' definida en macros.tm:7)".to_string(),
+        "macros.tm:11: [ERROR]: La macro 'par' recibe 1 argumentos, pero se dieron 2".to_string()
+    ]);
+
+    // the expanded definitions are regular types
+    let caja = match program.manager().get(&"caja".to_string()) {
+        Some(Type::Struct(s)) => s,
+        _ => panic!("caja should be a struct")
+    };
+    assert_eq!(caja.members, vec!["par_short".to_string(), "short".to_string()]);
+    assert_eq!(caja.fields[0].attributes.len(), 1);
+
+    // a macro calling itself forever stops, pointing at it once
+    program.run_line("macro eco(X) => eco(X)");
+    program.run_line("eco(1)");
+    assert_eq!(
+        program.take_output(), 
+        "[ERROR]: Demasiadas expansiones anidadas al expandir la macro 'eco' (en 'eco(1)', de la macro 'eco' definida en <interactivo>)\n"
+    );
+
+    // substitution only replaces whole words
+    let (name, params, body) = Macro::parse_definition("m(T) => struct T_s Tipo:T tT:T_T").unwrap();
+    assert_eq!((name.as_str(), params.clone()), ("m", vec!["T".to_string()]));
+    let m = Macro { params, body, origin: String::new() };
+    assert_eq!(m.expand("m", &["int".to_string()]), Ok(vec!["struct int_s Tipo:int tT:int_int".to_string()]));
+
+    for bad in ["m(T) =>", "m(T, T) => x", "m(T_1) => x", "1m() => x", "m(T) x"].iter() {
+        assert!(Macro::parse_definition(bad).is_err(), "'{}' should not parse", bad);
+    }
+}
+
+// ---- Expressions ----
+
+#[test]
+fn test_expressions() {
+    use crate::expr::{Expr, ExprError, split_arguments};
+
+    let mut manager = TypeManager::new();
+    manager.add("int".to_string(), Type::Atomic(Atomic::new(4, 4))).unwrap();
+    manager.add("s".to_string(), strc(vec!["int".to_string()])).unwrap();
+
+    let eval = |text : &str| Expr::parse(text).and_then(|e| e.eval(&manager));
+
+    assert_eq!(eval("1 + 2 * 3"), Ok(7));
+    assert_eq!(eval("(1 + 2) * 3"), Ok(9));
+    assert_eq!(eval("10 - 4 - 3"), Ok(3));
+    assert_eq!(eval("-int.size + 0x10 % 5"), Ok(-3));
+    assert_eq!(eval("int.size/2"), Ok(2));
+    assert_eq!(eval("int.tamaño * int.alineacion"), Ok(16));
+    assert_eq!(eval("1_024 / 0b10"), Ok(512));
+
+    assert_eq!(eval("1 / (int.size - 4)"), Err(ExprError::DivisionByZero));
+    assert_eq!(eval("5 % 0"), Err(ExprError::DivisionByZero));
+    assert_eq!(eval("9223372036854775807 + 1"), Err(ExprError::Overflow));
+    assert_eq!(eval("long.size"), Err(ExprError::UnknownType("long".to_string())));
+    assert_eq!(eval("s.size"), Err(ExprError::NotAtomic("s".to_string())));
+    assert_eq!(eval("int.peso"), Err(ExprError::UnknownProperty("peso".to_string())));
+    assert_eq!(eval("int"), Err(ExprError::UnknownName("int".to_string())));
+    for bad in ["", "1 +", "(1", "1)", "int.", "2 3", "1 $ 2", "0xZZ", "1 = 2", "f(1,", "f(1 2)"].iter() {
+        assert!(matches!(eval(bad), Err(ExprError::Syntax(_))), "'{}' should be a syntax error", bad);
+    }
+
+    assert_eq!(Expr::parse("3 - 5").unwrap().eval_natural(&manager), Err(ExprError::Negative(-2)));
+    assert_eq!(split_arguments(" a (int.size + 1)  4 "), vec!["a", "(int.size + 1)", "4"]);
+}
+
+#[test]
+fn test_atomic_expressions() {
+    let mut program = crate::driver::Program::new();
+    program.capture_output();
+    let summary = program.run_script("atomico int 4 4\natomico medio int.size/2 (int.align - 2)\natomico mal 1/0 1\n", "test");
+
+    assert_eq!(summary.errors, vec!["test:3: [TYPE ERROR]: División por cero".to_string()]);
+    match program.manager().get(&"medio".to_string()) {
+        Some(Type::Atomic(a)) => assert_eq!((a.representation, a.alignment), (2, 2)),
+        _ => panic!("medio should be an atomic type")
+    }
+
+    // saved with the computed values
+    assert!(program.session_script().contains("atomico medio 2 2\n"));
+
+    program.run_line("atomico x (1 +) 1");
+    assert_eq!(program.take_output(), "[ERROR]: Expresión inválida: no se esperaba ')'\n");
+}
+
+#[test]
+fn test_calculate() {
+    use crate::expr::{Expr, ExprError};
+
+    let mut program = crate::driver::Program::new();
+    program.capture_output();
+    program.run_script("atomico int 4 4\natomico char 1 1\nstruct s a:char b:int c:char\nunion u s int\n", "test");
+
+    let cases = [
+        ("calcular tamaño(s)",                         "12"),
+        ("calcular tamaño(s, empaquetado) + 1",        "7"),
+        ("calcular sizeof(s, sin_empaquetar)",         "9"),
+        ("calcular alineacion(u) * 2",                 "8"),
+        ("calcular offset(s, c)",                      "8"),
+        ("calcular offset(s, c, Optimizado)",          "5"),
+        ("calcular offset(s, b, packed)",              "1"),
+        ("calcular tamaño(s) == 3 * int.size",         "verdadero"),
+        ("calcular tamaño(s, c) < tamaño(s, packed)",  "falso"),
+        ("calcular (tamaño(u) >= 12) + 1",             "2")
+    ];
+    for (line, expected) in cases.iter() {
+        program.run_line(line);
+        assert_eq!(program.take_output(), format!("{}\n", expected), "running '{}'", line);
+    }
+
+    let manager = program.manager();
+    let eval = |text : &str| Expr::parse(text).and_then(|e| e.eval(manager));
+    assert_eq!(eval("offset(s, z)"), Err(ExprError::UnknownField("s".to_string(), "z".to_string())));
+    assert_eq!(eval("offset(u, a)"), Err(ExprError::NotAStruct("u".to_string())));
+    assert_eq!(eval("tamaño(s, mucho)"), Err(ExprError::UnknownStrategy("mucho".to_string())));
+    assert_eq!(eval("tamaño(nada)"), Err(ExprError::UnknownType("nada".to_string())));
+    assert_eq!(eval("raiz(4)"), Err(ExprError::UnknownFunction("raiz".to_string())));
+    assert!(matches!(eval("tamaño(1)"), Err(ExprError::Arguments(..))));
+    assert!(matches!(eval("offset(s)"), Err(ExprError::Arguments(..))));
+
+    assert_eq!(Strategy::parse("Sin Empaquetar"), Some(Strategy::Unpacked));
+    assert_eq!(Strategy::parse("sin-empaquetar"), Some(Strategy::Unpacked));
+    assert_eq!(Strategy::parse("OPTIMIZED"), Some(Strategy::Optimized));
+    assert_eq!(Strategy::parse("nada"), None);
+}
+
+#[test]
+fn test_variables() {
+    use crate::expr::ExprError;
+    use crate::driver::{CommandError, ProgramError};
+
+    let mut program = crate::driver::Program::new();
+    program.capture_output();
+    program.run_script("atomico int 4 4\nstruct h a:int b:int\n", "test");
+
+    let cases = [
+        ("let s = tamaño(h)",       "s = 8"),
+        ("calcular $s * 2",         "16"),
+        ("calcular $_ + 1",         "17"),
+        ("let t = $_ - $s",         "t = 9"),
+        ("calcular $_",             "9"),
+        ("variables",               "s = 8\nt = 9\n$_ = 9")
+    ];
+    for (line, expected) in cases.iter() {
+        program.run_line(line);
+        assert_eq!(program.take_output(), format!("{}\n", expected), "running '{}'", line);
+    }
+
+    // variables work wherever an expression does
+    program.run_line("atomico bloque $t ($s / 2)");
+    match program.manager().get(&"bloque".to_string()) {
+        Some(Type::Atomic(a)) => assert_eq!((a.representation, a.alignment), (9, 4)),
+        other => panic!("expected an atomic type, got {:?}", other)
+    }
+
+    let expect_error = |program : &mut crate::driver::Program, line : &str, expected : CommandError| {
+        assert_eq!(program.run_command(line), Err(expected), "running '{}'", line);
+    };
+    expect_error(&mut program, "calcular $nada", CommandError::Type(TypeError::Expression(ExprError::UnknownName("$nada".to_string()))));
+    expect_error(&mut program, "let 1a = 3", CommandError::Program(ProgramError::InvalidArgument("1a".to_string())));
+    expect_error(&mut program, "let x", CommandError::Program(ProgramError::NotEnoughArgs));
+    expect_error(&mut program, "let x =", CommandError::Program(ProgramError::NotEnoughArgs));
+    expect_error(&mut program, "variables x", CommandError::Program(ProgramError::TooManyArgs));
+}
+
+#[test]
+fn test_include() {
+    use crate::driver::{CommandError, ProgramError};
+
+    let dir = std::env::temp_dir().join(format!("typemanager_include_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::write(dir.join("main.tm"), "atomico int 4 4\nincluir sub/base.tm\nstruct s int base\n").unwrap();
+    std::fs::write(dir.join("sub/base.tm"), "# relative to this file\nincluir char.tm\nstruct base char int\n").unwrap();
+    std::fs::write(dir.join("sub/char.tm"), "atomico char 1 1\n").unwrap();
+    std::fs::write(dir.join("a.tm"), "atomico a 1 1\nincluir b.tm\n").unwrap();
+    std::fs::write(dir.join("b.tm"), "atomico b 1 1\nincluir a.tm\n").unwrap();
+
+    let mut program = crate::driver::Program::new();
+    let summary = program.run_file(&dir.join("main.tm")).unwrap();
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+    assert_eq!(summary.defined, 4);
+    assert_eq!(program.manager().get(&"s".to_string()).map(|t| t.size(program.manager(), Strategy::C.size_fn())), Some(12));
+
+    // the cycle is reported where it closes, inside the first included file
+    let summary = program.run_file(&dir.join("a.tm")).unwrap();
+    assert_eq!(summary.errors.len(), 1);
+    let error = &summary.errors[0];
+    assert!(error.starts_with(&format!("{}:2: ", dir.join("a.tm").display())), "{}", error);
+    assert!(error.contains(&format!("{}:2: ", dir.join("b.tm").display())), "{}", error);
+    assert!(error.contains("Inclusión cíclica"), "{}", error);
+    assert_eq!(summary.defined, 2);
+
+    // from the REPL paths are relative to the working directory
+    let missing = dir.join("nada.tm").display().to_string();
+    match program.run_command(&format!("incluir {}", missing)) {
+        Err(CommandError::Program(ProgramError::IncludeRead(file, _))) => assert_eq!(file, missing),
+        other => panic!("expected a read error, got {:?}", other)
+    }
+    assert_eq!(program.run_command("incluir"), Err(CommandError::Program(ProgramError::NotEnoughArgs)));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_history() {
+    use crate::driver::{CommandError, ProgramError};
+
+    let mut program = crate::driver::Program::new();
+    program.capture_output();
+
+    // a typo is fixed with a substitution on the last command
+    program.run_line("atomico int 4 4");
+    program.run_line("struct s int chr");
+    program.take_output();
+    program.run_line("^chr^int");
+    assert_eq!(program.take_output(), "struct s int int\n");
+    assert!(program.manager().get(&"s".to_string()).is_some());
+
+    program.run_line("!1");
+    assert_eq!(program.take_output(), format!("atomico int 4 4\n{}\n", CommandError::Type(TypeError::TypeRedefinition).display()));
+    program.run_line("!!");
+    assert!(program.take_output().starts_with("atomico int 4 4\n"));
+
+    program.run_line("historial");
+    let listed : Vec<String> = program.take_output().lines().map(|l| l.trim().to_string()).collect();
+    assert_eq!(listed, vec![
+        "1  atomico int 4 4",
+        "2  struct s int chr",
+        "3  struct s int int",
+        "4  atomico int 4 4",
+        "5  atomico int 4 4",
+        "6  historial"
+    ]);
+
+    for reference in ["!0", "!7", "!x", "^zz^y", "^^y"].iter() {
+        program.run_line(reference);
+        let expected = CommandError::Program(ProgramError::HistoryEvent(reference.to_string())).display();
+        assert_eq!(program.take_output(), format!("{}\n", expected));
+    }
+
+    // scripts don't go to the history
+    program.run_script("atomico char 1 1\n", "test");
+    program.run_line("historial");
+    assert_eq!(program.take_output().lines().count(), 7);
+}
+
+#[test]
+fn test_prompt() {
+    use crate::config::Config;
+
+    let (config, warnings) = Config::parse("prompt = \"[{n} tipos{cambios}|{macros}]>> \"\n");
+    assert!(warnings.is_empty(), "{:?}", warnings);
+
+    let mut program = crate::driver::Program::with_config(Config { autosave: false, ..config });
+    assert_eq!(program.prompt(), "[0 tipos|0]>> ");
+
+    // without autosave every change is pending
+    program.run_script("atomico int 4 4\nmacro par(T) => struct par_T T T\npar(int)\n", "test");
+    assert_eq!(program.prompt(), "[2 tipos*|1]>> ");
+
+    program.run_line("asistente");
+    assert_eq!(program.prompt(), "?> ");
+
+    // the target of the session in use
+    let (config, _) = Config::parse("prompt = \"[{abi}|{n} tipos]>> \"\n");
+    let mut program = crate::driver::Program::with_config(Config { autosave: false, ..config });
+    assert_eq!(program.prompt(), "[x86_64|0 tipos]>> ");
+    program.run_line("sesion nueva movil wasm");
+    assert_eq!(program.prompt(), "[wasm32|0 tipos]>> ");
+
+    for bad in ["prompt = {arquitectura}>", "prompt = {n>"].iter() {
+        let (config, warnings) = Config::parse(bad);
+        assert_eq!(warnings.len(), 1, "{}", bad);
+        assert_eq!(config.prompt, crate::config::DEFAULT_PROMPT);
+    }
+}
+
+#[test]
+fn test_number_format() {
+    use crate::utils::number::NumberFormat;
+    use crate::config::Config;
+
+    let cases = [
+        (NumberFormat::Plain,      1_048_576, "1048576"),
+        (NumberFormat::Underscore, 1_048_576, "1_048_576"),
+        (NumberFormat::Dots,       1_048_576, "1.048.576"),
+        (NumberFormat::Commas,     -1_048_576, "-1,048,576"),
+        (NumberFormat::Dots,       100_000,   "100.000"),
+        (NumberFormat::Dots,       999,       "999"),
+        (NumberFormat::Commas,     0,         "0")
+    ];
+    for (format, n, expected) in cases.iter() {
+        assert_eq!(format.format(n), *expected);
+    }
+
+    assert_eq!(NumberFormat::for_locale("es_VE.UTF-8"), NumberFormat::Dots);
+    assert_eq!(NumberFormat::for_locale("en_US.UTF-8"), NumberFormat::Commas);
+    assert_eq!(NumberFormat::for_locale("C"), NumberFormat::Plain);
+
+    // every report uses the configured format
+    let (config, warnings) = Config::parse("numeros = puntos\ntablas = ascii\n");
+    assert!(warnings.is_empty(), "{:?}", warnings);
+    let mut program = crate::driver::Program::with_config(Config { autosave: false, ..config });
+    program.capture_output();
+    program.run_script("atomico pagina 4096 4096\nstruct grande pagina pagina pagina\n", "test");
+    program.take_output();
+
+    program.run_line("calcular tamaño(grande) * 100");
+    assert_eq!(program.take_output(), "1.228.800\n");
+    program.run_line("describir pagina");
+    assert!(program.take_output().contains("Representación: 4.096"));
+    program.run_line("listar");
+    assert!(program.take_output().contains("| 12.288 |"));
+
+    let (_, warnings) = Config::parse("numeros = romanos\n");
+    assert_eq!(warnings.len(), 1);
+}
+
+#[test]
+fn test_keywords() {
+    use crate::config::Config;
+    use crate::driver::{CommandError, ProgramError};
+
+    // the same script runs in both languages
+    let (config, warnings) = Config::parse("comandos = ambos\nalias.describir = mostrar, ver\n");
+    assert!(warnings.is_empty(), "{:?}", warnings);
+    let mut program = crate::driver::Program::with_config(Config { autosave: false, ..config });
+    program.capture_output();
+    let summary = program.run_script("atomic int 4 4\natomico char 1 1\nSTRUCT s char int\ncalculate tamaño(s)\n", "test");
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+    assert_eq!(summary.defined, 3);
+    assert_eq!(program.take_output(), "8\n");
+
+    program.run_line("ver int");
+    let shown = program.take_output();
+    program.run_line("describe int");
+    assert_eq!(program.take_output(), shown);
+
+    // only English words
+    let (config, _) = Config::parse("comandos = en\n");
+    let mut program = crate::driver::Program::with_config(Config { autosave: false, ..config });
+    assert!(program.run_command("atomic int 4 4").is_ok());
+    assert_eq!(program.run_command("atomico char 1 1"), Err(CommandError::Program(ProgramError::InvalidAction("atomico".to_string()))));
+    program.run_script("exit\natomic char 1 1\n", "test");
+    assert!(program.manager().get(&"char".to_string()).is_none());
+
+    let bad = ["comandos = klingon", "alias.volar = fly", "alias.listar = describir", "alias.listar = a b"];
+    for line in bad.iter() {
+        let (_, warnings) = Config::parse(line);
+        assert_eq!(warnings.len(), 1, "{}", line);
+    }
+}
+
+#[test]
+fn test_ignore_case() {
+    use crate::config::Config;
+    use crate::driver::{CommandError, Program};
+
+    // names are case sensitive by default
+    let mut program = Program::new();
+    program.run_script("atomico int 4 4\n", "test");
+    assert!(program.run_command("atomico Int 2 2").is_ok());
+
+    let (config, warnings) = Config::parse("ignorar_mayusculas = si\n");
+    assert!(warnings.is_empty(), "{:?}", warnings);
+    let mut program = Program::with_config(Config { autosave: false, ..config });
+    program.capture_output();
+    let summary = program.run_script("atomico int 4 4\natomico Char 1 1\nstruct S CHAR Int\nunion u s INT\n", "test");
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+
+    // references keep the name the type was defined with
+    let manager = program.manager();
+    assert_eq!(manager.resolve(&"s".to_string()), Some(&"S".to_string()));
+    match manager.get(&"U".to_string()) {
+        Some(Type::Union(u)) => assert_eq!(u.variants, vec!["S".to_string(), "int".to_string()]),
+        other => panic!("expected a union, got {:?}", other)
+    }
+    assert!(program.session_script().contains("struct S Char int\n"));
+
+    assert_eq!(
+        program.run_command("atomico INT 2 2"),
+        Err(CommandError::Defined {
+            name: "int".to_string(),
+            origin: "test:1".to_string(),
+            error: Box::new(CommandError::Type(TypeError::CaseRedefinition("INT".to_string(), "int".to_string())))
+        })
+    );
+
+    program.run_line("calcular tamaño(s) + CHAR.size");
+    assert_eq!(program.take_output(), "9\n");
+    program.run_line("dependencias u");
+    assert_eq!(program.take_output(), "Usa: int, Char, S\nUsado por: (ninguno)\n");
+}
+
+#[test]
+fn test_diagnostics() {
+    use crate::driver::{CommandError, Program};
+
+    let mut manager = TypeManager::new();
+    assert_eq!(manager.add("int".to_string(), atom(4, 4)), Ok(vec![]));
+    assert_eq!(
+        manager.add("raro".to_string(), atom(2, 3)),
+        Ok(vec![Diagnostic::AlignAboveSize { align: 3, size: 2 }, Diagnostic::AlignNotPowerOfTwo(3)])
+    );
+    assert_eq!(manager.add("c".to_string(), atom(1, 1)), Ok(vec![]));
+
+    // 1 + 3 padding + 4 + 1 + 3 padding is not mostly padding, 1 + 7 + 1 + 7 is
+    assert_eq!(manager.add("s".to_string(), strc(vec!["c".to_string(), "int".to_string(), "c".to_string()])), Ok(vec![]));
+    let _ = manager.add("l".to_string(), atom(8, 8));
+    assert_eq!(
+        manager.add("p".to_string(), strc(vec!["c".to_string(), "l".to_string(), "c".to_string()])),
+        Ok(vec![Diagnostic::MostlyPadding { padding: 14, size: 24 }])
+    );
+
+    let many = vec!["c".to_string(); LARGE_PERMUTATIONS];
+    let diagnostics = manager.add("muchos".to_string(), strc(many)).unwrap();
+    assert_eq!(diagnostics, vec![Diagnostic::LargePermutationSpace(LARGE_PERMUTATIONS)]);
+    assert_eq!(diagnostics[0].level(), Level::Info);
+
+    // warnings are shown but the type is added
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_line("atomico raro 4 3");
+    assert_eq!(program.take_output(), "[AVISO]: La alineación (3) no es una potencia de dos\n");
+    assert!(program.manager().get(&"raro".to_string()).is_some());
+
+    // strict mode rejects types with warnings, not with information
+    let mut program = Program::with_config(crate::config::Config { autosave: false, strict: true, ..Default::default() });
+    assert_eq!(
+        program.run_command("atomico raro 4 3"),
+        Err(CommandError::Type(TypeError::Strict(Diagnostic::AlignNotPowerOfTwo(3))))
+    );
+    assert!(program.manager().get(&"raro".to_string()).is_none());
+
+    let members = ["c"; LARGE_PERMUTATIONS].join(" ");
+    assert!(program.run_command("atomico c 1 1").is_ok());
+    assert!(program.run_command(&format!("struct muchos {}", members)).is_ok());
+
+    let (options, args) = crate::cli::split_options(vec!["run".to_string(), "--strict".to_string(), "a.tm".to_string()]);
+    assert!(options.strict);
+    assert_eq!(args, vec!["run".to_string(), "a.tm".to_string()]);
+}
+
+#[test]
+fn test_lint() {
+    use crate::driver::{CommandError, ProgramError, Program};
+
+    let mut program = Program::new();
+    program.capture_output();
+    let script = "\
+atomico char 1 1
+atomico int 4 4
+atomico raro 1 4
+struct bien int char char
+struct mal char int char
+# lint: permitir orden, relleno
+struct permitido char int char
+struct alineado raro char
+union variantes char bien
+# lint: permitir volar
+";
+    let summary = program.run_script(script, "test.tm");
+    assert_eq!(summary.errors.len(), 1);
+    assert!(summary.errors[0].starts_with("test.tm:10: [ERROR]: 'volar' no es una regla de lint"), "{}", summary.errors[0]);
+    program.take_output();
+
+    let manager = program.manager();
+    let rules = |name : &str| -> Vec<&str> {
+        manager.lint(&[name.to_string()], |_, _| false).iter().map(|f| f.rule).collect()
+    };
+    assert!(rules("bien").is_empty());
+    assert_eq!(rules("mal"), vec!["relleno", "orden"]);
+    assert_eq!(rules("alineado"), vec!["relleno", "sobrealineado"]);
+    assert_eq!(rules("variantes"), vec!["union_desigual"]);
+
+    // suppressions only apply to the type right after them
+    program.run_line("lint permitido");
+    assert_eq!(program.take_output(), "Sin problemas\n");
+    program.run_line("lint mal");
+    assert_eq!(program.take_output(), "\
+mal: [relleno] 6 de 12 bytes son relleno (50%)
+mal: [orden] reordenar los miembros ahorra 4 bytes (8 en vez de 12): int char char
+");
+    program.run_line("lint");
+    assert_eq!(program.take_output().lines().count(), 5);
+    assert!(program.session_script().contains("# lint: permitir orden, relleno\nstruct permitido char int char\n"));
+
+    assert_eq!(program.run_command("lint nada"), Err(CommandError::Type(TypeError::TypeDoesNotExist("nada".to_string()))));
+    assert_eq!(program.run_command("lint a b"), Err(CommandError::Program(ProgramError::TooManyArgs)));
+    program.run_line("lint --reglas");
+    assert_eq!(program.take_output().lines().count(), lint::RULES.len());
+}
+
+#[test]
+fn test_hot_cold_split() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico int 4 4
+atomico char 1 1
+atomico texto 64 1
+struct jugador x:int nombre:texto @frio y:int @caliente bio:texto @frío vida:int
+struct plano int char
+", "test");
+
+    // x and nombre share the first line, y the second and vida the third
+    let report = program.manager().hot_cold_split(&"jugador".to_string(), DEFAULT_CACHE_LINE).unwrap();
+    assert_eq!((report.hot.clone(), report.cold.clone()), (vec![0, 2, 4], vec![1, 3]));
+    assert_eq!((report.size, report.lines, report.hot_lines_now), (140, 3, 3));
+    assert_eq!((report.hot_size, report.hot_lines()), (24, 1));
+    assert_eq!((report.cold_size, report.cold_lines()), (128, 2));
+
+    program.run_line("dividir jugador --linea 128");
+    assert_eq!(program.take_output(), "\
+División de 'jugador' (líneas de caché de 128 bytes):
+  Ahora: 140 bytes en 2 líneas, los campos calientes están en 2 líneas
+  Caliente 'jugador_caliente': x, y, vida, puntero -> 24 bytes en 1 línea
+  Frío 'jugador_frio': nombre, bio -> 128 bytes en 1 línea
+");
+
+    program.run_line("dividir plano");
+    assert_eq!(program.take_output(), "'plano' no tiene campos marcados con @frio, no hay nada que dividir\n");
+    assert_eq!(program.manager().hot_cold_split(&"int".to_string(), 64), Err(TypeError::NotAStruct("int".to_string())));
+    assert!(program.run_command("dividir jugador --linea 0").is_err());
+    assert!(program.run_command("dividir").is_err());
+}
+
+#[test]
+fn test_group_constraints() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.run_script("\
+atomico char 1 1
+atomico int 4 4
+struct libre a:char b:int c:char d:int
+struct grupos a:char @grupo=x b:int @grupo=x c:char @grupo=y d:int @grupo=y
+struct mezcla a:char @grupo=x b:int c:char @grupo=x @caliente d:int
+", "test");
+    let manager = program.manager();
+    let optimized = |name : &str| {
+        match manager.get(&name.to_string()) {
+            Some(Type::Struct(s)) => {
+                let order : Vec<usize> = s.layout(manager, Strategy::Optimized).iter().map(|m| m.index).collect();
+                let (_, size, _) = s.optimize(manager);
+                (order, size)
+            },
+            _ => panic!("{} is not a struct", name)
+        }
+    };
+
+    // without groups both ints go first
+    assert_eq!(optimized("libre").1, 10);
+
+    // every group stays together, even if the struct gets bigger
+    let (order, size) = optimized("grupos");
+    assert_eq!(size, 12);
+    let position = |i : usize| order.iter().position(|m| *m == i).unwrap();
+    assert_eq!((position(0) as i64 - position(1) as i64).abs(), 1);
+    assert_eq!((position(2) as i64 - position(3) as i64).abs(), 1);
+
+    // members without a group go anywhere
+    let (order, size) = optimized("mezcla");
+    assert_eq!(size, 10);
+    let position = |i : usize| order.iter().position(|m| *m == i).unwrap();
+    assert_eq!((position(0) as i64 - position(2) as i64).abs(), 1);
+}
+
+#[test]
+fn test_flag_packing() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico int 4 4
+atomico bool 1 1
+atomico char 1 1
+struct estado vivo:bool id:int visible:bool letra:char nivel:char @bandera @bits=3 activo:bool
+struct simple a:bool b:int
+", "test");
+
+    let report = program.manager().flag_packing(&"estado".to_string()).unwrap();
+    let indices : Vec<usize> = report.flags.iter().map(|f| f.index).collect();
+    assert_eq!(indices, vec![0, 2, 4, 5]);
+    assert_eq!((report.bits(), report.bytes()), (6, 1));
+    assert_eq!((report.size, report.packed_size, report.savings()), (12, 12, 0));
+    assert!(!report.worth_it());
+    program.take_output();
+
+    program.run_line("atomico largo 8 8");
+    program.run_line("struct paquete a:bool b:bool c:largo d:bool e:bool f:bool g:bool h:bool i:bool j:bool");
+    program.take_output();
+    program.run_line("banderas paquete --aplicar paquete_compacto");
+    assert_eq!(program.take_output(), "\
+Banderas de 'paquete': a, b, d, e, f, g, h, i, j (9 bits en 2 bytes)
+  Ahora: 24 bytes
+  Empaquetadas: 16 bytes, ahorra 8 bytes
+Struct 'paquete_compacto' definido
+");
+    match program.manager().get(&"paquete_compacto".to_string()) {
+        Some(Type::Struct(s)) => {
+            assert_eq!(s.members, vec!["bits16".to_string(), "largo".to_string()]);
+            assert_eq!(s.fields[0].name, "banderas");
+            assert_eq!(s.c_size(program.manager()), 16);
+        },
+        _ => panic!("paquete_compacto is not a struct")
+    }
+
+    program.run_line("banderas simple");
+    assert_eq!(program.take_output(), "'simple' tiene menos de dos banderas, no hay nada que empaquetar\n");
+    assert_eq!(program.manager().flag_packing(&"int".to_string()), Err(TypeError::NotAStruct("int".to_string())));
+    assert!(program.run_command("banderas").is_err());
+    assert!(program.run_command("banderas paquete --aplicar").is_err());
+}
+
+#[test]
+fn test_enum_representation() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico char 1 1
+enum color rojo verde azul
+enum estado {listo, ocupado} --repr minimo
+struct pixel c:color a:char
+struct celda e:estado a:char
+", "test");
+    let manager = program.manager();
+    let size = |name : &str| Strategy::C.type_size(manager.get(&name.to_string()).unwrap(), manager);
+
+    // the int representation pads the struct, the minimal one doesn't
+    assert_eq!((size("color"), size("pixel")), (4, 8));
+    assert_eq!((size("estado"), size("celda")), (1, 2));
+    program.take_output();
+
+    program.run_line("describir color");
+    assert_eq!(program.take_output(), "\
+Símbolo: color
+🔢 Enum:
+   * Variantes: 3
+   * Representación mínima: 1
+   * Representación int: 4
+   * En uso: int (4 bytes)
+📍 Definido en test:2
+");
+
+    let big = Enum::new((0..300).map(|i| format!("v{}", i)).collect(), EnumRepr::Minimal);
+    assert_eq!((big.minimal_size(), big.int_size()), (2, 4));
+    program.run_line("calcular estado.size + color.align");
+    assert_eq!(program.take_output(), "5\n");
+    assert!(program.session_script().contains("enum estado listo ocupado --repr minimo\n"));
+
+    assert!(program.run_command("enum doble a a").is_err());
+    assert!(program.run_command("enum vacio").is_err());
+    assert!(program.run_command("enum malo a --repr largo").is_err());
+
+    // the configuration picks the representation for enums that don't
+    let mut config = crate::config::Config::default();
+    config.set("enums", "minimo").unwrap();
+    assert!(config.set("enums", "corto").is_err());
+    let mut program = Program::with_config(crate::config::Config { autosave: false, ..config });
+    program.run_line("enum dia lunes martes");
+    assert_eq!(Strategy::C.type_size(program.manager().get(&"dia".to_string()).unwrap(), program.manager()), 1);
+}
+
+#[test]
+fn test_optional_niche() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico int 4 4
+puntero nodo_ptr nodo
+struct nodo valor:int siguiente:nodo_ptr
+", "test");
+    let manager = program.manager();
+
+    // pointers may name types defined later, and come first in saved sessions
+    assert_eq!(Strategy::C.type_size(manager.get(&"nodo".to_string()).unwrap(), manager), 16);
+    let script = program.session_script();
+    assert!(script.find("puntero nodo_ptr nodo").unwrap() < script.find("struct nodo").unwrap());
+
+    let pointer = manager.optional(&"nodo_ptr".to_string()).unwrap();
+    assert_eq!((pointer.flagged, pointer.niche, pointer.best()), (16, Some(8), 8));
+    let int = manager.optional(&"int".to_string()).unwrap();
+    assert_eq!((int.flagged, int.flag_padding(), int.niche, int.best()), (8, 3, None, 8));
+    assert_eq!(manager.optional(&"nada".to_string()), Err(TypeError::TypeDoesNotExist("nada".to_string())));
+
+    program.take_output();
+    program.run_line("opcional nodo_ptr");
+    assert_eq!(program.take_output(), "\
+Opcional de 'nodo_ptr' (8 bytes):
+  Sin nicho: 16 bytes (1 byte de bandera y 7 de relleno)
+  Con nicho: 8 bytes, el puntero nulo representa el valor vacío
+");
+    program.run_line("opcional nodo");
+    assert_eq!(program.take_output(), "\
+Opcional de 'nodo' (16 bytes):
+  Sin nicho: 24 bytes (1 byte de bandera y 7 de relleno)
+  Con nicho: no es posible, 'nodo' puede tomar cualquier valor
+");
+
+    assert!(program.run_command("puntero solo").is_err());
+    assert!(program.run_command("opcional").is_err());
+}
+
+#[test]
+fn test_containers() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico int 4 4
+contenedor enteros vec int --capacidad 16
+contenedor nombre string --capacidad 32 --ancho 4
+contenedor hijo box nodo
+struct nodo valor:int hijo:hijo
+struct registro id:int datos:enteros nombre:nombre
+", "test");
+    let manager = program.manager();
+    let size = |name : &str| Strategy::C.type_size(manager.get(&name.to_string()).unwrap(), manager);
+
+    // handles only take their words inline, what they own is on the heap
+    assert_eq!((size("enteros"), size("nombre"), size("hijo")), (24, 16, 8));
+    assert_eq!(size("registro"), 48);
+    assert_eq!(manager.heap_size(&"enteros".to_string()), 64);
+    assert_eq!(manager.heap_size(&"registro".to_string()), 96);
+
+    // a node owning another node counts it once
+    assert_eq!(manager.heap_size(&"nodo".to_string()), 16);
+    assert_eq!(manager.heap_size(&"int".to_string()), 0);
+
+    program.take_output();
+    program.run_line("describir enteros");
+    assert_eq!(program.take_output(), "\
+Símbolo: enteros
+📚 Contenedor vec de int:
+   * En línea: 24
+   * Alineación: 8
+   * Capacidad: 16
+   * Memoria dinámica: 64
+📍 Definido en test:2
+");
+    program.run_line("describir registro --json");
+    assert!(program.take_output().contains("\"heap\":96"));
+    assert!(program.session_script().contains("contenedor nombre string --capacidad 32 --ancho 4\n"));
+
+    assert!(program.run_command("contenedor v vec").is_err());
+    assert!(program.run_command("contenedor v lista int").is_err());
+    assert!(program.run_command("contenedor v vec int --ancho 3").is_err());
+    assert!(program.run_command("contenedor b box int --capacidad 2").is_err());
+}
+
+#[test]
+fn test_string_models() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico char 1 1
+cadena fija crudo 20
+cadena vista ptr_len
+cadena corta sso
+cadena larga sso 32
+struct a c:char s:fija
+struct b c:char s:vista
+struct c c:char s:corta
+", "test");
+    let manager = program.manager();
+    let size = |name : &str| Strategy::C.type_size(manager.get(&name.to_string()).unwrap(), manager);
+
+    // the same struct under every representation
+    assert_eq!((size("a"), size("b"), size("c")), (21, 24, 32));
+    assert_eq!(size("larga"), 32);
+    match manager.get(&"larga".to_string()) {
+        Some(Type::Text(t)) => {
+            assert_eq!(t.inline_capacity(), 31);
+            assert_eq!(t.fields(POINTER_SIZE)[0].last(), Some(&TextField { name: "relleno", offset: 24, size: 8 }));
+        },
+        _ => panic!("larga is not a string")
+    }
+
+    program.take_output();
+    program.run_line("describir corta --detalle");
+    assert_eq!(program.take_output(), "\
+Símbolo: corta
+🔤 Cadena (sso 24):
+   * Tamaño: 24
+   * Alineación: 8
+   * Sin memoria dinámica: hasta 23 bytes de texto
+   * Modo largo:
+      + ptr: desplazamiento 0, 8 bytes
+      + len: desplazamiento 8, 8 bytes
+      + cap: desplazamiento 16, 8 bytes
+   * Modo corto:
+      + buf: desplazamiento 0, 23 bytes
+      + etiqueta: desplazamiento 23, 1 bytes
+📍 Definido en test:4
+");
+    program.run_line("describir vista");
+    assert!(!program.take_output().contains("Campos"));
+    assert!(program.session_script().contains("cadena larga sso 32\n"));
+
+    assert!(TextModel::parse("sso", Some(20)).is_err());
+    assert!(program.run_command("cadena x crudo").is_err());
+    assert!(program.run_command("cadena x ptr_len 8").is_err());
+    assert!(program.run_command("cadena x utf8").is_err());
+}
+
+#[test]
+fn test_fat_pointers() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico char 1 1
+atomico int 4 4
+puntero texto char --rebanada
+puntero forma figura --rasgo
+puntero ref int
+struct dibujo c:char nombre:texto f:forma r:ref
+", "test");
+    let manager = program.manager();
+    let size = |name : &str| Strategy::C.type_size(manager.get(&name.to_string()).unwrap(), manager);
+    let align = |name : &str| Strategy::C.type_align(manager.get(&name.to_string()).unwrap(), manager);
+
+    assert_eq!((size("texto"), align("texto")), (2 * POINTER_SIZE, POINTER_SIZE));
+    assert_eq!((size("forma"), size("ref")), (16, 8));
+    assert_eq!(size("dibujo"), 48);
+
+    // the address of a fat pointer is never null either
+    assert_eq!(manager.optional(&"texto".to_string()).unwrap().best(), 16);
+
+    program.take_output();
+    program.run_line("describir forma");
+    assert_eq!(program.take_output(), "\
+Símbolo: forma
+👉 Puntero:
+   * Apunta a: figura
+   * Tipo: objeto de rasgo (dirección y vtable)
+   * Tamaño: 16
+   * Alineación: 8
+📍 Definido en test:4
+");
+    let script = program.session_script();
+    assert!(script.contains("puntero texto char --rebanada\n"));
+    assert!(script.contains("puntero ref int\n"));
+
+    assert!(program.run_command("puntero p int --gordo").is_err());
+    assert!(program.run_command("puntero p int --rasgo extra").is_err());
+}
+
+#[test]
+fn test_bitfield_policies() {
+    use crate::driver::{CommandError, Program};
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico int 4 4
+atomico char 1 1
+struct mezcla a:int @bits=3 b:char @bits=4 c:int @bits=10
+struct regular a:int @bits=1 b:char
+", "test");
+    let manager = program.manager();
+    let layout = |name : &str, policy| match manager.get(&name.to_string()) {
+        Some(Type::Struct(s)) => s.bitfield_layout(manager, policy).unwrap(),
+        _ => panic!("{} is not a struct", name)
+    };
+    let offsets = |l : &BitLayout| l.members.iter().map(|m| m.offset).collect::<Vec<usize>>();
+
+    // GCC shares bits across types while no field crosses a unit of its own type
+    let gcc = layout("mezcla", BitfieldPolicy::Gcc);
+    assert_eq!((offsets(&gcc), gcc.size), (vec![0, 3, 7], 4));
+
+    // MSVC opens a new unit every time the type size changes
+    let msvc = layout("mezcla", BitfieldPolicy::Msvc);
+    assert_eq!((offsets(&msvc), msvc.size), (vec![0, 32, 64], 12));
+
+    // a regular member goes after the whole unit with MSVC
+    assert_eq!(offsets(&layout("regular", BitfieldPolicy::Gcc)), vec![0, 8]);
+    assert_eq!(offsets(&layout("regular", BitfieldPolicy::Msvc)), vec![0, 32]);
+
+    program.take_output();
+    program.run_line("bits regular --politica msvc");
+    let output = program.take_output();
+    assert!(output.starts_with("Campos de bits de 'regular' (msvc):\n"));
+    assert!(output.ends_with("Tamaño: 8 bytes, alineación 4\n"));
+
+    assert_eq!(
+        program.run_command("struct ancho x:char @bits=9"),
+        Err(CommandError::Type(TypeError::BitfieldTooWide("x".to_string())))
+    );
+    assert!(program.run_command("bits regular --politica borland").is_err());
+    assert!(program.run_command("bits int").is_err());
+}
+
+#[test]
+fn test_variant_report() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico int 4 4
+atomico char 1 1
+struct par c:char i:int
+union dato int par char
+", "test");
+    let manager = program.manager();
+    let report = |variant : &str| manager.variant_report(&"dato".to_string(), &variant.to_string());
+
+    let int = report("int").unwrap();
+    let c = int.iter().find(|r| r.strategy == Strategy::C).unwrap();
+    assert_eq!((c.variant, c.size, c.slack), (4, 8, 4));
+    let packed = int.iter().find(|r| r.strategy == Strategy::Packed).unwrap();
+    assert_eq!((packed.variant, packed.size, packed.slack), (4, 5, 1));
+
+    // the biggest variant drives the size, no slack left
+    assert!(report("par").unwrap().iter().all(|r| r.slack == 0));
+    assert_eq!(report("float"), Err(TypeError::NotAVariant("dato".to_string(), "float".to_string())));
+    assert_eq!(manager.variant_report(&"par".to_string(), &"int".to_string()), Err(TypeError::NotAUnion("par".to_string())));
+
+    program.take_output();
+    program.run_line("variante dato char");
+    let output = program.take_output();
+    assert!(output.starts_with("Variante 'char' de 'dato':\n"));
+    assert!(output.contains("Holgura"));
+    assert!(program.run_command("variante dato").is_err());
+}
+
+#[test]
+fn test_union_variant_losses() {
+    let mut manager = TypeManager::new();
+    manager.add("char".to_string(), atom(1, 1)).unwrap();
+    manager.add("int".to_string(), atom(4, 4)).unwrap();
+    manager.add("seis".to_string(), atom(6, 1)).unwrap();
+    manager.add("par".to_string(), strc(vec!["char".to_string(), "int".to_string()])).unwrap();
+    manager.add("u".to_string(), uni(vec!["par".to_string(), "seis".to_string()])).unwrap();
+
+    let losses = match manager.report(&"u".to_string()).unwrap() {
+        Report::Union { variants, .. } => variants.into_iter().filter(|v| v.strategy == Strategy::C).collect::<Vec<VariantLoss>>(),
+        _ => panic!("u is not a union")
+    };
+    assert_eq!(losses.len(), 2);
+    assert_eq!((losses[0].size, losses[0].padding, losses[0].slack), (8, 3, 0));
+    assert_eq!((losses[1].size, losses[1].padding, losses[1].slack), (6, 0, 2));
+
+    // the loss is what the least wasteful variant wastes, not the padding of the biggest one
+    let c = manager.get(&"u".to_string()).unwrap().report(&manager);
+    match c {
+        Report::Union { strategies, .. } => {
+            let c = strategies.iter().find(|s| s.strategy == Strategy::C).unwrap();
+            assert_eq!((c.size, c.loss), (8, 2));
+        },
+        _ => panic!("u is not a union")
+    }
+}
+
+#[test]
+fn test_tail_padding_reuse() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico int 4 4
+atomico short 2 2
+atomico char 1 1
+struct base x:int c:char
+struct hijo a:base @no_unique_address d:char e:short
+struct nieto h:hijo @sin_direccion_unica z:char
+struct normal a:base d:char
+", "test");
+    let manager = program.manager();
+    let reuse = |name : &str| manager.tail_reuse(&name.to_string()).unwrap();
+
+    // d and e go in the 3 bytes of padding after base's char
+    let hijo = reuse("hijo");
+    assert_eq!((hijo.plain_offsets.clone(), hijo.plain_size), (vec![0, 8, 10], 12));
+    assert_eq!((hijo.offsets.clone(), hijo.size, hijo.savings()), (vec![0, 5, 6], 8, 4));
+
+    // the data of a member is measured with its own reuse
+    assert_eq!((reuse("nieto").offsets.clone(), reuse("nieto").size), (vec![0, 8], 12));
+    assert_eq!(reuse("normal").savings(), 0);
+    assert_eq!(manager.tail_reuse(&"int".to_string()), Err(TypeError::NotAStruct("int".to_string())));
+
+    program.take_output();
+    program.run_line("reutilizar hijo");
+    assert!(program.take_output().ends_with("Tamaño: 12 bytes sin reutilizar, 8 reutilizando (ahorra 4)\n"));
+    assert!(program.run_command("reutilizar").is_err());
+}
+
+#[test]
+fn test_density() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico int 4 4
+atomico char 1 1
+atomico bloque 100 4
+struct trio a:int b:int c:int
+struct mixto c:char i:int d:char
+", "test");
+    let manager = program.manager();
+
+    let trio = manager.density(&"trio".to_string(), Strategy::C, DEFAULT_CACHE_LINE, DEFAULT_PAGE).unwrap();
+    assert_eq!(trio.stride, 12);
+    assert_eq!((trio.line.count, trio.line.waste), (5, 4));
+    assert_eq!((trio.page.count, trio.page.waste), (341, 4));
+
+    // the strategy changes the stride
+    let packed = manager.density(&"mixto".to_string(), Strategy::Packed, 64, 4096).unwrap();
+    let c = manager.density(&"mixto".to_string(), Strategy::C, 64, 4096).unwrap();
+    assert_eq!((packed.stride, packed.line.count), (6, 10));
+    assert_eq!((c.stride, c.line.count), (12, 5));
+
+    program.take_output();
+    program.run_line("densidad bloque");
+    assert_eq!(program.take_output(), "\
+Densidad de 'bloque' (C, 100 bytes por elemento):
+  Línea de caché (64 bytes): ningún elemento entero
+  Página (4096 bytes): 40 elementos, 96 bytes sin usar
+");
+    program.run_line("densidad char --linea 1 --pagina 3");
+    assert!(program.take_output().contains("(1 bytes): 1 elemento, 0 bytes sin usar"));
+    assert!(program.run_command("densidad trio --estrategia ninguna").is_err());
+    assert!(program.run_command("densidad trio --pagina 0").is_err());
+}
+
+#[test]
+fn test_memory_plan() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico int 4 4
+atomico char 1 1
+struct mixto c:char i:int d:char
+", "test");
+    let manager = program.manager();
+
+    let plans = manager.plan(&[("mixto".to_string(), 10), ("int".to_string(), 3)]).unwrap();
+    let c = plans.iter().find(|p| p.strategy == Strategy::C).unwrap();
+    let optimized = plans.iter().find(|p| p.strategy == Strategy::Optimized).unwrap();
+    assert_eq!((c.subtotals.clone(), c.total), (vec![120, 12], 132));
+    assert_eq!((optimized.subtotals.clone(), optimized.total), (vec![80, 12], 92));
+    assert!(optimized.fits(100) && !c.fits(100));
+    assert!(manager.plan(&[("nada".to_string(), 1)]).is_err());
+
+    program.take_output();
+    program.run_line("planificar 100 mixto:10 int:3");
+    let output = program.take_output();
+    assert!(output.starts_with("Plan de memoria (100 bytes disponibles):"));
+    assert!(output.contains("  C: no cabe, faltan 32 bytes"));
+    assert!(output.contains("  Optimizado: cabe, sobran 8 bytes"));
+    assert!(output.contains("Reordenar los campos ahorra 40 bytes respecto a C"));
+    assert!(program.run_command("planificar 100").is_err());
+    assert!(program.run_command("planificar 100 mixto").is_err());
+    assert!(program.run_command("planificar mucho mixto:1").is_err());
+}
+
+#[test]
+fn test_member_addresses() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico int 4 4
+atomico char 1 1
+struct cabecera tipo:char largo:int
+union dato int char
+struct paquete id:char cab:cabecera d:dato
+", "test");
+    let manager = program.manager();
+
+    let members = manager.nested_members(&"paquete".to_string(), Strategy::C).unwrap();
+    let paths : Vec<(&str, usize)> = members.iter().map(|m| (m.path.as_str(), m.offset)).collect();
+    assert_eq!(paths, vec![
+        ("id", 0), ("cab", 4), ("cab.tipo", 4), ("cab.largo", 8), ("d", 12), ("d.int", 12), ("d.char", 12)
+    ]);
+    assert_eq!(members[3].depth, 1);
+    assert!(manager.nested_members(&"int".to_string(), Strategy::C).unwrap().is_empty());
+
+    // packed layouts move nested members too
+    let packed = manager.nested_members(&"paquete".to_string(), Strategy::Packed).unwrap();
+    assert_eq!((packed[3].path.as_str(), packed[3].offset), ("cab.largo", 2));
+
+    program.take_output();
+    program.run_line("direcciones paquete 0x1000");
+    let output = program.take_output();
+    assert!(output.starts_with("Direcciones de 'paquete' desde 0x00001000 (C):"));
+    assert!(output.contains("0x00001008"));
+    assert!(output.contains("cab.largo"));
+    program.run_line("direcciones paquete 16 --estrategia empaquetado");
+    assert!(program.take_output().contains("(Empaquetado)"));
+    assert!(program.run_command("direcciones paquete").is_err());
+    assert!(program.run_command("direcciones paquete 0x10 --estrategia nada").is_err());
+    assert!(program.run_command("direcciones nada 0").is_err());
+}
+
+#[test]
+fn test_annotated_hexdump() {
+    use crate::driver::Program;
+
+    assert_eq!(ValueKind::Signed.decode(&[0xfe, 0xff], Endian::Little), Some(String::from("-2")));
+    assert_eq!(ValueKind::Unsigned.decode(&[0xfe, 0xff], Endian::Little), Some(String::from("65534")));
+    assert_eq!(ValueKind::Float.decode(&1.5f32.to_le_bytes(), Endian::Little), Some(String::from("1.5")));
+    assert_eq!(ValueKind::Float.decode(&[0, 0], Endian::Little), None);
+    assert_eq!(parse_hex("0x2a:00"), Some(vec![0x2a, 0]));
+    assert_eq!(parse_hex("2a0"), None);
+    assert_eq!(parse_hex("+f"), None);
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico int 4 4 entero
+atomico float 4 4 real
+atomico char 1 1
+struct muestra c:char i:int f:float
+", "test");
+    let manager = program.manager();
+
+    let bytes = parse_hex("41000000feffffff0000c03f").unwrap();
+    let annotations = manager.annotate(&"muestra".to_string(), Strategy::C, &bytes, Endian::Little).unwrap();
+    assert_eq!(annotations.len(), 4);
+    assert_eq!(annotations[1], Annotation::Padding { offset: 1, size: 3 });
+    match &annotations[2] {
+        Annotation::Member { path, value, .. } => assert_eq!((path.as_str(), value.as_deref()), ("i", Some("-2"))),
+        other => panic!("se esperaba un miembro, no {:?}", other)
+    }
+
+    // values past the end of the buffer aren't read
+    let short = manager.annotate(&"muestra".to_string(), Strategy::C, &bytes[..6], Endian::Little).unwrap();
+    assert!(matches!(&short[2], Annotation::Member { value: None, .. }));
+
+    program.take_output();
+    program.run_line("anotar muestra 41000000feffffff0000c03f");
+    assert_eq!(program.take_output(), "\
+Anotación de 'muestra' (C, 12 bytes, 12 leídos):
+  0000  41                                                c: char
+  0001  00 00 00                                          relleno (3 bytes)
+  0004  fe ff ff ff                                       i: int = -2
+  0008  00 00 c0 3f                                       f: float = 1.5
+");
+    program.run_line("anotar muestra 4100 --estrategia empaquetado");
+    let output = program.take_output();
+    assert!(output.contains("?? ?? ??"));
+    assert!(output.ends_with("Faltan 7 bytes para cubrir el tipo\n"));
+    assert!(program.run_command("anotar muestra /no/existe.bin").is_err());
+    program.run_line("describir int");
+    assert!(program.take_output().contains("* Valor: entero"));
+    assert!(program.session_script().contains("atomico int 4 4 entero"));
+}
+
+#[test]
+fn test_decode_values() {
+    use crate::driver::Program;
+
+    assert_eq!(Endian::Big.read(&[0x01, 0x02]), Some(0x0102));
+    assert_eq!(Endian::Little.read(&[0x01, 0x02]), Some(0x0201));
+    assert_eq!(ValueKind::Signed.decode(&[0xff, 0xfe], Endian::Big), Some(String::from("-2")));
+    assert_eq!(ValueKind::Char.decode(b"A", Endian::Little), Some(String::from("A")));
+    assert_eq!(ValueKind::Char.decode(&[0x0a], Endian::Little), Some(String::from("\\n")));
+    assert_eq!(ValueKind::Char.decode(&[0x00, 0xf1], Endian::Big), Some(String::from("ñ")));
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico u16 2 2 natural
+atomico i32 4 4 entero
+atomico char 1 1 caracter
+atomico raro 2 2
+enum color rojo verde azul --repr minimo
+struct trama tipo:char c:color largo:u16 valor:i32 x:raro
+", "test");
+
+    program.take_output();
+    program.run_line("decodificar trama 4102000a00000100ffff --estrategia empaquetado --orden big");
+    assert_eq!(program.take_output(), "\
+Valores de 'trama' (Empaquetado, big endian):
+┌─────────┬───────┬────────────────┬────────┐
+│ Miembro │ Tipo  │ Desplazamiento │ Valor  │
+├─────────┼───────┼────────────────┼────────┤
+│ tipo    │ char  │              0 │ A      │
+│ c       │ color │              1 │ azul   │
+│ largo   │ u16   │              2 │ 10     │
+│ valor   │ i32   │              4 │ 256    │
+│ x       │ raro  │              8 │ 0xffff │
+└─────────┴───────┴────────────────┴────────┘
+");
+
+    // little endian and the C layout by default
+    program.run_line("decodificar trama 41010000ff7f0000");
+    let output = program.take_output();
+    assert!(output.contains("│ verde "));
+    assert!(output.contains("│ 32767 "));
+    assert!(output.contains("(incompleto)"));
+    assert!(output.ends_with("Faltan 4 bytes para cubrir el tipo\n"));
+    program.run_line("decodificar trama 41070000");
+    assert!(program.take_output().contains("7 (fuera de rango)"));
+    assert!(program.run_command("decodificar trama xyz").is_err());
+    assert!(program.run_command("decodificar trama 00 --orden medio").is_err());
+    assert!(program.run_command("decodificar trama").is_err());
+}
+
+#[test]
+fn test_vector_generation() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico int 4 4 entero
+atomico char 1 1
+union numero int char
+struct registro c:char n:numero f:char
+atomico enorme 2000000 1
+", "test");
+    let manager = program.manager();
+
+    // every member holds its number, padding stays zero and union variants don't overwrite each other
+    let vector = manager.test_vector(&"registro".to_string(), Strategy::C, None).unwrap();
+    assert_eq!(vector.bytes, vec![1, 0, 0, 0, 2, 2, 2, 2, 3, 0, 0, 0]);
+    let paths : Vec<&str> = vector.members.iter().map(|m| m.path.as_str()).collect();
+    assert_eq!(paths, vec!["c", "n.int", "f"]);
+
+    // the same seed gives the same bytes
+    let first = manager.test_vector(&"registro".to_string(), Strategy::Packed, Some(7)).unwrap();
+    let second = manager.test_vector(&"registro".to_string(), Strategy::Packed, Some(7)).unwrap();
+    assert_eq!(first, second);
+    assert_eq!(first.bytes.len(), 6);
+    assert_ne!(first.bytes, manager.test_vector(&"registro".to_string(), Strategy::Packed, Some(8)).unwrap().bytes);
+
+    assert_eq!(
+        manager.test_vector(&"enorme".to_string(), Strategy::C, None),
+        Err(TypeError::TooLarge("enorme".to_string(), 2000000, MAX_VECTOR))
+    );
+
+    program.take_output();
+    program.run_line("generar registro");
+    let output = program.take_output();
+    assert!(output.starts_with("Vector de prueba de 'registro' (C, 12 bytes):\n0x010000000202020203000000\n"));
+    assert!(output.contains("│ n.int   │ int  │              4 │      4 │ 02020202 │"));
+
+    // vectors can be read back
+    program.run_line("decodificar registro 010000000202020203000000");
+    assert!(program.take_output().contains("│ 33686018 "));
+    program.run_line("generar registro 42 --estrategia empaquetado");
+    assert!(program.take_output().contains("(Empaquetado, 6 bytes, semilla 42)"));
+    assert!(program.run_command("generar registro semilla").is_err());
+}
+
+#[test]
+fn test_c_initializer_snippet() {
+    use crate::driver::Program;
+    use std::process::Command;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico i32 4 4 entero
+atomico f32 4 4 real
+atomico c8 1 1 caracter
+puntero p i32
+struct cabecera tipo:c8 largo:i32
+union dato i32 f32
+struct paquete id:c8 cab:cabecera d:dato siguiente:p
+", "test");
+
+    program.take_output();
+    program.run_line("codigo paquete");
+    let code = program.take_output();
+    assert!(code.contains("\
+static const struct paquete paquete_inicial = {
+    .id = '\\0',
+    .cab = {
+        .tipo = '\\0',
+        .largo = 0
+    },
+    .d = {
+        .m0 = 0
+    },
+    .siguiente = NULL
+};
+"));
+    assert!(code.contains("static_assert(sizeof(struct paquete) == 24, \"sizeof(struct paquete) == 24\");\n"));
+    assert!(code.contains("static_assert(offsetof(struct paquete, cab.largo) == 8, \"offsetof(struct paquete, cab.largo) == 8\");\n"));
+    assert!(code.contains("offsetof(struct paquete, siguiente) == 16"));
+    assert!(program.run_command("codigo nada").is_err());
+
+    // the snippet compiles next to the generated definitions, if there's a C compiler
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    if Command::new(&cc).arg("--version").output().is_err() {
+        return
+    }
+    let manager = program.manager();
+    let names : Vec<Name> = manager.definition_order();
+    let path = std::env::temp_dir().join(format!("typemanager_snippet_{}.c", std::process::id()));
+
+    // and next to definitions written by hand, with structs and unions only tagged
+    let by_hand = "\
+#include <stdint.h>
+typedef int32_t i32;
+typedef float f32;
+typedef char c8;
+typedef i32 *p;
+struct cabecera { c8 tipo; i32 largo; };
+union dato { i32 m0; f32 m1; };
+struct paquete { c8 id; struct cabecera cab; union dato d; p siguiente; };
+";
+    for definitions in [crate::codegen::c_layout_check(manager, &names), by_hand.to_string()] {
+        let source = format!("{}{}", definitions, code);
+        std::fs::write(&path, &source).unwrap();
+        let output = Command::new(&cc).args(["-std=c11", "-fsyntax-only"]).arg(&path).output().unwrap();
+        assert!(output.status.success(), "{}\n{}", source, String::from_utf8_lossy(&output.stderr));
+    }
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_rust_layout_tests() {
+    use crate::driver::{ExportFormat, Program};
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico u8 1 1
+atomico u32 4 4
+struct Cabecera tipo:u8 largo:u32
+union Dato u8 u32
+", "test");
+
+    program.take_output();
+    program.run_line("codigo --rust Cabecera");
+    assert_eq!(program.take_output(), "\
+// Generado por TypeManager, los tipos deben estar en alcance
+
+use core::mem::{align_of, size_of};
+use memoffset::offset_of;
+
+#[test]
+#[allow(non_snake_case)]
+fn disposicion_Cabecera() {
+    assert_eq!(size_of::<Cabecera>(), 8);
+    assert_eq!(align_of::<Cabecera>(), 4);
+    assert_eq!(offset_of!(Cabecera, tipo), 0);
+    assert_eq!(offset_of!(Cabecera, largo), 4);
+}
+");
+    assert!(program.run_command("codigo --rust Cabecera Nada").is_err());
+    assert!(program.run_command("codigo --java Cabecera").is_err());
+
+    // exporting checks every struct and union, atomics aren't Rust types
+    let exported = program.export(ExportFormat::Rust);
+    assert!(exported.contains("fn disposicion_Dato()"));
+    assert!(!exported.contains("size_of::<u8>()"));
+    assert_eq!(crate::cli::parse(vec!["export".to_string(), "--format=rs".to_string()]), Ok(crate::cli::Command::Export(ExportFormat::Rust, None)));
+}
+
+#[test]
+fn test_clang_ast_import() {
+    use crate::driver::Program;
+    use crate::import::{self, ImportError};
+
+    // clang -Xclang -ast-dump=json -fsyntax-only, trimmed to what's read
+    let ast = r#"{
+  "id": "0x1", "kind": "TranslationUnitDecl", "inner": [
+    { "id": "0x2", "kind": "TypedefDecl", "isImplicit": true, "name": "__int128_t", "type": { "qualType": "__int128" } },
+    { "id": "0x3", "kind": "TypedefDecl", "loc": { "offset": 10, "file": "/usr/include/stdint.h", "line": 2, "includedFrom": { "file": "nodo.c" } },
+      "name": "uint32_t", "type": { "qualType": "unsigned int" } },
+    { "id": "0x30", "kind": "RecordDecl", "loc": { "line": 5 }, "name": "oculto", "tagUsed": "struct", "completeDefinition": true, "inner": [
+      { "id": "0x31", "kind": "FieldDecl", "loc": { "line": 5 }, "name": "x", "type": { "qualType": "int" } } ] },
+    { "id": "0x4", "kind": "EnumDecl", "loc": { "offset": 5, "file": "nodo.c", "line": 2 }, "name": "color", "inner": [
+      { "id": "0x5", "kind": "EnumConstantDecl", "name": "ROJO", "type": { "qualType": "int" } },
+      { "id": "0x6", "kind": "EnumConstantDecl", "name": "VERDE", "type": { "qualType": "int" } } ] },
+    { "id": "0x7", "kind": "RecordDecl", "loc": { "line": 3 }, "name": "cabecera", "tagUsed": "struct", "completeDefinition": true, "inner": [
+      { "id": "0x8", "kind": "PackedAttr" },
+      { "id": "0x9", "kind": "FieldDecl", "name": "tipo", "type": { "qualType": "char" } },
+      { "id": "0xa", "kind": "FieldDecl", "name": "largo", "type": { "desugaredQualType": "unsigned int", "qualType": "uint32_t" } } ] },
+    { "id": "0xb", "kind": "RecordDecl", "loc": { "line": 4 }, "name": "nodo", "tagUsed": "struct", "completeDefinition": true, "inner": [
+      { "id": "0xc", "kind": "FieldDecl", "name": "siguiente", "type": { "qualType": "struct nodo *" } },
+      { "id": "0xd", "kind": "FieldDecl", "name": "valores", "type": { "qualType": "const int[3]" } },
+      { "id": "0xe", "kind": "FieldDecl", "name": "bandera", "isBitfield": true, "type": { "qualType": "unsigned int" }, "inner": [
+        { "id": "0xf", "kind": "ConstantExpr", "value": "1" } ] },
+      { "id": "0x10", "kind": "FieldDecl", "name": "nivel", "isBitfield": true, "type": { "qualType": "unsigned int" }, "inner": [
+        { "id": "0x11", "kind": "ConstantExpr", "value": "3" } ] },
+      { "id": "0x12", "kind": "FieldDecl", "name": "d", "type": { "qualType": "double" }, "inner": [
+        { "id": "0x13", "kind": "AlignedAttr", "inner": [ { "id": "0x14", "kind": "ConstantExpr", "value": "16" } ] } ] },
+      { "id": "0x15", "kind": "FieldDecl", "name": "c", "type": { "desugaredQualType": "enum color", "qualType": "enum color" } },
+      { "id": "0x16", "kind": "RecordDecl", "tagUsed": "union", "completeDefinition": true, "inner": [
+        { "id": "0x17", "kind": "FieldDecl", "name": "i", "type": { "qualType": "int" } },
+        { "id": "0x18", "kind": "FieldDecl", "name": "f", "type": { "qualType": "float" } } ] },
+      { "id": "0x19", "kind": "FieldDecl", "name": "dato", "type": { "qualType": "union (unnamed union at nodo.c:10:5)" } },
+      { "id": "0x1a", "kind": "FieldDecl", "name": "llamada", "type": { "qualType": "void (*)(int)" } } ] },
+    { "id": "0x1b", "kind": "RecordDecl", "loc": { "line": 12 }, "tagUsed": "struct", "completeDefinition": true, "inner": [
+      { "id": "0x1c", "kind": "FieldDecl", "name": "x", "type": { "qualType": "short" } },
+      { "id": "0x1d", "kind": "FieldDecl", "name": "y", "type": { "qualType": "short" } } ] },
+    { "id": "0x1e", "kind": "TypedefDecl", "loc": { "line": 12 }, "name": "punto", "type": { "qualType": "struct punto" } },
+    { "id": "0x1f", "kind": "RecordDecl", "loc": { "line": 13 }, "name": "alineado", "tagUsed": "struct", "completeDefinition": true, "inner": [
+      { "id": "0x20", "kind": "AlignedAttr", "inner": [ { "id": "0x21", "kind": "ConstantExpr", "value": "32" } ] },
+      { "id": "0x22", "kind": "FieldDecl", "name": "p", "type": { "desugaredQualType": "punto", "qualType": "punto" } } ] }
+  ]
+}"#;
+
+    let types = import::clang_ast(ast).unwrap();
+    let names : Vec<&str> = types.iter().map(|(n, _)| n.as_str()).collect();
+    assert!(names.contains(&"uint32_t") && names.contains(&"nodo_dato") && names.contains(&"ptr_nodo"));
+    assert!(!names.contains(&"__int128_t") && !names.contains(&"oculto"));
+    let position = |n: &str| names.iter().position(|m| *m == n).unwrap();
+    assert!(position("int_x3") < position("nodo") && position("uint32_t") < position("cabecera"));
+
+    let path = std::env::temp_dir().join(format!("typemanager_clang_{}.json", std::process::id()));
+    std::fs::write(&path, ast).unwrap();
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_line("atomico int 4 4 entero");
+    program.run_line(&format!("importar clang-ast {}", path.display()));
+    let output = program.take_output();
+    assert!(output.contains("Importados "), "{}", output);
+    assert!(output.contains("Ya estaban definidos: int"), "{}", output);
+    std::fs::remove_file(&path).unwrap();
+
+    let manager = program.manager();
+    let get_struct = |n: &str| match manager.get(&n.to_string()) {
+        Some(Type::Struct(s)) => s.clone(),
+        other => panic!("'{}' debería ser un struct, no {:?}", n, other)
+    };
+
+    // packed: no padding after the char
+    let cabecera = get_struct("cabecera");
+    assert!(cabecera.is_packed());
+    assert_eq!((cabecera.c_offsets(manager), cabecera.c_size(manager)), (vec![0, 1], 5));
+
+    let nodo = get_struct("nodo");
+    assert_eq!(nodo.fields[2].bit_width(), Some(1));
+    assert_eq!(nodo.members[1], "int_x3");
+    assert_eq!(nodo.members[7], "ptr_void");
+    assert_eq!(Strategy::C.type_align(manager.get(&"nodo".to_string()).unwrap(), manager), 16);
+    assert!(matches!(manager.get(&"nodo_dato".to_string()), Some(Type::Union(_))));
+    assert!(matches!(manager.get(&"color".to_string()), Some(Type::Enum(_))));
+
+    let alineado = get_struct("alineado");
+    assert_eq!(alineado.members, vec!["punto".to_string()]);
+    assert_eq!((alineado.c_size(manager), alineado.c_align(manager)), (32, 32));
+
+    assert!(matches!(import::clang_ast("[1, 2]"), Err(ImportError::NotAnAst)));
+    assert!(matches!(import::clang_ast("{\"kind\": "), Err(ImportError::Json(_))));
+    program.run_line("importar clang-ast /no/existe.json");
+    assert!(program.take_output().contains("No se pudo leer '/no/existe.json'"));
+    program.run_line("importar pdb a.out");
+    assert!(program.take_output().contains("pdb"));
+}
+
+#[test]
+fn test_dwarf_import() {
+    use std::process::Command;
+    use crate::driver::Program;
+    use crate::import::{self, ImportError};
+
+    assert!(matches!(import::dwarf(b"#!/bin/sh"), Err(ImportError::Object(_))));
+    let mut elf32 = vec![0u8; 64];
+    elf32[..6].copy_from_slice(b"\x7fELF\x01\x01");
+    assert_eq!(import::dwarf(&elf32).unwrap_err().display(), "binario inválido: solo se leen archivos ELF de 64 bits little endian");
+
+    // compile an object file with debug information, if there's a C compiler
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    if Command::new(&cc).arg("--version").output().is_err() {
+        return
+    }
+    let source = "\
+#include <stdint.h>
+enum color { ROJO, VERDE, AZUL };
+struct __attribute__((packed)) cabecera { char tipo; uint32_t largo; };
+typedef struct { short x; short y; } punto;
+struct nodo {
+    struct nodo *siguiente;
+    const int valores[3];
+    double d __attribute__((aligned(16)));
+    enum color c;
+    union { int i; float f; } dato;
+    punto p;
+    unsigned bandera : 1;
+};
+struct nodo n;
+struct cabecera c;
+";
+    let dir = std::env::temp_dir();
+    let (path, object) = (dir.join(format!("typemanager_dwarf_{}.c", std::process::id())), dir.join(format!("typemanager_dwarf_{}.o", std::process::id())));
+    std::fs::write(&path, source).unwrap();
+    let output = Command::new(&cc).args(["-g", "-c", "-o"]).arg(&object).arg(&path).output().unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let imported = import::dwarf(&std::fs::read(&object).unwrap()).unwrap();
+    assert!(imported.skipped.is_empty(), "{:?}", imported.skipped);
+    let names : Vec<&str> = imported.types.iter().map(|(n, _)| n.as_str()).collect();
+    for name in ["cabecera", "uint32_t", "punto", "nodo", "nodo_dato", "color", "ptr_nodo", "int_x3"] {
+        assert!(names.contains(&name), "falta '{}' en {:?}", name, names);
+    }
+    assert!(!names.contains(&"__uint32_t"));
+    let cabecera = imported.compiled.iter().find(|c| c.name == "cabecera").unwrap();
+    assert_eq!((cabecera.size, cabecera.offsets.clone()), (5, vec![Some(0), Some(1)]));
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_line(&format!("importar dwarf {}", object.display()));
+    let _ = std::fs::remove_file(&object);
+    let output = program.take_output();
+    assert!(output.contains("Disposición en el binario comparada con la estrategia C:"), "{}", output);
+    let manager = program.manager();
+    match manager.get(&"cabecera".to_string()) {
+        Some(Type::Struct(s)) => assert!(s.is_packed() && s.c_size(manager) == 5),
+        other => panic!("'cabecera' debería ser un struct, no {:?}", other)
+    }
+    let nodo = imported.compiled.iter().find(|c| c.name == "nodo").unwrap();
+    match manager.get(&"nodo".to_string()) {
+        Some(Type::Struct(s)) => {
+            let ours : Vec<Option<usize>> = s.c_offsets(manager).into_iter().zip(&nodo.offsets).map(|(o, theirs)| theirs.map(|_| o)).collect();
+            assert_eq!((ours, s.c_size(manager)), (nodo.offsets.clone(), nodo.size));
+        },
+        other => panic!("'nodo' debería ser un struct, no {:?}", other)
+    }
+}
+
+#[test]
+fn test_cpp_class_import() {
+    use crate::import::{self, ImportError};
+
+    // clang++ -Xclang -ast-dump=json -fsyntax-only, trimmed to what's read
+    let ast = r#"{
+  "id": "0x1", "kind": "TranslationUnitDecl", "inner": [
+    { "id": "0x2", "kind": "NamespaceDecl", "loc": { "file": "clases.cpp", "line": 1 }, "name": "zoo", "inner": [
+      { "id": "0x3", "kind": "CXXRecordDecl", "loc": { "line": 2 }, "name": "Vacia", "tagUsed": "struct", "completeDefinition": true,
+        "definitionData": { "isEmpty": true, "isPOD": true }, "inner": [
+        { "id": "0x4", "kind": "CXXRecordDecl", "isImplicit": true, "name": "Vacia", "tagUsed": "struct" } ] },
+      { "id": "0x5", "kind": "CXXRecordDecl", "loc": { "line": 3 }, "name": "Punto", "tagUsed": "struct", "completeDefinition": true,
+        "definitionData": { "isPOD": true, "isStandardLayout": true }, "inner": [
+        { "id": "0x6", "kind": "FieldDecl", "name": "x", "type": { "qualType": "int" } },
+        { "id": "0x7", "kind": "FieldDecl", "name": "y", "type": { "qualType": "int" } },
+        { "id": "0x8", "kind": "CXXMethodDecl", "name": "norma", "type": { "qualType": "int () const" } } ] },
+      { "id": "0x9", "kind": "CXXRecordDecl", "loc": { "line": 4 }, "name": "Punto3", "tagUsed": "struct", "completeDefinition": true,
+        "definitionData": { "isPOD": true }, "bases": [ { "access": "public", "type": { "qualType": "zoo::Punto" }, "writtenAccess": "none" } ], "inner": [
+        { "id": "0xa", "kind": "FieldDecl", "name": "z", "type": { "qualType": "int" } } ] },
+      { "id": "0xb", "kind": "CXXRecordDecl", "loc": { "line": 5 }, "name": "Animal", "tagUsed": "class", "completeDefinition": true,
+        "definitionData": { "isPolymorphic": true, "isAbstract": true }, "inner": [
+        { "id": "0xc", "kind": "FieldDecl", "name": "tipo", "type": { "qualType": "char" } },
+        { "id": "0xd", "kind": "AccessSpecDecl", "access": "public" },
+        { "id": "0xe", "kind": "CXXMethodDecl", "name": "hablar", "virtual": true, "pure": true, "type": { "qualType": "void ()" } } ] },
+      { "id": "0xf", "kind": "CXXRecordDecl", "loc": { "line": 9 }, "name": "Perro", "tagUsed": "class", "completeDefinition": true,
+        "definitionData": { "isPolymorphic": true }, "bases": [ { "access": "public", "type": { "qualType": "Animal" }, "writtenAccess": "public" } ], "inner": [
+        { "id": "0x10", "kind": "AccessSpecDecl", "access": "protected" },
+        { "id": "0x11", "kind": "FieldDecl", "name": "patas", "type": { "qualType": "short" } },
+        { "id": "0x12", "kind": "FieldDecl", "name": "tipo", "type": { "qualType": "const zoo::Punto &" } },
+        { "id": "0x13", "kind": "CXXMethodDecl", "name": "hablar", "type": { "qualType": "void ()" } } ] },
+      { "id": "0x14", "kind": "CXXRecordDecl", "loc": { "line": 13 }, "name": "Etiquetada", "tagUsed": "struct", "completeDefinition": true,
+        "definitionData": { "isPOD": true }, "bases": [ { "access": "public", "type": { "qualType": "Vacia" }, "writtenAccess": "none" } ], "inner": [
+        { "id": "0x15", "kind": "FieldDecl", "name": "v", "type": { "qualType": "wchar_t" } } ] }
+    ] }
+  ]
+}"#;
+
+    let types = import::clang_ast(ast).unwrap();
+    let mut manager = TypeManager::new();
+    for (name, t) in types {
+        manager.add(name, t).unwrap();
+    }
+    let layout = |name: &str| match manager.get(&name.to_string()) {
+        Some(Type::Struct(s)) => (s.fields.iter().map(|f| f.name.clone()).collect::<Vec<Name>>(), s.c_offsets(&manager), s.c_size(&manager)),
+        other => panic!("'{}' debería ser un struct, no {:?}", name, other)
+    };
+
+    // methods and access specifiers don't count, empty classes take a byte
+    assert_eq!(layout("Punto"), (vec!["x".to_string(), "y".to_string()], vec![0, 4], 8));
+    assert!(matches!(manager.get(&"Vacia".to_string()), Some(Type::Atomic(a)) if a.representation == 1));
+
+    // a POD base is a member, a polymorphic class starts with its vptr
+    assert_eq!(layout("Punto3"), (vec!["base_Punto".to_string(), "z".to_string()], vec![0, 8], 12));
+    assert_eq!(layout("Animal"), (vec!["vptr".to_string(), "tipo".to_string()], vec![0, 8], 16));
+
+    // members of classes deriving from a non POD base go in its tail padding, as in Itanium
+    assert_eq!(layout("Perro"), (vec!["vptr".to_string(), "Animal_tipo".to_string(), "patas".to_string(), "tipo".to_string()], vec![0, 8, 10, 16], 24));
+    assert_eq!(layout("Etiquetada"), (vec!["v".to_string()], vec![0], 4));
+
+    let virtual_base = ast.replace(r#""writtenAccess": "public" }"#, r#""writtenAccess": "public", "isVirtual": true }"#);
+    match import::clang_ast(&virtual_base) {
+        Err(e) => assert_eq!(e.display(), "'Perro' usa herencia virtual, que no está soportada"),
+        Ok(_) => panic!("la herencia virtual no se puede importar")
+    }
+    let multiple = ast.replace(r#""type": { "qualType": "Vacia" }, "writtenAccess": "none" }"#, r#""type": { "qualType": "Vacia" } }, { "type": { "qualType": "Punto" } }"#);
+    assert!(matches!(import::clang_ast(&multiple), Err(ImportError::Unsupported(_, "herencia múltiple"))));
+}
+
+#[test]
+fn test_proto_import() {
+    use crate::driver::Program;
+    use crate::import::{self, ImportError};
+
+    let schema = r#"
+syntax = "proto3";
+package tienda.v1;
+import "google/protobuf/timestamp.proto";
+option java_package = "com.tienda";
+
+/* un pedido
+   con varias líneas */
+message Pedido {
+  enum Estado { PENDIENTE = 0; ENVIADO = 1 [deprecated = true]; }
+  message Linea {
+    string producto = 1;
+    uint32 cantidad = 2;
+  }
+  int64 id = 1;
+  Estado estado = 2;
+  repeated Linea lineas = 3;
+  map<string, int32> etiquetas = 4 [json_name = "tags"];
+  google.protobuf.Timestamp creado = 5;
+  oneof pago {
+    string tarjeta = 6;
+    .tienda.v1.Pedido.Linea regalo = 7;
+  }
+  optional bool urgente = 8;
+  reserved 9, 10;
+}
+message Vacio {}
+service Tienda { rpc Pedir(Pedido) returns (Vacio) { option idempotency_level = IDEMPOTENT; } }
+"#;
+
+    let imported = import::proto(schema, &[]).unwrap();
+    let names : Vec<&str> = imported.types.iter().map(|(n, _)| n.as_str()).collect();
+    let position = |n: &str| names.iter().position(|m| *m == n).unwrap_or_else(|| panic!("falta '{}' en {:?}", n, names));
+    assert!(position("Pedido_Estado") < position("Pedido"));
+    assert!(position("Pedido_Linea") < position("Pedido"));
+    assert!(position("Pedido_PagoCase") < position("Pedido_pago") && position("Pedido_pago") < position("Pedido"));
+    assert!(position("Pedido_EtiquetasEntry") < position("vec_Pedido_EtiquetasEntry"));
+    assert!(names.contains(&"vec_Pedido_Linea") && names.contains(&"box_google_protobuf_Timestamp") && names.contains(&"Vacio"));
+
+    let mut program = Program::new();
+    program.capture_output();
+    let path = std::env::temp_dir().join(format!("typemanager_proto_{}.proto", std::process::id()));
+    std::fs::write(&path, schema).unwrap();
+    program.run_line(&format!("importar proto {}", path.display()));
+    assert!(program.take_output().starts_with(&format!("Importados {} tipos", names.len())));
+
+    let manager = program.manager();
+    match manager.get(&"Pedido".to_string()) {
+        Some(Type::Struct(s)) => {
+            let fields : Vec<&str> = s.fields.iter().map(|f| f.name.as_str()).collect();
+            assert_eq!(fields, vec!["id", "estado", "lineas", "etiquetas", "creado", "pago_caso", "pago", "urgente"]);
+            assert_eq!(s.members[2], "vec_Pedido_Linea");
+            assert_eq!(s.members[6], "Pedido_pago");
+            assert_eq!(s.c_offsets(manager), vec![0, 8, 16, 40, 64, 72, 80, 104]);
+        },
+        other => panic!("'Pedido' debería ser un struct, no {:?}", other)
+    }
+    match manager.get(&"Pedido_PagoCase".to_string()) {
+        Some(Type::Enum(e)) => assert_eq!(e.variants, vec!["PAGO_NOT_SET", "kTarjeta", "kRegalo"]),
+        other => panic!("'Pedido_PagoCase' debería ser un enum, no {:?}", other)
+    }
+
+    // scalars can be mapped to other types
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_line("atomico puntero_texto 8 8");
+    program.run_line(&format!("importar proto {} --escalar string=puntero_texto --escalar int64=long", path.display()));
+    let output = program.take_output();
+    assert!(output.contains("'long' no existe"), "{}", output);
+    program.run_line("atomico long 8 8 entero");
+    program.run_line(&format!("importar proto {} --escalar string=puntero_texto --escalar int64=long", path.display()));
+    let manager = program.manager();
+    match manager.get(&"Pedido_Linea".to_string()) {
+        Some(Type::Struct(s)) => assert_eq!((s.members[0].as_str(), s.c_size(manager)), ("puntero_texto", 16)),
+        other => panic!("'Pedido_Linea' debería ser un struct, no {:?}", other)
+    }
+    assert!(manager.get(&"string".to_string()).is_none());
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(import::proto(schema, &[("texto".to_string(), "int".to_string())]), Err(ImportError::UnknownType(_))));
+    match import::proto("message A {\n  int32 x = 1\n}", &[]) {
+        Err(e) => assert_eq!(e.display(), "línea 3: se esperaba ';' y se encontró '}'"),
+        Ok(_) => panic!("falta un ';'")
+    }
+    assert!(matches!(import::proto("message A { repeated group B = 1 {} }", &[]), Err(ImportError::Syntax(1, _))));
+}
+
+#[test]
+fn test_capnp_export() {
+    use crate::driver::{ExportFormat, Program};
+
+    let mut program = Program::new();
+    program.capture_output();
+    let summary = program.run_script(
+        "atomico byte 1 1\natomico long 8 8 entero\natomico short 2 2\natomico float 4 4 flotante\natomico tres 3 1\n\
+         enum Estado_pedido NOT_SET pendiente enviado\n\
+         struct cabecera byte, long, short, float\n\
+         struct plano long, short, short, float\n\
+         contenedor vec_byte vec byte --capacidad 4\n\
+         union mezcla long, cabecera\n\
+         struct pedido cabecera, Estado_pedido, vec_byte, tres",
+        "tipos.tm"
+    );
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+    let schema = program.export(ExportFormat::Capnp);
+
+    assert!(schema.starts_with("# Generado por TypeManager\n@0x"));
+    // scalars fill the holes left by smaller ones, so the float goes before the long
+    assert!(schema.contains(
+        "# cabecera: 24 bytes en C, 2 palabras de datos y 0 punteros en Cap'n Proto (16 bytes)\n\
+         #   m2: 2 bytes en el byte 16 en C, 2 bytes en el byte 2 en Cap'n Proto\n\
+         #   m3: 4 bytes en el byte 20 en C, 4 bytes en el byte 4 en Cap'n Proto\n\
+         struct Cabecera {\n  m0 @0 :UInt8;\n  m1 @1 :Int64;\n  m2 @2 :UInt16;\n  m3 @3 :Float32;\n}\n"
+    ), "{}", schema);
+    assert!(schema.contains("# plano: misma disposición en C y en Cap'n Proto\nstruct Plano {"), "{}", schema);
+    assert!(schema.contains(
+        "# pedido: 64 bytes en C, 1 palabras de datos y 3 punteros en Cap'n Proto (32 bytes)\n\
+         #   m0: 24 bytes en el byte 0 en C, un puntero en el byte 8 en Cap'n Proto\n\
+         #   m1: 4 bytes en el byte 24 en C, 2 bytes en el byte 0 en Cap'n Proto\n\
+         #   m2: 24 bytes en el byte 32 en C, un puntero en el byte 16 en Cap'n Proto\n\
+         #   m3: 3 bytes en el byte 56 en C, un puntero en el byte 24 en Cap'n Proto\n\
+         struct Pedido {\n  m0 @0 :Cabecera;\n  m1 @1 :EstadoPedido;\n  m2 @2 :List(UInt8);\n  m3 @3 :Data;\n}\n"
+    ), "{}", schema);
+    assert!(schema.contains("# Estado_pedido: 4 bytes en C, 2 en Cap'n Proto\nenum EstadoPedido {\n  notSet @0;\n  pendiente @1;\n  enviado @2;\n}\n"), "{}", schema);
+    assert!(schema.contains("struct Mezcla {\n  union {\n    m0 @0 :Int64;\n    m1 @1 :Cabecera;\n  }\n}\n"), "{}", schema);
+    assert!(!schema.contains("struct Byte") && !schema.contains("VecByte"));
+    assert_eq!(schema, program.export(ExportFormat::Capnp));
+
+    assert_eq!(crate::cli::parse(vec!["export".to_string(), "--format=capnp".to_string()]), Ok(crate::cli::Command::Export(ExportFormat::Capnp, None)));
+}
+
+#[test]
+fn test_fbs_export() {
+    use crate::driver::{ExportFormat, Program};
+
+    let mut program = Program::new();
+    program.capture_output();
+    let summary = program.run_script(
+        "atomico byte 1 1\natomico int 4 4 entero\natomico double 8 8 flotante\natomico tres 3 1\n\
+         enum color rojo verde azul --repr minimo\n\
+         struct punto @aligned=8 x:int y:int\n\
+         struct pixel p:punto c:color t:tres\n\
+         struct bandera valor:int @bits=3 resto:byte\n\
+         contenedor puntos vec punto\n\
+         contenedor nombre string\n\
+         puntero siguiente pixel\n\
+         union forma punto, pixel\n\
+         union numero int, double\n\
+         struct figura n:nombre ps:puntos f:forma v:numero sig:siguiente",
+        "tipos.tm"
+    );
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+
+    program.run_line("exportar fbs");
+    let schema = program.take_output();
+    assert_eq!(schema.trim_end(), program.export(ExportFormat::Fbs).trim_end());
+    assert!(schema.starts_with("// Generado por TypeManager\n"));
+
+    // only scalars and fixed structs inside give a struct
+    assert!(schema.contains("struct punto (force_align: 8) {\n  x: int;\n  y: int;\n}\n"), "{}", schema);
+    assert!(schema.contains("struct pixel {\n  p: punto;\n  c: color;\n  t: [ubyte:3];\n}\n"), "{}", schema);
+    assert!(schema.contains("enum color : ubyte { rojo, verde, azul }\n"), "{}", schema);
+    assert!(schema.contains("// ADVERTENCIA: 'bandera.valor' es un campo de bits de 3 bits, se exporta entero\nstruct bandera {"), "{}", schema);
+
+    assert!(schema.contains(
+        "// ADVERTENCIA: 'figura.v' usa la union 'numero', hay que traducirlo a mano\n\
+         // ADVERTENCIA: 'figura.sig' es un puntero, se exporta como ulong\n\
+         table figura {\n  n: string;\n  ps: [punto];\n  f: forma;\n  // v: numero;\n  sig: ulong;\n}\n"
+    ), "{}", schema);
+    assert!(schema.contains("// ADVERTENCIA: 'forma' se exporta como union etiquetada, sus variantes no comparten memoria\nunion forma { punto, pixel }\n"), "{}", schema);
+    assert!(schema.contains("// ADVERTENCIA: 'numero' tiene variantes que no son structs, FlatBuffers no puede expresarla\n// union numero { int, double }\n"), "{}", schema);
+
+    program.run_line("exportar protobuf");
+    assert!(program.take_output().contains("protobuf"));
+    program.run_line("exportar");
+    program.run_line("exportar fbs capnp");
+    assert_eq!(program.take_output().lines().count(), 2);
+    program.run_line("exportar capnp");
+    assert!(program.take_output().contains("struct Figura {"));
+
+    assert_eq!(crate::cli::parse(vec!["export".to_string(), "-f".to_string(), "fbs".to_string()]), Ok(crate::cli::Command::Export(ExportFormat::Fbs, None)));
+}
+
+#[test]
+fn test_sql_export() {
+    use crate::codegen::Engine;
+    use crate::driver::{ExportFormat, Program};
+
+    let mut program = Program::new();
+    program.capture_output();
+    let summary = program.run_script(
+        "atomico short 2 2 entero\natomico double 8 8 real\natomico byte 1 1\natomico int 4 4 natural\n\
+         cadena nombre crudo 10\n\
+         struct punto x:int y:int\n\
+         struct fila a:short b:double c:byte d:int n:nombre\n\
+         struct lugar centro:punto clave:byte",
+        "tipos.tm"
+    );
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+
+    program.run_line("exportar sql");
+    let postgres = program.take_output();
+    assert_eq!(postgres.trim_end(), program.export(ExportFormat::Sql(Engine::Postgres)).trim_end());
+    assert!(postgres.contains(
+        "-- fila: 40 bytes en C\n\
+         -- fila en postgres: 68 bytes si alguna columna es nula, 68 si no. Cabecera de 23 bytes, 1 del mapa de nulos, 35 de datos con 8 de relleno y 4 del puntero de línea\n\
+         -- las columnas de longitud variable cuentan con su longitud máxima\n\
+         CREATE TABLE \"fila\" (\n    \"a\" SMALLINT,\n    \"b\" DOUBLE PRECISION,\n    \"c\" SMALLINT,\n    \"d\" INTEGER,\n    \"n\" VARCHAR(10)\n);\n"
+    ), "{}", postgres);
+    // nested structs are flattened
+    assert!(postgres.contains("CREATE TABLE \"lugar\" (\n    \"centro_x\" INTEGER,\n    \"centro_y\" INTEGER,\n    \"clave\" SMALLINT\n);"), "{}", postgres);
+
+    program.run_line("exportar sql --motor mysql");
+    let mysql = program.take_output();
+    assert!(mysql.contains(
+        "-- fila en mysql: 51 bytes. Cabecera de 5 bytes, 1 del mapa de nulos, 1 de longitudes, 19 de columnas ocultas y 25 de datos, sin relleno\n"
+    ), "{}", mysql);
+    assert!(mysql.contains("`d` INT UNSIGNED,\n    `n` VARCHAR(10)\n"), "{}", mysql);
+
+    program.run_line("exportar sql --motor sqlite");
+    let sqlite = program.take_output();
+    assert!(sqlite.contains(
+        "-- fila en sqlite: entre 35 y 43 bytes, según el rowid. Cabecera del registro de 6 bytes, 25 de datos como mucho, sin relleno, y 2 del puntero de celda\n"
+    ), "{}", sqlite);
+    assert!(sqlite.contains("\"b\" REAL,") && sqlite.contains("\"n\" TEXT\n"), "{}", sqlite);
+
+    program.run_line("exportar sql --motor oracle");
+    assert!(program.take_output().contains("oracle"));
+    program.run_line("exportar fbs --motor mysql");
+    assert!(program.take_output().contains("--motor"));
+}
+
+#[test]
+fn test_arrow_export() {
+    use crate::driver::{ExportFormat, Program};
+
+    let mut program = Program::new();
+    program.capture_output();
+    let summary = program.run_script(
+        "atomico short 2 2 entero\natomico int 4 4 natural\n\
+         cadena nombre crudo 10\n\
+         enum color rojo verde azul --repr minimo\n\
+         contenedor lista vec short --capacidad 4\n\
+         contenedor siguiente box fila\n\
+         struct punto x:int y:int\n\
+         struct fila p:punto e:color n:nombre v:lista sig:siguiente",
+        "tipos.tm"
+    );
+    assert!(summary.errors.is_empty(), "{:?}", summary.errors);
+
+    program.run_line("exportar arrow --filas 100");
+    let schema = program.take_output();
+    assert_eq!(schema.trim_end(), program.export(ExportFormat::Arrow(100)).trim_end());
+    assert!(schema.starts_with("# Generado por TypeManager, memoria para 100 filas\n"));
+    // boxes of the struct holding them are kept as addresses
+    assert!(schema.contains(
+        "fila: struct<p: struct<x: uint32, y: uint32>, e: dictionary<values=string, indices=int8>, n: string, v: list<item: int16>, sig: uint64>\n\
+         #   p: 896 bytes de valores y 192 de validez\n\
+         #   e: 256 bytes de valores y 64 de validez\n\
+         #   n: 1472 bytes de valores y 64 de validez\n\
+         #   v: 1280 bytes de valores y 128 de validez\n\
+         #   sig: 832 bytes de valores y 64 de validez\n\
+         # en columnas: 5312 bytes, 4736 sin mapas de validez. Por filas en C: 100 x 56 = 5600 bytes\n\
+         # las columnas ahorran 288 bytes\n"
+    ), "{}", schema);
+    assert!(schema.contains("punto: struct<x: uint32, y: uint32>\n"), "{}", schema);
+    assert!(program.export(ExportFormat::Arrow(1)).contains("# las columnas ocupan 1160 bytes más\n"));
+
+    program.run_line("exportar arrow --filas 0");
+    assert!(program.take_output().contains("argumento válido: 0"));
+    program.run_line("exportar arrow");
+    assert!(program.take_output().starts_with("# Generado por TypeManager, memoria para 1024 filas\n"));
+}
+
+#[test]
+fn test_binary_session() {
+    use crate::driver::Program;
+
+    let script = "atomico int 4 4 entero\natomico char 1 1 caracter\natomico raro 3 1\n\
+                  # lint: permitir orden, relleno\n\
+                  struct s @packed c:char @frio int @bits=3 raro\n\
+                  union u s int\n\
+                  enum e a b c --repr minimo\n\
+                  puntero p nodo --rebanada\n\
+                  contenedor v vec s --capacidad 3 --ancho 4\n\
+                  contenedor caja box nodo\n\
+                  cadena corta sso 24\n\
+                  cadena fija crudo 8\n";
+    let mut program = Program::new();
+    program.capture_output();
+    assert!(program.run_script(script, "tipos.tm").errors.is_empty());
+
+    let dir = std::env::temp_dir();
+    let binary = dir.join(format!("typemanager_sesion_{}.bin", std::process::id()));
+    let text = dir.join(format!("typemanager_sesion_{}.tm", std::process::id()));
+    program.run_line(&format!("guardar {} --formato bin", binary.display()));
+    program.run_line(&format!("guardar {}", text.display()));
+    assert_eq!(program.take_output(), format!("Guardados 11 tipos en '{}'\nGuardados 11 tipos en '{}'\n", binary.display(), text.display()));
+
+    let bytes = std::fs::read(&binary).unwrap();
+    assert!(bytes.starts_with(b"TMSB\x04\x00"));
+    assert!(bytes.len() < std::fs::read(&text).unwrap().len());
+
+    // the format is told by the contents, both give the same session
+    for file in [&binary, &text] {
+        let mut restored = Program::new();
+        restored.capture_output();
+        restored.run_line(&format!("cargar {}", file.display()));
+        assert_eq!(restored.take_output(), format!("Cargados 11 tipos de '{}'\n", file.display()));
+        assert_eq!(restored.session_script(), program.session_script());
+    }
+
+    // types already defined are kept
+    let mut restored = Program::new();
+    restored.capture_output();
+    restored.run_line("atomico int 8 8");
+    restored.run_line(&format!("cargar {} --formato bin", binary.display()));
+    assert!(restored.take_output().starts_with("Ya estaban definidos: int\nCargados 10 tipos"));
+    assert_eq!(restored.manager().get(&"s".to_string()).map(|t| Strategy::C.type_size(t, restored.manager())), Some(12));
+
+    // broken files are reported, not loaded
+    let mut broken = bytes.clone();
+    broken.truncate(bytes.len() - 3);
+    std::fs::write(&binary, &broken).unwrap();
+    restored.run_line(&format!("cargar {}", binary.display()));
+    assert_eq!(restored.take_output(), format!("[ERROR]: No se pudo cargar la sesión de '{}': el archivo está truncado\n", binary.display()));
+
+    broken = bytes.clone();
+    broken[4] = 9;
+    std::fs::write(&binary, &broken).unwrap();
+    restored.run_line(&format!("cargar {}", binary.display()));
+    assert!(restored.take_output().contains("la sesión es de la versión 9 del formato y solo se leen hasta la 4"));
+
+    restored.run_line(&format!("cargar {} --formato bin", text.display()));
+    assert!(restored.take_output().contains("no es una sesión binaria"));
+    restored.run_line(&format!("guardar {} --formato json", text.display()));
+    assert!(restored.take_output().contains("json"));
+    restored.run_line("cargar");
+    assert!(restored.take_output().contains("No hay suficientes argumentos"));
+
+    std::fs::remove_file(&binary).unwrap();
+    std::fs::remove_file(&text).unwrap();
+}
+
+#[test]
+fn test_multiple_sessions() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    let script = "atomico int 4 4\npuntero p int\nstruct nodo siguiente:p valor:int\ncadena nombre ptr_len\n";
+    program.run_script(script, "tipos.tm");
+    program.take_output();
+
+    // a session named after an ABI uses it, and starts empty
+    program.run_line("sesion nueva x86");
+    assert_eq!(program.take_output(), "[x86] Usando la sesión 'x86' (x86, punteros de 4 bytes)\n");
+    assert!(program.manager().is_empty());
+    program.run_script(script, "tipos.tm");
+    program.take_output();
+
+    let size = |program : &Program, name : &str| Strategy::C.type_size(program.manager().get(&name.to_string()).unwrap(), program.manager());
+    assert_eq!((size(&program, "p"), size(&program, "nodo"), size(&program, "nombre")), (4, 8, 8));
+
+    program.run_line("sesion usar principal");
+    program.take_output();
+    assert_eq!((size(&program, "p"), size(&program, "nodo"), size(&program, "nombre")), (8, 16, 16));
+
+    // the ABI can be given apart from the name, and every line is prefixed
+    program.run_line("sesion nueva movil aarch64");
+    program.run_line("sesion");
+    let output = program.take_output();
+    assert!(output.lines().all(|l| l.starts_with("[movil] ")), "{}", output);
+    assert!(output.contains("arm64"));
+    assert!(output.contains("principal") && output.contains("x86"));
+
+    program.run_line("sesion nueva x86");
+    assert!(program.take_output().contains("Ya existe una sesión llamada 'x86'"));
+    program.run_line("sesion usar riscv");
+    assert!(program.take_output().contains("No existe una sesión llamada 'riscv'"));
+    program.run_line("sesion nueva otra sparc");
+    assert!(program.take_output().contains("'sparc' no es una ABI conocida"));
+    program.run_line("sesion usar");
+    assert!(program.take_output().contains("No hay suficientes argumentos"));
+    assert_eq!(Abi::parse("AMD64"), Some(ABIS[0]));
+}
+
+#[test]
+fn test_merge_sessions() {
+    use crate::driver::Program;
+
+    let file = std::env::temp_dir().join(format!("typemanager_fusion_{}.tm", std::process::id()));
+    std::fs::write(&file, "atomico int 4 4\natomico corto 2 2\nstruct punto x:int y:int\nstruct nuevo p:punto c:corto\n").unwrap();
+
+    let base = "atomico int 4 4\natomico corto 4 4\nstruct punto x:int y:int\n";
+    let merge = |options : &str| {
+        let mut program = Program::new();
+        program.capture_output();
+        program.run_script(base, "base.tm");
+        program.take_output();
+        program.run_line(&format!("fusionar {} {}", file.display(), options));
+        (program.take_output(), program)
+    };
+    let size = |program : &Program, name : &str| program.manager().get(&name.to_string()).map(|t| Strategy::C.type_size(t, program.manager()));
+
+    // identical types are left alone, conflicts keep ours
+    let (output, program) = merge("");
+    assert!(output.contains("Fusionados 1 tipos"), "{}", output);
+    assert!(output.contains("Definidos igual, sin cambios: int, punto"));
+    assert!(output.contains("se mantienen los actuales: corto"));
+    assert_eq!(size(&program, "nuevo"), Some(12));
+
+    // renamed types are used by the types merged with them
+    let (output, program) = merge("--prefijo otro_");
+    assert!(output.contains("Renombrados: corto -> otro_corto"), "{}", output);
+    assert_eq!(size(&program, "otro_corto"), Some(2));
+    assert_eq!(size(&program, "corto"), Some(4));
+    assert_eq!(size(&program, "nuevo"), Some(12));
+    match program.manager().get(&"nuevo".to_string()) {
+        Some(Type::Struct(s)) => assert_eq!(s.members, vec!["punto", "otro_corto"]),
+        _ => panic!("nuevo should be a struct")
+    }
+
+    // replacing needs the same layout
+    let (output, program) = merge("--reemplazar");
+    assert!(output.contains("Conflictos con otra disposición, se mantienen los actuales: corto"), "{}", output);
+    assert_eq!(size(&program, "corto"), Some(4));
+
+    std::fs::write(&file, "atomico int 4 4\natomico corto 4 4 natural\n").unwrap();
+    let (output, program) = merge("--reemplazar");
+    assert!(output.contains("Reemplazados, con la misma disposición: corto"), "{}", output);
+    assert!(matches!(program.manager().get(&"corto".to_string()), Some(Type::Atomic(a)) if a.kind == Some(ValueKind::Unsigned)));
+
+    // a broken file changes nothing
+    std::fs::write(&file, "atomico raro 4 4\nstruct roto x:nada\n").unwrap();
+    let (output, program) = merge("");
+    assert!(output.contains("nada"), "{}", output);
+    assert_eq!(program.manager().len(), 3);
+
+    let (output, _) = merge("--prefijo");
+    assert!(output.contains("Este no es un argumento válido: --prefijo"));
+
+    std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn test_session_diff() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("atomico int 4 4\natomico char 1 1\npuntero p int\nstruct nodo siguiente:p valor:int\nstruct viejo a:int\n", "a.tm");
+    program.run_line("sesion nueva arm");
+    program.run_script("atomico int 4 4 entero\natomico char 1 1\npuntero p int\nstruct nodo siguiente:p marca:char valor:int\nstruct nuevo a:char\n", "b.tm");
+    program.take_output();
+
+    program.run_line("diff principal arm");
+    let output = program.take_output();
+    let lines : Vec<&str> = output.lines().map(|l| l.trim_start_matches("[arm] ")).collect();
+    assert_eq!(lines, vec![
+        "De 'principal' a 'arm': 1 añadidos, 1 quitados, 3 modificados",
+        "~ int: definido de otra forma, misma disposición",
+        "~ nodo: tamaño 16 -> 12, alineación 8 -> 4, relleno 4 -> 3",
+        "    + marca: desplazamiento 4",
+        "+ nuevo",
+        "~ p: tamaño 8 -> 4, alineación 8 -> 4",
+        "- viejo"
+    ]);
+
+    // without changes, and with sessions that don't exist
+    let diff = crate::type_system::diff(program.manager(), program.manager());
+    assert!(diff.is_empty());
+    program.run_line("diff arm arm");
+    assert!(program.take_output().contains("tienen los mismos tipos"));
+    program.run_line("diff arm x86");
+    assert!(program.take_output().contains("No existe una sesión llamada 'x86'"));
+    program.run_line("diff arm");
+    assert!(program.take_output().contains("No hay suficientes argumentos"));
+}
+
+#[test]
+fn test_shared_session() {
+    use std::io::{BufReader, Cursor, Write};
+    use std::sync::{Arc, Mutex};
+    use crate::driver::Program;
+    use crate::shared::{Message, SharedSession};
+    use crate::cli::{parse, Command};
+
+    // what a client receives, kept to read it back
+    #[derive(Clone, Default)]
+    struct Inbox(Arc<Mutex<Vec<u8>>>);
+    impl Write for Inbox {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> { self.0.lock().unwrap().write(buf) }
+        fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+    }
+    impl Inbox {
+        fn messages(&self) -> Vec<Message> {
+            let bytes = std::mem::take(&mut *self.0.lock().unwrap());
+            let mut input = BufReader::new(Cursor::new(bytes));
+            std::iter::from_fn(|| Message::read(&mut input).unwrap()).collect()
+        }
+    }
+
+    let session = SharedSession::new(Program::new());
+    let (first, second) = (Inbox::default(), Inbox::default());
+    let a = session.join(Box::new(first.clone()));
+    let b = session.join(Box::new(second.clone()));
+    assert_eq!(first.messages(), vec![Message::Hello(a)]);
+    assert_eq!(second.messages(), vec![Message::Hello(b)]);
+
+    // changes go to everyone, queries only to whoever asked
+    session.run(a, "atomico int 4 4");
+    assert_eq!(first.messages(), vec![Message::Result(a, vec![])]);
+    assert_eq!(second.messages(), vec![Message::Change(a, String::from("atomico int 4 4"), vec![])]);
+
+    session.serve_client(b, Cursor::new("struct par int int\n\ncalcular 1 + 1\nsalir\natomico char 1 1\n"));
+    assert_eq!(second.messages(), vec![Message::Result(b, vec![]), Message::Result(b, vec![String::from("2")])]);
+    assert_eq!(first.messages(), vec![Message::Change(b, String::from("struct par int int"), vec![]), Message::Bye(b)]);
+
+    // the session survives a client leaving, and errors come back as output
+    session.run(a, "describir nada");
+    match first.messages().as_slice() {
+        [Message::Result(client, output)] => assert!(*client == a && output.len() == 1 && output[0].contains("nada")),
+        other => panic!("unexpected messages {:?}", other)
+    }
+
+    // every message reads back as it was sent
+    for message in [Message::Hello(3), Message::Bye(2), Message::Result(1, vec![String::from(""), String::from("x")]), Message::Change(4, String::from("c"), vec![])] {
+        let encoded = message.encode();
+        assert_eq!(Message::read(&mut Cursor::new(encoded)).unwrap(), Some(message));
+    }
+    assert!(Message::read(&mut Cursor::new("HOLA uno 0\n")).is_err());
+    assert!(Message::read(&mut Cursor::new("RESULTADO 1 2\nsolo una\n")).is_err());
+    assert!(Message::read(&mut Cursor::new("CAMBIO 1 0\n")).is_err());
+
+    let parse = |line : &str| parse(line.split_whitespace().map(String::from));
+    assert_eq!(parse("share -p 9000"), Ok(Command::Share(9000, None)));
+    assert_eq!(parse("connect --host 10.0.0.2"), Ok(Command::Connect(String::from("10.0.0.2"), crate::cli::DEFAULT_PORT)));
+    assert!(parse("serve --host 10.0.0.2").is_err());
+}
+
+#[test]
+fn test_snapshot_reads() {
+    use std::sync::Arc;
+    use crate::driver::Program;
+    use crate::shared::{Reader, SharedSession, Snapshot};
+
+    // readers keep the version they loaded after a new one is published
+    let snapshot = Snapshot::new(0, String::from("uno"));
+    let (revision, old) = snapshot.load();
+    snapshot.store(1, String::from("dos"));
+    assert_eq!((revision, old.as_str()), (0, "uno"));
+    assert_eq!(snapshot.revision(), 1);
+    assert_eq!(snapshot.load().1.as_str(), "dos");
+    assert_eq!(Arc::strong_count(&old), 1);
+
+    // queries are answered from the types of the last change, changes publish new ones
+    let session = Arc::new(SharedSession::new(Program::new()));
+    let client = session.join(Box::new(std::io::sink()));
+    let mut reader = Reader::default();
+    assert!(!session.query(client, "atomico int 4 4", &mut reader));
+    session.run(client, "atomico int 4 4");
+    let revision = session.types().revision();
+    assert!(session.types().load().1.get(&String::from("int")).is_some());
+    assert!(session.query(client, "describir int", &mut reader));
+    assert_eq!(reader.revision(), Some(revision));
+
+    session.run(client, "struct par int int");
+    assert!(session.types().revision() > revision);
+    assert!(session.types().load().1.get(&String::from("par")).is_some());
+
+    // every command that only reads types is a query, addresses only if their base reads no variable
+    for line in ["direcciones par 0x1000", "anotar par 0100000002000000", "decodificar par 0100000002000000", "generar par 42", "planificar 100 par:4", "lint", "lint par", "lint --reglas"] {
+        assert!(session.query(client, line, &mut reader), "'{}' is not a query", line);
+    }
+    assert!(!session.query(client, "direcciones par $base", &mut reader));
+    assert!(!session.query(client, "let base = 16", &mut reader));
+
+    // a query doesn't wait for a command holding the session
+    let busy = Arc::clone(&session);
+    let held = std::thread::spawn(move || busy.run(client, "calcular 1 + 1"));
+    assert!(session.query(client, "describir par", &mut reader));
+    assert_eq!(reader.revision(), Some(session.types().revision()));
+    held.join().unwrap();
+}
+
+#[test]
+fn test_cancel() {
+    use std::io::Cursor;
+    use crate::driver::{Keywords, Program};
+    use crate::type_system::cancel::{self, Canceller};
+
+    // permutations come one at a time, in the order they always had
+    for n in 0..6 {
+        let list : Vec<usize> = (0..n).collect();
+        assert_eq!(Permutations::new(list.clone()).collect::<Vec<_>>(), permutations(&mut list.clone()));
+    }
+
+    let mut manager = TypeManager::new();
+    let _ = manager.add("char".to_string(), atom(1, 1));
+    let _ = manager.add("int".to_string(), atom(4, 4));
+    let members = (0..9).map(|i| match i % 2 { 0 => "char", _ => "int" }.to_string()).collect();
+    let _ = manager.add("s".to_string(), strc(members));
+    let s = match manager.get(&"s".to_string()) {
+        Some(Type::Struct(s)) => s,
+        _ => panic!("s is not a struct")
+    };
+
+    // nothing to cancel outside of a computation
+    let canceller = Canceller::default();
+    assert!(!canceller.cancel());
+    assert!(!cancel::requested());
+
+    // a cancelled search keeps the best order found so far, and it's not cached
+    manager.layout_cache().clear();
+    let computation = canceller.start();
+    assert!(canceller.cancel() && computation.cancelled() && cancel::requested());
+    let (_, size, stats) = s.optimize(&manager);
+    assert!(stats.cancelled && stats.permutations < 362880);
+    assert!(size >= 21 && stats.display().contains("Cancelado"));
+    manager.size_of(&"s".to_string(), Strategy::Optimized);
+    assert!(manager.layout_cache().is_empty());
+    drop(computation);
+
+    let (_, size, stats) = s.optimize(&manager);
+    assert_eq!((size, stats.cancelled, stats.permutations), (21, false, 362880));
+
+    // commands nested in another one are cancelled with it
+    let outer = canceller.start();
+    let inner = Canceller::default().start();
+    assert!(canceller.cancel() && !inner.cancelled() && cancel::requested());
+    drop(inner);
+    drop(outer);
+
+    // servers read `cancelar` while a command runs, it only comes out when there's nothing to stop
+    let running = canceller.start();
+    let lines : Vec<String> = Program::read_commands(Cursor::new("cancelar\nlistar\n"), Keywords::default(), vec![canceller.clone()])
+                                .into_iter()
+                                .map(|l| l.unwrap())
+                                .collect();
+    assert_eq!(lines, vec!["listar"]);
+    assert!(running.cancelled());
+    drop(running);
+    let lines : Vec<String> = Program::read_commands(Cursor::new("cancelar\n"), Keywords::default(), vec![canceller.clone()])
+                                .into_iter()
+                                .map(|l| l.unwrap())
+                                .collect();
+    assert_eq!(lines, vec!["cancelar"]);
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_line("cancelar");
+    assert_eq!(program.take_output(), "No hay ningún cálculo en curso\n");
+    program.run_line("cancelar ya");
+    assert!(program.take_output().starts_with("[ERROR]"));
+}
+
+#[test]
+fn test_limits() {
+    use std::time::Duration;
+    use crate::driver::Program;
+    use crate::config::Config;
+
+    let (config, warnings) = Config::parse("limite_miembros = 3\nlimite_anidamiento = 2\nlimite_tipos = 6\nlimite_optimizador_ms = no\nlimite_tipos = 0\n");
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(config.limits, Limits { members: Some(3), depth: Some(2), types: Some(6), optimizer: None });
+
+    // each limit has its own error, the type is not added
+    let mut program = Program::with_config(Config { autosave: false, ..config });
+    program.capture_output();
+    program.run_script("atomico c 1 1\natomico i 4 4\nstruct a c i\nstruct b a c\n", "limites");
+    assert_eq!(program.take_output(), "");
+    program.run_line("struct grande c i c i");
+    assert_eq!(program.take_output(), "[TYPE ERROR]: 'grande' tiene 4 miembros, el límite es 3\n");
+    program.run_line("union u c i c i");
+    assert!(program.take_output().contains("'u' tiene 4 miembros"));
+    program.run_line("struct hondo b c");
+    assert_eq!(program.take_output(), "[TYPE ERROR]: 'hondo' anida 3 tipos compuestos, el límite es 2\n");
+    program.run_script("struct ancho a a c\natomico d 8 8\n", "limites");
+    program.run_line("atomico e 2 2");
+    assert_eq!(program.take_output(), "[TYPE ERROR]: La sesión ya tiene 6 tipos, el límite configurado\n");
+    assert_eq!(program.manager().len(), 6);
+
+    // the optimizer keeps the best order it found in time, and the command warns about it
+    let mut manager = TypeManager::new();
+    manager.set_limits(Limits { optimizer: Some(Duration::from_millis(1)), ..Limits::default() });
+    let _ = manager.add("c".to_string(), atom(1, 1));
+    let _ = manager.add("i".to_string(), atom(4, 4));
+    let _ = manager.add("s".to_string(), strc((0..10).map(|i| match i % 2 { 0 => "c", _ => "i" }.to_string()).collect()));
+    let before = limits::timed_out();
+    let stats = manager.optimizer_stats(&"s".to_string()).unwrap().unwrap();
+    assert!(stats.timed_out && !stats.cancelled && stats.permutations < 3628800);
+    assert!(limits::timed_out() > before && stats.display().contains("Sin tiempo"));
+    manager.layout_cache().clear();
+    manager.size_of(&"s".to_string(), Strategy::Optimized);
+    assert_eq!(manager.layout_cache().len(), 2);     // its members, not the struct
+
+    let mut program = Program::with_config(Config { autosave: false, limits: manager.limits(), ..Config::default() });
+    program.capture_output();
+    program.run_script("atomico c 1 1\natomico i 4 4\nstruct s c i c i c i c i c i\n", "limites");
+    program.take_output();
+    program.run_line("describir s");
+    assert!(program.take_output().ends_with("[AVISO]: El optimizador llegó a su límite de 1 ms, los resultados son los mejores encontrados hasta entonces\n"));
+}
+
+#[test]
+fn test_progressive_optimization() {
+    use std::time::Duration;
+    use crate::driver::Program;
+    use crate::config::Config;
+
+    // every order reported is strictly better than the ones before, the last is the result
+    let mut manager = TypeManager::new();
+    let _ = manager.add("c".to_string(), atom(1, 1));
+    let _ = manager.add("i".to_string(), atom(4, 4));
+    let _ = manager.add("s".to_string(), strc(vec!["c".to_string(), "i".to_string(), "c".to_string(), "i".to_string(), "c".to_string()]));
+    let s = match manager.get(&"s".to_string()) {
+        Some(Type::Struct(s)) => s,
+        _ => panic!("s is not a struct")
+    };
+    let mut found = vec![];
+    let (order, size, _) = s.optimize_with(&manager, &mut |order, size| found.push((order.to_vec(), size)));
+    assert_eq!(found.first().map(|f| f.1), Some(17));
+    assert!(found.windows(2).all(|w| w[1].1 < w[0].1));
+    assert_eq!(found.last(), Some(&(order, size)));
+    assert_eq!(size, 11);
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("atomico c 1 1\natomico i 4 4\nstruct s a:c b:i c:c d:i e:c\n", "progresivo");
+    program.run_line("describir s --progresivo");
+    let output = program.take_output();
+    let lines : Vec<&str> = output.lines().take_while(|l| l.starts_with("⏳")).collect();
+    assert!(lines.len() >= 2 && lines[0].ends_with(": 17 bytes con a b c d e"));
+    assert!(lines.last().unwrap().contains(": 11 bytes con "));
+    assert!(output.contains("Símbolo: s"));
+
+    program.run_line("describir s --progresivo --json");
+    let output = program.take_output();
+    assert!(output.starts_with("{\"size\":17,\"order\":[\"a\",\"b\",\"c\",\"d\",\"e\"]}\n"));
+    program.run_line("describir c --progresivo");
+    assert!(program.take_output().starts_with("Símbolo: c"));
+
+    // a search stopped early ends with the best order it found, without searching again
+    let limits = Limits { optimizer: Some(Duration::from_millis(1)), ..Limits::default() };
+    let mut program = Program::with_config(Config { autosave: false, limits, ..Config::default() });
+    program.capture_output();
+    program.run_script("atomico c 1 1\natomico i 4 4\nstruct g c i c i c i c i c i\n", "progresivo");
+    program.take_output();
+    program.run_line("describir g --progresivo");
+    let output = program.take_output();
+    assert!(output.starts_with("⏳") && !output.contains("Símbolo"));
+    assert!(output.contains("La búsqueda se detuvo antes de probar todos los órdenes"));
+}
+
+#[test]
+fn test_anneal() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico c 1 1
+atomico s 2 2
+atomico i 4 4
+atomico l 8 8
+struct g a:c b:l c:s d:c e:i f:l g:c h:s @grupo=x i:c @grupo=x j:i k:c l:l m:s n:c o:i p:c q:l r:c
+struct par x:c y:l
+", "recocido");
+    program.take_output();
+    let manager = program.manager();
+    let g = "g".to_string();
+
+    // the same seed always gives the same order, never worse than the greedy one
+    let options = AnnealOptions { seed: 7, steps: 2000, restarts: 3 };
+    let annealing = manager.anneal(&g, options).unwrap();
+    assert_eq!(manager.anneal(&g, options).unwrap().order, annealing.order);
+    assert!(annealing.size <= annealing.greedy_size && annealing.greedy_size < annealing.declared_size);
+    assert!(!annealing.stopped);
+    let mut sorted = annealing.order.clone();
+    sorted.sort();
+    assert_eq!(sorted, (0..18).collect::<Vec<usize>>());
+
+    // grouped members stay next to each other, the greedy order puts the biggest alignment first
+    let h = annealing.order.iter().position(|i| *i == 7).unwrap();
+    assert!(annealing.order.get(h + 1) == Some(&8) || (h > 0 && annealing.order[h - 1] == 8));
+    assert_eq!(&annealing.greedy[..4], &[1, 5, 11, 16]);
+
+    // without steps the greedy order is the result
+    let greedy = manager.anneal(&g, AnnealOptions { steps: 0, ..options }).unwrap();
+    assert_eq!((greedy.order.clone(), greedy.size), (greedy.greedy.clone(), greedy.greedy_size));
+    assert!(manager.anneal(&"c".to_string(), options).is_err());
+
+    program.run_line("recocer par --semilla 3 --pasos 100");
+    let output = program.take_output();
+    let lines : Vec<&str> = output.lines().collect();
+    assert!(lines[0].starts_with("Recocido simulado de 'par' (semilla 3, 4 reinicios de 100 pasos, "));
+    assert_eq!(&lines[1..], &[
+        "   * Recocido: 9 bytes con y x",
+        "   * Voraz (mayor alineación primero): 9 bytes con y x",
+        "   * Declaración: 16 bytes",
+        "El recocido no mejora el orden voraz"
+    ]);
+    program.run_line("recocer par --pasos muchos");
+    assert!(program.take_output().contains("muchos"));
+}
+
+#[cfg(feature = "solver")]
+#[test]
+fn test_solver() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico c 1 1
+atomico s 2 2
+atomico i 4 4
+atomico l 8 8
+struct libre a:c b:l c:s d:c e:i f:c g:s
+struct grupos a:c @grupo=x b:l c:c @grupo=x d:s e:i @grupo=y f:c g:i @grupo=y
+struct mezcla a:l b:c @grupo=z c:i d:c @grupo=z e:s
+", "solucionador");
+    program.take_output();
+    let manager = program.manager();
+
+    // the solver proves the same size the optimizer finds trying every order, keeping groups together
+    for name in ["libre", "grupos", "mezcla"] {
+        let name = name.to_string();
+        let solution = manager.solve(&name).unwrap().unwrap();
+        let s = match manager.get(&name) {
+            Some(Type::Struct(s)) => s,
+            _ => panic!("{} is not a struct", name)
+        };
+        let (_, size, _) = s.optimize(manager);
+        assert_eq!(solution.size, size, "{}", name);
+        let mut sorted = solution.order.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..s.members.len()).collect::<Vec<usize>>());
+        assert!(solution.states <= 1 << s.members.len());
+    }
+    // a group can't be left unfinished, so fewer subsets can come first
+    let grouped = manager.solve(&"grupos".to_string()).unwrap().unwrap();
+    assert_eq!(grouped.states, 96);
+    let grouped = grouped.order;
+    let position = |i : usize| grouped.iter().position(|m| *m == i).unwrap();
+    assert_eq!(position(0).abs_diff(position(2)), 1);
+    assert_eq!(position(4).abs_diff(position(6)), 1);
+
+    assert!(manager.solve(&"c".to_string()).is_err());
+    program.run_line("resolver libre");
+    let output = program.take_output();
+    assert!(output.starts_with("Orden óptimo de 'libre': 19 bytes con b c a d e g f\n"));
+    assert!(output.contains("Probado sobre 128 subconjuntos de miembros"));
+
+    let members = vec!["c"; 21].join(" ");
+    program.run_line(&format!("struct enorme {}", members));
+    program.run_line("resolver enorme");
+    assert!(program.take_output().contains("'enorme' tiene 21 miembros, el solucionador exacto toma hasta 20"));
+}
+
+#[test]
+fn test_pareto() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico c 1 1
+atomico i 4 4
+atomico l 8 8
+atomico b 40 8
+struct p a:b b:b x:c @caliente y:i @caliente z:l w:c
+struct frio a:b x:c
+", "pareto");
+    program.take_output();
+
+    // the smallest order leaves hot members last, keeping them first costs some bytes
+    let pareto = program.manager().pareto(&"p".to_string(), 64).unwrap();
+    assert_eq!(pareto.hot, vec![2, 3]);
+    let points : Vec<(usize, usize)> = pareto.tradeoffs.iter().map(|t| (t.size, t.outside)).collect();
+    assert_eq!(points, vec![(94, 5), (96, 0)]);
+    assert!(!pareto.stopped);
+    let local = &pareto.tradeoffs[1].order;
+    assert!(local.iter().position(|i| *i == 2).unwrap() < 2 || local.iter().position(|i| *i == 3).unwrap() < 4);
+
+    program.run_line("pareto p --linea 16");
+    let output = program.take_output();
+    assert!(output.starts_with("Compromisos de 'p' entre tamaño y campos calientes en la primera línea de caché (16 bytes):"));
+    assert!(output.ends_with("Dejar 5 bytes calientes menos fuera de la primera línea cuesta 2 bytes más\n"));
+
+    // without hot members the smallest order is all there is
+    program.run_line("pareto frio");
+    let output = program.take_output();
+    assert!(output.starts_with("'frio' no tiene campos marcados con @caliente"));
+    assert_eq!(program.manager().pareto(&"frio".to_string(), 64).unwrap().tradeoffs.len(), 1);
+    program.run_line("pareto c");
+    assert!(program.take_output().contains("no es un struct"));
+}
+
+#[test]
+fn test_pinned_members() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico c 1 1
+atomico i 4 4
+atomico l 8 8
+struct h magia:i @fijo a:c b:l c:c d:i
+struct g a:c b:l c:c @fijo d:i
+struct x a:c @grupo=x b:c @fijo c:c @grupo=x d:l
+", "test");
+    program.take_output();
+    let manager = program.manager();
+    let optimized = |name : &str| match manager.get(&name.to_string()) {
+        Some(Type::Struct(s)) => s.layout(manager, Strategy::Optimized).iter().map(|m| (m.index, m.offset)).collect::<Vec<_>>(),
+        _ => panic!("{} is not a struct", name)
+    };
+
+    // the header stays first, the rest is still optimized
+    let layout = optimized("h");
+    assert_eq!(layout[0], (0, 0));
+    assert_eq!(manager.size_of(&"h".to_string(), Strategy::Optimized), 18);
+
+    // a pinned member keeps its declared position and offset
+    let layout = optimized("g");
+    assert_eq!(layout[2], (2, 16));
+    assert_eq!(manager.size_of(&"g".to_string(), Strategy::Optimized), 18);
+    assert!(manager.constraints(&"g".to_string()).unwrap().iter().all(|c| c.kept()));
+
+    // every search keeps pinned members in place
+    #[cfg(feature = "solver")]
+    assert_eq!(manager.solve(&"g".to_string()).unwrap().unwrap().size, 18);
+    let annealing = manager.anneal(&"g".to_string(), AnnealOptions::default()).unwrap();
+    assert_eq!((annealing.order[2], annealing.size), (2, 18));
+    let pareto = manager.pareto(&"g".to_string(), 64).unwrap();
+    assert_eq!(pareto.tradeoffs[0].order[2], 2);
+
+    // with no order keeping every constraint the declared one is kept, and the report tells which failed
+    assert_eq!(optimized("x").iter().map(|(i, _)| *i).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+    let constraints = manager.constraints(&"x".to_string()).unwrap();
+    assert_eq!(constraints, vec![
+        Constraint::Pinned { member: 1, position: 1, offset: 1, kept: true },
+        Constraint::Group { label: "x".to_string(), members: vec![0, 2], kept: false }
+    ]);
+
+    program.run_line("desplazamientos g");
+    assert!(program.take_output().ends_with("🔒 Restricciones:\n   * 'c' fijo en la posición 2, desplazamiento 16: respetada\n"));
+    program.run_line("describir x");
+    assert!(program.take_output().contains("   * grupo 'x' junto (a, c): no respetada"));
+    program.run_line("describir c");
+    assert!(!program.take_output().contains("Restricciones"));
+}
+
+#[test]
+fn test_keep_first_member() {
+    use crate::driver::Program;
+    use crate::config::Config;
+    use crate::cli::split_options;
+
+    let script = "\
+atomico c 1 1
+atomico l 8 8
+struct base tag:c a:l b:c
+struct hijo @conservar_primero tag:c a:l b:c
+struct fijo tag:c a:l @fijo b:c
+";
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script(script, "test");
+    program.take_output();
+    let manager = program.manager();
+    let order = |name : &str| match manager.get(&name.to_string()) {
+        Some(Type::Struct(s)) => s.layout(manager, Strategy::Optimized).iter().map(|m| m.index).collect::<Vec<_>>(),
+        _ => panic!("{} is not a struct", name)
+    };
+
+    // only the struct asking for it keeps its first member first, the rest is reordered
+    assert_eq!(order("base")[0], 1);
+    assert_eq!(order("hijo"), vec![0, 2, 1]);
+    assert_eq!(manager.size_of(&"hijo".to_string(), Strategy::Optimized), 16);
+    assert_eq!(manager.constraints(&"hijo".to_string()).unwrap(), vec![Constraint::First { member: 0, kept: true }]);
+
+    // apart from pinning: a pinned member doesn't keep the first one first
+    assert_eq!(manager.constraints(&"fijo".to_string()).unwrap().len(), 1);
+    assert!(!matches!(manager.constraints(&"fijo".to_string()).unwrap()[0], Constraint::First { .. }));
+
+    // for every struct, from the configuration or the command line
+    let (config, _) = Config::parse("conservar_primero = si\n");
+    assert!(config.keep_first);
+    assert!(split_options(["--conservar-primero"].iter().map(|s| s.to_string())).0.keep_first);
+    let mut program = Program::with_config(Config { autosave: false, ..config });
+    program.capture_output();
+    program.run_script(script, "test");
+    program.run_line("describir base");
+    assert!(program.take_output().contains("   * 'tag' primero, en el desplazamiento 0: respetada"));
+    let manager = program.manager();
+    match manager.get(&"base".to_string()) {
+        Some(Type::Struct(s)) => assert_eq!(s.layout(manager, Strategy::Optimized)[0].index, 0),
+        _ => panic!("base is not a struct")
+    }
+    #[cfg(feature = "solver")]
+    assert_eq!(manager.solve(&"base".to_string()).unwrap().unwrap().order, vec![0, 2, 1]);
+}
+
+#[test]
+fn test_stride_objective() {
+    use crate::driver::Program;
+    use crate::config::Config;
+
+    let script = "\
+atomico c 1 1
+atomico i 4 4
+atomico l 8 8
+struct s a:l b:c d:i e:c f:c g:c h:c
+struct t a:c b:l c:c
+";
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script(script, "test");
+    program.take_output();
+    let manager = program.manager();
+
+    // saving 3 bytes doesn't save a whole alignment unit, the stride objective keeps declaration order
+    let reports = manager.objectives(&"s".to_string()).unwrap();
+    let summary : Vec<(Option<Objective>, usize, usize)> = reports.iter().map(|r| (r.objective, r.size, r.stride)).collect();
+    assert_eq!(summary, vec![(Some(Objective::Size), 17, 24), (Some(Objective::Stride), 20, 24), (None, 20, 24)]);
+    assert_eq!(reports[1].order, reports[2].order);
+
+    // both objectives save a whole element here
+    let reports = manager.objectives(&"t".to_string()).unwrap();
+    assert_eq!((reports[0].stride, reports[1].stride, reports[2].stride), (16, 16, 24));
+
+    program.run_line("paso s");
+    let output = program.take_output();
+    assert!(output.starts_with("Objetivos de 's' (los arrays avanzan de a múltiplos de 8 bytes, la mayor alineación):"));
+    assert!(output.contains("Reordenar ahorra 3 bytes de tamaño pero ninguno en arrays"));
+    assert!(output.ends_with("El optimizador usa el objetivo 'tamaño'\n"));
+    program.run_line("paso t");
+    assert!(program.take_output().contains("Reordenar ahorra 8 bytes por elemento en arrays"));
+    assert!(program.run_command("paso c").is_err());
+    assert!(program.run_command("paso").is_err());
+
+    // the configured objective is what every layout uses
+    let (config, warnings) = Config::parse("objetivo = paso\n");
+    assert!(warnings.is_empty());
+    assert_eq!(config.objective, Objective::Stride);
+    assert!(!Config::parse("objetivo = velocidad\n").1.is_empty());
+    let mut program = Program::with_config(Config { autosave: false, ..config });
+    program.capture_output();
+    program.run_script(script, "test");
+    assert_eq!(program.manager().size_of(&"s".to_string(), Strategy::Optimized), 20);
+    program.run_line("paso s");
+    assert!(program.take_output().ends_with("El optimizador usa el objetivo 'paso'\n"));
+}
+
+#[test]
+fn test_access_trace() {
+    use crate::driver::{CommandError, Program};
+
+    let dir = std::env::temp_dir().join(format!("typemanager_trace_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("accesos.txt"), "# bucle principal\n100 x q\n10 a w\ny z\n").unwrap();
+    std::fs::write(dir.join("mala.txt"), "5 x nada\n7\n").unwrap();
+    std::fs::write(dir.join("vacia.txt"), "# nada\n").unwrap();
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("\
+atomico c 1 1
+atomico i 4 4
+atomico l 8 8
+atomico b 40 8
+struct p a:b x:i y:c z:l w:b q:i
+", "test");
+    program.take_output();
+
+    // weights come first, a pattern without one happens once
+    let text = std::fs::read_to_string(dir.join("accesos.txt")).unwrap();
+    let s = match program.manager().get(&"p".to_string()) {
+        Some(Type::Struct(s)) => s.clone(),
+        _ => panic!("p is not a struct")
+    };
+    let patterns = parse_trace(&text, &s).unwrap();
+    assert_eq!(patterns, vec![
+        AccessPattern { weight: 100, members: vec![1, 5] },
+        AccessPattern { weight: 10, members: vec![0, 4] },
+        AccessPattern { weight: 1, members: vec![2, 3] }
+    ]);
+    assert_eq!(parse_trace("5 x nada\n7\n", &s).unwrap_err().iter().map(|(i, _)| *i).collect::<Vec<_>>(), vec![1, 2]);
+
+    // the trace order never touches more lines than the others, and may cost some bytes
+    let layout = program.manager().trace_layout(&"p".to_string(), &patterns, 32).unwrap();
+    assert_eq!(layout.best.lines, vec![1, 3, 1]);
+    assert_eq!(layout.smallest.lines, vec![1, 3, 2]);
+    assert_eq!(layout.declared.lines, vec![2, 3, 1]);
+    assert!(layout.best.expected(&patterns) < layout.smallest.expected(&patterns));
+    assert_eq!((layout.best.size, layout.smallest.size), (104, 97));
+    assert!(!layout.stopped);
+
+    let file = dir.join("accesos.txt").display().to_string();
+    program.run_line(&format!("traza p {} --linea 32", file));
+    let output = program.take_output();
+    assert!(output.starts_with(&format!("Accesos a 'p' según '{}' (líneas de caché de 32 bytes, 3 patrones):", file)));
+    assert!(output.ends_with("El orden de la traza toca 0.01 líneas menos por acceso a cambio de 7 bytes más\n"));
+
+    // problems in the trace are reported with their line
+    match program.run_command(&format!("traza p {}", dir.join("mala.txt").display())) {
+        Err(CommandError::Include { errors, .. }) => {
+            assert_eq!(errors.len(), 2);
+            assert!(errors[0].ends_with("mala.txt:1: 'nada' no es un campo del struct"));
+        },
+        other => panic!("unexpected result {:?}", other)
+    }
+    assert!(program.run_command(&format!("traza p {}", dir.join("vacia.txt").display())).is_err());
+    assert!(program.run_command(&format!("traza c {}", file)).is_err());
+    assert!(program.run_command("traza p").is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_output_redirection() {
+    use crate::driver::{CommandError, Program, ProgramError};
+
+    let dir = std::env::temp_dir().join(format!("typemanager_redirect_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = |name : &str| dir.join(name).display().to_string();
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("atomico c 1 1\natomico i 4 4\nstruct s a:c b:i\n", "test");
+    program.take_output();
+
+    // the output goes to the file and nothing is shown
+    program.run_line(&format!("describir s > {}", file("s.txt")));
+    assert_eq!(program.take_output(), "");
+    let described = std::fs::read_to_string(file("s.txt")).unwrap();
+    assert!(described.starts_with("Símbolo: s\n"));
+
+    // > replaces the file and >> appends to it
+    program.run_line(&format!("listar >> {}", file("s.txt")));
+    let appended = std::fs::read_to_string(file("s.txt")).unwrap();
+    assert!(appended.starts_with(&described) && appended.len() > described.len());
+    program.run_line(&format!("exportar json   >   {}  ", file("s.txt")));
+    assert!(std::fs::read_to_string(file("s.txt")).unwrap().starts_with("[{\"name\":\"c\""));
+
+    // scripts write relative to themselves, and the lines after a redirected one are shown again
+    std::fs::write(dir.join("script.tm"), "describir c > c.txt\nlistar\n").unwrap();
+    program.run_line(&format!("incluir {}", file("script.tm")));
+    assert!(program.take_output().contains("│ s      │ struct  │"));
+    assert!(std::fs::read_to_string(file("c.txt")).unwrap().starts_with("Símbolo: c\n"));
+
+    // > compares in expressions
+    program.run_line("calcular 3 > 2");
+    assert_eq!(program.take_output(), "verdadero\n");
+
+    // errors are not redirected
+    program.run_line(&format!("describir nada > {}", file("nada.txt")));
+    assert!(program.take_output().starts_with("[TYPE ERROR]:"));
+    assert_eq!(std::fs::read_to_string(file("nada.txt")).unwrap(), "");
+    match program.run_command(&format!("describir s > {}", dir.join("no").join("existe.txt").display())) {
+        Err(CommandError::Program(ProgramError::OutputWrite(..))) => {},
+        other => panic!("unexpected result {:?}", other)
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_watch_changes() {
+    use crate::driver::Program;
+    use crate::cli::{parse, Command};
+    use std::path::PathBuf;
+
+    let parse = |args : &str| parse(args.split_whitespace().map(|s| s.to_string()));
+    assert_eq!(parse("watch defs.tm"), Ok(Command::Watch(PathBuf::from("defs.tm"))));
+    assert!(parse("watch").is_err());
+    assert!(parse("watch a.tm b.tm").is_err());
+
+    let run = |script : &str| {
+        let mut program = Program::new();
+        program.capture_output();
+        for line in script.lines() {
+            program.run_command(line).unwrap();
+        }
+        program
+    };
+    let before = run("atomico char 1 1\natomico int 4 4\nstruct s char int char\nstruct viejo int");
+    let after = run("atomico char 1 1\natomico int 4 4\nstruct s int char char\nstruct nuevo int");
+
+    assert_eq!(after.changes_since(&before), vec![
+        "+ nuevo",
+        "~ s: tamaño 12 -> 8, relleno 6 -> 2",
+        "    m2: desplazamiento 8 -> 5",
+        "- viejo"
+    ]);
+    assert!(after.changes_since(&after).is_empty());
+}
+
+#[test]
+fn test_assert() {
+    use crate::driver::{CommandError, Program, ProgramError};
+    use crate::expr::Cmp;
+
+    let mut program = Program::new();
+    program.capture_output();
+    for line in ["atomico char 1 1", "atomico int 4 4", "struct header char int char"] {
+        program.run_command(line).unwrap();
+    }
+
+    program.run_line("afirmar tamaño(header, empaquetado) == 6");
+    program.run_line("afirmar tamaño(header) > 8");
+    assert_eq!(program.take_output(), "Se cumple 'tamaño(header, empaquetado) == 6'\nSe cumple 'tamaño(header) > 8'\n");
+
+    let failed = program.run_command("afirmar tamaño(header, empaquetado) == 12");
+    assert_eq!(failed, Err(CommandError::Program(ProgramError::AssertionFailed("tamaño(header, empaquetado) == 12".to_string(), Some((6, Cmp::Eq, 12))))));
+    assert_eq!(failed.unwrap_err().display(), "[ERROR]: No se cumple 'tamaño(header, empaquetado) == 12': 6 == 12 es falso");
+    assert_eq!(program.run_command("afirmar 4 - 4"), Err(CommandError::Program(ProgramError::AssertionFailed("4 - 4".to_string(), None))));
+    assert!(program.run_command("afirmar tamaño(nada) == 1").is_err());
+    assert_eq!(program.run_command("afirmar"), Err(CommandError::Program(ProgramError::NotEnoughArgs)));
+
+    // failed assertions fail the script they're in
+    let dir = std::env::temp_dir().join(format!("typemanager_assert_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("regresion.tm"), "afirmar tamaño(header) == 12\nafirmar offset(header, m2) == 4\n").unwrap();
+    let summary = program.run_file(&dir.join("regresion.tm")).unwrap();
+    assert_eq!(summary.errors.len(), 1);
+    assert!(summary.errors[0].contains("regresion.tm:2: [ERROR]: No se cumple 'offset(header, m2) == 4': 8 == 4 es falso"), "{}", summary.errors[0]);
+}
+
+#[test]
+fn test_shadowing() {
+    use crate::driver::{CommandError, Program};
+    use crate::config::Config;
+
+    let size = |program : &Program, name : &str| program.manager().get(&name.to_string()).map(|t| Strategy::C.type_size(t, program.manager()));
+
+    let mut program = Program::with_config(Config { autosave: false, shadowing: true, ..Config::default() });
+    program.capture_output();
+    program.run_script("atomico char 1 1\natomico int 4 4\nstruct s char int\nstruct t s char\n", "test");
+    assert_eq!(program.take_output(), "");
+
+    // the old definition keeps its users, the new one takes the name
+    program.run_line("struct s int");
+    assert_eq!(program.take_output(), "[AVISO]: Ya había un tipo con este nombre, la definición anterior sigue como 's@anterior'\n");
+    assert_eq!(size(&program, "s"), Some(4));
+    assert_eq!(size(&program, "s@anterior"), Some(8));
+    assert_eq!(size(&program, "t"), Some(12));
+    assert_eq!(program.manager().dependents(&"s@anterior".to_string()), vec!["t".to_string()]);
+    assert!(program.manager().dependents(&"s".to_string()).is_empty());
+
+    // shadowing again moves the older one further, a new definition may use the one it shadows
+    program.run_line("struct s s char");
+    assert_eq!(size(&program, "s"), Some(8));
+    assert_eq!(size(&program, "s@anterior"), Some(4));
+    assert_eq!(size(&program, "s@anterior@anterior"), Some(8));
+    assert_eq!(program.manager().dependents(&"s@anterior@anterior".to_string()), vec!["t".to_string()]);
+    assert_eq!(program.manager().dependents(&"s@anterior".to_string()), vec!["s".to_string()]);
+
+    // a rejected type shadows nothing
+    assert!(program.run_command("struct s nada").is_err());
+    assert_eq!(size(&program, "s"), Some(8));
+    assert!(program.manager().get(&"s@anterior@anterior@anterior".to_string()).is_none());
+
+    // in strict mode the warning is an error, and without shadowing it's a redefinition
+    let mut strict = Program::with_config(Config { autosave: false, shadowing: true, strict: true, ..Config::default() });
+    strict.run_script("atomico int 4 4\n", "test");
+    assert_eq!(strict.run_command("atomico int 8 8"), Err(CommandError::Type(TypeError::Strict(Diagnostic::Shadowed("int@anterior".to_string())))));
+    let mut plain = Program::new();
+    plain.run_script("atomico int 4 4\n", "test");
+    let redefinition = CommandError::Type(TypeError::TypeRedefinition);
+    assert_eq!(plain.run_command("atomico int 8 8"), Err(CommandError::Defined { name: "int".to_string(), origin: "test:1".to_string(), error: Box::new(redefinition) }));
+}
+
+#[test]
+fn test_clean() {
+    use crate::driver::{CommandError, Program};
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("atomico char 1 1\natomico int 4 4\natomico long 8 8\natomico sin_uso 2 2\nstruct a char int\nstruct b long\nstruct c a\npuntero pb b\nstruct d pb\n", "test");
+    let names = |program : &Program| {
+        let mut names : Vec<String> = program.manager().names().cloned().collect();
+        names.sort();
+        names
+    };
+
+    // with no roots, only what no struct or union uses goes
+    program.run_line("limpiar");
+    assert_eq!(program.take_output(), "Eliminados 1 tipos: sin_uso\n");
+    program.run_line("limpiar");
+    assert_eq!(program.take_output(), "No hay tipos que eliminar\n");
+
+    // pointed types are kept along with the pointer
+    program.run_line("limpiar c d");
+    assert_eq!(program.take_output(), "No hay tipos que eliminar\n");
+    assert_eq!(names(&program), vec!["a", "b", "c", "char", "d", "int", "long", "pb"]);
+
+    assert_eq!(program.run_command("limpiar c nada"), Err(CommandError::Type(TypeError::TypeDoesNotExist("nada".to_string()))));
+    program.run_line("limpiar c");
+    assert_eq!(program.take_output(), "Eliminados 4 tipos: b, d, long, pb\n");
+    assert_eq!(names(&program), vec!["a", "c", "char", "int"]);
+    assert!(program.manager().dependents(&"long".to_string()).is_empty());
+
+    // removed types can be defined again
+    program.run_line("atomico long 8 8");
+    assert_eq!(program.take_output(), "");
+}
+
+#[test]
+fn test_strict_batches() {
+    use crate::driver::{CommandError, ExportFormat, Program, ProgramError};
+    use crate::config::Config;
+    use std::time::Duration;
+
+    // the first failing line stops the script
+    let dir = std::env::temp_dir().join(format!("typemanager_strict_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("raro.tm"), "atomico char 1 1\natomico raro 3 3\natomico int 4 4\nstruct s char int\n").unwrap();
+    let mut lenient = Program::new();
+    let summary = lenient.run_file(&dir.join("raro.tm")).unwrap();
+    assert_eq!((summary.defined, summary.errors.len()), (4, 0));
+
+    let mut strict = Program::with_config(Config { autosave: false, strict: true, ..Config::default() });
+    let summary = strict.run_file(&dir.join("raro.tm")).unwrap();
+    assert_eq!((summary.defined, summary.errors.len()), (1, 1));
+    assert!(summary.errors[0].contains("raro.tm:2: "), "{}", summary.errors[0]);
+    assert!(strict.manager().get(&"s".to_string()).is_none());
+
+    // warnings left in exports are errors
+    strict.run_script("atomico int 4 4\nstruct p @packed char int\n", "test");
+    assert!(lenient.checked_export(ExportFormat::Fbs).is_ok());
+    assert_eq!(strict.checked_export(ExportFormat::Fbs), Err(ProgramError::Strict(vec!["'p' es @packed, FlatBuffers siempre alinea los campos".to_string()])));
+    assert_eq!(
+        strict.run_command("exportar fbs"),
+        Err(CommandError::Program(ProgramError::Strict(vec!["'p' es @packed, FlatBuffers siempre alinea los campos".to_string()])))
+    );
+    assert!(strict.checked_export(ExportFormat::Json).is_ok());
+
+    // and so is an optimizer running out of time
+    let limits = Limits { optimizer: Some(Duration::from_millis(1)), ..Limits::default() };
+    let mut strict = Program::with_config(Config { autosave: false, strict: true, limits, ..Config::default() });
+    strict.capture_output();
+    strict.run_script("atomico c 1 1\natomico i 4 4\nstruct s c i c i c i c i c i\n", "limites");
+    match strict.run_command("describir s") {
+        Err(CommandError::Program(ProgramError::Strict(warnings))) => assert!(warnings[0].starts_with("El optimizador llegó a su límite de 1 ms"), "{:?}", warnings),
+        other => panic!("{:?}", other)
+    }
+}
+
+#[test]
+fn test_deferred_resolution() {
+    use crate::driver::{CommandError, Program};
+    use crate::config::Config;
+
+    let mut program = Program::with_config(Config { autosave: false, deferred: true, ..Config::default() });
+    program.capture_output();
+
+    // types used before they're defined wait for them
+    program.run_line("struct nodo cabeza:punto siguiente:pnodo");
+    assert_eq!(program.take_output(), "[INFO]: Usa tipos que aún no existen (punto, pnodo), queda pendiente hasta que se definan\n");
+    program.run_script("struct punto x:int y:int\npuntero pnodo nodo\nunion roto int falta\n", "test");
+    assert!(program.manager().get(&"nodo".to_string()).is_none());
+    assert_eq!(program.manager().pending(), vec![
+        ("nodo".to_string(), vec!["punto".to_string()]),
+        ("punto".to_string(), vec!["int".to_string()]),
+        ("roto".to_string(), vec!["int".to_string(), "falta".to_string()])
+    ]);
+    assert_eq!(program.run_command("struct nodo int"), Err(CommandError::Type(TypeError::TypeRedefinition)));
+
+    // resolving tells what's still missing
+    assert_eq!(program.run_command("resolver"), Err(CommandError::Type(TypeError::Unresolved(program.manager().pending()))));
+    program.run_line("atomico int 4 4");
+    program.take_output();
+
+    // a query resolves whatever it can first
+    program.run_line("calcular tamaño(nodo)");
+    assert_eq!(program.take_output(), "16\n");
+    assert_eq!(program.manager().dependents(&"punto".to_string()), vec!["nodo".to_string()]);
+    assert_eq!(
+        program.run_command("describir roto"),
+        Err(CommandError::Type(TypeError::Unresolved(vec![("roto".to_string(), vec!["falta".to_string()])])))
+    );
+    assert_eq!(
+        CommandError::Type(TypeError::Unresolved(program.manager().pending())).display(),
+        "[TYPE ERROR]: Quedan tipos sin resolver: 'roto' necesita falta"
+    );
+    assert!(program.session_script().ends_with("union roto int falta\n"));
+
+    // types turning out invalid are dropped
+    program.run_script("struct doble x:int x:int otro\natomico otro 1 1\natomico falta 2 2\n", "test");
+    program.take_output();
+    program.run_line("resolver");
+    assert_eq!(program.take_output(), "[AVISO]: 'doble' estaba pendiente y no se pudo definir: El campo 'x' está repetido\nResueltos 1 tipos pendientes: roto\n");
+    assert!(!program.manager().has_pending());
+
+    // without deferring, undefined members are an error
+    let mut program = Program::new();
+    assert_eq!(program.run_command("struct nodo punto"), Err(CommandError::Type(TypeError::TypeDoesNotExist("punto".to_string()))));
+}
+
+#[test]
+fn test_provenance() {
+    use crate::config::Config;
+    use crate::driver::Program;
+    use crate::import;
+
+    let mut program = Program::new();
+    program.capture_output();
+    assert!(program.run_script("atomico int 4 4\n\nstruct s int\n", "tipos.tm").errors.is_empty());
+    program.run_line("atomico char 1 1");
+    program.take_output();
+
+    // describir tells where types come from, nothing for the ones written at the prompt
+    program.run_line("describir s");
+    assert!(program.take_output().ends_with("📍 Definido en tipos.tm:3\n"));
+    program.run_line("describir char");
+    assert!(!program.take_output().contains("📍"));
+    program.run_line("describir s --json");
+    assert!(program.take_output().contains("\"origin\":\"tipos.tm:3\""));
+
+    // errors about a type point at its definition
+    let error = program.run_command("atomico int 2 2").unwrap_err();
+    assert!(error.display().ends_with("('int' se definió en tipos.tm:1)"), "{}", error.display());
+    assert!(!program.run_command("atomico char 2 2").unwrap_err().display().contains("se definió"));
+
+    // sessions keep origins, and a shadowed type keeps its own
+    let script = program.session_script();
+    assert!(script.contains("# origen: tipos.tm:3\nstruct s int\n"));
+    assert!(script.contains("\natomico char 1 1\n") && !script.contains("# origen: interactivo"));
+    let mut restored = Program::with_config(Config { autosave: false, shadowing: true, ..Config::default() });
+    restored.capture_output();
+    assert!(restored.run_script(&script, "sesion.tm").errors.is_empty());
+    assert_eq!(restored.manager().origin(&"s".to_string()), Some("tipos.tm:3"));
+    assert_eq!(restored.manager().origin(&"char".to_string()), Some("sesion.tm:6"));
+    restored.run_line("atomico int 8 8");
+    assert_eq!(restored.manager().origin(&"int@anterior".to_string()), Some("tipos.tm:1"));
+    assert_eq!(restored.manager().origin(&"int".to_string()), None);
+
+    // binary sessions of the first version have no origins, types take the file's
+    let path = std::env::temp_dir().join(format!("typemanager_origen_{}.bin", std::process::id()));
+    std::fs::write(&path, b"TMSB\x01\x00\x01\x03int\x00\x00\x04\x04\x00").unwrap();
+    let mut old = Program::new();
+    old.capture_output();
+    old.run_line(&format!("cargar {}", path.display()));
+    assert_eq!(old.manager().origin(&"int".to_string()), Some(path.display().to_string().as_str()));
+    std::fs::remove_file(&path).unwrap();
+
+    // imports tell the line of every declaration, and the file Clang read
+    let ast = r#"{ "id": "0x1", "kind": "TranslationUnitDecl", "inner": [
+    { "id": "0x2", "kind": "RecordDecl", "loc": { "offset": 8, "file": "cabecera.h", "line": 3, "col": 8 },
+      "range": { "begin": { "offset": 1, "col": 1 }, "end": { "offset": 30, "line": 6, "col": 1 } },
+      "name": "par", "tagUsed": "struct", "completeDefinition": true, "inner": [
+      { "id": "0x3", "kind": "FieldDecl", "loc": { "offset": 20, "line": 4, "col": 9 }, "name": "a", "type": { "qualType": "int" } },
+      { "id": "0x4", "kind": "FieldDecl", "loc": { "offset": 28, "line": 5, "col": 9 }, "name": "b", "type": { "qualType": "char" } } ] },
+    { "id": "0x5", "kind": "EnumDecl", "loc": { "offset": 40, "line": 8, "col": 6 }, "name": "color", "inner": [
+      { "id": "0x6", "kind": "EnumConstantDecl", "name": "ROJO", "type": { "qualType": "int" } } ] }
+  ] }"#;
+    let imported = import::clang_ast_declared(ast).unwrap();
+    assert_eq!(imported.declared.get("par"), Some(&(Some("cabecera.h".to_string()), 3)));
+    assert_eq!(imported.declared.get("color"), Some(&(Some("cabecera.h".to_string()), 8)));
+    assert_eq!(imported.declared.get("int"), None);
+
+    let path = std::env::temp_dir().join(format!("typemanager_origen_{}.json", std::process::id()));
+    std::fs::write(&path, ast).unwrap();
+    let mut clang = Program::new();
+    clang.capture_output();
+    clang.run_line(&format!("importar clang-ast {}", path.display()));
+    clang.take_output();
+    clang.run_line("describir par");
+    assert!(clang.take_output().contains("📍 Definido en cabecera.h:3\n"));
+    assert_eq!(clang.manager().origin(&"int".to_string()), Some(path.display().to_string().as_str()));
+    std::fs::remove_file(&path).unwrap();
+
+    let imported = import::proto("syntax = \"proto3\";\n\nmessage A {\n  int32 x = 1;\n}\nenum E {\n  CERO = 0;\n}\n", &[]).unwrap();
+    assert_eq!(imported.declared.get("A"), Some(&(None, 3)));
+    assert_eq!(imported.declared.get("E"), Some(&(None, 6)));
+}
+
+#[test]
+fn test_metrics() {
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use crate::driver::Program;
+    use crate::metrics;
+
+    // counters are shared by the whole process, so only growth is checked
+    metrics::enable();
+    let sample = |text: &str, name: &str| -> u64 {
+        text.lines().find_map(|l| l.strip_prefix(name).and_then(|v| v.trim().parse().ok())).unwrap_or(0)
+    };
+    let before = metrics::render();
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_line("atomico int 4 4");
+    program.run_line("atomico char 1 1");
+    program.run_line("struct s int char int");
+    program.run_line("describir s");
+    program.run_line("describir nada");
+
+    let after = metrics::render();
+    assert!(sample(&after, "typemanager_commands_total{result=\"ok\"}") >= sample(&before, "typemanager_commands_total{result=\"ok\"}") + 4);
+    assert!(sample(&after, "typemanager_commands_total{result=\"error\"}") > sample(&before, "typemanager_commands_total{result=\"error\"}"));
+    assert!(sample(&after, "typemanager_types_registered_total") >= sample(&before, "typemanager_types_registered_total") + 3);
+    assert!(sample(&after, "typemanager_optimizer_seconds_count") > sample(&before, "typemanager_optimizer_seconds_count"));
+    assert!(sample(&after, "typemanager_request_seconds_bucket{le=\"+Inf\"}") >= sample(&before, "typemanager_request_seconds_count") + 5);
+    assert!(after.contains("# TYPE typemanager_layout_cache_hit_ratio gauge\n"));
+
+    // served over HTTP, only on /metrics
+    let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        for _ in 0..2 {
+            let (mut stream, _) = listener.accept().unwrap();
+            metrics::respond(&mut stream).unwrap();
+        }
+    });
+    let get = |path: &str| {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+    let response = get("/metrics");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.contains("\r\n\r\n# HELP typemanager_commands_total "));
+    assert!(get("/").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    server.join().unwrap();
+
+    assert_eq!(crate::cli::parse(vec!["serve".to_string(), "--metrics-port".to_string(), "9100".to_string()]), Ok(crate::cli::Command::Serve(crate::cli::DEFAULT_PORT, Some(9100))));
+    assert!(crate::cli::parse(vec!["connect".to_string(), "--metrics-port=9100".to_string()]).is_err());
+}
+
+#[test]
+fn test_stdio_json() {
+    use crate::driver::Program;
+    use crate::json;
+
+    let mut program = Program::new();
+    let input = [
+        r#"{"id": 1, "command": "atomico int 4 4"}"#,
+        r#"{"id": "dos", "command": "struct s int int"}"#,
+        "",
+        r#"{"command": "describir s"}"#,
+        r#"{"id": 4, "command": "describir nada"}"#,
+        r#"{"id": 5, "command": "struct t char", "allow": ["ninguna"]}"#,
+        r#"{"id": 6}"#,
+        "no es json",
+        r#"{"id": 7, "command": "salir"}"#,
+        r#"{"id": 8, "command": "atomico char 1 1"}"#
+    ].join("\n");
+    let mut output = vec![];
+    program.serve_json(std::io::Cursor::new(input), &mut output).unwrap();
+
+    // a response per request, in a line of its own, until salir
+    let output = String::from_utf8(output).unwrap();
+    let responses : Vec<json::Value> = output.lines().map(|l| json::parse(l).unwrap()).collect();
+    assert_eq!(responses.len(), 8);
+    assert_eq!(output.lines().next(), Some(r#"{"id":1,"ok":true,"output":[],"error":null}"#));
+    assert_eq!(responses[1].get("id"), Some(&json::Value::str("dos")));
+    assert_eq!(responses[2].get("id"), Some(&json::Value::Null));
+    let lines = responses[2].get("output").and_then(|o| o.as_array()).unwrap();
+    assert!(lines.iter().any(|l| l.as_str().unwrap().ends_with("Tamaño: 8")), "{}", output);
+
+    let error = |i : usize, field : &str| responses[i].get("error").and_then(|e| e.get(field)).and_then(|v| v.as_str()).map(String::from);
+    assert!(!responses[3].get("ok").unwrap().is_true());
+    assert_eq!(error(3, "kind").as_deref(), Some("tipo"));
+    assert!(error(3, "message").unwrap().contains("nada"));
+    assert_eq!(error(4, "kind").as_deref(), Some("solicitud"));
+    assert_eq!(responses[4].get("id"), Some(&json::Value::Int(5)));
+    assert_eq!(error(5, "kind").as_deref(), Some("solicitud"));
+    assert_eq!(error(6, "kind").as_deref(), Some("solicitud"));
+    assert_eq!(responses[6].get("id"), Some(&json::Value::Null));
+    assert!(responses[7].get("ok").unwrap().is_true());
+
+    // rejected requests run nothing, and nothing runs after salir
+    assert!(program.manager().get(&"t".to_string()).is_none());
+    assert!(program.manager().get(&"char".to_string()).is_none());
+    assert!(!program.should_run());
+}
+
+#[test]
+fn test_image_export() {
+    use crate::driver::Program;
+    use crate::image;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("atomico int 4 4\natomico char 1 1\nstruct s tag:char valor:int c:char\n", "test");
+    program.take_output();
+
+    // every byte belongs to a member or to padding, once
+    let diagram = program.manager().diagram(&"s".to_string(), Strategy::C).unwrap();
+    let spans : Vec<(usize, usize, Option<usize>, &str)> = diagram.spans.iter().map(|s| (s.offset, s.size, s.member, s.label.as_str())).collect();
+    assert_eq!(spans, vec![(0, 1, Some(0), "tag"), (1, 3, None, ""), (4, 4, Some(1), "valor"), (8, 1, Some(2), "c"), (9, 3, None, "")]);
+    assert_eq!(diagram.rows(), 2);
+    let packed = program.manager().diagram(&"s".to_string(), Strategy::Packed).unwrap();
+    assert!(packed.spans.iter().all(|s| s.member.is_some()));
+    assert_eq!(program.manager().diagram(&"int".to_string(), Strategy::C), Err(TypeError::NotAStruct("int".to_string())));
+
+    // padding is hatched, members are flat
+    let canvas = image::draw(&diagram);
+    let row : Vec<[u8; 3]> = (0..canvas.width()).filter_map(|x| canvas.pixel(x, 50)).collect();
+    let colors = |from : usize, to : usize| row[from..to].iter().collect::<std::collections::HashSet<_>>().len();
+    assert_eq!(colors(50, 80), 1);
+    assert_eq!(colors(100, 170), 2);
+
+    // a valid PNG: signature, size in the header and a CRC closing every chunk
+    let path = std::env::temp_dir().join(format!("typemanager_image_{}.png", std::process::id()));
+    program.run_line(&format!("exportar imagen s {} --estrategia c", path.display()));
+    assert!(program.take_output().contains(&format!("{}x{} píxeles", canvas.width(), canvas.height())));
+    let png = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(png, canvas.png());
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    assert_eq!(&png[16..24], [(canvas.width() as u32).to_be_bytes(), (canvas.height() as u32).to_be_bytes()].concat().as_slice());
+    let mut i = 8;
+    while i < png.len() {
+        let length = u32::from_be_bytes([png[i], png[i + 1], png[i + 2], png[i + 3]]) as usize;
+        let crc = &png[i + 8 + length..i + 12 + length];
+        assert_eq!(crc, image::crc32(&png[i + 4..i + 8 + length]).to_be_bytes());
+        i += 12 + length;
+    }
+    assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+
+    program.run_line("exportar imagen s");
+    assert!(program.take_output().contains("[ERROR]"));
+    program.run_line("exportar imagen s s.png --estrategia rara");
+    assert!(program.take_output().contains("rara"));
+}
+
+#[test]
+fn test_documentation_site() {
+    use crate::codegen;
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("atomico int 4 4\natomico char 1 1\npuntero p registro\nstruct registro tag:char valor:int c:char\nunion u int registro\n", "test");
+    program.take_output();
+
+    let files = codegen::site(program.manager(), &program.manager().definition_order());
+    let file = |name : &str| files.iter().find(|(f, _)| f == name).map(|(_, c)| String::from_utf8_lossy(c).to_string());
+    assert_eq!(files[0].0, codegen::INDEX);
+
+    // the index links every type
+    let index = file("index.html").unwrap();
+    for name in ["int", "char", "p", "registro", "u"] {
+        assert!(index.contains(&format!("<a href=\"tipo-{}.html\">{}</a>", name, name)), "{}", index);
+    }
+
+    // type pages have members, diagrams and links both ways, each type once
+    let page = file("tipo-registro.html").unwrap();
+    assert!(page.contains("<td>valor</td><td><a href=\"tipo-int.html\">int</a></td><td class=\"n\">4</td>"));
+    assert!(page.contains("<img src=\"tipo-registro-c.png\""));
+    assert!(page.contains("<h2>Usa</h2>\n<p><a href=\"tipo-char.html\">char</a>, <a href=\"tipo-int.html\">int</a></p>"));
+    assert!(page.contains("<h2>Usado por</h2>\n<p><a href=\"tipo-p.html\">p</a>, <a href=\"tipo-u.html\">u</a></p>"));
+    assert!(files.iter().any(|(f, c)| f == "tipo-registro-optimized.png" && c.starts_with(b"\x89PNG")));
+    assert!(file("tipo-int-c.png").is_none());
+
+    // types are a level above what they're built on, pointers don't count
+    let graph = file("grafo.html").unwrap();
+    assert!(graph.find("Nivel 1").unwrap() < graph.find("<a href=\"tipo-registro.html\">registro</a> →").unwrap());
+    assert!(graph.find("Nivel 2").unwrap() < graph.find("<a href=\"tipo-u.html\">u</a> →").unwrap());
+    assert!(file("grafo.dot").unwrap().contains("\"p\" -> \"registro\" [style=dashed];"));
+
+    // written to a directory, names are escaped
+    let dir = std::env::temp_dir().join(format!("typemanager_docs_{}", std::process::id()));
+    program.run_line("atomico <raro> 2 2");
+    program.run_line(&format!("documentar {}", dir.display()));
+    assert!(program.take_output().contains(&format!("Documentados 6 tipos en {} archivos", files.len() + 1)));
+    assert!(std::fs::read_to_string(dir.join("index.html")).unwrap().contains("<a href=\"tipo--3craro-3e.html\">&lt;raro&gt;</a>"));
+    assert!(dir.join("tipo-registro-c.png").is_file());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_pahole_format() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("atomico int 4 4\natomico char 1 1\natomico long 8 8\nstruct registro tag:char valor:int c:char\nunion u int registro\n", "test");
+    program.take_output();
+
+    program.run_line("describir registro --formato pahole");
+    assert_eq!(program.take_output(), "struct registro {
+\tchar                       tag;                  /*     0     1 */
+
+\t/* XXX 3 bytes hole, try to pack */
+
+\tint                        valor;                /*     4     4 */
+\tchar                       c;                    /*     8     1 */
+
+\t/* size: 12, cachelines: 1, members: 3 */
+\t/* sum members: 6, holes: 1, sum holes: 3 */
+\t/* padding: 3 */
+\t/* last cacheline: 12 bytes */
+};
+
+");
+
+    // cache line boundaries, bit holes and unions
+    program.run_script("struct grande a:char b:long c:long d:long e:long f:long g:long h:long i:int j:long\nstruct bits a:int @bits=3 b:int @bits=2 c:char d:int\n", "test");
+    program.take_output();
+    let text = program.manager().pahole(&"grande".to_string(), BitfieldPolicy::Gcc, 64).unwrap();
+    assert!(text.contains("/*    56     8 */\n\t/* --- cacheline 1 boundary (64 bytes) --- */\n\tint                        i;"));
+    assert!(text.contains("/* size: 80, cachelines: 2, members: 10 */\n\t/* sum members: 69, holes: 2, sum holes: 11 */\n\t/* last cacheline: 16 bytes */\n};"));
+    let text = program.manager().pahole(&"bits".to_string(), BitfieldPolicy::Gcc, 64).unwrap();
+    assert!(text.contains("\tint                        b:2;                  /*     0: 3    4 */\n\n\t/* XXX 3 bits hole, try to pack */\n"));
+    assert!(text.contains("/* bit holes: 1, sum bit holes: 3 bits */"));
+    let text = program.manager().pahole(&"u".to_string(), BitfieldPolicy::Gcc, 64).unwrap();
+    assert!(text.starts_with("union u {\n\tint                        m0;                   /*           4 */\n\tstruct registro            m1;"));
+
+    // every struct and union, and nothing else
+    program.run_line("describir todo --formato pahole");
+    let all = program.take_output();
+    assert_eq!(all.matches("};").count(), 4);
+    assert!(!all.contains("long {"));
+    program.run_line("describir int --formato pahole");
+    assert!(program.take_output().contains("no es un struct"));
+    program.run_line("describir registro --formato xml");
+    assert!(program.take_output().contains("xml"));
+}
+
+#[test]
+fn test_checked_layouts() {
+    use crate::driver::Program;
+    use crate::type_system::{Strategy, TypeError};
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("atomico int 4 4\natomico char 1 1\nstruct registro tag:char valor:int c:char\nunion u int registro\n", "test");
+    program.take_output();
+
+    let manager = program.manager();
+    let registro = "registro".to_string();
+    assert_eq!(manager.size(&registro, Strategy::C), Ok(12));
+    assert_eq!(manager.size(&registro, Strategy::Optimized), Ok(6));
+    assert_eq!(manager.align(&registro, Strategy::C), Ok(4));
+    assert_eq!(manager.loss(&registro, Strategy::C), Ok(6));
+    assert_eq!(manager.loss(&registro, Strategy::Packed), Ok(0));
+    assert_eq!(manager.loss(&"u".to_string(), Strategy::C), Ok(6));
+    assert!(matches!(manager.checked(&"u".to_string()), Ok(Type::Union(_))));
+
+    let missing = "nada".to_string();
+    assert_eq!(manager.size(&missing, Strategy::C), Err(TypeError::TypeDoesNotExist(missing.clone())));
+    assert_eq!(manager.align(&missing, Strategy::C), Err(TypeError::TypeDoesNotExist(missing.clone())));
+    assert_eq!(manager.loss(&missing, Strategy::C), Err(TypeError::TypeDoesNotExist(missing.clone())));
+    assert!(manager.report(&missing).is_err());
+
+    // unchecked queries on missing types answer without panicking
+    assert_eq!(manager.size_of(&missing, Strategy::C), 0);
+    assert_eq!(manager.align_of(&missing, Strategy::C), 1);
+
+    program.run_line("describir nada");
+    assert!(program.take_output().contains("nada"));
+}
+
+#[test]
+fn test_empty_layouts() {
+    use crate::type_system::{Atomic, Enum, EnumRepr, Strategy, Struct, Type, TypeError, TypeManager, Union};
+
+    // types that can't be stored, laid out as if some mutation left them behind
+    let mut manager = TypeManager::new();
+    manager.add("int".to_string(), Type::Atomic(Atomic::new(4, 4))).unwrap();
+    let zero = Type::Atomic(Atomic::new(4, 0));
+    let empty_struct = Type::Struct(Struct::new(vec![]));
+    let empty_union = Type::Union(Union::new(vec![]));
+    let empty_enum = Type::Enum(Enum::new(vec![], EnumRepr::Int));
+    for t in [&zero, &empty_struct, &empty_union, &empty_enum] {
+        for strategy in Strategy::ALL {
+            strategy.type_size(t, &manager);
+            strategy.type_align(t, &manager);
+        }
+        t.report(&manager).display();
+    }
+    assert_eq!(Strategy::Optimized.type_size(&empty_struct, &manager), 0);
+    assert_eq!(Strategy::C.type_align(&empty_struct, &manager), 1);
+    assert_eq!(Strategy::Unpacked.type_align(&empty_union, &manager), 1);
+    assert_eq!(Strategy::C.type_size(&empty_union, &manager), 0);
+    assert_eq!(Strategy::C.type_align(&zero, &manager), 1);
+
+    assert_eq!(manager.check_layout(&"vacio".to_string(), &empty_struct), Err(TypeError::NoMembers("vacio".to_string())));
+    assert_eq!(manager.check_layout(&"vacia".to_string(), &empty_union), Err(TypeError::NoMembers("vacia".to_string())));
+    assert_eq!(manager.check_layout(&"cero".to_string(), &zero), Err(TypeError::ZeroAlign("cero".to_string())));
+    assert_eq!(manager.check_layout(&"s".to_string(), &Type::Struct(Struct::new(vec!["int".to_string()]))), Ok(()));
+}
+
+#[test]
+fn test_definition_order_is_kept() {
+    use crate::driver::{ExportFormat, Program};
+    use crate::type_system::{Atomic, Type, TypeTable};
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("atomico zeta 4 4\natomico alfa 1 1\nstruct m zeta alfa\natomico beta 2 2\nstruct sobra beta\n", "test");
+    let names : Vec<&String> = program.manager().names().collect();
+    assert_eq!(names, vec!["zeta", "alfa", "m", "beta", "sobra"]);
+
+    // removed types leave the rest in place
+    program.run_line("limpiar m");
+    let names : Vec<&String> = program.manager().names().collect();
+    assert_eq!(names, vec!["zeta", "alfa", "m"]);
+    let snapshot = program.manager().snapshot();
+    assert!(snapshot.names().eq(program.manager().names()));
+
+    // listings, exports and sessions show types as defined, only moving dependencies ahead
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("atomico int 4 4\nstruct b int\natomico char 1 1\nstruct a char\nstruct c a b\n", "test");
+    program.take_output();
+    program.run_line("listar");
+    let output = program.take_output();
+    let names : Vec<&str> = output.lines().skip(3).filter_map(|l| l.split('│').nth(1)).map(|c| c.trim()).collect();
+    assert_eq!(names, vec!["int", "b", "char", "a", "c"]);
+    let script = program.export(ExportFormat::Script);
+    let defined : Vec<&str> = script.lines().filter(|l| !l.starts_with('#') && !l.is_empty()).collect();
+    assert_eq!(defined, vec!["atomico int 4 4", "struct b int", "atomico char 1 1", "struct a char", "struct c a b"]);
+
+    // a redefined type keeps its place, and a type it now uses is moved ahead of it
+    program.run_line("redefinir struct b int char");
+    program.run_line("redefinir struct a int");
+    assert_eq!(program.manager().names().collect::<Vec<_>>(), vec!["int", "b", "char", "a", "c"]);
+    assert_eq!(program.manager().definition_order(), vec!["int", "char", "b", "a", "c"]);
+
+    let mut table = TypeTable::new();
+    table.insert("b".to_string(), Type::Atomic(Atomic::new(1, 1)));
+    table.insert("a".to_string(), Type::Atomic(Atomic::new(2, 2)));
+    table.insert("c".to_string(), Type::Atomic(Atomic::new(4, 4)));
+    assert!(table.insert("b".to_string(), Type::Atomic(Atomic::new(8, 8))).is_some());
+    assert_eq!(table.remove(&"a".to_string()), Some(Type::Atomic(Atomic::new(2, 2))));
+    let keys : Vec<&String> = table.keys().collect();
+    assert_eq!(keys, vec!["b", "c"]);
+    assert_eq!(table.get(&"c".to_string()), Some(&Type::Atomic(Atomic::new(4, 4))));
+    assert_eq!(table.get(&"b".to_string()), Some(&Type::Atomic(Atomic::new(8, 8))));
+    assert_eq!(table.len(), 2);
+}
+
+#[test]
+fn test_member_suggestions() {
+    use crate::driver::{CommandError, Program};
+    use crate::type_system::TypeError;
+    use crate::utils::levenshtein;
+
+    assert_eq!(levenshtein("intt", "int"), 1);
+    assert_eq!(levenshtein("strcut", "struct"), 2);
+    assert_eq!(levenshtein("", "abc"), 3);
+    assert_eq!(levenshtein("año", "ano"), 1);
+
+    let mut program = Program::new();
+    program.capture_output();
+    let summary = program.run_script("atomico int 4 4\natomico int8 1 1\nstruct punto x:int y:int\nunion pinto int punto\nstruct s a:Int b:pnto\n", "tipos.tm");
+    assert_eq!(summary.errors, vec![
+        "tipos.tm:5: [TYPE ERROR]: El símbolo 'Int' no existe, quizás quisiste decir 'int' (atómico), 'int8' (atómico) o 'pinto' (variante)".to_string()
+    ]);
+
+    assert_eq!(
+        program.run_command("union u pnto int"),
+        Err(CommandError::Type(TypeError::UnknownMember("pnto".to_string(), vec![("pinto".to_string(), "variante"), ("punto".to_string(), "struct"), ("int".to_string(), "atómico")])))
+    );
+    program.run_line("struct t a:int8 b:itn");
+    assert_eq!(program.take_output(), "[TYPE ERROR]: El símbolo 'itn' no existe, quizás quisiste decir 'int' (atómico) o 'int8' (atómico)\n");
+
+    // names too far from every stored type get no suggestions
+    assert_eq!(program.run_command("struct v a:double"), Err(CommandError::Type(TypeError::TypeDoesNotExist("double".to_string()))));
+}
+
+#[test]
+fn test_bool_kind() {
+    use crate::codegen::{arrow_schema, c_layout_check, capnp_schema, fbs_schema, sql_schema, Engine};
+    use crate::driver::Program;
+    use crate::type_system::{Endian, ValueKind};
+
+    assert_eq!(ValueKind::parse("booleano"), Some(ValueKind::Bool));
+    assert_eq!(ValueKind::parse("bool"), Some(ValueKind::Bool));
+    assert_eq!(ValueKind::Bool.name(), "booleano");
+    assert_eq!(ValueKind::Bool.decode(&[1], Endian::Little), Some("true".to_string()));
+    assert_eq!(ValueKind::Bool.decode(&[0], Endian::Little), Some("false".to_string()));
+    assert_eq!(ValueKind::Bool.decode(&[2], Endian::Little), Some("2 (no es un booleano)".to_string()));
+    assert_eq!(ValueKind::Signed.c_type(4), Some("int32_t"));
+    assert_eq!(ValueKind::Float.c_type(2), None);
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("atomico flag 1 1 booleano\natomico i32 4 4 entero\natomico raro 4 2 entero\nstruct s activo:flag n:i32 r:raro\n", "test");
+    assert_eq!(program.take_output(), "");
+    let names = program.manager().definition_order();
+
+    // every exporter picks a boolean type
+    let c = c_layout_check(program.manager(), &names);
+    assert!(c.contains("typedef _Bool flag;\n") && c.contains("typedef int32_t i32;\n"), "{}", c);
+    assert!(c.contains("typedef struct { _Alignas(2) unsigned char bytes[4]; } raro;\n"), "{}", c);
+    assert!(fbs_schema(program.manager(), &names).contains("activo: bool;"));
+    let capnp = capnp_schema(program.manager(), &names);
+    assert!(capnp.contains("activo @0 :Bool;") && capnp.contains("un bit en el bit 0 del byte 0"), "{}", capnp);
+    assert!(arrow_schema(program.manager(), &names, 8).contains("activo: bool"));
+    assert!(sql_schema(program.manager(), &names, Engine::Postgres).contains("\"activo\" BOOLEAN"));
+
+    // sessions keep the kind
+    let script = program.session_script();
+    assert!(script.contains("atomico flag 1 1 booleano\n"));
+    program.run_line("decodificar s 010000002a00000000000000");
+    assert!(program.take_output().contains("true"));
+
+    // booleans are flags by their kind, whatever their name
+    program.run_line("struct opciones a:flag n:i32 b:flag");
+    let report = program.manager().flag_packing(&"opciones".to_string()).unwrap();
+    assert_eq!(report.flags.iter().map(|f| f.index).collect::<Vec<_>>(), vec![0, 2]);
+    assert_eq!((report.size, report.packed_size), (12, 8));
+}
+
+#[test]
+fn test_attributes() {
+    use crate::driver::{ExportFormat, Program};
+    use crate::type_system::Strategy;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_line("atomico byte 1 1");
+    program.run_line("atomico int 4 4");
+    program.run_line("struct paquete @nota=\"Cabecera de red\" tipo:byte len:int");
+    program.take_output();
+
+    // attributes go on types, members of structs and types other than structs
+    program.run_line("atributo paquete.len @doc=\"Bytes tras la cabecera\"");
+    assert_eq!(program.take_output(), "Atributos de 'paquete.len' actualizados\n");
+    program.run_line("atributo byte @nota=Octeto");
+    let manager = program.manager();
+    assert_eq!(manager.note(&"paquete".to_string()), Some("Cabecera de red"));
+    assert_eq!(manager.note(&"byte".to_string()), Some("Octeto"));
+    assert_eq!(manager.note(&"int".to_string()), None);
+
+    // layout attributes added later change the layout
+    assert_eq!(program.manager().size(&"paquete".to_string(), Strategy::C), Ok(8));
+    program.run_line("atributo paquete @packed");
+    assert_eq!(program.manager().size(&"paquete".to_string(), Strategy::C), Ok(5));
+
+    assert!(program.run_command("atributo paquete.nada @caliente").unwrap_err().display().contains("El struct 'paquete' no tiene un campo 'nada'"));
+    assert!(program.run_command("atributo byte.x @caliente").unwrap_err().display().contains("El tipo 'byte' no es un struct"));
+    assert!(program.run_command("atributo nada @caliente").is_err());
+    assert!(program.run_command("atributo paquete").is_err());
+    assert!(program.run_command("atributo paquete nota").is_err());
+    program.take_output();
+
+    // reports and exports carry notes
+    program.run_line("describir paquete");
+    let report = program.take_output();
+    assert!(report.contains("📝 Cabecera de red\n") && report.contains("📝 len: Bytes tras la cabecera\n"), "{}", report);
+    let c = program.export(ExportFormat::C);
+    assert!(c.contains("/* Octeto */\ntypedef uint8_t byte;") || c.contains("/* Octeto */\ntypedef struct"), "{}", c);
+    assert!(c.contains("/* Cabecera de red */\n/* paquete.len: Bytes tras la cabecera */\ntypedef struct __attribute__((packed))"), "{}", c);
+    let rust = program.export(ExportFormat::Rust);
+    assert!(rust.contains("/// Cabecera de red\n/// `paquete` se declara con `#[repr(C, packed)]`\n#[test]\n#[allow(non_snake_case)]\nfn disposicion_paquete()"), "{}", rust);
+    assert!(program.export(ExportFormat::Fbs).contains("/// Cabecera de red\nstruct paquete {\n  tipo: ubyte;\n  /// Bytes tras la cabecera\n"));
+
+    // sessions keep them, quoted when they have spaces
+    let script = program.session_script();
+    assert!(script.contains("struct paquete @nota=\"Cabecera de red\" @packed tipo:byte len:int @doc=\"Bytes tras la cabecera\"\n"), "{}", script);
+    assert!(script.contains("atomico byte 1 1\natributo byte @nota=Octeto\n"), "{}", script);
+    let mut restored = Program::new();
+    restored.capture_output();
+    assert!(restored.run_script(&script, "sesion.tm").errors.is_empty());
+    assert_eq!(restored.manager().note(&"byte".to_string()), Some("Octeto"));
+    assert!(restored.session_script().contains("atomico byte 1 1\natributo byte @nota=Octeto\n"));
+    assert_eq!(restored.manager().note(&"paquete".to_string()), Some("Cabecera de red"));
+
+    let path = std::env::temp_dir().join(format!("typemanager_atributos_{}.bin", std::process::id()));
+    program.run_line(&format!("guardar {} --formato bin", path.display()));
+    let mut restored = Program::new();
+    restored.capture_output();
+    restored.run_line(&format!("cargar {}", path.display()));
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(restored.manager().note(&"byte".to_string()), Some("Octeto"));
+    assert_eq!(restored.manager().note(&"paquete".to_string()), Some("Cabecera de red"));
+    assert_eq!(restored.manager().size(&"paquete".to_string(), Strategy::C), Ok(5));
+}
+
+#[test]
+fn test_try_definition() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_line("atomico char 1 1");
+    program.run_line("atomico int 4 4");
+    program.run_line("struct par int char");
+    program.take_output();
+    let revision = program.revision();
+
+    // a new type is reported and not stored
+    program.run_line("probar struct nuevo char int char");
+    let output = program.take_output();
+    assert!(output.starts_with("Prueba de 'nuevo', no se guardó:\nSímbolo: nuevo\n"), "{}", output);
+    assert!(output.contains("      + Tamaño: 12\n") && output.ends_with("nuevo: [orden] reordenar los miembros ahorra 4 bytes (8 en vez de 12): int char char\n"), "{}", output);
+    program.run_line("describir nuevo");
+    assert_eq!(program.take_output(), "[TYPE ERROR]: El símbolo 'nuevo' no existe\n");
+    assert!(program.manager().get(&"nuevo".to_string()).is_none());
+    assert_eq!(program.revision(), revision);
+
+    // a stored name is tried in place of its definition, with no redefinition error
+    program.run_line("probar atomico int 8 8");
+    let output = program.take_output();
+    assert!(output.starts_with("Prueba de 'int' en lugar de la definición guardada, no se guardó:\n"), "{}", output);
+    assert!(output.contains("   * Representación: 8\n") && output.contains("Tipos afectados (tamaño en C):\n   * par: 8 -> 16 bytes\n"), "{}", output);
+    assert_eq!(program.manager().size(&"int".to_string(), crate::type_system::Strategy::C), Ok(4));
+
+    // invalid definitions fail as they would when defined
+    assert!(program.run_command("probar struct roto nada").unwrap_err().display().contains("El símbolo 'nada' no existe"));
+    assert!(program.run_command("probar describir par").is_err());
+    assert!(program.run_command("probar").is_err());
+    assert_eq!(program.revision(), revision);
+}
+
+#[test]
+fn test_describe_matching() {
+    use crate::driver::Program;
+    use crate::utils::wildcard;
+
+    assert!(wildcard("net::*", "net::cabecera") && wildcard("*", "") && wildcard("n?t*a", "net::cola"));
+    assert!(!wildcard("net::*", "red::cola") && !wildcard("?", "") && !wildcard("*a", "ab"));
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_line("atomico int 4 4");
+    program.run_line("atomico char 1 1");
+    program.run_line("struct net::cabecera int char");
+    program.run_line("struct net::cola char int char");
+    program.run_line("struct otro char int");
+    program.take_output();
+
+    // patterns take every matching type, in definition order, under one strategy if asked
+    program.run_line("describir net::* --estrategia empaquetado");
+    let output = program.take_output();
+    let first = output.find("Símbolo: net::cabecera\n").unwrap();
+    assert!(first < output.find("Símbolo: net::cola\n").unwrap(), "{}", output);
+    assert!(!output.contains("otro") && output.contains("   * Empaquetado:\n"), "{}", output);
+    assert!(!output.contains("Sin Empaquetar") && !output.contains("Optimizado") && !output.contains("   * C:"), "{}", output);
+
+    // names and patterns mix, each type is described once
+    program.run_line("describir otro net::cola *cola --estrategia c");
+    let output = program.take_output();
+    assert_eq!(output.matches("Símbolo: ").count(), 2, "{}", output);
+    assert!(output.starts_with("Símbolo: otro\n📦 Struct:\n   * C:\n      + Tamaño: 8\n"), "{}", output);
+
+    program.run_line("describir red::*");
+    assert_eq!(program.take_output(), "Ningún tipo coincide con 'red::*'\n");
+    program.run_line("describir net::cabecera --json --estrategia optimizado");
+    assert!(program.take_output().contains("\"strategies\":[{\"strategy\":\"optimized\",\"size\":5,"));
+    program.run_line("describir int --estrategia c");
+    assert!(program.take_output().contains("   * Representación: 4\n"));
+}
+
+#[test]
+fn test_budgets() {
+    use crate::driver::Program;
+    use crate::type_system::{Budget, Strategy};
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_line("atomico int 4 4");
+    program.run_line("atomico char 1 1");
+    program.run_line("struct cabecera int char");
+    program.take_output();
+
+    program.run_line("validar");
+    assert_eq!(program.take_output(), "No hay presupuestos que validar\n");
+    program.run_line("presupuesto cabecera 8");
+    program.run_line("validar");
+    assert_eq!(program.take_output(), "Presupuesto de 'cabecera': 8 bytes (C)\nSe cumple el único presupuesto\n");
+
+    // budgets may come before their types, and are checked under their strategy
+    program.run_line("presupuesto futuro 4 --estrategia empaquetado");
+    assert_eq!(program.budget_errors(), vec!["'futuro' tiene un presupuesto de 4 bytes pero no está definido"]);
+    program.run_line("struct futuro int char");
+    program.run_line("redefinir struct cabecera int int char");
+    program.take_output();
+    let error = program.run_command("validar").unwrap_err().display();
+    assert!(error.contains("Hay presupuestos que no se cumplen:\n  * 'cabecera' ocupa 12 bytes (C), 4 más que su presupuesto de 8\n  * 'futuro' ocupa 5 bytes (Empaquetado), 1 más que su presupuesto de 4"), "{}", error);
+    program.run_line("presupuesto");
+    let table = program.take_output();
+    assert!(table.contains("cabecera") && table.contains("Empaquetado") && table.matches("❌").count() == 2, "{}", table);
+
+    // sessions keep budgets, even of types not defined yet
+    program.run_line("presupuesto futuro --quitar");
+    program.run_line("presupuesto lejano 64 --estrategia optimizado");
+    let script = program.session_script();
+    assert!(script.contains("struct cabecera int int char\npresupuesto cabecera 8\n") && script.ends_with("presupuesto lejano 64 --estrategia optimized\n"), "{}", script);
+    assert!(!script.contains("presupuesto futuro"));
+    let mut restored = Program::new();
+    restored.capture_output();
+    assert!(restored.run_script(&script, "sesion.tm").errors.is_empty());
+    assert_eq!(restored.manager().budget(&"lejano".to_string()), Some(Budget { limit: 64, strategy: Strategy::Optimized }));
+    assert_eq!(restored.budget_errors().len(), 2);
+
+    let path = std::env::temp_dir().join(format!("typemanager_presupuesto_{}.bin", std::process::id()));
+    program.run_line(&format!("guardar {} --formato bin", path.display()));
+    let mut restored = Program::new();
+    restored.capture_output();
+    restored.run_line(&format!("cargar {}", path.display()));
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(restored.manager().budget(&"cabecera".to_string()), Some(Budget { limit: 8, strategy: Strategy::C }));
+
+    assert!(program.run_command("presupuesto cabecera").is_err());
+    assert!(program.run_command("presupuesto cabecera mucho").is_err());
+    assert!(program.run_command("presupuesto cabecera 8 --linea 64").is_err());
+    assert!(program.run_command("validar todo").is_err());
+}
+
+#[test]
+fn test_growth() {
+    use crate::driver::Program;
+    use crate::config::Config;
+
+    let mut program = Program::new();
+    program.capture_output();
+    let script = "atomico int 4 4\natomico char 1 1\nstruct cabecera int char\nredefinir struct cabecera int int char\n";
+    assert!(program.run_script(script, "cabecera.tm").errors.is_empty());
+    // same layout, nothing to record
+    program.run_line("redefinir struct cabecera int char int");
+    // a type it's built on grows, and so does it
+    program.run_line("redefinir atomico int 8 8");
+    program.take_output();
+
+    let history = program.manager().growth(&"cabecera".to_string());
+    assert_eq!(history.iter().map(|g| (g.size, g.align)).collect::<Vec<_>>(), vec![(8, 4), (12, 4), (24, 8)]);
+    assert_eq!(history[0].origin.as_deref(), Some("cabecera.tm:3"));
+    assert_eq!(history[2].cause.as_deref(), Some("int"));
+
+    program.run_line("historia cabecera");
+    let output = program.take_output();
+    assert!(output.starts_with("Historia de 'cabecera' (C):\n"), "{}", output);
+    assert!(output.contains("struct cabecera int char en cabecera.tm:3"), "{}", output);
+    assert!(output.contains("+4") && output.contains("+12"), "{}", output);
+    assert!(output.contains("redefinir atomico int 8 8 (por 'int')"), "{}", output);
+    assert!(!output.contains("int char int"), "{}", output);
+
+    // a shadowed name keeps its history, the new definition continues it
+    let mut shadowing = Program::with_config(Config { autosave: false, shadowing: true, ..Config::default() });
+    shadowing.capture_output();
+    shadowing.run_line("atomico int 4 4");
+    shadowing.run_line("atomico int 2 2");
+    assert_eq!(shadowing.manager().growth(&"int".to_string()).len(), 2);
+    assert_eq!(shadowing.manager().growth(&"int@anterior".to_string()).len(), 0);
+
+    assert!(program.run_command("historia nada").is_err());
+    assert!(program.run_command("historia").is_err());
+    assert!(program.run_command("historia cabecera char").is_err());
+}
+
+#[test]
+fn test_treemap_export() {
+    use crate::driver::{ExportFormat, Program};
+
+    let mut program = Program::new();
+    program.capture_output();
+    assert!(program.export(ExportFormat::Treemap).contains("No hay tipos definidos"));
+    program.run_script("atomico int 4 4\natomico char 1 1\nstruct par char int\nstruct grande int int int int par\n", "treemap.tm");
+
+    let svg = program.export(ExportFormat::Treemap);
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"960\" height=\"600\""), "{}", svg);
+    assert!(svg.contains("<title>4 tipos, 37 bytes, 6 de relleno (16.2%)</title>"), "{}", svg);
+    assert!(svg.contains("<title>par: 8 bytes, 3 de relleno (37.5%)</title>"), "{}", svg);
+    assert!(svg.contains("<title>grande: 24 bytes, 3 de relleno (12.5%)</title>"), "{}", svg);
+    assert_eq!(svg.matches("fill=\"url(#relleno)\"").count(), 2);
+
+    // the area of every type is its share of the picture, and they cover it all, but for rounding
+    let mut covered = 0.0;
+    for group in svg.split("<g><title>").skip(1) {
+        let size : f64 = group.split(": ").nth(1).unwrap().split(' ').next().unwrap().parse().unwrap();
+        let number = |attribute : &str| -> f64 { group.split(&format!(" {}=\"", attribute)).nth(1).unwrap().split('"').next().unwrap().parse().unwrap() };
+        let area = number("width") * number("height");
+        assert!((area - size * 960.0 * 600.0 / 37.0).abs() < 10.0, "{} {}", size, area);
+        assert!(number("x") + number("width") <= 960.01 && number("y") + number("height") <= 600.01);
+        covered += area;
+    }
+    assert!((covered - 960.0 * 600.0).abs() < 20.0);
+
+    program.run_line("exportar treemap-html");
+    let page = program.take_output();
+    assert!(page.starts_with("<!DOCTYPE html>") && page.contains("<svg "), "{}", page);
+    assert!(page.contains("<tr><td>grande</td><td class=\"n\">24</td><td class=\"n\">3</td><td class=\"n\">12.5</td></tr>"), "{}", page);
+    assert!(!page.contains("<td>int</td>"));
+    assert!(program.run_command("exportar treemap --filas 2").is_err());
+}
+
+#[test]
+fn test_flatten() {
+    use crate::driver::{ExportFormat, Program};
+
+    let mut program = Program::new();
+    program.capture_output();
+    let script = "atomico int 4 4\natomico char 1 1\natomico long 8 8\n\
+                  struct punto x:int y:int\nstruct cabecera tipo:char origen:punto marca:char\n\
+                  struct par a:long b:char\nstruct envoltorio p:par c:char\n\
+                  struct compacto @packed a:char b:int\nstruct fuera c:compacto d:int\n";
+    assert!(program.run_script(script, "aplanar.tm").errors.is_empty());
+    program.take_output();
+
+    let offsets = |program : &Program, name : &str| -> Vec<(String, usize)> {
+        match program.manager().get(&name.to_string()) {
+            Some(Type::Struct(s)) => s.fields.iter().map(|f| f.name.clone()).zip(s.c_offsets(program.manager())).collect(),
+            _ => panic!("'{}' no es un struct", name)
+        }
+    };
+    let expected = |pairs : &[(&str, usize)]| -> Vec<(String, usize)> { pairs.iter().map(|(f, o)| (f.to_string(), *o)).collect() };
+
+    program.run_line("aplanar cabecera");
+    let output = program.take_output();
+    assert!(output.starts_with("'cabecera_plano' es 'cabecera' aplanado, 4 campos en los desplazamientos de C:\n"), "{}", output);
+    assert!(!output.contains(" ocupa "), "{}", output);
+    assert_eq!(offsets(&program, "cabecera_plano"), expected(&[("tipo", 0), ("origen.x", 4), ("origen.y", 8), ("marca", 12)]));
+
+    // tail padding of a nested struct needs the smallest alignment reaching the offset, so the size is kept
+    program.run_line("aplanar envoltorio");
+    assert_eq!(offsets(&program, "envoltorio_plano"), expected(&[("p.a", 0), ("p.b", 8), ("c", 16)]));
+    assert_eq!(program.manager().size(&"envoltorio_plano".to_string(), Strategy::C), Ok(24));
+    // leaves of packed structs off their alignment make the flat struct packed
+    program.run_line("aplanar fuera");
+    assert_eq!(offsets(&program, "fuera_plano"), expected(&[("c.a", 0), ("c.b", 1), ("d", 8)]));
+    assert_eq!(program.manager().size(&"fuera_plano".to_string(), Strategy::C), Ok(12));
+    // offsets of another strategy, in memory order
+    program.run_line("aplanar cabecera --estrategia optimizado --nombre cab_opt");
+    assert_eq!(offsets(&program, "cab_opt"), expected(&[("origen.x", 0), ("origen.y", 4), ("tipo", 8), ("marca", 9)]));
+    program.take_output();
+
+    // dotted names survive sessions and attributes, and are identifiers in generated code
+    program.run_line("atributo cabecera_plano.origen.x @nota=abscisa");
+    let script = program.session_script();
+    assert!(script.contains("struct envoltorio_plano p.a:long p.b:char c:char @aligned=8\n"), "{}", script);
+    let mut restored = Program::new();
+    restored.capture_output();
+    assert!(restored.run_script(&script, "sesion.tm").errors.is_empty());
+    assert_eq!(offsets(&restored, "fuera_plano"), offsets(&program, "fuera_plano"));
+    let c = program.export(ExportFormat::C);
+    assert!(c.contains("typedef struct envoltorio_plano { long p_a; char p_b; char c __attribute__((aligned(8))); } envoltorio_plano;"), "{}", c);
+    assert!(c.contains("/* cabecera_plano.origen.x: abscisa */"), "{}", c);
+
+    assert!(matches!(program.run_command("aplanar int"), Err(e) if e.display().contains("no es un struct")));
+    assert!(program.run_command("aplanar cabecera").is_err());
+    assert!(program.run_command("aplanar nada").is_err());
+    assert!(program.run_command("aplanar cabecera --nombre").is_err());
+    assert!(program.run_command("aplanar cabecera --color rojo").is_err());
+    assert!(program.run_command("struct malo a..b:int").is_err());
+}
+
+#[test]
+fn test_sandbox() {
+    use crate::driver::Program;
+
+    let dir = std::env::temp_dir().join(format!("typemanager_sandbox_{}", std::process::id()));
+    let file = |name : &str| dir.join(name).display().to_string();
+    let mut program = Program::sandboxed();
+    program.capture_output();
+    program.run_line("atomico int 4 4");
+    program.run_line("struct s int int");
+    program.take_output();
+
+    // every command reading or writing a file is rejected before touching it
+    for line in [
+        format!("guardar {}", file("s.tm")),
+        format!("cargar {}", file("s.tm")),
+        format!("incluir {}", file("s.tm")),
+        format!("fusionar {}", file("s.tm")),
+        format!("importar dwarf {}", file("a.out")),
+        format!("importar jsonl {}", file("c.jsonl")),
+        format!("exportar imagen s {}", file("s.png")),
+        format!("documentar {}", file("sitio")),
+        format!("diario {}", file("diario.tm")),
+        format!("reproducir {}", file("diario.tm")),
+        format!("traza s {}", file("traza.txt")),
+        format!("decodificar int {}", file("bytes.bin")),
+        format!("describir s > {}", file("s.txt")),
+        format!("listar >> {}", file("tipos.txt"))
+    ].iter() {
+        program.run_line(line);
+        assert!(program.take_output().starts_with("[ERROR]: Este programa no puede usar archivos"), "{}", line);
+    }
+    assert!(!dir.exists());
+
+    // everything else works as usual
+    program.run_line("decodificar int 01000000");
+    assert!(program.take_output().contains('1'));
+    program.run_line("exportar tm");
+    assert!(program.take_output().contains("struct s int int"));
+    program.run_line("diario");
+    assert!(!program.take_output().contains("[ERROR]"));
+    assert_eq!(program.manager().len(), 2);
+}
+
+#[test]
+fn test_read_only() {
+    use crate::driver::Program;
+    use crate::config::Config;
+    use crate::cli::split_options;
+
+    let mut program = Program::with_config(Config { autosave: false, read_only: true, ..Config::default() });
+    program.capture_output();
+
+    // queries and exports work, changes are rejected with their own error
+    program.run_line("atomico int 4 4");
+    assert_eq!(program.take_output(), "[ERROR]: La sesión 'principal' es de solo lectura, sus tipos no pueden cambiar\n");
+    program.run_line("asistente");
+    assert!(program.take_output().contains("solo lectura"));
+    program.run_line("calcular 2 * 3");
+    assert_eq!(program.take_output(), "6\n");
+    program.run_line("exportar tm");
+    assert!(program.take_output().contains("Sesión guardada"));
+    assert!(program.manager().is_empty());
+
+    // scripts report every rejected line, and a read only program can't be unlocked
+    let summary = program.run_script("atomico int 4 4\nlistar\natomico char 1 1\n", "tipos.tm");
+    assert_eq!(summary.errors.len(), 2);
+    program.run_line("sesion desbloquear");
+    assert!(program.take_output().contains("solo lectura"));
+
+    // sessions can be locked one by one
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_line("atomico int 4 4");
+    program.run_line("sesion bloquear");
+    program.run_line("struct s int int");
+    assert!(program.take_output().contains("La sesión 'principal' es de solo lectura, sus tipos"));
+    program.run_line("sesion nueva arm");
+    program.run_line("atomico int 4 4");
+    assert_eq!(program.manager().len(), 1);
+    program.run_line("sesion usar principal");
+    program.run_line("sesion");
+    assert!(program.take_output().lines().any(|l| l.contains("principal") && l.contains("sí")));
+    program.run_line("sesion desbloquear");
+    program.run_line("struct s int int");
+    assert_eq!(program.manager().len(), 2);
+
+    let (options, rest) = split_options(["--solo-lectura", "serve"].iter().map(|s| s.to_string()));
+    assert!(options.read_only && rest == vec!["serve"]);
+    assert!(Config::parse("solo_lectura = si\n").0.read_only);
+}
+
+#[test]
+fn test_journal_replay() {
+    use crate::driver::Program;
+    use crate::config::Config;
+
+    let dir = std::env::temp_dir().join(format!("typemanager_journal_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let journal = dir.join("diario.tmj");
+    let _ = std::fs::remove_file(&journal);
+
+    // only commands that changed the session are recorded, each with its time
+    let mut program = Program::with_config(Config { autosave: false, journal: Some(journal.clone()), ..Config::default() });
+    program.capture_output();
+    program.run_line("atomico int 4 4");
+    program.run_line("atomico char 1 1");
+    program.run_line("struct s char int");
+    program.run_line("struct s nada");
+    program.run_line("describir s");
+    program.run_line("listar");
+    let text = std::fs::read_to_string(&journal).unwrap();
+    let commands : Vec<&str> = text.lines().filter(|l| !l.starts_with('#')).map(|l| l.split('\t').nth(1).unwrap()).collect();
+    assert_eq!(commands, vec!["atomico int 4 4", "atomico char 1 1", "struct s char int"]);
+    program.run_line("diario --parar");
+    program.run_line("atomico long 8 8");
+    assert_eq!(std::fs::read_to_string(&journal).unwrap(), text);
+
+    // replaying rebuilds the same types
+    let mut replayed = Program::new();
+    replayed.capture_output();
+    replayed.run_line(&format!("reproducir {}", journal.display()));
+    assert!(replayed.take_output().contains("Reproducidos 3 comandos"));
+    assert_eq!(replayed.manager().len(), 3);
+    assert_eq!(replayed.manager().get(&String::from("s")), program.manager().get(&String::from("s")));
+
+    // bad entries are reported by line
+    std::fs::write(dir.join("malo.tmj"), "# diario\nayer\tatomico int 4 4\n1.000\tstruct t nada\n").unwrap();
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_line(&format!("reproducir {}", dir.join("malo.tmj").display()));
+    let output = program.take_output();
+    assert!(output.contains("malo.tmj:2:") && output.contains("malo.tmj:3:"));
+    assert!(program.run_command("reproducir").is_err());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_verify_replay() {
+    use crate::driver::Program;
+
+    let dir = std::env::temp_dir().join(format!("typemanager_verify_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("diario.tmj"), "1.000\tatomico int 4 4\n2.000\tatomico char 1 1\n3.000\tstruct s { a:char, b:int }\n").unwrap();
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_line(&format!("reproducir {}", dir.join("diario.tmj").display()));
+    program.run_line(&format!("guardar {}", dir.join("bien.tm").display()));
+    program.take_output();
+    std::fs::write(dir.join("mal.tm"), "atomico int 4 4\natomico char 1 1\nstruct s { b:int, a:char }\n").unwrap();
+
+    // the session in use is left alone
+    program.run_line(&format!("verificar-reproduccion {} {}", dir.join("diario.tmj").display(), dir.join("bien.tm").display()));
+    assert!(program.take_output().contains("Reproducción verificada"));
+    assert_eq!(program.manager().len(), 3);
+
+    // the first divergence is told, with how its layout changed
+    program.run_line(&format!("verificar-reproduccion {} {}", dir.join("diario.tmj").display(), dir.join("mal.tm").display()));
+    let output = program.take_output();
+    assert!(output.starts_with("[ERROR]: La reproducción de"), "{}", output);
+    assert!(output.contains("'s' difiere: mismo tamaño, otros desplazamientos"), "{}", output);
+    assert!(output.contains("a: desplazamiento 4 -> 0"));
+    assert!(program.run_command("verificar-reproduccion diario.tmj").is_err());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_profile() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("atomico int 4 4\natomico char 1 1\nstruct a char int char\nstruct b a char a int\n", "perfil.tm");
+
+    let profiles = program.manager().profile(&String::from("b")).unwrap();
+    assert_eq!(profiles.len(), Strategy::ALL.len());
+    for p in &profiles {
+        assert_eq!(p.depth, 3);
+        assert_eq!(p.misses, 3);
+        assert_eq!(p.lookups, p.hits + p.misses);
+    }
+    // a size and an alignment computed once for each of 'a', 'char' and 'int', packed structs
+    // don't ask for alignments, every other use of them comes from the cache
+    let cached : Vec<(usize, usize)> = profiles.iter().map(|p| (p.cache_hits, p.cache_misses)).collect();
+    assert_eq!(cached, vec![(38, 6), (10, 6), (4, 5), (22, 6)]);
+    // every profile starts from an empty cache, so taking it again does the same work
+    let again = program.manager().profile(&String::from("b")).unwrap();
+    assert!(profiles.iter().zip(&again).all(|(a, b)| (a.lookups, a.hits, a.misses, a.optimizations) == (b.lookups, b.hits, b.misses, b.optimizations)));
+    // only the optimized strategy searches member orders, here for the size and alignment of 'b' and 'a'
+    let optimized = profiles.iter().find(|p| p.strategy == Some(Strategy::Optimized)).unwrap();
+    assert!(optimized.optimizations == 4 && optimized.optimizer.permutations > 0);
+    assert!(profiles.iter().filter(|p| p.strategy != Some(Strategy::Optimized)).all(|p| p.optimizations == 0));
+
+    // nothing is counted outside of a profile
+    let atomic = program.manager().profile(&String::from("int")).unwrap();
+    assert!(atomic.iter().all(|p| p.depth == 1 && p.lookups == 0));
+    program.take_output();
+    program.run_line("perfil b");
+    let output = program.take_output();
+    assert!(output.starts_with("Perfil de 'b':") && output.contains("Optimizado"));
+    assert!(output.contains("El optimizador buscó un orden de miembros"));
+    assert!(program.run_command("perfil nada").is_err());
+}
+
+#[test]
+fn test_streaming_import() {
+    use crate::driver::Program;
+    use crate::cli::split_options;
+    use crate::config::Config;
+
+    let dir = std::env::temp_dir().join(format!("typemanager_stream_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    // scripts are run as they're read
+    let mut script = String::from("atomico int 4 4\n");
+    for i in 0..2000 {
+        script += &format!("struct s{} int int\n", i);
+    }
+    script += "struct roto nada\n";
+    std::fs::write(dir.join("grande.tm"), &script).unwrap();
+    let mut program = Program::with_config(Config { autosave: false, progress: true, ..Config::default() });
+    program.capture_output();
+    let summary = program.run_file(&dir.join("grande.tm")).unwrap();
+    assert_eq!(summary.defined, 2001);
+    assert_eq!(summary.errors.len(), 1);
+    assert!(summary.errors[0].contains("grande.tm:2002:"));
+
+    // JSON lines, with lint rules allowed per line
+    let lines = [
+        r#"{"command": "atomico char 1 1"}"#,
+        "",
+        r#"{"command": "struct par char int", "allow": ["relleno"]}"#,
+        r#"{"comando": "atomico long 8 8"}"#,
+        r#"{"command": "struct x char", "allow": ["nada"]}"#,
+        "no es json"
+    ];
+    std::fs::write(dir.join("tipos.jsonl"), lines.join("\n")).unwrap();
+    program.take_output();
+    program.run_line(&format!("importar jsonl {}", dir.join("tipos.jsonl").display()));
+    let output = program.take_output();
+    assert!(output.contains("Importados 2 tipos de"), "{}", output);
+    assert!(output.contains("tipos.jsonl:4:") && output.contains("tipos.jsonl:5:") && output.contains("tipos.jsonl:6:"));
+    assert!(program.manager().get(&String::from("par")).is_some());
+    assert!(program.run_command("importar jsonl nada.jsonl").is_err());
+
+    let (options, _) = split_options(["--progreso"].iter().map(|s| s.to_string()));
+    assert!(options.progress);
+    assert!(Config::parse("progreso = si\n").0.progress);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_redefine() {
+    use crate::driver::Program;
+
+    let mut program = Program::new();
+    program.capture_output();
+    program.run_script("atomico int 4 4\natomico char 1 1\nstruct a char\nstruct b a int\nstruct c b\nstruct otro int\n", "redefinir.tm");
+    let manager = program.manager();
+    assert_eq!(manager.dependents(&String::from("a")), vec!["b", "c"]);
+    assert!(manager.dependents(&String::from("otro")).is_empty());
+
+    // layouts are cached once asked for, and only the ones built on a redefined type are dropped
+    assert_eq!(Strategy::C.type_size(manager.get(&String::from("c")).unwrap(), manager), 8);
+    manager.size_of(&String::from("otro"), Strategy::C);
+    let cached = manager.layout_cache().len();
+    program.run_line("redefinir struct a int int");
+    assert_eq!(program.take_output(), "'a' redefinido, 2 tipos afectados: b, c\n");
+    let manager = program.manager();
+    assert_eq!(manager.layout_cache().len(), cached);    // a and b, c itself was never a member, laid out again for their history
+    assert_eq!(Strategy::C.type_size(manager.get(&String::from("c")).unwrap(), manager), 12);
+    assert_eq!(manager.size_of(&String::from("b"), Strategy::C), 12);
+
+    // a type can't be built on a type built on it, and what's not stored can't be redefined
+    assert!(program.run_command("redefinir struct a c").is_err());
+    assert!(program.run_command("redefinir struct a a").is_err());
+    assert!(program.run_command("redefinir struct nada int").is_err());
+    assert!(program.run_command("redefinir listar").is_err());
+    assert!(program.run_command("redefinir redefinir atomico int 8 8").is_err());
+    assert_eq!(program.manager().size_of(&String::from("b"), Strategy::C), 12);
+
+    program.run_line("redefinir atomico int 8 8");
+    assert!(program.take_output().contains("'int' redefinido, 4 tipos afectados: a, b, c, otro"));
+    assert_eq!(program.manager().size_of(&String::from("c"), Strategy::C), 24);
+    assert_eq!(program.manager().dependents(&String::from("a")), vec!["b", "c"]);
+}
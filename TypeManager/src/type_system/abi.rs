@@ -0,0 +1,64 @@
+/*
+    ABI presets: what a target machine decides for every layout, which here is
+    the size of its pointers. Pointers, strings and containers are made of
+    pointer sized words, so the same definitions take different sizes on each
+*/
+use super::{TypeManager, POINTER_SIZE};
+
+/// A target machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Abi {
+    pub name:         &'static str,
+    pub pointer_size: usize
+}
+
+/// Every known target, the first one is the default
+pub const ABIS : [Abi; 5] = [
+    Abi { name: "x86_64", pointer_size: POINTER_SIZE },
+    Abi { name: "x86",    pointer_size: 4 },
+    Abi { name: "arm64",  pointer_size: 8 },
+    Abi { name: "arm",    pointer_size: 4 },
+    Abi { name: "wasm32", pointer_size: 4 }
+];
+
+impl Abi {
+
+    /// Find a target by name, common aliases such as `amd64` or `aarch64` work too
+    pub fn parse(name : &str) -> Option<Abi> {
+        let name = match name.to_lowercase().as_str() {
+            "amd64" | "x64"            => "x86_64",
+            "i386" | "i686" | "x86_32" => "x86",
+            "aarch64"                  => "arm64",
+            "armv7" | "arm32"          => "arm",
+            "wasm"                     => "wasm32",
+            other => return ABIS.iter().find(|a| a.name == other).copied()
+        };
+
+        ABIS.iter().find(|a| a.name == name).copied()
+    }
+}
+
+impl Default for Abi {
+    fn default() -> Self {
+        ABIS[0]
+    }
+}
+
+impl TypeManager {
+
+    /// Target machine our layouts are computed for
+    pub fn abi(&self) -> Abi {
+        self.abi
+    }
+
+    /// Compute layouts for another target machine
+    pub fn set_abi(&mut self, abi : Abi) {
+        self.abi = abi;
+        self.layout_cache().clear();
+    }
+
+    /// Size and alignment of a pointer in our target machine
+    pub fn pointer_size(&self) -> usize {
+        self.abi.pointer_size
+    }
+}
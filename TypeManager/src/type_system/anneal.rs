@@ -0,0 +1,182 @@
+/*
+    Simulated annealing: a stochastic search for a good member order, for structs
+    too big for the optimizer to try every order. Members sharing a `@grupo` label
+    move as a single block, so groups stay together. Every restart starts from a
+    random order but the first, which starts from the greedy heuristic (biggest
+    alignment first), so the result is never worse than it. Members marked `@fijo`
+    never move. The same seed always gives the same result
+*/
+use std::time::{Duration, Instant};
+
+use super::{cancel, limits, Name, Struct, Type, TypeError, TypeManager, CANCEL_CHECK, GROUP};
+use crate::utils::Rng;
+
+/// How long to search and where to start from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnnealOptions {
+    pub seed:     u64,
+    pub steps:    usize,    // orders tried in every restart
+    pub restarts: usize
+}
+
+impl Default for AnnealOptions {
+    fn default() -> Self {
+        AnnealOptions { seed: 1, steps: 20_000, restarts: 4 }
+    }
+}
+
+/// Result of annealing a struct, compared with the greedy heuristic and declaration order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annealing {
+    pub order:       Vec<usize>,    // best order found, as member indices
+    pub size:        usize,
+    pub greedy:      Vec<usize>,    // biggest alignment first
+    pub greedy_size: usize,
+    pub declared_size: usize,
+    pub steps:       usize,         // orders tried in every restart
+    pub elapsed:     Duration,
+    pub stopped:     bool           // cancelled or out of time before every step
+}
+
+impl TypeManager {
+
+    /// Search a good member order for a struct with simulated annealing
+    /// ## Params
+    /// * `typename` - struct to lay out
+    /// * `options` - seed and length of the search
+    /// ---
+    /// ## Return
+    /// The best order found with the greedy one to compare, or an error if the type is not a struct
+    pub fn anneal(&self, typename : &Name, options : AnnealOptions) -> Result<Annealing, TypeError> {
+        match self.get(typename) {
+            None => Err(TypeError::TypeDoesNotExist(typename.clone())),
+            Some(Type::Struct(s)) => Ok(s.anneal(self, options)),
+            Some(_) => Err(TypeError::NotAStruct(typename.clone()))
+        }
+    }
+}
+
+impl Struct {
+
+    /// Search a good member order with simulated annealing, see `TypeManager::anneal`
+    pub fn anneal(&self, manager : &TypeManager, options : AnnealOptions) -> Annealing {
+        let start = Instant::now();
+        let members = self.member_sizes(manager);
+        let pins = self.pins(manager, &members);
+        let blocks = self.greedy_blocks(&members, &pins);
+        let size = |order : &[usize]| Struct::place_members(&Struct::flatten(&blocks, order, &pins), &members, &pins, usize::MAX).unwrap_or(usize::MAX);
+
+        let greedy : Vec<usize> = (0..blocks.len()).collect();
+        let greedy_size = size(&greedy);
+        let declared : Vec<usize> = (0..members.len()).collect();
+        let declared_size = Struct::place_members(&declared, &members, &pins, usize::MAX).unwrap_or(usize::MAX);
+
+        // temperature starts around the cost of misplacing the biggest member and cools down to almost nothing
+        let hottest = members.iter().map(|(size, align)| size.max(align)).max().copied().unwrap_or(1) as f64;
+        let cooling = (0.01f64).powf(1.0 / options.steps.max(1) as f64);
+
+        let mut rng = Rng::new(options.seed);
+        let (mut best, mut best_size) = (greedy.clone(), greedy_size);
+        let mut stopped = false;
+        'restarts: for restart in 0..options.restarts.max(1) {
+            let mut current = greedy.clone();
+            if restart > 0 {
+                Struct::shuffle(&mut current, &mut rng);
+            }
+            let mut current_size = size(&current);
+            let mut temperature = hottest;
+
+            for step in 0..options.steps {
+                if step % CANCEL_CHECK == 0 {
+                    if cancel::requested() {
+                        stopped = true;
+                        break 'restarts
+                    }
+                    if manager.limits().optimizer.is_some_and(|limit| start.elapsed() > limit) {
+                        stopped = true;
+                        limits::time_out();
+                        break 'restarts
+                    }
+                }
+                if current.len() < 2 {
+                    break
+                }
+
+                // try swapping two blocks, worse orders are taken less often as it cools down
+                let (i, j) = (rng.below(current.len()), rng.below(current.len()));
+                current.swap(i, j);
+                let candidate = size(&current);
+                let worse = candidate.saturating_sub(current_size) as f64;
+                let chance = rng.next_u64() as f64 / u64::MAX as f64;
+                match candidate <= current_size || chance < (-worse / temperature).exp() {
+                    true  => current_size = candidate,
+                    false => current.swap(i, j)
+                }
+                if current_size < best_size {
+                    best_size = current_size;
+                    best = current.clone();
+                }
+                temperature *= cooling;
+            }
+        }
+
+        // no order found keeps pinned members in place, keep the declared one
+        let (order, size) = match best_size {
+            usize::MAX => (declared.clone(), declared_size),
+            _ => (Struct::flatten(&blocks, &best, &pins), best_size)
+        };
+        let (greedy, greedy_size) = match greedy_size {
+            usize::MAX => (declared, declared_size),
+            _ => (Struct::flatten(&blocks, &greedy, &pins), greedy_size)
+        };
+
+        Annealing {
+            order,
+            size,
+            greedy,
+            greedy_size,
+            declared_size,
+            steps: options.steps,
+            elapsed: start.elapsed(),
+            stopped
+        }
+    }
+
+    /// Members that move together, the ones sharing a group label and every other one
+    /// on its own, sorted by the greedy heuristic: biggest alignment first, then biggest size.
+    /// Pinned members don't move, they are in no block
+    fn greedy_blocks(&self, members : &[(usize, usize)], pins : &[Option<usize>]) -> Vec<Vec<usize>> {
+        let mut blocks : Vec<(Option<&str>, Vec<usize>)> = vec![];
+        for (i, field) in self.fields.iter().enumerate() {
+            if pins[i].is_some() {
+                continue
+            }
+            let group = field.attribute(GROUP);
+            match blocks.iter_mut().find(|(g, _)| group.is_some() && *g == group) {
+                Some((_, block)) => block.push(i),
+                None => blocks.push((group, vec![i]))
+            }
+        }
+
+        let key = |i : &usize| (std::cmp::Reverse(members[*i].1), std::cmp::Reverse(members[*i].0));
+        let mut blocks : Vec<Vec<usize>> = blocks.into_iter().map(|(_, mut block)| { block.sort_by_key(key); block }).collect();
+        blocks.sort_by_key(|block| block.iter().map(key).min());
+        blocks
+    }
+
+    /// Member indices for blocks placed in some order, with pinned members in their declared position
+    fn flatten(blocks : &[Vec<usize>], order : &[usize], pins : &[Option<usize>]) -> Vec<usize> {
+        let mut members : Vec<usize> = order.iter().flat_map(|b| blocks[*b].iter().copied()).collect();
+        for (i, _) in pins.iter().enumerate().filter(|(_, pin)| pin.is_some()) {
+            members.insert(i, i);
+        }
+        members
+    }
+
+    /// Put a list in random order (Fisher-Yates)
+    fn shuffle(list : &mut [usize], rng : &mut Rng) {
+        for i in (1..list.len()).rev() {
+            list.swap(i, rng.below(i + 1));
+        }
+    }
+}
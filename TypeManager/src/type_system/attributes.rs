@@ -0,0 +1,94 @@
+/*
+    Attributes: `@clave=valor` annotations added to a type or to a struct member
+    after it was defined. Structs and their members keep them in the model, next
+    to the ones changing their layout such as `@packed` or `@caliente`, so those
+    can be added later too. Other types keep them in the manager. Reports show
+    notes, and exporters turn notes into comments and doc strings and layout
+    attributes into what each language writes for them
+*/
+use super::{Attribute, Name, Type, TypeError, TypeManager, NOTE};
+
+/// Note among some attributes, the last one if there are several
+/// ## Return
+/// Its text, or none if there's no note with a value
+pub fn note(attributes : &[Attribute]) -> Option<&str> {
+    attributes.iter().rev().find(|a| NOTE.contains(&a.key.as_str())).and_then(|a| a.value.as_deref())
+}
+
+/// Add attributes to a list, replacing the ones already there with the same key
+fn merge(list : &mut Vec<Attribute>, attributes : Vec<Attribute>) {
+    for a in attributes {
+        match list.iter_mut().find(|old| old.key == a.key) {
+            Some(old) => *old = a,
+            None => list.push(a)
+        }
+    }
+}
+
+impl TypeManager {
+
+    /// Add attributes to a stored type or to a member of a stored struct. Attributes
+    /// already there with the same key are replaced
+    /// ## Params
+    /// * `typename` - type to annotate
+    /// * `member` - field of the struct to annotate, none for the type itself
+    /// * `attributes` - attributes to add
+    /// ---
+    /// ## Return
+    /// An error if the type or the field don't exist, a member is given for a type
+    /// that is not a struct, or the attributes make the struct invalid
+    pub fn add_attributes(&mut self, typename : &Name, member : Option<&str>, attributes : Vec<Attribute>) -> Result<(), TypeError> {
+        let stored = self.resolve(typename).cloned().ok_or_else(|| TypeError::TypeDoesNotExist(typename.clone()))?;
+        let mut s = match (self.get(&stored), member) {
+            (Some(Type::Struct(s)), _) => s.clone(),
+            (_, Some(_)) => return Err(TypeError::NotAStruct(stored)),
+            (_, None) => {
+                merge(self.attributes.entry(stored).or_default(), attributes);
+                return Ok(())
+            }
+        };
+
+        match member {
+            Some(field) => {
+                let i = s.field_index(field).ok_or_else(|| TypeError::UnknownField(stored.clone(), field.to_string()))?;
+                merge(&mut s.fields[i].attributes, attributes)
+            },
+            None => merge(&mut s.attributes, attributes)
+        }
+
+        // attributes like @packed change the layout, of the struct and of every type using it
+        let annotated = Type::Struct(s);
+        self.check_type(&annotated)?;
+        self.invalidate(&stored);
+        self.types.insert(stored, annotated);
+        Ok(())
+    }
+
+    /// Attributes of a stored type: the ones of the struct itself for structs, the ones
+    /// added with `add_attributes` for other types
+    pub fn attributes(&self, typename : &Name) -> &[Attribute] {
+        let stored = self.resolve(typename).unwrap_or(typename);
+        match self.get(stored) {
+            Some(Type::Struct(s)) => &s.attributes,
+            _ => self.attributes.get(stored).map_or(&[], Vec::as_slice)
+        }
+    }
+
+    /// Attributes added with `add_attributes` to a type that is not a struct, as sessions save them
+    pub fn extra_attributes(&self, typename : &Name) -> &[Attribute] {
+        let stored = self.resolve(typename).unwrap_or(typename);
+        self.attributes.get(stored).map_or(&[], Vec::as_slice)
+    }
+
+    /// Note of a stored type, if it has one
+    pub fn note(&self, typename : &Name) -> Option<&str> {
+        note(self.attributes(typename))
+    }
+
+    /// Move the attributes of a type that's stored with a new name
+    pub(super) fn move_attributes(&mut self, from : &Name, to : &Name) {
+        if let Some(attributes) = self.attributes.remove(from) {
+            self.attributes.insert(to.clone(), attributes);
+        }
+    }
+}
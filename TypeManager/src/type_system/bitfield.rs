@@ -0,0 +1,131 @@
+/*
+    Bitfields: struct members marked `@bits=N` take N bits of their type instead
+    of the whole type. Compilers disagree on how to place them: GCC and Clang
+    pack bits freely as long as a field doesn't cross a unit of its type, while
+    MSVC opens a new unit whenever the type size changes or the bits don't fit
+*/
+use super::{Field, Strategy, Struct, TypeError, TypeManager, BITS};
+use crate::utils;
+
+/// Rules for placing bitfields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitfieldPolicy {
+    Gcc,    // also Clang and most Unix ABIs
+    Msvc
+}
+
+/// Where a member goes, in bits from the start of the struct
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitPlacement {
+    pub index:    usize,    // member position in the struct
+    pub offset:   usize,    // in bits
+    pub bits:     usize,    // width for bitfields, whole size in bits otherwise
+    pub bitfield: bool
+}
+
+/// Layout of a struct with bitfields under some policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitLayout {
+    pub members: Vec<BitPlacement>,
+    pub size:    usize,     // in bytes
+    pub align:   usize
+}
+
+impl BitfieldPolicy {
+
+    /// Parse a policy name: `gcc`, `clang` or `msvc`
+    pub fn parse(name : &str) -> Option<BitfieldPolicy> {
+        match name.to_lowercase().as_str() {
+            "gcc" | "clang" => Some(BitfieldPolicy::Gcc),
+            "msvc"          => Some(BitfieldPolicy::Msvc),
+            _ => None
+        }
+    }
+
+    /// Name for this policy, as parsed by `parse`
+    pub fn name(self) -> &'static str {
+        match self {
+            BitfieldPolicy::Gcc  => "gcc",
+            BitfieldPolicy::Msvc => "msvc"
+        }
+    }
+}
+
+impl Field {
+
+    /// Bits this member takes if it's a bitfield, from its `@bits` attribute
+    pub fn bit_width(&self) -> Option<usize> {
+        self.attribute(BITS).and_then(|bits| bits.parse().ok())
+    }
+}
+
+impl Struct {
+
+    /// Place every member as a C compiler with the given bitfield policy would
+    /// ## Params
+    /// * `manager` - manager storing every member type
+    /// * `policy` - rules for bitfields
+    /// ---
+    /// ## Return
+    /// Bit offset of every member in declaration order, or an error if a bitfield is wider than its type
+    pub fn bitfield_layout(&self, manager : &TypeManager, policy : BitfieldPolicy) -> Result<BitLayout, TypeError> {
+        let mut members = Vec::with_capacity(self.members.len());
+        let mut align = 1;
+        let mut position = 0;           // first free bit
+        let mut unit : Option<(usize, usize)> = None;    // MSVC storage unit being filled: end in bits, type size
+
+        for (index, (member, field)) in self.members.iter().zip(&self.fields).enumerate() {
+            let t = manager.get(member).ok_or_else(|| TypeError::TypeDoesNotExist(member.clone()))?;
+            let size = Strategy::C.type_size(t, manager);
+            let member_align = Strategy::C.type_align(t, manager);
+            let unit_bits = size.saturating_mul(8);
+            align = align.max(member_align);
+
+            let width = match field.bit_width() {
+                Some(w) if w > unit_bits => return Err(TypeError::BitfieldTooWide(field.name.clone())),
+                Some(w) => w,
+                None => {
+                    // a regular member starts after the current unit
+                    if let Some((end, _)) = unit.take() {
+                        position = position.max(end);
+                    }
+                    position = utils::round_up(position, member_align.saturating_mul(8));
+                    members.push(BitPlacement { index, offset: position, bits: unit_bits, bitfield: false });
+                    position = position.saturating_add(unit_bits);
+                    continue
+                }
+            };
+
+            let offset = match policy {
+                BitfieldPolicy::Gcc => {
+                    // a zero width field or one crossing a unit of its type moves to the next unit
+                    let crosses = width == 0 || position / unit_bits != (position + width - 1) / unit_bits;
+                    match crosses {
+                        true  => utils::round_up(position, unit_bits),
+                        false => position
+                    }
+                },
+                BitfieldPolicy::Msvc => match unit {
+                    Some((end, unit_size)) if unit_size == size && width > 0 && position + width <= end => position,
+                    _ => {
+                        let start = utils::round_up(unit.map(|(end, _)| end).unwrap_or(position), member_align.saturating_mul(8));
+                        unit = match width {
+                            0 => None,
+                            _ => Some((start + unit_bits, size))
+                        };
+                        start
+                    }
+                }
+            };
+
+            members.push(BitPlacement { index, offset, bits: width, bitfield: true });
+            position = offset + width;
+        }
+
+        if let Some((end, _)) = unit {
+            position = position.max(end);
+        }
+        let size = utils::round_up(utils::round_up(position, 8) / 8, align);
+        Ok(BitLayout { members, size, align })
+    }
+}
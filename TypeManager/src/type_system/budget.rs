@@ -0,0 +1,82 @@
+/*
+    Size budgets: the most bytes a type may take under a strategy. A budget
+    doesn't stop a type from growing, it's checked when asked, by `validar`,
+    batch runs and watch mode, so a struct growing by accident fails a build
+    instead of going unnoticed. Budgets stay with their name: a type defined
+    again, or defined after its budget, is checked against it
+*/
+use super::{Name, Strategy, TypeManager};
+
+/// Most bytes a type may take under a strategy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Budget {
+    pub limit:    usize,
+    pub strategy: Strategy
+}
+
+/// A budget that doesn't hold
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overrun {
+    pub name:   Name,
+    pub budget: Budget,
+    pub size:   Option<usize>  // size under the budget's strategy, none if the type is not defined
+}
+
+impl Overrun {
+
+    /// Describe what went over its budget
+    pub fn display(&self) -> String {
+        match self.size {
+            Some(size) => format!(
+                "'{}' ocupa {} bytes ({}), {} más que su presupuesto de {}",
+                self.name, size, self.budget.strategy.name(), size - self.budget.limit, self.budget.limit
+            ),
+            None => format!("'{}' tiene un presupuesto de {} bytes pero no está definido", self.name, self.budget.limit)
+        }
+    }
+}
+
+impl TypeManager {
+
+    /// Set the budget of a type, replacing the one it had
+    /// ## Params
+    /// * `typename` - type to budget, it may be defined later
+    /// * `budget` - most bytes it may take, none to remove its budget
+    pub fn set_budget(&mut self, typename : &Name, budget : Option<Budget>) {
+        let stored = self.resolve(typename).cloned().unwrap_or_else(|| typename.clone());
+        match budget {
+            Some(budget) => self.budgets.insert(stored, budget),
+            None => self.budgets.remove(&stored)
+        };
+    }
+
+    /// Budget of a type, if it has one
+    pub fn budget(&self, typename : &Name) -> Option<Budget> {
+        let stored = self.resolve(typename).unwrap_or(typename);
+        self.budgets.get(stored).copied()
+    }
+
+    /// Every budget, by name
+    pub fn budgets(&self) -> Vec<(&Name, Budget)> {
+        let mut budgets : Vec<(&Name, Budget)> = self.budgets.iter().map(|(name, budget)| (name, *budget)).collect();
+        budgets.sort_by_key(|(name, _)| *name);
+        budgets
+    }
+
+    /// Check every budget
+    /// ## Return
+    /// The budgets that don't hold, by name: types bigger than their limit and budgeted
+    /// types that are not defined
+    pub fn overruns(&self) -> Vec<Overrun> {
+        self.budgets()
+            .into_iter()
+            .filter_map(|(name, budget)| {
+                let size = self.size(name, budget.strategy).ok();
+                match size {
+                    Some(size) if size <= budget.limit => None,
+                    _ => Some(Overrun { name: name.clone(), budget, size })
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,180 @@
+/*
+    Layout cache: the size and alignment of every stored type under every strategy,
+    computed the first time something asks for them. Structs and unions ask for their
+    members through the cache, so a type used in many places is laid out once. A
+    reverse dependency index tells which types use each type, so when a type is
+    redefined only the layouts of the types built on it are dropped
+*/
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard};
+
+use super::{cancel, limits, profile, Name, Strategy, Type, TypeManager};
+
+/// Size and alignment of a type under a strategy, each once computed
+#[derive(Debug, Default, Clone, Copy)]
+struct Layout {
+    size:  Option<usize>,
+    align: Option<usize>
+}
+
+/// Layouts computed so far, by type name and strategy
+#[derive(Debug, Default)]
+pub struct LayoutCache {
+    layouts: Mutex<HashMap<Name, [Layout; 4]>>
+}
+
+impl LayoutCache {
+
+    /// Lock the layouts, even if a thread panicked while holding them
+    fn lock(&self) -> MutexGuard<'_, HashMap<Name, [Layout; 4]>> {
+        self.layouts.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Amount of types with some layout computed
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Tells if no layout is computed
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Drop every layout computed
+    pub fn clear(&self) {
+        self.lock().clear()
+    }
+}
+
+impl Clone for LayoutCache {
+    fn clone(&self) -> Self {
+        LayoutCache { layouts: Mutex::new(self.lock().clone()) }
+    }
+}
+
+impl Strategy {
+
+    /// Position of this strategy in cached layouts
+    fn slot(&self) -> usize {
+        match self {
+            Strategy::Optimized => 0,
+            Strategy::Unpacked  => 1,
+            Strategy::Packed    => 2,
+            Strategy::C         => 3
+        }
+    }
+}
+
+impl TypeManager {
+
+    /// Size of a stored type under a strategy, the same as `Strategy::type_size` but
+    /// computed only once until the type, or a type it uses, is redefined
+    /// ## Params
+    /// * `typename` - a stored type, every member of a stored struct or union is one
+    pub fn size_of(&self, typename : &Name, strategy : Strategy) -> usize {
+        self.cached(typename, strategy, true)
+    }
+
+    /// Alignment of a stored type under a strategy, cached like `size_of`
+    pub fn align_of(&self, typename : &Name, strategy : Strategy) -> usize {
+        self.cached(typename, strategy, false)
+    }
+
+    /// Layouts computed so far
+    pub fn layout_cache(&self) -> &LayoutCache {
+        &self.layouts
+    }
+
+    /// Size or alignment of a type, from the cache or computed and cached now
+    fn cached(&self, typename : &Name, strategy : Strategy, size : bool) -> usize {
+        let name = self.resolve(typename).unwrap_or(typename);
+        let found = self.layouts.lock().get(name).and_then(|l| {
+            let layout = l[strategy.slot()];
+            match size {
+                true  => layout.size,
+                false => layout.align
+            }
+        });
+        profile::cached(found.is_some());
+        crate::metrics::cached(found.is_some());
+        if let Some(value) = found {
+            return value
+        }
+
+        // computed without holding the lock, members are looked up through the cache too
+        let timed_out = limits::timed_out();
+        // a missing type takes no room, `checked` tells callers about it
+        let value = match (self.get(name), size) {
+            (Some(t), true)  => strategy.type_size(t, self),
+            (Some(t), false) => strategy.type_align(t, self),
+            (None, true)  => return 0,
+            (None, false) => return 1
+        };
+
+        // a computation cancelled or out of time may not have found the best layout, it's not kept
+        if cancel::requested() || limits::timed_out() != timed_out {
+            return value
+        }
+
+        let mut layouts = self.layouts.lock();
+        let layout = &mut layouts.entry(name.clone()).or_default()[strategy.slot()];
+        match size {
+            true  => layout.size = Some(value),
+            false => layout.align = Some(value)
+        }
+        value
+    }
+
+    /// Every type built on a type, directly or through others
+    /// ## Params
+    /// * `typename` - a stored type
+    /// ---
+    /// ## Return
+    /// Names of the types using it, sorted
+    pub fn dependents(&self, typename : &Name) -> Vec<Name> {
+        let mut found : HashSet<&Name> = HashSet::new();
+        let mut pending : Vec<&Name> = self.resolve(typename).into_iter().collect();
+        while let Some(name) = pending.pop() {
+            for user in self.users.get(name).into_iter().flatten() {
+                if found.insert(user) {
+                    pending.push(user);
+                }
+            }
+        }
+
+        let mut dependents : Vec<Name> = found.into_iter().cloned().collect();
+        dependents.sort();
+        dependents
+    }
+
+    /// Record a stored type in the index of the types each of its members is used by
+    pub(super) fn index_users(&mut self, typename : &Name, t : &Type) {
+        for dependency in t.dependencies() {
+            let users = self.users.entry(dependency.clone()).or_default();
+            if !users.contains(typename) {
+                users.push(typename.clone());
+            }
+        }
+    }
+
+    /// Remove a type from the index of the types its members are used by
+    pub(super) fn unindex_users(&mut self, typename : &Name, t : &Type) {
+        for dependency in t.dependencies() {
+            if let Some(users) = self.users.get_mut(dependency) {
+                users.retain(|u| u != typename);
+            }
+        }
+    }
+
+    /// Drop the cached layouts of a type and of every type built on it
+    /// ## Return
+    /// How many layouts were dropped, the type's own included
+    pub(super) fn invalidate(&self, typename : &Name) -> usize {
+        let mut layouts = self.layouts.lock();
+        let mut dropped = layouts.remove(typename).is_some() as usize;
+        for dependent in self.dependents(typename) {
+            dropped += layouts.remove(&dependent).is_some() as usize;
+        }
+        dropped
+    }
+}
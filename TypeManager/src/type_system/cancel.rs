@@ -0,0 +1,134 @@
+/*
+    Cancellation: long computations, such as the optimizer trying every member
+    order of a big struct, check every so often whether they were asked to stop
+    and, if so, give back the best result found so far. Requests come from Ctrl-C
+    or from a `Canceller` used by another thread, as servers do on `cancelar`.
+    Results of a cancelled computation are never cached, they may not be the best
+*/
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Set by Ctrl-C while some computation is running, it stops every one of them
+static INTERRUPTED : AtomicBool = AtomicBool::new(false);
+
+/// Computations running in every thread, Ctrl-C ends the process when there are none
+static RUNNING : AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static CURRENT : RefCell<Option<Arc<State>>> = const { RefCell::new(None) };
+}
+
+/// Whether the computations run with a canceller should stop
+#[derive(Debug, Default)]
+struct State {
+    cancelled: AtomicBool,
+    running:   AtomicUsize
+}
+
+/// Stops the computations run with it, from any thread
+#[derive(Debug, Clone, Default)]
+pub struct Canceller {
+    state: Arc<State>
+}
+
+/// A computation in progress, it ends when dropped
+pub struct Computation {
+    state: Option<Arc<State>>   // none for computations nested in another one
+}
+
+impl Canceller {
+
+    /// Start a computation in this thread. Computations started while another one
+    /// is running are part of it and are cancelled with it
+    pub fn start(&self) -> Computation {
+        let nested = CURRENT.with(|c| c.borrow().is_some());
+        if nested {
+            return Computation { state: None }
+        }
+
+        self.state.cancelled.store(false, Ordering::SeqCst);
+        self.state.running.fetch_add(1, Ordering::SeqCst);
+        RUNNING.fetch_add(1, Ordering::SeqCst);
+        CURRENT.with(|c| *c.borrow_mut() = Some(Arc::clone(&self.state)));
+        Computation { state: Some(Arc::clone(&self.state)) }
+    }
+
+    /// Ask the computation running with this canceller to stop
+    /// ## Return
+    /// Whether there was one running
+    pub fn cancel(&self) -> bool {
+        let running = self.state.running.load(Ordering::SeqCst) > 0;
+        if running {
+            self.state.cancelled.store(true, Ordering::SeqCst);
+        }
+        running
+    }
+}
+
+impl Computation {
+
+    /// Tells if this computation is not part of another one
+    pub fn outermost(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Tells if this computation was asked to stop
+    pub fn cancelled(&self) -> bool {
+        match &self.state {
+            Some(state) => state.cancelled.load(Ordering::SeqCst) || INTERRUPTED.load(Ordering::SeqCst),
+            None => false
+        }
+    }
+}
+
+impl Drop for Computation {
+    fn drop(&mut self) {
+        if let Some(state) = self.state.take() {
+            CURRENT.with(|c| *c.borrow_mut() = None);
+            state.running.fetch_sub(1, Ordering::SeqCst);
+            if RUNNING.fetch_sub(1, Ordering::SeqCst) == 1 {
+                INTERRUPTED.store(false, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+/// Tells if the computation running in this thread was asked to stop
+pub fn requested() -> bool {
+    CURRENT.with(|c| match c.borrow().as_ref() {
+        Some(state) => state.cancelled.load(Ordering::SeqCst) || INTERRUPTED.load(Ordering::SeqCst),
+        None => false
+    })
+}
+
+/// Make Ctrl-C stop the computations running instead of the process. With none
+/// running it still ends the process, as always
+#[cfg(unix)]
+pub fn on_interrupt() {
+    const SIGINT : i32 = 2;
+    const SIG_DFL : usize = 0;
+    extern "C" {
+        fn signal(signum : i32, handler : usize) -> usize;
+        fn raise(signum : i32) -> i32;
+    }
+
+    extern "C" fn interrupted(_ : i32) {
+        match RUNNING.load(Ordering::SeqCst) {
+            // only async-signal-safe calls here: go back to the default and let it end the process
+            0 => unsafe {
+                signal(SIGINT, SIG_DFL);
+                raise(SIGINT);
+            },
+            _ => INTERRUPTED.store(true, Ordering::SeqCst)
+        }
+    }
+
+    unsafe {
+        signal(SIGINT, interrupted as extern "C" fn(i32) as usize);
+    }
+}
+
+/// Make Ctrl-C stop the computations running instead of the process, only on unix
+#[cfg(not(unix))]
+pub fn on_interrupt() {}
@@ -0,0 +1,89 @@
+/*
+    Checked layouts: adding a type makes sure every type it's built on is stored,
+    and layouts are computed trusting that. Anything taking types out has to keep
+    the promise, and if some day it doesn't, queries should answer with an error
+    naming the missing type instead of taking the whole session down. Public
+    layout queries check the type and everything under it before computing, the
+    computations themselves count a missing type as empty, an empty struct or
+    union as taking no room and an alignment of 0 as 1
+*/
+use std::collections::HashSet;
+
+use super::{Name, Strategy, Struct, Type, TypeError, TypeManager};
+
+impl TypeManager {
+
+    /// Get a stored type after checking every type it's built on, directly or through
+    /// others, is stored too
+    /// ## Params
+    /// * `typename` - type to check
+    /// ---
+    /// ## Return
+    /// The stored type, or an error naming the first missing type
+    pub fn checked(&self, typename : &Name) -> Result<&Type, TypeError> {
+        let t = self.get(typename).ok_or_else(|| TypeError::TypeDoesNotExist(typename.clone()))?;
+        self.check_layout(typename, t)?;
+
+        let mut seen : HashSet<&Name> = HashSet::new();
+        let mut pending : Vec<&Name> = t.dependencies().iter().collect();
+        while let Some(name) = pending.pop() {
+            if !seen.insert(name) {
+                continue
+            }
+            match self.get(name) {
+                Some(used) => {
+                    self.check_layout(name, used)?;
+                    pending.extend(used.dependencies())
+                },
+                None => return Err(TypeError::TypeDoesNotExist(name.clone()))
+            }
+        }
+
+        Ok(t)
+    }
+
+    /// Check a type has a layout on its own: compound types need some member, and
+    /// atomic types an alignment. Adding a type rejects these, but taking members out
+    /// of stored types could leave them behind
+    /// ## Params
+    /// * `typename` - name of the type, for errors
+    /// * `t` - type to check, its members are not looked at
+    pub fn check_layout(&self, typename : &Name, t : &Type) -> Result<(), TypeError> {
+        let empty = match t {
+            Type::Struct(s) => s.members.is_empty(),
+            Type::Union(u)  => u.variants.is_empty(),
+            Type::Enum(e)   => e.variants.is_empty(),
+            Type::Atomic(a) if a.alignment == 0 => return Err(TypeError::ZeroAlign(typename.clone())),
+            _ => false
+        };
+
+        match empty {
+            true  => Err(TypeError::NoMembers(typename.clone())),
+            false => Ok(())
+        }
+    }
+
+    /// Size of a type under a strategy
+    /// ## Return
+    /// The size, or an error if the type or a type it's built on doesn't exist
+    pub fn size(&self, typename : &Name, strategy : Strategy) -> Result<usize, TypeError> {
+        self.checked(typename)?;
+        Ok(self.size_of(typename, strategy))
+    }
+
+    /// Alignment of a type under a strategy
+    /// ## Return
+    /// The alignment, or an error if the type or a type it's built on doesn't exist
+    pub fn align(&self, typename : &Name, strategy : Strategy) -> Result<usize, TypeError> {
+        self.checked(typename)?;
+        Ok(self.align_of(typename, strategy))
+    }
+
+    /// Bytes a type wastes under a strategy compared with its members packed with no padding
+    /// ## Return
+    /// The loss, or an error if the type or a type it's built on doesn't exist
+    pub fn loss(&self, typename : &Name, strategy : Strategy) -> Result<usize, TypeError> {
+        let packed = self.checked(typename)?.size(self, Struct::packed_size);
+        Ok(self.size_of(typename, strategy).saturating_sub(packed))
+    }
+}
@@ -0,0 +1,76 @@
+/*
+    Cleaning: importing a header defines every type it uses, and most of them
+    are helpers nobody looks at. Types reachable from some roots are kept, as
+    members, variants, pointed types or container elements, and the rest are
+    removed. With no roots given every struct and union is one, so what goes is
+    whatever no compound type uses
+*/
+use std::collections::HashSet;
+
+use super::{Name, Type, TypeError, TypeList, TypeManager};
+
+impl Type {
+
+    /// Every type this type refers to: the ones it's built on, and the ones it points
+    /// to or owns, which may not be defined
+    pub fn references(&self) -> Vec<&Name> {
+        match self {
+            Type::Pointer(p) => vec![&p.target],
+            Type::Container(c) => c.element.iter().collect(),
+            t => t.dependencies().iter().collect()
+        }
+    }
+}
+
+impl TypeManager {
+
+    /// Remove every type not reachable from some roots
+    /// ## Params
+    /// * `roots` - types to keep along with every type they refer to, every struct and union if empty
+    /// ---
+    /// ## Return
+    /// Names of the removed types, sorted, or an error if some root doesn't exist
+    pub fn clean(&mut self, roots : &[Name]) -> Result<TypeList, TypeError> {
+        let mut pending : Vec<Name> = match roots.is_empty() {
+            true  => self.types.iter().filter(|(_, t)| matches!(t, Type::Struct(_) | Type::Union(_))).map(|(n, _)| n.clone()).collect(),
+            false => roots
+                        .iter()
+                        .map(|r| self.resolve(r).cloned().ok_or_else(|| TypeError::TypeDoesNotExist(r.clone())))
+                        .collect::<Result<_, _>>()?
+        };
+
+        let mut reachable = HashSet::new();
+        while let Some(name) = pending.pop() {
+            let t = match self.types.get(&name) {
+                Some(t) => t,
+                None => continue
+            };
+            for reference in t.references() {
+                if let Some(stored) = self.resolve(reference) {
+                    if !reachable.contains(stored) {
+                        pending.push(stored.clone());
+                    }
+                }
+            }
+            reachable.insert(name);
+        }
+
+        let mut removed : TypeList = self.types.keys().filter(|n| !reachable.contains(*n)).cloned().collect();
+        removed.sort();
+        for name in &removed {
+            self.invalidate(name);
+            if let Some(t) = self.types.remove(name) {
+                self.unindex_users(name, &t);
+            }
+            self.users.remove(name);
+            self.origins.remove(name);
+            self.attributes.remove(name);
+            self.budgets.remove(name);
+            if let Some(folded) = &mut self.folded {
+                folded.remove(&name.to_lowercase());
+            }
+        }
+
+        Ok(removed)
+    }
+}
@@ -0,0 +1,174 @@
+/*
+    Constraints on member order: members marked `@fijo` stay where they were
+    declared, for headers or magic numbers other code reads at a known offset,
+    and members sharing a `@grupo` label stay next to each other. Keeping the
+    first member first, for C structs cast to the type of their first member, is
+    asked for apart: with `@conservar_primero` on a struct or for every struct
+    with `TypeManager::set_keep_first`. Every search
+    (optimizer, annealing, exact solver and Pareto front) only takes orders keeping
+    them, falling back to declaration order when no order keeps them all, so
+    layouts come with a report telling which constraints were kept
+*/
+use super::{Name, Strategy, Struct, Type, TypeError, TypeManager, GROUP};
+use crate::utils;
+
+/// Attribute keeping a struct member in its declared position and offset: `@fijo`
+pub const PINNED : &str = "fijo";
+
+/// Struct attribute keeping its first declared member at offset 0: `@conservar_primero`
+pub const KEEP_FIRST : &str = "conservar_primero";
+
+/// A constraint on member order and whether some order keeps it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constraint {
+    First  { member: usize, kept: bool },                                   // first member at offset 0
+    Pinned { member: usize, position: usize, offset: usize, kept: bool },   // declared position and offset
+    Group  { label: String, members: Vec<usize>, kept: bool }
+}
+
+impl Constraint {
+
+    /// Tells if the order this constraint was checked against keeps it
+    pub fn kept(&self) -> bool {
+        match self {
+            Constraint::First { kept, .. } | Constraint::Pinned { kept, .. } | Constraint::Group { kept, .. } => *kept
+        }
+    }
+}
+
+impl TypeManager {
+
+    /// Make the optimizer keep the first declared member of every struct at offset 0,
+    /// reordering only the rest
+    pub fn set_keep_first(&mut self, keep_first : bool) {
+        self.keep_first = keep_first;
+        self.layout_cache().clear();
+    }
+
+    /// Tells if the optimizer keeps the first declared member of every struct first
+    pub fn keeps_first(&self) -> bool {
+        self.keep_first
+    }
+
+    /// Check the constraints on a struct's member order against the order picked by the optimizer
+    /// ## Params
+    /// * `typename` - struct to check
+    /// ---
+    /// ## Return
+    /// Every constraint of the struct, or an error if the type is not a struct
+    pub fn constraints(&self, typename : &Name) -> Result<Vec<Constraint>, TypeError> {
+        match self.get(typename) {
+            None => Err(TypeError::TypeDoesNotExist(typename.clone())),
+            Some(Type::Struct(s)) => {
+                let order : Vec<usize> = s.layout(self, Strategy::Optimized).iter().map(|m| m.index).collect();
+                Ok(s.constraints(self, &order))
+            },
+            Some(_) => Err(TypeError::NotAStruct(typename.clone()))
+        }
+    }
+}
+
+impl Struct {
+
+    /// Check the constraints on member order against an order
+    /// ## Params
+    /// * `manager` - manager to retrieve member types from
+    /// * `order` - member indices, in memory order
+    /// ---
+    /// ## Return
+    /// The first member kept first, pinned members, then groups in the order their first member was declared
+    pub fn constraints(&self, manager : &TypeManager, order : &[usize]) -> Vec<Constraint> {
+        let members = self.member_sizes(manager);
+        let pins = self.pins(manager, &members);
+        let (offsets, _) = Struct::place_offsets(order, &members, &pins);
+
+        let mut constraints = vec![];
+        if self.keeps_first(manager) && !members.is_empty() {
+            constraints.push(Constraint::First { member: 0, kept: order.first() == Some(&0) });
+        }
+        for (member, pin) in pins.iter().enumerate() {
+            if let (Some(offset), true) = (pin, self.fields[member].attributes.iter().any(|a| a.key == PINNED)) {
+                let position = order.iter().position(|i| *i == member);
+                constraints.push(Constraint::Pinned {
+                    member,
+                    position: member,
+                    offset: *offset,
+                    kept: position == Some(member) && position.map(|p| offsets[p]) == Some(*offset)
+                });
+            }
+        }
+
+        let groups : Vec<Option<&str>> = self.fields.iter().map(|f| f.attribute(GROUP)).collect();
+        let mut labels : Vec<&str> = vec![];
+        for label in groups.iter().flatten() {
+            if labels.contains(label) {
+                continue
+            }
+            labels.push(label);
+
+            let members : Vec<usize> = (0..groups.len()).filter(|i| groups[*i] == Some(*label)).collect();
+            let positions : Vec<usize> = order.iter().enumerate().filter(|(_, i)| members.contains(i)).map(|(p, _)| p).collect();
+            constraints.push(Constraint::Group {
+                label: label.to_string(),
+                kept: positions.last().zip(positions.first()).is_some_and(|(last, first)| last - first + 1 == members.len()),
+                members
+            });
+        }
+
+        constraints
+    }
+
+    /// Size and alignment of every member once optimized, they don't depend on order
+    pub(super) fn member_sizes(&self, manager : &TypeManager) -> Vec<(usize, usize)> {
+        self.members
+            .iter()
+            .map(|m| (manager.size_of(m, Strategy::Optimized), manager.align_of(m, Strategy::Optimized)))
+            .collect()
+    }
+
+    /// Tells if the optimizer keeps the first declared member of this struct first
+    pub fn keeps_first(&self, manager : &TypeManager) -> bool {
+        manager.keeps_first() || self.attributes.iter().any(|a| a.key == KEEP_FIRST)
+    }
+
+    /// Declared offset of every member that can't move, by index: the ones marked `@fijo`
+    /// and the first one if it's kept first
+    /// ## Params
+    /// * `manager` - manager telling if first members are kept first
+    /// * `members` - size and alignment for every member
+    pub(super) fn pins(&self, manager : &TypeManager, members : &[(usize, usize)]) -> Vec<Option<usize>> {
+        let keep_first = self.keeps_first(manager);
+        let mut offset = 0;
+        let mut pins = Vec::with_capacity(members.len());
+        for (i, (field, (size, align))) in self.fields.iter().zip(members).enumerate() {
+            offset = utils::round_up(offset, *align);
+            let pinned = (keep_first && i == 0) || field.attributes.iter().any(|a| a.key == PINNED);
+            pins.push(pinned.then_some(offset));
+            offset = offset.saturating_add(*size);
+        }
+
+        pins
+    }
+
+    /// Offset of every member placed in some order, pinned members go to their declared
+    /// offset with padding before them if the members before end earlier
+    /// ## Params
+    /// * `order` - member indices, in memory order
+    /// * `members` - size and alignment for every member
+    /// * `pins` - declared offset of every pinned member
+    /// ---
+    /// ## Return
+    /// Offsets in memory order and the end of the last member
+    pub(super) fn place_offsets(order : &[usize], members : &[(usize, usize)], pins : &[Option<usize>]) -> (Vec<usize>, usize) {
+        let mut offsets = Vec::with_capacity(order.len());
+        let mut position = 0;
+        for i in order {
+            let (size, align) = members[*i];
+            position = utils::round_up(position, align).max(pins[*i].unwrap_or(0));
+            offsets.push(position);
+            position = position.saturating_add(size);
+        }
+
+        (offsets, position)
+    }
+}
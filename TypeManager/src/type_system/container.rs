@@ -0,0 +1,148 @@
+/*
+    Dynamic containers: handles such as `Vec`, `String` or `Box` take a few words
+    inside a struct (a pointer, and a length and capacity for growable ones),
+    while what they own lives on the heap. Both are reported separately
+*/
+use std::collections::HashSet;
+
+use super::{Name, Strategy, Type, TypeManager};
+use crate::utils;
+
+/// Default width of length and capacity words, in bytes
+pub const DEFAULT_WIDTH : usize = 8;
+
+/// Kind of handle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerKind {
+    Vec,        // pointer, length and capacity, owns `capacity` elements
+    String,     // pointer, length and capacity, owns `capacity` bytes
+    Box         // pointer, owns a single element
+}
+
+/// Container type structure
+#[derive(Debug, Clone, PartialEq)]
+pub struct Container {
+    pub kind:     ContainerKind,
+    pub element:  Option<Name>,     // owned type, none for strings. It may be defined later
+    pub capacity: usize,            // owned elements for vectors, owned bytes for strings
+    pub width:    usize             // size of length and capacity words
+}
+
+impl ContainerKind {
+
+    /// Parse a container kind, in English or Spanish: `vec`, `cadena`, `caja`...
+    pub fn parse(name : &str) -> Option<ContainerKind> {
+        match name.to_lowercase().as_str() {
+            "vec" | "vector"   => Some(ContainerKind::Vec),
+            "string" | "cadena" => Some(ContainerKind::String),
+            "box" | "caja"     => Some(ContainerKind::Box),
+            _ => None
+        }
+    }
+
+    /// Name for this kind, as parsed by `parse`
+    pub fn name(self) -> &'static str {
+        match self {
+            ContainerKind::Vec    => "vec",
+            ContainerKind::String => "string",
+            ContainerKind::Box    => "box"
+        }
+    }
+
+    /// Tells if this kind stores a length and a capacity next to the pointer
+    pub fn growable(self) -> bool {
+        !matches!(self, ContainerKind::Box)
+    }
+}
+
+impl Container {
+
+    /// Create a container handle
+    pub fn new(kind : ContainerKind, element : Option<Name>, capacity : usize, width : usize) -> Container {
+        Container {
+            kind,
+            element,
+            capacity,
+            width
+        }
+    }
+
+    /// Bytes taken by the handle itself, inside whatever holds it
+    /// ## Params
+    /// * `pointer_size` - size of an address in the target machine
+    pub fn inline_size(&self, pointer_size : usize) -> usize {
+        let words = match self.kind.growable() {
+            true  => pointer_size.saturating_add(self.width.saturating_mul(2)),
+            false => pointer_size
+        };
+
+        utils::round_up(words, self.align(pointer_size))
+    }
+
+    /// Alignment of the handle
+    pub fn align(&self, pointer_size : usize) -> usize {
+        match self.kind.growable() {
+            true  => pointer_size.max(self.width),
+            false => pointer_size
+        }
+    }
+}
+
+impl TypeManager {
+
+    /// Bytes owned on the heap by a value of some type, counting the heap bytes
+    /// of owned values too. Types not defined yet own nothing
+    /// ## Params
+    /// * `typename` - type to check
+    /// ---
+    /// ## Return
+    /// Heap bytes under the C strategy, saturating at `usize::MAX`
+    pub fn heap_size(&self, typename : &Name) -> usize {
+        self.heap_size_visiting(typename, &mut HashSet::new())
+    }
+
+    /// Helper function for `heap_size`, a type already being visited owns itself recursively
+    /// (such as a tree node with a box to another node) and is counted once
+    fn heap_size_visiting(&self, typename : &Name, visiting : &mut HashSet<Name>) -> usize {
+        let t = match self.get(typename) {
+            Some(t) => t,
+            None => return 0
+        };
+        if !visiting.insert(typename.clone()) {
+            return 0
+        }
+
+        let heap = match t {
+            Type::Struct(s) => s.members.iter().fold(0usize, |total, m| total.saturating_add(self.heap_size_visiting(m, visiting))),
+            Type::Union(u)  => u.variants.iter().map(|v| self.heap_size_visiting(v, visiting)).max().unwrap_or(0),
+            Type::Container(c) => self.container_heap(c, visiting),
+            _ => 0
+        };
+
+        visiting.remove(typename);
+        heap
+    }
+
+    /// Bytes owned on the heap by a container handle, counting the heap bytes of its elements
+    pub fn owned_bytes(&self, c : &Container) -> usize {
+        self.container_heap(c, &mut HashSet::new())
+    }
+
+    /// Helper function for `owned_bytes` and `heap_size`
+    fn container_heap(&self, c : &Container, visiting : &mut HashSet<Name>) -> usize {
+        let (count, element) = match (c.kind, &c.element) {
+            (ContainerKind::String, _) => (c.capacity, None),
+            (ContainerKind::Vec, Some(e)) => (c.capacity, Some(e)),
+            (ContainerKind::Box, Some(e)) => (1, Some(e)),
+            (_, None) => (0, None)
+        };
+
+        let each = match element.and_then(|e| self.get(e).map(|t| (e, t))) {
+            Some((name, t)) => Strategy::C.type_size(t, self).saturating_add(self.heap_size_visiting(name, visiting)),
+            None if element.is_some() => 0,     // not defined yet
+            None => 1                           // string bytes
+        };
+
+        count.saturating_mul(each)
+    }
+}
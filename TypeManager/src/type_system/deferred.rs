@@ -0,0 +1,104 @@
+/*
+    Deferred resolution: definitions pasted from real code use types defined
+    further down. With deferred resolution on, a struct or union using types
+    that don't exist yet is kept apart as pending instead of being rejected,
+    and it's checked and added once every type it uses is there, when asked
+    to resolve pending types. Layout queries only see resolved types, so
+    callers resolve before asking, as the driver does before every query
+*/
+use super::{Diagnostic, Name, Type, TypeError, TypeManager};
+
+/// A pending type and the types it uses that don't exist yet
+pub type Unresolved = (Name, Vec<Name>);
+
+/// What came out of resolving pending types
+#[derive(Debug, Default, PartialEq)]
+pub struct Resolution {
+    pub resolved: Vec<(Name, Vec<Diagnostic>)>,     // types added, with their diagnostics
+    pub failed:   Vec<(Name, TypeError)>,           // types dropped for being invalid
+    pub pending:  Vec<Unresolved>                   // types still missing something
+}
+
+impl TypeManager {
+
+    /// Keep structs and unions using types that don't exist yet as pending instead
+    /// of rejecting them, see `resolve_pending`
+    pub fn set_deferred(&mut self, deferred : bool) {
+        self.deferred = deferred;
+    }
+
+    /// Tells if structs and unions using types that don't exist yet are kept as pending
+    pub fn defers(&self) -> bool {
+        self.deferred
+    }
+
+    /// Tells if some type is waiting for the types it uses
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Every pending type with the types it still misses, in the order they were defined
+    pub fn pending(&self) -> Vec<Unresolved> {
+        self.pending.iter().map(|(name, t)| (name.clone(), self.missing(t))).collect()
+    }
+
+    /// Definition of a pending type
+    pub fn pending_type(&self, typename : &Name) -> Option<&Type> {
+        self.pending.iter().find(|(name, _)| name == typename).map(|(_, t)| t)
+    }
+
+    /// Add every pending type whose members exist now, over and over, since a type
+    /// added may be the one others were waiting for
+    /// ## Return
+    /// Types added, types dropped for being invalid and types still missing something
+    pub fn resolve_pending(&mut self) -> Resolution {
+        let mut resolution = Resolution::default();
+        loop {
+            let ready = self.pending.iter().position(|(_, t)| self.missing(t).is_empty());
+            let (name, t) = match ready {
+                Some(i) => self.pending.remove(i),
+                None => break
+            };
+            match self.add(name.clone(), t) {
+                Ok(diagnostics) => resolution.resolved.push((name, diagnostics)),
+                Err(e) => {
+                    self.origins.remove(&name);
+                    resolution.failed.push((name, e))
+                }
+            }
+        }
+
+        resolution.pending = self.pending();
+        resolution
+    }
+
+    /// Keep a type as pending if it uses types that don't exist yet and types are deferred
+    /// ## Return
+    /// The types it misses if it was kept, none if it has to be added now
+    pub(super) fn defer(&mut self, typename : &Name, t : &Type) -> Option<Vec<Name>> {
+        if !self.deferred || self.resolve(typename).is_some() || self.pending_type(typename).is_some() {
+            return None
+        }
+
+        let missing = self.missing(t);
+        match missing.is_empty() {
+            true  => None,
+            false => {
+                self.pending.push((typename.clone(), t.clone()));
+                Some(missing)
+            }
+        }
+    }
+
+    /// Types a type is built on that don't exist, in the order it uses them
+    fn missing(&self, t : &Type) -> Vec<Name> {
+        let mut missing : Vec<Name> = vec![];
+        for dependency in t.dependencies() {
+            if self.resolve(dependency).is_none() && !missing.contains(dependency) {
+                missing.push(dependency.clone());
+            }
+        }
+
+        missing
+    }
+}
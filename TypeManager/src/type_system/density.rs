@@ -0,0 +1,53 @@
+/*
+    Density: how many instances of a type fit in a cache line or a memory page
+    when stored one after another in an array, and how many bytes are left over
+*/
+use super::{Name, Strategy, TypeError, TypeManager};
+use crate::utils;
+
+/// Page size used when none is given, in bytes
+pub const DEFAULT_PAGE : usize = 4096;
+
+/// Whole instances fitting in a block of memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fit {
+    pub block: usize,   // block size, in bytes
+    pub count: usize,   // whole instances in the block
+    pub waste: usize    // bytes after the last whole instance
+}
+
+/// Density of an array of some type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Density {
+    pub stride: usize,  // distance between consecutive instances
+    pub line:   Fit,
+    pub page:   Fit
+}
+
+impl Fit {
+
+    /// Fit instances with the given stride in a block
+    pub fn new(block : usize, stride : usize) -> Fit {
+        let count = block / stride.max(1);
+        Fit { block, count, waste: block - count * stride }
+    }
+}
+
+impl TypeManager {
+
+    /// Compute how many instances of a type fit in a cache line and in a page
+    /// ## Params
+    /// * `typename` - type stored in the array
+    /// * `strategy` - how structs are laid out
+    /// * `line` - cache line size, in bytes
+    /// * `page` - page size, in bytes
+    /// ---
+    /// ## Return
+    /// Stride and fit in both blocks, or an error if the type does not exist
+    pub fn density(&self, typename : &Name, strategy : Strategy, line : usize, page : usize) -> Result<Density, TypeError> {
+        let t = self.checked(typename)?;
+        let stride = utils::round_up(strategy.type_size(t, self), strategy.type_align(t, self));
+
+        Ok(Density { stride, line: Fit::new(line, stride), page: Fit::new(page, stride) })
+    }
+}
@@ -0,0 +1,119 @@
+/*
+    Diagnostics: things worth knowing about a new type that don't make it
+    invalid, such as odd alignments or structs that are mostly padding
+*/
+use super::{Atomic, Name, Struct, Type, TypeManager};
+
+/// Amount of members from which the optimizer has too many orders to try
+pub const LARGE_PERMUTATIONS : usize = 9;
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,       // just so the user knows
+    Warning     // probably a mistake, an error in strict mode
+}
+
+/// Something worth knowing about a type
+#[derive(Debug, Clone, PartialEq)]
+pub enum Diagnostic {
+    AlignAboveSize { align: usize, size: usize },
+    AlignNotPowerOfTwo(usize),
+    MostlyPadding { padding: usize, size: usize },     // under the C strategy
+    LargePermutationSpace(usize),                       // amount of members
+    Shadowed(Name),                                     // name the type with the same name is kept as
+    Deferred(Vec<Name>)                                 // types it uses that don't exist yet
+}
+
+impl Diagnostic {
+
+    /// How serious this diagnostic is
+    pub fn level(&self) -> Level {
+        match self {
+            Diagnostic::LargePermutationSpace(_) | Diagnostic::Deferred(_) => Level::Info,
+            _ => Level::Warning
+        }
+    }
+
+    /// Get human readable description for this diagnostic
+    pub fn display(&self) -> String {
+        match self {
+            Diagnostic::AlignAboveSize { align, size } => {
+                format!("La alineación ({}) es mayor que el tamaño ({})", align, size)
+            },
+            Diagnostic::AlignNotPowerOfTwo(align) => {
+                format!("La alineación ({}) no es una potencia de dos", align)
+            },
+            Diagnostic::MostlyPadding { padding, size } => {
+                format!("Más de la mitad del struct es relleno ({} de {} bytes con la estrategia C)", padding, size)
+            },
+            Diagnostic::LargePermutationSpace(n) => {
+                format!("El struct tiene {} miembros, optimizarlo prueba {}! órdenes y puede tardar, Ctrl-C lo detiene con el mejor orden hasta entonces", n, n)
+            },
+            Diagnostic::Deferred(missing) => {
+                format!("Usa tipos que aún no existen ({}), queda pendiente hasta que se definan", missing.join(", "))
+            },
+            Diagnostic::Shadowed(previous) => {
+                format!("Ya había un tipo con este nombre, la definición anterior sigue como '{}'", previous)
+            }
+        }
+    }
+
+    /// Description with a prefix for its level, to show it to the user
+    pub fn display_with_level(&self) -> String {
+        match self.level() {
+            Level::Info    => format!("[INFO]: {}", self.display()),
+            Level::Warning => format!("[AVISO]: {}", self.display())
+        }
+    }
+}
+
+impl TypeManager {
+
+    /// Check a type for things that are valid but probably not intended.
+    /// The type doesn't need to be stored, but the types it uses do
+    /// ## Params
+    /// * `t` - type to check
+    /// ---
+    /// ## Return
+    /// Every diagnostic found, in no particular order
+    pub fn diagnose(&self, t : &Type) -> Vec<Diagnostic> {
+        match t {
+            Type::Atomic(a) => diagnose_atomic(a),
+            Type::Struct(s) => self.diagnose_struct(s),
+            _ => vec![]
+        }
+    }
+
+    /// Diagnostics for a struct whose members are stored
+    fn diagnose_struct(&self, s : &Struct) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+
+        let size = s.c_size(self);
+        let padding = size.saturating_sub(s.packed_size(self));
+        if padding * 2 > size {
+            diagnostics.push(Diagnostic::MostlyPadding { padding, size });
+        }
+
+        if s.members.len() >= LARGE_PERMUTATIONS {
+            diagnostics.push(Diagnostic::LargePermutationSpace(s.members.len()));
+        }
+
+        diagnostics
+    }
+}
+
+/// Diagnostics for an atomic type
+fn diagnose_atomic(a : &Atomic) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+
+    if a.alignment > a.representation {
+        diagnostics.push(Diagnostic::AlignAboveSize { align: a.alignment, size: a.representation });
+    }
+
+    if !a.alignment.is_power_of_two() {
+        diagnostics.push(Diagnostic::AlignNotPowerOfTwo(a.alignment));
+    }
+
+    diagnostics
+}
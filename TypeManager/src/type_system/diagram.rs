@@ -0,0 +1,86 @@
+/*
+    Diagrams: the bytes of a struct laid out in rows, as drawn on slides. Every
+    byte belongs to a member or is padding, and consecutive bytes of the same
+    member or padding hole make a span. The geometry only says which bytes go
+    where, backends such as `image` decide how they look
+*/
+use super::{Name, Strategy, Type, TypeError, TypeManager};
+
+/// Bytes in every row of a diagram
+pub const DIAGRAM_ROW : usize = 8;
+
+/// Largest struct drawn, in bytes
+pub const MAX_DIAGRAM : usize = 4096;
+
+/// Consecutive bytes of a member or of padding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub offset: usize,
+    pub size:   usize,
+    pub member: Option<usize>,  // position of the member in memory order, none for padding
+    pub label:  String          // field name, empty for padding
+}
+
+/// Bytes of a struct in rows, see `TypeManager::diagram`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagram {
+    pub name:  Name,
+    pub size:  usize,
+    pub row:   usize,       // bytes per row
+    pub spans: Vec<Span>    // covering every byte once, by offset
+}
+
+impl Diagram {
+
+    /// Rows needed to draw every byte, at least one
+    pub fn rows(&self) -> usize {
+        self.size.div_ceil(self.row).max(1)
+    }
+}
+
+impl TypeManager {
+
+    /// Lay out the bytes of a struct for drawing
+    /// ## Params
+    /// * `typename` - struct to draw
+    /// * `strategy` - how it's laid out
+    /// ---
+    /// ## Return
+    /// Spans of members and padding covering every byte, or an error if the type doesn't
+    /// exist, is not a struct or is too big to draw
+    pub fn diagram(&self, typename : &Name, strategy : Strategy) -> Result<Diagram, TypeError> {
+        let t = self.checked(typename)?;
+        let s = match t {
+            Type::Struct(s) => s,
+            _ => return Err(TypeError::NotAStruct(typename.clone()))
+        };
+        let size = strategy.type_size(t, self);
+        if size > MAX_DIAGRAM {
+            return Err(TypeError::TooLarge(typename.clone(), size, MAX_DIAGRAM))
+        }
+
+        let mut layout = s.layout(self, strategy);
+        layout.sort_by_key(|m| m.offset);
+
+        // bit fields may share bytes, each byte goes to the first member using it
+        let mut spans = vec![];
+        let mut covered = 0;
+        for (position, m) in layout.iter().enumerate() {
+            let end = (m.offset + m.size).min(size);
+            if end <= covered {
+                continue
+            }
+            if m.offset > covered {
+                spans.push(Span { offset: covered, size: m.offset - covered, member: None, label: String::new() });
+            }
+            let start = m.offset.max(covered);
+            spans.push(Span { offset: start, size: end - start, member: Some(position), label: s.fields[m.index].name.clone() });
+            covered = end;
+        }
+        if covered < size {
+            spans.push(Span { offset: covered, size: size - covered, member: None, label: String::new() });
+        }
+
+        Ok(Diagram { name: typename.clone(), size, row: DIAGRAM_ROW, spans })
+    }
+}
@@ -0,0 +1,112 @@
+/*
+    Differences between the types of two managers, such as two sessions loaded
+    from two versions of a header: types added, removed, and types whose layout
+    changed, down to the offset of every struct field under the C strategy
+*/
+use super::{Name, Strategy, Type, TypeManager};
+
+/// How a type differs from one manager to another
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeChange {
+    Added(Name),
+    Removed(Name),
+    Modified(TypeDiff)
+}
+
+/// Layout of a type in both managers, sizes, alignments and padding are `(before, after)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDiff {
+    pub name:       Name,
+    pub size:       (usize, usize),
+    pub align:      (usize, usize),
+    pub padding:    (usize, usize),     // bytes a struct wastes compared to packing it
+    pub fields:     Vec<FieldChange>,   // struct fields that moved, appeared or disappeared
+    pub definition: bool                // whether it's defined otherwise, its layout may be the same
+}
+
+/// How a struct field differs, found by name
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldChange {
+    Moved(Name, usize, usize),  // field, offset before, offset after
+    Added(Name, usize),         // field, offset
+    Removed(Name, usize)        // field, offset
+}
+
+impl TypeDiff {
+
+    /// Tells if the type takes memory in another way
+    pub fn layout_changed(&self) -> bool {
+        self.size.0 != self.size.1 || self.align.0 != self.align.1 || !self.fields.is_empty()
+    }
+}
+
+/// Compare every type in two managers, by name
+/// ## Params
+/// * `before` - manager with the old types
+/// * `after` - manager with the new types
+/// ---
+/// ## Return
+/// A change for every type added, removed or modified, sorted by name
+pub fn diff(before : &TypeManager, after : &TypeManager) -> Vec<TypeChange> {
+    let mut names : Vec<&Name> = before.names().chain(after.names()).collect();
+    names.sort();
+    names.dedup();
+
+    names
+        .into_iter()
+        .filter_map(|name| match (before.get(name), after.get(name)) {
+            (Some(_), None) => Some(TypeChange::Removed(name.clone())),
+            (None, Some(_)) => Some(TypeChange::Added(name.clone())),
+            (Some(a), Some(b)) => {
+                let change = TypeDiff {
+                    name:       name.clone(),
+                    size:       (Strategy::C.type_size(a, before), Strategy::C.type_size(b, after)),
+                    align:      (Strategy::C.type_align(a, before), Strategy::C.type_align(b, after)),
+                    padding:    (padding(a, before), padding(b, after)),
+                    fields:     field_changes(before, a, after, b),
+                    definition: a != b
+                };
+                match change.definition || change.layout_changed() {
+                    true  => Some(TypeChange::Modified(change)),
+                    false => None
+                }
+            },
+            (None, None) => None
+        })
+        .collect()
+}
+
+/// Bytes of padding in a struct under the C strategy, none for other types
+fn padding(t : &Type, manager : &TypeManager) -> usize {
+    match t {
+        Type::Struct(s) => Strategy::C.type_size(t, manager).saturating_sub(s.packed_size(manager)),
+        _ => 0
+    }
+}
+
+/// Fields of two structs whose offset is not the same, in the order of the new struct
+/// and then the removed ones
+fn field_changes(before : &TypeManager, a : &Type, after : &TypeManager, b : &Type) -> Vec<FieldChange> {
+    let (a, b) = match (a, b) {
+        (Type::Struct(a), Type::Struct(b)) => (a, b),
+        _ => return vec![]
+    };
+    let old : Vec<(&Name, usize)> = a.fields.iter().map(|f| &f.name).zip(a.c_offsets(before)).collect();
+    let new : Vec<(&Name, usize)> = b.fields.iter().map(|f| &f.name).zip(b.c_offsets(after)).collect();
+
+    let mut changes = vec![];
+    for (name, offset) in &new {
+        match old.iter().find(|(n, _)| n == name) {
+            None => changes.push(FieldChange::Added((*name).clone(), *offset)),
+            Some((_, from)) if from != offset => changes.push(FieldChange::Moved((*name).clone(), *from, *offset)),
+            Some(_) => {}
+        }
+    }
+    for (name, offset) in &old {
+        if !new.iter().any(|(n, _)| n == name) {
+            changes.push(FieldChange::Removed((*name).clone(), *offset));
+        }
+    }
+
+    changes
+}
@@ -0,0 +1,78 @@
+/*
+    Enums: a list of variant names stored as an integer. C compilers use an
+    `int` for them, but many embedded targets (or `__attribute__((packed))`)
+    use the smallest integer fitting every variant, which changes struct padding
+*/
+use super::Name;
+
+/// Size of `int`, what most ABIs use for enums
+pub const INT_SIZE : usize = 4;
+
+/// Which integer holds an enum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnumRepr {
+    Int,        // as big as `int`, or bigger if variants don't fit
+    Minimal     // smallest integer of 1, 2, 4 or 8 bytes fitting every variant
+}
+
+/// Enum type structure
+#[derive(Debug, Clone, PartialEq)]
+pub struct Enum {
+    pub variants: Vec<Name>,
+    pub repr:     EnumRepr
+}
+
+impl EnumRepr {
+
+    /// Parse a representation name: `int` or `minimo`
+    pub fn parse(name : &str) -> Option<EnumRepr> {
+        match name.to_lowercase().as_str() {
+            "int"              => Some(EnumRepr::Int),
+            "minimo" | "mínimo" => Some(EnumRepr::Minimal),
+            _ => None
+        }
+    }
+
+    /// Name for this representation, as parsed by `parse`
+    pub fn name(self) -> &'static str {
+        match self {
+            EnumRepr::Int     => "int",
+            EnumRepr::Minimal => "minimo"
+        }
+    }
+}
+
+impl Enum {
+
+    /// Create an enum with the given variants
+    pub fn new(variants : Vec<Name>, repr : EnumRepr) -> Enum {
+        Enum {
+            variants,
+            repr
+        }
+    }
+
+    /// Smallest integer size, in bytes, with a value for every variant
+    pub fn minimal_size(&self) -> usize {
+        let values = self.variants.len() as u128;
+        [1, 2, 4, 8].iter().copied().find(|bytes| values <= 1 << (8 * bytes)).unwrap_or(8)
+    }
+
+    /// Size used by C compilers following the ABI, in bytes
+    pub fn int_size(&self) -> usize {
+        self.minimal_size().max(INT_SIZE)
+    }
+
+    /// Size under the chosen representation, enums are aligned to their size
+    pub fn size(&self) -> usize {
+        match self.repr {
+            EnumRepr::Int     => self.int_size(),
+            EnumRepr::Minimal => self.minimal_size()
+        }
+    }
+
+    /// Alignment under the chosen representation
+    pub fn align(&self) -> usize {
+        self.size()
+    }
+}
@@ -0,0 +1,152 @@
+/*
+    Flag packing: 1 byte members used as booleans waste 7 bits each, several of
+    them fit in a single bitset member. Members are flags if their type is a
+    boolean, by name or by value kind, or if they're marked `@bandera`; `@bits=N`
+    gives the bits a flag uses
+*/
+use super::{Atomic, Field, Name, Strategy, Struct, Type, TypeError, TypeList, TypeManager, ValueKind};
+use crate::utils;
+
+/// Attribute for members used as flags
+pub const FLAG : &str = "bandera";
+
+/// Attribute for the bits a flag needs, 1 if not given
+pub const BITS : &str = "bits";
+
+/// Types taken as flags without marking them
+pub const BOOLEANS : [&str; 3] = ["bool", "booleano", "_Bool"];
+
+/// Name of the member holding every flag in the packed struct
+pub const BITSET_FIELD : &str = "banderas";
+
+/// A flag member and the bits it needs once packed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bitfield {
+    pub index: usize,   // member position in the struct
+    pub bits:  usize
+}
+
+/// Layout projection for a struct with its flags packed in a bitset
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlagReport {
+    pub flags:       Vec<Bitfield>,
+    pub size:        usize,     // current size under the C strategy
+    pub packed_size: usize      // size with the bitset where the first flag is, under the C strategy
+}
+
+impl FlagReport {
+
+    /// Bits used by every flag
+    pub fn bits(&self) -> usize {
+        self.flags.iter().map(|f| f.bits).sum()
+    }
+
+    /// Bytes taken by the bitset
+    pub fn bytes(&self) -> usize {
+        utils::round_up(self.bits(), 8) / 8
+    }
+
+    /// Bytes saved by packing, if any
+    pub fn savings(&self) -> usize {
+        self.size.saturating_sub(self.packed_size)
+    }
+
+    /// Tells if packing is worth it: there are two flags or more and it makes the struct smaller
+    pub fn worth_it(&self) -> bool {
+        self.flags.len() >= 2 && self.savings() > 0
+    }
+}
+
+/// Name for the atomic type holding some bytes of flags, such as `bits16`
+pub fn bitset_name(bytes : usize) -> Name {
+    format!("bits{}", bytes * 8)
+}
+
+impl TypeManager {
+
+    /// Find the flags of a struct and project its size with them packed in a bitset
+    /// ## Params
+    /// * `typename` - struct to check
+    /// ---
+    /// ## Return
+    /// Flags found and sizes before and after packing them, or an error if the type is not a struct
+    pub fn flag_packing(&self, typename : &Name) -> Result<FlagReport, TypeError> {
+        let s = match self.checked(typename)? {
+            Type::Struct(s) => s,
+            _ => return Err(TypeError::NotAStruct(typename.clone()))
+        };
+
+        // members as (size, alignment) under the C strategy
+        let members : Vec<(usize, usize)> = s.members
+                                                .iter()
+                                                .map(|m| (self.size_of(m, Strategy::C), self.align_of(m, Strategy::C)))
+                                                .collect();
+
+        let flags : Vec<Bitfield> = s.fields
+                                        .iter()
+                                        .enumerate()
+                                        .filter(|(i, field)| members[*i] == (1, 1) && self.is_flag(&s.members[*i], field))
+                                        .map(|(index, field)| Bitfield { index, bits: flag_bits(field) })
+                                        .collect();
+
+        let report = FlagReport { flags, size: s.c_size(self), packed_size: 0 };
+        let bitset = (report.bytes(), 1);
+        let packed : Vec<(usize, usize)> = (0..members.len())
+                                            .filter_map(|i| match report.flags.iter().position(|f| f.index == i) {
+                                                None    => Some(members[i]),
+                                                Some(0) => Some(bitset),
+                                                Some(_) => None
+                                            })
+                                            .collect();
+
+        Ok(FlagReport { packed_size: utils::c_size(&packed), ..report })
+    }
+
+    /// Rewrite a struct with its flags packed, the bitset takes the place of the first flag
+    /// ## Params
+    /// * `typename` - struct to rewrite
+    /// * `report` - its flags, as found by `flag_packing`
+    /// ---
+    /// ## Return
+    /// Name and layout of the bitset type, and the rewritten struct using it
+    pub fn pack_flags(&self, typename : &Name, report : &FlagReport) -> Result<(Name, Atomic, Struct), TypeError> {
+        let s = match self.checked(typename)? {
+            Type::Struct(s) => s,
+            _ => return Err(TypeError::NotAStruct(typename.clone()))
+        };
+
+        let bitset = bitset_name(report.bytes());
+        let mut members = TypeList::new();
+        let mut fields = vec![];
+        for (i, (member, field)) in s.members.iter().zip(&s.fields).enumerate() {
+            match report.flags.iter().position(|f| f.index == i) {
+                None => {
+                    members.push(member.clone());
+                    fields.push(field.clone());
+                },
+                Some(0) => {
+                    members.push(bitset.clone());
+                    fields.push(Field::new(BITSET_FIELD.to_string()));
+                },
+                Some(_) => {}
+            }
+        }
+
+        Ok((bitset, Atomic::new(report.bytes(), 1), Struct::with_fields(members, fields)))
+    }
+
+    /// Tells if a member is used as a flag
+    fn is_flag(&self, member : &Name, field : &Field) -> bool {
+        let stored = self.resolve(member).unwrap_or(member);
+        let boolean = matches!(self.types.get(stored), Some(Type::Atomic(a)) if a.kind == Some(ValueKind::Bool));
+        field.attributes.iter().any(|a| a.key == FLAG || a.key == BITS) || boolean || BOOLEANS.contains(&stored.as_str())
+    }
+}
+
+/// Bits needed by a flag, from its `@bits` attribute
+fn flag_bits(field : &Field) -> usize {
+    match field.bit_width() {
+        Some(bits) if (1..=8).contains(&bits) => bits,
+        _ => 1
+    }
+}
@@ -0,0 +1,113 @@
+/*
+    Flattening: a struct made of the leaf fields of a struct, the members of
+    every nested struct taken out of it and named by their path, such as
+    `cabecera.largo`. Leaves keep the offsets they have in the nested struct
+    under a strategy, and the flat struct is declared so a C compiler puts
+    them there: in memory order, `@aligned` on the ones after a gap the
+    natural alignment doesn't leave, and `@packed` if some leaf sits off its
+    alignment. Flat structs are what serialization tables want, and comparing
+    one with the nested struct tells what nesting costs
+*/
+use super::{Attribute, Field, Name, Strategy, Struct, Type, TypeError, TypeManager, ALIGNED, NOTE, PACKED};
+use crate::utils;
+
+/// A member that's not a struct, somewhere inside a struct
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leaf {
+    pub path:   Name,             // field names from the outermost struct, joined by dots
+    pub member: Name,             // its type
+    pub offset: usize,            // from the start of the outermost struct
+    pub size:   usize,
+    pub attributes: Vec<Attribute>   // its notes, the rest are about the struct it was in
+}
+
+impl TypeManager {
+
+    /// Every leaf of a struct under a strategy, going into nested structs
+    /// ## Params
+    /// * `typename` - a stored struct
+    /// * `strategy` - strategy laying out the struct and every struct nested in it
+    /// ---
+    /// ## Return
+    /// The leaves in memory order, or an error if the type is not a stored struct
+    pub fn leaves(&self, typename : &Name, strategy : Strategy) -> Result<Vec<Leaf>, TypeError> {
+        let s = match self.checked(typename)? {
+            Type::Struct(s) => s,
+            _ => return Err(TypeError::NotAStruct(typename.clone()))
+        };
+
+        let mut leaves = vec![];
+        self.collect_leaves(s, strategy, "", 0, &mut leaves);
+        leaves.sort_by_key(|l| l.offset);
+        Ok(leaves)
+    }
+
+    /// Helper function for `leaves`, add the leaves of a struct placed at some offset
+    fn collect_leaves(&self, s : &Struct, strategy : Strategy, prefix : &str, base : usize, leaves : &mut Vec<Leaf>) {
+        for m in s.layout(self, strategy) {
+            let field = &s.fields[m.index];
+            let member = &s.members[m.index];
+            let path = format!("{}{}", prefix, field.name);
+            match self.get(member) {
+                Some(Type::Struct(inner)) => self.collect_leaves(inner, strategy, &format!("{}.", path), base + m.offset, leaves),
+                _ => leaves.push(Leaf {
+                    path,
+                    member: self.resolve(member).unwrap_or(member).clone(),
+                    offset: base + m.offset,
+                    size: m.size,
+                    attributes: field.attributes.iter().filter(|a| NOTE.contains(&a.key.as_str())).cloned().collect()
+                })
+            }
+        }
+    }
+
+    /// Flat struct with the leaves of a struct, at the offsets they have under a strategy
+    /// when laid out as C does
+    /// ## Params
+    /// * `typename` - a stored struct
+    /// * `strategy` - strategy giving the offsets to keep
+    /// ---
+    /// ## Return
+    /// The flat struct, or an error if a leaf can't be placed at its offset: it takes
+    /// another size under C, or the gap before it is more than `@aligned` can leave
+    pub fn flatten(&self, typename : &Name, strategy : Strategy) -> Result<Struct, TypeError> {
+        let leaves = self.leaves(typename, strategy)?;
+        let packed = leaves.iter().any(|l| l.offset % self.align_of(&l.member, Strategy::C) != 0);
+
+        let (mut members, mut fields) = (vec![], vec![]);
+        let mut position = 0;
+        for leaf in leaves {
+            let unplaceable = || TypeError::Unflattenable(typename.clone(), leaf.path.clone());
+            if self.size_of(&leaf.member, Strategy::C) != leaf.size {
+                return Err(unplaceable())
+            }
+
+            let natural = match packed {
+                true  => 1,
+                false => self.align_of(&leaf.member, Strategy::C)
+            };
+            let mut attributes = leaf.attributes.clone();
+            if utils::round_up(position, natural) != leaf.offset {
+                // the smallest alignment reaching the offset, so the flat struct is as aligned as it can be
+                let mut align = natural.max(1) * 2;
+                while align <= leaf.offset && utils::round_up(position, align) < leaf.offset {
+                    align *= 2;
+                }
+                if utils::round_up(position, align) != leaf.offset {
+                    return Err(unplaceable())
+                }
+                attributes.push(Attribute { key: ALIGNED[0].to_string(), value: Some(align.to_string()) });
+            }
+            position = leaf.offset + leaf.size;
+
+            members.push(leaf.member);
+            fields.push(Field { name: leaf.path, attributes });
+        }
+
+        let flat = Struct::with_fields(members, fields);
+        Ok(match packed {
+            true  => flat.with_attributes(vec![Attribute { key: PACKED[0].to_string(), value: None }]),
+            false => flat
+        })
+    }
+}
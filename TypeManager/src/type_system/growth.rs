@@ -0,0 +1,61 @@
+/*
+    Growth: the size and alignment every type had over a session, and the edit
+    that changed them, be it a definition of the type itself, a redefinition,
+    a definition shadowing it, or any of those on a type it's built on. Tells
+    when a struct got so big after a long session or a replayed journal.
+    Layouts are the C ones. The history stays with the name, a type shadowed
+    or taken out doesn't take it along
+*/
+use super::{Name, Strategy, TypeManager};
+
+/// A layout a type took, and the edit giving it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Growth {
+    pub edit:   String,          // command as written
+    pub origin: Option<String>,  // file and line of the command, none if written at the prompt
+    pub cause:  Option<Name>,    // type edited, none if it was this one
+    pub size:   usize,
+    pub align:  usize
+}
+
+impl TypeManager {
+
+    /// Record the layout of a type just edited and of every type built on it, for the
+    /// ones whose C size or alignment is not what it was
+    /// ## Params
+    /// * `typename` - type defined or edited, nothing is recorded if it's not stored
+    /// * `edit` - command editing it
+    /// * `origin` - file and line of the command, none if written at the prompt
+    pub fn record_growth(&mut self, typename : &Name, edit : &str, origin : Option<&str>) {
+        let stored = match self.resolve(typename) {
+            Some(stored) => stored.clone(),
+            None => return
+        };
+
+        let mut edited = vec![stored.clone()];
+        edited.extend(self.dependents(&stored));
+        for name in edited {
+            let (size, align) = match (self.size(&name, Strategy::C), self.align(&name, Strategy::C)) {
+                (Ok(size), Ok(align)) => (size, align),
+                _ => continue
+            };
+            let history = self.growth.entry(name.clone()).or_default();
+            if history.last().is_some_and(|g| g.size == size && g.align == align) {
+                continue
+            }
+            history.push(Growth {
+                edit: edit.to_string(),
+                origin: origin.map(str::to_string),
+                cause: (name != stored).then(|| stored.clone()),
+                size,
+                align
+            });
+        }
+    }
+
+    /// Layouts a type took, oldest first, empty if it was never recorded
+    pub fn growth(&self, typename : &Name) -> &[Growth] {
+        let stored = self.resolve(typename).unwrap_or(typename);
+        self.growth.get(stored).map(Vec::as_slice).unwrap_or_default()
+    }
+}
@@ -0,0 +1,99 @@
+/*
+    Hot/cold splitting: members marked `@frio` move to a side table reached through
+    a pointer while the rest (`@caliente` or unmarked) stay, so the data used all
+    the time fits in fewer cache lines
+*/
+use super::{Name, Strategy, Type, TypeError, TypeManager};
+use crate::utils;
+
+/// Cache line size used when none is given, in bytes
+pub const DEFAULT_CACHE_LINE : usize = 64;
+
+/// Attributes for members rarely used
+pub const COLD : [&str; 2] = ["frio", "frío"];
+
+/// Layout projection for a struct split in a hot part and a cold part
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitReport {
+    pub line:          usize,       // cache line size
+    pub hot:           Vec<usize>,  // indices of members staying in the struct, unmarked ones too
+    pub cold:          Vec<usize>,  // indices of members moving to the side table
+    pub size:          usize,       // current size under the C strategy
+    pub lines:         usize,       // cache lines the current struct spans
+    pub hot_lines_now: usize,       // cache lines holding some hot member now
+    pub hot_size:      usize,       // size of the hot struct, with the pointer to the cold one
+    pub cold_size:     usize
+}
+
+impl SplitReport {
+
+    /// Cache lines the hot struct spans
+    pub fn hot_lines(&self) -> usize {
+        lines(self.hot_size, self.line)
+    }
+
+    /// Cache lines the cold struct spans
+    pub fn cold_lines(&self) -> usize {
+        lines(self.cold_size, self.line)
+    }
+}
+
+impl TypeManager {
+
+    /// Project what splitting a struct in a hot and a cold part would look like,
+    /// assuming every struct starts at the beginning of a cache line
+    /// ## Params
+    /// * `typename` - struct to split
+    /// * `line` - cache line size, in bytes
+    /// ---
+    /// ## Return
+    /// Sizes and cache lines before and after the split, or an error if the type is not a struct
+    pub fn hot_cold_split(&self, typename : &Name, line : usize) -> Result<SplitReport, TypeError> {
+        let s = match self.checked(typename)? {
+            Type::Struct(s) => s,
+            _ => return Err(TypeError::NotAStruct(typename.clone()))
+        };
+        let line = line.max(1);
+
+        let is_cold = |i : &usize| s.fields[*i].attributes.iter().any(|a| COLD.contains(&a.key.as_str()));
+        let (cold, hot) : (Vec<usize>, Vec<usize>) = (0..s.members.len()).partition(is_cold);
+
+        // members as (size, alignment) under the C strategy
+        let members : Vec<(usize, usize)> = s.members
+                                                .iter()
+                                                .map(|m| (self.size_of(m, Strategy::C), self.align_of(m, Strategy::C)))
+                                                .collect();
+
+        // cache lines touched by hot members right now
+        let mut touched : Vec<usize> = s.layout(self, Strategy::C)
+                                        .iter()
+                                        .filter(|m| !is_cold(&m.index) && m.size > 0)
+                                        .flat_map(|m| m.offset / line..=(m.offset + m.size - 1) / line)
+                                        .collect();
+        touched.sort_unstable();
+        touched.dedup();
+
+        let mut hot_members : Vec<(usize, usize)> = hot.iter().map(|i| members[*i]).collect();
+        if !cold.is_empty() {
+            hot_members.push((self.pointer_size(), self.pointer_size()));
+        }
+        let cold_members : Vec<(usize, usize)> = cold.iter().map(|i| members[*i]).collect();
+
+        let size = s.c_size(self);
+        Ok(SplitReport {
+            line,
+            hot,
+            cold,
+            size,
+            lines: lines(size, line),
+            hot_lines_now: touched.len(),
+            hot_size: utils::c_size(&hot_members),
+            cold_size: utils::c_size(&cold_members)
+        })
+    }
+}
+
+/// Cache lines needed for some bytes
+fn lines(size : usize, line : usize) -> usize {
+    utils::round_up(size, line) / line
+}
@@ -0,0 +1,98 @@
+/*
+    Resource limits: bounds on what a session can hold, so a pathological input
+    sent to a server or run in a batch can't take all of its memory or time.
+    Types going over a limit are rejected with an error telling which one. The
+    optimizer stops searching when it runs out of time instead, keeping the best
+    order found, and the command that made it search gets a warning
+*/
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::{Name, Type, TypeError, TypeManager};
+
+/// Bounds on types and on the work done with them, none by default
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+    pub members:   Option<usize>,       // members of a struct, variants of a union
+    pub depth:     Option<usize>,       // compound types nested in each other, 1 for a struct of atomic types
+    pub types:     Option<usize>,       // types stored in a session
+    pub optimizer: Option<Duration>     // time the optimizer searches the member order of a struct
+}
+
+thread_local! {
+    static TIMED_OUT : Cell<usize> = const { Cell::new(0) };
+}
+
+/// Times the optimizer ran out of time in this thread, compare it before and after
+/// some work to know if its results may not be the best
+pub fn timed_out() -> usize {
+    TIMED_OUT.with(|t| t.get())
+}
+
+/// The optimizer ran out of time
+pub(super) fn time_out() {
+    TIMED_OUT.with(|t| t.set(t.get() + 1))
+}
+
+impl TypeManager {
+
+    /// Limits types added from now on are checked against
+    pub fn set_limits(&mut self, limits : Limits) {
+        self.limits = limits;
+    }
+
+    /// Limits types are checked against
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Check a new type doesn't go over any limit
+    /// ## Params
+    /// * `typename` - name the type is added with
+    /// * `t` - type to add, every type it uses is stored
+    /// ---
+    /// ## Return
+    /// Error telling the first limit it goes over, if any
+    pub(super) fn check_limits(&self, typename : &Name, t : &Type) -> Result<(), TypeError> {
+        if let Some(limit) = self.limits.types {
+            if self.types.len() >= limit {
+                return Err(TypeError::TooManyTypes(limit))
+            }
+        }
+
+        let members = t.dependencies().len();
+        if let Some(limit) = self.limits.members.filter(|limit| members > *limit) {
+            return Err(TypeError::TooManyMembers(typename.clone(), members, limit))
+        }
+
+        if let Some(limit) = self.limits.depth {
+            let depth = self.nesting(t, &mut HashMap::new());
+            if depth > limit {
+                return Err(TypeError::TooDeep(typename.clone(), depth, limit))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compound types nested in a type, itself included
+    /// ## Params
+    /// * `known` - nesting of the stored types found so far, so shared members are visited once
+    fn nesting<'a>(&'a self, t : &'a Type, known : &mut HashMap<&'a Name, usize>) -> usize {
+        let mut deepest = None;
+        for member in t.dependencies() {
+            let depth = match known.get(member) {
+                Some(depth) => *depth,
+                None => {
+                    let depth = self.get(member).map(|m| self.nesting(m, known)).unwrap_or(0);
+                    known.insert(member, depth);
+                    depth
+                }
+            };
+            deepest = deepest.max(Some(depth));
+        }
+
+        deepest.map(|d| d + 1).unwrap_or(0)
+    }
+}
@@ -0,0 +1,208 @@
+/*
+    Lint: named rules looking for layout smells in stored types, such as
+    structs with too much padding or members in a wasteful order
+*/
+use super::{Name, Strategy, Struct, Type, TypeManager, LARGE_PERMUTATIONS};
+
+/// Share of a struct that can be padding before `relleno` complains, in percent
+pub const PADDING_PERCENT : usize = 25;
+
+/// How many times bigger than the smallest variant the biggest one can be before `union_desigual` complains
+pub const UNION_RATIO : usize = 4;
+
+/// A check run over every stored type
+pub struct Rule {
+    pub name:        &'static str,
+    pub description: &'static str,
+    check: fn(&Type, &TypeManager) -> Option<String>   // what's wrong with a type, if anything
+}
+
+/// Every lint rule
+pub const RULES : [Rule; 4] = [
+    Rule {
+        name: "relleno",
+        description: "structs donde el relleno es buena parte del tamaño con la estrategia C",
+        check: excessive_padding
+    },
+    Rule {
+        name: "orden",
+        description: "structs que serían más pequeños con sus miembros en otro orden",
+        check: misordered_members
+    },
+    Rule {
+        name: "sobrealineado",
+        description: "miembros de structs con alineación mayor que su tamaño",
+        check: over_aligned_members
+    },
+    Rule {
+        name: "union_desigual",
+        description: "uniones cuya variante más grande es varias veces la más pequeña",
+        check: uneven_union
+    }
+];
+
+/// A problem found by a rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub rule:     &'static str,
+    pub typename: Name,
+    pub message:  String
+}
+
+impl Finding {
+
+    /// Get human readable description for this finding
+    pub fn display(&self) -> String {
+        format!("{}: [{}] {}", self.typename, self.rule, self.message)
+    }
+}
+
+/// Find a rule by its name
+pub fn rule(name : &str) -> Option<&'static Rule> {
+    RULES.iter().find(|r| r.name == name)
+}
+
+/// Parse a comment disabling rules for the next definition: `# lint: permitir relleno, orden`
+/// ## Return
+/// None if the comment is not about lint, the rules it names or the first unknown rule
+pub fn parse_suppression(comment : &str) -> Option<Result<Vec<String>, String>> {
+    let text = comment.trim_start_matches('#').trim();
+    let rules = text.strip_prefix("lint:")?.trim().strip_prefix("permitir")?;
+
+    let rules : Vec<String> = rules.split([',', ' ']).filter(|r| !r.is_empty()).map(String::from).collect();
+    match rules.iter().find(|r| rule(r).is_none()) {
+        Some(unknown) => Some(Err(unknown.clone())),
+        None => Some(Ok(rules))
+    }
+}
+
+impl TypeManager {
+
+    /// Run every lint rule over some types
+    /// ## Params
+    /// * `names` - types to check
+    /// * `allowed` - tells if a rule is disabled for a type
+    /// ---
+    /// ## Return
+    /// Every finding, in the order of the given names and then in rule order
+    pub fn lint<F>(&self, names : &[Name], allowed : F) -> Vec<Finding>
+        where
+            F: Fn(&Name, &str) -> bool
+    {
+        let mut findings = vec![];
+        for name in names {
+            let t = match self.get(name) {
+                Some(t) => t,
+                None => continue
+            };
+
+            for rule in RULES.iter().filter(|r| !allowed(name, r.name)) {
+                if let Some(message) = (rule.check)(t, self) {
+                    findings.push(Finding { rule: rule.name, typename: name.clone(), message });
+                }
+            }
+        }
+
+        findings
+    }
+}
+
+/// `relleno`: padding is more than PADDING_PERCENT of the struct
+fn excessive_padding(t : &Type, manager : &TypeManager) -> Option<String> {
+    let s = match t {
+        Type::Struct(s) => s,
+        _ => return None
+    };
+
+    let size = s.c_size(manager);
+    let padding = size.saturating_sub(s.packed_size(manager));
+    if padding * 100 <= size * PADDING_PERCENT {
+        return None
+    }
+
+    Some(format!("{} de {} bytes son relleno ({}%)", padding, size, padding * 100 / size))
+}
+
+/// `orden`: the optimizer finds a smaller order than the declared one
+fn misordered_members(t : &Type, manager : &TypeManager) -> Option<String> {
+    let s = match t {
+        Type::Struct(s) => s,
+        _ => return None
+    };
+
+    // too many orders to try them all every time lint runs
+    if s.members.len() >= LARGE_PERMUTATIONS {
+        return None
+    }
+
+    // compare both orders as a C compiler would lay them out
+    let declared = s.c_size(manager);
+    let (layout, _, _) = s.optimize(manager);
+    let optimal = Struct::new(layout.clone()).c_size(manager);
+    if optimal >= declared {
+        return None
+    }
+
+    Some(format!(
+        "reordenar los miembros ahorra {} bytes ({} en vez de {}): {}",
+        declared - optimal,
+        optimal,
+        declared,
+        layout.join(" ")
+    ))
+}
+
+/// `sobrealineado`: members that need more alignment than the space they take
+fn over_aligned_members(t : &Type, manager : &TypeManager) -> Option<String> {
+    let s = match t {
+        Type::Struct(s) => s,
+        _ => return None
+    };
+
+    let over : Vec<String> = s.members
+                                .iter()
+                                .zip(&s.fields)
+                                .filter_map(|(member, field)| {
+                                    let member_type = manager.get(member)?;
+                                    let size = Strategy::C.type_size(member_type, manager);
+                                    let align = Strategy::C.type_align(member_type, manager);
+                                    match align > size {
+                                        true  => Some(format!("'{}' ({} bytes, alineación {})", field.name, size, align)),
+                                        false => None
+                                    }
+                                })
+                                .collect();
+
+    match over.is_empty() {
+        true  => None,
+        false => Some(format!("miembros con alineación mayor que su tamaño: {}", over.join(", ")))
+    }
+}
+
+/// `union_desigual`: the biggest variant is UNION_RATIO times the smallest one or more
+fn uneven_union(t : &Type, manager : &TypeManager) -> Option<String> {
+    let u = match t {
+        Type::Union(u) => u,
+        _ => return None
+    };
+
+    let sizes : Vec<(&Name, usize)> = u.variants
+                                        .iter()
+                                        .filter_map(|v| manager.get(v).map(|t| (v, Strategy::C.type_size(t, manager))))
+                                        .collect();
+    let smallest = sizes.iter().min_by_key(|(_, size)| *size)?;
+    let biggest = sizes.iter().max_by_key(|(_, size)| *size)?;
+
+    if biggest.1 < smallest.1.saturating_mul(UNION_RATIO) {
+        return None
+    }
+
+    Some(format!(
+        "la variante '{}' ({} bytes) es {} veces más grande que '{}' ({} bytes)",
+        biggest.0,
+        biggest.1,
+        biggest.1 / smallest.1.max(1),
+        smallest.0,
+        smallest.1
+    ))
+}
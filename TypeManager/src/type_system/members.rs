@@ -0,0 +1,67 @@
+/*
+    Nested members: every member of a type and of the structs and unions inside
+    it, each with its full path (`cabecera.largo`) and its offset from the start
+    of the outermost type. Union variants have no names, so the variant type
+    names them in paths
+*/
+use super::{Name, Strategy, Type, TypeError, TypeManager};
+
+/// A member somewhere inside a type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NestedMember {
+    pub path:     String,   // member names from the outermost type, joined by `.`
+    pub typename: Name,
+    pub offset:   usize,    // from the start of the outermost type
+    pub size:     usize,
+    pub depth:    usize     // 0 for members of the outermost type
+}
+
+impl TypeManager {
+
+    /// List every member inside a type, nested ones right after the member holding them
+    /// ## Params
+    /// * `typename` - outermost type
+    /// * `strategy` - how structs are laid out, members follow memory order
+    /// ---
+    /// ## Return
+    /// Every member in depth first order, empty for types without members, or an error if the type does not exist
+    pub fn nested_members(&self, typename : &Name, strategy : Strategy) -> Result<Vec<NestedMember>, TypeError> {
+        let t = self.checked(typename)?;
+        let mut members = vec![];
+        self.collect_members(t, strategy, "", 0, 0, &mut members);
+
+        Ok(members)
+    }
+
+    /// Helper function for `nested_members`, add members of a type placed at `base`
+    fn collect_members(&self, t : &Type, strategy : Strategy, prefix : &str, base : usize, depth : usize, members : &mut Vec<NestedMember>) {
+        let inner : Vec<(String, &Name, usize)> = match t {
+            Type::Struct(s) => s.layout(self, strategy)
+                                .into_iter()
+                                .map(|m| (s.fields[m.index].name.clone(), &s.members[m.index], m.offset))
+                                .collect(),
+            Type::Union(u)  => u.variants.iter().map(|v| (v.clone(), v, 0)).collect(),
+            _ => return
+        };
+
+        for (name, member, offset) in inner {
+            let member_type = match self.get(member) {
+                Some(t) => t,
+                None => continue
+            };
+            let path = match prefix.is_empty() {
+                true  => name,
+                false => format!("{}.{}", prefix, name)
+            };
+            let offset = base.saturating_add(offset);
+            members.push(NestedMember {
+                path: path.clone(),
+                typename: member.clone(),
+                offset,
+                size: strategy.type_size(member_type, self),
+                depth
+            });
+            self.collect_members(member_type, strategy, &path, offset, depth + 1, members);
+        }
+    }
+}
@@ -3,42 +3,189 @@
     for our type system simulator 
 */
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 use crate::utils;
+use crate::expr::ExprError;
+
+mod report;
+mod diagnostic;
+pub mod lint;
+mod hotcold;
+mod flags;
+mod enums;
+mod pointer;
+mod container;
+mod text;
+mod bitfield;
+mod tail;
+mod density;
+mod plan;
+mod members;
+mod values;
+mod vectors;
+mod abi;
+mod diff;
+mod profile;
+mod cache;
+pub mod cancel;
+pub mod limits;
+mod anneal;
+mod pareto;
+mod constraints;
+mod objective;
+mod trace;
+mod shadow;
+mod clean;
+mod deferred;
+mod provenance;
+mod diagram;
+mod pahole;
+mod checked;
+mod table;
+mod suggest;
+mod attributes;
+mod budget;
+mod growth;
+mod flatten;
+#[cfg(feature = "solver")]
+mod solver;
+pub use report::*;
+pub use diagnostic::*;
+pub use hotcold::*;
+pub use flags::*;
+pub use enums::*;
+pub use pointer::*;
+pub use container::*;
+pub use text::*;
+pub use bitfield::*;
+pub use tail::*;
+pub use density::*;
+pub use plan::*;
+pub use members::*;
+pub use values::*;
+pub use vectors::*;
+pub use abi::*;
+pub use diff::*;
+pub use profile::*;
+pub use cache::LayoutCache;
+pub use limits::Limits;
+pub use anneal::*;
+pub use pareto::*;
+pub use constraints::*;
+pub use objective::*;
+pub use trace::*;
+pub use shadow::*;
+pub use deferred::*;
+pub use provenance::*;
+pub use diagram::*;
+pub use table::TypeTable;
+pub use suggest::*;
+pub use attributes::*;
+pub use budget::*;
+pub use growth::*;
+pub use flatten::*;
+#[cfg(feature = "solver")]
+pub use solver::*;
 
 
 // A type name
 pub type Name = String;
 // A list of types
 pub type TypeList = Vec<Name>;
-// A map from names to type data
-pub type TypeTable = HashMap<Name, Type>;
 
 
+/// Most members the exact solver takes, it keeps a state for every subset of them
+pub const MAX_SOLVER_MEMBERS : usize = 20;
+
+/// Member orders the optimizer tries between checks for cancellation and its time limit
+const CANCEL_CHECK : usize = 4096;
+
+/// Attribute labelling struct members the optimizer keeps next to each other: `@grupo=red`
+pub const GROUP : &str = "grupo";
+
+/// Struct attribute removing padding under the C strategy, as GCC's `__attribute__((packed))`
+pub const PACKED : [&str; 2] = ["packed", "empaquetado"];
+
+/// Struct or member attribute raising alignment under the C strategy: `@aligned=16`
+pub const ALIGNED : [&str; 2] = ["aligned", "alineado"];
+
+/// Attribute documenting a type or member, shown in reports and exported as comments: `@nota="..."`
+pub const NOTE : [&str; 2] = ["nota", "doc"];
+
 /// Atomic Data type structure
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Atomic {
     pub representation: usize,
-    pub alignment:      usize
+    pub alignment:      usize,
+    pub kind:           Option<ValueKind>   // how to read its bytes, if declared
 }
 
 /// Struct type structure
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Struct {
-    pub members: TypeList
+    pub members:    TypeList,
+    pub fields:     Vec<Field>,     // name and attributes for every member, in the same order
+    pub attributes: Vec<Attribute>  // for the whole struct, such as `@packed`
+}
+
+/// Name and attributes for a single struct member
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name:       Name,
+    pub attributes: Vec<Attribute>
+}
+
+/// A `@clave` or `@clave=valor` annotation on a type or a struct member. Values with
+/// spaces go between quotes: `@nota="cabecera de red"`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    pub key:   String,
+    pub value: Option<String>
 }
 
 /// Union type structure
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Union {
     pub variants: TypeList
 }
 
 /// Every Possible data type
-#[derive(Debug)] 
+#[derive(Debug, Clone, PartialEq)]
 pub enum Type {
     Atomic  (Atomic),
     Struct  (Struct),
-    Union   (Union)
+    Union   (Union),
+    Enum    (Enum),
+    Pointer (Pointer),
+    Container (Container),
+    Text    (Text)
+}
+
+/// Every way to place struct members in memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    Unpacked,   // declaration order, padding to align every member
+    Packed,     // declaration order, no padding at all
+    Optimized,  // best member order, padding to align every member
+    C           // what a C compiler does: declaration order, aligned to the biggest member, tail padding
+}
+
+/// Where a single struct member ends up in memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberLayout {
+    pub index:  usize,  // position in the member list
+    pub offset: usize,
+    pub size:   usize
+}
+
+/// Statistics about a single layout optimization
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OptimizerStats {
+    pub elapsed:      Duration,
+    pub permutations: usize,    // member orders considered
+    pub pruned:       usize,    // member orders dropped before placing every member
+    pub cancelled:    bool,     // whether the search was stopped before trying every order
+    pub timed_out:    bool      // whether the search ran out of time before trying every order
 }
 
 /// Every possible error 
@@ -48,13 +195,50 @@ pub enum TypeError {
     NoZeroAlign,
     NoZeroSizedType,
     EmptyCompoundType,
+    SizeOverflow,
+    NotAStruct(Name),
+    DuplicateField(Name),
+    Expression(ExprError),
+    CaseRedefinition(Name, Name),   // new name, stored name differing only in case
+    Strict(Diagnostic),             // a warning, rejected in strict mode
+    BitfieldTooWide(Name),          // member with more bits than its type
+    NotAUnion(Name),
+    NotAVariant(Name, Name),        // union, type that is not one of its variants
+    TooLarge(Name, usize, usize),   // type too big to work with, its size, largest size allowed
+    CyclicDefinition(Name, Name),   // type redefined, type built on it it would use
+    InvalidAttribute(String),       // attribute as written
+    TooManyTypes(usize),            // limit of types in a session
+    TooManyMembers(Name, usize, usize), // type, its members, limit
+    TooDeep(Name, usize, usize),    // type, compound types nested in it, limit
+    TooBigToSolve(Name, usize),     // struct, its members
+    Unresolved(Vec<Unresolved>),    // pending types and what they miss
+    NoMembers(Name),                // stored struct, union or enum left without members
+    ZeroAlign(Name),                // stored atomic type left with alignment 0
+    UnknownMember(Name, Vec<Suggestion>),   // member type that doesn't exist, stored types with the closest names
+    UnknownField(Name, Name),       // struct, field it doesn't have
+    Unflattenable(Name, Name),      // struct, nested field a flat struct can't place at its offset
     TypeDoesNotExist(Name)
 }
 
 /// Manager object controlling our stored types
-#[derive(Debug)]
+#[derive(Debug, Default, Clone)]
 pub struct TypeManager {
-    types: TypeTable
+    types:  TypeTable,
+    folded: Option<HashMap<String, Name>>,  // lowercase name to stored name, if case is ignored
+    strict: bool,                           // whether warnings reject new types
+    abi:    Abi,                            // target machine, deciding the size of pointers
+    users:  HashMap<Name, TypeList>,        // types using each type as a member or variant
+    layouts: LayoutCache,                   // layouts computed so far
+    limits: Limits,                         // bounds on the types stored and the work done with them
+    keep_first: bool,                       // whether the optimizer keeps the first member of every struct first
+    objective: Objective,                   // what the optimizer minimizes
+    shadowing: bool,                        // whether new types shadow stored ones with the same name
+    deferred: bool,                         // whether compound types using undefined types are kept as pending
+    pending: Vec<(Name, Type)>,             // types waiting for the ones they use, in definition order
+    origins: HashMap<Name, String>,         // where every type was defined
+    attributes: HashMap<Name, Vec<Attribute>>, // attributes of types other than structs, which keep their own
+    budgets: HashMap<Name, Budget>,         // most bytes every budgeted type may take
+    growth: HashMap<Name, Vec<Growth>>      // layouts every type had and the edits changing them
 }
 
 impl TypeManager {
@@ -62,8 +246,59 @@ impl TypeManager {
     /// Create a new type manager
     pub fn new() -> TypeManager {
         TypeManager {
-            types: TypeTable::new()
+            types: TypeTable::new(),
+            folded: None,
+            strict: false,
+            abi: Abi::default(),
+            users: HashMap::new(),
+            layouts: LayoutCache::default(),
+            limits: Limits::default(),
+            keep_first: false,
+            objective: Objective::Size,
+            shadowing: false,
+            deferred: false,
+            pending: vec![],
+            origins: HashMap::new(),
+            attributes: HashMap::new(),
+            budgets: HashMap::new(),
+            growth: HashMap::new()
+        }
+    }
+
+    /// Create a new type manager where `Int` and `int` name the same type. Types
+    /// keep the name they were defined with, and references to them are rewritten to it
+    pub fn ignoring_case() -> TypeManager {
+        TypeManager { folded: Some(HashMap::new()), ..TypeManager::new() }
+    }
+
+    /// Copy of every stored type and setting, to be read while this manager keeps
+    /// changing. Layouts computed so far are copied too, they hold for the same types
+    pub fn snapshot(&self) -> TypeManager {
+        self.clone()
+    }
+
+    /// Make warnings about new types errors, so those types are not added
+    pub fn set_strict(&mut self, strict : bool) {
+        self.strict = strict;
+    }
+
+    /// Tells if names differing only in case refer to the same type
+    pub fn ignores_case(&self) -> bool {
+        self.folded.is_some()
+    }
+
+    /// Name a type is stored with
+    /// ## Params
+    /// * `typename` - name as written by the user
+    /// ---
+    /// ## Return
+    /// The stored name, or None if there's no such type
+    pub fn resolve<'a>(&'a self, typename : &'a Name) -> Option<&'a Name> {
+        if self.types.contains_key(typename) {
+            return Some(typename)
         }
+
+        self.folded.as_ref().and_then(|folded| folded.get(&typename.to_lowercase()))
     }
 
     /// Try to add a new type to our type manager
@@ -72,17 +307,105 @@ impl TypeManager {
     /// * `new_type` - type definition itself
     /// ---
     /// ## Return 
-    /// Error describing the issue if could not add, or nothing on success 
-    pub fn add(&mut self, typename : Name,  new_type : Type) -> Result<(), TypeError> {
+    /// Error describing the issue if could not add, or diagnostics about the new type on success.
+    /// In strict mode the first warning is an error. When shadowing, a stored type with the
+    /// same name is kept as `nombre@anterior` with a warning instead of being an error
+    pub fn add(&mut self, typename : Name,  new_type : Type) -> Result<Vec<Diagnostic>, TypeError> {
+
+        // types using undefined ones wait for them, if deferred
+        if let Some(missing) = self.defer(&typename, &new_type) {
+            return Ok(vec![Diagnostic::Deferred(missing)])
+        }
 
         // if there was some error, return such error. Else, keep going
-        if let Err(e) = self.check_new_type(&typename, &new_type) {
-            return Err(e)
+        let shadowed = self.resolve(&typename).filter(|_| self.shadowing).cloned();
+        match &shadowed {
+            Some(_) => self.check_type(&new_type)?,
+            None => self.check_new_type(&typename, &new_type)?
         }
+        self.check_limits(&typename, &new_type)?;
 
-        // add the new type
+        let mut diagnostics = self.diagnose(&new_type);
+        if let Some(stored) = &shadowed {
+            diagnostics.push(Diagnostic::Shadowed(format!("{}{}", stored, SHADOWED)));
+        }
+        if self.strict {
+            if let Some(warning) = diagnostics.iter().find(|d| d.level() == Level::Warning) {
+                return Err(TypeError::Strict(warning.clone()))
+            }
+        }
+
+        // add the new type, using stored names for the types it refers to
+        let mut new_type = self.canonical(new_type);
+        if let Some(stored) = &shadowed {
+            let older = self.shelve(stored);
+            new_type = new_type.rename_references(|n| match n == stored {
+                true  => older.clone(),
+                false => n.clone()
+            });
+        }
+        if let Some(folded) = &mut self.folded {
+            folded.insert(typename.to_lowercase(), typename.clone());
+        }
+        self.invalidate(&typename);
+        self.index_users(&typename, &new_type);
         self.types.insert(typename, new_type);
-        Ok(())
+        Ok(diagnostics)
+    }
+
+    /// Put a type in place of a stored one with the same name
+    /// ## Params
+    /// * `typename` - name of the stored type
+    /// * `new_type` - type to store instead
+    /// ---
+    /// ## Return
+    /// Warnings found in the new type, or an error leaving the stored type as it was
+    pub fn replace(&mut self, typename : &Name, new_type : Type) -> Result<Vec<Diagnostic>, TypeError> {
+        // the new type can't be built on a type built on it
+        let dependents = self.dependents(typename);
+        if let Some(cycle) = new_type.dependencies().iter().find(|d| self.resolve(d).is_some_and(|d| d == typename || dependents.contains(d))) {
+            return Err(TypeError::CyclicDefinition(typename.clone(), cycle.clone()))
+        }
+
+        // layouts built on the old type are dropped before it's gone, while the index still has its users
+        self.invalidate(typename);
+        let position = self.types.position(typename).ok_or_else(|| TypeError::TypeDoesNotExist(typename.clone()))?;
+        let old = self.types.remove(typename).ok_or_else(|| TypeError::TypeDoesNotExist(typename.clone()))?;
+        self.unindex_users(typename, &old);
+        if let Some(folded) = &mut self.folded {
+            folded.remove(&typename.to_lowercase());
+        }
+
+        // the new type takes the place of the old one in the definition order
+        let result = self.add(typename.clone(), new_type);
+        match (&result, self.types.remove(typename)) {
+            (Ok(_), Some(new)) => self.types.insert_at(position, typename.clone(), new),
+            (Ok(_), None) => (),
+            (Err(_), _) => {
+                if let Some(folded) = &mut self.folded {
+                    folded.insert(typename.to_lowercase(), typename.clone());
+                }
+                self.index_users(typename, &old);
+                self.types.insert_at(position, typename.clone(), old);
+            }
+        }
+
+        result
+    }
+
+    /// Rewrite every type referenced by a type with its stored name
+    fn canonical(&self, t : Type) -> Type {
+        t.rename_references(|n| self.resolve(n).unwrap_or(n).clone())
+    }
+
+    /// Amount of stored types
+    pub fn len(&self) -> usize {
+        self.types.len()
+    }
+
+    /// Tells if there's no type stored
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty()
     }
 
     /// Try to get data for a type given its name
@@ -92,7 +415,81 @@ impl TypeManager {
     /// A reference to this type's data if the given name is a valid one,
     /// None otherwhise
     pub fn get(&self, typename: &Name) -> Option<&Type> {
-        self.types.get(typename)
+        profile::lookup(typename);
+        self.resolve(typename).and_then(|name| self.types.get(name))
+    }
+
+    /// Iterate over the name of every stored type, in the order they were defined
+    pub fn names(&self) -> impl Iterator<Item = &Name> {
+        self.types.keys()
+    }
+
+    /// Every stored type name in the order they were defined, except that every type
+    /// comes after the types it uses, which only moves those ahead
+    pub fn definition_order(&self) -> TypeList {
+        let mut order = TypeList::with_capacity(self.types.len());
+        let mut visited = std::collections::HashSet::new();
+        for name in self.types.keys() {
+            self.visit_dependencies(name, &mut visited, &mut order);
+        }
+
+        order
+    }
+
+    /// Helper function for `definition_order`, add dependencies of a type before the type itself
+    fn visit_dependencies(&self, name : &Name, visited : &mut std::collections::HashSet<Name>, order : &mut TypeList) {
+        if !visited.insert(name.clone()) {
+            return
+        }
+
+        if let Some(t) = self.types.get(name) {
+            for dependency in t.dependencies() {
+                self.visit_dependencies(dependency, visited, order);
+            }
+        }
+
+        order.push(name.clone());
+    }
+
+    /// Get a structured report for a single type
+    /// ## Params
+    /// * `typename` - name of type to describe
+    pub fn report(&self, typename: &Name) -> Result<Report, TypeError> {
+        Ok(self.checked(typename)?.report(self))
+    }
+
+    /// Get layout data for a union with one of its variants active
+    /// ## Params
+    /// * `union` - name of the union
+    /// * `variant` - type of the active variant
+    /// ---
+    /// ## Return
+    /// Variant size, union size and slack for every strategy, or an error if the union
+    /// doesn't exist or the type is not one of its variants
+    pub fn variant_report(&self, union: &Name, variant: &Name) -> Result<Vec<VariantReport>, TypeError> {
+        let u = match self.checked(union)? {
+            Type::Union(u) => u,
+            _ => return Err(TypeError::NotAUnion(union.clone()))
+        };
+
+        let stored = self.resolve(variant).unwrap_or(variant);
+        u.variant_report(self, stored).ok_or_else(|| TypeError::NotAVariant(union.clone(), variant.clone()))
+    }
+
+    /// Run the optimizer for a single type and get statistics about the search
+    /// ## Params
+    /// * `typename` - name of type to optimize
+    /// ---
+    /// ## Return
+    /// Search statistics, or None if this type is not a struct and there's nothing to optimize
+    pub fn optimizer_stats(&self, typename: &Name) -> Result<Option<OptimizerStats>, TypeError> {
+        match self.checked(typename)? {
+            Type::Struct(s) => {
+                let (_, _, stats) = s.optimize(self);
+                Ok(Some(stats))
+            },
+            _ => Ok(None)
+        }
     }
 
     /// Return a human-readable String information about a single type
@@ -102,22 +499,28 @@ impl TypeManager {
     /// ## Return
     /// String with data about the given type 
     pub fn display(&self, typename: &Name) -> Result<String, TypeError> {
-        if !self.types.contains_key(typename) {
-            Err(TypeError::TypeDoesNotExist(typename.clone()))
-        }
-        else {
-            Ok(self.types.get(typename).unwrap().display(self))
-        }
+        Ok(self.checked(typename)?.display(self))
     }
 
     /// Checks if the given type could be a valid new type
     fn check_new_type(&self, name: &Name, type_data: &Type) -> Result<(), TypeError> {
 
-        // if name already stored, raise an error
-        if self.types.contains_key(name) {
+        // if name already stored or waiting to be, raise an error
+        if self.types.contains_key(name) || self.pending_type(name).is_some() {
             return Err(TypeError::TypeRedefinition)
         }
 
+        // same name in another case, when case is ignored
+        if let Some(stored) = self.resolve(name) {
+            return Err(TypeError::CaseRedefinition(name.clone(), stored.clone()))
+        }
+
+        self.check_type(type_data)
+    }
+
+    /// Check a type is valid on its own, whatever its name
+    fn check_type(&self, type_data: &Type) -> Result<(), TypeError> {
+
         // Check for every kind of type
         match type_data {
             Type::Atomic(a) => {
@@ -137,8 +540,8 @@ impl TypeManager {
                 
                 // Check if some member type is an invalid type
                 for sym in &s.members {
-                    if !self.types.contains_key(sym) {
-                        return Err(TypeError::TypeDoesNotExist(sym.clone()))
+                    if self.resolve(sym).is_none() {
+                        return Err(self.unknown(sym))
                     }
                 }
 
@@ -147,14 +550,41 @@ impl TypeManager {
                     return Err(TypeError::EmptyCompoundType)
                 }
 
+                // every member needs its own name
+                for (i, field) in s.fields.iter().enumerate() {
+                    if s.fields[..i].iter().any(|f| f.name == field.name) {
+                        return Err(TypeError::DuplicateField(field.name.clone()))
+                    }
+                }
+
+                // bitfields can't be wider than their type
+                for (member, field) in s.members.iter().zip(&s.fields) {
+                    let bits = self.get(member).map(|t| Strategy::C.type_size(t, self).saturating_mul(8)).unwrap_or(0);
+                    if matches!(field.bit_width(), Some(w) if w > bits) {
+                        return Err(TypeError::BitfieldTooWide(field.name.clone()))
+                    }
+                }
+
+                // alignments asked for must be powers of 2
+                for a in s.attributes.iter().chain(s.fields.iter().flat_map(|f| &f.attributes)) {
+                    if ALIGNED.contains(&a.key.as_str()) && attribute_align(std::slice::from_ref(a)).is_none() {
+                        return Err(TypeError::InvalidAttribute(a.to_string()))
+                    }
+                }
+
+                // sizes saturate instead of overflowing, so a saturated size means overflow
+                if s.unpacked_size(self) == usize::MAX || s.packed_size(self) == usize::MAX {
+                    return Err(TypeError::SizeOverflow)
+                }
+
                 Ok(())
             },
             Type::Union(u) => {
 
                 // Check if some member type is an invalid type
                 for sym in &u.variants {
-                    if !self.types.contains_key(sym) {
-                        return Err(TypeError::TypeDoesNotExist(sym.clone()))
+                    if self.resolve(sym).is_none() {
+                        return Err(self.unknown(sym))
                     }
                 }
 
@@ -164,19 +594,85 @@ impl TypeManager {
                 }
 
                 Ok(())
-            }
+            },
+            Type::Enum(e) => {
+                if e.variants.is_empty() {
+                    return Err(TypeError::EmptyCompoundType)
+                }
+
+                // every variant needs its own name
+                for (i, variant) in e.variants.iter().enumerate() {
+                    if e.variants[..i].contains(variant) {
+                        return Err(TypeError::DuplicateField(variant.clone()))
+                    }
+                }
+
+                Ok(())
+            },
+
+            // pointed types may be defined later, so a struct can point to itself
+            Type::Pointer(_) | Type::Container(_) => Ok(()),
+            Type::Text(_) => Ok(())
         }
     }
 }
 
 impl Type {
 
+    /// Names of the types this type is made of
+    pub fn dependencies(&self) -> &[Name] {
+        match self {
+            Type::Atomic(_) | Type::Enum(_) | Type::Pointer(_) | Type::Container(_) | Type::Text(_) => &[],
+            Type::Struct(s) => &s.members,
+            Type::Union(u)  => &u.variants
+        }
+    }
+
+    /// Change the names of the types this type refers to
+    /// ## Params
+    /// * `rename` - new name for every referenced name
+    pub fn rename_references(self, rename : impl Fn(&Name) -> Name) -> Type {
+        match self {
+            Type::Struct(s) => Type::Struct(Struct { members: s.members.iter().map(&rename).collect(), ..s }),
+            Type::Union(u)  => Type::Union(Union { variants: u.variants.iter().map(&rename).collect() }),
+            Type::Pointer(p) => Type::Pointer(Pointer { target: rename(&p.target), ..p }),
+            Type::Container(c) => Type::Container(Container { element: c.element.as_ref().map(&rename), ..c }),
+            t @ (Type::Atomic(_) | Type::Enum(_) | Type::Text(_)) => t
+        }
+    }
+
     /// Create an human readable description for this type 
     pub fn display(&self, manager : &TypeManager) -> String {
+        self.report(manager).display()
+    }
+
+    /// Create a structured report with the layout details for this type
+    pub fn report(&self, manager : &TypeManager) -> Report {
         match self {
-            Type::Atomic(a) => a.display(),
-            Type::Struct(s) => s.display(manager),
-            Type::Union(u)  => u.display(manager)
+            Type::Atomic(a) => Report::Atomic { representation: a.representation, alignment: a.alignment, kind: a.kind },
+            Type::Struct(s) => Report::Struct(s.report(manager)),
+            Type::Union(u)  => Report::Union { strategies: u.report(manager), variants: u.variant_losses(manager) },
+            Type::Enum(e)   => Report::Enum {
+                variants: e.variants.len(),
+                minimal:  e.minimal_size(),
+                int:      e.int_size(),
+                repr:     e.repr
+            },
+            Type::Pointer(p) => Report::Pointer { target: p.target.clone(), kind: p.kind, size: p.size(manager.pointer_size()), align: p.align(manager.pointer_size()) },
+            Type::Container(c) => Report::Container {
+                kind:     c.kind,
+                element:  c.element.clone(),
+                capacity: c.capacity,
+                inline:   c.inline_size(manager.pointer_size()),
+                align:    c.align(manager.pointer_size()),
+                heap:     manager.owned_bytes(c)
+            },
+            Type::Text(t) => Report::Text {
+                model:  t.model,
+                size:   t.size(manager.pointer_size()),
+                align:  t.align(manager.pointer_size()),
+                inline: t.inline_capacity()
+            }
         }
     }
 
@@ -188,10 +684,15 @@ impl Type {
                     manager : &TypeManager, 
                     struct_packing_align : fn(&Struct, &TypeManager) -> usize
                     ) -> usize {
+        let _level = profile::enter();
         match self {
             Type::Atomic(a) => a.align(),
             Type::Struct(s) => struct_packing_align(s, manager),
-            Type::Union(u)  => u.align(manager, struct_packing_align)
+            Type::Union(u)  => u.align(manager, struct_packing_align),
+            Type::Enum(e)   => e.align(),
+            Type::Pointer(p) => p.align(manager.pointer_size()),
+            Type::Container(c) => c.align(manager.pointer_size()),
+            Type::Text(t)   => t.align(manager.pointer_size())
         }
     }
 
@@ -207,27 +708,108 @@ impl Type {
                     struct_packing_size : fn(&Struct, &TypeManager) -> usize
                     ) -> usize {
 
+        let _level = profile::enter();
         match self {
             Type::Atomic(a) => a.size(),
             Type::Struct(s) => struct_packing_size(s, manager),
-            Type::Union(u)  => u.size(manager, struct_packing_size)
+            Type::Union(u)  => u.size(manager, struct_packing_size),
+            Type::Enum(e)   => e.size(),
+            Type::Pointer(p) => p.size(manager.pointer_size()),
+            Type::Container(c) => c.inline_size(manager.pointer_size()),
+            Type::Text(t)   => t.size(manager.pointer_size())
         }
     }
 }
 
+impl Strategy {
+
+    /// Every strategy, in the order reports show them
+    pub const ALL : [Strategy; 4] = [Strategy::Optimized, Strategy::Unpacked, Strategy::Packed, Strategy::C];
+
+    /// Function computing struct size under this strategy
+    pub fn size_fn(&self) -> fn(&Struct, &TypeManager) -> usize {
+        match self {
+            Strategy::Unpacked  => Struct::unpacked_size,
+            Strategy::Packed    => Struct::packed_size,
+            Strategy::Optimized => Struct::optimized_size,
+            Strategy::C         => Struct::c_size
+        }
+    }
+
+    /// Function computing struct alignment under this strategy
+    pub fn align_fn(&self) -> fn(&Struct, &TypeManager) -> usize {
+        match self {
+            Strategy::Unpacked  => Struct::unpacked_align,
+            Strategy::Packed    => Struct::packed_align,
+            Strategy::Optimized => Struct::optimized_align,
+            Strategy::C         => Struct::c_align
+        }
+    }
+
+    /// Stable identifier for this strategy, for machine readable output
+    pub fn id(&self) -> &'static str {
+        match self {
+            Strategy::Unpacked  => "unpacked",
+            Strategy::Packed    => "packed",
+            Strategy::Optimized => "optimized",
+            Strategy::C         => "c"
+        }
+    }
+
+    /// Find a strategy by its id or its Spanish name, ignoring case, `_` and `-`:
+    /// `packed`, `empaquetado`, `sin-empaquetar`...
+    pub fn parse(text: &str) -> Option<Strategy> {
+        let text = text.to_lowercase().replace(['_', '-', ' '], "");
+
+        Strategy::ALL
+            .iter()
+            .copied()
+            .find(|s| s.id() == text || s.name().to_lowercase().replace(' ', "") == text)
+    }
+
+    /// Human readable name for this strategy
+    pub fn name(&self) -> &'static str {
+        match self {
+            Strategy::Unpacked  => "Sin Empaquetar",
+            Strategy::Packed    => "Empaquetado",
+            Strategy::Optimized => "Optimizado",
+            Strategy::C         => "C"
+        }
+    }
+
+    /// Size for any type under this strategy. Prefer this over `Type::size` for the
+    /// C strategy, since C unions get tail padding too
+    pub fn type_size(&self, my_type : &Type, manager : &TypeManager) -> usize {
+        match my_type {
+            Type::Union(u) => u.size_in(manager, *self),
+            _ => my_type.size(manager, self.size_fn())
+        }
+    }
+
+    /// Alignment for any type under this strategy
+    pub fn type_align(&self, my_type : &Type, manager : &TypeManager) -> usize {
+        my_type.align(manager, self.align_fn())
+    }
+}
+
 impl Atomic {
 
     /// Create new atomic type
     pub fn new(representation : usize, alignment : usize) -> Atomic {
         Atomic {
             representation,
-            alignment
+            alignment,
+            kind: None
         }
     }
 
-    /// return human readable string with details for this type
-    pub fn display(&self) -> String {
-        format!("⚛️  Atómico:\n   * Representación: {}\n   * Alineación: {}", self.representation, self.alignment)
+    /// Create new atomic type whose bytes are read as the given kind of value
+    pub fn with_kind(representation : usize, alignment : usize, kind : Option<ValueKind>) -> Atomic {
+        Atomic {
+            representation,
+            alignment,
+            kind
+        }
     }
 
     /// get size
@@ -235,73 +817,158 @@ impl Atomic {
         self.representation
     }
 
-    /// Get alignment
+    /// Get alignment, at least 1 even if an alignment of 0 got here
     pub fn align(&self) -> usize {
-        self.alignment
+        self.alignment.max(1)
+    }
+}
+
+impl Field {
+
+    /// Create a field with no attributes
+    pub fn new(name: Name) -> Field {
+        Field {
+            name,
+            attributes: vec![]
+        }
+    }
+
+    /// Name given to the i-th member when none is provided
+    pub fn default_name(i: usize) -> Name {
+        format!("m{}", i)
+    }
+
+    /// Value of an attribute, if this field has it with a value
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.iter().find(|a| a.key == key).and_then(|a| a.value.as_deref())
+    }
+
+    /// Tells if a name can be used for a field: a letter or `_` followed by letters, digits or `_`
+    pub fn valid_name(name: &str) -> bool {
+        let mut chars = name.chars();
+        match chars.next() {
+            Some(c) if c.is_alphabetic() || c == '_' => chars.all(|c| c.is_alphanumeric() || c == '_'),
+            _ => false
+        }
+    }
+
+    /// Tells if a name can be used for a field of a flattened struct: valid field names
+    /// joined by dots, such as `cabecera.largo`
+    pub fn valid_path(name: &str) -> bool {
+        name.split('.').all(Field::valid_name)
+    }
+
+    /// Name of this field as an identifier in generated code, dots become `_`
+    pub fn identifier(&self) -> String {
+        self.name.replace('.', "_")
+    }
+}
+
+impl Attribute {
+
+    /// Parse an attribute written as `@clave`, `@clave=valor` or `@clave="valor con espacios"`
+    /// ## Return
+    /// The attribute, or None if the text is not a valid attribute
+    pub fn parse(text: &str) -> Option<Attribute> {
+        let text = text.strip_prefix('@')?;
+        let (key, value) = match text.split_once('=') {
+            Some((k, v)) => (k, Some(v.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(v).to_string())),
+            None => (text, None)
+        };
+
+        if !Field::valid_name(key) || value.as_deref() == Some("") {
+            return None
+        }
+
+        Some(Attribute { key: key.to_string(), value })
+    }
+}
+
+impl std::fmt::Display for Attribute {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.value {
+            Some(v) if v.contains(char::is_whitespace) => write!(f, "@{}=\"{}\"", self.key, v),
+            Some(v) => write!(f, "@{}={}", self.key, v),
+            None    => write!(f, "@{}", self.key)
+        }
     }
 }
 
 impl Struct {
 
-    /// Create a new struct
+    /// Create a new struct, members are named after their position: `m0`, `m1`...
     pub fn new(members: TypeList) -> Struct {
+        let fields = (0..members.len()).map(|i| Field::new(Field::default_name(i))).collect();
+
         Struct {
             members,
+            fields,
+            attributes: vec![]
         }
     }
 
-    /// Create human-readable string with information about this struct
-    pub fn display(&self, manager : &TypeManager) -> String {
-
-        let optimal_size  = self.optimized_size(manager);
-        let unpacked_size = self.unpacked_size(manager);
-        let packed_size   = self.packed_size(manager);
+    /// Create a new struct with named members
+    /// ## Params
+    /// * `members` - type for every member
+    /// * `fields` - name and attributes for every member, in the same order
+    pub fn with_fields(members: TypeList, fields: Vec<Field>) -> Struct {
+        Struct {
+            members,
+            fields,
+            attributes: vec![]
+        }
+    }
 
-        let optimized_data = format!(
-            "   * Optimizado:\n      + Tamaño: {}\n      + Perdida: {}", 
-            optimal_size, 
-            optimal_size - packed_size
-        );
+    /// Set attributes for the whole struct
+    pub fn with_attributes(self, attributes: Vec<Attribute>) -> Struct {
+        Struct { attributes, ..self }
+    }
 
-        let unpacked_data = format!(
-            "   * Sin Empaquetar:\n      + Tamaño: {}\n      + Perdida: {}", 
-            unpacked_size,
-            unpacked_size - packed_size
-        );
+    /// Tells if this struct is marked `@packed`
+    pub fn is_packed(&self) -> bool {
+        self.attributes.iter().any(|a| PACKED.contains(&a.key.as_str()))
+    }
 
-        let packed_data = format!(
-            "   * Empaquetado:\n      + Tamaño: {}\n      + Perdida: {}", 
-            packed_size,
-            packed_size - packed_size
-        );
+    /// Position of the member with the given name, if any
+    pub fn field_index(&self, name: &str) -> Option<usize> {
+        self.fields.iter().position(|f| f.name == name)
+    }
 
-        format!("📦 Struct:\n{}\n{}\n{}\n", optimized_data, unpacked_data, packed_data)
+    /// Create a report with size and loss for this struct under every packing strategy
+    pub fn report(&self, manager : &TypeManager) -> Vec<StrategyReport> {
+        let packed_size = self.packed_size(manager);
+
+        Strategy::ALL
+            .iter()
+            .map(|strategy| {
+                let size = strategy.size_fn()(self, manager);
+                StrategyReport {
+                    strategy: *strategy,
+                    size,
+                    align: strategy.align_fn()(self, manager),
+                    loss: size - packed_size
+                }
+            })
+            .collect()
     }
 
     /// compute unpacked size 
     pub fn unpacked_size(&self, manager: &TypeManager) -> usize {
-        // We are going to compute the next available position in the struct
-        // where the next data should be. When the loop ends, current position
-        // will actually be our desired struct size
-        // .....................
-        // ^ curr pos starts here
-        // ------..-----.----...
-        //                   ^ curr pos ends here
+        let (_, size) = Struct::layout_offsets(&self.members, manager, Strategy::Unpacked);
 
-        let mut curr_pos = 0;
-        for member in &self.members {
-            let my_type = manager.get(member).unwrap();
-            let size = my_type.size(manager, Struct::unpacked_size);
-            let align = my_type.align(manager, Struct::unpacked_align);
-
-            if curr_pos % align != 0 {
-                curr_pos += align - curr_pos % align
-            }
+        size
+    }
 
-            curr_pos += size
-        }
+    /// Compute offset for every member when stored in declaration order
+    /// ## Params
+    /// * `manager` - manager to retrieve member types from
+    /// ---
+    /// ## Return
+    /// Offset for every member, in declaration order
+    pub fn unpacked_offsets(&self, manager: &TypeManager) -> Vec<usize> {
+        let (offsets, _) = Struct::layout_offsets(&self.members, manager, Strategy::Unpacked);
 
-        curr_pos
+        offsets
     }
 
     /// compute packed size
@@ -309,13 +976,24 @@ impl Struct {
         
         let mut sum = 0;
         for t in &self.members {
-            let my_type = manager.get(&t).unwrap();
-            sum += my_type.size(manager, Struct::packed_size);
+            sum = manager.size_of(t, Strategy::Packed).saturating_add(sum);
         }
 
         sum
     }
 
+    /// Compute offset for every member when packed, there's no padding at all
+    pub fn packed_offsets(&self, manager: &TypeManager) -> Vec<usize> {
+        let mut offsets = Vec::with_capacity(self.members.len());
+        let mut curr_pos = 0;
+        for t in &self.members {
+            offsets.push(curr_pos);
+            curr_pos = manager.size_of(t, Strategy::Packed).saturating_add(curr_pos);
+        }
+
+        offsets
+    }
+
     /// Compute optimized size
     pub fn optimized_size(&self, manager: &TypeManager) -> usize {
         let (_, size) = self.get_optimal_layout(manager);
@@ -323,20 +1001,105 @@ impl Struct {
         size
     }
 
+    /// Compute optimal member order and the offset for every member in that order
+    /// ## Return
+    /// Members in their optimal order and their respective offsets
+    pub fn optimized_offsets(&self, manager: &TypeManager) -> (TypeList, Vec<usize>) {
+        let offsets = self.layout(manager, Strategy::Optimized);
+        let layout = offsets.iter().map(|m| self.members[m.index].clone()).collect();
+
+        (layout, offsets.into_iter().map(|m| m.offset).collect())
+    }
+
+    /// Compute size as a C compiler would: declaration order, padding to align 
+    /// every member and tail padding up to the struct's alignment
+    pub fn c_size(&self, manager: &TypeManager) -> usize {
+        let (_, size) = self.c_layout(manager);
+
+        utils::round_up(size, self.c_align(manager))
+    }
+
+    /// Compute offset for every member as a C compiler would
+    pub fn c_offsets(&self, manager: &TypeManager) -> Vec<usize> {
+        let (offsets, _) = self.c_layout(manager);
+
+        offsets
+    }
+
+    /// Compute alignment as a C compiler would, the biggest alignment among members
+    /// or the one asked for with `@aligned`
+    pub fn c_align(&self, manager: &TypeManager) -> usize {
+        (0..self.members.len())
+            .map(|i| self.c_member_align(i, manager))
+            .chain(attribute_align(&self.attributes))
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// Alignment of a member as a C compiler would place it: 1 in packed structs,
+    /// raised by an `@aligned` attribute on the member
+    fn c_member_align(&self, index: usize, manager: &TypeManager) -> usize {
+        let align = match self.is_packed() {
+            true  => 1,
+            false => manager.align_of(&self.members[index], Strategy::C)
+        };
+
+        attribute_align(&self.fields[index].attributes).map_or(align, |a| a.max(align))
+    }
+
+    /// Offset of every member and end of the last one, as a C compiler would place them
+    fn c_layout(&self, manager: &TypeManager) -> (Vec<usize>, usize) {
+        let mut offsets = Vec::with_capacity(self.members.len());
+        let mut position = 0;
+        for (i, member) in self.members.iter().enumerate() {
+            let offset = utils::round_up(position, self.c_member_align(i, manager));
+            offsets.push(offset);
+            position = offset.saturating_add(manager.size_of(member, Strategy::C));
+        }
+
+        (offsets, position)
+    }
+
+    /// Compute where every member goes under the given strategy
+    /// ## Params
+    /// * `manager` - manager to retrieve member types from
+    /// * `strategy` - packing strategy
+    /// ---
+    /// ## Return
+    /// Position of every member, in memory order
+    pub fn layout(&self, manager: &TypeManager, strategy: Strategy) -> Vec<MemberLayout> {
+        let (order, offsets) : (Vec<usize>, Vec<usize>) = match strategy {
+            Strategy::Unpacked  => ((0..self.members.len()).collect(), self.unpacked_offsets(manager)),
+            Strategy::Packed    => ((0..self.members.len()).collect(), self.packed_offsets(manager)),
+            Strategy::C         => ((0..self.members.len()).collect(), self.c_offsets(manager)),
+            Strategy::Optimized => {
+                // pinned members may have padding before them
+                let (order, _, _) = self.optimize_with(manager, &mut |_, _| {});
+                let members = self.member_sizes(manager);
+                let (offsets, _) = Struct::place_offsets(&order, &members, &self.pins(manager, &members));
+                (order, offsets)
+            }
+        };
+
+        order
+            .into_iter()
+            .zip(offsets)
+            .map(|(index, offset)| MemberLayout {
+                index,
+                offset,
+                size: manager.size_of(&self.members[index], strategy)
+            })
+            .collect()
+    }
+
     /// Compute unpacked alignment
     pub fn unpacked_align(&self, manager: &TypeManager) -> usize {
-        manager
-            .get(&self.members[0])
-            .unwrap()
-            .align(manager, Struct::unpacked_align)
+        self.members.first().map_or(1, |m| manager.align_of(m, Strategy::Unpacked))
     }
 
-    #[allow(unused)] // por completitud, en realidad la alineacion no importa en empaquetado
+    /// Compute packed alignment, it doesn't really matter when packing but it's here for completeness
     pub fn packed_align(&self, manager: &TypeManager) -> usize {
-        manager
-            .get(&self.members[0])
-            .unwrap()
-            .align(manager, Struct::packed_align)
+        self.members.first().map_or(1, |m| manager.align_of(m, Strategy::Packed))
     }
 
     /// Compute optimized aligment, it's different depending on the packing type
@@ -344,71 +1107,206 @@ impl Struct {
     pub fn optimized_align(&self, manager: &TypeManager) -> usize {
         let (layout, _) = self.get_optimal_layout(manager);
 
-        manager
-            .get(&layout[0])
-            .unwrap()
-            .align(manager, Struct::optimized_align)
+        layout.first().map_or(1, |m| manager.align_of(m, Strategy::Optimized))
     }
 
     /// Helper function that returns the optimal data layout for this struct (member's order)
     /// and it's size
     fn get_optimal_layout(&self, manager : &TypeManager) -> (TypeList, usize) { // layout, size
-        // Compute every permutation
-        let permuts = self.members_permutations();
+        let (layout, size, _) = self.optimize(manager);
 
-        // Compute size for every permutation and select the best one
-        let mut min = usize::MAX;
-        let mut layout = vec![];
+        (layout, size)
+    }
 
-        // Search for optimal layout
-        for typelist in permuts {
+    /// Search for the member order with the smallest size, or stride if that's the manager's
+    /// objective, trying every permutation
+    /// ## Params
+    /// * `manager` - manager to retrieve member types from
+    /// ---
+    /// ## Return
+    /// Optimal layout (member's order), its size and statistics about the search
+    pub fn optimize(&self, manager : &TypeManager) -> (TypeList, usize, OptimizerStats) {
+        let (order, size, stats) = self.optimize_with(manager, &mut |_, _| {});
+        let layout = order.iter().map(|i| self.members[*i].clone()).collect();
 
-            let mut curr_pos = 0;
-            for typename in &typelist {
+        (layout, size, stats)
+    }
 
-                let my_type = manager
-                                .get(&typename)
-                                .unwrap();
+    /// Same as `optimize`, but the layout is given as indices into our member list and
+    /// every order better than the ones found before is reported as soon as it's found,
+    /// so a search stopped early still gave something
+    /// ## Params
+    /// * `manager` - manager to retrieve member types from
+    /// * `on_better` - called with every better order, as member indices, and its size
+    pub fn optimize_with(&self, manager : &TypeManager, on_better : &mut dyn FnMut(&[usize], usize)) -> (Vec<usize>, usize, OptimizerStats) {
+        self.optimize_for(manager, manager.objective(), on_better)
+    }
 
-                // compute size and alignment
-                let size = my_type.size(manager, Struct::optimized_size);
-                let align = my_type.align(manager, Struct::optimized_align);
+    /// Same as `optimize_with`, minimizing some objective instead of the manager's
+    /// ## Params
+    /// * `manager` - manager to retrieve member types from
+    /// * `objective` - what makes an order better than another
+    /// * `on_better` - called with every better order, as member indices, and its size
+    pub fn optimize_for(&self, manager : &TypeManager, objective : Objective, on_better : &mut dyn FnMut(&[usize], usize)) -> (Vec<usize>, usize, OptimizerStats) {
+        let start = Instant::now();
+        let mut stats = OptimizerStats::default();
+
+        // member sizes and alignments don't depend on their order, compute them just once
+        let members = self.member_sizes(manager);
+
+        // members sharing a group label have to stay together, pinned ones where they were declared
+        let groups : Vec<Option<&str>> = self.fields.iter().map(|f| f.attribute(GROUP)).collect();
+        let pins = self.pins(manager, &members);
+
+        // Start with declaration order as our best candidate, if it keeps groups together
+        let declared : Vec<usize> = (0..members.len()).collect();
+        let mut best = declared.clone();
+        let mut min = match Struct::groups_together(&best, &groups) {
+            true  => Struct::place_members(&best, &members, &pins, usize::MAX).unwrap_or(usize::MAX),
+            false => usize::MAX
+        };
+        if min != usize::MAX {
+            on_better(&best, min);
+        }
 
-                // if not aligned, add to position extra bytes to slign next field
-                if curr_pos % align != 0 {
-                    curr_pos += align - curr_pos % align;
+        // an order has to cost less than the best one, the stride is a multiple of the array alignment
+        let align = self.array_align(manager);
+        let mut bound = objective.bound(objective.cost(min, align), align);
+
+        // Search for optimal layout, dropping a permutation as soon as it can't beat the best one.
+        // Every so often check if we were asked to stop or ran out of time, the best order so
+        // far is kept then, once there's one
+        for order in utils::Permutations::new((0..members.len()).collect()) {
+            if stats.permutations % CANCEL_CHECK == 0 && min != usize::MAX {
+                if cancel::requested() {
+                    stats.cancelled = true;
+                    break
+                }
+                if manager.limits().optimizer.is_some_and(|limit| start.elapsed() > limit) {
+                    stats.timed_out = true;
+                    limits::time_out();
+                    break
                 }
+            }
+            stats.permutations += 1;
 
-                curr_pos += size;
+            if !Struct::groups_together(&order, &groups) {
+                stats.pruned += 1;
+                continue
+            }
+
+            match Struct::place_members(&order, &members, &pins, bound) {
+                Some(size) => { on_better(&order, size); min = size; best = order; bound = objective.bound(objective.cost(size, align), align) },
+                None       => stats.pruned += 1
             }
-            
-            if curr_pos < min {
-                min = curr_pos;
-                layout = typelist;
-            } 
         }
 
-        (layout,min)
+        // no order keeps every constraint, keep the declared one
+        if min == usize::MAX {
+            min = Struct::place_members(&declared, &members, &pins, usize::MAX).unwrap_or(usize::MAX);
+            best = declared;
+            on_better(&best, min);
+        }
+
+        stats.elapsed = start.elapsed();
+        profile::optimized(&stats);
+        crate::metrics::optimized(stats.elapsed);
+
+        (best, min, stats)
     }
 
-    // compute every possible permutation for the member list
-    fn members_permutations(&self) -> Vec<TypeList> {
-            // get indices
-            let indices = utils::permutations(&mut (0..self.members.len()).collect());
-            // Convert permutations of indices into permutations of names
-            indices
-                .iter()
-                .map(
-                    |l| l
-                        .iter()
-                        .map(
-                            |i| 
-                            self.members[*i].clone()
-                        )
-                        .collect()
-                )
-                .collect()
-        // Esto se podria implementar mas eficientemente con iteradores, pero no nos pagan lo suficiente
+    /// Helper function computing size for members placed in the given order
+    /// ## Params
+    /// * `order` - indices of members, in order
+    /// * `members` - size and alignment for every member
+    /// * `pins` - declared offset of every member marked `@fijo`, by index
+    /// * `bound` - give up when size reaches this value
+    /// ---
+    /// ## Return
+    /// Size for this order, or None if it's not smaller than the bound or it moves a pinned member
+    fn place_members(order : &[usize], members : &[(usize, usize)], pins : &[Option<usize>], bound : usize) -> Option<usize> {
+        let mut curr_pos = 0;
+        for (position, i) in order.iter().enumerate() {
+            let (size, align) = members[*i];
+
+            // if not aligned, add to position extra bytes to align next field
+            curr_pos = utils::round_up(curr_pos, align);
+
+            // pinned members keep their declared position and offset, padded up to it
+            if let Some(offset) = pins[*i] {
+                if position != *i || curr_pos > offset {
+                    return None
+                }
+                curr_pos = offset;
+            }
+
+            curr_pos = curr_pos.saturating_add(size);
+            if curr_pos >= bound {
+                return None
+            }
+        }
+
+        Some(curr_pos)
+    }
+
+    /// Tells if every member with a group label is next to the other members with the same label
+    /// ## Params
+    /// * `order` - member indices, in memory order
+    /// * `groups` - group label for every member, by index
+    fn groups_together(order : &[usize], groups : &[Option<&str>]) -> bool {
+        let mut closed : Vec<&str> = vec![];     // groups we already left
+        let mut current = None;
+
+        for i in order {
+            let group = groups[*i];
+            if group == current {
+                continue
+            }
+
+            if let Some(left) = current {
+                closed.push(left);
+            }
+            if matches!(group, Some(g) if closed.contains(&g)) {
+                return false
+            }
+            current = group;
+        }
+
+        true
+    }
+
+    /// Helper function placing members one after another in the given order, 
+    /// adding padding before every member that's not properly aligned
+    /// ## Params
+    /// * `members` - members to place, in order
+    /// * `manager` - manager to retrieve member types from
+    /// * `strategy` - strategy to compute nested types' size and alignment
+    /// ---
+    /// ## Return
+    /// Offset for every member and the total size, without tail padding
+    fn layout_offsets(members: &[Name], manager: &TypeManager, strategy: Strategy) -> (Vec<usize>, usize) {
+        // We are going to compute the next available position in the struct
+        // where the next data should be. When the loop ends, current position
+        // will actually be our desired struct size
+        // .....................
+        // ^ curr pos starts here
+        // ------..-----.----...
+        //                   ^ curr pos ends here
+
+        let mut offsets = Vec::with_capacity(members.len());
+        let mut curr_pos = 0;
+        for member in members {
+            let size = manager.size_of(member, strategy);
+            let align = manager.align_of(member, strategy);
+
+            // if not aligned, add to position extra bytes to align next field
+            curr_pos = utils::round_up(curr_pos, align);
+
+            offsets.push(curr_pos);
+            curr_pos = curr_pos.saturating_add(size)
+        }
+
+        (offsets, curr_pos)
     }
 }
 
@@ -421,71 +1319,87 @@ impl Union {
         }
     }
 
-    /// Return a human-readable string describing this type
+    /// Create a report with size and loss for this union under every packing strategy
     /// ## Params
     /// * `manager` - manager object where the types are stored
-    /// --
+    /// ---
     /// ## Return 
-    /// String with required details for our union type
-    pub fn display(&self, manager : &TypeManager) -> String {
+    /// Size, alignment and loss for every strategy
+    pub fn report(&self, manager : &TypeManager) -> Vec<StrategyReport> {
+        Strategy::ALL
+            .iter()
+            .map(|strategy| StrategyReport {
+                strategy: *strategy,
+                size: self.size_in(manager, *strategy),
+                align: self.align(manager, strategy.align_fn()),
+                loss: self.loss(manager, *strategy)
+            })
+            .collect()
+    }
 
-        // Comput loss & size for every possible packing type
-        let unpacked_size   = self.size(manager, Struct::unpacked_size);
-        let unpacked_loss   = self.loss(manager, Struct::unpacked_size);
-        let packed_size     = self.size(manager, Struct::packed_size);
-        let packed_loss     = self.loss(manager, Struct::packed_size);
-        let optimal_size    = self.size(manager, Struct::optimized_size);
-        let optimal_loss    = self.loss(manager, Struct::optimized_size);
-
-        let optimized_data = format!(
-            "* Optimizado:\n      + Tamaño: {}\n      + Perdida: {}", 
-            optimal_size, 
-            optimal_loss
-        );
-
-        let unpacked_data = format!(
-            "* Sin Empaquetar:\n      + Tamaño: {}\n      + Perdida: {}", 
-            unpacked_size, 
-            unpacked_loss
-        );
-
-        let packed_data  = format!(
-            "* Empaquetado:\n      + Tamaño: {}\n      + Perdida: {}", 
-            packed_size, 
-            packed_loss
-        );
-        
+    /// Size of a variant against the size of the whole union, under every packing strategy
+    /// ## Params
+    /// * `manager` - manager object where the types are stored
+    /// * `variant` - name of the active variant's type, as stored
+    /// ---
+    /// ## Return
+    /// Variant size, union size and slack for every strategy, None if the type is not a variant
+    pub fn variant_report(&self, manager : &TypeManager, variant : &Name) -> Option<Vec<VariantReport>> {
+        let my_type = manager.get(self.variants.iter().find(|v| *v == variant)?)?;
 
-        format!("👺 Variante:\n{}\n{}\n{}\n", optimized_data, unpacked_data, packed_data)
+        let reports = Strategy::ALL
+                        .iter()
+                        .map(|strategy| {
+                            let size = self.size_in(manager, *strategy);
+                            let variant = strategy.type_size(my_type, manager);
+                            VariantReport { strategy: *strategy, variant, size, slack: size.saturating_sub(variant) }
+                        })
+                        .collect();
+
+        Some(reports)
     }
 
-    /// Return loss for this ariant register depending on its packing type for structs
-    fn loss(&self, manager : &TypeManager, struct_packing_size : fn (&Struct, &TypeManager) -> usize) -> usize {
-        let size = self.size(manager, struct_packing_size);
-        let biggest_packed = {
-            
-            // Compute variant whose loss is the lesser
-            let mut packed_size = usize::MIN;
-            for typename in &self.variants {
-                // Get the type
-                let my_type = manager.get(&typename).unwrap();
-
-                // if not the biggest one, continue
-                if my_type.size(manager, struct_packing_size) != size {
-                    continue
-                }
-
-                // check if the loss of this type is less than our current loss
-                packed_size = std::cmp::max(
-                                    packed_size, 
-                                    my_type.size(manager, Struct::packed_size)
-                                )
-            }
+    /// Padding and slack of every variant under every packing strategy
+    /// ## Params
+    /// * `manager` - manager object where the types are stored
+    /// ---
+    /// ## Return
+    /// A row per strategy and variant, strategies in report order and variants in declaration order
+    pub fn variant_losses(&self, manager : &TypeManager) -> Vec<VariantLoss> {
+        Strategy::ALL
+            .iter()
+            .flat_map(|strategy| self.variant_losses_in(manager, *strategy))
+            .collect()
+    }
 
-            packed_size
-        };
+    /// Padding and slack of every variant under a single strategy
+    fn variant_losses_in(&self, manager : &TypeManager, strategy : Strategy) -> Vec<VariantLoss> {
+        let union_size = self.size_in(manager, strategy);
+
+        self.variants
+            .iter()
+            .filter_map(|name| manager.get(name).map(|my_type| (name, my_type)))
+            .map(|(name, my_type)| {
+                let size = strategy.type_size(my_type, manager);
+                VariantLoss {
+                    name: name.clone(),
+                    strategy,
+                    size,
+                    padding: size.saturating_sub(my_type.size(manager, Struct::packed_size)),
+                    slack: union_size.saturating_sub(size)
+                }
+            })
+            .collect()
+    }
 
-        size - biggest_packed
+    /// Return loss for this union depending on its packing type for structs: bytes
+    /// wasted by the variant wasting the least, padding and slack together
+    fn loss(&self, manager : &TypeManager, strategy : Strategy) -> usize {
+        self.variant_losses_in(manager, strategy)
+            .iter()
+            .map(VariantLoss::waste)
+            .min()
+            .unwrap_or(0)
     }
 
     /// Return size for this variant type given the struct packing type
@@ -500,9 +1414,7 @@ impl Union {
     {
         // Linear search for max value
         let mut maxi = usize::MIN;
-        for t in &self.variants {
-            // the type is available, our api to add types will ensure it
-            let my_type = manager.get(&t).unwrap();
+        for my_type in self.variants.iter().filter_map(|t| manager.get(t)) {
             let size = my_type.size(manager, struct_packing_size);
             
             maxi = std::cmp::max(size, maxi)
@@ -511,6 +1423,27 @@ impl Union {
         maxi
     }
 
+    /// Return size for this variant type under the given strategy
+    pub fn size_in(&self, manager: &TypeManager, strategy: Strategy) -> usize {
+        let _level = profile::enter();
+        match strategy {
+            Strategy::C => self.c_size(manager),
+            _ => self.size(manager, strategy.size_fn())
+        }
+    }
+
+    /// Compute size as a C compiler would: the biggest variant plus tail padding
+    /// up to the union's alignment
+    pub fn c_size(&self, manager: &TypeManager) -> usize {
+        let biggest = self.variants
+                        .iter()
+                        .map(|v| manager.size_of(v, Strategy::C))
+                        .max()
+                        .unwrap_or(0);
+
+        utils::round_up(biggest, self.align(manager, Struct::c_align))
+    }
+
     /// Get alignment for a variant type, 
     /// is the lowest common multiple between every align for every possible variant
     /// ## Params 
@@ -524,14 +1457,11 @@ impl Union {
                     struct_packing_align : fn (&Struct, &TypeManager) -> usize
                 ) -> usize
     {
+        // Compute lcm for every variant's alignment
         let mut lcm = 1;
-        for i in 0..(self.variants.len() - 1) {
-
-            // Compute lcm for every number
-            let size1 = manager.get(&self.variants[i]).unwrap().align(manager, struct_packing_align);
-            let size2 = manager.get(&self.variants[i+1]).unwrap().align(manager, struct_packing_align);
-
-            lcm = utils::lcm(size1, size2)
+        for my_type in self.variants.iter().filter_map(|t| manager.get(t)) {
+            let align = my_type.align(manager, struct_packing_align);
+            lcm = utils::lcm(lcm, align)
         }
 
         lcm
@@ -543,14 +1473,30 @@ impl TypeError {
     /// Turns an error into an human-readable string
     /// ## Return
     /// An human-readable string for the given error
+    #[allow(clippy::useless_format)]
     pub fn display(&self) -> String {
         match self {
             TypeError::TypeRedefinition => {
                 String::from("No puedes redefinir un tipo ya existente")
             },
+            TypeError::Strict(d) => {
+                format!("{} (modo estricto)", d.display())
+            },
+            TypeError::CaseRedefinition(name, stored) => {
+                format!("No puedes redefinir '{}', ya existe como '{}' y no se distinguen mayúsculas", name, stored)
+            },
             TypeError::TypeDoesNotExist(s) => {
                 format!("El símbolo '{}' no existe", s)
             },
+            TypeError::UnknownMember(s, suggestions) => {
+                let names : Vec<String> = suggestions.iter().map(|(name, kind)| format!("'{}' ({})", name, kind)).collect();
+                let names = match names.split_last() {
+                    Some((last, [])) => last.clone(),
+                    Some((last, rest)) => format!("{} o {}", rest.join(", "), last),
+                    None => String::new()
+                };
+                format!("El símbolo '{}' no existe, quizás quisiste decir {}", s, names)
+            },
             TypeError::EmptyCompoundType => {
                 format!("No se permiten datos compuestos vacíos")
             },
@@ -559,7 +1505,76 @@ impl TypeError {
             },
             TypeError::NoZeroAlign => {
                 format!("No se permite alinear a 0")
-            }
+            },
+            TypeError::SizeOverflow => {
+                String::from("El tamaño del tipo es demasiado grande para representarlo")
+            },
+            TypeError::NotAStruct(s) => {
+                format!("El tipo '{}' no es un struct", s)
+            },
+            TypeError::UnknownField(t, f) => {
+                format!("El struct '{}' no tiene un campo '{}'", t, f)
+            },
+            TypeError::Unflattenable(t, f) => {
+                format!("El campo '{}' de '{}' no puede quedar en su desplazamiento en un struct plano con la disposición de C", f, t)
+            },
+            TypeError::DuplicateField(s) => {
+                format!("El campo '{}' está repetido", s)
+            },
+            TypeError::NotAUnion(s) => {
+                format!("El tipo '{}' no es una unión", s)
+            },
+            TypeError::NotAVariant(u, t) => {
+                format!("'{}' no es una variante de la unión '{}'", t, u)
+            },
+            TypeError::TooLarge(t, size, limit) => {
+                format!("'{}' ocupa {} bytes, más de los {} admitidos", t, size, limit)
+            },
+            TypeError::CyclicDefinition(t, user) => {
+                format!("'{}' no puede usar '{}', que ya está hecho con '{}'", t, user, t)
+            },
+            TypeError::BitfieldTooWide(s) => {
+                format!("El campo '{}' tiene más bits que su tipo", s)
+            },
+            TypeError::InvalidAttribute(a) => {
+                format!("El atributo '{}' no es válido, la alineación debe ser una potencia de 2", a)
+            },
+            TypeError::TooManyTypes(limit) => {
+                format!("La sesión ya tiene {} tipos, el límite configurado", limit)
+            },
+            TypeError::TooManyMembers(t, members, limit) => {
+                format!("'{}' tiene {} miembros, el límite es {}", t, members, limit)
+            },
+            TypeError::TooDeep(t, depth, limit) => {
+                format!("'{}' anida {} tipos compuestos, el límite es {}", t, depth, limit)
+            },
+            TypeError::Unresolved(pending) => {
+                let missing : Vec<String> = pending.iter().map(|(t, missing)| format!("'{}' necesita {}", t, missing.join(", "))).collect();
+                format!("Quedan tipos sin resolver: {}", missing.join("; "))
+            },
+            TypeError::TooBigToSolve(t, members) => {
+                format!("'{}' tiene {} miembros, el solucionador exacto toma hasta {}", t, members, MAX_SOLVER_MEMBERS)
+            },
+            TypeError::NoMembers(t) => {
+                format!("'{}' se quedó sin miembros, su disposición no está definida", t)
+            },
+            TypeError::ZeroAlign(t) => {
+                format!("'{}' tiene alineación 0, su disposición no está definida", t)
+            },
+            TypeError::Expression(e) => e.display()
         }
     }
-}
\ No newline at end of file
+}
+
+/// Alignment asked for with an `@aligned=N` attribute, the biggest one if there are several
+/// ## Return
+/// The alignment, or none if there's no such attribute or its value is not a power of 2
+pub fn attribute_align(attributes: &[Attribute]) -> Option<usize> {
+    attributes
+        .iter()
+        .filter(|a| ALIGNED.contains(&a.key.as_str()))
+        .map(|a| a.value.as_deref().and_then(|v| v.parse::<usize>().ok()).filter(|n| n.is_power_of_two()))
+        .collect::<Option<Vec<usize>>>()?
+        .into_iter()
+        .max()
+}
@@ -0,0 +1,119 @@
+/*
+    Optimizer objectives: by default the optimizer looks for the smallest size,
+    but in an array every element takes its size rounded up to the alignment, the
+    stride. Saving a byte of size that doesn't save a whole alignment unit saves
+    nothing there, so the stride objective only takes orders with a smaller stride
+    and keeps the declared order when reordering wouldn't make arrays any smaller
+*/
+use super::{Name, Strategy, Struct, Type, TypeError, TypeManager};
+use crate::utils;
+
+/// What the optimizer minimizes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Objective {
+    #[default]
+    Size,       // bytes of a single struct
+    Stride      // bytes between elements of an array of structs
+}
+
+/// Best member order for an objective, or declaration order
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectiveReport {
+    pub objective: Option<Objective>,   // none for declaration order
+    pub order:     Vec<usize>,          // member indices, in memory order
+    pub size:      usize,
+    pub stride:    usize
+}
+
+impl Objective {
+
+    /// Every objective, in the order they are reported
+    pub const ALL : [Objective; 2] = [Objective::Size, Objective::Stride];
+
+    /// Parse an objective name: `tamaño` or `paso`
+    pub fn parse(name : &str) -> Option<Objective> {
+        match name.to_lowercase().as_str() {
+            "tamaño" | "tamano" | "size" => Some(Objective::Size),
+            "paso" | "stride"            => Some(Objective::Stride),
+            _ => None
+        }
+    }
+
+    /// Name for this objective, as parsed by `parse`
+    pub fn name(self) -> &'static str {
+        match self {
+            Objective::Size   => "tamaño",
+            Objective::Stride => "paso"
+        }
+    }
+
+    /// What an order with some size costs under this objective
+    /// ## Params
+    /// * `size` - size of the order
+    /// * `align` - alignment arrays step by
+    pub fn cost(self, size : usize, align : usize) -> usize {
+        match self {
+            Objective::Size   => size,
+            Objective::Stride => utils::round_up(size, align)
+        }
+    }
+
+    /// Size an order has to stay under to cost less than some cost
+    pub(super) fn bound(self, cost : usize, align : usize) -> usize {
+        match self {
+            Objective::Size   => cost,
+            Objective::Stride => cost.checked_sub(align).map_or(0, |below| below.saturating_add(1))
+        }
+    }
+}
+
+impl TypeManager {
+
+    /// Pick what the optimizer minimizes for every struct
+    pub fn set_objective(&mut self, objective : Objective) {
+        self.objective = objective;
+        self.layout_cache().clear();
+    }
+
+    /// What the optimizer minimizes
+    pub fn objective(&self) -> Objective {
+        self.objective
+    }
+
+    /// Compare the best member order of a struct for every objective with declaration order
+    /// ## Params
+    /// * `typename` - struct to optimize
+    /// ---
+    /// ## Return
+    /// Order, size and stride for every objective and then for declaration order,
+    /// or an error if the type is not a struct
+    pub fn objectives(&self, typename : &Name) -> Result<Vec<ObjectiveReport>, TypeError> {
+        let s = match self.get(typename) {
+            None => return Err(TypeError::TypeDoesNotExist(typename.clone())),
+            Some(Type::Struct(s)) => s,
+            Some(_) => return Err(TypeError::NotAStruct(typename.clone()))
+        };
+
+        let align = s.array_align(self);
+        let mut reports = vec![];
+        for objective in Objective::ALL {
+            let (order, size, _) = s.optimize_for(self, objective, &mut |_, _| {});
+            reports.push(ObjectiveReport { objective: Some(objective), order, size, stride: Objective::Stride.cost(size, align) });
+        }
+
+        let declared : Vec<usize> = (0..s.members.len()).collect();
+        let (_, size) = Struct::place_offsets(&declared, &s.member_sizes(self), &vec![None; declared.len()]);
+        reports.push(ObjectiveReport { objective: None, order: declared, size, stride: Objective::Stride.cost(size, align) });
+
+        Ok(reports)
+    }
+}
+
+impl Struct {
+
+    /// Alignment arrays of this struct step by: the biggest among its members,
+    /// whatever their order
+    pub fn array_align(&self, manager : &TypeManager) -> usize {
+        self.members.iter().map(|m| manager.align_of(m, Strategy::Optimized)).max().unwrap_or(1)
+    }
+}
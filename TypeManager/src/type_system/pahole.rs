@@ -0,0 +1,171 @@
+/*
+    pahole output: the C layout of structs and unions written the way the
+    `pahole` tool writes the layouts it reads from debug info, so scripts and
+    habits built around it work on modeled types too. Every member with its
+    offset and size, holes between members, cache line boundaries and a
+    summary with the size, the holes and the padding at the end
+*/
+use super::{BitfieldPolicy, Field, Name, Strategy, Type, TypeError, TypeManager};
+
+/// Width of the column of member types
+const TYPE_COLUMN : usize = 26;
+
+/// Width of the column of member names
+const NAME_COLUMN : usize = 21;
+
+/// A member placed in bits, as pahole sees it
+struct Placed {
+    typename: String,
+    name:     String,
+    offset:   usize,        // in bits
+    bits:     usize,
+    unit:     Option<usize> // bytes of the type of a bitfield, none for whole members
+}
+
+impl Placed {
+
+    /// First bit after this member
+    fn end(&self) -> usize {
+        self.offset + self.bits
+    }
+}
+
+impl TypeManager {
+
+    /// Describe a struct or union as pahole does, under the C layout
+    /// ## Params
+    /// * `typename` - type to describe
+    /// * `policy` - rules for placing bitfields
+    /// * `line` - cache line size, in bytes
+    /// ---
+    /// ## Return
+    /// The description, ending in `};`, or an error if the type doesn't exist or is not a struct or union
+    pub fn pahole(&self, typename : &Name, policy : BitfieldPolicy, line : usize) -> Result<String, TypeError> {
+        let t = self.checked(typename)?;
+        let line = line.max(1);
+        let mut size = Strategy::C.type_size(t, self);
+
+        let (keyword, members) : (&str, Vec<Placed>) = match t {
+            Type::Struct(s) if s.fields.iter().any(|f| f.bit_width().is_some()) => {
+                let layout = s.bitfield_layout(self, policy)?;
+                size = layout.size;
+                let members = layout.members.iter().map(|m| Placed {
+                    typename: self.c_name(&s.members[m.index]),
+                    name:     s.fields[m.index].name.clone(),
+                    offset:   m.offset,
+                    bits:     m.bits,
+                    unit:     match m.bitfield {
+                        true  => self.get(&s.members[m.index]).map(|t| Strategy::C.type_size(t, self)),
+                        false => None
+                    }
+                }).collect();
+                ("struct", members)
+            },
+            Type::Struct(s) => ("struct", s.layout(self, Strategy::C).iter().map(|m| Placed {
+                typename: self.c_name(&s.members[m.index]),
+                name:     s.fields[m.index].name.clone(),
+                offset:   m.offset * 8,
+                bits:     m.size * 8,
+                unit:     None
+            }).collect()),
+            Type::Union(u) => ("union", u.variants.iter().enumerate().map(|(i, v)| Placed {
+                typename: self.c_name(v),
+                name:     Field::default_name(i),
+                offset:   0,
+                bits:     self.get(v).map_or(0, |t| Strategy::C.type_size(t, self)) * 8,
+                unit:     None
+            }).collect()),
+            _ => return Err(TypeError::NotAStruct(typename.clone()))
+        };
+
+        let mut text = format!("{} {} {{\n", keyword, typename);
+        let (mut holes, mut hole_bytes, mut bit_holes, mut hole_bits) = (0, 0, 0, 0);
+        let mut boundary = line;   // next cache line boundary, in bytes
+        let mut end : usize = 0;    // first bit after the members so far
+        for (i, m) in members.iter().enumerate() {
+            if keyword == "struct" && i > 0 && m.offset > end {
+                let (bytes, bits) = ((m.offset - end) / 8, (m.offset - end) % 8);
+                text += "\n";
+                if bytes > 0 {
+                    text += &format!("\t/* XXX {} byte{} hole, try to pack */\n", bytes, plural(bytes));
+                    holes += 1;
+                    hole_bytes += bytes;
+                }
+                if bits > 0 {
+                    text += &format!("\t/* XXX {} bit{} hole, try to pack */\n", bits, plural(bits));
+                    bit_holes += 1;
+                    hole_bits += bits;
+                }
+                text += "\n";
+            }
+
+            let start = m.offset / 8;
+            while start >= boundary {
+                text += &match start - boundary {
+                    0 => format!("\t/* --- cacheline {} boundary ({} bytes) --- */\n", boundary / line, boundary),
+                    ago => format!("\t/* --- cacheline {} boundary ({} bytes) was {} bytes ago --- */\n", boundary / line, boundary, ago)
+                };
+                boundary += line;
+            }
+
+            let name = match m.unit {
+                Some(_) => format!("{}:{};", m.name, m.bits),
+                None => format!("{};", m.name)
+            };
+            text += &format!("\t{:<tw$} {:<nw$} ", m.typename, name, tw = TYPE_COLUMN, nw = NAME_COLUMN);
+            text += &match (m.unit, keyword) {
+                (Some(unit), _) => format!("/* {:>5}:{:>2} {:>4} */\n", start, m.offset % 8, unit),
+                (None, "union") => format!("/* {:>11} */\n", m.bits / 8),
+                (None, _) => format!("/* {:>5} {:>5} */\n", start, m.bits / 8)
+            };
+
+            // a member crossing a boundary gets it right after
+            let last = m.end().div_ceil(8);
+            while keyword == "struct" && start < boundary && last > boundary {
+                text += &format!("\t/* --- cacheline {} boundary ({} bytes) was {} bytes ago --- */\n", boundary / line, boundary, last - boundary);
+                boundary += line;
+            }
+            end = end.max(m.end());
+        }
+
+        let padding = size.saturating_sub(end.div_ceil(8));
+        let bitfields = members.iter().any(|m| m.unit.is_some());
+        text += &format!("\n\t/* size: {}, cachelines: {}, members: {} */\n", size, size.div_ceil(line), members.len());
+        if holes > 0 || bit_holes > 0 {
+            let sum = match bitfields {
+                true  => format!("sum members (bits): {}", members.iter().map(|m| m.bits).sum::<usize>()),
+                false => format!("sum members: {}", members.iter().map(|m| m.bits / 8).sum::<usize>())
+            };
+            text += &format!("\t/* {}, holes: {}, sum holes: {} */\n", sum, holes, hole_bytes);
+        }
+        if bit_holes > 0 {
+            text += &format!("\t/* bit holes: {}, sum bit holes: {} bits */\n", bit_holes, hole_bits);
+        }
+        if padding > 0 && keyword == "struct" {
+            text += &format!("\t/* padding: {} */\n", padding);
+        }
+        if !size.is_multiple_of(line) {
+            text += &format!("\t/* last cacheline: {} bytes */\n", size % line);
+        }
+
+        Ok(text + "};")
+    }
+
+    /// Name of a type as C writes it: with `struct`, `union` or `enum` before compound types
+    fn c_name(&self, typename : &Name) -> String {
+        match self.get(typename) {
+            Some(Type::Struct(_)) => format!("struct {}", typename),
+            Some(Type::Union(_))  => format!("union {}", typename),
+            Some(Type::Enum(_))   => format!("enum {}", typename),
+            _ => typename.clone()
+        }
+    }
+}
+
+/// `s` for plural amounts, as pahole writes `1 byte` and `3 bytes`
+fn plural(n : usize) -> &'static str {
+    match n {
+        1 => "",
+        _ => "s"
+    }
+}
@@ -0,0 +1,128 @@
+/*
+    Size against locality: besides being small, a struct whose `@caliente` members
+    sit in its first cache line touches a single line on its hot path. The two
+    goals pull apart when hot members are small and would go last in the smallest
+    order, so instead of a single best order this search gives the Pareto front:
+    every order no other beats in both size and hot bytes out of the first line.
+    Only orders keeping groups together and pinned members in place are tried
+*/
+use super::{cancel, limits, Name, Struct, Type, TypeError, TypeManager, CANCEL_CHECK, GROUP};
+use crate::utils;
+
+/// Attributes for members used all the time
+pub const HOT : [&str; 1] = ["caliente"];
+
+/// An order no other one beats in both goals
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tradeoff {
+    pub order:   Vec<usize>,    // member indices, in memory order
+    pub size:    usize,
+    pub outside: usize          // bytes of hot members out of the first cache line
+}
+
+/// Every trade-off between size and locality for a struct
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pareto {
+    pub line:      usize,
+    pub hot:       Vec<usize>,      // indices of members marked hot
+    pub tradeoffs: Vec<Tradeoff>,   // smallest first, the last one keeps the most hot bytes in the first line
+    pub stopped:   bool             // cancelled or out of time before trying every order
+}
+
+impl TypeManager {
+
+    /// Find every trade-off between size and keeping `@caliente` members in the first
+    /// cache line, trying every member order that keeps groups together
+    /// ## Params
+    /// * `typename` - struct to lay out
+    /// * `line` - cache line size, in bytes
+    /// ---
+    /// ## Return
+    /// Orders on the Pareto front, or an error if the type is not a struct
+    pub fn pareto(&self, typename : &Name, line : usize) -> Result<Pareto, TypeError> {
+        match self.get(typename) {
+            None => Err(TypeError::TypeDoesNotExist(typename.clone())),
+            Some(Type::Struct(s)) => Ok(s.pareto(self, line.max(1))),
+            Some(_) => Err(TypeError::NotAStruct(typename.clone()))
+        }
+    }
+}
+
+impl Struct {
+
+    /// Find every trade-off between size and locality, see `TypeManager::pareto`
+    pub fn pareto(&self, manager : &TypeManager, line : usize) -> Pareto {
+        let start = std::time::Instant::now();
+        let members = self.member_sizes(manager);
+        let pins = self.pins(manager, &members);
+        let groups : Vec<Option<&str>> = self.fields.iter().map(|f| f.attribute(GROUP)).collect();
+        let hot : Vec<usize> = (0..members.len())
+                                .filter(|i| self.fields[*i].attributes.iter().any(|a| HOT.contains(&a.key.as_str())))
+                                .collect();
+
+        // smallest order found for every amount of hot bytes out of the first line
+        let mut best : Vec<Tradeoff> = vec![];
+        let mut stopped = false;
+        for (tried, order) in utils::Permutations::new((0..members.len()).collect()).enumerate() {
+            if tried % CANCEL_CHECK == 0 && !best.is_empty() {
+                if cancel::requested() {
+                    stopped = true;
+                    break
+                }
+                if manager.limits().optimizer.is_some_and(|limit| start.elapsed() > limit) {
+                    stopped = true;
+                    limits::time_out();
+                    break
+                }
+            }
+            if !Struct::groups_together(&order, &groups) {
+                continue
+            }
+
+            let (size, outside) = match Struct::locality(&order, &members, &pins, &hot, line) {
+                Some(placed) => placed,
+                None => continue
+            };
+            match best.iter_mut().find(|t| t.outside == outside) {
+                Some(t) if size < t.size => *t = Tradeoff { order, size, outside },
+                Some(_) => {},
+                None => best.push(Tradeoff { order, size, outside })
+            }
+        }
+
+        // no order keeps every constraint, keep the declared one
+        if best.is_empty() && !stopped {
+            let order : Vec<usize> = (0..members.len()).collect();
+            if let Some((size, outside)) = Struct::locality(&order, &members, &pins, &hot, line) {
+                best.push(Tradeoff { order, size, outside });
+            }
+        }
+
+        // keep the orders no other one beats in both goals
+        best.sort_by_key(|t| (t.size, t.outside));
+        let mut tradeoffs : Vec<Tradeoff> = vec![];
+        for t in best {
+            if tradeoffs.last().is_none_or(|last| t.outside < last.outside) {
+                tradeoffs.push(t);
+            }
+        }
+
+        Pareto { line, hot, tradeoffs, stopped }
+    }
+
+    /// Size of an order and bytes of hot members it leaves out of the first cache line,
+    /// none if it moves a pinned member
+    fn locality(order : &[usize], members : &[(usize, usize)], pins : &[Option<usize>], hot : &[usize], line : usize) -> Option<(usize, usize)> {
+        Struct::place_members(order, members, pins, usize::MAX)?;
+
+        let (offsets, size) = Struct::place_offsets(order, members, pins);
+        let outside = order
+                        .iter()
+                        .zip(offsets)
+                        .filter(|(i, _)| hot.contains(i))
+                        .map(|(i, offset)| (offset + members[*i].0).saturating_sub(offset.max(line)))
+                        .sum();
+
+        Some((size, outside))
+    }
+}
@@ -0,0 +1,56 @@
+/*
+    Memory planning: total bytes taken by arrays of several types, so a set of
+    object counts can be checked against a fixed budget such as the static RAM
+    of a microcontroller
+*/
+use super::{Name, Strategy, TypeError, TypeManager};
+use crate::utils;
+
+/// Bytes taken by every array under a single strategy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrategyPlan {
+    pub strategy:  Strategy,
+    pub subtotals: Vec<usize>,  // one per array, in the order given
+    pub total:     usize
+}
+
+impl StrategyPlan {
+
+    /// Tells if every array fits in the given budget
+    pub fn fits(&self, budget : usize) -> bool {
+        self.total <= budget
+    }
+}
+
+impl TypeManager {
+
+    /// Compute the bytes taken by arrays of several types under every strategy
+    /// ## Params
+    /// * `arrays` - type and amount of instances of every array
+    /// ---
+    /// ## Return
+    /// Subtotals and total for every strategy, in report order, or an error if some type does not exist
+    pub fn plan(&self, arrays : &[(Name, usize)]) -> Result<Vec<StrategyPlan>, TypeError> {
+        let types = arrays
+                        .iter()
+                        .map(|(name, count)| self.get(name).map(|t| (t, *count)).ok_or_else(|| TypeError::TypeDoesNotExist(name.clone())))
+                        .collect::<Result<Vec<_>, TypeError>>()?;
+
+        let plans = Strategy::ALL
+                        .iter()
+                        .map(|strategy| {
+                            let subtotals : Vec<usize> = types
+                                                            .iter()
+                                                            .map(|(t, count)| {
+                                                                let stride = utils::round_up(strategy.type_size(t, self), strategy.type_align(t, self));
+                                                                stride.saturating_mul(*count)
+                                                            })
+                                                            .collect();
+                            let total = subtotals.iter().fold(0usize, |total, s| total.saturating_add(*s));
+                            StrategyPlan { strategy: *strategy, subtotals, total }
+                        })
+                        .collect();
+
+        Ok(plans)
+    }
+}
@@ -0,0 +1,133 @@
+/*
+    Pointers and optional values. A pointer is never null when it points to
+    something, so an optional pointer can use null for "nothing" (a niche) and
+    stay pointer sized, like `Option<&T>` in Rust. Other types need a flag byte.
+    Fat pointers carry a second word: a length for slices, a vtable for trait objects
+*/
+use super::{Name, Strategy, Type, TypeError, TypeManager};
+use crate::utils;
+
+/// Size and alignment of pointers in the default target, in bytes
+pub const POINTER_SIZE : usize = 8;
+
+/// Words a pointer is made of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerKind {
+    Thin,       // just the address
+    Slice,      // address and length, like `&[T]`
+    Trait       // address and vtable address, like `&dyn Trait`
+}
+
+/// Pointer type structure
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pointer {
+    pub target: Name,   // pointed type, it may be defined later
+    pub kind:   PointerKind
+}
+
+/// Layout of an optional value of some type
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionalReport {
+    pub size:       usize,          // size of the wrapped type under the C strategy
+    pub align:      usize,
+    pub flagged:    usize,          // size with a flag byte telling if there's a value
+    pub niche:      Option<usize>   // size using a value the type never takes, if it has one
+}
+
+impl PointerKind {
+
+    /// Parse a pointer kind option: `--rebanada` or `--rasgo`, English names work too
+    pub fn parse(option : &str) -> Option<PointerKind> {
+        match option {
+            "--rebanada" | "--slice" => Some(PointerKind::Slice),
+            "--rasgo" | "--dyn"      => Some(PointerKind::Trait),
+            _ => None
+        }
+    }
+
+    /// Option for this kind, as parsed by `parse`, empty for thin pointers
+    pub fn option(self) -> &'static str {
+        match self {
+            PointerKind::Thin  => "",
+            PointerKind::Slice => "--rebanada",
+            PointerKind::Trait => "--rasgo"
+        }
+    }
+
+    /// Human readable name for this kind
+    pub fn name(self) -> &'static str {
+        match self {
+            PointerKind::Thin  => "simple",
+            PointerKind::Slice => "rebanada (dirección y largo)",
+            PointerKind::Trait => "objeto de rasgo (dirección y vtable)"
+        }
+    }
+
+    /// Pointer sized words this kind takes
+    pub fn words(self) -> usize {
+        match self {
+            PointerKind::Thin => 1,
+            _ => 2
+        }
+    }
+}
+
+impl Pointer {
+
+    /// Create a pointer to the given type
+    pub fn new(target : Name, kind : PointerKind) -> Pointer {
+        Pointer {
+            target,
+            kind
+        }
+    }
+
+    /// Size of this pointer, in bytes
+    /// ## Params
+    /// * `pointer_size` - size of an address in the target machine
+    pub fn size(&self, pointer_size : usize) -> usize {
+        self.kind.words() * pointer_size
+    }
+
+    /// Alignment of this pointer, every word is aligned to a pointer
+    pub fn align(&self, pointer_size : usize) -> usize {
+        pointer_size
+    }
+}
+
+impl OptionalReport {
+
+    /// Size actually used: the niche one if possible
+    pub fn best(&self) -> usize {
+        self.niche.unwrap_or(self.flagged)
+    }
+
+    /// Padding added after the flag byte
+    pub fn flag_padding(&self) -> usize {
+        self.flagged.saturating_sub(self.size).saturating_sub(1)
+    }
+}
+
+impl TypeManager {
+
+    /// Compute the size of an optional value of some type, with and without a niche
+    /// ## Params
+    /// * `typename` - wrapped type
+    /// ---
+    /// ## Return
+    /// Sizes under the C strategy, or an error if the type does not exist
+    pub fn optional(&self, typename : &Name) -> Result<OptionalReport, TypeError> {
+        let t = self.checked(typename)?;
+        let size = Strategy::C.type_size(t, self);
+        let align = Strategy::C.type_align(t, self);
+
+        // the flag goes after the value, then the whole thing is padded to the alignment
+        let flagged = utils::round_up(size.saturating_add(1), align);
+        let niche = match t {
+            Type::Pointer(_) => Some(size),
+            _ => None
+        };
+
+        Ok(OptionalReport { size, align, flagged, niche })
+    }
+}
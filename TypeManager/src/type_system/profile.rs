@@ -0,0 +1,127 @@
+/*
+    Profiling: where the time computing a layout goes. While a profile is being
+    taken, the layout functions count how deep they recurse, how many times they
+    look types up, how the layout cache does and how much the optimizer searches. Nothing is counted the
+    rest of the time, so the cost outside of `perfil` is checking a flag
+*/
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use super::{Name, OptimizerStats, Strategy, TypeError, TypeManager};
+
+/// Work done computing the size and alignment of a type under one strategy
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    pub strategy:      Option<Strategy>,
+    pub elapsed:       Duration,
+    pub depth:         usize,   // deepest nesting of size and alignment computations
+    pub lookups:       usize,   // types looked up by name
+    pub hits:          usize,   // lookups of a type already looked up, a cache by name would answer them
+    pub misses:        usize,   // lookups of a type for the first time
+    pub cache_hits:    usize,   // layouts of members found in the layout cache
+    pub cache_misses:  usize,   // layouts of members computed and cached
+    pub optimizations: usize,   // times the optimizer searched a member order
+    pub optimizer:     OptimizerStats  // every search added up
+}
+
+/// Counters for the profile being taken
+#[derive(Default)]
+struct Counters {
+    depth:   usize,
+    profile: Profile,
+    seen:    HashSet<Name>
+}
+
+thread_local! {
+    static COUNTERS : RefCell<Option<Counters>> = const { RefCell::new(None) };
+}
+
+/// Run a function on the counters, if a profile is being taken
+fn count(f : impl FnOnce(&mut Counters)) {
+    COUNTERS.with(|c| {
+        if let Some(counters) = c.borrow_mut().as_mut() {
+            f(counters)
+        }
+    })
+}
+
+/// A size or alignment computation in progress, it's left when dropped
+pub(super) struct Nesting;
+
+/// Start a size or alignment computation, nested in the ones in progress
+pub(super) fn enter() -> Nesting {
+    count(|c| {
+        c.depth += 1;
+        c.profile.depth = c.profile.depth.max(c.depth);
+    });
+    Nesting
+}
+
+impl Drop for Nesting {
+    fn drop(&mut self) {
+        count(|c| c.depth = c.depth.saturating_sub(1))
+    }
+}
+
+/// A type is being looked up by name
+pub(super) fn lookup(name : &Name) {
+    count(|c| {
+        c.profile.lookups += 1;
+        match c.seen.insert(name.clone()) {
+            true  => c.profile.misses += 1,
+            false => c.profile.hits += 1
+        }
+    })
+}
+
+/// The layout cache was asked for the size or alignment of a type
+/// ## Params
+/// * `hit` - whether it had it
+pub(super) fn cached(hit : bool) {
+    count(|c| match hit {
+        true  => c.profile.cache_hits += 1,
+        false => c.profile.cache_misses += 1
+    })
+}
+
+/// The optimizer finished searching a member order
+pub(super) fn optimized(stats : &OptimizerStats) {
+    count(|c| {
+        c.profile.optimizations += 1;
+        c.profile.optimizer.elapsed += stats.elapsed;
+        c.profile.optimizer.permutations += stats.permutations;
+        c.profile.optimizer.pruned += stats.pruned;
+    })
+}
+
+impl TypeManager {
+
+    /// Profile computing the size and alignment of a type under every strategy, each
+    /// one starting from an empty layout cache so the whole work is measured
+    /// ## Params
+    /// * `typename` - type to profile
+    /// ---
+    /// ## Return
+    /// A profile per strategy, in the order reports show them, or an error if the type does not exist
+    pub fn profile(&self, typename : &Name) -> Result<Vec<Profile>, TypeError> {
+        let t = self.checked(typename)?;
+
+        let profiles = Strategy::ALL
+            .iter()
+            .map(|strategy| {
+                self.layout_cache().clear();
+                COUNTERS.with(|c| *c.borrow_mut() = Some(Counters::default()));
+                let start = Instant::now();
+                strategy.type_size(t, self);
+                strategy.type_align(t, self);
+                let elapsed = start.elapsed();
+
+                let counters = COUNTERS.with(|c| c.borrow_mut().take()).unwrap_or_default();
+                Profile { strategy: Some(*strategy), elapsed, ..counters.profile }
+            })
+            .collect();
+
+        Ok(profiles)
+    }
+}
@@ -0,0 +1,71 @@
+/*
+    Provenance: where every type was defined, such as `cabecera.tm:120` for a
+    line of a script or `cabecera.h:87` for a declaration imported from C.
+    Types without an origin were written at the prompt. A bad layout in a type
+    imported from a long header is easier to fix knowing the line it came from.
+    Origins follow their types when they're shadowed and go with them when
+    they're removed
+*/
+use super::{Name, TypeError, TypeManager};
+
+/// Start of the comment saying where the next definition of a script came from
+const ORIGIN_COMMENT : &str = "origen:";
+
+/// Comment saying where the next definition of a script came from: `# origen: cabecera.tm:120`
+pub fn origin_comment(origin : &str) -> String {
+    format!("# {} {}", ORIGIN_COMMENT, origin)
+}
+
+/// Parse a comment written by `origin_comment`
+/// ## Return
+/// The origin it gives, or none if the comment is not about origins
+pub fn parse_origin(comment : &str) -> Option<String> {
+    let origin = comment.trim_start_matches('#').trim().strip_prefix(ORIGIN_COMMENT)?.trim();
+    match origin.is_empty() {
+        true  => None,
+        false => Some(origin.to_string())
+    }
+}
+
+impl TypeManager {
+
+    /// Record where a type was defined, replacing what was recorded before
+    /// ## Params
+    /// * `typename` - type defined, stored or pending
+    /// * `origin` - where it was defined, such as `archivo:línea`, none if it was written at the prompt
+    pub fn set_origin(&mut self, typename : &Name, origin : Option<String>) {
+        let stored = self.resolve(typename).cloned().unwrap_or_else(|| typename.clone());
+        match origin {
+            Some(origin) => self.origins.insert(stored, origin),
+            None => self.origins.remove(&stored)
+        };
+    }
+
+    /// Where a type was defined, none if it was written at the prompt
+    pub fn origin(&self, typename : &Name) -> Option<&str> {
+        let stored = self.resolve(typename).unwrap_or(typename);
+        self.origins.get(stored).map(String::as_str)
+    }
+
+    /// Move the origin of a type that's stored with a new name
+    pub(super) fn move_origin(&mut self, from : &Name, to : &Name) {
+        if let Some(origin) = self.origins.remove(from) {
+            self.origins.insert(to.clone(), origin);
+        }
+    }
+}
+
+impl TypeError {
+
+    /// Stored type an error is about, to tell where it was defined
+    pub fn subject(&self) -> Option<&Name> {
+        match self {
+            TypeError::NotAStruct(name) | TypeError::NotAUnion(name) | TypeError::NotAVariant(name, _) |
+            TypeError::CaseRedefinition(_, name) | TypeError::TooLarge(name, ..) | TypeError::CyclicDefinition(name, _) |
+            TypeError::TooManyMembers(name, ..) | TypeError::TooDeep(name, ..) | TypeError::TooBigToSolve(name, _) |
+            TypeError::NoMembers(name) | TypeError::ZeroAlign(name) | TypeError::UnknownField(name, _) |
+            TypeError::Unflattenable(name, _) => Some(name),
+            _ => None
+        }
+    }
+}
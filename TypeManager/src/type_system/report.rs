@@ -0,0 +1,294 @@
+/*
+    Structured reports describing a type's layout. Every human readable
+    description is rendered from these, so the same data can be shown
+    in different formats and tested without parsing text
+*/
+use super::{ContainerKind, EnumRepr, Name, OptimizerStats, PointerKind, Strategy, TextModel, ValueKind};
+use crate::json::Value;
+use crate::utils::number::NumberFormat;
+
+/// Layout data for a type under a single packing strategy
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrategyReport {
+    pub strategy: Strategy,
+    pub size:     usize,
+    pub align:    usize,
+    pub loss:     usize    // bytes wasted compared to the packed version
+}
+
+/// Layout data for a union with one of its variants active, under a single packing strategy
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantReport {
+    pub strategy: Strategy,
+    pub variant:  usize,    // size of the active variant
+    pub size:     usize,    // size of the whole union
+    pub slack:    usize     // bytes of the union not used by the variant
+}
+
+/// Bytes a union variant wastes under a single packing strategy
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantLoss {
+    pub name:     Name,
+    pub strategy: Strategy,
+    pub size:     usize,
+    pub padding:  usize,    // padding inside the variant, compared to its packed version
+    pub slack:    usize     // bytes of the union after the variant
+}
+
+impl VariantLoss {
+
+    /// Every byte of the union this variant doesn't use for data
+    pub fn waste(&self) -> usize {
+        self.padding.saturating_add(self.slack)
+    }
+}
+
+/// Layout data for a type
+#[derive(Debug, Clone, PartialEq)]
+pub enum Report {
+    Atomic { representation: usize, alignment: usize, kind: Option<ValueKind> },
+    Struct (Vec<StrategyReport>),
+    Union  { strategies: Vec<StrategyReport>, variants: Vec<VariantLoss> },
+    Enum   { variants: usize, minimal: usize, int: usize, repr: EnumRepr },
+    Pointer { target: Name, kind: PointerKind, size: usize, align: usize },
+    Container { kind: ContainerKind, element: Option<Name>, capacity: usize, inline: usize, align: usize, heap: usize },
+    Text { model: TextModel, size: usize, align: usize, inline: usize }    // inline is the longest text not using the heap
+}
+
+impl Report {
+
+    /// Keep only the layout under a strategy. Types whose layout doesn't change
+    /// with the strategy are kept as they are
+    pub fn only(self, strategy : Strategy) -> Report {
+        match self {
+            Report::Struct(strategies) => Report::Struct(strategies.into_iter().filter(|s| s.strategy == strategy).collect()),
+            Report::Union { strategies, variants } => Report::Union {
+                strategies: strategies.into_iter().filter(|s| s.strategy == strategy).collect(),
+                variants:   variants.into_iter().filter(|v| v.strategy == strategy).collect()
+            },
+            report => report
+        }
+    }
+
+    /// Render this report as a human readable string
+    pub fn display(&self) -> String {
+        self.display_with(NumberFormat::Plain)
+    }
+
+    /// Render this report as a human readable string
+    /// ## Params
+    /// * `numbers` - how to write sizes
+    pub fn display_with(&self, numbers : NumberFormat) -> String {
+        match self {
+            Report::Atomic { representation, alignment, kind } => format!(
+                "⚛️  Atómico:\n   * Representación: {}\n   * Alineación: {}{}",
+                numbers.format(representation),
+                numbers.format(alignment),
+                kind.map(|k| format!("\n   * Valor: {}", k.name())).unwrap_or_default()
+            ),
+            Report::Struct(strategies) => format!("📦 Struct:\n{}", Report::display_strategies(strategies, numbers)),
+            Report::Union { strategies, variants } => format!("👺 Variante:\n{}", Report::display_union(strategies, variants, numbers)),
+            Report::Enum { variants, minimal, int, repr } => format!(
+                "🔢 Enum:\n   * Variantes: {}\n   * Representación mínima: {}\n   * Representación int: {}\n   * En uso: {} ({} bytes)",
+                numbers.format(variants),
+                numbers.format(minimal),
+                numbers.format(int),
+                repr.name(),
+                numbers.format(match repr { EnumRepr::Int => int, EnumRepr::Minimal => minimal })
+            ),
+            Report::Pointer { target, kind, size, align } => format!(
+                "👉 Puntero:\n   * Apunta a: {}\n   * Tipo: {}\n   * Tamaño: {}\n   * Alineación: {}",
+                target,
+                kind.name(),
+                numbers.format(size),
+                numbers.format(align)
+            ),
+            Report::Container { kind, element, capacity, inline, align, heap } => format!(
+                "📚 Contenedor {}{}:\n   * En línea: {}\n   * Alineación: {}\n   * Capacidad: {}\n   * Memoria dinámica: {}",
+                kind.name(),
+                element.as_ref().map(|e| format!(" de {}", e)).unwrap_or_default(),
+                numbers.format(inline),
+                numbers.format(align),
+                numbers.format(capacity),
+                numbers.format(heap)
+            ),
+            Report::Text { model, size, align, inline } => format!(
+                "🔤 Cadena ({}):\n   * Tamaño: {}\n   * Alineación: {}\n   * Sin memoria dinámica: hasta {} bytes de texto",
+                model.definition(),
+                numbers.format(size),
+                numbers.format(align),
+                numbers.format(inline)
+            )
+        }
+    }
+
+    /// Render this report as a JSON object
+    /// ## Params
+    /// * `name` - name of the type this report describes
+    pub fn to_json(&self, name : &Name) -> Value {
+        match self {
+            Report::Atomic { representation, alignment, kind } => {
+                let mut pairs = vec![
+                    ("name",  Value::str(name)),
+                    ("kind",  Value::str("atomic")),
+                    ("size",  Value::uint(*representation)),
+                    ("align", Value::uint(*alignment))
+                ];
+                if let Some(kind) = kind {
+                    pairs.push(("value", Value::str(kind.name())));
+                }
+                Value::object(pairs)
+            },
+            Report::Struct(strategies) => Value::object(vec![
+                ("name", Value::str(name)),
+                ("kind", Value::str("struct")),
+                ("strategies", Report::strategies_json(strategies))
+            ]),
+            Report::Union { strategies, variants } => Value::object(vec![
+                ("name", Value::str(name)),
+                ("kind", Value::str("union")),
+                ("strategies", Report::strategies_json(strategies)),
+                ("variants", Value::Array(
+                    variants
+                        .iter()
+                        .map(|v| Value::object(vec![
+                            ("name",     Value::str(&v.name)),
+                            ("strategy", Value::str(v.strategy.id())),
+                            ("size",     Value::uint(v.size)),
+                            ("padding",  Value::uint(v.padding)),
+                            ("slack",    Value::uint(v.slack))
+                        ]))
+                        .collect()
+                ))
+            ]),
+            Report::Enum { variants, minimal, int, repr } => Value::object(vec![
+                ("name",     Value::str(name)),
+                ("kind",     Value::str("enum")),
+                ("variants", Value::uint(*variants)),
+                ("minimal",  Value::uint(*minimal)),
+                ("int",      Value::uint(*int)),
+                ("repr",     Value::str(repr.name()))
+            ]),
+            Report::Pointer { target, kind, size, align } => Value::object(vec![
+                ("name",    Value::str(name)),
+                ("kind",    Value::str("pointer")),
+                ("target",  Value::str(target)),
+                ("pointer", Value::str(match kind { PointerKind::Thin => "thin", PointerKind::Slice => "slice", PointerKind::Trait => "trait" })),
+                ("size",    Value::uint(*size)),
+                ("align",   Value::uint(*align))
+            ]),
+            Report::Container { kind, element, capacity, inline, align, heap } => Value::object(vec![
+                ("name",     Value::str(name)),
+                ("kind",     Value::str("container")),
+                ("container", Value::str(kind.name())),
+                ("element",  element.as_ref().map(|e| Value::str(e)).unwrap_or(Value::Null)),
+                ("capacity", Value::uint(*capacity)),
+                ("size",     Value::uint(*inline)),
+                ("align",    Value::uint(*align)),
+                ("heap",     Value::uint(*heap))
+            ]),
+            Report::Text { model, size, align, inline } => Value::object(vec![
+                ("name",   Value::str(name)),
+                ("kind",   Value::str("string")),
+                ("model",  Value::str(&model.definition())),
+                ("size",   Value::uint(*size)),
+                ("align",  Value::uint(*align)),
+                ("inline", Value::uint(*inline))
+            ])
+        }
+    }
+
+    /// JSON array with an object per strategy
+    fn strategies_json(strategies : &[StrategyReport]) -> Value {
+        Value::Array(
+            strategies
+                .iter()
+                .map(|s| Value::object(vec![
+                    ("strategy", Value::str(s.strategy.id())),
+                    ("size",     Value::uint(s.size)),
+                    ("align",    Value::uint(s.align)),
+                    ("loss",     Value::uint(s.loss))
+                ]))
+                .collect()
+        )
+    }
+
+    /// Render size and loss for every strategy, followed by the padding and slack of every variant
+    fn display_union(strategies : &[StrategyReport], variants : &[VariantLoss], numbers : NumberFormat) -> String {
+        strategies
+            .iter()
+            .map(|s| {
+                let mut block = Report::display_strategies(std::slice::from_ref(s), numbers);
+                for v in variants.iter().filter(|v| v.strategy == s.strategy) {
+                    block += &format!(
+                        "      - {}: {} bytes, {} de relleno, {} de holgura\n",
+                        v.name,
+                        numbers.format(v.size),
+                        numbers.format(v.padding),
+                        numbers.format(v.slack)
+                    );
+                }
+                block
+            })
+            .collect()
+    }
+
+    /// Render size and loss for every strategy, one block per strategy
+    fn display_strategies(strategies : &[StrategyReport], numbers : NumberFormat) -> String {
+        strategies
+            .iter()
+            .map(|s| format!(
+                "   * {}:\n      + Tamaño: {}\n      + Perdida: {}\n",
+                s.strategy.name(),
+                numbers.format(s.size),
+                numbers.format(s.loss)
+            ))
+            .collect()
+    }
+}
+
+impl OptimizerStats {
+
+    /// Percentage of member orders dropped before being fully evaluated
+    pub fn pruned_percent(&self) -> f64 {
+        if self.permutations == 0 {
+            return 0.0
+        }
+
+        100.0 * self.pruned as f64 / self.permutations as f64
+    }
+
+    /// Render these statistics as a human readable string
+    pub fn display(&self) -> String {
+        self.display_with(NumberFormat::Plain)
+    }
+
+    /// Render these statistics as a human readable string
+    /// ## Params
+    /// * `numbers` - how to write counts
+    pub fn display_with(&self, numbers : NumberFormat) -> String {
+        let text = format!(
+            "⏱️  Optimizador:\n   * Tiempo: {:.3} ms\n   * Permutaciones: {}\n   * Podadas: {} ({:.1}%)",
+            self.elapsed.as_secs_f64() * 1000.0,
+            numbers.format(self.permutations),
+            numbers.format(self.pruned),
+            self.pruned_percent()
+        );
+        match (self.cancelled, self.timed_out) {
+            (true, _) => text + "\n   * Cancelado: el orden es el mejor encontrado hasta entonces",
+            (_, true) => text + "\n   * Sin tiempo: el orden es el mejor encontrado en el tiempo límite",
+            _ => text
+        }
+    }
+
+    /// Render these statistics as a JSON object
+    pub fn to_json(&self) -> Value {
+        Value::object(vec![
+            ("elapsed_ms",   Value::Float(self.elapsed.as_secs_f64() * 1000.0)),
+            ("permutations", Value::uint(self.permutations)),
+            ("pruned",       Value::uint(self.pruned)),
+            ("cancelled",    Value::Bool(self.cancelled)),
+            ("timed_out",    Value::Bool(self.timed_out))
+        ])
+    }
+}
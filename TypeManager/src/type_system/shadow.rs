@@ -0,0 +1,71 @@
+/*
+    Shadowing: replaying the imports of a header as it evolves defines the same
+    names again and again. Instead of rejecting every redefinition, a manager
+    can let the new definition take the name while the old one stays reachable
+    as `nombre@anterior`, along with every type built on it. Shadowing it again
+    moves the older one to `nombre@anterior@anterior`, and so on
+*/
+use super::{Name, TypeManager};
+
+/// Suffix for the name a shadowed type keeps: `nombre@anterior`
+pub const SHADOWED : &str = "@anterior";
+
+impl TypeManager {
+
+    /// Let types defined with a stored name shadow the stored one instead of being
+    /// rejected, see `add`
+    pub fn set_shadowing(&mut self, shadowing : bool) {
+        self.shadowing = shadowing;
+    }
+
+    /// Tells if new types shadow stored ones with the same name
+    pub fn shadows(&self) -> bool {
+        self.shadowing
+    }
+
+    /// Move a stored type to its shadowed name, moving first whatever type was already
+    /// there. Types built on it are rewritten to use it by its new name, so their
+    /// layouts stay as they were
+    /// ## Params
+    /// * `typename` - stored name of the type to move
+    /// ---
+    /// ## Return
+    /// The name the type is stored with now
+    pub(super) fn shelve(&mut self, typename : &Name) -> Name {
+        let older = format!("{}{}", typename, SHADOWED);
+        if let Some(stored) = self.resolve(&older).cloned() {
+            self.shelve(&stored);
+        }
+
+        // layouts are dropped while the index still has its users
+        self.invalidate(typename);
+        let t = match self.types.remove(typename) {
+            Some(t) => t,
+            None => return older
+        };
+        self.unindex_users(typename, &t);
+        self.move_origin(typename, &older);
+        self.move_attributes(typename, &older);
+        if let Some(folded) = &mut self.folded {
+            folded.remove(&typename.to_lowercase());
+            folded.insert(older.to_lowercase(), older.clone());
+        }
+
+        let rename = |n : &Name| match n == typename {
+            true  => older.clone(),
+            false => n.clone()
+        };
+        for user in self.users.remove(typename).unwrap_or_default() {
+            if let Some(used) = self.types.get(&user).cloned() {
+                self.unindex_users(&user, &used);
+                let used = used.rename_references(rename);
+                self.index_users(&user, &used);
+                self.types.insert(user, used);     // replaced where it was
+            }
+        }
+
+        self.index_users(&older, &t);
+        self.types.insert(older.clone(), t);
+        older
+    }
+}
@@ -0,0 +1,143 @@
+/*
+    Exact solver: the smallest member order of a struct, with proof that there's
+    no smaller one. Instead of trying every order it solves the problem by dynamic
+    programming over the subsets of members placed first: where the last of them
+    ends only grows with where the ones before end, so the smallest end for every
+    subset is all there is to keep. That's 2^n states instead of n! orders, enough
+    to prove the best order of mid-size structs. Members sharing a `@grupo` label
+    stay together: a group can't be left before every member of it is placed.
+    Members marked `@fijo` only go in their declared position, padded up to their
+    declared offset, which keeps ends growing so the smallest one is still enough.
+    When no order keeps every constraint the declared one is given back.
+    Built with the `solver` feature, on by default
+*/
+use std::time::{Duration, Instant};
+
+use super::{cancel, limits, Name, Struct, Type, TypeError, TypeManager, CANCEL_CHECK, GROUP, MAX_SOLVER_MEMBERS};
+
+/// Smallest member order of a struct, proved so
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    pub order:   Vec<usize>,    // member indices, in memory order
+    pub size:    usize,
+    pub states:  usize,         // subsets of members that could be placed first
+    pub elapsed: Duration
+}
+
+impl TypeManager {
+
+    /// Find the smallest member order of a struct, proving no other is smaller
+    /// ## Params
+    /// * `typename` - struct to lay out
+    /// ---
+    /// ## Return
+    /// The best order, none if the search was cancelled or ran out of time, or an error
+    /// if the type is not a struct or has too many members
+    pub fn solve(&self, typename : &Name) -> Result<Option<Solution>, TypeError> {
+        match self.get(typename) {
+            None => Err(TypeError::TypeDoesNotExist(typename.clone())),
+            Some(Type::Struct(s)) if s.members.len() > MAX_SOLVER_MEMBERS => {
+                Err(TypeError::TooBigToSolve(typename.clone(), s.members.len()))
+            },
+            Some(Type::Struct(s)) => Ok(s.solve(self)),
+            Some(_) => Err(TypeError::NotAStruct(typename.clone()))
+        }
+    }
+}
+
+impl Struct {
+
+    /// Find the smallest member order, see `TypeManager::solve`. It must have at most
+    /// `MAX_SOLVER_MEMBERS` members
+    pub fn solve(&self, manager : &TypeManager) -> Option<Solution> {
+        let start = Instant::now();
+        let members = self.member_sizes(manager);
+        let pins = self.pins(manager, &members);
+        let n = members.len();
+
+        // members of every group, as a bit set
+        let labels : Vec<Option<&str>> = self.fields.iter().map(|f| f.attribute(GROUP)).collect();
+        let group_of : Vec<u32> = labels
+                                    .iter()
+                                    .map(|label| match label {
+                                        None => 0,
+                                        Some(_) => (0..n).filter(|j| labels[*j] == *label).fold(0, |set, j| set | 1 << j)
+                                    })
+                                    .collect();
+
+        // smallest end for every subset placed first and the member placed last to get it
+        let full = (1u32 << n) - 1;
+        let mut end = vec![usize::MAX; 1 << n];
+        let mut last = vec![u8::MAX; 1 << n];
+        end[0] = 0;
+        let mut states = 0;
+
+        for placed in 0..=full {
+            if end[placed as usize] == usize::MAX {
+                continue
+            }
+            states += 1;
+            if states % CANCEL_CHECK == 0 {
+                if cancel::requested() {
+                    return None
+                }
+                if manager.limits().optimizer.is_some_and(|limit| start.elapsed() > limit) {
+                    limits::time_out();
+                    return None
+                }
+            }
+
+            // a group started and not finished has to go on, a pinned member goes in its position
+            let open = group_of.iter().find(|g| **g & placed != 0 && **g & placed != **g).copied();
+            let position = placed.count_ones() as usize;
+            let pinned_here = position < n && pins[position].is_some();
+            for (i, (size, align)) in members.iter().enumerate() {
+                let member = 1 << i;
+                let allowed = match open {
+                    Some(group) => group & member != 0,
+                    None => group_of[i] & placed == 0
+                };
+                let in_place = match pins[i] {
+                    Some(_) => i == position,
+                    None => !pinned_here
+                };
+                if placed & member != 0 || !allowed || !in_place {
+                    continue
+                }
+
+                let from = end[placed as usize];
+                let offset = from.saturating_add((align - from % align) % align);
+                let offset = match pins[i] {
+                    Some(pinned) if offset > pinned => continue,
+                    Some(pinned) => pinned,
+                    None => offset
+                };
+                let next = offset.saturating_add(*size);
+                let index = (placed | member) as usize;
+                if next < end[index] {
+                    end[index] = next;
+                    last[index] = i as u8;
+                }
+            }
+        }
+
+        // no order keeps every constraint, keep the declared one
+        if end[full as usize] == usize::MAX {
+            let order : Vec<usize> = (0..n).collect();
+            let (_, size) = Struct::place_offsets(&order, &members, &pins);
+            return Some(Solution { order, size, states, elapsed: start.elapsed() })
+        }
+
+        // walk back from every member placed
+        let mut order = Vec::with_capacity(n);
+        let mut placed = full;
+        while placed != 0 {
+            let i = last[placed as usize] as usize;
+            order.push(i);
+            placed &= !(1 << i);
+        }
+        order.reverse();
+
+        Some(Solution { order, size: end[full as usize], states, elapsed: start.elapsed() })
+    }
+}
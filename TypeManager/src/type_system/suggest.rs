@@ -0,0 +1,68 @@
+/*
+    Suggestions: a member naming a type that doesn't exist is most of the time
+    a typo, so errors about it come with the stored types whose names are the
+    closest to it and what kind of type each one is. Names are compared without
+    case, and names too far from the one written are not offered
+*/
+use super::{Name, Type, TypeError, TypeManager};
+use crate::utils;
+
+/// Most types suggested for a name that doesn't exist
+pub const MAX_SUGGESTIONS : usize = 3;
+
+/// A stored type close to a name that doesn't exist: its name and the name of its kind
+pub type Suggestion = (Name, &'static str);
+
+impl Type {
+
+    /// Name of the kind of this type, as listings show it
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            Type::Atomic(_) => "atómico",
+            Type::Struct(_) => "struct",
+            Type::Union(_)  => "variante",
+            Type::Enum(_)   => "enum",
+            Type::Pointer(_) => "puntero",
+            Type::Container(_) => "contenedor",
+            Type::Text(_)   => "cadena"
+        }
+    }
+}
+
+impl TypeManager {
+
+    /// Stored types whose names are the closest to a name, for errors about names that don't exist
+    /// ## Params
+    /// * `typename` - name as written
+    /// ---
+    /// ## Return
+    /// Up to `MAX_SUGGESTIONS` types, closest first and by name when as close, none further
+    /// than a third of the name's length, or 2 for short names
+    pub fn suggestions(&self, typename : &str) -> Vec<Suggestion> {
+        let written = typename.to_lowercase();
+        let furthest = written.chars().count().div_ceil(3).max(2);
+
+        let mut close : Vec<(usize, &Name, &Type)> = self.types
+                                                        .iter()
+                                                        .map(|(name, t)| (utils::levenshtein(&written, &name.to_lowercase()), name, t))
+                                                        .filter(|(distance, ..)| *distance <= furthest)
+                                                        .collect();
+        close.sort_by(|(d1, n1, _), (d2, n2, _)| d1.cmp(d2).then(n1.cmp(n2)));
+
+        close.into_iter().take(MAX_SUGGESTIONS).map(|(_, name, t)| (name.clone(), t.kind_name())).collect()
+    }
+
+    /// Error for a member naming a type that doesn't exist, with the closest stored types
+    /// if there are any. Types waiting to be defined get no suggestions, they're not typos
+    pub(super) fn unknown(&self, typename : &Name) -> TypeError {
+        let suggestions = match self.pending_type(typename) {
+            Some(_) => vec![],
+            None => self.suggestions(typename)
+        };
+
+        match suggestions.is_empty() {
+            true  => TypeError::TypeDoesNotExist(typename.clone()),
+            false => TypeError::UnknownMember(typename.clone(), suggestions)
+        }
+    }
+}
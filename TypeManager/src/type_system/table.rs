@@ -0,0 +1,101 @@
+/*
+    Type table: stored types by name, remembering the order they were stored
+    in. Everything going over the whole table, listings, exports, session files,
+    sees the types in that order, so the same script gives the same output on
+    every run instead of whatever order a hash map picked
+*/
+use std::collections::HashMap;
+
+use super::{Name, Type};
+
+/// Stored types by name, in the order they were stored
+#[derive(Debug, Default, Clone)]
+pub struct TypeTable {
+    entries: Vec<(Name, Type)>,
+    index:   HashMap<Name, usize>   // position of every name in entries
+}
+
+impl TypeTable {
+
+    /// Create an empty table
+    pub fn new() -> TypeTable {
+        TypeTable::default()
+    }
+
+    /// Type stored with a name, if any
+    pub fn get(&self, name : &Name) -> Option<&Type> {
+        self.index.get(name).map(|i| &self.entries[*i].1)
+    }
+
+    /// Tells if a type is stored with a name
+    pub fn contains_key(&self, name : &Name) -> bool {
+        self.index.contains_key(name)
+    }
+
+    /// Store a type. A type already stored with the same name is replaced where it was,
+    /// a new one goes after every other
+    /// ## Return
+    /// The type replaced, if any
+    pub fn insert(&mut self, name : Name, t : Type) -> Option<Type> {
+        match self.index.get(&name) {
+            Some(i) => Some(std::mem::replace(&mut self.entries[*i].1, t)),
+            None => {
+                self.index.insert(name.clone(), self.entries.len());
+                self.entries.push((name, t));
+                None
+            }
+        }
+    }
+
+    /// Store a type at a position, the ones from there on go after it
+    /// ## Params
+    /// * `position` - where to store it, the end if past it
+    /// * `name` - a name not stored yet
+    pub fn insert_at(&mut self, position : usize, name : Name, t : Type) {
+        let position = position.min(self.entries.len());
+        self.entries.insert(position, (name, t));
+        for (i, (later, _)) in self.entries.iter().enumerate().skip(position) {
+            self.index.insert(later.clone(), i);
+        }
+    }
+
+    /// Position of a type in the order they were stored, if it's stored
+    pub fn position(&self, name : &Name) -> Option<usize> {
+        self.index.get(name).copied()
+    }
+
+    /// Take a type out of the table, the ones after it keep their order
+    /// ## Return
+    /// The type removed, if any
+    pub fn remove(&mut self, name : &Name) -> Option<Type> {
+        let i = self.index.remove(name)?;
+        let (_, t) = self.entries.remove(i);
+        for (later, _) in &self.entries[i..] {
+            if let Some(position) = self.index.get_mut(later) {
+                *position -= 1;
+            }
+        }
+
+        Some(t)
+    }
+
+    /// Every name, in the order the types were stored
+    pub fn keys(&self) -> impl Iterator<Item = &Name> {
+        self.entries.iter().map(|(name, _)| name)
+    }
+
+    /// Every name with its type, in the order they were stored
+    pub fn iter(&self) -> impl Iterator<Item = (&Name, &Type)> {
+        self.entries.iter().map(|(name, t)| (name, t))
+    }
+
+    /// Amount of stored types
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Tells if no type is stored
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
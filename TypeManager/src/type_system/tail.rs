@@ -0,0 +1,82 @@
+/*
+    Tail padding reuse: a member marked `@no_unique_address` (as in C++) lets the
+    next members start inside its tail padding, right after its last data byte,
+    the way Itanium C++ compilers place them. The struct still fits the whole member
+*/
+use super::{Name, Strategy, Struct, Type, TypeError, TypeManager};
+use crate::utils;
+
+/// Attributes for members whose tail padding can be reused
+pub const REUSE : [&str; 2] = ["no_unique_address", "sin_direccion_unica"];
+
+/// Layout of a struct with and without reusing tail padding, under the C strategy
+#[derive(Debug, Clone, PartialEq)]
+pub struct TailReuse {
+    pub plain_offsets: Vec<usize>,  // every member, in declaration order
+    pub plain_size:    usize,
+    pub offsets:       Vec<usize>,  // reusing tail padding of marked members
+    pub size:          usize
+}
+
+impl TailReuse {
+
+    /// Bytes saved by reusing tail padding
+    pub fn savings(&self) -> usize {
+        self.plain_size.saturating_sub(self.size)
+    }
+}
+
+impl TypeManager {
+
+    /// Lay out a struct as a C compiler would, with and without reusing the tail
+    /// padding of members marked `@no_unique_address`
+    /// ## Params
+    /// * `typename` - struct to lay out
+    /// ---
+    /// ## Return
+    /// Offsets and size both ways, or an error if the type is not a struct
+    pub fn tail_reuse(&self, typename : &Name) -> Result<TailReuse, TypeError> {
+        let s = match self.checked(typename)? {
+            Type::Struct(s) => s,
+            _ => return Err(TypeError::NotAStruct(typename.clone()))
+        };
+
+        let (offsets, _, size) = s.reuse_layout(self);
+        Ok(TailReuse { plain_offsets: s.c_offsets(self), plain_size: s.c_size(self), offsets, size })
+    }
+
+    /// Bytes up to the last data byte of a type, a struct's tail padding is not counted
+    fn data_size(&self, t : &Type) -> usize {
+        match t {
+            Type::Struct(s) => s.reuse_layout(self).1,
+            _ => Strategy::C.type_size(t, self)
+        }
+    }
+}
+
+impl Struct {
+
+    /// C layout where members after a marked one may start in its tail padding
+    /// ## Return
+    /// Offset of every member, end of the last data byte and size of the struct
+    fn reuse_layout(&self, manager : &TypeManager) -> (Vec<usize>, usize, usize) {
+        let mut offsets = Vec::with_capacity(self.members.len());
+        let mut position = 0;
+        let mut end = 0;    // every member must fit whole, padding included
+
+        for (member, field) in self.members.iter().zip(&self.fields) {
+            let offset = utils::round_up(position, manager.align_of(member, Strategy::C));
+            let size = manager.size_of(member, Strategy::C);
+            let reusable = field.attributes.iter().any(|a| REUSE.contains(&a.key.as_str()));
+
+            position = offset.saturating_add(match (reusable, manager.get(member)) {
+                (true, Some(t)) => manager.data_size(t),
+                _ => size
+            });
+            end = end.max(offset.saturating_add(size));
+            offsets.push(offset);
+        }
+
+        (offsets, position, utils::round_up(position.max(end), self.c_align(manager)))
+    }
+}
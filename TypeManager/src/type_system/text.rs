@@ -0,0 +1,127 @@
+/*
+    String representations: the same text can be stored as a fixed array of
+    characters, as a pointer and a length, or with a small string optimization
+    (SSO) keeping short strings inside the handle. Each model is used like an
+    atomic type, its inner fields are only shown when asked for
+*/
+use super::POINTER_SIZE;
+
+/// Smallest size for an SSO string: a pointer, a length and a capacity
+pub const MIN_SSO : usize = 3 * POINTER_SIZE;
+
+/// How a string is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextModel {
+    Raw(usize),     // an array with room for this many bytes, such as `char[32]`
+    PtrLen,         // pointer and length, such as `&str`
+    Sso(usize)      // total size, holding short strings inline
+}
+
+/// String type structure
+#[derive(Debug, Clone, PartialEq)]
+pub struct Text {
+    pub model: TextModel
+}
+
+/// A field inside a string representation
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextField {
+    pub name:   &'static str,
+    pub offset: usize,
+    pub size:   usize
+}
+
+impl TextModel {
+
+    /// Parse a model: `crudo N`, `ptr_len` or `sso [N]`
+    /// ## Params
+    /// * `name` - model name
+    /// * `size` - size given after the name, if any
+    /// ---
+    /// ## Return
+    /// The model, or a description of the problem
+    pub fn parse(name : &str, size : Option<usize>) -> Result<TextModel, String> {
+        match (name.to_lowercase().as_str(), size) {
+            ("crudo", Some(n)) if n > 0    => Ok(TextModel::Raw(n)),
+            ("crudo", _)                    => Err(String::from("el modelo 'crudo' necesita un tamaño mayor que 0")),
+            ("ptr_len", None)               => Ok(TextModel::PtrLen),
+            ("sso", None)                   => Ok(TextModel::Sso(MIN_SSO)),
+            ("sso", Some(n)) if n >= MIN_SSO && n % POINTER_SIZE == 0 => Ok(TextModel::Sso(n)),
+            ("sso", Some(n)) => Err(format!("{} no es un tamaño para 'sso', debe ser múltiplo de {} y al menos {}", n, POINTER_SIZE, MIN_SSO)),
+            ("ptr_len", Some(_))            => Err(String::from("el modelo 'ptr_len' no lleva tamaño")),
+            (other, _) => Err(format!("'{}' no es un modelo de cadena, se esperaba 'crudo', 'ptr_len' o 'sso'", other))
+        }
+    }
+
+    /// Model as written in a `cadena` command
+    pub fn definition(self) -> String {
+        match self {
+            TextModel::Raw(n) => format!("crudo {}", n),
+            TextModel::PtrLen => String::from("ptr_len"),
+            TextModel::Sso(n) => format!("sso {}", n)
+        }
+    }
+}
+
+impl Text {
+
+    /// Create a string type with the given representation
+    pub fn new(model : TextModel) -> Text {
+        Text {
+            model
+        }
+    }
+
+    /// Bytes taken by this string inline
+    /// ## Params
+    /// * `pointer_size` - size of an address in the target machine
+    pub fn size(&self, pointer_size : usize) -> usize {
+        match self.model {
+            TextModel::Raw(n) => n,
+            TextModel::PtrLen => 2 * pointer_size,
+            TextModel::Sso(n) => n
+        }
+    }
+
+    /// Alignment of this string
+    pub fn align(&self, pointer_size : usize) -> usize {
+        match self.model {
+            TextModel::Raw(_) => 1,
+            _ => pointer_size
+        }
+    }
+
+    /// Longest text stored without a heap allocation, in bytes
+    pub fn inline_capacity(&self) -> usize {
+        match self.model {
+            TextModel::Raw(n) => n - 1,     // room for the final `\0`
+            TextModel::PtrLen => 0,
+            TextModel::Sso(n) => n - 1      // the last byte tells the length and mode
+        }
+    }
+
+    /// Fields inside this representation. SSO strings overlap two layouts: a long one
+    /// with pointer, length and capacity, and a short one with the characters inline
+    /// ## Params
+    /// * `pointer_size` - size of an address in the target machine
+    pub fn fields(&self, pointer_size : usize) -> Vec<Vec<TextField>> {
+        let field = |name, offset, size| TextField { name, offset, size };
+        let words = 3 * pointer_size;
+        match self.model {
+            TextModel::Raw(n) => vec![vec![field("bytes", 0, n)]],
+            TextModel::PtrLen => vec![vec![field("ptr", 0, pointer_size), field("len", pointer_size, pointer_size)]],
+            TextModel::Sso(n) => {
+                let mut long = vec![
+                    field("ptr", 0, pointer_size),
+                    field("len", pointer_size, pointer_size),
+                    field("cap", 2 * pointer_size, pointer_size)
+                ];
+                if n > words {
+                    long.push(field("relleno", words, n - words));
+                }
+                let short = vec![field("buf", 0, n - 1), field("etiqueta", n - 1, 1)];
+                vec![long, short]
+            }
+        }
+    }
+}
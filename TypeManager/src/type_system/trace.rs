@@ -0,0 +1,191 @@
+/*
+    Access traces: which members are read together and how often, such as
+    `100 x y` for a loop reading `x` and `y` a hundred times. Every access pattern
+    loads the cache lines holding its members, so the order touching the fewest
+    lines per access, weighted by how often each pattern happens, is the one to
+    pick for code following the trace, even if it's bigger than the smallest one.
+    Structs are assumed to start at the beginning of a cache line
+*/
+use std::time::Instant;
+
+use super::{cancel, limits, Name, Struct, Type, TypeError, TypeManager, CANCEL_CHECK, GROUP};
+
+/// Members read together and how often
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessPattern {
+    pub weight:  usize,
+    pub members: Vec<usize>     // member indices
+}
+
+/// A member order and the cache lines every pattern touches with it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceOrder {
+    pub order: Vec<usize>,  // member indices, in memory order
+    pub size:  usize,
+    pub lines: Vec<usize>   // cache lines touched, by pattern
+}
+
+/// Best order for an access trace, compared with the smallest one and declaration order
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceLayout {
+    pub line:     usize,
+    pub patterns: Vec<AccessPattern>,
+    pub best:     TraceOrder,
+    pub smallest: TraceOrder,
+    pub declared: TraceOrder,
+    pub stopped:  bool      // cancelled or out of time before trying every order
+}
+
+impl TraceOrder {
+
+    /// Cache lines touched by an access, on average over the trace
+    pub fn expected(&self, patterns : &[AccessPattern]) -> f64 {
+        let total : usize = patterns.iter().map(|p| p.weight).sum();
+        match total {
+            0 => 0.0,
+            _ => weighted(&self.lines, patterns) as f64 / total as f64
+        }
+    }
+}
+
+/// Parse an access trace for a struct, a pattern per line: an optional weight (1 if
+/// not given) and the names of the members read together. `#` starts a comment
+/// ## Params
+/// * `text` - trace to parse
+/// * `s` - struct whose members the trace reads
+/// ---
+/// ## Return
+/// Every pattern read, or the problems found with their line numbers
+pub fn parse_trace(text : &str, s : &Struct) -> Result<Vec<AccessPattern>, Vec<(usize, String)>> {
+    let mut patterns = vec![];
+    let mut errors = vec![];
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut words = line.split_whitespace().peekable();
+        let weight = match words.peek().map(|w| w.parse::<usize>()) {
+            Some(Ok(weight)) => { words.next(); weight },
+            _ => 1
+        };
+
+        let mut members = vec![];
+        for word in words {
+            match s.fields.iter().position(|f| f.name == word) {
+                Some(member) if !members.contains(&member) => members.push(member),
+                Some(_) => {},
+                None => errors.push((i + 1, format!("'{}' no es un campo del struct", word)))
+            }
+        }
+        match members.is_empty() {
+            true if !line.trim().is_empty() => errors.push((i + 1, String::from("el patrón no lee ningún campo"))),
+            true => {},
+            false => patterns.push(AccessPattern { weight, members })
+        }
+    }
+
+    match (errors.is_empty(), patterns.is_empty()) {
+        (true, false) => Ok(patterns),
+        (true, true)  => Err(vec![(0, String::from("la traza no tiene ningún patrón de acceso"))]),
+        (false, _)    => Err(errors)
+    }
+}
+
+impl TypeManager {
+
+    /// Find the member order touching the fewest cache lines per access for a trace,
+    /// trying every order keeping groups together and pinned members in place
+    /// ## Params
+    /// * `typename` - struct to lay out
+    /// * `patterns` - access trace, see `parse_trace`
+    /// * `line` - cache line size, in bytes
+    /// ---
+    /// ## Return
+    /// The best order for the trace with the smallest and declared ones, or an error
+    /// if the type is not a struct
+    pub fn trace_layout(&self, typename : &Name, patterns : &[AccessPattern], line : usize) -> Result<TraceLayout, TypeError> {
+        match self.get(typename) {
+            None => Err(TypeError::TypeDoesNotExist(typename.clone())),
+            Some(Type::Struct(s)) => Ok(s.trace_layout(self, patterns, line.max(1))),
+            Some(_) => Err(TypeError::NotAStruct(typename.clone()))
+        }
+    }
+}
+
+impl Struct {
+
+    /// Find the best member order for an access trace, see `TypeManager::trace_layout`
+    pub fn trace_layout(&self, manager : &TypeManager, patterns : &[AccessPattern], line : usize) -> TraceLayout {
+        let start = Instant::now();
+        let members = self.member_sizes(manager);
+        let pins = self.pins(manager, &members);
+        let groups : Vec<Option<&str>> = self.fields.iter().map(|f| f.attribute(GROUP)).collect();
+        let touched = |order : Vec<usize>| Struct::touched(order, &members, &pins, patterns, line);
+
+        let declared = touched((0..members.len()).collect());
+        let (order, _, _) = self.optimize_with(manager, &mut |_, _| {});
+        let smallest = touched(order);
+
+        // fewest lines per access first, then smallest size
+        let cost = |t : &TraceOrder| (weighted(&t.lines, patterns), t.size);
+        let mut best = match Struct::groups_together(&declared.order, &groups) {
+            true  => declared.clone(),
+            false => smallest.clone()
+        };
+        let mut stopped = false;
+        for (tried, order) in crate::utils::Permutations::new((0..members.len()).collect()).enumerate() {
+            if tried % CANCEL_CHECK == 0 {
+                if cancel::requested() {
+                    stopped = true;
+                    break
+                }
+                if manager.limits().optimizer.is_some_and(|limit| start.elapsed() > limit) {
+                    stopped = true;
+                    limits::time_out();
+                    break
+                }
+            }
+            if !Struct::groups_together(&order, &groups) || Struct::place_members(&order, &members, &pins, usize::MAX).is_none() {
+                continue
+            }
+
+            let candidate = touched(order);
+            if cost(&candidate) < cost(&best) {
+                best = candidate;
+            }
+        }
+
+        TraceLayout { line, patterns: patterns.to_vec(), best, smallest, declared, stopped }
+    }
+
+    /// Cache lines every pattern touches with members placed in some order
+    fn touched(order : Vec<usize>, members : &[(usize, usize)], pins : &[Option<usize>], patterns : &[AccessPattern], line : usize) -> TraceOrder {
+        let (offsets, size) = Struct::place_offsets(&order, members, pins);
+        let mut place = vec![(0, 0); members.len()];
+        for (i, offset) in order.iter().zip(offsets) {
+            place[*i] = (offset, members[*i].0);
+        }
+
+        let lines = patterns
+                        .iter()
+                        .map(|p| {
+                            let mut touched : Vec<usize> = p.members
+                                                            .iter()
+                                                            .flat_map(|m| {
+                                                                let (offset, size) = place[*m];
+                                                                offset / line..=(offset + size.max(1) - 1) / line
+                                                            })
+                                                            .collect();
+                            touched.sort_unstable();
+                            touched.dedup();
+                            touched.len()
+                        })
+                        .collect();
+
+        TraceOrder { order, size, lines }
+    }
+}
+
+/// Cache lines touched by the whole trace, every pattern as many times as its weight
+fn weighted(lines : &[usize], patterns : &[AccessPattern]) -> usize {
+    lines.iter().zip(patterns).map(|(l, p)| l.saturating_mul(p.weight)).fold(0, usize::saturating_add)
+}
@@ -0,0 +1,240 @@
+/*
+    Values: atomics may declare how their bytes are read (signed or unsigned
+    integers, floating point, characters, booleans), so raw memory can be annotated with
+    the values it holds. Exporters pick target types by kind and `banderas` takes
+    booleans as flags; enums keep sizing themselves by their amount of variants.
+    Bytes are read little endian unless told otherwise, as in x86 and most ARM systems
+*/
+use super::{Name, Strategy, Type, TypeError, TypeManager};
+
+/// How the bytes of an atomic are read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueKind {
+    Signed,
+    Unsigned,
+    Float,
+    Char,       // ASCII for 1 byte, UTF-16 or UTF-32 code units otherwise
+    Bool        // 0 is false and 1 is true, anything else is not a valid value
+}
+
+/// Byte order of values in memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big
+}
+
+/// A region of a buffer overlaid with a type
+#[derive(Debug, Clone, PartialEq)]
+pub enum Annotation {
+    Member {
+        path:     String,
+        typename: Name,
+        offset:   usize,
+        size:     usize,
+        value:    Option<String>    // only for atomics with a kind, fully inside the buffer
+    },
+    Padding { offset: usize, size: usize }
+}
+
+impl ValueKind {
+
+    /// Parse a kind name, in Spanish or as in C: `entero`, `natural`, `real`, `int`, `unsigned`...
+    pub fn parse(name : &str) -> Option<ValueKind> {
+        match name.to_lowercase().as_str() {
+            "entero" | "int" | "signed"             => Some(ValueKind::Signed),
+            "natural" | "uint" | "unsigned"         => Some(ValueKind::Unsigned),
+            "real" | "float" | "flotante"           => Some(ValueKind::Float),
+            "caracter" | "carácter" | "char"        => Some(ValueKind::Char),
+            "booleano" | "bool" | "logico" | "lógico" => Some(ValueKind::Bool),
+            _ => None
+        }
+    }
+
+    /// Name for this kind, as parsed by `parse`
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueKind::Signed   => "entero",
+            ValueKind::Unsigned => "natural",
+            ValueKind::Float    => "real",
+            ValueKind::Char     => "caracter",
+            ValueKind::Bool     => "booleano"
+        }
+    }
+
+    /// C type holding values of this kind with some size, for exporters
+    /// ## Return
+    /// The type, or none if C has no standard type of this kind and size
+    pub fn c_type(self, size : usize) -> Option<&'static str> {
+        match (self, size) {
+            (ValueKind::Signed, 1)   => Some("int8_t"),
+            (ValueKind::Signed, 2)   => Some("int16_t"),
+            (ValueKind::Signed, 4)   => Some("int32_t"),
+            (ValueKind::Signed, 8)   => Some("int64_t"),
+            (ValueKind::Unsigned, 1) => Some("uint8_t"),
+            (ValueKind::Unsigned, 2) => Some("uint16_t"),
+            (ValueKind::Unsigned, 4) => Some("uint32_t"),
+            (ValueKind::Unsigned, 8) => Some("uint64_t"),
+            (ValueKind::Float, 4)    => Some("float"),
+            (ValueKind::Float, 8)    => Some("double"),
+            (ValueKind::Char, 1)     => Some("char"),
+            (ValueKind::Bool, 1)     => Some("_Bool"),
+            _ => None
+        }
+    }
+
+    /// Read a value of this kind
+    /// ## Params
+    /// * `bytes` - bytes of the value
+    /// * `endian` - their order
+    /// ---
+    /// ## Return
+    /// The value as text, or none if there's no such value with this many bytes
+    pub fn decode(self, bytes : &[u8], endian : Endian) -> Option<String> {
+        let unsigned = endian.read(bytes);
+        match (self, bytes.len()) {
+            (ValueKind::Float, 4) => Some(f32::from_bits(unsigned? as u32).to_string()),
+            (ValueKind::Float, 8) => Some(f64::from_bits(unsigned? as u64).to_string()),
+            (ValueKind::Float, _) => None,
+            (ValueKind::Unsigned, _) => unsigned.map(|u| u.to_string()),
+            (ValueKind::Signed, n) => {
+                // sign extend from the highest bit read
+                let shift = 128 - 8 * n as u32;
+                unsigned.map(|u| ((u << shift) as i128 >> shift).to_string())
+            },
+            (ValueKind::Char, 1) => Some(std::ascii::escape_default(bytes[0]).to_string()),
+            (ValueKind::Char, 2) | (ValueKind::Char, 4) => {
+                let code = unsigned? as u32;
+                Some(match std::char::from_u32(code) {
+                    Some(c) if !c.is_control() => c.to_string(),
+                    _ => format!("\\u{{{:x}}}", code)
+                })
+            },
+            (ValueKind::Char, _) => None,
+            (ValueKind::Bool, _) => match unsigned? {
+                0 => Some(String::from("false")),
+                1 => Some(String::from("true")),
+                n => Some(format!("{} (no es un booleano)", n))
+            }
+        }
+    }
+}
+
+impl Endian {
+
+    /// Parse a byte order: `little`, `big`, or their Spanish names `pequeño` and `grande`
+    pub fn parse(name : &str) -> Option<Endian> {
+        match name.to_lowercase().as_str() {
+            "little" | "le" | "pequeño" | "pequeno" => Some(Endian::Little),
+            "big" | "be" | "grande"                 => Some(Endian::Big),
+            _ => None
+        }
+    }
+
+    /// Name for this order, as parsed by `parse`
+    pub fn name(self) -> &'static str {
+        match self {
+            Endian::Little => "little",
+            Endian::Big    => "big"
+        }
+    }
+
+    /// Read bytes in this order as an unsigned number
+    /// ## Return
+    /// The number, or none if there are no bytes or more than 16
+    pub fn read(self, bytes : &[u8]) -> Option<u128> {
+        let mut wide = [0u8; 16];
+        let n = bytes.len();
+        if n == 0 || n > wide.len() {
+            return None
+        }
+
+        match self {
+            Endian::Little => { wide[..n].copy_from_slice(bytes); Some(u128::from_le_bytes(wide)) },
+            Endian::Big    => { wide[16 - n..].copy_from_slice(bytes); Some(u128::from_be_bytes(wide)) }
+        }
+    }
+}
+
+impl TypeManager {
+
+    /// Overlay the layout of a type on a buffer
+    /// ## Params
+    /// * `typename` - type stored in the buffer
+    /// * `strategy` - how structs are laid out
+    /// * `bytes` - buffer to read values from, it may be shorter or longer than the type
+    /// * `endian` - byte order of values in the buffer
+    /// ---
+    /// ## Return
+    /// Members without members of their own and padding between them, sorted by offset,
+    /// or an error if the type does not exist
+    pub fn annotate(&self, typename : &Name, strategy : Strategy, bytes : &[u8], endian : Endian) -> Result<Vec<Annotation>, TypeError> {
+        let t = self.checked(typename)?;
+        let size = strategy.type_size(t, self);
+
+        let mut leaves : Vec<(String, Name, usize, usize)> = self.nested_members(typename, strategy)?
+                                                                .into_iter()
+                                                                .filter(|m| !matches!(self.get(&m.typename), Some(Type::Struct(_)) | Some(Type::Union(_))))
+                                                                .map(|m| (m.path, m.typename, m.offset, m.size))
+                                                                .collect();
+        if leaves.is_empty() {
+            leaves.push((String::new(), typename.clone(), 0, size));
+        }
+        leaves.sort_by_key(|(_, _, offset, _)| *offset);     // stable, union variants keep their order
+
+        let mut annotations = vec![];
+        let mut covered = 0;    // end of the last member seen
+        for (path, member, offset, member_size) in leaves {
+            if offset > covered {
+                annotations.push(Annotation::Padding { offset: covered, size: offset - covered });
+            }
+            let value = bytes
+                            .get(offset..offset.saturating_add(member_size))
+                            .and_then(|b| self.read_value(&member, b, endian));
+            annotations.push(Annotation::Member { path, typename: member, offset, size: member_size, value });
+            covered = covered.max(offset.saturating_add(member_size));
+        }
+        if size > covered {
+            annotations.push(Annotation::Padding { offset: covered, size: size - covered });
+        }
+
+        Ok(annotations)
+    }
+
+    /// Read the value of an atomic with a value kind, or the variant of an enum
+    /// ## Return
+    /// The value as text, or none for other types
+    fn read_value(&self, typename : &Name, bytes : &[u8], endian : Endian) -> Option<String> {
+        match self.get(typename)? {
+            Type::Atomic(a) => a.kind?.decode(bytes, endian),
+            Type::Enum(e)   => {
+                let index = endian.read(bytes)?;
+                Some(match e.variants.get(index as usize) {
+                    Some(variant) if index < e.variants.len() as u128 => variant.clone(),
+                    _ => format!("{} (fuera de rango)", index)
+                })
+            },
+            _ => None
+        }
+    }
+}
+
+/// Parse bytes written in hexadecimal, such as `0x2a000000` or `2a:00:00:00`
+/// ## Return
+/// The bytes, or none if the text is not hexadecimal or has an odd amount of digits
+pub fn parse_hex(text : &str) -> Option<Vec<u8>> {
+    let digits : String = text
+                            .strip_prefix("0x")
+                            .unwrap_or(text)
+                            .chars()
+                            .filter(|c| !matches!(c, ':' | '_' | '-'))
+                            .collect();
+    if digits.is_empty() || !digits.len().is_multiple_of(2) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None
+    }
+
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok())
+        .collect()
+}
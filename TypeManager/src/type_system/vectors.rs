@@ -0,0 +1,77 @@
+/*
+    Test vectors: a buffer holding a value of some type where every member is
+    easy to spot, so a parser written elsewhere can be checked against the
+    modeled layout. Without a seed every byte of a member holds its number,
+    with one members get pseudo random bytes. Padding is always zero
+*/
+use super::{Name, NestedMember, Strategy, Type, TypeError, TypeManager};
+use crate::utils::Rng;
+
+/// Largest buffer generated, in bytes
+pub const MAX_VECTOR : usize = 1 << 20;
+
+/// A buffer and the members written to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestVector {
+    pub bytes:   Vec<u8>,
+    pub members: Vec<NestedMember>  // members without members of their own, sorted by offset
+}
+
+impl TestVector {
+
+    /// Bytes written for a member
+    pub fn member_bytes(&self, member : &NestedMember) -> &[u8] {
+        &self.bytes[member.offset..member.offset + member.size]
+    }
+}
+
+impl TypeManager {
+
+    /// Generate a buffer holding a value of some type. Union variants share their bytes,
+    /// so only members not overlapping an earlier one are written
+    /// ## Params
+    /// * `typename` - type of the value
+    /// * `strategy` - how structs are laid out
+    /// * `seed` - seed for pseudo random member bytes, if none every member is filled with its number
+    /// ---
+    /// ## Return
+    /// The buffer and the members written, or an error if the type does not exist or it's too big
+    pub fn test_vector(&self, typename : &Name, strategy : Strategy, seed : Option<u64>) -> Result<TestVector, TypeError> {
+        let t = self.checked(typename)?;
+        let size = strategy.type_size(t, self);
+        if size > MAX_VECTOR {
+            return Err(TypeError::TooLarge(typename.clone(), size, MAX_VECTOR))
+        }
+
+        let mut leaves : Vec<NestedMember> = self.nested_members(typename, strategy)?
+                                                .into_iter()
+                                                .filter(|m| !matches!(self.get(&m.typename), Some(Type::Struct(_)) | Some(Type::Union(_))))
+                                                .collect();
+        if leaves.is_empty() {
+            leaves.push(NestedMember { path: String::new(), typename: typename.clone(), offset: 0, size, depth: 0 });
+        }
+        leaves.sort_by_key(|m| m.offset);
+
+        let mut rng = seed.map(Rng::new);
+        let mut bytes = vec![0u8; size];
+        let mut members = vec![];
+        let mut covered = 0;
+        for member in leaves.into_iter().filter(|m| m.size > 0) {
+            if member.offset < covered {
+                continue
+            }
+
+            let tag = (members.len() % 255 + 1) as u8;     // never 0, so members don't look like padding
+            for byte in bytes[member.offset..member.offset + member.size].iter_mut() {
+                *byte = match rng.as_mut() {
+                    Some(rng) => rng.next_u64() as u8,
+                    None => tag
+                };
+            }
+            covered = member.offset + member.size;
+            members.push(member);
+        }
+
+        Ok(TestVector { bytes, members })
+    }
+}
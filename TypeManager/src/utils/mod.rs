@@ -3,15 +3,110 @@
     Simple library with utility functions
 */
 
+pub mod table;
+pub mod number;
+
 /// Lowest Common Multiple for two numbers
 /// ## Params
 /// `x` - a number
 /// `y` - other number
 /// ---
 /// ## Return
-/// lowest common multiple for x and y
+/// lowest common multiple for x and y, saturating at `usize::MAX` if it's too big
 pub fn lcm(x : usize, y : usize) -> usize {
-    x * y / gcd(x, y)
+    match gcd(x, y) {
+        0 => 0,
+        d => (x / d).saturating_mul(y)
+    }
+}
+
+/// Round a number up to the next multiple of another
+/// ## Params
+/// `x` - number to round
+/// `multiple` - number whose multiple we want, 0 leaves `x` as it is
+/// ---
+/// ## Return
+/// smallest multiple of `multiple` that's not less than x, saturating at `usize::MAX`
+pub fn round_up(x : usize, multiple : usize) -> usize {
+    match x.checked_rem(multiple).unwrap_or(0) {
+        0 => x,
+        r => x.saturating_add(multiple - r)
+    }
+}
+
+/// Size of a struct with the given members, as a C compiler would lay it out
+/// ## Params
+/// `members` - (size, alignment) of every member, in order
+/// ---
+/// ## Return
+/// end of the last member rounded up to the biggest alignment, saturating at `usize::MAX`
+pub fn c_size(members : &[(usize, usize)]) -> usize {
+    let align = members.iter().map(|(_, a)| *a).max().unwrap_or(1);
+    let end = members.iter().fold(0, |position, (size, align)| round_up(position, *align).saturating_add(*size));
+
+    round_up(end, align)
+}
+
+/// Edit distance between two texts: characters to insert, delete or change to turn one into the other
+/// ## Params
+/// `a` - a text
+/// `b` - other text
+/// ---
+/// ## Return
+/// Levenshtein distance between a and b, counted in characters
+pub fn levenshtein(a : &str, b : &str) -> usize {
+    let b : Vec<char> = b.chars().collect();
+    let mut row : Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let changed = diagonal + (ca != *cb) as usize;
+            diagonal = row[j + 1];
+            row[j + 1] = changed.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Tells if a text matches a pattern where `*` stands for any text, even empty, and `?`
+/// for a single character
+/// ## Params
+/// `pattern` - pattern to match, such as `net::*`
+/// `text` - text to check
+/// ---
+/// ## Return
+/// Whether the whole text matches the whole pattern
+pub fn wildcard(pattern : &str, text : &str) -> bool {
+    let pattern : Vec<char> = pattern.chars().collect();
+    let text : Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut star : Option<(usize, usize)> = None;   // last `*` seen and where its text ends so far
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            },
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            },
+            _ => match star {
+                // the last `*` takes one more character and the rest of the pattern tries again
+                Some((s, end)) => {
+                    star = Some((s, end + 1));
+                    p = s + 1;
+                    t = end + 1;
+                },
+                None => return false
+            }
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
 }
 
 /// Gratest common divisor for two numbers
@@ -21,6 +116,7 @@ pub fn lcm(x : usize, y : usize) -> usize {
 /// ---
 /// ## Return 
 /// lowest common multiple for x, y
+#[allow(clippy::manual_swap)]
 pub fn gcd(x : usize, y : usize) -> usize {
     let mut max = x;
     let mut min = y;
@@ -29,6 +125,9 @@ pub fn gcd(x : usize, y : usize) -> usize {
         max = min;
         min = val;
     }
+    if min == 0 {
+        return max;
+    }
 
     loop {
         let res = max % min;
@@ -63,6 +162,7 @@ pub fn permutations<T>(list : &mut Vec<T>) -> Vec<Vec<T>>
 }
 
 /// Helper function to co,pute all permutations for a vector
+#[allow(clippy::clone_on_copy)]
 fn permutation_helper<T>(list :&mut Vec<T>, l : usize, r : usize, buff :&mut Vec<Vec<T>>) 
     where  
         T : Eq,
@@ -88,4 +188,90 @@ fn permutation_helper<T>(list :&mut Vec<T>, l : usize, r : usize, buff :&mut Vec
         list[i] = list[l].clone();
         list[l] = temp;
     }
-} 
\ No newline at end of file
+} 
+
+/// Permutations of a list generated one at a time, in the same order `permutations`
+/// returns them, so searches over big lists can stop early without having
+/// built every permutation first
+#[derive(Debug, Clone)]
+pub struct Permutations<T> {
+    list:   Vec<T>,
+    chosen: Vec<usize>, // element swapped into every position but the last
+    state:  Option<bool> // whether the first permutation was given, none when done
+}
+
+impl<T : Copy> Permutations<T> {
+
+    /// Permute the given list
+    pub fn new(list : Vec<T>) -> Permutations<T> {
+        let chosen = (0..list.len().saturating_sub(1)).collect();
+        let state = match list.is_empty() {
+            true  => None,
+            false => Some(false)
+        };
+        Permutations { list, chosen, state }
+    }
+}
+
+impl<T : Copy> Iterator for Permutations<T> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        if !self.state? {
+            self.state = Some(true);
+            return Some(self.list.clone())
+        }
+
+        // undo the deepest swap and try the next element there, going up when there's none left
+        let last = self.list.len() - 1;
+        let mut position = self.chosen.len();
+        while position > 0 {
+            position -= 1;
+            let i = self.chosen[position];
+            self.list.swap(i, position);
+            if i < last {
+                self.chosen[position] = i + 1;
+                self.list.swap(i + 1, position);
+                for deeper in position + 1..self.chosen.len() {
+                    self.chosen[deeper] = deeper;
+                }
+                return Some(self.list.clone())
+            }
+        }
+
+        self.state = None;
+        None
+    }
+}
+
+/// Small pseudo random number generator (xorshift64*), good enough
+/// for generating test data and randomized searches without external crates
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64
+}
+
+impl Rng {
+
+    /// Create a new generator from a seed, the same seed always
+    /// produces the same sequence
+    pub fn new(seed : u64) -> Rng {
+        // state can't be 0 or the generator gets stuck
+        Rng { state: (seed ^ 0x9E37_79B9_7F4A_7C15) | 1 }
+    }
+
+    /// Get next random number
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Get a random number in range [0, n)
+    /// ## Params
+    /// * `n` - upper bound, should be greater than 0
+    pub fn below(&mut self, n : usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
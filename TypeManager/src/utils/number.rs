@@ -0,0 +1,89 @@
+/*
+    Number formatting shared by every report, so big sizes are
+    easy to read: 1048576, 1_048_576, 1.048.576 or 1,048,576
+*/
+use std::fmt::Display;
+
+/// How to group the digits of a number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberFormat {
+    Plain,          // 1048576
+    Underscore,     // 1_048_576, as written in Rust or in our expressions
+    Dots,           // 1.048.576
+    Commas          // 1,048,576
+}
+
+impl NumberFormat {
+
+    /// Parse a format name, `local` picks one from the locale environment variables
+    pub fn parse(name : &str) -> Option<NumberFormat> {
+        match name.to_lowercase().as_str() {
+            "simple"     => Some(NumberFormat::Plain),
+            "guion_bajo" => Some(NumberFormat::Underscore),
+            "puntos"     => Some(NumberFormat::Dots),
+            "comas"      => Some(NumberFormat::Commas),
+            "local"      => Some(NumberFormat::from_locale()),
+            _ => None
+        }
+    }
+
+    /// Format used by the current locale, from `LC_ALL`, `LC_NUMERIC` or `LANG`
+    pub fn from_locale() -> NumberFormat {
+        let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+                        .iter()
+                        .filter_map(|v| std::env::var(v).ok())
+                        .find(|v| !v.is_empty())
+                        .unwrap_or_default();
+
+        NumberFormat::for_locale(&locale)
+    }
+
+    /// Format used by a locale such as `es_VE.UTF-8`
+    pub fn for_locale(locale : &str) -> NumberFormat {
+        let language = locale.split(['_', '.', '-']).next().unwrap_or("");
+        match language {
+            "es" | "de" | "it" | "pt" | "nl" | "id" | "tr" => NumberFormat::Dots,
+            "en" | "ja" | "zh" | "ko" | "he" | "th"        => NumberFormat::Commas,
+            _ => NumberFormat::Plain
+        }
+    }
+
+    /// Character between every group of three digits, if any
+    pub fn separator(self) -> Option<char> {
+        match self {
+            NumberFormat::Plain      => None,
+            NumberFormat::Underscore => Some('_'),
+            NumberFormat::Dots       => Some('.'),
+            NumberFormat::Commas     => Some(',')
+        }
+    }
+
+    /// Write an integer grouping its digits
+    /// ## Params
+    /// * `n` - an integer, it may be negative
+    /// ---
+    /// ## Return
+    /// The number with a separator every three digits, from the right
+    pub fn format<N: Display>(self, n : N) -> String {
+        let text = n.to_string();
+        let separator = match self.separator() {
+            Some(c) => c,
+            None    => return text
+        };
+
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None         => ("", text.as_str())
+        };
+
+        let mut result = String::from(sign);
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                result.push(separator);
+            }
+            result.push(digit);
+        }
+
+        result
+    }
+}
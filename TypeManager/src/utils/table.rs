@@ -0,0 +1,111 @@
+/*
+    Small table writer to render aligned tables with headers
+*/
+
+/// Characters used to draw tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableStyle {
+    Ascii,
+    Unicode
+}
+
+/// Horizontal alignment for a column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right
+}
+
+/// A table with a header row and any amount of data rows
+#[derive(Debug, Clone)]
+pub struct Table {
+    headers: Vec<String>,
+    aligns:  Vec<Align>,
+    rows:    Vec<Vec<String>>
+}
+
+impl Table {
+
+    /// Create an empty table with the given headers, every column left aligned
+    pub fn new(headers : &[&str]) -> Table {
+        Table {
+            headers: headers.iter().map(|h| h.to_string()).collect(),
+            aligns: vec![Align::Left; headers.len()],
+            rows: vec![]
+        }
+    }
+
+    /// Set alignment for a column, usually right for numbers
+    pub fn align(mut self, column : usize, align : Align) -> Table {
+        if column < self.aligns.len() {
+            self.aligns[column] = align;
+        }
+
+        self
+    }
+
+    /// Add a row, missing cells are left empty and extra cells are ignored
+    pub fn row(&mut self, cells : Vec<String>) {
+        let mut cells = cells;
+        cells.resize(self.headers.len(), String::new());
+        self.rows.push(cells);
+    }
+
+    /// Amount of data rows
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Tells if there's no data rows
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Render this table as text, every line ends with a line break
+    pub fn render(&self, style : TableStyle) -> String {
+        // (horizontal, vertical, top left, top mid, top right, mid left, cross, mid right, bottom left, bottom mid, bottom right)
+        let c = match style {
+            TableStyle::Ascii   => ['-', '|', '+', '+', '+', '+', '+', '+', '+', '+', '+'],
+            TableStyle::Unicode => ['─', '│', '┌', '┬', '┐', '├', '┼', '┤', '└', '┴', '┘']
+        };
+
+        // every column is as wide as its widest cell
+        let widths : Vec<usize> = (0..self.headers.len())
+                                    .map(|i| {
+                                        self.rows
+                                            .iter()
+                                            .map(|r| r[i].chars().count())
+                                            .chain(std::iter::once(self.headers[i].chars().count()))
+                                            .max()
+                                            .unwrap_or(0)
+                                    })
+                                    .collect();
+
+        let rule = |left : char, mid : char, right : char| -> String {
+            let segments : Vec<String> = widths.iter().map(|w| c[0].to_string().repeat(w + 2)).collect();
+            format!("{}{}{}\n", left, segments.join(&mid.to_string()), right)
+        };
+
+        let line = |cells : &[String], aligns : &[Align]| -> String {
+            let cells : Vec<String> = cells
+                                        .iter()
+                                        .zip(widths.iter().zip(aligns))
+                                        .map(|(cell, (w, a))| match a {
+                                            Align::Left  => format!(" {:<w$} ", cell, w = w),
+                                            Align::Right => format!(" {:>w$} ", cell, w = w)
+                                        })
+                                        .collect();
+            format!("{}{}{}\n", c[1], cells.join(&c[1].to_string()), c[1])
+        };
+
+        let mut text = rule(c[2], c[3], c[4]);
+        text += &line(&self.headers, &vec![Align::Left; self.headers.len()]);
+        text += &rule(c[5], c[6], c[7]);
+        for row in &self.rows {
+            text += &line(row, &self.aligns);
+        }
+        text += &rule(c[8], c[9], c[10]);
+
+        text
+    }
+}